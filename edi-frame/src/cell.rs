@@ -1,3 +1,6 @@
+use std::rc::Rc;
+
+pub use edi_term::escaping::Style;
 use edi_term::{escaping::ANSIColor, window};
 
 #[allow(unused)]
@@ -13,10 +16,84 @@ pub enum Color {
     Cyan,
     #[default]
     White,
+    /// A true color, given by its red, green and blue components
+    Rgb(u8, u8, u8),
+    /// A color of the 256-color palette
+    Indexed(u8),
 
     None,
 }
 
+impl Color {
+    /// Parses a color name such as `red`, a `#rrggbb` true color or the index of a color of the
+    /// 256-color palette. `none` is the default color of the terminal
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let color = match s.to_ascii_lowercase().as_str() {
+            "black" => Self::Black,
+            "red" => Self::Red,
+            "green" => Self::Green,
+            "yellow" => Self::Yellow,
+            "blue" => Self::Blue,
+            "magenta" => Self::Magenta,
+            "cyan" => Self::Cyan,
+            "white" => Self::White,
+            "none" => Self::None,
+            s => {
+                if let Some(hex) = s.strip_prefix('#') {
+                    let rgb = u32::from_str_radix(hex, 16)
+                        .ok()
+                        .filter(|_| hex.len() == 6)?;
+                    let [_, r, g, b] = rgb.to_be_bytes();
+                    Self::Rgb(r, g, b)
+                } else {
+                    Self::Indexed(s.parse().ok()?)
+                }
+            }
+        };
+        Some(color)
+    }
+
+    /// Returns the closest color of the 256-color palette for a true color, for terminals that
+    /// can not show true colors. Other colors are returned as they are
+    #[must_use]
+    pub fn to_palette(self) -> Self {
+        const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+        let Self::Rgb(r, g, b) = self else {
+            return self;
+        };
+        let distance = |(r2, g2, b2): (u8, u8, u8)| {
+            [(r, r2), (g, g2), (b, b2)]
+                .into_iter()
+                .map(|(a, b)| u32::from(a.abs_diff(b)).pow(2))
+                .sum::<u32>()
+        };
+        let closest_level = |c: u8| {
+            (0..LEVELS.len())
+                .min_by_key(|&i| LEVELS[i].abs_diff(c))
+                .unwrap_or_default()
+        };
+
+        let (ri, gi, bi) = (closest_level(r), closest_level(g), closest_level(b));
+        let cube = (LEVELS[ri], LEVELS[gi], LEVELS[bi]);
+        // The grays go from 8 to 238 in steps of 10
+        let gray_step = ((u16::from(r) + u16::from(g) + u16::from(b)) / 3).saturating_sub(3) / 10;
+        let gray_step = u8::try_from(gray_step.min(23)).unwrap_or_default();
+        let gray = 8 + 10 * gray_step;
+
+        #[expect(
+            clippy::cast_possible_truncation,
+            reason = "the indices of the levels are less than 6"
+        )]
+        if distance((gray, gray, gray)) < distance(cube) {
+            Self::Indexed(232 + gray_step)
+        } else {
+            Self::Indexed(16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8)
+        }
+    }
+}
+
 impl From<ANSIColor> for Color {
     fn from(value: ANSIColor) -> Self {
         match value {
@@ -28,6 +105,8 @@ impl From<ANSIColor> for Color {
             ANSIColor::Magenta => Color::Magenta,
             ANSIColor::Cyan => Color::Cyan,
             ANSIColor::White => Color::White,
+            ANSIColor::Rgb(r, g, b) => Color::Rgb(r, g, b),
+            ANSIColor::Indexed(i) => Color::Indexed(i),
             _ => Color::default(),
         }
     }
@@ -44,22 +123,53 @@ impl From<Color> for ANSIColor {
             Color::Magenta => Self::Magenta,
             Color::Cyan => Self::Cyan,
             Color::White => Self::White,
+            Color::Rgb(r, g, b) => Self::Rgb(r, g, b),
+            Color::Indexed(i) => Self::Indexed(i),
             Color::None => Self::Default,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Cell {
     pub char: char,
     pub fg: Color,
     pub bg: Color,
+    pub style: Style,
+    pub underline: Color,
+    /// URI the cell links to
+    pub link: Option<Rc<str>>,
 }
 
 impl Cell {
     #[must_use]
     pub const fn new(char: char, fg: Color, bg: Color) -> Self {
-        Self { char, fg, bg }
+        Self {
+            char,
+            fg,
+            bg,
+            style: Style::NONE,
+            underline: Color::None,
+            link: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_underline(mut self, color: Color) -> Self {
+        self.underline = color;
+        self
+    }
+
+    #[must_use]
+    pub fn with_link(mut self, uri: Rc<str>) -> Self {
+        self.link = Some(uri);
+        self
     }
 }
 
@@ -74,6 +184,12 @@ impl From<window::Cell> for Cell {
         Self {
             char: value.character,
             fg: Color::from(value.fg_color),
+            style: value.style,
+            underline: match value.underline_color {
+                ANSIColor::Default => Color::None,
+                color => Color::from(color),
+            },
+            link: value.link,
             ..Default::default()
         }
     }
@@ -81,10 +197,45 @@ impl From<window::Cell> for Cell {
 
 impl From<Cell> for window::Cell {
     fn from(value: Cell) -> Self {
-        Self::new(
-            value.char,
-            ANSIColor::from(value.fg),
-            ANSIColor::from(value.bg),
-        )
+        Self {
+            link: value.link,
+            ..Self::new(
+                value.char,
+                ANSIColor::from(value.fg),
+                ANSIColor::from(value.bg),
+            )
+            .with_style(value.style)
+            .with_underline_color(ANSIColor::from(value.underline))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Color::parse("Magenta"), Some(Color::Magenta));
+        assert_eq!(Color::parse("none"), Some(Color::None));
+        assert_eq!(Color::parse("#ff8000"), Some(Color::Rgb(255, 128, 0)));
+        assert_eq!(Color::parse("208"), Some(Color::Indexed(208)));
+        assert_eq!(Color::parse("#fff"), None);
+        assert_eq!(Color::parse("256"), None);
+        assert_eq!(Color::parse("purple"), None);
+    }
+
+    #[test]
+    fn palette() {
+        assert_eq!(Color::Rgb(255, 0, 0).to_palette(), Color::Indexed(196));
+        assert_eq!(
+            Color::Rgb(0x5f, 0x87, 0xd7).to_palette(),
+            Color::Indexed(68)
+        );
+        assert_eq!(
+            Color::Rgb(0x80, 0x80, 0x80).to_palette(),
+            Color::Indexed(244)
+        );
+        assert_eq!(Color::Red.to_palette(), Color::Red);
     }
 }
@@ -0,0 +1,227 @@
+//! Constraint-based layout of nested horizontal and vertical containers
+
+use crate::rect::Rect;
+
+/// The direction a `Layout` places its items in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Items are placed left to right
+    Horizontal,
+    /// Items are placed top to bottom
+    Vertical,
+}
+
+/// The length of an item along the direction of its `Layout`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    /// Exactly the given number of cells
+    Fixed(usize),
+    /// At least the given number of cells, grows only when there is nothing to `Fill`
+    Min(usize),
+    /// Up to the given number of cells, gets space before the items that `Fill`
+    Max(usize),
+    /// A share of the space left over, proportional to the weight
+    Fill(u16),
+}
+
+/// A container that splits an area between its items along a `Direction`
+///
+/// An item is either a leaf, that ends up as a single `Rect`, or a nested `Layout` that splits
+/// the item's area further
+///
+/// Space is handed out in order of priority: first `Fixed` and `Min` items get their lengths,
+/// then `Max` items get up to their limits, then the rest is shared between `Fill` items by
+/// weight, or between `Min` items equally if there are none. When the area is too small, the
+/// items at the end are cut off
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    direction: Direction,
+    items: Vec<(Constraint, Option<Layout>)>,
+}
+
+impl Layout {
+    /// Creates an empty layout that places items in the given `direction`
+    #[must_use]
+    pub const fn new(direction: Direction) -> Self {
+        Self {
+            direction,
+            items: Vec::new(),
+        }
+    }
+
+    /// Creates an empty layout that places items left to right
+    #[must_use]
+    pub const fn horizontal() -> Self {
+        Self::new(Direction::Horizontal)
+    }
+
+    /// Creates an empty layout that places items top to bottom
+    #[must_use]
+    pub const fn vertical() -> Self {
+        Self::new(Direction::Vertical)
+    }
+
+    /// Adds a leaf item
+    #[must_use]
+    pub fn with_leaf(mut self, constraint: Constraint) -> Self {
+        self.items.push((constraint, None));
+        self
+    }
+
+    /// Adds an item that is split further by `layout`
+    #[must_use]
+    pub fn with_layout(mut self, constraint: Constraint, layout: Layout) -> Self {
+        self.items.push((constraint, Some(layout)));
+        self
+    }
+
+    /// Returns the areas of the direct items of the layout inside `area`
+    #[must_use]
+    pub fn split(&self, area: Rect) -> Vec<Rect> {
+        let total = match self.direction {
+            Direction::Horizontal => area.width(),
+            Direction::Vertical => area.height(),
+        };
+        let constraints: Vec<Constraint> = self.items.iter().map(|&(c, _)| c).collect();
+
+        let mut offset = 0;
+        lengths(&constraints, total)
+            .into_iter()
+            .map(|len| {
+                let rest = match self.direction {
+                    Direction::Horizontal => area.split_horizontal(offset).1,
+                    Direction::Vertical => area.split_vertical(offset).1,
+                };
+                offset += len;
+                match self.direction {
+                    Direction::Horizontal => rest.split_horizontal(len).0,
+                    Direction::Vertical => rest.split_vertical(len).0,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the areas of all leaves of the layout inside `area`, in depth-first order
+    #[must_use]
+    pub fn resolve(&self, area: Rect) -> Vec<Rect> {
+        let mut leaves = Vec::new();
+        self.resolve_into(area, &mut leaves);
+        leaves
+    }
+
+    fn resolve_into(&self, area: Rect, leaves: &mut Vec<Rect>) {
+        for ((_, layout), rect) in self.items.iter().zip(self.split(area)) {
+            match layout {
+                Some(layout) => layout.resolve_into(rect, leaves),
+                None => leaves.push(rect),
+            }
+        }
+    }
+}
+
+/// Resolves the `constraints` to lengths that fit into `total`
+fn lengths(constraints: &[Constraint], total: usize) -> Vec<usize> {
+    let mut remaining = total;
+    let mut lengths: Vec<usize> = constraints
+        .iter()
+        .map(|c| {
+            let wanted = match *c {
+                Constraint::Fixed(len) | Constraint::Min(len) => len,
+                Constraint::Max(_) | Constraint::Fill(_) => 0,
+            };
+            let len = wanted.min(remaining);
+            remaining -= len;
+            len
+        })
+        .collect();
+
+    for (len, c) in lengths.iter_mut().zip(constraints) {
+        if let Constraint::Max(max) = *c {
+            *len = max.min(remaining);
+            remaining -= *len;
+        }
+    }
+
+    let fill_weight = |c: &Constraint| match *c {
+        Constraint::Fill(weight) => usize::from(weight),
+        _ => 0,
+    };
+    let min_weight = |c: &Constraint| usize::from(matches!(c, Constraint::Min(_)));
+    let weight: &dyn Fn(&Constraint) -> usize = if constraints.iter().any(|c| fill_weight(c) > 0) {
+        &fill_weight
+    } else {
+        &min_weight
+    };
+
+    let total_weight: usize = constraints.iter().map(weight).sum();
+    if total_weight == 0 {
+        return lengths;
+    }
+
+    let shared = remaining;
+    for (len, c) in lengths.iter_mut().zip(constraints) {
+        let share = shared * weight(c) / total_weight;
+        *len += share;
+        remaining -= share;
+    }
+    // Hands out what rounding down left over, one cell at a time
+    for (len, c) in lengths.iter_mut().zip(constraints) {
+        if remaining == 0 {
+            break;
+        }
+        if weight(c) > 0 {
+            *len += 1;
+            remaining -= 1;
+        }
+    }
+
+    lengths
+}
+
+#[cfg(test)]
+mod tests {
+    use edi_term::coord::Coord;
+
+    use super::*;
+    use Constraint::{Fill, Fixed, Max, Min};
+
+    #[test]
+    fn constraints() {
+        assert_eq!(lengths(&[Fixed(3), Fill(1), Fixed(1)], 10), [3, 6, 1]);
+        assert_eq!(lengths(&[Fill(1), Fill(2)], 10), [4, 6]);
+        assert_eq!(lengths(&[Fill(1), Fill(1), Fill(1)], 10), [4, 3, 3]);
+        assert_eq!(lengths(&[Max(4), Fill(1)], 10), [4, 6]);
+        assert_eq!(lengths(&[Max(40), Fill(1)], 10), [10, 0]);
+        assert_eq!(lengths(&[Min(3), Fill(1)], 10), [3, 7]);
+        assert_eq!(lengths(&[Min(3), Min(1), Fixed(2)], 10), [5, 3, 2]);
+        assert_eq!(lengths(&[Fixed(6), Min(3), Fill(1)], 8), [6, 2, 0]);
+        assert_eq!(lengths(&[Fill(0), Fixed(2)], 10), [0, 2]);
+        assert!(lengths(&[], 10).is_empty());
+    }
+
+    #[test]
+    fn nested() {
+        let layout = Layout::vertical()
+            .with_layout(
+                Fill(1),
+                Layout::horizontal().with_leaf(Fixed(4)).with_leaf(Fill(1)),
+            )
+            .with_leaf(Fixed(1));
+        let area = Rect::new(2, 1, 20, 10);
+
+        let rects = layout.resolve(area);
+        let summary: Vec<_> = rects
+            .iter()
+            .map(|r| (r.position(), r.width(), r.height()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Coord::new(2, 1), 4, 9),
+                (Coord::new(6, 1), 16, 9),
+                (Coord::new(2, 10), 20, 1),
+            ]
+        );
+        assert_eq!(layout.split(area).len(), 2);
+    }
+}
@@ -1,7 +1,9 @@
 //! Micro tui-framework with provimitves and components set to make a good tui
 
 pub mod cell;
+pub mod layout;
 pub mod prelude;
 pub mod rect;
 pub mod surface;
 pub mod unit;
+pub mod widget;
@@ -3,7 +3,7 @@
 use edi_term::coord::Coord;
 
 /// A rectangular shape in tui sceenspace
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Rect {
     position: Coord,
     width: usize,
@@ -110,6 +110,67 @@ impl Rect {
 
         (left, right)
     }
+
+    /// Returns the rectangle shrunk by `margin` cells on every side
+    #[must_use]
+    pub const fn inner(&self, margin: usize) -> Rect {
+        let width = self.width.saturating_sub(2 * margin);
+        let height = self.height.saturating_sub(2 * margin);
+        let dx = if margin < self.width {
+            margin
+        } else {
+            self.width
+        };
+        let dy = if margin < self.height {
+            margin
+        } else {
+            self.height
+        };
+        Rect::new(self.position.x + dx, self.position.y + dy, width, height)
+    }
+
+    /// Returns a rectangle of the given size centered within this one, clamped to its bounds
+    #[must_use]
+    pub const fn centered(&self, width: usize, height: usize) -> Rect {
+        let width = if width < self.width {
+            width
+        } else {
+            self.width
+        };
+        let height = if height < self.height {
+            height
+        } else {
+            self.height
+        };
+        Rect::new(
+            self.position.x + (self.width - width) / 2,
+            self.position.y + (self.height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    /// Returns the area shared by both rectangles, `None` if they do not overlap
+    #[must_use]
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.position.x.max(other.position.x);
+        let y = self.position.y.max(other.position.y);
+        let right = (self.position.x + self.width).min(other.position.x + other.width);
+        let bottom = (self.position.y + self.height).min(other.position.y + other.height);
+
+        (x < right && y < bottom).then(|| Rect::new(x, y, right - x, bottom - y))
+    }
+
+    /// Returns the smallest rectangle that contains both rectangles
+    #[must_use]
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.position.x.min(other.position.x);
+        let y = self.position.y.min(other.position.y);
+        let right = (self.position.x + self.width).max(other.position.x + other.width);
+        let bottom = (self.position.y + self.height).max(other.position.y + other.height);
+
+        Rect::new(x, y, right - x, bottom - y)
+    }
 }
 
 #[cfg(test)]
@@ -216,4 +277,34 @@ mod tests {
         assert_eq!(right.width(), 0);
         assert_eq!(right.height(), 0);
     }
+
+    #[test]
+    fn inner() {
+        let rect = Rect::new(5, 5, 10, 6);
+        assert_eq!(rect.inner(0), rect);
+        assert_eq!(rect.inner(2), Rect::new(7, 7, 6, 2));
+        assert_eq!(rect.inner(3), Rect::new(8, 8, 4, 0));
+        assert_eq!(rect.inner(20), Rect::new(15, 11, 0, 0));
+    }
+
+    #[test]
+    fn centered() {
+        let rect = Rect::new(2, 2, 10, 5);
+        assert_eq!(rect.centered(4, 3), Rect::new(5, 3, 4, 3));
+        assert_eq!(rect.centered(3, 2), Rect::new(5, 3, 3, 2));
+        assert_eq!(rect.centered(20, 20), rect);
+    }
+
+    #[test]
+    fn intersection_and_union() {
+        let a = Rect::new(0, 0, 10, 10);
+        let b = Rect::new(5, 8, 10, 10);
+        assert_eq!(a.intersection(&b), Some(Rect::new(5, 8, 5, 2)));
+        assert_eq!(a.union(&b), Rect::new(0, 0, 15, 18));
+
+        let c = Rect::new(10, 0, 5, 5);
+        assert_eq!(a.intersection(&c), None);
+        assert_eq!(a.union(&c), Rect::new(0, 0, 15, 10));
+        assert_eq!(a.intersection(&Rect::new(3, 3, 0, 0)), None);
+    }
 }
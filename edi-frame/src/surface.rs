@@ -21,13 +21,21 @@ pub trait Surface {
 
 pub trait WindowBind<'a> {
     fn bind(self, window: &'a mut window::Window) -> BoundedWindow<'a>;
+    /// Binds to the given layer of the window instead of the one that is drawn to
+    fn bind_layer(self, window: &'a mut window::Window, layer: usize) -> BoundedWindow<'a>;
 }
 
 impl<'a> WindowBind<'a> for Rect {
     fn bind(self, window: &'a mut window::Window) -> BoundedWindow<'a> {
+        let layer = window.layer();
+        self.bind_layer(window, layer)
+    }
+
+    fn bind_layer(self, window: &'a mut window::Window, layer: usize) -> BoundedWindow<'a> {
         BoundedWindow {
             window,
             bound: self,
+            layer,
         }
     }
 }
@@ -119,15 +127,26 @@ impl Surface for window::Window {
 pub struct BoundedWindow<'a> {
     window: &'a mut window::Window,
     bound: Rect,
+    layer: usize,
+}
+
+impl BoundedWindow<'_> {
+    /// Runs `f` with the window drawing to the bound layer
+    fn on_layer(&mut self, f: impl FnOnce(&Rect, &mut window::Window)) {
+        let prev = self.window.layer();
+        self.window.set_layer(self.layer);
+        f(&self.bound, self.window);
+        self.window.set_layer(prev);
+    }
 }
 
 impl Surface for BoundedWindow<'_> {
     fn set(&mut self, position: Coord, cell: Cell) {
-        self.bound.set(position, cell, self.window);
+        self.on_layer(|bound, window| bound.set(position, cell, window));
     }
 
     fn clear(&mut self, color: Color) {
-        self.bound.clear(self.window, color);
+        self.on_layer(|bound, window| bound.clear(window, color));
     }
 
     fn move_cursor(&mut self, point: Coord) {
@@ -138,3 +157,189 @@ impl Surface for BoundedWindow<'_> {
         self.bound.dimensions(self.window)
     }
 }
+
+/// A surface that keeps the drawn cells in memory, so that the output can be compared against
+/// expected screens in tests
+#[derive(Debug, Clone)]
+pub struct MemorySurface {
+    cells: Vec<Vec<Cell>>,
+    cursor: Option<Coord>,
+    dimensions: UDims,
+}
+
+impl MemorySurface {
+    /// Creates a blank surface of the given size
+    #[must_use]
+    pub fn new(dimensions: UDims) -> Self {
+        Self {
+            cells: vec![vec![Cell::default(); dimensions.width]; dimensions.height],
+            cursor: None,
+            dimensions,
+        }
+    }
+
+    /// Returns the cell at `position`, if it lies within the surface
+    #[must_use]
+    pub fn cell(&self, position: Coord) -> Option<&Cell> {
+        self.cells.get(position.y)?.get(position.x)
+    }
+
+    /// Returns where the cursor was last moved to, `None` if it was not moved since the last clear
+    #[must_use]
+    pub const fn cursor(&self) -> Option<Coord> {
+        self.cursor
+    }
+
+    /// Returns the characters of the given row
+    #[must_use]
+    pub fn row(&self, y: usize) -> Option<String> {
+        self.cells
+            .get(y)
+            .map(|row| row.iter().map(|cell| cell.char).collect())
+    }
+
+    /// Returns the characters of the surface, one line per row
+    #[must_use]
+    pub fn to_string_grid(&self) -> String {
+        self.map_grid(|cell| cell.char)
+    }
+
+    /// Returns a grid of characters produced by `f` for every cell, one line per row
+    ///
+    /// Makes it possible to assert on colors and styles the same way as on the contents
+    #[must_use]
+    pub fn map_grid(&self, mut f: impl FnMut(&Cell) -> char) -> String {
+        let rows: Vec<String> = self
+            .cells
+            .iter()
+            .map(|row| row.iter().map(&mut f).collect())
+            .collect();
+        rows.join("\n")
+    }
+
+    /// Asserts that the surface shows the `expected` rows
+    ///
+    /// Trailing whitespace is ignored and missing rows are expected to be blank
+    ///
+    /// # Panics
+    ///
+    /// Panics with both screens printed if the contents differ
+    #[track_caller]
+    pub fn assert_screen(&self, expected: &[&str]) {
+        assert_grid(&self.to_string_grid(), expected);
+    }
+
+    /// Asserts that the grid produced by `f` matches the `expected` rows, see `map_grid` and
+    /// `assert_screen`
+    ///
+    /// # Panics
+    ///
+    /// Panics with both grids printed if they differ
+    #[track_caller]
+    pub fn assert_grid(&self, f: impl FnMut(&Cell) -> char, expected: &[&str]) {
+        assert_grid(&self.map_grid(f), expected);
+    }
+}
+
+#[track_caller]
+fn assert_grid(actual: &str, expected: &[&str]) {
+    let actual: Vec<&str> = actual.lines().map(str::trim_end).collect();
+    let mut expected: Vec<&str> = expected.iter().map(|row| row.trim_end()).collect();
+    if expected.len() < actual.len() {
+        expected.resize(actual.len(), "");
+    }
+
+    assert!(
+        actual == expected,
+        "screens differ\nexpected:\n{}\nactual:\n{}",
+        expected.join("\n"),
+        actual.join("\n"),
+    );
+}
+
+impl Surface for MemorySurface {
+    fn set(&mut self, position: Coord, cell: Cell) {
+        if let Some(c) = self
+            .cells
+            .get_mut(position.y)
+            .and_then(|row| row.get_mut(position.x))
+        {
+            *c = cell;
+        }
+    }
+
+    fn clear(&mut self, color: Color) {
+        let cell = Cell::new(' ', Color::None, color);
+        for row in &mut self.cells {
+            row.fill(cell.clone());
+        }
+        self.cursor = None;
+    }
+
+    fn dimensions(&self) -> Dimensions<usize> {
+        self.dimensions
+    }
+
+    fn move_cursor(&mut self, point: Coord) {
+        self.cursor = Some(point);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::Style;
+
+    #[test]
+    fn memory_surface() {
+        let mut surface = MemorySurface::new(Dimensions::new(4, 3));
+        let bound = Rect::new(1, 1, 2, 2);
+        bound.set(
+            Coord::new(0, 0),
+            Cell::default().with_style(Style::BOLD),
+            &mut surface,
+        );
+        bound.set(
+            Coord::new(1, 1),
+            Cell::new('x', Color::Red, Color::None),
+            &mut surface,
+        );
+        bound.set(
+            Coord::new(2, 0),
+            Cell::new('y', Color::Red, Color::None),
+            &mut surface,
+        );
+        bound.move_cursor(Coord::new(1, 0), &mut surface);
+
+        surface.assert_screen(&["", "", "  x"]);
+        surface.assert_grid(
+            |cell| {
+                if cell.style.contains(Style::BOLD) {
+                    'b'
+                } else {
+                    '.'
+                }
+            },
+            &["....", ".b..", "...."],
+        );
+        assert_eq!(
+            surface.cell(Coord::new(2, 2)).map(|c| c.fg),
+            Some(Color::Red)
+        );
+        assert_eq!(surface.cursor(), Some(Coord::new(2, 1)));
+        assert_eq!(surface.row(2).as_deref(), Some("  x "));
+
+        surface.clear(Color::Blue);
+        assert_eq!(surface.cursor(), None);
+        surface.assert_grid(
+            |cell| if cell.bg == Color::Blue { 'b' } else { '.' },
+            &["bbbb"; 3],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "screens differ")]
+    fn memory_surface_mismatch() {
+        MemorySurface::new(Dimensions::new(2, 1)).assert_screen(&["", "a"]);
+    }
+}
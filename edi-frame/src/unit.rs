@@ -7,6 +7,10 @@ pub enum Unit {
     WidthRatio(f64),
     MinRatio(f64),
     MaxRatio(f64),
+    /// A percentage of the parent's size along the same axis
+    Percent(f32),
+    /// A fraction of the parent's size along the same axis, given as numerator and denominator
+    Ratio(u8, u8),
 }
 
 impl Unit {
@@ -30,14 +34,28 @@ impl Unit {
         Self::HeightRatio(0.5)
     }
 
+    /// Resolves the unit as a horizontal length inside a parent of the given `dimensions`
     #[must_use]
-    pub fn resolve(self, dimensions: Dimensions<usize>) -> usize {
+    pub fn resolve_width(self, dimensions: Dimensions<usize>) -> usize {
+        self.resolve(dimensions, dimensions.width)
+    }
+
+    /// Resolves the unit as a vertical length inside a parent of the given `dimensions`
+    #[must_use]
+    pub fn resolve_height(self, dimensions: Dimensions<usize>) -> usize {
+        self.resolve(dimensions, dimensions.height)
+    }
+
+    fn resolve(self, dimensions: Dimensions<usize>, parent: usize) -> usize {
         match self {
             Self::Cells(num) => num,
             Self::HeightRatio(factor) => Self::scale(dimensions.height, factor),
             Self::WidthRatio(factor) => Self::scale(dimensions.width, factor),
             Self::MinRatio(factor) => Self::scale(dimensions.width.min(dimensions.height), factor),
             Self::MaxRatio(factor) => Self::scale(dimensions.width.max(dimensions.height), factor),
+            Self::Percent(percent) => Self::scale(parent, f64::from(percent) / 100.0),
+            Self::Ratio(_, 0) => 0,
+            Self::Ratio(num, den) => parent * usize::from(num) / usize::from(den),
         }
     }
 
@@ -54,3 +72,23 @@ impl Unit {
         (value as f64 * factor) as usize
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_to_parent() {
+        let dims = Dimensions::new(101, 40);
+
+        assert_eq!(Unit::Percent(30.0).resolve_width(dims), 30);
+        assert_eq!(Unit::Percent(70.0).resolve_width(dims), 70);
+        assert_eq!(Unit::Percent(25.0).resolve_height(dims), 10);
+        assert_eq!(Unit::Ratio(1, 3).resolve_height(dims), 13);
+        assert_eq!(Unit::Ratio(2, 3).resolve_width(dims), 67);
+        assert_eq!(Unit::Ratio(1, 0).resolve_width(dims), 0);
+
+        assert_eq!(Unit::HeightRatio(0.5).resolve_width(dims), 20);
+        assert_eq!(Unit::Cells(7).resolve_height(dims), 7);
+    }
+}
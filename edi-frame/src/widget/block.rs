@@ -0,0 +1,159 @@
+//! A frame drawn around an area
+
+use edi_term::{coord::Coord, window::char_width};
+
+use crate::{
+    cell::{Cell, Color, Style},
+    rect::Rect,
+    surface::{BoundExt, Surface},
+};
+
+/// The set of box-drawing characters a `Block` is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BorderType {
+    /// `┌─┐`
+    #[default]
+    Single,
+    /// `╔═╗`
+    Double,
+    /// `╭─╮`
+    Rounded,
+}
+
+impl BorderType {
+    /// Returns the horizontal, vertical, top-left, top-right, bottom-left and bottom-right
+    /// characters
+    const fn chars(self) -> [char; 6] {
+        match self {
+            Self::Single => ['─', '│', '┌', '┐', '└', '┘'],
+            Self::Double => ['═', '║', '╔', '╗', '╚', '╝'],
+            Self::Rounded => ['─', '│', '╭', '╮', '╰', '╯'],
+        }
+    }
+}
+
+/// A border around an area with an optional title on its top edge
+#[derive(Debug, Clone)]
+pub struct Block {
+    border: BorderType,
+    title: Option<String>,
+    fg: Color,
+    bg: Color,
+    style: Style,
+}
+
+impl Block {
+    #[must_use]
+    pub const fn new(border: BorderType) -> Self {
+        Self {
+            border,
+            title: None,
+            fg: Color::White,
+            bg: Color::None,
+            style: Style::NONE,
+        }
+    }
+
+    #[must_use]
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    #[must_use]
+    pub const fn with_colors(mut self, fg: Color, bg: Color) -> Self {
+        self.fg = fg;
+        self.bg = bg;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Returns the part of `area` inside the border
+    #[must_use]
+    pub const fn inner(&self, area: Rect) -> Rect {
+        area.inner(1)
+    }
+
+    /// Draws the border along the edges of `area` and returns the area left for the contents
+    pub fn draw<S: Surface>(&self, area: Rect, surface: &mut S) -> Rect {
+        let inner = self.inner(area);
+        let (width, height) = (area.width(), area.height());
+        if width == 0 || height == 0 {
+            return inner;
+        }
+
+        let [
+            horizontal,
+            vertical,
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+        ] = self.border.chars();
+        let cell = |c| Cell::new(c, self.fg, self.bg).with_style(self.style);
+        let (right, bottom) = (width - 1, height - 1);
+
+        for x in 1..right {
+            area.set(Coord::new(x, 0), cell(horizontal), surface);
+            area.set(Coord::new(x, bottom), cell(horizontal), surface);
+        }
+        for y in 1..bottom {
+            area.set(Coord::new(0, y), cell(vertical), surface);
+            area.set(Coord::new(right, y), cell(vertical), surface);
+        }
+        area.set(Coord::new(0, 0), cell(top_left), surface);
+        area.set(Coord::new(right, 0), cell(top_right), surface);
+        area.set(Coord::new(0, bottom), cell(bottom_left), surface);
+        area.set(Coord::new(right, bottom), cell(bottom_right), surface);
+
+        let mut x = 1;
+        for c in self.title.iter().flat_map(|title| title.chars()) {
+            let w = char_width(c);
+            if x + w > right {
+                break;
+            }
+            area.set(Coord::new(x, 0), cell(c), surface);
+            x += w;
+        }
+
+        inner
+    }
+}
+
+impl Default for Block {
+    fn default() -> Self {
+        Self::new(BorderType::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edi_term::coord::Dimensions;
+
+    use super::*;
+    use crate::surface::MemorySurface;
+
+    #[test]
+    fn borders() {
+        let mut surface = MemorySurface::new(Dimensions::new(8, 5));
+
+        let inner = Block::new(BorderType::Rounded)
+            .with_title("title too long")
+            .draw(Rect::new(1, 1, 6, 3), &mut surface);
+        surface.assert_screen(&["", " ╭titl╮", " │    │", " ╰────╯"]);
+        assert_eq!(inner.position(), Coord::new(2, 2));
+        assert_eq!((inner.width(), inner.height()), (4, 1));
+
+        let mut surface = MemorySurface::new(Dimensions::new(4, 2));
+        Block::new(BorderType::Double).draw(Rect::new_in_origin(4, 2), &mut surface);
+        surface.assert_screen(&["╔══╗", "╚══╝"]);
+
+        let inner = Block::default().draw(Rect::new(3, 3, 1, 0), &mut surface);
+        assert_eq!((inner.width(), inner.height()), (0, 0));
+    }
+}
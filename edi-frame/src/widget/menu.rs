@@ -0,0 +1,213 @@
+//! A list of items to pick one of, drawn over the contents around a point of the screen
+
+use edi_term::{coord::Coord, window::char_width};
+
+use crate::{
+    cell::Color,
+    rect::Rect,
+    surface::{BoundExt, Surface},
+    widget::{Alignment, Scrollbar, Span, draw_line},
+};
+
+/// An item of a menu, its detail is shown after it in the same row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MenuItem<'a> {
+    pub label: &'a str,
+    pub detail: &'a str,
+}
+
+impl<'a> MenuItem<'a> {
+    #[must_use]
+    pub const fn new(label: &'a str) -> Self {
+        Self { label, detail: "" }
+    }
+
+    #[must_use]
+    pub const fn with_detail(mut self, detail: &'a str) -> Self {
+        self.detail = detail;
+        self
+    }
+
+    fn width(&self) -> usize {
+        let width = |s: &str| s.chars().map(char_width).sum::<usize>();
+        match width(self.detail) {
+            0 => width(self.label),
+            detail => width(self.label) + 1 + detail,
+        }
+    }
+}
+
+/// A menu of items one of which may be selected. Only as many items as fit are shown, scrolled
+/// so the selected one is among them
+#[derive(Debug, Clone)]
+pub struct Menu<'a> {
+    items: Vec<MenuItem<'a>>,
+    selected: Option<usize>,
+    fg: Color,
+    bg: Color,
+    selected_fg: Color,
+    selected_bg: Color,
+}
+
+impl<'a> Menu<'a> {
+    #[must_use]
+    pub const fn new(items: Vec<MenuItem<'a>>, selected: Option<usize>) -> Self {
+        Self {
+            items,
+            selected,
+            fg: Color::Black,
+            bg: Color::White,
+            selected_fg: Color::White,
+            selected_bg: Color::Blue,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_colors(mut self, fg: Color, bg: Color) -> Self {
+        self.fg = fg;
+        self.bg = bg;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_selected_colors(mut self, fg: Color, bg: Color) -> Self {
+        self.selected_fg = fg;
+        self.selected_bg = bg;
+        self
+    }
+
+    /// Returns the area the menu is drawn in for the cell at `anchor`, showing at most
+    /// `max_height` items. It goes below the cell if they fit there or there are no fewer rows
+    /// below it than above it, and above the cell otherwise. It starts at the column of the cell
+    /// and is moved left to fit the `screen`
+    #[must_use]
+    pub fn place(&self, anchor: Coord, screen: Rect, max_height: usize) -> Rect {
+        let height = self.items.len().min(max_height);
+        let below = (screen.height() + screen.position().y).saturating_sub(anchor.y + 1);
+        let above = anchor.y.saturating_sub(screen.position().y);
+        let (y, height) = if below >= height || below >= above {
+            (anchor.y + 1, height.min(below))
+        } else {
+            (anchor.y - height.min(above), height.min(above))
+        };
+
+        // A column on each side is padding, and one more is taken by a scrollbar
+        let scrollbar = usize::from(self.items.len() > height);
+        let width = self.items.iter().map(MenuItem::width).max().unwrap_or(0) + 2 + scrollbar;
+        let width = width.min(screen.width());
+        let right = screen.position().x + screen.width();
+        let x = anchor
+            .x
+            .min(right.saturating_sub(width))
+            .max(screen.position().x);
+        Rect::new(x, y, width, height)
+    }
+
+    /// Returns the index of the first item shown in `height` rows
+    fn first_shown(&self, height: usize) -> usize {
+        let selected = self.selected.unwrap_or(0);
+        (selected + 1)
+            .saturating_sub(height)
+            .min(self.items.len().saturating_sub(height))
+    }
+
+    pub fn draw<S: Surface>(&self, area: Rect, surface: &mut S) {
+        let height = area.height();
+        let first = self.first_shown(height);
+        let scrollbar = self.items.len() > height;
+        let text_width = area.width().saturating_sub(usize::from(scrollbar));
+
+        for (row, (i, item)) in self
+            .items
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(height)
+            .enumerate()
+        {
+            let (fg, bg) = if self.selected == Some(i) {
+                (self.selected_fg, self.selected_bg)
+            } else {
+                (self.fg, self.bg)
+            };
+            let line = Rect::new(area.position().x, area.position().y + row, text_width, 1);
+            line.clear(surface, bg);
+            let inner = Rect::new(
+                line.position().x + 1,
+                line.position().y,
+                text_width.saturating_sub(2),
+                1,
+            );
+            let label = [Span::new(item.label).with_colors(fg, bg)];
+            draw_line(inner, &label, Alignment::Left, surface);
+            // The detail is left out where it does not fit after the label
+            if item.detail.is_empty() || item.width() > inner.width() {
+                continue;
+            }
+            let detail = [Span::new(item.detail).with_colors(fg, bg)];
+            draw_line(inner, &detail, Alignment::Right, surface);
+        }
+
+        if scrollbar {
+            let track = Rect::new(area.position().x + text_width, area.position().y, 1, height);
+            Scrollbar::new(self.items.len(), height, first)
+                .with_colors(self.fg, self.bg)
+                .draw(track, surface);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edi_term::coord::Dimensions;
+
+    use super::*;
+    use crate::surface::MemorySurface;
+
+    #[test]
+    fn placement() {
+        let items = ["one", "three", "five"].map(MenuItem::new).to_vec();
+        let menu = Menu::new(items, None);
+        let screen = Rect::new_in_origin(20, 10);
+        assert_eq!(
+            menu.place(Coord::new(2, 1), screen, 10),
+            Rect::new(2, 2, 7, 3)
+        );
+        // There is more room above the cursor than below it
+        assert_eq!(
+            menu.place(Coord::new(2, 8), screen, 10),
+            Rect::new(2, 5, 7, 3)
+        );
+        assert_eq!(
+            menu.place(Coord::new(2, 8), screen, 1),
+            Rect::new(2, 9, 8, 1)
+        );
+        // The menu is moved left to fit
+        assert_eq!(
+            menu.place(Coord::new(18, 1), screen, 10),
+            Rect::new(13, 2, 7, 3)
+        );
+    }
+
+    #[test]
+    fn drawing() {
+        let items = vec![
+            MenuItem::new("alpha").with_detail("fn"),
+            MenuItem::new("beta"),
+            MenuItem::new("gamma"),
+        ];
+        let mut surface = MemorySurface::new(Dimensions::new(10, 2));
+        let menu = Menu::new(items, Some(2));
+        menu.draw(Rect::new_in_origin(10, 2), &mut surface);
+        surface.assert_screen(&[" beta    │", " gamma   █"]);
+        surface.assert_grid(
+            |cell| if cell.bg == Color::Blue { 's' } else { '.' },
+            &["..........", "sssssssss."],
+        );
+
+        let mut surface = MemorySurface::new(Dimensions::new(10, 3));
+        let menu = Menu::new(menu.items, None);
+        menu.draw(Rect::new_in_origin(10, 3), &mut surface);
+        surface.assert_screen(&[" alpha fn ", " beta     ", " gamma    "]);
+    }
+}
@@ -0,0 +1,11 @@
+//! Reusable components drawn onto any `Surface`
+
+pub mod block;
+pub mod menu;
+pub mod scrollbar;
+pub mod text;
+
+pub use block::{Block, BorderType};
+pub use menu::{Menu, MenuItem};
+pub use scrollbar::Scrollbar;
+pub use text::{Alignment, Line, Span, draw_line};
@@ -0,0 +1,98 @@
+//! A vertical indicator of the visible part of a scrolled view
+
+use std::ops::Range;
+
+use edi_term::coord::Coord;
+
+use crate::{
+    cell::{Cell, Color},
+    rect::Rect,
+    surface::{BoundExt, Surface},
+};
+
+/// A scrollbar with a thumb proportional to the part of the contents that is visible
+#[derive(Debug, Clone, Copy)]
+pub struct Scrollbar {
+    total: usize,
+    viewport: usize,
+    offset: usize,
+    fg: Color,
+    bg: Color,
+}
+
+impl Scrollbar {
+    const THUMB: char = '█';
+    const TRACK: char = '│';
+
+    /// Creates a scrollbar for `total` lines, `viewport` of which are visible starting at
+    /// `offset`
+    #[must_use]
+    pub const fn new(total: usize, viewport: usize, offset: usize) -> Self {
+        Self {
+            total,
+            viewport,
+            offset,
+            fg: Color::White,
+            bg: Color::None,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_colors(mut self, fg: Color, bg: Color) -> Self {
+        self.fg = fg;
+        self.bg = bg;
+        self
+    }
+
+    /// Returns the rows the thumb takes in a track of the given `height`
+    #[must_use]
+    pub fn thumb(&self, height: usize) -> Range<usize> {
+        if self.total <= self.viewport {
+            return 0..height;
+        }
+
+        let len = (height * self.viewport / self.total).clamp(1.min(height), height);
+        let max_offset = self.total - self.viewport;
+        let start = (height - len) * self.offset.min(max_offset) / max_offset;
+        start..start + len
+    }
+
+    /// Draws the scrollbar in the first column of `area`
+    pub fn draw<S: Surface>(&self, area: Rect, surface: &mut S) {
+        if area.width() == 0 {
+            return;
+        }
+
+        let thumb = self.thumb(area.height());
+        for y in 0..area.height() {
+            let c = if thumb.contains(&y) {
+                Self::THUMB
+            } else {
+                Self::TRACK
+            };
+            area.set(Coord::new(0, y), Cell::new(c, self.fg, self.bg), surface);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edi_term::coord::Dimensions;
+
+    use super::*;
+    use crate::surface::MemorySurface;
+
+    #[test]
+    fn thumb() {
+        assert_eq!(Scrollbar::new(100, 10, 0).thumb(10), 0..1);
+        assert_eq!(Scrollbar::new(100, 10, 90).thumb(10), 9..10);
+        assert_eq!(Scrollbar::new(100, 10, 500).thumb(10), 9..10);
+        assert_eq!(Scrollbar::new(20, 10, 5).thumb(10), 2..7);
+        assert_eq!(Scrollbar::new(5, 10, 0).thumb(10), 0..10);
+        assert_eq!(Scrollbar::new(1000, 1, 0).thumb(0), 0..0);
+
+        let mut surface = MemorySurface::new(Dimensions::new(2, 4));
+        Scrollbar::new(8, 4, 4).draw(Rect::new(1, 0, 1, 4), &mut surface);
+        surface.assert_screen(&[" │", " │", " █", " █"]);
+    }
+}
@@ -0,0 +1,165 @@
+//! Styled text drawn along a single row
+
+use edi_term::{coord::Coord, window::char_width};
+
+use crate::{
+    cell::{Cell, Color, Style},
+    rect::Rect,
+    surface::{BoundExt, Surface},
+};
+
+/// Where a line of text is placed when it is narrower than its area
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// A piece of text drawn with the same colors and style
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span<'a> {
+    pub text: &'a str,
+    pub fg: Color,
+    pub bg: Color,
+    pub style: Style,
+}
+
+impl<'a> Span<'a> {
+    #[must_use]
+    pub const fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            fg: Color::White,
+            bg: Color::None,
+            style: Style::NONE,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_colors(mut self, fg: Color, bg: Color) -> Self {
+        self.fg = fg;
+        self.bg = bg;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Returns the number of columns the span takes on the screen
+    #[must_use]
+    pub fn width(&self) -> usize {
+        self.text.chars().map(char_width).sum()
+    }
+}
+
+/// A sequence of spans drawn one after another
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Line<'a> {
+    pub spans: Vec<Span<'a>>,
+    pub alignment: Alignment,
+}
+
+impl<'a> Line<'a> {
+    #[must_use]
+    pub const fn new(spans: Vec<Span<'a>>) -> Self {
+        Self {
+            spans,
+            alignment: Alignment::Left,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    /// Draws the line in the first row of `area`, see `draw_line`
+    pub fn draw<S: Surface>(&self, area: Rect, surface: &mut S) -> usize {
+        draw_line(area, &self.spans, self.alignment, surface)
+    }
+}
+
+/// Draws `spans` in the first row of `area` and returns the number of columns drawn
+///
+/// The text is cut off at the right edge of the area, a wide character that does not fit is left
+/// out. Control characters are skipped
+pub fn draw_line<S: Surface>(
+    area: Rect,
+    spans: &[Span],
+    alignment: Alignment,
+    surface: &mut S,
+) -> usize {
+    let width = area.width();
+    if area.height() == 0 {
+        return 0;
+    }
+
+    let total: usize = spans.iter().map(Span::width).sum();
+    let free = width.saturating_sub(total);
+    let start = match alignment {
+        Alignment::Left => 0,
+        Alignment::Center => free / 2,
+        Alignment::Right => free,
+    };
+
+    let mut x = start;
+    for span in spans {
+        for c in span.text.chars().filter(|c| !c.is_control()) {
+            let w = char_width(c);
+            if x + w > width {
+                return x - start;
+            }
+            let cell = Cell::new(c, span.fg, span.bg).with_style(span.style);
+            area.set(Coord::new(x, 0), cell, surface);
+            x += w;
+        }
+    }
+
+    x - start
+}
+
+#[cfg(test)]
+mod tests {
+    use edi_term::coord::Dimensions;
+
+    use super::*;
+    use crate::surface::MemorySurface;
+
+    #[test]
+    fn alignment_and_truncation() {
+        let spans = [
+            Span::new("ab").with_style(Style::BOLD),
+            Span::new("界c").with_colors(Color::Red, Color::None),
+        ];
+        let mut surface = MemorySurface::new(Dimensions::new(8, 3));
+        let row = |y| Rect::new(1, y, 7, 1);
+
+        assert_eq!(draw_line(row(0), &spans, Alignment::Left, &mut surface), 5);
+        assert_eq!(
+            draw_line(row(1), &spans, Alignment::Center, &mut surface),
+            5
+        );
+        let line = Line::new(spans.to_vec()).with_alignment(Alignment::Right);
+        assert_eq!(line.draw(row(2), &mut surface), 5);
+        surface.assert_screen(&[" ab界 c", "  ab界 c", "   ab界 c"]);
+        surface.assert_grid(
+            |cell| match (cell.style.contains(Style::BOLD), cell.fg) {
+                (true, _) => 'b',
+                (_, Color::Red) => 'r',
+                _ => '.',
+            },
+            &[".bbr.r..", "..bbr.r.", "...bbr.r"],
+        );
+
+        let mut surface = MemorySurface::new(Dimensions::new(4, 1));
+        let area = Rect::new_in_origin(3, 1);
+        assert_eq!(draw_line(area, &spans, Alignment::Center, &mut surface), 2);
+        surface.assert_screen(&["ab"]);
+    }
+}
@@ -1,27 +1,89 @@
 //! Draw-related buffer functionality
 
+use std::ops::Range;
+
 use crate::itoa::itoa_into;
 use crate::{debug, span};
-use edi_frame::cell::Color;
+use edi_frame::prelude::*;
 use edi_frame::rect::Rect;
-use edi_frame::{cell::Cell, prelude::*};
+use edi_frame::widget::{Alignment, Scrollbar, Span, draw_line};
 use edi_rope::iter::LineInfo;
 use edi_term::coord::{Coord, Dimensions};
 
-use crate::string::highlight::{Highlight, Type};
+use crate::{diagnostic::Diagnostic, string::highlight::Highlight};
 
-use super::Buffer;
+use super::{
+    Buffer,
+    list::ListChars,
+    theme::{Face, Theme},
+    wrap,
+};
 
 #[derive(Debug)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "the flags toggle independent parts of the drawing"
+)]
 pub struct FlushOptions<'sl, 'hl> {
     pub wrap: bool,
+    /// Text drawn at the start of the continuation rows of wrapped lines
+    pub showbreak: &'sl str,
+    /// The markers whitespace is drawn as, if it is made visible
+    pub list_chars: Option<ListChars>,
     pub line_numbers: bool,
+    /// Numbers the lines by their distance to the cursor line, which keeps its own number
+    pub relative_line_numbers: bool,
 
     pub statusline: bool,
+    pub scrollbar: bool,
 
     pub mode: &'sl str,
+    /// Text shown on the right side of the statusline
+    pub status: &'sl str,
+    /// Keys of an unfinished command, shown in the statusline before the status
+    pub pending_keys: &'sl str,
     pub highlights: &'hl [Highlight],
+    /// The ranges drawn over the highlights, sorted by priority
+    pub layers: Vec<Layer<'hl>>,
+    /// Diagnostics sorted by where they start, the most severe one of each line is marked in the
+    /// gutter
+    pub diagnostics: &'hl [Diagnostic],
+    /// Shows the message of the marked diagnostic after the end of its line
+    pub virtual_text: bool,
+    /// Text shown after the end of each line, such as who changed it last. Lines without a
+    /// diagnostic message shown show it
+    pub annotations: &'hl [String],
     pub line_offset: usize,
+    /// The first screen column of the lines that is shown, only used when lines are not wrapped
+    pub column_offset: usize,
+    pub theme: Theme,
+}
+
+/// Character ranges drawn with a face over the highlights, such as the matches of a search or the
+/// selected text
+#[derive(Debug, Clone, Copy)]
+pub struct Layer<'a> {
+    /// Sorted ranges that do not overlap each other
+    pub ranges: &'a [Range<usize>],
+    pub face: Face,
+    /// The layers of a higher priority are drawn over the ones of a lower one
+    pub priority: u8,
+}
+
+impl<'a> Layer<'a> {
+    pub const SPELLING: u8 = 3;
+    pub const DIAGNOSTICS: u8 = 5;
+    pub const MATCHES: u8 = 10;
+    pub const SELECTION: u8 = 20;
+
+    #[must_use]
+    pub const fn new(ranges: &'a [Range<usize>], face: Face, priority: u8) -> Self {
+        Self {
+            ranges,
+            face,
+            priority,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -29,11 +91,12 @@ struct DrawBounds {
     statusline: Rect,
     line_numbers: Rect,
     main: Rect,
+    scrollbar: Rect,
 }
 
 impl DrawBounds {
     const LINE_NUMBER_MAX_WIDTH: usize = 5;
-    pub fn calculate(
+    pub const fn calculate(
         Dimensions { width, height }: Dimensions<usize>,
         total_lines: usize,
         opts: &FlushOptions,
@@ -43,11 +106,14 @@ impl DrawBounds {
         let line_number_width = Self::line_number_width(total_lines, opts);
         let (rest, statusline) =
             buffer_rect.split_vertical(height.saturating_sub(statusline_height));
-        let (line_numbers, main) = rest.split_horizontal(line_number_width);
+        let (line_numbers, rest) = rest.split_horizontal(line_number_width);
+        let scrollbar_width = if opts.scrollbar { 1 } else { 0 };
+        let (main, scrollbar) = rest.split_horizontal(rest.width().saturating_sub(scrollbar_width));
         Self {
             statusline,
             line_numbers,
             main,
+            scrollbar,
         }
     }
 
@@ -59,13 +125,16 @@ impl DrawBounds {
         if a > b { a } else { b }
     }
 
+    /// Returns the width of the gutter. The numbers are followed by a space, and preceded by a
+    /// column for the signs of the diagnostics if there are any
     pub const fn line_number_width(total_lines: usize, opts: &FlushOptions) -> usize {
+        let sign_width = if opts.diagnostics.is_empty() { 0 } else { 1 };
         if !opts.line_numbers {
-            return 0;
+            return if sign_width == 0 { 0 } else { sign_width + 1 };
         }
 
         let total_digits = Self::const_max(total_lines, 1).ilog10() as usize + 1;
-        Self::const_max(total_digits + 1, Self::LINE_NUMBER_MAX_WIDTH)
+        Self::const_max(sign_width + total_digits + 1, Self::LINE_NUMBER_MAX_WIDTH)
     }
 }
 
@@ -76,18 +145,77 @@ impl<'sl, 'hl> FlushOptions<'sl, 'hl> {
         self
     }
 
+    #[must_use]
+    pub const fn with_showbreak(mut self, showbreak: &'sl str) -> Self {
+        self.showbreak = showbreak;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_list_chars(mut self, list_chars: Option<ListChars>) -> Self {
+        self.list_chars = list_chars;
+        self
+    }
+
     #[must_use]
     pub const fn with_statusline(mut self, statusline: bool) -> Self {
         self.statusline = statusline;
         self
     }
 
+    /// Draws a scrollbar along the right edge of the buffer
+    #[must_use]
+    pub const fn with_scrollbar(mut self, scrollbar: bool) -> Self {
+        self.scrollbar = scrollbar;
+        self
+    }
+
     #[must_use]
     pub const fn with_mode(mut self, mode: &'static str) -> Self {
         self.mode = mode;
         self
     }
 
+    #[must_use]
+    pub const fn with_status(mut self, status: &'sl str) -> Self {
+        self.status = status;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_pending_keys(mut self, pending_keys: &'sl str) -> Self {
+        self.pending_keys = pending_keys;
+        self
+    }
+
+    /// Adds a layer, it is drawn over the ones of a lower or the same priority
+    #[must_use]
+    pub fn with_layer(mut self, layer: Layer<'hl>) -> Self {
+        let at = self
+            .layers
+            .partition_point(|other| other.priority <= layer.priority);
+        self.layers.insert(at, layer);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_diagnostics(mut self, diagnostics: &'hl [Diagnostic]) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_virtual_text(mut self, virtual_text: bool) -> Self {
+        self.virtual_text = virtual_text;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_annotations(mut self, annotations: &'hl [String]) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
     #[must_use]
     pub const fn with_line_numbers(mut self, line_numbers: bool) -> Self {
         self.line_numbers = line_numbers;
@@ -95,7 +223,13 @@ impl<'sl, 'hl> FlushOptions<'sl, 'hl> {
     }
 
     #[must_use]
-    pub fn with_highlights(mut self, highlights: &'hl [Highlight]) -> Self {
+    pub const fn with_relative_line_numbers(mut self, relative_line_numbers: bool) -> Self {
+        self.relative_line_numbers = relative_line_numbers;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_highlights(mut self, highlights: &'hl [Highlight]) -> Self {
         self.highlights = highlights;
         self
     }
@@ -105,34 +239,78 @@ impl<'sl, 'hl> FlushOptions<'sl, 'hl> {
         self.line_offset = line_offset;
         self
     }
+
+    #[must_use]
+    pub const fn with_column_offset(mut self, column_offset: usize) -> Self {
+        self.column_offset = column_offset;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
 }
 
 impl Default for FlushOptions<'_, '_> {
     fn default() -> Self {
         Self {
             wrap: true,
+            showbreak: "",
+            list_chars: None,
             mode: "",
+            status: "",
+            pending_keys: "",
             statusline: false,
+            scrollbar: false,
             line_numbers: false,
+            relative_line_numbers: false,
             highlights: &[],
+            layers: Vec::new(),
+            diagnostics: &[],
+            virtual_text: false,
+            annotations: &[],
             line_offset: 0,
+            column_offset: 0,
+            theme: Theme::default(),
         }
     }
 }
 
 struct FlushState<'a> {
     current_y: usize,
+    cursor_line: usize,
     highlights: &'a [Highlight],
+    /// The ranges of each layer that do not end before the character being drawn
+    layers: Vec<&'a [Range<usize>]>,
+    /// The diagnostics that do not start before the line being drawn
+    diagnostics: &'a [Diagnostic],
     bounds: DrawBounds,
+    /// The columns of the line being drawn at which its continuation rows start
+    breaks: Vec<usize>,
+    /// The columns the showbreak text takes at the start of the continuation rows
+    indent: usize,
 }
 
 impl<'a> FlushState<'a> {
     #[must_use]
-    pub const fn new(highlights: &'a [Highlight], bounds: DrawBounds) -> Self {
+    pub fn new(opts: &FlushOptions<'_, 'a>, bounds: DrawBounds, cursor_line: usize) -> Self {
+        let showbreak_width = opts
+            .showbreak
+            .chars()
+            .map(edi_term::window::char_width)
+            .sum();
+        let indent = wrap::effective_indent(bounds.main.width(), showbreak_width);
         Self {
             current_y: 0,
-            highlights,
+            cursor_line,
+            highlights: opts.highlights,
+            layers: opts.layers.iter().map(|layer| layer.ranges).collect(),
+            diagnostics: opts.diagnostics,
             bounds,
+            breaks: Vec::new(),
+            indent,
         }
     }
 }
@@ -143,8 +321,9 @@ impl Buffer {
         let start = std::time::Instant::now();
 
         let mut flush_state = FlushState::new(
-            &opts.highlights,
+            opts,
             DrawBounds::calculate(surface.dimensions(), self.inner.total_lines(), opts),
+            self.current_line(),
         );
         debug!(
             "cursor_offset: {}, opts: {:?}, len: {}",
@@ -158,7 +337,8 @@ impl Buffer {
         debug!("finished in {}ms", start.elapsed().as_millis());
     }
 
-    pub fn main_dimensions(
+    #[must_use]
+    pub const fn main_dimensions(
         &self,
         dimensions: Dimensions<usize>,
         total_lines: usize,
@@ -179,12 +359,16 @@ impl Buffer {
         let available_height = state.bounds.main.height();
 
         if opts.statusline {
-            self.flush_statusline(surface, opts, state);
+            Self::flush_statusline(surface, opts, state);
+        }
+
+        if opts.scrollbar {
+            Scrollbar::new(self.inner.total_lines(), available_height, opts.line_offset)
+                .draw(state.bounds.scrollbar, surface);
         }
 
         self.inner
-            .lines()
-            .skip(opts.line_offset)
+            .lines_at(opts.line_offset)
             .take(available_height)
             .for_each(|line_info| {
                 self.flush_line(surface, opts, &line_info, state);
@@ -192,10 +376,7 @@ impl Buffer {
 
         // Special case: even though there is nothing, draw a line
         if self.inner.is_empty() {
-            debug!(
-                "this is hit somehow, {:?}",
-                self.inner.chars().collect::<String>()
-            );
+            debug!("this is hit somehow, {:?}", self.inner.to_string());
             self.flush_line(
                 surface,
                 opts,
@@ -210,22 +391,20 @@ impl Buffer {
         }
     }
 
-    fn flush_statusline<S: Surface>(
-        &self,
-        surface: &mut S,
-        opts: &FlushOptions,
-        state: &FlushState,
-    ) {
-        state.bounds.statusline.clear(surface, Color::Cyan);
-        let mut offs = 0;
-        for c in " [".chars().chain(opts.mode.chars()).chain("]".chars()) {
-            state.bounds.statusline.set(
-                Coord::new(offs, 0),
-                Cell::new(c, Color::Black, Color::Cyan),
-                surface,
-            );
-            offs += 1;
-        }
+    fn flush_statusline<S: Surface>(surface: &mut S, opts: &FlushOptions, state: &FlushState) {
+        let Face { fg, bg, style } = opts.theme.statusline;
+        state.bounds.statusline.clear(surface, bg);
+        let span = |text| Span::new(text).with_colors(fg, bg).with_style(style);
+        let status = [" [", opts.mode, "]"].map(span);
+        draw_line(state.bounds.statusline, &status, Alignment::Left, surface);
+
+        let status: Vec<_> = [opts.pending_keys, opts.status]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .flat_map(|text| [text, " "])
+            .map(span)
+            .collect();
+        draw_line(state.bounds.statusline, &status, Alignment::Right, surface);
     }
 
     fn flush_line<S: Surface>(
@@ -242,20 +421,133 @@ impl Buffer {
         let mut max_y = flush_state.current_y;
 
         if opts.line_numbers {
-            Self::flush_line_number(info.line_number, flush_state, surface);
+            Self::flush_line_number(info.line_number, opts, flush_state, surface);
         }
 
-        self.flush_main(info, &mut max_y, flush_state, opts, surface);
+        let line_end = info.character_offset + info.length;
+        let diagnostic = Self::line_diagnostic(info.character_offset, line_end, flush_state);
+        if let Some(diagnostic) = diagnostic {
+            let sign = opts.theme.severity(diagnostic.severity);
+            flush_state.bounds.line_numbers.set(
+                Coord::new(0, flush_state.current_y),
+                sign.cell(diagnostic.severity.sign()),
+                surface,
+            );
+        }
+
+        if opts.wrap {
+            flush_state.breaks = self.wrap_breaks(
+                &info.contents,
+                flush_state.bounds.main.width(),
+                flush_state.indent,
+            );
+            Self::flush_showbreak(opts, flush_state, surface);
+        }
+        let width = self.flush_main(info, &mut max_y, flush_state, opts, surface);
+
+        let virtual_text = diagnostic.filter(|_| opts.virtual_text).map_or_else(
+            || {
+                let annotation = opts.annotations.get(info.line_number)?;
+                Some((annotation.as_str(), opts.theme.comment))
+            },
+            |diagnostic| {
+                let face = opts.theme.severity(diagnostic.severity);
+                Some((diagnostic.message.as_str(), face))
+            },
+        );
+        if let Some((text, face)) = virtual_text {
+            Self::flush_virtual_text(text, face, width, max_y, flush_state, opts, surface);
+        }
 
         flush_state.current_y = max_y + 1;
     }
 
+    /// Draws the showbreak text at the start of the continuation rows of the line
+    fn flush_showbreak<S: Surface>(opts: &FlushOptions, flush_state: &FlushState, surface: &mut S) {
+        if flush_state.indent == 0 {
+            return;
+        }
+
+        let main = Rect::new_in_origin(
+            flush_state.bounds.main.width(),
+            flush_state.bounds.main.height(),
+        );
+        for row in 1..=flush_state.breaks.len() {
+            let mut x = 0;
+            for c in opts.showbreak.chars() {
+                let pos = Coord::new(x, flush_state.current_y + row);
+                if !main.contains_point(pos) {
+                    return;
+                }
+                let cell = opts.theme.line_numbers.cell(c);
+                flush_state.bounds.main.set(pos, cell, surface);
+                x += edi_term::window::char_width(c);
+            }
+        }
+    }
+
+    /// Returns the most severe of the diagnostics that start in the line, skipping the ones that
+    /// start before it
+    fn line_diagnostic<'a>(
+        line_start: usize,
+        line_end: usize,
+        flush_state: &mut FlushState<'a>,
+    ) -> Option<&'a Diagnostic> {
+        let diagnostics = &mut flush_state.diagnostics;
+        while diagnostics
+            .first()
+            .is_some_and(|diagnostic| diagnostic.range.start < line_start)
+        {
+            *diagnostics = &diagnostics[1..];
+        }
+
+        diagnostics
+            .iter()
+            .take_while(|diagnostic| diagnostic.range.start <= line_end)
+            .min_by_key(|diagnostic| diagnostic.severity)
+    }
+
+    /// Draws the first line of the text a column after the end of the line, which takes `width`
+    /// columns, as far as the last row of the line goes
+    fn flush_virtual_text<S: Surface>(
+        text: &str,
+        face: Face,
+        width: usize,
+        max_y: usize,
+        flush_state: &FlushState,
+        opts: &FlushOptions,
+        surface: &mut S,
+    ) {
+        let text = text.lines().next().unwrap_or_default();
+        let mut x_offset = width + 2;
+        for c in text.chars() {
+            let Some(pos) = Self::get_char_pos(x_offset, opts, flush_state) else {
+                if opts.wrap {
+                    return;
+                }
+                x_offset += edi_term::window::char_width(c);
+                continue;
+            };
+            if pos.y != max_y {
+                return;
+            }
+            flush_state.bounds.main.set(pos, face.cell(c), surface);
+            x_offset += edi_term::window::char_width(c);
+        }
+    }
+
     fn flush_line_number<S: Surface>(
         line_number: usize,
+        opts: &FlushOptions,
         flush_state: &FlushState,
         surface: &mut S,
     ) {
-        let mut line_nr_buf = [0u8; 20];
+        let line_number = if opts.relative_line_numbers && line_number != flush_state.cursor_line {
+            line_number.abs_diff(flush_state.cursor_line)
+        } else {
+            line_number
+        };
+        let mut line_nr_buf = [0_u8; 20];
         let line_number_bytes = itoa_into(line_number as u64, &mut line_nr_buf);
         let offs = flush_state
             .bounds
@@ -271,12 +563,13 @@ impl Buffer {
             .for_each(|(i, c)| {
                 flush_state.bounds.line_numbers.set(
                     Coord::new(offs + i, flush_state.current_y),
-                    Cell::new(*c as char, Color::Cyan, Color::None),
+                    opts.theme.line_numbers.cell(*c as char),
                     surface,
                 );
             });
     }
 
+    /// Draws the text of the line, returning the number of columns it takes
     fn flush_main<S: Surface>(
         &self,
         info: &LineInfo,
@@ -284,7 +577,7 @@ impl Buffer {
         flush_state: &mut FlushState,
         opts: &FlushOptions,
         surface: &mut S,
-    ) {
+    ) -> usize {
         let LineInfo {
             contents: line_contents,
             character_offset: line_character_offset,
@@ -293,71 +586,107 @@ impl Buffer {
         } = info;
 
         let mut x_offset = 0;
+        let list = opts.list_chars.unwrap_or_default();
+        let trail_start = if list.trail.is_some() {
+            line_contents.trim_end_matches(' ').chars().count()
+        } else {
+            usize::MAX
+        };
 
         for (idx, character) in line_contents.chars().enumerate() {
-            if char::is_control(character) && character != '\t' {
-                todo!("control characters are not supported yet");
-            }
-
             let character_offset = line_character_offset + idx;
 
-            let Some(char_pos) = Self::get_char_pos(x_offset, opts, flush_state) else {
+            // Characters scrolled out of the view still take up their columns
+            let char_pos = Self::get_char_pos(x_offset, opts, flush_state);
+            let width = self.char_len(character, x_offset);
+            x_offset += width;
+            let Some(char_pos) = char_pos else {
                 continue;
             };
 
             *max_y = char_pos.y.max(*max_y);
 
-            x_offset += Self::char_len(character);
-
             if self.cursor_offset == character_offset {
                 flush_state.bounds.main.move_cursor(char_pos, surface);
             }
 
-            let color = Self::get_highlight_color(character_offset, &mut flush_state.highlights)
-                .unwrap_or(Color::White);
-
-            match character {
-                '\t' => {
-                    for i in 0..4 {
-                        let new_pos = Coord::new(char_pos.x + i, char_pos.y);
-                        flush_state.bounds.main.set(
-                            new_pos,
-                            Cell::new(character, color, Color::None),
-                            surface,
-                        );
-                    }
+            let mut face = Self::get_highlight_face(
+                character_offset,
+                &mut flush_state.highlights,
+                &opts.theme,
+            )
+            .unwrap_or(opts.theme.text);
+            for (layer, ranges) in opts.layers.iter().zip(&mut flush_state.layers) {
+                if Self::in_ranges(character_offset, ranges) {
+                    face = layer.face.over(face);
+                }
+            }
+
+            let whitespace = opts.theme.whitespace.over(face);
+            let marker = match character {
+                ' ' if idx >= trail_start => list.trail,
+                '\u{a0}' => list.nbsp,
+                _ => None,
+            };
+            if character == '\t' {
+                for i in 0..width {
+                    let new_pos = Coord::new(char_pos.x + i, char_pos.y);
+                    let cell = match list.tab {
+                        Some((first, _)) if i == 0 => whitespace.cell(first),
+                        Some((_, rest)) => whitespace.cell(rest),
+                        None => face.cell(' '),
+                    };
+                    flush_state.bounds.main.set(new_pos, cell, surface);
                 }
-                _ => {
-                    flush_state.bounds.main.set(
-                        char_pos,
-                        Cell::new(character, color, Color::None),
-                        surface,
-                    );
+            } else if character.is_control() {
+                let control = opts.theme.control.over(face);
+                for (i, c) in control_text(character).chars().enumerate() {
+                    let new_pos = Coord::new(char_pos.x + i, char_pos.y);
+                    flush_state
+                        .bounds
+                        .main
+                        .set(new_pos, control.cell(c), surface);
                 }
+            } else {
+                let cell = marker.map_or_else(|| face.cell(character), |m| whitespace.cell(m));
+                flush_state.bounds.main.set(char_pos, cell, surface);
             }
         }
 
-        if self.cursor_offset == line_character_offset + length {
-            if let Some(char_pos) = Self::get_char_pos(x_offset, opts, flush_state) {
-                flush_state.bounds.main.move_cursor(char_pos, surface);
-            }
+        if self.cursor_offset == line_character_offset + length
+            && let Some(char_pos) = Self::get_char_pos(x_offset, opts, flush_state)
+        {
+            flush_state.bounds.main.move_cursor(char_pos, surface);
         }
+
+        x_offset
     }
 
-    const fn char_len(c: char) -> usize {
+    /// Returns the number of columns `c` takes on the screen when it starts at column `x` of its
+    /// line. A tab goes on to the next multiple of the tab width
+    pub(crate) fn char_len(&self, c: char, x: usize) -> usize {
         match c {
-            '\t' => 4,
-            _other => 1,
+            '\t' => {
+                let tab_width = self.tab_width.max(1);
+                tab_width - x % tab_width
+            }
+            c if c.is_control() => control_text(c).len(),
+            other => edi_term::window::char_width(other),
         }
     }
 
     fn get_char_pos(x_offset: usize, opts: &FlushOptions, state: &FlushState) -> Option<Coord> {
         let (width, height) = (state.bounds.main.width(), state.bounds.main.height());
+        if width == 0 {
+            return None;
+        }
+
         let y_offset = state.current_y;
         let pos = if opts.wrap {
-            Coord::new(x_offset % width, y_offset + x_offset / width)
+            let (row, column) = Self::wrapped_pos(x_offset, width, state);
+            Coord::new(column, y_offset + row)
         } else {
-            Coord::new(x_offset, y_offset)
+            Coord::new(x_offset.checked_sub(opts.column_offset)?, y_offset)
         };
 
         Rect::new_in_origin(width, height)
@@ -365,96 +694,98 @@ impl Buffer {
             .then_some(pos)
     }
 
-    fn get_highlight_color(offs: usize, highlights: &mut &[Highlight]) -> Option<Color> {
+    /// Returns the row of the line and the column `x_offset` is drawn at when the line is wrapped.
+    /// Past the last character the columns go on to the rows below, as the cursor at the end of
+    /// a full row does
+    fn wrapped_pos(x_offset: usize, width: usize, state: &FlushState) -> (usize, usize) {
+        let row = state.breaks.partition_point(|&start| start <= x_offset);
+        let (start, indent) = match row {
+            0 => (0, 0),
+            row => (state.breaks[row - 1], state.indent),
+        };
+        let column = x_offset - start + indent;
+        if column < width {
+            return (row, column);
+        }
+
+        let overflow = column - width;
+        let row_width = width - state.indent;
+        (
+            row + 1 + overflow / row_width,
+            state.indent + overflow % row_width,
+        )
+    }
+
+    /// Returns whether `offs` lies in one of the `ranges`, skipping the ones that end before it
+    fn in_ranges(offs: usize, ranges: &mut &[Range<usize>]) -> bool {
+        while ranges.first().is_some_and(|r| r.end <= offs) {
+            *ranges = &ranges[1..];
+        }
+
+        ranges.first().is_some_and(|r| r.contains(&offs))
+    }
+
+    fn get_highlight_face(
+        offs: usize,
+        highlights: &mut &[Highlight],
+        theme: &Theme,
+    ) -> Option<Face> {
         let first_hl = highlights.first()?;
 
         if first_hl.start + first_hl.len < offs {
             *highlights = &highlights[1..];
-            return Self::get_highlight_color(offs, highlights);
+            return Self::get_highlight_face(offs, highlights, theme);
         }
 
         if !(first_hl.start..first_hl.start + first_hl.len).contains(&offs) {
             return None;
         }
 
-        Some(match first_hl.ty {
-            Type::Keyword => Color::Magenta,
-            _ => Color::Red,
-        })
+        Some(theme.highlight(first_hl.ty))
+    }
+}
+
+/// Returns the text a control character other than a tab is drawn as: `^G` for the ones below a
+/// space and `^?` for delete, `<85>` for the rest
+fn control_text(c: char) -> String {
+    let code = u32::from(c);
+    match u8::try_from(code) {
+        Ok(byte) if byte < b' ' || byte == 0x7f => format!("^{}", char::from(byte ^ 0x40)),
+        _ => format!("<{code:02x}>"),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::vec2::Vec2;
     use edi_frame::{
-        cell::{self, Color},
-        surface::Surface,
+        cell::{Color, Style},
+        surface::{MemorySurface, Surface},
     };
     use edi_term::coord::{Coord, Dimensions};
 
-    use crate::buffer::{Buffer, draw::FlushOptions};
-
-    struct TestSurface {
-        chars: Vec<Vec<char>>,
-        cursor_pos: Option<Coord>,
-    }
-
-    impl TestSurface {
-        pub fn new(dims: Vec2<usize>) -> Self {
-            Self {
-                chars: vec![vec![' '; dims.x]; dims.y],
-                cursor_pos: None,
-            }
-        }
-        pub fn get_contents(&self) -> Vec<String> {
-            self.chars.iter().map(|row| row.iter().collect()).collect()
-        }
-
-        pub fn clear(&mut self) {
-            self.chars = vec![vec![' '; self.chars[0].len()]; self.chars.len()];
-            self.cursor_pos = None;
-        }
-    }
-
-    impl Surface for TestSurface {
-        fn set(&mut self, position: Coord, cell: cell::Cell) {
-            let Coord { x, y } = position;
-            if y < self.chars.len() && x < self.chars[y].len() {
-                self.chars[y][x] = cell.char;
-            }
-        }
-        fn clear(&mut self, _color: Color) {
-            let Dimensions { width, height } = self.dimensions();
-            self.chars = vec![vec![' '; width]; height];
-            self.cursor_pos = None;
-        }
-        fn dimensions(&self) -> Dimensions<usize> {
-            Dimensions::new(self.chars[0].len(), self.chars.len())
-        }
-        fn move_cursor(&mut self, point: Coord) {
-            self.cursor_pos = Some(point)
-        }
-    }
+    use crate::{
+        buffer::{
+            Buffer,
+            draw::{FlushOptions, Layer},
+            list::ListChars,
+            theme::Theme,
+        },
+        diagnostic::{Diagnostic, Severity},
+        string::highlight::{Highlight, Type},
+    };
 
     #[test]
     fn simple() {
         let mut buf = Buffer::new("Hello!\nWorld!");
         buf.cursor_offset = 1;
 
-        let mut surface = TestSurface::new(Vec2::new(10, 5));
-
-        buf.flush(&mut surface, &Default::default());
+        let mut surface = MemorySurface::new(Dimensions::new(10, 5));
 
-        let contents = surface.get_contents();
-        assert_eq!(contents[0], "Hello!    ");
-        assert_eq!(contents[1], "World!    ");
+        buf.flush(&mut surface, &FlushOptions::default());
 
-        assert_eq!(surface.cursor_pos, Some(Coord::new(1, 0)));
+        surface.assert_screen(&["Hello!    ", "World!    "]);
 
-        for line in &contents[2..] {
-            assert_eq!(line, "          ");
-        }
+        assert_eq!(surface.cursor(), Some(Coord::new(1, 0)));
     }
 
     #[test]
@@ -463,106 +794,341 @@ mod tests {
         let mut buf = Buffer::new(long_line);
         buf.cursor_offset = 11;
 
-        let mut surface = TestSurface::new(Vec2::new(10, 5));
-
-        buf.flush(&mut surface, &Default::default());
-
-        let contents = surface.get_contents();
-        assert_eq!(contents[0], "This is a ");
-        assert_eq!(contents[1], "very long ");
-        assert_eq!(contents[2], "line that ");
-        assert_eq!(contents[3], "should wra");
-        assert_eq!(contents[4], "p around  ");
-
-        assert_eq!(surface.cursor_pos, Some(Coord::new(1, 1)));
-
-        let mut surface = TestSurface::new(Vec2::new(10, 5));
+        let mut surface = MemorySurface::new(Dimensions::new(10, 6));
+
+        buf.flush(&mut surface, &FlushOptions::default());
+
+        surface.assert_screen(&[
+            "This is a ",
+            "very long ",
+            "line that ",
+            "should    ",
+            "wrap      ",
+            "around    ",
+        ]);
+
+        assert_eq!(surface.cursor(), Some(Coord::new(1, 1)));
+
+        // The continuation rows start with the showbreak text
+        let mut surface = MemorySurface::new(Dimensions::new(10, 6));
+        buf.flush(&mut surface, &FlushOptions::default().with_showbreak("> "));
+        surface.assert_screen(&[
+            "This is a ",
+            "> very    ",
+            "> long    ",
+            "> line    ",
+            "> that    ",
+            "> should  ",
+        ]);
+        assert_eq!(surface.cursor(), Some(Coord::new(3, 1)));
+
+        // Words longer than a row are broken where it ends
+        let long_word = Buffer::new("a abcdefghijkl");
+        let mut surface = MemorySurface::new(Dimensions::new(10, 2));
+        long_word.flush(&mut surface, &FlushOptions::default());
+        surface.assert_screen(&["a abcdefgh", "ijkl      "]);
+
+        let mut surface = MemorySurface::new(Dimensions::new(10, 5));
         let opts = super::FlushOptions::default().with_wrap(false);
         buf.flush(&mut surface, &opts);
 
-        let contents = surface.get_contents();
-        assert_eq!(contents[0], "This is a ");
-        for line in &contents[1..] {
-            assert_eq!(line, "          ");
-        }
+        surface.assert_screen(&["This is a "]);
 
-        assert_eq!(surface.cursor_pos, None);
+        assert_eq!(surface.cursor(), None);
 
         let exact_width = "Exactly10c";
         let buf = Buffer::new(exact_width);
-        let mut surface = TestSurface::new(Vec2::new(10, 2));
+        let mut surface = MemorySurface::new(Dimensions::new(10, 2));
 
-        buf.flush(&mut surface, &Default::default());
+        buf.flush(&mut surface, &FlushOptions::default());
 
-        let contents = surface.get_contents();
-        assert_eq!(contents[0], "Exactly10c");
-        assert_eq!(contents[1], "          ");
-        assert_eq!(surface.cursor_pos, Some(Coord::new(0, 0)));
+        surface.assert_screen(&["Exactly10c", "          "]);
+        assert_eq!(surface.cursor(), Some(Coord::new(0, 0)));
 
         let with_empty = "First\nVery very long line that wraps\nLast";
         let buf = Buffer::new(with_empty);
-        let mut surface = TestSurface::new(Vec2::new(10, 6));
-
-        buf.flush(&mut surface, &Default::default());
-
-        let contents = surface.get_contents();
-        assert_eq!(contents[0], "First     ");
-        assert_eq!(contents[1], "Very very ");
-        assert_eq!(contents[2], "long line ");
-        assert_eq!(contents[3], "that wraps");
-        assert_eq!(contents[4], "Last      ");
-        assert_eq!(contents[5], "          ");
-        assert_eq!(surface.cursor_pos, Some(Coord::new(0, 0)));
+        let mut surface = MemorySurface::new(Dimensions::new(10, 6));
+
+        buf.flush(&mut surface, &FlushOptions::default());
+
+        surface.assert_screen(&[
+            "First     ",
+            "Very very ",
+            "long line ",
+            "that wraps",
+            "Last      ",
+            "          ",
+        ]);
+        assert_eq!(surface.cursor(), Some(Coord::new(0, 0)));
     }
 
     #[test]
     fn line_offset() {
         let text = "First line\nSecond line\nThird line\nFourth line";
         let buf = Buffer::new(text);
-        let mut surface = TestSurface::new(Vec2::new(20, 3));
+        let mut surface = MemorySurface::new(Dimensions::new(20, 3));
 
-        buf.flush(&mut surface, &Default::default());
-        let contents = surface.get_contents();
-        assert_eq!(contents[0], "First line          ");
-        assert_eq!(contents[1], "Second line         ");
-        assert_eq!(contents[2], "Third line          ");
+        buf.flush(&mut surface, &FlushOptions::default());
+        surface.assert_screen(&[
+            "First line          ",
+            "Second line         ",
+            "Third line          ",
+        ]);
 
         let opts = FlushOptions::default().with_line_offset(1);
-        surface.clear();
+        surface.clear(Color::None);
         buf.flush(&mut surface, &opts);
-        let contents = surface.get_contents();
-        assert_eq!(contents[0], "Second line         ");
-        assert_eq!(contents[1], "Third line          ");
-        assert_eq!(contents[2], "Fourth line         ");
+        surface.assert_screen(&[
+            "Second line         ",
+            "Third line          ",
+            "Fourth line         ",
+        ]);
 
         let opts = FlushOptions::default().with_line_offset(10);
         buf.flush(&mut surface, &opts);
-        surface.clear();
-        let contents = surface.get_contents();
-        assert_eq!(contents[0], "                    ");
-        assert_eq!(contents[1], "                    ");
-        assert_eq!(contents[2], "                    ");
+        surface.clear(Color::None);
+        surface.assert_screen(&[
+            "                    ",
+            "                    ",
+            "                    ",
+        ]);
+    }
+
+    #[test]
+    fn column_offset() {
+        let mut buf = Buffer::new("This is a long line\nshort");
+        buf.cursor_offset = 12;
+        let mut surface = MemorySurface::new(Dimensions::new(8, 2));
+        let opts = FlushOptions::default()
+            .with_wrap(false)
+            .with_column_offset(8);
+        buf.flush(&mut surface, &opts);
+        surface.assert_screen(&["a long l", "        "]);
+        assert_eq!(surface.cursor(), Some(Coord::new(4, 0)));
+
+        // The offset only applies to lines that are not wrapped
+        let mut surface = MemorySurface::new(Dimensions::new(8, 3));
+        buf.flush(&mut surface, &opts.with_wrap(true));
+        surface.assert_screen(&["This is ", "a long  ", "line    "]);
     }
 
     #[test]
     fn empty() {
         let text = "";
         let buf = Buffer::new(text);
-        let mut surface = TestSurface::new(Vec2::new(5, 2));
+        let mut surface = MemorySurface::new(Dimensions::new(5, 2));
         let opts = FlushOptions::default().with_line_numbers(true);
         buf.flush(&mut surface, &opts);
-        let contents = surface.get_contents();
-        assert_eq!(contents[0], "   0 ");
-        assert_eq!(contents[1], "     ");
-        assert_eq!(surface.cursor_pos, None);
+        surface.assert_screen(&["   0 ", "     "]);
+        assert_eq!(surface.cursor(), None);
         // TODO: fix a bug where when you start editing an empty file, every newline does not
         // create a line while drawing
         let text = "\n\n";
         let buf = Buffer::new(text);
-        surface.clear();
+        surface.clear(Color::None);
+        buf.flush(&mut surface, &opts);
+        surface.assert_screen(&["   0 ", "   1 "]);
+    }
+
+    #[test]
+    fn relative_line_numbers() {
+        let mut buf = Buffer::new("a\nb\nc\nd\n");
+        buf.cursor_offset = 4;
+        let mut surface = MemorySurface::new(Dimensions::new(6, 4));
+        let opts = FlushOptions::default()
+            .with_line_numbers(true)
+            .with_relative_line_numbers(true);
+        buf.flush(&mut surface, &opts);
+        surface.assert_screen(&["   2 a", "   1 b", "   2 c", "   1 d"]);
+    }
+
+    #[test]
+    fn scrollbar() {
+        let buf = Buffer::new("one\ntwo\nthree\nfour");
+        let mut surface = MemorySurface::new(Dimensions::new(6, 2));
+        let opts = FlushOptions::default()
+            .with_wrap(false)
+            .with_scrollbar(true)
+            .with_line_offset(2);
+        buf.flush(&mut surface, &opts);
+        surface.assert_screen(&["three│", "four █"]);
+    }
+
+    #[test]
+    fn statusline() {
+        let buf = Buffer::new("text");
+        let mut surface = MemorySurface::new(Dimensions::new(8, 2));
+        let opts = FlushOptions::default()
+            .with_statusline(true)
+            .with_mode("NORMAL");
+        buf.flush(&mut surface, &opts);
+        surface.assert_screen(&["text", " [NORMAL"]);
+
+        let mut surface = MemorySurface::new(Dimensions::new(16, 2));
+        let opts = opts.with_status("[1/2]");
+        buf.flush(&mut surface, &opts);
+        surface.assert_screen(&["text", " [NORMAL] [1/2] "]);
+
+        let mut surface = MemorySurface::new(Dimensions::new(20, 2));
+        buf.flush(&mut surface, &opts.with_pending_keys("\"ad"));
+        surface.assert_screen(&["text", " [NORMAL] \"ad [1/2] "]);
+    }
+
+    #[test]
+    fn matches() {
+        let buf = Buffer::new("abcabc\nab");
+        let mut surface = MemorySurface::new(Dimensions::new(6, 2));
+        let matches = [1..3, 4..6, 7..8];
+        let theme = Theme::default();
+        buf.flush(
+            &mut surface,
+            &FlushOptions::default().with_layer(Layer::new(
+                &matches,
+                theme.search_match,
+                Layer::MATCHES,
+            )),
+        );
+        surface.assert_grid(
+            |cell| if cell.bg == Color::Yellow { 'm' } else { '.' },
+            &[".mm.mm", "m....."],
+        );
+    }
+
+    #[test]
+    fn layers() {
+        let buf = Buffer::new("fn main");
+        let mut surface = MemorySurface::new(Dimensions::new(7, 1));
+        let highlights = [Highlight {
+            start: 0,
+            len: 2,
+            ty: Type::Keyword,
+        }];
+        let (selection, matches) = (1..4, [3..4, 5..7]);
+        let theme = Theme::default();
+        let opts = FlushOptions::default()
+            .with_highlights(&highlights)
+            .with_layer(Layer::new(
+                std::slice::from_ref(&selection),
+                theme.selection,
+                Layer::SELECTION,
+            ))
+            .with_layer(Layer::new(&matches, theme.search_match, Layer::MATCHES));
+        buf.flush(&mut surface, &opts);
+
+        // The selection keeps the colors below it and reverses them
+        surface.assert_grid(
+            |cell| match (cell.fg, cell.style.contains(Style::REVERSE)) {
+                (Color::Magenta, false) => 'k',
+                (Color::Magenta, true) => 'K',
+                (Color::Black, true) => 'M',
+                (Color::Black, false) => 'm',
+                _ => '.',
+            },
+            &["kK.M.mm"],
+        );
+    }
+
+    #[test]
+    fn list_chars() {
+        let mut buf = Buffer::new("\tfn  \na\u{a0}b c");
+        buf.tab_width = 2;
+        let mut surface = MemorySurface::new(Dimensions::new(6, 2));
+        let highlights = [Highlight {
+            start: 1,
+            len: 4,
+            ty: Type::Keyword,
+        }];
+        let opts = FlushOptions::default()
+            .with_highlights(&highlights)
+            .with_list_chars(ListChars::parse(ListChars::DEFAULT));
+        buf.flush(&mut surface, &opts);
+        surface.assert_screen(&["> fn--", "a+b c "]);
+
+        // The markers are dimmed and keep the colors of the highlights below them
+        surface.assert_grid(
+            |cell| match (cell.fg, cell.style.contains(Style::DIM)) {
+                (Color::Magenta, true) => 'K',
+                (Color::Magenta, false) => 'k',
+                (_, true) => 'w',
+                (_, false) => '.',
+            },
+            &["wwkkKK", ".w...."],
+        );
+    }
+
+    #[test]
+    fn control_characters() {
+        let buf = Buffer::new("a\u{7}b\u{7f}\u{85}\n\tx\n a\tb");
+        let mut surface = MemorySurface::new(Dimensions::new(12, 3));
+        buf.flush(&mut surface, &FlushOptions::default());
+        // Tabs go on to the next multiple of the tab width
+        surface.assert_screen(&["a^Gb^?<85>  ", "    x       ", " a  b       "]);
+        surface.assert_grid(
+            |cell| if cell.fg == Color::Blue { 'c' } else { '.' },
+            &[".cc.cccccc..", "............", "............"],
+        );
+    }
+
+    #[test]
+    fn diagnostics() {
+        let buf = Buffer::new("let x\nfoo()\nok\nabcdefgh");
+        let diagnostic = |start, severity, message: &str| Diagnostic {
+            range: start..start + 1,
+            severity,
+            message: String::from(message),
+        };
+        let diagnostics = [
+            diagnostic(4, Severity::Warning, "unused"),
+            diagnostic(6, Severity::Hint, "hint"),
+            diagnostic(9, Severity::Error, "not found\nmore"),
+            diagnostic(20, Severity::Info, "long"),
+        ];
+        let mut surface = MemorySurface::new(Dimensions::new(16, 4));
+        let opts = FlushOptions::default()
+            .with_line_numbers(true)
+            .with_diagnostics(&diagnostics);
+        buf.flush(&mut surface, &opts);
+        surface.assert_screen(&[
+            "W  0 let x      ",
+            "E  1 foo()      ",
+            "   2 ok         ",
+            "I  3 abcdefgh   ",
+        ]);
+        assert_eq!(surface.cell(Coord::new(0, 1)).unwrap().fg, Color::Red);
+
+        let mut surface = MemorySurface::new(Dimensions::new(14, 5));
+        let opts = FlushOptions::default()
+            .with_diagnostics(&diagnostics)
+            .with_virtual_text(true);
+        buf.flush(&mut surface, &opts);
+        surface.assert_screen(&[
+            "W let x  unuse",
+            "E foo()  not f",
+            "  ok          ",
+            "I abcdefgh  lo",
+            "              ",
+        ]);
+
+        let mut surface = MemorySurface::new(Dimensions::new(7, 5));
+        buf.flush(&mut surface, &opts);
+        surface.assert_screen(&["W let x", "E foo()", "  ok   ", "I abcde", "  fgh  "]);
+    }
+
+    #[test]
+    fn annotations() {
+        let buf = Buffer::new("let x\nfoo()\nok");
+        let diagnostics = [Diagnostic {
+            range: 6..7,
+            severity: Severity::Error,
+            message: String::from("bad"),
+        }];
+        let annotations = ["a1 me".to_string(), "b2 you".to_string()];
+        let mut surface = MemorySurface::new(Dimensions::new(14, 3));
+        let opts = FlushOptions::default()
+            .with_diagnostics(&diagnostics)
+            .with_virtual_text(true)
+            .with_annotations(&annotations);
         buf.flush(&mut surface, &opts);
-        let contents = surface.get_contents();
-        assert_eq!(contents[0], "   0 ");
-        assert_eq!(contents[1], "   1 ");
+        surface.assert_screen(&["  let x  a1 me", "E foo()  bad  ", "  ok          "]);
     }
 }
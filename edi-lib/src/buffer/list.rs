@@ -0,0 +1,63 @@
+//! The characters whitespace is drawn as when it is made visible
+
+/// The markers drawn in place of whitespace, none for the kinds that are drawn as they are
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListChars {
+    /// The marker of the first column of a tab, and the one of the rest of its columns
+    pub tab: Option<(char, char)>,
+    /// The marker of the spaces at the end of a line
+    pub trail: Option<char>,
+    /// The marker of non-breaking spaces
+    pub nbsp: Option<char>,
+}
+
+impl ListChars {
+    /// The markers used unless `listchars` is set
+    pub const DEFAULT: &str = "tab:> ,trail:-,nbsp:+";
+
+    /// Parses the comma-separated `kind:markers` items of `listchars`, such as `tab:>-,trail:.`.
+    /// Returns `None` if one of the kinds is unknown or given the wrong number of markers
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut list = Self::default();
+        for item in s.split(',').filter(|item| !item.is_empty()) {
+            let (kind, markers) = item.split_once(':')?;
+            let markers: Vec<char> = markers.chars().collect();
+            match (kind, &markers[..]) {
+                ("tab", &[first, rest]) => list.tab = Some((first, rest)),
+                ("trail", &[marker]) => list.trail = Some(marker),
+                ("nbsp", &[marker]) => list.nbsp = Some(marker),
+                _ => return None,
+            }
+        }
+        Some(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            ListChars::parse(ListChars::DEFAULT),
+            Some(ListChars {
+                tab: Some(('>', ' ')),
+                trail: Some('-'),
+                nbsp: Some('+'),
+            })
+        );
+        assert_eq!(
+            ListChars::parse("trail:·"),
+            Some(ListChars {
+                trail: Some('·'),
+                ..ListChars::default()
+            })
+        );
+        assert_eq!(ListChars::parse(""), Some(ListChars::default()));
+        assert_eq!(ListChars::parse("tab:>"), None);
+        assert_eq!(ListChars::parse("eol:$"), None);
+        assert_eq!(ListChars::parse("trail"), None);
+    }
+}
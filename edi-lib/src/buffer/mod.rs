@@ -1,8 +1,11 @@
 pub mod draw;
+pub mod list;
+pub mod theme;
+pub mod wrap;
 pub mod write;
 
 use crate::string::{
-    position::{GlobalPosition, LinePosition},
+    position::{CharSearch, GlobalPosition, LinePosition, WordPosition},
     search,
 };
 
@@ -20,17 +23,38 @@ pub enum Direction {
 pub struct Buffer {
     pub inner: Rope,
     pub cursor_offset: usize,
+    /// The number of columns a tab takes on the screen
+    pub tab_width: usize,
+    /// The column moving up and down aims for along with the cursor offset it was moved to. It is
+    /// forgotten when the cursor is moved sideways or the text is changed, or once the cursor is
+    /// not at the offset anymore
+    desired_column: Option<(usize, usize)>,
 }
 
 impl Buffer {
+    pub const DEFAULT_TAB_WIDTH: usize = 4;
+
     #[must_use]
     pub fn new(inner: &str) -> Self {
         Self {
             inner: Rope::from(inner),
             cursor_offset: 0,
+            tab_width: Self::DEFAULT_TAB_WIDTH,
+            desired_column: None,
+        }
+    }
+
+    #[must_use]
+    pub const fn from_rope(inner: Rope) -> Self {
+        Self {
+            inner,
+            cursor_offset: 0,
+            tab_width: Self::DEFAULT_TAB_WIDTH,
+            desired_column: None,
         }
     }
 
+    #[must_use]
     pub fn total_lines(&self) -> usize {
         self.inner.total_lines()
     }
@@ -40,22 +64,32 @@ impl Buffer {
     /// Never panics
     ///
     pub fn move_cursor(&mut self, direction: Direction, steps: usize) {
+        if matches!(direction, Direction::Left | Direction::Right) {
+            self.desired_column = None;
+        }
         match direction {
             Direction::Left => {
-                if self.is_at_line_start() {
-                    return;
-                }
+                let line_start = self.current_line_info().character_offset;
+                for _ in 0..steps {
+                    if self.cursor_offset <= line_start {
+                        break;
+                    }
 
-                let new_offset = self.cursor_offset.saturating_sub(steps);
-                self.cursor_offset = new_offset;
+                    let new_offset = self.inner.prev_grapheme_boundary(self.cursor_offset);
+                    self.cursor_offset = new_offset.max(line_start);
+                }
             }
             Direction::Right => {
-                if self.is_at_line_end() {
-                    return;
-                }
+                let line_info = self.current_line_info();
+                let line_end = line_info.character_offset + line_info.length;
+                for _ in 0..steps {
+                    if self.cursor_offset >= line_end {
+                        break;
+                    }
 
-                let new_offset = self.cursor_offset + steps;
-                self.cursor_offset = new_offset;
+                    let new_offset = self.inner.next_grapheme_boundary(self.cursor_offset);
+                    self.cursor_offset = new_offset.min(line_end);
+                }
             }
             Direction::Up => {
                 if self.current_line() == 0 || self.inner.total_lines() == 0 {
@@ -64,9 +98,7 @@ impl Buffer {
                 }
 
                 let current_line = self.current_line();
-                let line_start_offset = self.offset_from_line_start();
-
-                self.set_cursor_line(current_line.saturating_sub(steps), line_start_offset);
+                self.move_to_line(current_line.saturating_sub(steps));
             }
             Direction::Down => {
                 if self.inner.total_lines() == 0 {
@@ -74,65 +106,93 @@ impl Buffer {
                 }
 
                 let current_line = self.current_line();
-                let line_start_offset = self.offset_from_line_start();
-
-                self.set_cursor_line(current_line + steps, line_start_offset);
+                self.move_to_line(current_line + steps);
             }
         }
     }
 
     fn current_line_info(&self) -> LineInfo {
         let current_line = self.current_line();
-        self.inner
-            .lines()
-            .nth(current_line)
-            .unwrap_or_else(|| LineInfo {
-                character_offset: self.inner.len(),
-                line_number: current_line,
-                length: 0,
-                contents: String::new(),
-            })
+        self.inner.line(current_line).unwrap_or_else(|| LineInfo {
+            character_offset: self.inner.len(),
+            line_number: current_line,
+            length: 0,
+            contents: String::new(),
+        })
     }
 
-    fn offset_from_line_start(&self) -> usize {
-        let line_offset = self.current_line_info().character_offset;
-        self.cursor_offset - line_offset
+    /// Returns the screen column of the cursor in its line, counting wide characters and tabs
+    #[must_use]
+    pub fn cursor_column(&self) -> usize {
+        let line_info = self.current_line_info();
+        line_info
+            .contents
+            .chars()
+            .take(self.cursor_offset - line_info.character_offset)
+            .fold(0, |x, c| x + self.char_len(c, x))
     }
 
-    fn is_at_line_start(&self) -> bool {
-        self.current_line_info().character_offset == self.cursor_offset
+    /// Returns the offset of the character of `line_info` that is drawn at `column`, or the end of
+    /// the line if it is shorter
+    fn offset_at_column(&self, line_info: &LineInfo, column: usize) -> usize {
+        let mut x = 0;
+        for (idx, c) in line_info.contents.chars().enumerate() {
+            x += self.char_len(c, x);
+            if x > column {
+                return line_info.character_offset + idx;
+            }
+        }
+        line_info.character_offset + line_info.length
     }
 
-    fn is_at_line_end(&self) -> bool {
-        let line_info = self.current_line_info();
-        line_info.character_offset + line_info.length == self.cursor_offset
+    /// Returns the column moving up and down aims for, the one of the cursor unless it was moved
+    /// up or down from a longer line
+    fn desired_column(&self) -> usize {
+        match self.desired_column {
+            Some((column, offset)) if offset == self.cursor_offset => column,
+            _ => self.cursor_column(),
+        }
+    }
+
+    /// Moves the cursor to the line, as close to the desired column as the line allows
+    fn move_to_line(&mut self, line: usize) {
+        let column = self.desired_column();
+        self.set_cursor_line(line, column);
+        self.desired_column = Some((column, self.cursor_offset));
     }
 
-    fn set_cursor_line(&mut self, line: usize, offs: usize) {
+    /// Moves the cursor to the character of its line drawn at `column`
+    pub fn set_cursor_column(&mut self, column: usize) {
+        self.desired_column = None;
+        self.set_cursor_line(self.current_line(), column);
+    }
+
+    fn set_cursor_line(&mut self, line: usize, column: usize) {
         let total_lines = self.inner.total_lines();
         let actual_line = line.min(total_lines);
         crate::debug!(
-            "setting cursor to line: {line} (actual {}),  offs: {offs}, total_lines: {}",
+            "setting cursor to line: {line} (actual {}), column: {column}, total_lines: {}",
             actual_line,
             self.inner.total_lines()
         );
         let Some(line_info) = self
             .inner
-            .line_info(actual_line)
-            .or_else(|| self.inner.line_info(actual_line.saturating_sub(1)))
+            .line(actual_line)
+            .or_else(|| self.inner.line(actual_line.saturating_sub(1)))
         else {
             return;
         };
 
-        self.cursor_offset = line_info.character_offset + line_info.length.min(offs);
+        self.cursor_offset = self.offset_at_column(&line_info, column);
     }
 
     pub fn move_in_line(&mut self, position: LinePosition) {
+        self.desired_column = None;
         let current_line = self.current_line();
         let Some(LineInfo {
-            mut character_offset,
+            character_offset,
             length,
-            mut contents,
+            contents,
             ..
         }) = self.inner.line(current_line)
         else {
@@ -141,67 +201,205 @@ impl Buffer {
 
         self.cursor_offset = match position {
             LinePosition::Start => character_offset,
-            LinePosition::End => character_offset + length,
+            LinePosition::End => {
+                // Moving up and down from the end of a line keeps to the ends of the lines
+                self.desired_column = Some((usize::MAX, character_offset + length));
+                character_offset + length
+            }
             LinePosition::CharacterStart => character_offset + search::character_start(&contents),
-            LinePosition::CurrentWordEnd => {
-                let is_at_eol = self.cursor_offset - character_offset >= length.saturating_sub(1);
-                let offset = if is_at_eol {
-                    0
-                } else {
-                    self.cursor_offset - character_offset
-                };
-                if is_at_eol {
-                    let Some(next_line) = self.inner.line(current_line + 1) else {
-                        // at the end of the file, nothing we can do
-                        return;
-                    };
-                    contents = next_line.contents;
-                    character_offset = next_line.character_offset;
-                }
-                character_offset
-                    + search::Searcher::new(&contents, offset)
-                        .with_skip(!is_at_eol)
-                        .find()
+        }
+    }
+
+    /// Moves the cursor to the word position. Words are runs of any characters but whitespace if
+    /// `big` is set, and empty lines count as words
+    pub fn move_word(&mut self, position: WordPosition, big: bool) {
+        self.desired_column = None;
+        let current_line = self.current_line();
+        let Some(line) = self.inner.line(current_line) else {
+            return;
+        };
+        let column = self.cursor_offset - line.character_offset;
+
+        self.cursor_offset = match position {
+            WordPosition::NextStart => self.next_word_start(current_line, column, big),
+            WordPosition::End => self.next_word_end(current_line, column, big),
+            WordPosition::Start => self.previous_word_start(current_line, column, big),
+            WordPosition::PreviousEnd => self.previous_word_end(current_line, column, big),
+        }
+    }
+
+    /// Moves the cursor to the `count`th occurrence of the character in the cursor line, or next
+    /// to it for `t` and `T`. The cursor stays where it is if the line has fewer of them. A
+    /// `repeated` search skips the character right next to the cursor, so that `;` after `t` does
+    /// not stop in place
+    pub fn find_char(&mut self, search: CharSearch, count: usize, repeated: bool) {
+        let Some(line) = self.inner.line(self.current_line()) else {
+            return;
+        };
+        let column = self.cursor_offset - line.character_offset;
+        let skip = usize::from(search.till && repeated);
+        let till = usize::from(search.till);
+        let mut chars = line.contents.chars().enumerate();
+
+        let found = if search.backward {
+            chars
+                .take(column)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .skip(skip)
+                .filter(|&(_, c)| c == search.c)
+                .nth(count.saturating_sub(1))
+                .map(|(i, _)| i + till)
+        } else {
+            chars
+                .by_ref()
+                .nth(column + skip)
+                .and_then(|_| {
+                    chars
+                        .filter(|&(_, c)| c == search.c)
+                        .nth(count.saturating_sub(1))
+                })
+                .map(|(i, _)| i - till)
+        };
+        if let Some(found) = found {
+            self.desired_column = None;
+            self.cursor_offset = line.character_offset + found;
+        }
+    }
+
+    /// Returns the offset of the start of the next word, or of the end of the last line if there is
+    /// none
+    fn next_word_start(&self, current_line: usize, column: usize, big: bool) -> usize {
+        let mut search = Some(column);
+        let mut last = None;
+        for line_number in current_line..=self.last_line() {
+            let Some(line) = self.inner.line(line_number) else {
+                break;
+            };
+            let start = search.take().map_or_else(
+                || {
+                    let blank = line.contents.chars().all(char::is_whitespace);
+                    (line.length == 0 || !blank).then(|| search::character_start(&line.contents))
+                },
+                |column| {
+                    search::Searcher::new(&line.contents, column)
+                        .with_big(big)
+                        .find_next_start()
+                },
+            );
+            if let Some(start) = start {
+                return line.character_offset + start;
             }
-            LinePosition::CurrentWordStart => {
-                let is_at_start = self.cursor_offset - character_offset == 0;
-                crate::debug!("is_at_start: {}", is_at_start);
-                let mut offset = self.cursor_offset - character_offset;
-                if is_at_start {
-                    if current_line == 0 {
-                        return;
-                    }
-                    let Some(prev_line) = self.inner.line(current_line - 1) else {
-                        // at the start of the file, nothing we can do
-                        return;
-                    };
-                    offset = prev_line.length.saturating_sub(1);
-                    contents = prev_line.contents;
-                    character_offset = prev_line.character_offset;
-                }
-                character_offset
-                    + search::Searcher::new_rev(&contents, offset)
-                        .with_skip(!is_at_start)
-                        .find()
+            last = Some(line.character_offset + line.length);
+        }
+
+        last.unwrap_or(self.cursor_offset)
+    }
+
+    /// Returns the offset of the end of the next word, lines with no words are skipped. The
+    /// cursor stays where it is if there is none
+    fn next_word_end(&self, current_line: usize, column: usize, big: bool) -> usize {
+        let mut search = Some(column);
+        for line_number in current_line..=self.last_line() {
+            let Some(line) = self.inner.line(line_number) else {
+                break;
+            };
+            let end = search.take().map_or_else(
+                || {
+                    let blank = line.contents.chars().all(char::is_whitespace);
+                    let start = search::character_start(&line.contents);
+                    (!blank).then(|| {
+                        search::Searcher::new(&line.contents, start)
+                            .with_big(big)
+                            .find_current_end()
+                    })
+                },
+                |column| {
+                    search::Searcher::new(&line.contents, column)
+                        .with_big(big)
+                        .find_next_end()
+                },
+            );
+            if let Some(end) = end {
+                return line.character_offset + end;
             }
         }
+
+        self.cursor_offset
+    }
+
+    /// Returns the offset of the start of the previous word, an empty line counts as one. The
+    /// start of the text is returned if there is none
+    fn previous_word_start(&self, current_line: usize, column: usize, big: bool) -> usize {
+        let mut search = Some(column);
+        for line_number in (0..=current_line).rev() {
+            let Some(line) = self.inner.line(line_number) else {
+                continue;
+            };
+            let column = search.take();
+            if column.is_none() && line.length == 0 {
+                return line.character_offset;
+            }
+            let start = search::Searcher::new_rev(&line.contents, column.unwrap_or(line.length))
+                .with_big(big)
+                .find_previous_start();
+            if let Some(start) = start {
+                return line.character_offset + start;
+            }
+        }
+
+        0
+    }
+
+    /// Returns the offset of the end of the previous word, or the start of the text if there is
+    /// none
+    fn previous_word_end(&self, current_line: usize, column: usize, big: bool) -> usize {
+        let mut search = Some(column);
+        for line_number in (0..=current_line).rev() {
+            let Some(line) = self.inner.line(line_number) else {
+                continue;
+            };
+            let column = search.take();
+            if column.is_none() && line.length == 0 {
+                return line.character_offset;
+            }
+            let end = search::Searcher::new_rev(&line.contents, column.unwrap_or(line.length))
+                .with_big(big)
+                .find_previous_end();
+            if let Some(end) = end {
+                return line.character_offset + end;
+            }
+        }
+
+        0
     }
 
     pub fn move_global(&mut self, position: GlobalPosition) {
-        let line_start_offset = self.offset_from_line_start();
+        let column = self.desired_column();
         let target_line_nr = match position {
             GlobalPosition::Start => 0,
-            GlobalPosition::End => self.inner.total_lines().saturating_sub(1),
+            GlobalPosition::End => self.last_line(),
+            GlobalPosition::Line(line) => line.min(self.last_line()),
         };
-        let target_line = self.inner.line_info(target_line_nr).unwrap_or(LineInfo {
+        let target_line = self.inner.line(target_line_nr).unwrap_or(LineInfo {
             line_number: 0,
             character_offset: 0,
             length: 0,
             contents: String::new(),
         });
         crate::debug!("target_line: {:?}", target_line);
-        let new_line_start_offset = target_line.length.min(line_start_offset);
-        self.cursor_offset = target_line.character_offset + new_line_start_offset;
+        self.cursor_offset = self.offset_at_column(&target_line, column);
+        self.desired_column = Some((column, self.cursor_offset));
+    }
+
+    /// Returns the last line that has text, the empty one after a trailing newline is not counted
+    fn last_line(&self) -> usize {
+        let len = self.inner.len();
+        let trailing_newline = len > 0 && self.inner.get(len - 1) == Some('\n');
+        self.inner
+            .total_lines()
+            .saturating_sub(usize::from(trailing_newline))
     }
 
     #[must_use]
@@ -219,8 +417,10 @@ mod tests {
 
     fn test_inputs(inner: &str, n: usize) {
         let mut r = Buffer::new(inner);
-        let mut lines: Vec<_> = inner.lines().map(|v| v.to_owned()).collect();
+        let mut lines: Vec<_> = inner.lines().map(str::to_owned).collect();
         let mut expected_pos = Vec2::new(0, 0);
+        // The column moving up and down aims for
+        let mut desired_x = None;
         let mut rng = SmallRng::from_seed([1; 32]);
 
         for _ in 0..n {
@@ -238,27 +438,32 @@ mod tests {
             match dir {
                 Direction::Up => {
                     if expected_pos.y > 0 {
+                        let x = *desired_x.get_or_insert(expected_pos.x);
                         expected_pos.y -= 1;
-                        expected_pos.x = expected_pos.x.min(lines[expected_pos.y].len())
-                    } else {
+                        expected_pos.x = x.min(lines[expected_pos.y].len());
+                    } else if expected_pos.x > 0 {
                         expected_pos.x = 0;
+                        desired_x = None;
                     }
                 }
                 Direction::Down => {
                     if expected_pos.y + 1 < lines.len() {
+                        let x = *desired_x.get_or_insert(expected_pos.x);
                         expected_pos.y += 1;
-                        expected_pos.x = expected_pos.x.min(lines[expected_pos.y].len())
+                        expected_pos.x = x.min(lines[expected_pos.y].len());
                     }
                 }
                 Direction::Left => {
                     if expected_pos.x > 0 {
                         expected_pos.x -= 1;
                     }
+                    desired_x = None;
                 }
                 Direction::Right => {
-                    if expected_pos.x + 1 <= lines[expected_pos.y].chars().count() {
+                    if expected_pos.x < lines[expected_pos.y].chars().count() {
                         expected_pos.x += 1;
                     }
+                    desired_x = None;
                 }
             }
 
@@ -274,6 +479,7 @@ mod tests {
                 );
                 lines[expected_pos.y] = s;
                 expected_pos.x += 1;
+                desired_x = None;
             }
 
             let mut cursor_offs: usize = lines
@@ -288,7 +494,7 @@ mod tests {
                 r.cursor_offset,
                 cursor_offs,
                 "after: {dir:?}, string: {string:?}, moved: {moved}, expected_pos: {expected_pos:?}, lines: {lines:?}, original pos: {original_pos:?}, original buffer pos: {original_rope_pos}, tree:{tree}",
-                string = r.inner.chars().collect::<String>(),
+                string = r.inner.to_string(),
                 tree = r.inner.to_ascii_tree(),
             );
         }
@@ -313,6 +519,169 @@ mod tests {
         test_inputs("\nHe\nllo\n\n", TRIES);
     }
 
+    #[test]
+    fn wide_movement() {
+        let mut b = Buffer::new("世界abc\nabcdef\n\tx");
+        b.move_cursor(Direction::Right, 1);
+        b.move_cursor(Direction::Down, 1);
+        assert_eq!(b.cursor_offset, 8);
+        b.move_cursor(Direction::Right, 3);
+        b.move_cursor(Direction::Up, 1);
+        assert_eq!(b.cursor_offset, 3);
+        b.move_cursor(Direction::Down, 2);
+        assert_eq!(b.cursor_offset, 15);
+    }
+
+    #[test]
+    fn grapheme_movement() {
+        let mut b = Buffer::new("ae\u{301}👍🏽b\nc");
+        let expected = [1, 3, 5, 6, 6];
+        for offset in expected {
+            b.move_cursor(Direction::Right, 1);
+            assert_eq!(b.cursor_offset, offset);
+        }
+
+        b.move_cursor(Direction::Left, 2);
+        assert_eq!(b.cursor_offset, 3);
+        b.move_cursor(Direction::Left, 8);
+        assert_eq!(b.cursor_offset, 0);
+    }
+
+    #[test]
+    fn desired_column() {
+        let mut b = Buffer::new("abcd\nx\n\nabcdef\n");
+        b.move_cursor(Direction::Right, 3);
+        b.move_cursor(Direction::Down, 2);
+        assert_eq!(b.cursor_offset, 7);
+        b.move_cursor(Direction::Down, 1);
+        assert_eq!(b.cursor_offset, 11);
+        b.move_global(GlobalPosition::Line(1));
+        assert_eq!(b.cursor_offset, 6);
+        b.move_cursor(Direction::Up, 1);
+        assert_eq!(b.cursor_offset, 3);
+
+        // Moving sideways forgets the column
+        b.move_cursor(Direction::Down, 1);
+        b.move_cursor(Direction::Left, 1);
+        b.move_cursor(Direction::Down, 2);
+        assert_eq!(b.cursor_offset, 8);
+
+        b.move_in_line(LinePosition::End);
+        b.move_cursor(Direction::Up, 3);
+        assert_eq!(b.cursor_offset, 4);
+        b.move_cursor(Direction::Down, 3);
+        assert_eq!(b.cursor_offset, 14);
+    }
+
+    #[test]
+    fn global_movement() {
+        let mut b = Buffer::new("ab\ncde\nfg\n");
+        b.move_cursor(Direction::Right, 1);
+        b.move_global(GlobalPosition::Line(1));
+        assert_eq!(b.cursor_offset, 4);
+        b.move_global(GlobalPosition::Line(9));
+        assert_eq!(b.cursor_offset, 8);
+        b.move_global(GlobalPosition::Start);
+        assert_eq!(b.cursor_offset, 1);
+
+        let mut b = Buffer::new("a\nb");
+        b.move_global(GlobalPosition::End);
+        assert_eq!(b.cursor_offset, 2);
+    }
+
+    #[test]
+    fn word_movement() {
+        // 0   4 6 89  12  16
+        // "foo(bar\n\n  x.y baz\n"
+        let mut b = Buffer::new("foo(bar\n\n  x.y baz\n");
+        let mut positions = Vec::new();
+        for _ in 0..7 {
+            b.move_word(WordPosition::NextStart, false);
+            positions.push(b.cursor_offset);
+        }
+        assert_eq!(positions, [3, 4, 8, 11, 12, 13, 15]);
+        b.move_word(WordPosition::NextStart, false);
+        assert_eq!(b.cursor_offset, 18);
+
+        let mut positions = Vec::new();
+        for _ in 0..5 {
+            b.move_word(WordPosition::PreviousEnd, false);
+            positions.push(b.cursor_offset);
+        }
+        assert_eq!(positions, [17, 13, 12, 11, 8]);
+        b.move_word(WordPosition::PreviousEnd, false);
+        assert_eq!(b.cursor_offset, 6);
+        b.move_word(WordPosition::PreviousEnd, true);
+        assert_eq!(b.cursor_offset, 0);
+
+        b.move_word(WordPosition::NextStart, true);
+        assert_eq!(b.cursor_offset, 8);
+        b.move_word(WordPosition::NextStart, true);
+        assert_eq!(b.cursor_offset, 11);
+        b.move_word(WordPosition::End, true);
+        assert_eq!(b.cursor_offset, 13);
+        b.move_word(WordPosition::Start, true);
+        assert_eq!(b.cursor_offset, 11);
+    }
+
+    #[test]
+    fn word_movement_across_lines() {
+        // 0   4  7 8 9  12  16
+        // "foo bar\n\n  \n  baz.x\n"
+        let mut b = Buffer::new("foo bar\n\n  \n  baz.x\n");
+        let mut positions = Vec::new();
+        for _ in 0..4 {
+            b.move_word(WordPosition::End, false);
+            positions.push(b.cursor_offset);
+        }
+        assert_eq!(positions, [2, 6, 16, 17]);
+        b.move_word(WordPosition::End, false);
+        assert_eq!(b.cursor_offset, 18);
+        b.move_word(WordPosition::End, false);
+        assert_eq!(b.cursor_offset, 18);
+
+        let mut positions = Vec::new();
+        for _ in 0..4 {
+            b.move_word(WordPosition::Start, false);
+            positions.push(b.cursor_offset);
+        }
+        assert_eq!(positions, [17, 14, 8, 4]);
+        b.move_word(WordPosition::Start, true);
+        assert_eq!(b.cursor_offset, 0);
+        b.move_word(WordPosition::Start, true);
+        assert_eq!(b.cursor_offset, 0);
+
+        b.move_word(WordPosition::End, true);
+        assert_eq!(b.cursor_offset, 2);
+        b.move_word(WordPosition::End, true);
+        assert_eq!(b.cursor_offset, 6);
+        b.move_word(WordPosition::End, true);
+        assert_eq!(b.cursor_offset, 18);
+    }
+
+    #[test]
+    fn char_search() {
+        let mut b = Buffer::new("a(b)c(d)e\nf)");
+        let find = |c, backward, till| CharSearch { c, backward, till };
+
+        b.find_char(find(')', false, false), 1, false);
+        assert_eq!(b.cursor_offset, 3);
+        b.find_char(find(')', false, false), 2, false);
+        assert_eq!(b.cursor_offset, 3);
+        b.find_char(find('(', true, true), 1, false);
+        assert_eq!(b.cursor_offset, 2);
+        b.find_char(find('(', true, true), 1, true);
+        assert_eq!(b.cursor_offset, 2);
+
+        b.cursor_offset = 0;
+        b.find_char(find('(', false, true), 1, false);
+        assert_eq!(b.cursor_offset, 0);
+        b.find_char(find('(', false, true), 1, true);
+        assert_eq!(b.cursor_offset, 4);
+        b.find_char(find('a', true, false), 1, false);
+        assert_eq!(b.cursor_offset, 0);
+    }
+
     #[test]
     fn empty() {
         let mut b = Buffer::new("");
@@ -0,0 +1,329 @@
+//! The colors and attributes the parts of a buffer are drawn with, and the color schemes built in
+
+use edi_frame::cell::{Cell, Color, Style};
+
+use crate::{diagnostic::Severity, string::highlight::Type};
+
+/// The colors and the attributes text is drawn with. When a face is drawn over another one, its
+/// `Color::None` colors let those of the other one show through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Face {
+    pub fg: Color,
+    pub bg: Color,
+    pub style: Style,
+}
+
+impl Face {
+    /// A face that keeps everything of the one it is drawn over
+    pub const NONE: Self = Self::fg(Color::None);
+
+    /// Returns a face of the foreground color, on the background of the terminal
+    #[must_use]
+    pub const fn fg(fg: Color) -> Self {
+        Self {
+            fg,
+            bg: Color::None,
+            style: Style::NONE,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_bg(mut self, bg: Color) -> Self {
+        self.bg = bg;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Returns the face drawn over the `below` one, whose colors are kept where it has none and
+    /// whose attributes are added to
+    #[must_use]
+    pub fn over(self, below: Self) -> Self {
+        let pick = |color, below| if color == Color::None { below } else { color };
+        Self {
+            fg: pick(self.fg, below.fg),
+            bg: pick(self.bg, below.bg),
+            style: below.style | self.style,
+        }
+    }
+
+    /// Returns a cell of the character drawn with the face
+    #[must_use]
+    pub const fn cell(self, c: char) -> Cell {
+        Cell::new(c, self.fg, self.bg).with_style(self.style)
+    }
+
+    fn to_palette(self) -> Self {
+        Self {
+            fg: self.fg.to_palette(),
+            bg: self.bg.to_palette(),
+            ..self
+        }
+    }
+}
+
+/// The faces a buffer is drawn with, one for each highlight group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// The name of the color scheme the theme is
+    pub name: &'static str,
+    pub text: Face,
+    pub keyword: Face,
+    pub function: Face,
+    pub types: Face,
+    pub identifier: Face,
+    pub comment: Face,
+    pub string: Face,
+    pub number: Face,
+    pub line_numbers: Face,
+    pub statusline: Face,
+    /// The face of the text the language server reports a problem in, drawn over the highlights
+    pub diagnostic: Face,
+    /// The faces of the signs and the messages of the diagnostics of each severity
+    pub error: Face,
+    pub warning: Face,
+    pub info: Face,
+    pub hint: Face,
+    /// The face of the matches of the search, drawn over the highlights and the diagnostics
+    pub search_match: Face,
+    /// The face of the selected text, drawn over the highlights and the matches
+    pub selection: Face,
+    /// The faces of the items of a popup menu, and of the selected one
+    pub menu: Face,
+    pub menu_selected: Face,
+    /// The face of the markers whitespace is drawn as with `list` set, drawn over the highlights
+    pub whitespace: Face,
+    /// The face of the control characters, drawn as `^G` over the highlights
+    pub control: Face,
+    /// The face of the misspelled words with `spell` set, drawn under the diagnostics
+    pub spell: Face,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: "default",
+            text: Face::fg(Color::White),
+            keyword: Face::fg(Color::Magenta),
+            function: Face::fg(Color::Red),
+            types: Face::fg(Color::Red),
+            identifier: Face::fg(Color::Red),
+            comment: Face::fg(Color::Blue),
+            string: Face::fg(Color::Green),
+            number: Face::fg(Color::Red),
+            line_numbers: Face::fg(Color::Cyan),
+            statusline: Face::fg(Color::Black).with_bg(Color::Cyan),
+            diagnostic: Face::NONE.with_style(Style::UNDERCURL),
+            error: Face::fg(Color::Red),
+            warning: Face::fg(Color::Yellow),
+            info: Face::fg(Color::Blue),
+            hint: Face::fg(Color::Cyan),
+            search_match: Face::fg(Color::Black).with_bg(Color::Yellow),
+            selection: Face::NONE.with_style(Style::REVERSE),
+            menu: Face::fg(Color::Black).with_bg(Color::White),
+            menu_selected: Face::fg(Color::Black).with_bg(Color::Cyan),
+            whitespace: Face::NONE.with_style(Style::DIM),
+            control: Face::fg(Color::Blue),
+            spell: Face::fg(Color::Red).with_style(Style::UNDERLINE),
+        }
+    }
+}
+
+impl Theme {
+    /// The names of the color schemes built in
+    pub const BUILTIN: [&str; 3] = ["default", "gruvbox", "nord"];
+
+    /// Returns the color scheme built in under the `name`
+    #[must_use]
+    pub fn builtin(name: &str) -> Option<Self> {
+        let rgb = |hex| Color::parse(hex).unwrap_or_default();
+        let face = |hex| Face::fg(rgb(hex));
+
+        let theme = match name {
+            "default" => Self::default(),
+            "gruvbox" => Self {
+                name: "gruvbox",
+                text: face("#ebdbb2"),
+                keyword: face("#fb4934"),
+                function: face("#b8bb26").with_style(Style::BOLD),
+                types: face("#fabd2f"),
+                identifier: face("#83a598"),
+                comment: face("#928374").with_style(Style::ITALIC),
+                string: face("#b8bb26"),
+                number: face("#d3869b"),
+                line_numbers: face("#7c6f64"),
+                statusline: face("#ebdbb2").with_bg(rgb("#504945")),
+                diagnostic: Face::NONE.with_style(Style::UNDERCURL),
+                error: face("#fb4934"),
+                warning: face("#fabd2f"),
+                info: face("#83a598"),
+                hint: face("#8ec07c"),
+                search_match: face("#282828").with_bg(rgb("#fabd2f")),
+                selection: Face::NONE.with_bg(rgb("#665c54")),
+                menu: face("#ebdbb2").with_bg(rgb("#3c3836")),
+                menu_selected: face("#282828").with_bg(rgb("#83a598")),
+                whitespace: face("#665c54"),
+                control: face("#fe8019"),
+                spell: face("#fb4934").with_style(Style::UNDERCURL),
+            },
+            "nord" => Self {
+                name: "nord",
+                text: face("#d8dee9"),
+                keyword: face("#81a1c1"),
+                function: face("#88c0d0"),
+                types: face("#8fbcbb"),
+                identifier: face("#d8dee9"),
+                comment: face("#616e88").with_style(Style::ITALIC),
+                string: face("#a3be8c"),
+                number: face("#b48ead"),
+                line_numbers: face("#4c566a"),
+                statusline: face("#eceff4").with_bg(rgb("#3b4252")),
+                diagnostic: Face::NONE.with_style(Style::UNDERCURL),
+                error: face("#bf616a"),
+                warning: face("#ebcb8b"),
+                info: face("#81a1c1"),
+                hint: face("#8fbcbb"),
+                search_match: face("#2e3440").with_bg(rgb("#ebcb8b")),
+                selection: Face::NONE.with_bg(rgb("#434c5e")),
+                menu: face("#d8dee9").with_bg(rgb("#3b4252")),
+                menu_selected: face("#2e3440").with_bg(rgb("#88c0d0")),
+                whitespace: face("#4c566a"),
+                control: face("#d08770"),
+                spell: face("#bf616a").with_style(Style::UNDERCURL),
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+
+    /// Returns the face of the highlights of the type
+    #[must_use]
+    pub const fn highlight(&self, ty: Type) -> Face {
+        match ty {
+            Type::Keyword => self.keyword,
+            Type::Function => self.function,
+            Type::Type => self.types,
+            Type::Identifier => self.identifier,
+            Type::Comment => self.comment,
+            Type::String => self.string,
+            Type::Number => self.number,
+        }
+    }
+
+    /// Returns the face of the diagnostics of the severity
+    #[must_use]
+    pub const fn severity(&self, severity: Severity) -> Face {
+        match severity {
+            Severity::Error => self.error,
+            Severity::Warning => self.warning,
+            Severity::Info => self.info,
+            Severity::Hint => self.hint,
+        }
+    }
+
+    /// Returns the face of the highlight group, named as in the configuration file
+    pub fn face_mut(&mut self, group: &str) -> Option<&mut Face> {
+        let face = match group {
+            "text" => &mut self.text,
+            "keyword" => &mut self.keyword,
+            "function" => &mut self.function,
+            "type" => &mut self.types,
+            "identifier" => &mut self.identifier,
+            "comment" => &mut self.comment,
+            "string" => &mut self.string,
+            "number" => &mut self.number,
+            "line_numbers" => &mut self.line_numbers,
+            "statusline" => &mut self.statusline,
+            "diagnostic" => &mut self.diagnostic,
+            "error" => &mut self.error,
+            "warning" => &mut self.warning,
+            "info" => &mut self.info,
+            "hint" => &mut self.hint,
+            "match" => &mut self.search_match,
+            "selection" => &mut self.selection,
+            "menu" => &mut self.menu,
+            "menu_selected" => &mut self.menu_selected,
+            "whitespace" => &mut self.whitespace,
+            "control" => &mut self.control,
+            "spell" => &mut self.spell,
+            _ => return None,
+        };
+        Some(face)
+    }
+
+    /// Returns the theme with its true colors replaced by the closest ones of the 256-color
+    /// palette
+    #[must_use]
+    pub fn to_palette(mut self) -> Self {
+        for face in [
+            &mut self.text,
+            &mut self.keyword,
+            &mut self.function,
+            &mut self.types,
+            &mut self.identifier,
+            &mut self.comment,
+            &mut self.string,
+            &mut self.number,
+            &mut self.line_numbers,
+            &mut self.statusline,
+            &mut self.diagnostic,
+            &mut self.error,
+            &mut self.warning,
+            &mut self.info,
+            &mut self.hint,
+            &mut self.search_match,
+            &mut self.selection,
+            &mut self.menu,
+            &mut self.menu_selected,
+            &mut self.whitespace,
+            &mut self.control,
+            &mut self.spell,
+        ] {
+            *face = face.to_palette();
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schemes() {
+        for name in Theme::BUILTIN {
+            assert_eq!(Theme::builtin(name).map(|theme| theme.name), Some(name));
+        }
+        assert_eq!(Theme::builtin("solarized"), None);
+
+        let gruvbox = Theme::builtin("gruvbox").unwrap();
+        assert_eq!(
+            gruvbox.highlight(Type::Keyword).fg,
+            Color::Rgb(0xfb, 0x49, 0x34)
+        );
+        assert_eq!(gruvbox.to_palette().keyword.fg, Color::Indexed(203));
+        assert_eq!(
+            Theme::default().to_palette().statusline,
+            Theme::default().statusline
+        );
+    }
+
+    #[test]
+    fn layering() {
+        let keyword = Face::fg(Color::Red).with_style(Style::BOLD);
+        let selection = Face::NONE.with_bg(Color::Blue);
+        assert_eq!(
+            selection.over(keyword),
+            Face::fg(Color::Red)
+                .with_bg(Color::Blue)
+                .with_style(Style::BOLD)
+        );
+        let search_match = Face::fg(Color::Black).with_bg(Color::Yellow);
+        assert_eq!(search_match.over(keyword).fg, Color::Black);
+        assert_eq!(Face::NONE.over(keyword), keyword);
+    }
+}
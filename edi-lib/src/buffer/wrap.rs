@@ -0,0 +1,181 @@
+//! Breaking long lines into the rows they are drawn on
+
+use super::Buffer;
+
+/// Returns the columns of the line at which its continuation rows start when it is wrapped at
+/// `width` columns, given the characters of the line with the columns each of them takes. The
+/// continuation rows start after `indent` columns. A word is moved to the next row whole if it
+/// does not fit at the end of the row, unless it is longer than a row
+#[must_use]
+pub fn breaks(cells: &[(char, usize)], width: usize, indent: usize) -> Vec<usize> {
+    let indent = effective_indent(width, indent);
+    let mut breaks = Vec::new();
+    let (mut row_start, mut row_indent) = (0, 0);
+    let mut x = 0;
+
+    for (idx, &(c, char_width)) in cells.iter().enumerate() {
+        let column = x - row_start + row_indent;
+        let starts_word = !c.is_whitespace() && (idx == 0 || cells[idx - 1].0.is_whitespace());
+        let word_width: usize = if starts_word {
+            cells[idx..]
+                .iter()
+                .take_while(|(c, _)| !c.is_whitespace())
+                .map(|&(_, width)| width)
+                .sum()
+        } else {
+            char_width
+        };
+        let fits = column + char_width <= width
+            && (column + word_width <= width || word_width > width - indent);
+
+        if !fits && column > row_indent {
+            breaks.push(x);
+            (row_start, row_indent) = (x, indent);
+        }
+        x += char_width;
+    }
+
+    breaks
+}
+
+/// Returns the columns the continuation rows start after, no columns if a row would have none
+/// left for the text
+#[must_use]
+pub const fn effective_indent(width: usize, indent: usize) -> usize {
+    if indent < width { indent } else { 0 }
+}
+
+impl Buffer {
+    /// Returns the columns at which the continuation rows of the line start, see [`breaks`]
+    #[must_use]
+    pub fn wrap_breaks(&self, line: &str, width: usize, indent: usize) -> Vec<usize> {
+        let mut x = 0;
+        let cells: Vec<_> = line
+            .chars()
+            .map(|c| {
+                let width = self.char_len(c, x);
+                x += width;
+                (c, width)
+            })
+            .collect();
+        breaks(&cells, width, indent)
+    }
+
+    /// Moves the cursor `steps` rows down on the screen, or up if `down` is not set, when the lines
+    /// are wrapped at `width` columns and their continuation rows start after `indent` columns.
+    /// The cursor stays in the same screen column where the row is long enough
+    pub fn move_display_rows(&mut self, down: bool, steps: usize, width: usize, indent: usize) {
+        let indent = effective_indent(width, indent);
+        for _ in 0..steps {
+            if !self.move_display_row(down, width, indent) {
+                return;
+            }
+        }
+    }
+
+    /// Moves the cursor a row down or up, returning whether there was a row to move to
+    fn move_display_row(&mut self, down: bool, width: usize, indent: usize) -> bool {
+        let info = self.current_line_info();
+        let breaks = self.wrap_breaks(&info.contents, width, indent);
+        let x = self.cursor_column();
+        let row = breaks.partition_point(|&start| start <= x);
+        let row_start = |row: usize, breaks: &[usize]| match row {
+            0 => (0, 0),
+            row => (breaks[row - 1], indent),
+        };
+        let (start, row_indent) = row_start(row, &breaks);
+        let screen_column = x - start + row_indent;
+
+        let (info, breaks, row) = match (down, row) {
+            (true, row) if row < breaks.len() => (info, breaks, row + 1),
+            (false, row) if row > 0 => (info, breaks, row - 1),
+            (true, _) => {
+                let Some(next) = self.inner.line(info.line_number + 1) else {
+                    return false;
+                };
+                let breaks = self.wrap_breaks(&next.contents, width, indent);
+                (next, breaks, 0)
+            }
+            (false, _) => {
+                let Some(previous) = info
+                    .line_number
+                    .checked_sub(1)
+                    .and_then(|line| self.inner.line(line))
+                else {
+                    return false;
+                };
+                let breaks = self.wrap_breaks(&previous.contents, width, indent);
+                let row = breaks.len();
+                (previous, breaks, row)
+            }
+        };
+
+        let (start, row_indent) = row_start(row, &breaks);
+        let mut column = start + screen_column.saturating_sub(row_indent);
+        if let Some(&next_start) = breaks.get(row) {
+            column = column.min(next_start - 1);
+        }
+        self.cursor_offset = self.offset_at_column(&info, column);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(line: &str) -> Vec<(char, usize)> {
+        line.chars().map(|c| (c, 1)).collect()
+    }
+
+    #[test]
+    fn words() {
+        assert!(breaks(&cells("short line"), 10, 0).is_empty());
+        // "This is a " | "very long " | "line"
+        assert_eq!(breaks(&cells("This is a very long line"), 10, 0), [10, 20]);
+        // "one two " | "three"
+        assert_eq!(breaks(&cells("one two three"), 10, 0), [8]);
+        // "one two " | "> three"
+        assert_eq!(breaks(&cells("one two three"), 10, 2), [8]);
+        // Words longer than a row are broken where the row ends
+        assert_eq!(breaks(&cells("a abcdefghijkl"), 10, 0), [10]);
+        assert_eq!(breaks(&cells("abcdefghijkl"), 5, 2), [5, 8, 11]);
+        // An indent taking the whole row is not drawn
+        assert_eq!(breaks(&cells("abcdefghijkl"), 5, 5), [5, 10]);
+        // The spaces that do not fit start the next row
+        assert_eq!(breaks(&cells("abcde   f"), 5, 0), [5]);
+    }
+
+    #[test]
+    fn display_rows() {
+        let mut buffer = Buffer::new("one two three four\nfive");
+        buffer.cursor_offset = 1;
+
+        // "one two " | "three " | "four"
+        buffer.move_display_rows(true, 1, 8, 0);
+        assert_eq!(buffer.cursor_offset, 9);
+        buffer.move_display_rows(true, 1, 8, 0);
+        assert_eq!(buffer.cursor_offset, 15);
+        buffer.move_display_rows(true, 1, 8, 0);
+        assert_eq!(buffer.cursor_offset, 20);
+        buffer.move_display_rows(true, 1, 8, 0);
+        assert_eq!(buffer.cursor_offset, 20);
+
+        buffer.move_display_rows(false, 2, 8, 0);
+        assert_eq!(buffer.cursor_offset, 9);
+
+        // The cursor goes to the end of the row below when it is shorter than its column
+        buffer.cursor_offset = 13;
+        buffer.move_display_rows(true, 1, 8, 0);
+        assert_eq!(buffer.cursor_offset, 18);
+
+        // The indent of the continuation rows is skipped
+        // "one two " | "> three " | "> four"
+        buffer.cursor_offset = 0;
+        buffer.move_display_rows(true, 1, 8, 2);
+        assert_eq!(buffer.cursor_offset, 8);
+        buffer.cursor_offset = 10;
+        buffer.move_display_rows(false, 1, 8, 2);
+        assert_eq!(buffer.cursor_offset, 4);
+    }
+}
@@ -1,5 +1,7 @@
 //! All methods that mutate buffer's inner string
 
+use std::ops::Range;
+
 use super::Buffer;
 
 impl Buffer {
@@ -13,17 +15,27 @@ impl Buffer {
         self.apply_delete(self.cursor_offset)
     }
 
+    /// Replaces the characters in `range` with `s`, returning the removed ones. The cursor is
+    /// left after `s`
+    pub fn replace(&mut self, range: Range<usize>, s: &str) -> String {
+        let removed = self.inner.replace(range.clone(), s);
+        self.cursor_offset = range.start + s.chars().count();
+        self.desired_column = None;
+        removed
+    }
+
     fn apply_write(&mut self, position: usize, c: char) {
-        self.cursor_offset = position;
-        self.inner
-            .insert(self.cursor_offset, c.encode_utf8(&mut [0_u8; 4]));
-        self.cursor_offset += 1;
+        let mut cursor = self.inner.cursor(position);
+        cursor.insert_char(c);
+        self.cursor_offset = cursor.position();
+        self.desired_column = None;
     }
 
     fn apply_delete(&mut self, position: usize) -> Option<char> {
-        self.cursor_offset = position.checked_sub(1)?;
-        let deleted_char = self.inner.get(self.cursor_offset)?;
-        self.inner.delete(self.cursor_offset..=self.cursor_offset);
+        let mut cursor = self.inner.cursor(position);
+        let deleted_char = cursor.delete_char()?;
+        self.cursor_offset = cursor.position();
+        self.desired_column = None;
 
         Some(deleted_char)
     }
@@ -0,0 +1,72 @@
+//! Problems found in the text of a buffer, by a language server or a linter
+
+use std::ops::Range;
+
+/// How serious a problem is, the most serious one is the smallest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl Severity {
+    /// Returns the severity of the number the language server protocol gives it
+    #[must_use]
+    pub const fn from_lsp(n: i64) -> Self {
+        match n {
+            1 => Self::Error,
+            3 => Self::Info,
+            4 => Self::Hint,
+            _ => Self::Warning,
+        }
+    }
+
+    /// Returns the severity a linter names, such as `error` or `note`
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        let severity = match name.to_ascii_lowercase().as_str() {
+            "error" | "fatal error" => Self::Error,
+            "warning" => Self::Warning,
+            "info" | "note" => Self::Info,
+            "hint" | "help" | "style" => Self::Hint,
+            _ => return None,
+        };
+        Some(severity)
+    }
+
+    /// Returns the character the severity is marked with in the gutter
+    #[must_use]
+    pub const fn sign(self) -> char {
+        match self {
+            Self::Error => 'E',
+            Self::Warning => 'W',
+            Self::Info => 'I',
+            Self::Hint => 'H',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The characters the problem is about, at least one
+    pub range: Range<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severities() {
+        assert_eq!(Severity::from_lsp(1), Severity::Error);
+        assert_eq!(Severity::from_lsp(4), Severity::Hint);
+        assert_eq!(Severity::parse("Warning"), Some(Severity::Warning));
+        assert_eq!(Severity::parse("note"), Some(Severity::Info));
+        assert_eq!(Severity::parse("oops"), None);
+        assert!(Severity::Error < Severity::Warning);
+    }
+}
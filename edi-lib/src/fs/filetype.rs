@@ -11,6 +11,10 @@ pub static CPP: LazyLock<Filetype> = LazyLock::new(|| Filetype(Arc::from("cpp"))
 pub static GO: LazyLock<Filetype> = LazyLock::new(|| Filetype(Arc::from("go")));
 pub static RUST: LazyLock<Filetype> = LazyLock::new(|| Filetype(Arc::from("rust")));
 pub static MARKDOWN: LazyLock<Filetype> = LazyLock::new(|| Filetype(Arc::from("markdown")));
+pub static PYTHON: LazyLock<Filetype> = LazyLock::new(|| Filetype(Arc::from("python")));
+pub static SHELL: LazyLock<Filetype> = LazyLock::new(|| Filetype(Arc::from("sh")));
+pub static TOML: LazyLock<Filetype> = LazyLock::new(|| Filetype(Arc::from("toml")));
+pub static LUA: LazyLock<Filetype> = LazyLock::new(|| Filetype(Arc::from("lua")));
 
 /// A struct representing a filetype
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -29,6 +33,18 @@ impl Default for Filetype {
 }
 
 impl Filetype {
+    /// Returns the filetype of the given name, e.g. `rust`
+    #[must_use]
+    pub fn named(name: &str) -> Self {
+        Self(Arc::from(name))
+    }
+
+    /// Returns the name of the filetype, e.g. `rust`
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
     /// Extracts the filetype from an extension (with leading `.` removed), regardless if it's
     /// known or not
     #[must_use]
@@ -45,6 +61,10 @@ impl Filetype {
             "go" => &GO,
             "rs" => &RUST,
             "md" => &MARKDOWN,
+            "py" => &PYTHON,
+            "sh" | "bash" => &SHELL,
+            "toml" => &TOML,
+            "lua" => &LUA,
             _ => {
                 return None;
             }
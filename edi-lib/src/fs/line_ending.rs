@@ -0,0 +1,160 @@
+//! The line endings of files, which buffers keep as `\n` only
+
+use std::io::{self, Write};
+
+use edi_rope::{LineEnding, Rope};
+
+/// The names of the line endings as `fileformat` takes them
+pub const NAMES: [&str; 2] = ["unix", "dos"];
+
+#[must_use]
+pub fn parse(name: &str) -> Option<LineEnding> {
+    match name {
+        "unix" => Some(LineEnding::Lf),
+        "dos" => Some(LineEnding::CrLf),
+        _ => None,
+    }
+}
+
+#[must_use]
+pub const fn name(line_ending: LineEnding) -> &'static str {
+    match line_ending {
+        LineEnding::Lf => "unix",
+        LineEnding::CrLf => "dos",
+    }
+}
+
+/// Returns the line ending of the contents, which is `\r\n` only if every line of them ends with
+/// it. Unlike [`Rope::line_ending`], which goes by the first line break, a file that mixes them is
+/// taken to use `\n`, so its `\r`s are kept and written back as they were
+#[must_use]
+pub fn detect(contents: &Rope) -> LineEnding {
+    detect_bytes(contents.chunks().map(str::as_bytes))
+}
+
+fn detect_bytes<'a>(chunks: impl IntoIterator<Item = &'a [u8]>) -> LineEnding {
+    let mut previous = None;
+    let mut has_breaks = false;
+    for &b in chunks.into_iter().flatten() {
+        if b == b'\n' {
+            if previous != Some(b'\r') {
+                return LineEnding::Lf;
+            }
+            has_breaks = true;
+        }
+        previous = Some(b);
+    }
+
+    if has_breaks {
+        LineEnding::CrLf
+    } else {
+        LineEnding::Lf
+    }
+}
+
+/// Returns the contents of a file with its `\r\n` line breaks replaced by `\n`, and the line ending
+/// it was detected to have. The contents are only copied if every line ends with `\r\n`
+#[must_use]
+pub fn normalize(contents: Rope) -> (Rope, LineEnding) {
+    match detect(&contents) {
+        LineEnding::Lf => (contents, LineEnding::Lf),
+        LineEnding::CrLf => {
+            let text = contents.to_string().replace("\r\n", "\n");
+            (Rope::from(text.as_str()), LineEnding::CrLf)
+        }
+    }
+}
+
+/// Same as [`normalize`], for the bytes of a file
+#[must_use]
+pub fn normalize_bytes(mut bytes: Vec<u8>) -> (Vec<u8>, LineEnding) {
+    if detect_bytes([&bytes[..]]) == LineEnding::Lf {
+        return (bytes, LineEnding::Lf);
+    }
+
+    let mut kept = 0;
+    for idx in 0..bytes.len() {
+        if bytes[idx] != b'\r' || bytes.get(idx + 1) != Some(&b'\n') {
+            bytes[kept] = bytes[idx];
+            kept += 1;
+        }
+    }
+    bytes.truncate(kept);
+    (bytes, LineEnding::CrLf)
+}
+
+/// Writes the contents with their `\n` line breaks replaced by `line_ending`
+///
+/// # Errors
+///
+/// Returns the error of the writer
+pub fn write(contents: &Rope, line_ending: LineEnding, mut w: impl Write) -> io::Result<()> {
+    if line_ending == LineEnding::Lf {
+        return contents.write_to(w).map(|_| ());
+    }
+    for chunk in contents.chunks() {
+        let mut lines = chunk.split('\n');
+        if let Some(first) = lines.next() {
+            w.write_all(first.as_bytes())?;
+        }
+        for line in lines {
+            w.write_all(line_ending.as_str().as_bytes())?;
+            w.write_all(line.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detection() {
+        let detect = |text| detect(&Rope::from_str_chunked(text, 1));
+        assert_eq!(detect("a\r\nb\r\n"), LineEnding::CrLf);
+        assert_eq!(detect("a\r\nb"), LineEnding::CrLf);
+        assert_eq!(detect("a\nb\r\n"), LineEnding::Lf);
+        assert_eq!(detect("a\r\nb\n"), LineEnding::Lf);
+        assert_eq!(detect("a\r"), LineEnding::Lf);
+        assert_eq!(detect(""), LineEnding::Lf);
+
+        // A file that mixes the line endings is kept as it is
+        let (contents, line_ending) = normalize(Rope::from("a\r\nb\n"));
+        assert_eq!(line_ending, LineEnding::Lf);
+        assert_eq!(contents, "a\r\nb\n");
+        let (bytes, line_ending) = normalize_bytes(b"a\r\nb\n\xff".to_vec());
+        assert_eq!(
+            (&bytes[..], line_ending),
+            (&b"a\r\nb\n\xff"[..], LineEnding::Lf)
+        );
+    }
+
+    #[test]
+    fn normalize_and_write() {
+        let (contents, line_ending) = normalize(Rope::from("a\r\nb\r\n\r\nc"));
+        assert_eq!(line_ending, LineEnding::CrLf);
+        assert_eq!(contents, "a\nb\n\nc");
+        let (contents, line_ending) = normalize(Rope::from("a\nb\r\n"));
+        assert_eq!(line_ending, LineEnding::Lf);
+        assert_eq!(contents, "a\nb\r\n");
+        let (bytes, line_ending) = normalize_bytes(b"a\r\n\r\rb\r\n\xff\r\n".to_vec());
+        assert_eq!(line_ending, LineEnding::CrLf);
+        assert_eq!(bytes, b"a\n\r\rb\n\xff\n");
+        let (bytes, line_ending) = normalize_bytes(b"a\nb\r\n".to_vec());
+        assert_eq!(
+            (&bytes[..], line_ending),
+            (&b"a\nb\r\n"[..], LineEnding::Lf)
+        );
+
+        let contents = Rope::from("a\nb\n\nc");
+        let mut written = Vec::new();
+        write(&contents, LineEnding::CrLf, &mut written).unwrap();
+        assert_eq!(written, b"a\r\nb\r\n\r\nc");
+
+        written.clear();
+        write(&contents, LineEnding::Lf, &mut written).unwrap();
+        assert_eq!(written, b"a\nb\n\nc");
+        assert_eq!(parse(name(LineEnding::CrLf)), Some(LineEnding::CrLf));
+    }
+}
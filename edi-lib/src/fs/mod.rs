@@ -1 +1,4 @@
 pub mod filetype;
+pub mod line_ending;
+pub mod read;
+pub mod save;
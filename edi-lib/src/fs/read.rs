@@ -0,0 +1,177 @@
+//! Reading files into buffers, whatever bytes they have
+
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::Path,
+};
+
+use edi_rope::{LineEnding, Rope};
+
+use super::line_ending;
+use crate::string::highlight::Edit;
+
+/// The contents of a file as a buffer holds them
+#[derive(Debug)]
+pub struct Contents {
+    /// The text of the file, with `\n` line endings and invalid utf-8 replaced by U+FFFD
+    pub text: Rope,
+    pub line_ending: LineEnding,
+    pub invalid: InvalidBytes,
+}
+
+/// Reads the file at `path`. Sequences of bytes that are not valid utf-8 are replaced by U+FFFD,
+/// and are kept in [`InvalidBytes`] to be written back as they were
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read
+pub fn read(path: &Path) -> io::Result<Contents> {
+    match Rope::from_reader(File::open(path)?) {
+        Ok(text) => {
+            let (text, line_ending) = line_ending::normalize(text);
+            Ok(Contents {
+                text,
+                line_ending,
+                invalid: InvalidBytes::default(),
+            })
+        }
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+            let (bytes, line_ending) = line_ending::normalize_bytes(fs::read(path)?);
+            let (text, invalid) = InvalidBytes::decode(&bytes);
+            Ok(Contents {
+                text: Rope::from(text.as_str()),
+                line_ending,
+                invalid,
+            })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// The bytes that were not valid utf-8 when a file was read, each of them is a U+FFFD in the text
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InvalidBytes {
+    /// The character offset of each replacement character with the bytes it replaced, sorted
+    spans: Vec<(usize, Box<[u8]>)>,
+}
+
+impl InvalidBytes {
+    /// Decodes the bytes as utf-8, replacing each invalid sequence with U+FFFD as
+    /// [`String::from_utf8_lossy`] does
+    #[must_use]
+    pub fn decode(bytes: &[u8]) -> (String, Self) {
+        let mut text = String::with_capacity(bytes.len());
+        let mut spans = Vec::new();
+        let mut offset = 0;
+        for chunk in bytes.utf8_chunks() {
+            text.push_str(chunk.valid());
+            offset += chunk.valid().chars().count();
+            if !chunk.invalid().is_empty() {
+                text.push(char::REPLACEMENT_CHARACTER);
+                spans.push((offset, chunk.invalid().into()));
+                offset += 1;
+            }
+        }
+        (text, Self { spans })
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Returns the number of the invalid sequences
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Moves the invalid sequences after the edit by the number of characters it inserted and
+    /// removed, and drops the ones it removed
+    pub fn apply(&mut self, edit: &Edit) {
+        let removed_end = edit.offset + edit.removed;
+        self.spans
+            .retain(|&(offset, _)| offset < edit.offset || offset >= removed_end);
+        for (offset, _) in &mut self.spans {
+            if *offset >= removed_end {
+                *offset = *offset + edit.inserted - edit.removed;
+            }
+        }
+    }
+
+    /// Writes the contents with their `\n` line endings replaced by `line_ending` and the
+    /// replacement characters of the invalid sequences replaced by their bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the writer
+    pub fn write(
+        &self,
+        contents: &Rope,
+        line_ending: LineEnding,
+        mut w: impl Write,
+    ) -> io::Result<()> {
+        if self.is_empty() {
+            return line_ending::write(contents, line_ending, w);
+        }
+
+        let mut spans = self.spans.iter().peekable();
+        let mut buf = [0; 4];
+        for (offset, c) in contents.chars().enumerate() {
+            let span = spans.next_if(|&&(span_offset, _)| span_offset == offset);
+            match (c, span) {
+                (char::REPLACEMENT_CHARACTER, Some((_, bytes))) => w.write_all(bytes)?,
+                ('\n', _) => w.write_all(line_ending.as_str().as_bytes())?,
+                (c, _) => w.write_all(c.encode_utf8(&mut buf).as_bytes())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let bytes = b"a\xffb\n\xd0\xd0\x97\xe2\x82";
+        let (text, invalid) = InvalidBytes::decode(bytes);
+        assert_eq!(text, String::from_utf8_lossy(bytes));
+        assert_eq!(invalid.len(), 3);
+
+        let mut written = Vec::new();
+        let contents = Rope::from(text.as_str());
+        invalid
+            .write(&contents, LineEnding::Lf, &mut written)
+            .unwrap();
+        assert_eq!(written, bytes);
+
+        written.clear();
+        invalid
+            .write(&contents, LineEnding::CrLf, &mut written)
+            .unwrap();
+        assert_eq!(written, b"a\xffb\r\n\xd0\xd0\x97\xe2\x82");
+    }
+
+    #[test]
+    fn edits() {
+        // "a�b\n�З�"
+        let (text, mut invalid) = InvalidBytes::decode(b"a\xffb\n\xd0\xd0\x97\xe2\x82");
+        let mut contents = Rope::from(text.as_str());
+
+        contents.insert(0, "xy");
+        invalid.apply(&Edit::insert(0, 2));
+        // The second replacement character is removed
+        contents.delete(6..7);
+        invalid.apply(&Edit::remove(6, 1));
+        assert_eq!(contents.to_string(), "xya\u{fffd}b\n\u{0417}\u{fffd}");
+
+        let mut written = Vec::new();
+        invalid
+            .write(&contents, LineEnding::Lf, &mut written)
+            .unwrap();
+        assert_eq!(written, b"xya\xffb\n\xd0\x97\xe2\x82");
+    }
+}
@@ -0,0 +1,346 @@
+//! Writing buffer contents back to files
+
+use std::{
+    fs::{self, File, Metadata, OpenOptions},
+    io::{self, BufWriter, Write},
+    os::unix::fs::{MetadataExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+};
+
+use edi_rope::{LineEnding, Rope};
+
+use super::{line_ending, read::InvalidBytes};
+
+/// The permissions of a new file, before the umask is applied
+const DEFAULT_MODE: u32 = 0o666;
+/// How many names are tried for the temporary file before giving up
+const TMP_ATTEMPTS: usize = 100;
+
+/// How the contents are written to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveOptions<'a> {
+    /// Whether the file should end with a newline, one is added if the contents do not end with it
+    pub trailing_newline: bool,
+    /// Whether the previous contents of the file are kept in a file with `~` appended to its name
+    pub backup: bool,
+    /// The characters the lines are ended with in the file
+    pub line_ending: LineEnding,
+    /// The bytes the replacement characters of invalid utf-8 are written as
+    pub invalid_bytes: Option<&'a InvalidBytes>,
+}
+
+impl Default for SaveOptions<'_> {
+    fn default() -> Self {
+        Self {
+            trailing_newline: true,
+            backup: false,
+            line_ending: LineEnding::Lf,
+            invalid_bytes: None,
+        }
+    }
+}
+
+impl<'a> SaveOptions<'a> {
+    #[must_use]
+    pub const fn with_trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_backup(mut self, backup: bool) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_invalid_bytes(mut self, invalid_bytes: &'a InvalidBytes) -> Self {
+        self.invalid_bytes = Some(invalid_bytes);
+        self
+    }
+}
+
+/// Returns whether the contents end with a newline, an empty file is treated as if it did
+#[must_use]
+pub fn ends_with_newline(contents: &Rope) -> bool {
+    contents.is_empty() || contents.get(contents.len() - 1) == Some('\n')
+}
+
+/// Writes `contents` to the file at `path`. If `path` is a symlink, the file it points to is
+/// written instead
+///
+/// The contents are written to a temporary file next to the target, which then replaces it, so the
+/// file is never left half-written. The permissions and the ownership of the file are kept. Files
+/// with several hard links are written in place, as replacing them would split them off
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written. The file is left as it was in that case, unless
+/// it is written in place
+pub fn save(contents: &Rope, path: &Path, options: SaveOptions) -> io::Result<()> {
+    let target = resolve(path)?;
+    let metadata = match fs::metadata(&target) {
+        Ok(metadata) => Some(metadata),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    if options.backup && metadata.is_some() {
+        fs::copy(&target, backup_path(&target))?;
+    }
+
+    let add_newline = options.trailing_newline && !ends_with_newline(contents);
+    if metadata
+        .as_ref()
+        .is_some_and(|metadata| metadata.nlink() > 1)
+    {
+        let file = OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&target)?;
+        return write_contents(file, contents, add_newline, &options);
+    }
+
+    let mode = metadata
+        .as_ref()
+        .map_or(DEFAULT_MODE, |metadata| metadata.mode() & 0o7777);
+    let (file, tmp_path) = create_tmp(&target, mode)?;
+    let result = write_contents(file, contents, add_newline, &options)
+        .and_then(|()| metadata.map_or(Ok(()), |metadata| copy_metadata(&metadata, &tmp_path)))
+        .and_then(|()| fs::rename(&tmp_path, &target));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Returns whether the file at `path` can be opened for writing, a read-only file or a directory
+/// cannot
+#[must_use]
+pub fn is_writable(path: &Path) -> bool {
+    OpenOptions::new().write(true).open(path).is_ok()
+}
+
+/// Follows the symlinks in `path`, a path that does not exist yet is returned as it is
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    match fs::canonicalize(path) {
+        Ok(target) => Ok(target),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(path.to_path_buf()),
+        Err(e) => Err(e),
+    }
+}
+
+fn write_contents(
+    file: File,
+    contents: &Rope,
+    add_newline: bool,
+    options: &SaveOptions,
+) -> io::Result<()> {
+    let mut w = BufWriter::new(file);
+    match options.invalid_bytes {
+        Some(invalid_bytes) => invalid_bytes.write(contents, options.line_ending, &mut w)?,
+        None => line_ending::write(contents, options.line_ending, &mut w)?,
+    }
+    if add_newline {
+        w.write_all(options.line_ending.as_str().as_bytes())?;
+    }
+
+    w.into_inner()
+        .map_err(io::IntoInnerError::into_error)?
+        .sync_all()
+}
+
+/// Gives the file at `path` the permissions and the owner described by `metadata`
+fn copy_metadata(metadata: &Metadata, path: &Path) -> io::Result<()> {
+    fs::set_permissions(path, metadata.permissions())?;
+
+    let current = fs::metadata(path)?;
+    if (current.uid(), current.gid()) == (metadata.uid(), metadata.gid()) {
+        return Ok(());
+    }
+    // Only the superuser can give files away, others can at most change the group
+    match std::os::unix::fs::chown(path, Some(metadata.uid()), Some(metadata.gid())) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            std::os::unix::fs::chown(path, None, Some(metadata.gid())).or(Ok(()))
+        }
+        result => result,
+    }
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push("~");
+    PathBuf::from(name)
+}
+
+/// Creates a hidden file next to `path` for the contents to be written to, with the permissions
+/// `mode` allows from the start. The file is always a new one, an existing file or symlink with
+/// its name is never written through
+fn create_tmp(path: &Path, mode: u32) -> io::Result<(File, PathBuf)> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut attempt = 0;
+    loop {
+        let tmp_path = path.with_file_name(format!(".{name}.{}-{attempt}.tmp", std::process::id()));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(mode)
+            .open(&tmp_path)
+        {
+            Ok(file) => return Ok((file, tmp_path)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < TMP_ATTEMPTS => {
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+    use crate::fs::read;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("edi-save-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn trailing_newline() {
+        let dir = temp_dir("newline");
+        let path = dir.join("file");
+
+        save(&Rope::from("a\nb"), &path, SaveOptions::default()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb\n");
+
+        let options = SaveOptions::default().with_trailing_newline(false);
+        save(&Rope::from("a\nb"), &path, options).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\nb");
+
+        save(&Rope::from(""), &path, SaveOptions::default()).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        assert!(ends_with_newline(&Rope::from("")));
+        assert!(!ends_with_newline(&Rope::from("a")));
+
+        let options = SaveOptions::default().with_line_ending(LineEnding::CrLf);
+        save(&Rope::from("a\nb"), &path, options).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "a\r\nb\r\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let dir = temp_dir("invalid");
+        let path = dir.join("file");
+        let bytes = b"caf\xe9\r\n\xff\r\n";
+        fs::write(&path, bytes).unwrap();
+
+        let contents = read::read(&path).unwrap();
+        assert_eq!(contents.text, "caf\u{fffd}\n\u{fffd}\n");
+        let options = SaveOptions::default()
+            .with_line_ending(contents.line_ending)
+            .with_invalid_bytes(&contents.invalid);
+        save(&contents.text, &path, options).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writable() {
+        let dir = temp_dir("writable");
+        let path = dir.join("file");
+        assert!(!is_writable(&path));
+        fs::write(&path, "text\n").unwrap();
+        assert!(is_writable(&path));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "text\n");
+        assert!(!is_writable(&dir));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_metadata() {
+        let dir = temp_dir("metadata");
+        let path = dir.join("script.sh");
+        fs::write(&path, "old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+
+        let link = dir.join("link");
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+        let hard_link = dir.join("hard_link");
+        fs::hard_link(&path, &hard_link).unwrap();
+
+        save(&Rope::from("new\n"), &link, SaveOptions::default()).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        assert_eq!(fs::read_to_string(&hard_link).unwrap(), "new\n");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+
+        fs::remove_file(&hard_link).unwrap();
+        save(&Rope::from("newer\n"), &path, SaveOptions::default()).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+        // No temporary file is left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tmp_file() {
+        let dir = temp_dir("tmp");
+        let path = dir.join("secret");
+        fs::write(&path, "old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+        let (_, tmp_path) = create_tmp(&path, 0o600).unwrap();
+        let mode = fs::metadata(&tmp_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // The name is taken, so another one is used
+        let (_, other) = create_tmp(&path, 0o600).unwrap();
+        assert_ne!(tmp_path, other);
+        fs::remove_file(&other).unwrap();
+
+        // A symlink where the temporary file would go is not written through
+        let victim = dir.join("victim");
+        fs::write(&victim, "victim\n").unwrap();
+        fs::remove_file(&tmp_path).unwrap();
+        std::os::unix::fs::symlink(&victim, &tmp_path).unwrap();
+        save(&Rope::from("new\n"), &path, SaveOptions::default()).unwrap();
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "victim\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn backup() {
+        let dir = temp_dir("backup");
+        let path = dir.join("file");
+
+        let options = SaveOptions::default().with_backup(true);
+        save(&Rope::from("first\n"), &path, options).unwrap();
+        assert!(!backup_path(&path).exists());
+
+        save(&Rope::from("second\n"), &path, options).unwrap();
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "first\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -18,35 +18,35 @@ mod tests {
 
     #[test]
     fn zero() {
-        let mut buf = [0u8; 20];
+        let mut buf = [0_u8; 20];
         let s = itoa_into(0, &mut buf);
         assert_eq!(s, b"0");
     }
 
     #[test]
     fn single_digit() {
-        let mut buf = [0u8; 20];
+        let mut buf = [0_u8; 20];
         let s = itoa_into(7, &mut buf);
         assert_eq!(s, b"7");
     }
 
     #[test]
     fn multiple_digits() {
-        let mut buf = [0u8; 20];
+        let mut buf = [0_u8; 20];
         let s = itoa_into(42, &mut buf);
         assert_eq!(s, b"42");
     }
 
     #[test]
     fn large_number() {
-        let mut buf = [0u8; 20];
-        let s = itoa_into(1234567890, &mut buf);
+        let mut buf = [0_u8; 20];
+        let s = itoa_into(1_234_567_890, &mut buf);
         assert_eq!(s, b"1234567890");
     }
 
     #[test]
     fn max_u64() {
-        let mut buf = [0u8; 20];
+        let mut buf = [0_u8; 20];
         let s = itoa_into(u64::MAX, &mut buf);
         assert_eq!(s, b"18446744073709551615");
     }
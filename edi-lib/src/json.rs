@@ -0,0 +1,445 @@
+//! A parser and a writer of JSON, as spoken by language servers
+//!
+//! Numbers are kept as `f64`, which holds every integer the protocol uses exactly
+
+use std::{collections::BTreeMap, fmt, iter::Peekable, str::Chars};
+
+/// Keys of an object and their values
+pub type Object = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Object),
+}
+
+impl Value {
+    /// Returns the value of `key` if the value is an object that has it
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Object(object) => object.get(key),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if it is a whole one
+    #[must_use]
+    #[expect(
+        clippy::cast_possible_truncation,
+        reason = "the number is whole and checked to be in range"
+    )]
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Self::Number(n) if n.fract() == 0.0 && n.abs() < 2_f64.powi(53) => Some(n as i64),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if it is a whole one that is not negative
+    #[must_use]
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_i64().and_then(|n| usize::try_from(n).ok())
+    }
+
+    #[must_use]
+    pub fn as_array(&self) -> Option<&[Self]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(String::from(value))
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
+    }
+}
+
+impl From<usize> for Value {
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "the protocol does not use numbers above 2^53"
+    )]
+    fn from(value: usize) -> Self {
+        Self::Number(value as f64)
+    }
+}
+
+impl From<i64> for Value {
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "the protocol does not use numbers above 2^53"
+    )]
+    fn from(value: i64) -> Self {
+        Self::Number(value as f64)
+    }
+}
+
+impl<const N: usize> From<[(&str, Value); N]> for Value {
+    fn from(entries: [(&str, Value); N]) -> Self {
+        Self::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (String::from(key), value))
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Boolean(b) => write!(f, "{b}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::String(s) => write_string(f, s),
+            Self::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_str("]")
+            }
+            Self::Object(object) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in object.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", u32::from(c))?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// A syntax error in a document, `offset` is the number of characters before it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Parses a document made of a single value
+///
+/// # Errors
+///
+/// Returns an error if the document is not valid JSON
+pub fn parse(s: &str) -> Result<Value, Error> {
+    let mut parser = Parser {
+        chars: s.chars().peekable(),
+        offset: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.chars.peek().is_some() {
+        return Err(parser.error("expected the end of the document"));
+    }
+    Ok(value)
+}
+
+/// How deeply objects and arrays may be nested, so that parsing does not overflow the stack
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    offset: usize,
+    /// How many objects and arrays the parsed value is in
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.offset += 1;
+        Some(c)
+    }
+
+    /// Consumes the next character if it is `c`
+    fn eat(&mut self, c: char) -> bool {
+        let eaten = self.chars.next_if_eq(&c).is_some();
+        if eaten {
+            self.offset += 1;
+        }
+        eaten
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('{' | '[') if self.depth == MAX_DEPTH => Err(self.error("too deeply nested")),
+            Some(&c @ ('{' | '[')) => {
+                self.depth += 1;
+                let value = if c == '{' {
+                    self.object()
+                } else {
+                    self.array()
+                };
+                self.depth -= 1;
+                value
+            }
+            Some('"') => self.string().map(Value::String),
+            Some('t') => self.word("true", Value::Boolean(true)),
+            Some('f') => self.word("false", Value::Boolean(false)),
+            Some('n') => self.word("null", Value::Null),
+            Some(c) if c.is_ascii_digit() || *c == '-' => self.number(),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, Error> {
+        self.next();
+        let mut object = Object::new();
+        self.skip_whitespace();
+        if self.eat('}') {
+            return Ok(Value::Object(object));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek() != Some(&'"') {
+                return Err(self.error("expected a key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(':') {
+                return Err(self.error("expected `:`"));
+            }
+            object.insert(key, self.value()?);
+
+            self.skip_whitespace();
+            if self.eat('}') {
+                return Ok(Value::Object(object));
+            }
+            if !self.eat(',') {
+                return Err(self.error("expected `,` or `}` in an object"));
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, Error> {
+        self.next();
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.eat(']') {
+            return Ok(Value::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            if self.eat(']') {
+                return Ok(Value::Array(values));
+            }
+            if !self.eat(',') {
+                return Err(self.error("expected `,` or `]` in an array"));
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.next();
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let escaped = match self.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some(c @ ('"' | '\\' | '/')) => c,
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    s.push(escaped);
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// Parses the digits of a `\u` escape, and the escape of the low surrogate that follows a
+    /// high one
+    fn unicode_escape(&mut self) -> Result<char, Error> {
+        let Some(high) = self.code_unit() else {
+            return Err(self.error("invalid unicode escape"));
+        };
+
+        let mut units = vec![high];
+        if (0xd800..0xdc00).contains(&high) && self.eat('\\') && self.eat('u') {
+            units.extend(self.code_unit());
+        }
+        char::decode_utf16(units)
+            .next()
+            .and_then(Result::ok)
+            .ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    /// Parses the four hexadecimal digits of a UTF-16 code unit
+    fn code_unit(&mut self) -> Option<u16> {
+        let hex: String = (0..4).filter_map(|_| self.next()).collect();
+        u16::from_str_radix(&hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 4)
+    }
+
+    fn number(&mut self) -> Result<Value, Error> {
+        let mut number = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+            number.push(c);
+            self.next();
+        }
+        number
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| self.error(&format!("invalid number `{number}`")))
+    }
+
+    fn word(&mut self, word: &str, value: Value) -> Result<Value, Error> {
+        for expected in word.chars() {
+            if self.next() != Some(expected) {
+                return Err(self.error("expected a value"));
+            }
+        }
+        Ok(value)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(char::is_ascii_whitespace).is_some() {
+            self.offset += 1;
+        }
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error {
+            offset: self.offset,
+            message: String::from(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values() {
+        let value = parse(
+            r#" {"id": 1, "ok": true, "none": null, "list": [-1.5e2, "a\"\né😀"],
+                "nested": {}} "#,
+        )
+        .unwrap();
+
+        assert_eq!(value.get("id").and_then(Value::as_i64), Some(1));
+        assert_eq!(value.get("ok"), Some(&Value::Boolean(true)));
+        assert_eq!(value.get("none"), Some(&Value::Null));
+        assert_eq!(
+            value.get("list").and_then(Value::as_array),
+            Some(&[Value::Number(-150.0), Value::from("a\"\né😀")][..])
+        );
+        assert_eq!(value.get("nested"), Some(&Value::Object(Object::new())));
+        assert_eq!(value.get("missing"), None);
+    }
+
+    #[test]
+    fn writing() {
+        let value = Value::from([
+            ("text", Value::from("a \"b\"\n\u{1}")),
+            ("n", Value::from(3_usize)),
+            ("list", Value::Array(vec![Value::Null, Value::from(false)])),
+        ]);
+        let written = value.to_string();
+        assert_eq!(
+            written,
+            r#"{"list":[null,false],"n":3,"text":"a \"b\"\n\u0001"}"#
+        );
+        assert_eq!(parse(&written), Ok(value));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |s| parse(s).unwrap_err().to_string();
+        assert_eq!(error("{\"a\" 1}"), "at 5: expected `:`");
+        assert_eq!(error("[1 2]"), "at 3: expected `,` or `]` in an array");
+        assert_eq!(error("\"abc"), "at 4: unterminated string");
+        assert_eq!(error("tru"), "at 3: expected a value");
+        assert_eq!(error("1 1"), "at 2: expected the end of the document");
+        assert_eq!(error("{1: 2}"), "at 1: expected a key");
+    }
+
+    #[test]
+    fn nesting() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            parse(&nested(MAX_DEPTH + 1)).unwrap_err().to_string(),
+            format!("at {MAX_DEPTH}: too deeply nested")
+        );
+
+        let deep = format!("{}1", r#"{"a":"#.repeat(100_000));
+        assert_eq!(parse(&deep).unwrap_err().message, "too deeply nested");
+    }
+}
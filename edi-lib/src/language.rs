@@ -0,0 +1,344 @@
+//! Definitions of how the languages are written, as far as highlighting and commenting go
+//!
+//! A language is defined in a TOML file named after its filetype, e.g. `python.toml`:
+//!
+//! ```toml
+//! extensions = ["py"]
+//! line_comment = "#"
+//! block_comment = ["/*", "*/"]
+//! string_quotes = ['"', "'"]
+//! char_quote = "'"
+//! keywords = ["def", "return"]
+//! types = ["int", "str"]
+//! language_server = ["pylsp", "--check-parent-process"]
+//! linter = ["pyflakes"]
+//! ```
+//!
+//! Every key may be left out. The definitions of C, C++, Go, Rust, Python, shell, TOML and Lua
+//! are built in, a file of the same name replaces them
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsStr,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    fs::filetype::Filetype,
+    string::highlight::Type,
+    toml::{self, Table, Value},
+};
+
+/// The languages built in, named after their filetype
+const BUILTIN: [(&str, &str); 8] = [
+    ("c", include_str!("../languages/c.toml")),
+    ("cpp", include_str!("../languages/cpp.toml")),
+    ("go", include_str!("../languages/go.toml")),
+    ("lua", include_str!("../languages/lua.toml")),
+    ("python", include_str!("../languages/python.toml")),
+    ("rust", include_str!("../languages/rust.toml")),
+    ("sh", include_str!("../languages/sh.toml")),
+    ("toml", include_str!("../languages/toml.toml")),
+];
+
+/// How the code of a filetype is written
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Language {
+    pub filetype: Filetype,
+    /// The extensions of the files of the language, without the leading `.`
+    pub extensions: Vec<String>,
+    /// The words highlighted wherever they appear in the code, and their type
+    pub keywords: HashMap<String, Type>,
+    /// The prefix that makes the rest of a line a comment
+    pub line_comment: Option<String>,
+    /// What a comment that may span several lines opens and closes with
+    pub block_comment: Option<(String, String)>,
+    /// The characters string literals are quoted with, they may span several lines
+    pub string_quotes: Vec<char>,
+    /// The character a single character literal is quoted with
+    pub char_quote: Option<char>,
+    /// The command that starts the language server of the language and its arguments, empty if
+    /// there is none
+    pub language_server: Vec<String>,
+    /// The command that checks a file of the language, which is given as the last argument, and
+    /// its arguments. Empty if there is none
+    pub linter: Vec<String>,
+}
+
+impl Language {
+    /// Returns a language of the filetype that has no syntax at all
+    #[must_use]
+    pub fn plain(filetype: Filetype) -> Self {
+        Self {
+            filetype,
+            ..Self::default()
+        }
+    }
+
+    /// Parses the definition of the language of the `filetype`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the definition is not valid TOML or has a key of the wrong type or an
+    /// unknown one
+    pub fn parse(filetype: Filetype, definition: &str) -> Result<Self, String> {
+        let table = toml::parse(definition).map_err(|e| e.to_string())?;
+        let mut language = Self::plain(filetype);
+        language.apply(&table)?;
+        Ok(language)
+    }
+
+    fn apply(&mut self, table: &Table) -> Result<(), String> {
+        for (name, value) in table {
+            match name.as_str() {
+                "extensions" => self.extensions = strings(name, value)?,
+                "keywords" | "types" => {
+                    let ty = if name == "types" {
+                        Type::Type
+                    } else {
+                        Type::Keyword
+                    };
+                    let words = strings(name, value)?;
+                    self.keywords
+                        .extend(words.into_iter().map(|word| (word, ty)));
+                }
+                "line_comment" => self.line_comment = Some(string(name, value)?),
+                "block_comment" => {
+                    let [open, close] = <[String; 2]>::try_from(strings(name, value)?)
+                        .map_err(|_| format!("`{name}` is not a pair of strings"))?;
+                    self.block_comment = Some((open, close));
+                }
+                "string_quotes" => {
+                    self.string_quotes = strings(name, value)?
+                        .iter()
+                        .map(|quote| character(name, quote))
+                        .collect::<Result<_, _>>()?;
+                }
+                "char_quote" => self.char_quote = Some(character(name, &string(name, value)?)?),
+                "language_server" => self.language_server = strings(name, value)?,
+                "linter" => self.linter = strings(name, value)?,
+                _ => return Err(format!("unknown key `{name}`")),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn string(name: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        _ => Err(format!("`{name}` is not a string")),
+    }
+}
+
+fn strings(name: &str, value: &Value) -> Result<Vec<String>, String> {
+    let not_strings = || format!("`{name}` is not an array of strings");
+    let Value::Array(values) = value else {
+        return Err(not_strings());
+    };
+    values
+        .iter()
+        .map(|value| string(name, value).map_err(|_| not_strings()))
+        .collect()
+}
+
+fn character(name: &str, s: &str) -> Result<char, String> {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .filter(|_| chars.next().is_none())
+        .ok_or_else(|| format!("`{name}` has `{s}`, which is not a single character"))
+}
+
+/// An error in a file of a language definitions directory
+#[derive(Debug)]
+pub enum Error {
+    /// The file or the directory could not be read
+    Io(PathBuf, std::io::Error),
+    /// The file is not a valid definition
+    Invalid(PathBuf, String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "unable to read `{}`: {e}", path.display()),
+            Self::Invalid(path, message) => {
+                write!(f, "invalid language `{}`: {message}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, e) => Some(e),
+            Self::Invalid(..) => None,
+        }
+    }
+}
+
+/// The known languages, by filetype
+#[derive(Debug, Clone)]
+pub struct Registry {
+    languages: BTreeMap<Filetype, Arc<Language>>,
+}
+
+impl Default for Registry {
+    /// Returns a registry of the languages built in
+    fn default() -> Self {
+        let languages = BUILTIN
+            .into_iter()
+            .map(|(name, definition)| {
+                let filetype = Filetype::named(name);
+                let language = Language::parse(filetype.clone(), definition)
+                    .expect("the built-in languages are valid");
+                (filetype, Arc::new(language))
+            })
+            .collect();
+        Self { languages }
+    }
+}
+
+impl Registry {
+    /// Adds the language, replacing the one of the same filetype
+    pub fn add(&mut self, language: Language) {
+        self.languages
+            .insert(language.filetype.clone(), Arc::new(language));
+    }
+
+    /// Adds the languages defined by the `*.toml` files in `dir`, each of them named after the
+    /// filetype. Nothing is added if the directory does not exist
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or one of the files can not be read, or if a file is not
+    /// a valid definition
+    pub fn load_dir(&mut self, dir: &Path) -> Result<(), Error> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(Error::Io(dir.to_path_buf(), e)),
+        };
+
+        let mut paths = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| Error::Io(dir.to_path_buf(), e))?.path();
+            if path.extension() == Some(OsStr::new("toml")) {
+                paths.push(path);
+            }
+        }
+        // Read in the same order every time, so the same extension always ends up with the same
+        // language
+        paths.sort();
+
+        for path in paths {
+            let Some(name) = path.file_stem().and_then(OsStr::to_str) else {
+                continue;
+            };
+            let definition =
+                std::fs::read_to_string(&path).map_err(|e| Error::Io(path.clone(), e))?;
+            let language = Language::parse(Filetype::named(name), &definition)
+                .map_err(|message| Error::Invalid(path.clone(), message))?;
+            self.add(language);
+        }
+        Ok(())
+    }
+
+    /// Returns the language of the `filetype`, one without any syntax if it is not known
+    #[must_use]
+    pub fn language(&self, filetype: &Filetype) -> Arc<Language> {
+        self.languages
+            .get(filetype)
+            .map_or_else(|| Arc::new(Language::plain(filetype.clone())), Arc::clone)
+    }
+
+    /// Returns the filetype of the file at `path`, that of the language with its extension or
+    /// the one the extension maps to otherwise
+    #[must_use]
+    pub fn filetype(&self, path: &Path) -> Filetype {
+        let ext = path.extension().and_then(OsStr::to_str);
+        self.languages
+            .values()
+            .find(|language| ext.is_some_and(|ext| language.extensions.iter().any(|e| e == ext)))
+            .map_or_else(
+                || Filetype::from(path),
+                |language| language.filetype.clone(),
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::filetype;
+
+    #[test]
+    fn definitions() {
+        let language = Language::parse(
+            Filetype::named("js"),
+            "extensions = [\"js\", \"mjs\"]\nline_comment = \"//\"\n\
+             block_comment = [\"/*\", \"*/\"]\nstring_quotes = ['\"', \"'\", '`']\n\
+             keywords = [\"let\"]\ntypes = [\"Number\"]\n\
+             language_server = [\"tsserver\", \"--stdio\"]\nlinter = [\"eslint\", \"-f\", \"unix\"]\n",
+        )
+        .unwrap();
+        assert_eq!(language.extensions, ["js", "mjs"]);
+        assert_eq!(language.line_comment.as_deref(), Some("//"));
+        assert_eq!(
+            language.block_comment,
+            Some((String::from("/*"), String::from("*/")))
+        );
+        assert_eq!(language.string_quotes, ['"', '\'', '`']);
+        assert_eq!(language.char_quote, None);
+        assert_eq!(language.keywords.get("let"), Some(&Type::Keyword));
+        assert_eq!(language.keywords.get("Number"), Some(&Type::Type));
+        assert_eq!(language.language_server, ["tsserver", "--stdio"]);
+        assert_eq!(language.linter, ["eslint", "-f", "unix"]);
+
+        let error = |definition| Language::parse(Filetype::default(), definition).unwrap_err();
+        assert_eq!(
+            error("keywords = \"let\""),
+            "`keywords` is not an array of strings"
+        );
+        assert_eq!(
+            error("block_comment = [\"/*\"]"),
+            "`block_comment` is not a pair of strings"
+        );
+        assert_eq!(
+            error("char_quote = \"''\""),
+            "`char_quote` has `''`, which is not a single character"
+        );
+        assert_eq!(error("comment = \"#\""), "unknown key `comment`");
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = Registry::default();
+        let rust = registry.language(&filetype::RUST);
+        assert_eq!(rust.line_comment.as_deref(), Some("//"));
+        assert_eq!(rust.keywords.get("fn"), Some(&Type::Keyword));
+        assert_eq!(registry.language(&filetype::MARKDOWN).line_comment, None);
+        assert_eq!(
+            registry.filetype(Path::new("a/b.bash")),
+            filetype::SHELL.clone()
+        );
+
+        let zig = Filetype::named("zig");
+        assert_eq!(
+            registry.filetype(Path::new("main.zig")),
+            Filetype::from_ext("zig")
+        );
+        registry.add(Language {
+            extensions: vec![String::from("zig"), String::from("zon")],
+            ..Language::plain(zig.clone())
+        });
+        assert_eq!(registry.filetype(Path::new("build.zig.zon")), zig);
+
+        registry.add(Language::plain(filetype::RUST.clone()));
+        assert!(registry.language(&filetype::RUST).keywords.is_empty());
+    }
+}
@@ -1,8 +1,13 @@
 pub mod brand;
 pub mod buffer;
+pub mod diagnostic;
 pub mod fs;
 pub mod itoa;
+pub mod json;
+pub mod language;
+pub mod spell;
 pub mod string;
+pub mod toml;
 pub mod trace;
 pub mod trace_subscriber;
 pub mod vec2;
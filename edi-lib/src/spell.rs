@@ -0,0 +1,444 @@
+//! Checking the spelling of words against a dictionary
+//!
+//! A dictionary is either a plain list of words, one per line, or a Hunspell dictionary: a `.dic`
+//! file of words with the flags of the affixes they take, and an `.aff` file that defines the
+//! affixes. Of the `.aff` file only `FLAG`, `PFX` and `SFX` are understood, which is enough to
+//! expand the words of the common dictionaries to their forms
+
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+/// The words that are spelled correctly
+#[derive(Debug, Default, Clone)]
+pub struct Dictionary {
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    /// Makes a dictionary of the words of a list, one per line
+    #[must_use]
+    pub fn from_words(list: &str) -> Self {
+        let words = list
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .map(String::from)
+            .collect();
+        Self { words }
+    }
+
+    /// Makes a dictionary of the words of a Hunspell `.dic` file along with the forms the
+    /// affixes of the `.aff` file make of them
+    #[must_use]
+    pub fn from_hunspell(dic: &str, aff: &str) -> Self {
+        let affixes = Affixes::parse(aff);
+        let mut words = HashSet::new();
+        let mut lines = dic.lines().peekable();
+        // The first line is the number of the words
+        lines.next_if(|line| line.trim().parse::<usize>().is_ok());
+        for line in lines {
+            let entry = line.split_whitespace().next().unwrap_or_default();
+            let (word, flags) = entry.split_once('/').unwrap_or((entry, ""));
+            if word.is_empty() {
+                continue;
+            }
+            affixes.expand(word, &affixes.flags(flags), &mut words);
+            words.insert(String::from(word));
+        }
+        Self { words }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Returns whether the word is spelled correctly. A capitalized word is also correct if it is
+    /// in the dictionary in lowercase, as it is at the start of a sentence
+    #[must_use]
+    pub fn check(&self, word: &str) -> bool {
+        if self.words.contains(word) {
+            return true;
+        }
+        let mut chars = word.chars();
+        chars.next().is_some_and(char::is_uppercase)
+            && !chars.any(char::is_uppercase)
+            && self.words.contains(&word.to_lowercase())
+    }
+
+    /// Returns the character ranges of the misspelled words of the text, which starts at the
+    /// character `offset`. Words with digits or underscores in them, words in all capitals and
+    /// words with capitals in their middle are taken to be identifiers and are not checked
+    #[must_use]
+    pub fn misspellings(
+        &self,
+        text: impl IntoIterator<Item = char>,
+        offset: usize,
+    ) -> Vec<Range<usize>> {
+        let mut misspellings = Vec::new();
+        let mut word = String::new();
+        let mut start = offset;
+        for (idx, c) in text.into_iter().chain(std::iter::once(' ')).enumerate() {
+            if c.is_alphanumeric() || c == '_' || c == '\'' {
+                if word.is_empty() {
+                    start = offset + idx;
+                }
+                word.push(c);
+                continue;
+            }
+            if word.is_empty() {
+                continue;
+            }
+
+            let leading = word.chars().take_while(|&c| c == '\'').count();
+            let trimmed = word.trim_matches('\'');
+            if is_checked(trimmed) && !self.check(trimmed) {
+                let start = start + leading;
+                misspellings.push(start..start + trimmed.chars().count());
+            }
+            word.clear();
+        }
+        misspellings
+    }
+
+    /// Returns up to `limit` words of the dictionary that the misspelled word may have been meant
+    /// to be, the closest ones first. They are capitalized if the word is
+    #[must_use]
+    pub fn suggest(&self, word: &str, limit: usize) -> Vec<String> {
+        const MAX_DISTANCE: usize = 2;
+
+        let lowercase: Vec<char> = word.to_lowercase().chars().collect();
+        let first = lowercase.first().copied();
+        let mut found: Vec<(usize, bool, &str)> = self
+            .words
+            .iter()
+            .filter_map(|candidate| {
+                let chars: Vec<char> = candidate.chars().collect();
+                if chars.len().abs_diff(lowercase.len()) > MAX_DISTANCE {
+                    return None;
+                }
+                let distance = distance(&lowercase, &chars);
+                (distance <= MAX_DISTANCE).then_some((
+                    distance,
+                    chars.first() != first.as_ref(),
+                    candidate.as_str(),
+                ))
+            })
+            .collect();
+        found.sort_unstable();
+
+        let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+        let mut seen = HashSet::new();
+        found
+            .into_iter()
+            .map(|(_, _, candidate)| {
+                if capitalized {
+                    capitalize(candidate)
+                } else {
+                    String::from(candidate)
+                }
+            })
+            .filter(|candidate| candidate != word && seen.insert(candidate.clone()))
+            .take(limit)
+            .collect()
+    }
+}
+
+/// Returns whether a word is checked, as it does not look like an identifier or an acronym
+fn is_checked(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars.next().is_some_and(char::is_alphabetic)
+        && !word.chars().any(|c| c.is_numeric() || c == '_')
+        && !chars.any(char::is_uppercase)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Returns the number of the insertions, deletions, substitutions and swaps of adjacent
+/// characters it takes to turn `a` into `b`
+fn distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>(); a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut d = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d = d.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = d;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// How the flags of the words are written in a Hunspell dictionary
+#[derive(Debug, Clone, Copy, Default)]
+enum FlagFormat {
+    /// A character per flag
+    #[default]
+    Char,
+    /// Two characters per flag
+    Long,
+    /// Numbers separated by commas
+    Num,
+}
+
+/// A prefix or a suffix of a Hunspell dictionary
+#[derive(Debug)]
+struct Rule {
+    /// What is removed from the word before `add` is added
+    strip: String,
+    add: String,
+    /// What the word has to start with for a prefix, or end with for a suffix
+    condition: Vec<Condition>,
+}
+
+/// A character of the condition of a rule
+#[derive(Debug)]
+enum Condition {
+    Any,
+    OneOf { chars: Vec<char>, negated: bool },
+}
+
+impl Condition {
+    /// Parses a condition, which is a pattern of characters, `.` and `[...]`/`[^...]` groups
+    fn parse(pattern: &str) -> Vec<Self> {
+        let mut condition = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            let part = match c {
+                '.' => Self::Any,
+                '[' => {
+                    let group: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                    let negated = group.starts_with('^');
+                    Self::OneOf {
+                        chars: group.chars().skip(usize::from(negated)).collect(),
+                        negated,
+                    }
+                }
+                c => Self::OneOf {
+                    chars: vec![c],
+                    negated: false,
+                },
+            };
+            condition.push(part);
+        }
+        condition
+    }
+
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Self::Any => true,
+            Self::OneOf { chars, negated } => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+/// The rules of a flag
+#[derive(Debug)]
+struct Affix {
+    is_prefix: bool,
+    /// Whether the affix combines with the affixes of the other kind
+    cross_product: bool,
+    rules: Vec<Rule>,
+}
+
+impl Affix {
+    /// Returns the forms the rules make of the word
+    fn apply<'a>(&'a self, word: &'a str) -> impl Iterator<Item = String> + 'a {
+        let chars: Vec<char> = word.chars().collect();
+        self.rules.iter().filter_map(move |rule| {
+            let len = rule.condition.len();
+            if self.is_prefix {
+                let matches = chars.len() >= len
+                    && rule
+                        .condition
+                        .iter()
+                        .zip(&chars)
+                        .all(|(c, &ch)| c.matches(ch));
+                let rest = word.strip_prefix(rule.strip.as_str())?;
+                (matches && !rest.is_empty()).then(|| format!("{}{rest}", rule.add))
+            } else {
+                let matches = chars.len() >= len
+                    && (rule.condition.iter())
+                        .zip(&chars[chars.len() - len..])
+                        .all(|(c, &ch)| c.matches(ch));
+                let rest = word.strip_suffix(rule.strip.as_str())?;
+                (matches && !rest.is_empty()).then(|| format!("{rest}{}", rule.add))
+            }
+        })
+    }
+}
+
+/// The affixes of a Hunspell dictionary by their flags
+#[derive(Debug, Default)]
+struct Affixes {
+    format: FlagFormat,
+    affixes: HashMap<String, Affix>,
+}
+
+impl Affixes {
+    /// Parses an `.aff` file, ignoring what it defines other than flags and affixes
+    fn parse(aff: &str) -> Self {
+        let mut affixes = Self::default();
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                ["FLAG", "long", ..] => affixes.format = FlagFormat::Long,
+                ["FLAG", "num", ..] => affixes.format = FlagFormat::Num,
+                [kind @ ("PFX" | "SFX"), flag, cross_product, count]
+                    if count.parse::<usize>().is_ok() =>
+                {
+                    affixes.affixes.insert(
+                        String::from(flag),
+                        Affix {
+                            is_prefix: kind == "PFX",
+                            cross_product: cross_product == "Y",
+                            rules: Vec::new(),
+                        },
+                    );
+                }
+                ["PFX" | "SFX", flag, strip, add, ref rest @ ..] => {
+                    let Some(affix) = affixes.affixes.get_mut(flag) else {
+                        continue;
+                    };
+                    let empty = |s: &str| if s == "0" { String::new() } else { s.into() };
+                    // Affixes may have flags of their own, the affixes they take are left out
+                    let add = add.split('/').next().unwrap_or_default();
+                    affix.rules.push(Rule {
+                        strip: empty(strip),
+                        add: empty(add),
+                        condition: rest.first().map_or_else(Vec::new, |c| Condition::parse(c)),
+                    });
+                }
+                _ => {}
+            }
+        }
+        affixes
+    }
+
+    /// Splits the flags of a word up
+    fn flags(&self, flags: &str) -> Vec<String> {
+        match self.format {
+            FlagFormat::Char => flags.chars().map(String::from).collect(),
+            FlagFormat::Long => {
+                let chars: Vec<char> = flags.chars().collect();
+                chars.chunks(2).map(|flag| flag.iter().collect()).collect()
+            }
+            FlagFormat::Num => flags.split(',').map(String::from).collect(),
+        }
+    }
+
+    /// Adds the forms the affixes of the flags make of the word to `words`, combining the
+    /// prefixes and suffixes that allow it
+    fn expand(&self, word: &str, flags: &[String], words: &mut HashSet<String>) {
+        let affixes: Vec<&Affix> = flags
+            .iter()
+            .filter_map(|flag| self.affixes.get(flag))
+            .collect();
+        let prefixes = affixes.iter().filter(|affix| affix.is_prefix);
+        for suffix in affixes.iter().filter(|affix| !affix.is_prefix) {
+            for form in suffix.apply(word) {
+                if suffix.cross_product {
+                    for prefix in prefixes.clone().filter(|affix| affix.cross_product) {
+                        words.extend(prefix.apply(&form));
+                    }
+                }
+                words.insert(form);
+            }
+        }
+        for prefix in prefixes {
+            words.extend(prefix.apply(word));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AFF: &str = "SET UTF-8
+TRY esianrtolcdugmphbyfvkwzESIANRTOLCDUGMPHBYFVKWZ'
+
+PFX U Y 1
+PFX U   0     un         .
+
+SFX D Y 4
+SFX D   0     d          e
+SFX D   y     ied        [^aeiou]y
+SFX D   0     ed         [^ey]
+SFX D   0     ed         [aeiou]y
+
+SFX S Y 2
+SFX S   y     ies        [^aeiou]y
+SFX S   0     s          [^sxzhy]
+";
+
+    const DIC: &str = "4
+like/UDS
+try/DS
+play/D
+cat/S
+";
+
+    #[test]
+    fn hunspell() {
+        let dictionary = Dictionary::from_hunspell(DIC, AFF);
+        for word in [
+            "like", "liked", "likes", "unlike", "unliked", "tries", "tried", "played", "cats",
+        ] {
+            assert!(dictionary.check(word), "{word}");
+        }
+        for word in ["unplay", "trys", "plaied", "caties", "lik"] {
+            assert!(!dictionary.check(word), "{word}");
+        }
+
+        let long = Dictionary::from_hunspell("dog/Aa", "FLAG long\nSFX Aa Y 1\nSFX Aa 0 s .");
+        assert!(long.check("dogs"));
+        let num = Dictionary::from_hunspell("dog/10,7", "FLAG num\nSFX 7 Y 1\nSFX 7 0 gy .");
+        assert!(num.check("doggy"));
+    }
+
+    #[test]
+    fn misspellings() {
+        let dictionary = Dictionary::from_words("the\ncat\nsat\non\ndon't\nParis\n");
+        assert!(dictionary.check("The"));
+        assert!(!dictionary.check("paris"));
+        assert!(!dictionary.check("tHe"));
+
+        let text = "The cta sat, don't 'sat' on HTTP foo_bar x2 isOk Paris tset";
+        let misspelled: Vec<&str> = dictionary
+            .misspellings(text.chars(), 10)
+            .into_iter()
+            .map(|range| &text[range.start - 10..range.end - 10])
+            .collect();
+        assert_eq!(misspelled, ["cta", "tset"]);
+    }
+
+    #[test]
+    fn suggestions() {
+        let dictionary = Dictionary::from_words("cat\ncart\ncast\ndog\nact\ncats\n");
+        assert_eq!(dictionary.suggest("cta", 3), ["cat", "cats", "act"]);
+        assert_eq!(dictionary.suggest("Cta", 1), ["Cat"]);
+        assert_eq!(dictionary.suggest("cat", 3), ["cart", "cast", "cats"]);
+        assert!(dictionary.suggest("xylophone", 3).is_empty());
+    }
+}
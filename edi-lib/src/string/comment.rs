@@ -0,0 +1,66 @@
+//! Commenting lines out
+
+/// Returns the lines commented out with `prefix`, or with the comments removed if all of them are
+/// comments already. Blank lines are kept as they are and do not need to be comments. The prefix
+/// goes right after the smallest indentation of the lines, so they stay aligned
+#[must_use]
+pub fn toggle<S: AsRef<str>>(lines: &[S], prefix: &str) -> Vec<String> {
+    let is_blank = |line: &&str| line.trim().is_empty();
+    let filled = lines
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|line| !is_blank(line));
+
+    if filled
+        .clone()
+        .all(|line| line.trim_start().starts_with(prefix))
+    {
+        return lines
+            .iter()
+            .map(|line| uncomment(line.as_ref(), prefix))
+            .collect();
+    }
+
+    let indent = filled
+        .map(|line| super::indent::leading_whitespace(line).len())
+        .min()
+        .unwrap_or_default();
+    lines
+        .iter()
+        .map(AsRef::as_ref)
+        .map(|line| {
+            if is_blank(&line) {
+                return String::from(line);
+            }
+            let (indent, text) = line.split_at(indent);
+            format!("{indent}{prefix} {text}")
+        })
+        .collect()
+}
+
+/// Removes the comment prefix of the line and a space after it
+fn uncomment(line: &str, prefix: &str) -> String {
+    let indent = super::indent::leading_whitespace(line);
+    let Some(text) = line[indent.len()..].strip_prefix(prefix) else {
+        return String::from(line);
+    };
+    let text = text.strip_prefix(' ').unwrap_or(text);
+    format!("{indent}{text}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggling() {
+        let lines = ["    if x {", "", "        y", "    }"];
+        let commented = toggle(&lines, "//");
+        assert_eq!(commented, ["    // if x {", "", "    //     y", "    // }"]);
+        assert_eq!(toggle(&commented, "//"), lines);
+
+        assert_eq!(toggle(&["  # a", "b"], "#"), ["#   # a", "# b"]);
+        assert_eq!(toggle(&["  #a", "#  b"], "#"), ["  a", " b"]);
+        assert_eq!(toggle(&["   "], "#"), ["   "]);
+    }
+}
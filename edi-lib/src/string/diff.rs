@@ -0,0 +1,90 @@
+//! Finding what differs between two versions of a text
+
+use std::ops::Range;
+
+use edi_rope::Rope;
+
+/// A replacement of the characters in `range` with `text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl Change {
+    /// Returns the smallest single change that turns `old` into `new`, none if they are equal. The
+    /// text both of them start and end with is left out of the change
+    #[must_use]
+    pub fn between(old: &Rope, new: &str) -> Option<Self> {
+        let new_len = new.chars().count();
+        let prefix = old
+            .chars()
+            .zip(new.chars())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if prefix == old.len() && prefix == new_len {
+            return None;
+        }
+
+        let suffix = old
+            .chars_rev()
+            .zip(new.chars().rev())
+            .take(old.len().min(new_len) - prefix)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        Some(Self {
+            range: prefix..old.len() - suffix,
+            text: new
+                .chars()
+                .skip(prefix)
+                .take(new_len - suffix - prefix)
+                .collect(),
+        })
+    }
+
+    /// Returns where a position in the text before the change is after it, positions inside of
+    /// the replaced characters move to the start of the change
+    #[must_use]
+    pub fn map(&self, offset: usize) -> usize {
+        if offset < self.range.start {
+            return offset;
+        }
+        if offset < self.range.end {
+            return self.range.start;
+        }
+        offset - self.range.len() + self.text.chars().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn between() {
+        let old = Rope::from("hello world\n");
+        assert_eq!(Change::between(&old, "hello world\n"), None);
+
+        let change = Change::between(&old, "hello there world\n").unwrap();
+        assert_eq!((change.range, change.text.as_str()), (6..6, "there "));
+
+        let change = Change::between(&old, "help\n").unwrap();
+        assert_eq!((change.range, change.text.as_str()), (3..11, "p"));
+
+        // The shared prefix and suffix must not overlap
+        let change = Change::between(&Rope::from("aaa"), "aaaa").unwrap();
+        assert_eq!((change.range, change.text.as_str()), (3..3, "a"));
+        let change = Change::between(&Rope::from("ab"), "").unwrap();
+        assert_eq!((change.range, change.text.as_str()), (0..2, ""));
+    }
+
+    #[test]
+    fn map() {
+        let change = Change::between(&Rope::from("one two three"), "one 2 three").unwrap();
+        assert_eq!(change.map(2), 2);
+        assert_eq!(change.map(5), 4);
+        assert_eq!(change.map(8), 6);
+        assert_eq!(change.map(13), 11);
+    }
+}
@@ -0,0 +1,351 @@
+//! The highlighter that needs no grammar, it splits lines into words, literals and comments
+
+use std::sync::Arc;
+
+use edi_rope::Rope;
+
+use super::{Edit, Highlight, HighlightProvider, Type, shift};
+use crate::language::Language;
+
+/// Returns the highlights of the line, their offsets counted from its start, and what the next
+/// line starts inside of
+fn highlight_line(
+    language: &Language,
+    line: &str,
+    mut state: LineState,
+) -> (Vec<Highlight>, LineState) {
+    let chars: Vec<char> = line.chars().collect();
+    let at = |i: usize, s: &str| {
+        s.chars()
+            .enumerate()
+            .all(|(j, c)| chars.get(i + j) == Some(&c))
+    };
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let line_comment = language.line_comment.as_deref();
+    let block_comment = language.block_comment.as_ref();
+
+    let mut highlights = Vec::new();
+    let mut push = |start: usize, end: usize, ty: Type| {
+        if end > start {
+            highlights.push(Highlight {
+                start,
+                len: end - start,
+                ty,
+            });
+        }
+    };
+
+    // Where the comment or the string the line is inside of started
+    let mut from = 0;
+    let mut i = 0;
+    while i < chars.len() || state != LineState::Code {
+        match state {
+            LineState::BlockComment => {
+                let close = block_comment.map_or("", |(_, close)| close.as_str());
+                let Some(end) = (i..chars.len()).find(|&j| at(j, close)) else {
+                    push(from, chars.len(), Type::Comment);
+                    break;
+                };
+                i = end + close.chars().count();
+                push(from, i, Type::Comment);
+                state = LineState::Code;
+            }
+            LineState::String(quote) => {
+                let mut end = None;
+                while i < chars.len() {
+                    match chars[i] {
+                        '\\' => i += 1,
+                        c if c == quote => {
+                            end = Some(i + 1);
+                            break;
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                let Some(end) = end else {
+                    push(from, chars.len(), Type::String);
+                    break;
+                };
+                push(from, end, Type::String);
+                i = end;
+                state = LineState::Code;
+            }
+            LineState::Code => {
+                let c = chars[i];
+                if let Some((open, _)) = block_comment.filter(|(open, _)| at(i, open)) {
+                    (from, state) = (i, LineState::BlockComment);
+                    i += open.chars().count();
+                } else if line_comment.is_some_and(|prefix| at(i, prefix)) {
+                    push(i, chars.len(), Type::Comment);
+                    break;
+                } else if language.string_quotes.contains(&c) {
+                    (from, state) = (i, LineState::String(c));
+                    i += 1;
+                } else if language.char_quote == Some(c) {
+                    let len = char_literal_len(&chars[i..]);
+                    push(i, i + len.unwrap_or(0), Type::String);
+                    i += len.unwrap_or(1);
+                } else if is_word_char(c) {
+                    let start = i;
+                    while i < chars.len()
+                        && (is_word_char(chars[i])
+                            || chars[i] == '.'
+                                && chars[start].is_ascii_digit()
+                                && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+                    {
+                        i += 1;
+                    }
+                    if chars[start].is_ascii_digit() {
+                        push(start, i, Type::Number);
+                        continue;
+                    }
+                    let word: String = chars[start..i].iter().collect();
+                    if let Some(&ty) = language.keywords.get(&word) {
+                        push(start, i, ty);
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    (highlights, state)
+}
+
+/// Returns the length of the character literal `chars` start with, `None` if the quote does not
+/// start one, as a lifetime does
+fn char_literal_len(chars: &[char]) -> Option<usize> {
+    let quote = chars[0];
+    match chars.get(1) {
+        Some('\\') => (3..chars.len().min(12))
+            .find(|&j| chars[j] == quote)
+            .map(|j| j + 1),
+        Some(_) if chars.get(2) == Some(&quote) => Some(3),
+        _ => None,
+    }
+}
+
+/// What a line starts inside of
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum LineState {
+    #[default]
+    Code,
+    BlockComment,
+    /// A string quoted with the character
+    String(char),
+}
+
+/// Highlights keywords, literals and comments without parsing the code, so it only needs to know
+/// how they are written in the filetype. Every highlight stays within a line, what a line starts
+/// inside of is kept so that edited lines can be highlighted on their own
+#[derive(Debug, Clone)]
+pub struct BuiltinHighlighter {
+    language: Arc<Language>,
+    /// What each line starts inside of, and one more for the line after the last line break
+    states: Vec<LineState>,
+}
+
+impl BuiltinHighlighter {
+    #[must_use]
+    pub const fn new(language: Arc<Language>) -> Self {
+        Self {
+            language,
+            states: Vec::new(),
+        }
+    }
+
+    /// Highlights the lines from the `first` one on, replacing their highlights, and returns what
+    /// each of them started inside of. Stops at the first line after `last` that starts inside of
+    /// the same thing as the line `moved_by` lines before or after it did before the edits, as the
+    /// lines from there on did not change
+    fn highlight_from(
+        &self,
+        content: &Rope,
+        (first, last): (usize, usize),
+        moved_by: isize,
+        highlights: &mut Vec<Highlight>,
+    ) -> (Vec<LineState>, bool) {
+        let mut states = Vec::new();
+        let mut state = self.states.get(first).copied().unwrap_or_default();
+
+        for (n, line) in (first..).zip(content.lines_at(first)) {
+            let unchanged = n
+                .checked_add_signed(-moved_by)
+                .and_then(|old| self.states.get(old));
+            if n > last && unchanged == Some(&state) {
+                return (states, true);
+            }
+            states.push(state);
+
+            let (line_highlights, next) = highlight_line(&self.language, &line.contents, state);
+            let next_line = content.line_to_char(n + 1);
+            let start = highlights.partition_point(|hl| hl.start < line.character_offset);
+            let end = highlights.partition_point(|hl| hl.start < next_line);
+            highlights.splice(
+                start..end,
+                line_highlights.into_iter().map(|hl| Highlight {
+                    start: hl.start + line.character_offset,
+                    ..hl
+                }),
+            );
+            state = next;
+        }
+
+        // The text may end with a line break, after which an empty line starts
+        if first + states.len() <= content.total_lines() {
+            states.push(state);
+        }
+        (states, false)
+    }
+}
+
+impl HighlightProvider for BuiltinHighlighter {
+    fn highlights(&mut self, content: &Rope) -> Vec<Highlight> {
+        let mut highlights = Vec::new();
+        self.states.clear();
+        let (states, _) = self.highlight_from(content, (0, usize::MAX), 0, &mut highlights);
+        self.states = states;
+        highlights
+    }
+
+    /// Highlights only the lines the edits touched and the ones after them that start inside of
+    /// something else now, the highlights of the other lines are moved by the number of
+    /// characters inserted and removed before them
+    fn update(&mut self, content: &Rope, edits: &[Edit], highlights: &mut Vec<Highlight>) {
+        if self.states.is_empty() {
+            *highlights = self.highlights(content);
+            return;
+        }
+
+        let changed = shift(edits, highlights);
+        let (Some(first), Some(last)) = (
+            changed.iter().map(|r| content.line_of_index(r.start)).min(),
+            changed.iter().map(|r| content.line_of_index(r.end)).max(),
+        ) else {
+            return;
+        };
+
+        #[expect(
+            clippy::cast_possible_wrap,
+            reason = "a rope can not have more lines than `isize::MAX`"
+        )]
+        let moved_by = (content.total_lines() + 1) as isize - self.states.len() as isize;
+        let (states, stopped) = self.highlight_from(content, (first, last), moved_by, highlights);
+
+        let old_end = if stopped {
+            (first + states.len())
+                .checked_add_signed(-moved_by)
+                .unwrap_or(first)
+        } else {
+            self.states.len()
+        };
+        let old_end = old_end.clamp(first.min(self.states.len()), self.states.len());
+        self.states.splice(first.min(old_end)..old_end, states);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fs::filetype::{self, Filetype},
+        language::Registry,
+    };
+
+    fn highlighter(filetype: &Filetype) -> BuiltinHighlighter {
+        BuiltinHighlighter::new(Registry::default().language(filetype))
+    }
+
+    fn highlighted(text: &str, filetype: &Filetype) -> Vec<(String, Type)> {
+        highlighter(filetype)
+            .highlights(&Rope::from(text))
+            .into_iter()
+            .map(|hl| (text.chars().skip(hl.start).take(hl.len).collect(), hl.ty))
+            .collect()
+    }
+
+    fn owned(highlights: &[(&str, Type)]) -> Vec<(String, Type)> {
+        highlights
+            .iter()
+            .map(|&(text, ty)| (String::from(text), ty))
+            .collect()
+    }
+
+    #[test]
+    fn tokens() {
+        let rust = filetype::RUST.clone();
+        assert_eq!(
+            highlighted(
+                "let s = \"fn \\\" if\"; // for\nfn f<'a>() -> u8 { b'\\n' + 1.5e3 }",
+                &rust
+            ),
+            owned(&[
+                ("let", Type::Keyword),
+                ("\"fn \\\" if\"", Type::String),
+                ("// for", Type::Comment),
+                ("fn", Type::Keyword),
+                ("u8", Type::Type),
+                ("'\\n'", Type::String),
+                ("1.5e3", Type::Number),
+            ])
+        );
+
+        let python = filetype::PYTHON.clone();
+        assert_eq!(
+            highlighted("x = 'a # b' # c", &python),
+            owned(&[("'a # b'", Type::String), ("# c", Type::Comment)])
+        );
+    }
+
+    #[test]
+    fn multiline() {
+        let c = filetype::C.clone();
+        assert_eq!(
+            highlighted("int a; /* x\nint b;\n*/ int c = 0x1f;", &c),
+            owned(&[
+                ("int", Type::Keyword),
+                ("/* x", Type::Comment),
+                ("int b;", Type::Comment),
+                ("*/", Type::Comment),
+                ("int", Type::Keyword),
+                ("0x1f", Type::Number),
+            ])
+        );
+    }
+
+    #[test]
+    fn incremental_update() {
+        let mut highlighter = highlighter(&filetype::RUST);
+        let mut rope = Rope::from("fn a() {\n    let x = 1;\n}\nfn b() {}\n");
+        let mut highlights = highlighter.highlights(&rope);
+
+        // `let` becomes `letter`, a line is split and `fn` is typed on the first line
+        let mut edits = Vec::new();
+        rope.insert(16, "ter");
+        edits.push(Edit::insert(16, 3));
+        rope.insert(9, "\n");
+        edits.push(Edit::insert(9, 1));
+        rope.delete(0..2);
+        edits.push(Edit::remove(0, 2));
+        rope.insert(0, "pub fn");
+        edits.push(Edit::insert(0, 6));
+
+        highlighter.update(&rope, &edits, &mut highlights);
+        assert_eq!(highlights, highlighter.clone().highlights(&rope));
+        assert_eq!(highlights.len(), 4);
+
+        // Opening a comment changes the lines after the edited one
+        rope.insert(0, "/*");
+        highlighter.update(&rope, &[Edit::insert(0, 2)], &mut highlights);
+        assert_eq!(highlights, highlighter.clone().highlights(&rope));
+        assert!(highlights.iter().all(|hl| hl.ty == Type::Comment));
+
+        rope.delete(0..2);
+        highlighter.update(&rope, &[Edit::remove(0, 2)], &mut highlights);
+        let mut fresh = highlighter.clone();
+        assert_eq!(highlights, fresh.highlights(&rope));
+        assert_eq!(highlighter.states, fresh.states);
+    }
+}
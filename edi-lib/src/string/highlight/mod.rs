@@ -0,0 +1,146 @@
+//! Highlighting utilities
+
+mod builtin;
+#[cfg(feature = "tree-sitter")]
+mod treesitter;
+
+pub use builtin::BuiltinHighlighter;
+
+use std::{fmt, ops::Range, sync::Arc};
+
+use edi_rope::Rope;
+
+use crate::language::Language;
+
+/// A type of the highlight
+///
+/// Used to determine which color to apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Type {
+    /// Function name in definition / call
+    Function,
+    /// A keyword, e.g "let, const, var"
+    Keyword,
+    /// Identifier, e.g Variable name
+    Identifier,
+    /// A type definition
+    Type,
+    /// A comment
+    Comment,
+    /// A string or character literal
+    String,
+    /// A number literal
+    Number,
+}
+
+/// Represents a chunk of characters that should be highlighed grouped by highlihght type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Highlight {
+    /// Character number of the start of highlighed word or symbol
+    pub start: usize,
+    /// Length of the highlighted word or symbol
+    pub len: usize,
+    /// Type of the highlight, for more information, see `Type`
+    pub ty: Type,
+}
+
+impl PartialOrd for Highlight {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Highlight {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.start.cmp(&other.start)
+    }
+}
+
+/// A change of the text, `removed` characters at `offset` replaced with `inserted` ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edit {
+    pub offset: usize,
+    pub removed: usize,
+    pub inserted: usize,
+}
+
+impl Edit {
+    #[must_use]
+    pub const fn insert(offset: usize, inserted: usize) -> Self {
+        Self {
+            offset,
+            removed: 0,
+            inserted,
+        }
+    }
+
+    #[must_use]
+    pub const fn remove(offset: usize, removed: usize) -> Self {
+        Self {
+            offset,
+            removed,
+            inserted: 0,
+        }
+    }
+}
+
+/// Finds the highlights of the text of a buffer. A provider is made for a single buffer, so it
+/// can reuse what it found out about the previous text to highlight the next one
+pub trait HighlightProvider: fmt::Debug {
+    /// Returns the highlights of `content`, sorted and not overlapping
+    fn highlights(&mut self, content: &Rope) -> Vec<Highlight>;
+
+    /// Updates the `highlights` of the text to those of `content`, which the `edits` made out of
+    /// it. By default the whole text is highlighted again
+    fn update(&mut self, content: &Rope, edits: &[Edit], highlights: &mut Vec<Highlight>) {
+        if !edits.is_empty() {
+            *highlights = self.highlights(content);
+        }
+    }
+}
+
+/// Moves the highlights after each edit by the number of characters it inserted and removed, and
+/// drops the ones it changed. Returns the ranges of the text the edits made, whose lines have to
+/// be highlighted again
+fn shift(edits: &[Edit], highlights: &mut Vec<Highlight>) -> Vec<Range<usize>> {
+    let mut changed: Vec<Range<usize>> = Vec::with_capacity(edits.len());
+    for edit in edits {
+        let removed_end = edit.offset + edit.removed;
+        let moved = |offset: usize| {
+            if offset >= removed_end {
+                offset - edit.removed + edit.inserted
+            } else {
+                offset.min(edit.offset)
+            }
+        };
+
+        highlights.retain(|hl| hl.start + hl.len <= edit.offset || hl.start >= removed_end);
+        let after = highlights.partition_point(|hl| hl.start < removed_end);
+        for hl in &mut highlights[after..] {
+            hl.start = moved(hl.start);
+        }
+        for range in &mut changed {
+            *range = moved(range.start)..moved(range.end);
+        }
+        changed.push(edit.offset..edit.offset + edit.inserted);
+    }
+    changed
+}
+
+/// Returns a provider for the `language`, one that parses the text if the `tree-sitter` feature
+/// is enabled and the language has a grammar, and the built-in one otherwise
+#[must_use]
+pub fn provider(language: &Arc<Language>) -> Box<dyn HighlightProvider> {
+    #[cfg(feature = "tree-sitter")]
+    if let Some(provider) = treesitter::TreeSitter::new(&language.filetype) {
+        return Box::new(provider);
+    }
+
+    Box::new(BuiltinHighlighter::new(Arc::clone(language)))
+}
+
+/// Get all highlights of `contents` based on the `language`. Highlights are sorted by default
+#[must_use]
+pub fn get_highlights(content: &Rope, language: &Arc<Language>) -> Vec<Highlight> {
+    provider(language).highlights(content)
+}
@@ -0,0 +1,240 @@
+//! Highlighting based on the syntax tree tree-sitter parses the text into
+
+use std::{fmt, ops::Range};
+
+use edi_rope::Rope;
+use tree_sitter::{
+    InputEdit, Language, Parser, Point, Query, QueryCursor, StreamingIterator, Tree,
+};
+
+use super::{Highlight, HighlightProvider, Type};
+use crate::fs::filetype::{self, Filetype};
+
+/// Parses the text with the grammar of its language and highlights the nodes the highlight query
+/// of the grammar captures. The text is reparsed incrementally, reusing the tree of the previous
+/// one where it did not change
+pub struct TreeSitter {
+    parser: Parser,
+    query: Query,
+    /// The type of the highlight of each capture of the query, `None` if it is not highlighted
+    types: Vec<Option<Type>>,
+    /// The text parsed the last time and its tree
+    last: Option<(String, Tree)>,
+}
+
+impl fmt::Debug for TreeSitter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TreeSitter")
+            .field("types", &self.types)
+            .field("last", &self.last)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TreeSitter {
+    /// Returns a highlighter for the `filetype`, or `None` if no grammar of it is included
+    pub fn new(filetype: &Filetype) -> Option<Self> {
+        let (language, highlights): (Language, _) = if filetype.eq(&filetype::RUST) {
+            (
+                tree_sitter_rust::LANGUAGE.into(),
+                tree_sitter_rust::HIGHLIGHTS_QUERY,
+            )
+        } else if filetype.eq(&filetype::C) {
+            (
+                tree_sitter_c::LANGUAGE.into(),
+                tree_sitter_c::HIGHLIGHT_QUERY,
+            )
+        } else if filetype.eq(&filetype::GO) {
+            (
+                tree_sitter_go::LANGUAGE.into(),
+                tree_sitter_go::HIGHLIGHTS_QUERY,
+            )
+        } else {
+            return None;
+        };
+
+        let mut parser = Parser::new();
+        parser.set_language(&language).ok()?;
+        let query = Query::new(&language, highlights).ok()?;
+        let types = query
+            .capture_names()
+            .iter()
+            .map(|name| capture_type(name))
+            .collect();
+
+        Some(Self {
+            parser,
+            query,
+            types,
+            last: None,
+        })
+    }
+
+    /// Returns the highlights of the nodes of the tree. A node is highlighted by the first
+    /// pattern that captures it, and the nodes inside of it are not highlighted again
+    fn collect(&self, tree: &Tree, text: &str) -> Vec<Highlight> {
+        let mut cursor = QueryCursor::new();
+        let mut captures = cursor.captures(&self.query, tree.root_node(), text.as_bytes());
+
+        let mut ranges: Vec<(Range<usize>, Type)> = Vec::new();
+        while let Some((query_match, index)) = captures.next() {
+            let capture = query_match.captures[*index];
+            let Some(ty) = self.types[capture.index as usize] else {
+                continue;
+            };
+            let range = capture.node.byte_range();
+            if ranges
+                .last()
+                .is_some_and(|(last, _)| range.start < last.end)
+            {
+                continue;
+            }
+            ranges.push((range, ty));
+        }
+
+        // The ranges are sorted, so the characters before each of them are only counted once
+        let mut counted = (0, 0);
+        let mut char_offset = |byte: usize| {
+            let (bytes, chars) = counted;
+            counted = (byte, chars + text[bytes..byte].chars().count());
+            counted.1
+        };
+        ranges
+            .into_iter()
+            .map(|(range, ty)| {
+                let start = char_offset(range.start);
+                let end = char_offset(range.end);
+                Highlight {
+                    start,
+                    len: end - start,
+                    ty,
+                }
+            })
+            .collect()
+    }
+}
+
+impl HighlightProvider for TreeSitter {
+    fn highlights(&mut self, content: &Rope) -> Vec<Highlight> {
+        let text: String = content.chars().collect();
+        let old_tree = self.last.take().map(|(old_text, mut tree)| {
+            tree.edit(&edit_between(&old_text, &text));
+            tree
+        });
+
+        let Some(tree) = self.parser.parse(&text, old_tree.as_ref()) else {
+            return Vec::new();
+        };
+        let highlights = self.collect(&tree, &text);
+        self.last = Some((text, tree));
+        highlights
+    }
+}
+
+/// Returns the type of the highlight for a capture named as in the highlight queries of the
+/// grammars, e.g. `keyword` or `function.method`
+fn capture_type(name: &str) -> Option<Type> {
+    let group = name.split('.').next().unwrap_or(name);
+    match group {
+        _ if name == "constant.builtin" => Some(Type::Keyword),
+        "keyword" => Some(Type::Keyword),
+        "function" => Some(Type::Function),
+        "type" | "constructor" => Some(Type::Type),
+        "comment" => Some(Type::Comment),
+        "string" | "escape" => Some(Type::String),
+        "number" => Some(Type::Number),
+        _ => None,
+    }
+}
+
+/// Returns the edit that turns `old` into `new`, which replaces everything between the longest
+/// common prefix and the longest common suffix of the two
+fn edit_between(old: &str, new: &str) -> InputEdit {
+    let mut start = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(start) {
+        start -= 1;
+    }
+
+    let mut suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(old.len() - start)
+        .min(new.len() - start);
+    while !old.is_char_boundary(old.len() - suffix) {
+        suffix -= 1;
+    }
+
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    InputEdit {
+        start_byte: start,
+        old_end_byte: old_end,
+        new_end_byte: new_end,
+        start_position: point_at(old, start),
+        old_end_position: point_at(old, old_end),
+        new_end_position: point_at(new, new_end),
+    }
+}
+
+/// Returns the row and the byte column of the `byte`th byte of `text`
+fn point_at(text: &str, byte: usize) -> Point {
+    let before = &text[..byte];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Point::new(before.matches('\n').count(), byte - line_start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlighted(text: &str, filetype: &Filetype) -> Vec<(String, Type)> {
+        let mut highlighter = TreeSitter::new(filetype).unwrap();
+        highlighter
+            .highlights(&Rope::from(text))
+            .into_iter()
+            .map(|hl| (text.chars().skip(hl.start).take(hl.len).collect(), hl.ty))
+            .collect()
+    }
+
+    #[test]
+    fn strings_and_comments() {
+        let rust = filetype::RUST.clone();
+        let highlights = highlighted("let s = \"fn é\"; // if x\nfn f() {}", &rust);
+        assert_eq!(
+            highlights,
+            [
+                (String::from("let"), Type::Keyword),
+                (String::from("\"fn é\""), Type::String),
+                (String::from("// if x"), Type::Comment),
+                (String::from("fn"), Type::Keyword),
+                (String::from("f"), Type::Function),
+            ]
+        );
+    }
+
+    #[test]
+    fn reparsing() {
+        let mut highlighter = TreeSitter::new(&filetype::GO).unwrap();
+        let mut rope = Rope::from("func f() {}\n");
+        let before = highlighter.highlights(&rope);
+
+        rope.insert(0, "// é\n");
+        let after = highlighter.highlights(&rope);
+        assert_eq!(after.len(), before.len() + 1);
+        assert_eq!(after[0].ty, Type::Comment);
+        assert_eq!(after[1].start, before[0].start + 5);
+
+        let edit = edit_between("ab\ncd", "ab\nXYcd");
+        assert_eq!(
+            (edit.start_byte, edit.old_end_byte, edit.new_end_byte),
+            (3, 3, 5)
+        );
+        assert_eq!(edit.new_end_position, Point::new(1, 2));
+    }
+}
@@ -0,0 +1,98 @@
+//! Indentation of lines
+
+use crate::fs::filetype::{self, Filetype};
+
+/// Returns the whitespace `line` starts with
+#[must_use]
+pub fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Returns the number of columns the whitespace `line` starts with takes, a tab going on to the
+/// next multiple of `tab_width`
+#[must_use]
+pub fn indent_columns(line: &str, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    leading_whitespace(line).chars().fold(0, |x, c| {
+        if c == '\t' {
+            x + tab_width - x % tab_width
+        } else {
+            x + 1
+        }
+    })
+}
+
+/// Returns whitespace that takes `columns` columns. Unless `expand_tab` is set, as much of it as
+/// possible is made of tabs
+#[must_use]
+pub fn whitespace(columns: usize, tab_width: usize, expand_tab: bool) -> String {
+    if expand_tab || tab_width == 0 {
+        return " ".repeat(columns);
+    }
+    "\t".repeat(columns / tab_width) + &" ".repeat(columns % tab_width)
+}
+
+/// Returns the indentation of the line started after `before`, the part of a line in front of the
+/// cursor. The line keeps the indentation of `before`, one `unit` more if `before` opens a block
+#[must_use]
+pub fn next_line_indent(before: &str, filetype: &Filetype, unit: &str) -> String {
+    let mut indent = String::from(leading_whitespace(before));
+    if before
+        .trim_end()
+        .chars()
+        .next_back()
+        .is_some_and(|c| block_openers(filetype).contains(&c))
+    {
+        indent.push_str(unit);
+    }
+    indent
+}
+
+/// Returns the characters that open a block when they end a line, which makes the lines after
+/// them indented
+fn block_openers(filetype: &Filetype) -> &'static [char] {
+    if filetype.eq(&filetype::PYTHON) {
+        return &[':'];
+    }
+
+    let c_like = [&filetype::C, &filetype::CPP, &filetype::GO, &filetype::RUST];
+    if c_like.into_iter().any(|known| filetype.eq(known)) {
+        return &['{', '(', '['];
+    }
+
+    &[]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indent() {
+        assert_eq!(leading_whitespace("\t  a b"), "\t  ");
+        assert_eq!(leading_whitespace("  "), "  ");
+
+        let rust = filetype::RUST.clone();
+        assert_eq!(next_line_indent("    let x = 1;", &rust, "    "), "    ");
+        assert_eq!(
+            next_line_indent("    fn f() {  ", &rust, "    "),
+            "        "
+        );
+        assert_eq!(next_line_indent("\tcall(", &rust, "\t"), "\t\t");
+
+        let python = filetype::PYTHON.clone();
+        assert_eq!(next_line_indent("  if x:", &python, "  "), "    ");
+        assert_eq!(next_line_indent("  d = {", &python, "  "), "  ");
+        assert_eq!(next_line_indent("fn f() {", &Filetype::default(), "  "), "");
+    }
+
+    #[test]
+    fn columns() {
+        assert_eq!(indent_columns("\t  x", 4), 6);
+        assert_eq!(indent_columns("x\t", 4), 0);
+        assert_eq!(indent_columns("  \t x", 4), 5);
+        assert_eq!(whitespace(6, 4, false), "\t  ");
+        assert_eq!(whitespace(6, 4, true), "      ");
+        assert_eq!(whitespace(3, 0, false), "   ");
+    }
+}
@@ -1,7 +1,11 @@
 //! String manipulation
 
+pub mod comment;
+pub mod diff;
 pub mod highlight;
+pub mod indent;
+pub mod object;
 pub mod position;
 pub mod search;
 
-pub use position::{GlobalPosition, LinePosition};
+pub use position::{CharSearch, GlobalPosition, LinePosition, WordPosition};
@@ -0,0 +1,330 @@
+//! Text objects, the pieces of text around the cursor that operators can act on
+
+use std::ops::Range;
+
+use edi_rope::Rope;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    /// A run of word characters, of punctuation or of whitespace within a line
+    Word,
+    /// Text between two of the given quote characters within a line
+    Quote(char),
+    /// Text between a pair of brackets, which may span several lines
+    Bracket { open: char, close: char },
+    /// A run of non-blank lines, or of blank ones
+    Paragraph,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextObject {
+    pub kind: ObjectKind,
+    /// Whether the surroundings of the object are included: the quotes or brackets, or the
+    /// whitespace or blank lines next to it
+    pub around: bool,
+}
+
+impl TextObject {
+    /// The object without its surroundings, `i` in `ciw`
+    #[must_use]
+    pub const fn inner(kind: ObjectKind) -> Self {
+        Self {
+            kind,
+            around: false,
+        }
+    }
+
+    /// The object along with its surroundings, `a` in `daw`
+    #[must_use]
+    pub const fn around(kind: ObjectKind) -> Self {
+        Self { kind, around: true }
+    }
+
+    /// Returns whether the object is made of whole lines
+    #[must_use]
+    pub const fn is_linewise(self) -> bool {
+        matches!(self.kind, ObjectKind::Paragraph)
+    }
+
+    /// Returns the character range of the object at `cursor`, or `None` if there is no such
+    /// object there
+    #[must_use]
+    pub fn find(self, rope: &Rope, cursor: usize) -> Option<Range<usize>> {
+        match self.kind {
+            ObjectKind::Word => in_line(rope, cursor, |line, col| word(line, col, self.around)),
+            ObjectKind::Quote(quote_char) => in_line(rope, cursor, |line, col| {
+                quote(line, col, quote_char, self.around)
+            }),
+            ObjectKind::Bracket { open, close } => bracket(rope, cursor, open, close, self.around),
+            ObjectKind::Paragraph => paragraph(rope, cursor, self.around),
+        }
+    }
+}
+
+/// Calls `f` with the characters of the line containing `cursor` and the cursor's column in it,
+/// turning the returned range into one relative to the start of `rope`
+fn in_line(
+    rope: &Rope,
+    cursor: usize,
+    f: impl FnOnce(&[char], usize) -> Option<Range<usize>>,
+) -> Option<Range<usize>> {
+    let line = rope.line(rope.line_of_index(cursor))?;
+    let chars: Vec<char> = line.contents.chars().collect();
+    let col = cursor
+        .saturating_sub(line.character_offset)
+        .min(chars.len().saturating_sub(1));
+
+    let range = f(&chars, col)?;
+    Some(line.character_offset + range.start..line.character_offset + range.end)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if c.is_whitespace() {
+            Self::Whitespace
+        } else if c.is_alphanumeric() || c == '_' {
+            Self::Word
+        } else {
+            Self::Punctuation
+        }
+    }
+}
+
+/// Returns the run of characters of the same class as the one at `col`
+fn run(line: &[char], col: usize) -> Range<usize> {
+    let class = CharClass::of(line[col]);
+    let same = |i: &usize| CharClass::of(line[*i]) == class;
+
+    let start = (0..col).rev().take_while(same).last().unwrap_or(col);
+    let end = (col + 1..line.len()).take_while(same).last().unwrap_or(col) + 1;
+    start..end
+}
+
+fn word(line: &[char], col: usize, around: bool) -> Option<Range<usize>> {
+    if line.is_empty() {
+        return None;
+    }
+
+    let word = run(line, col);
+    if !around {
+        return Some(word);
+    }
+
+    let is_space = |i: usize| line.get(i).is_some_and(|c| c.is_whitespace());
+    if is_space(col) {
+        // The whitespace goes along with the word after it
+        let end = if word.end < line.len() {
+            run(line, word.end).end
+        } else {
+            word.end
+        };
+        return Some(word.start..end);
+    }
+
+    if is_space(word.end) {
+        Some(word.start..run(line, word.end).end)
+    } else if word.start > 0 && is_space(word.start - 1) {
+        Some(run(line, word.start - 1).start..word.end)
+    } else {
+        Some(word)
+    }
+}
+
+/// Quotes are paired from the start of the line, the pair is the one around `col`, or the first
+/// one after it
+fn quote(line: &[char], col: usize, quote_char: char, around: bool) -> Option<Range<usize>> {
+    let quotes: Vec<usize> = (0..line.len())
+        .filter(|&i| line[i] == quote_char && (i == 0 || line[i - 1] != '\\'))
+        .collect();
+    let (start, end) = quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|&(_, end)| col <= end)?;
+
+    if !around {
+        return Some(start + 1..end);
+    }
+
+    let is_space = |i: &usize| line[*i].is_whitespace();
+    let trailing = (end + 1..line.len()).take_while(is_space).count();
+    let leading = if trailing == 0 {
+        (0..start).rev().take_while(is_space).count()
+    } else {
+        0
+    };
+    Some(start - leading..end + 1 + trailing)
+}
+
+fn bracket(
+    rope: &Rope,
+    cursor: usize,
+    open: char,
+    close: char,
+    around: bool,
+) -> Option<Range<usize>> {
+    // A closing bracket under the cursor belongs to the pair, so it is not counted
+    let mut depth = 0_usize;
+    let start = (0..=cursor.min(rope.len().checked_sub(1)?))
+        .rev()
+        .find(|&i| match rope.get(i) {
+            Some(c) if c == close && i != cursor => {
+                depth += 1;
+                false
+            }
+            Some(c) if c == open => depth.checked_sub(1).map(|d| depth = d).is_none(),
+            _ => false,
+        })?;
+
+    let mut depth = 0_usize;
+    let end = start
+        + 1
+        + rope.chars_at(start + 1).position(|c| {
+            if c == open {
+                depth += 1;
+            }
+            c == close && depth.checked_sub(1).map(|d| depth = d).is_none()
+        })?;
+
+    if around {
+        return Some(start..end + 1);
+    }
+
+    // When the brackets are on lines of their own, the inner part is the lines between them
+    let mut inner = start + 1..end;
+    if rope.get(inner.start) == Some('\n') {
+        inner.start += 1;
+    }
+    let last_newline = (inner.start..end)
+        .rev()
+        .find(|&i| rope.get(i) == Some('\n'));
+    if let Some(newline) = last_newline
+        && rope.substr(newline + 1..end).all(char::is_whitespace)
+    {
+        inner.end = newline + 1;
+    }
+
+    Some(inner.start.min(inner.end)..inner.end)
+}
+
+fn paragraph(rope: &Rope, cursor: usize, around: bool) -> Option<Range<usize>> {
+    let is_blank = |n: usize| rope.line(n).map(|line| line.contents.trim().is_empty());
+
+    let current = rope.line_of_index(cursor);
+    let blank = is_blank(current)?;
+    let run_start = |mut n: usize, blank: bool| {
+        while n > 0 && is_blank(n - 1) == Some(blank) {
+            n -= 1;
+        }
+        n
+    };
+    let run_end = |mut n: usize, blank: bool| {
+        while is_blank(n + 1) == Some(blank) {
+            n += 1;
+        }
+        n
+    };
+
+    let (mut first, mut last) = (run_start(current, blank), run_end(current, blank));
+    if around {
+        // The lines of the other kind after the paragraph, or before it if there are none after
+        if is_blank(last + 1).is_some() {
+            last = run_end(last + 1, !blank);
+        } else if first > 0 {
+            first = run_start(first - 1, !blank);
+        }
+    }
+
+    let line_start = |n: usize| {
+        rope.line_info(n)
+            .map_or(rope.len(), |line| line.character_offset)
+    };
+    Some(line_start(first)..line_start(last + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(text: &str, cursor: usize, object: TextObject) -> Option<String> {
+        let rope = Rope::from(text);
+        let range = object.find(&rope, cursor)?;
+        Some(rope.substr(range).collect())
+    }
+
+    #[test]
+    fn words() {
+        let iw = TextObject::inner(ObjectKind::Word);
+        let aw = TextObject::around(ObjectKind::Word);
+        let text = "foo.bar  baz\nqux";
+
+        assert_eq!(select(text, 1, iw).as_deref(), Some("foo"));
+        assert_eq!(select(text, 3, iw).as_deref(), Some("."));
+        assert_eq!(select(text, 7, iw).as_deref(), Some("  "));
+        assert_eq!(select(text, 5, aw).as_deref(), Some("bar  "));
+        assert_eq!(select(text, 10, aw).as_deref(), Some("  baz"));
+        assert_eq!(select(text, 7, aw).as_deref(), Some("  baz"));
+        assert_eq!(select(text, 14, aw).as_deref(), Some("qux"));
+        assert_eq!(select("a\n\nb", 2, iw), None);
+    }
+
+    #[test]
+    fn quotes() {
+        let i = TextObject::inner(ObjectKind::Quote('"'));
+        let a = TextObject::around(ObjectKind::Quote('"'));
+        let text = r#"x = "a \" b" + "c";"#;
+
+        assert_eq!(select(text, 6, i).as_deref(), Some(r#"a \" b"#));
+        assert_eq!(select(text, 4, i).as_deref(), Some(r#"a \" b"#));
+        assert_eq!(select(text, 0, i).as_deref(), Some(r#"a \" b"#));
+        assert_eq!(select(text, 13, i).as_deref(), Some("c"));
+        assert_eq!(select(text, 6, a).as_deref(), Some(r#""a \" b" "#));
+        assert_eq!(select(text, 16, a).as_deref(), Some(r#" "c""#));
+        assert_eq!(select(text, 18, i), None);
+    }
+
+    #[test]
+    fn brackets() {
+        let i = TextObject::inner(ObjectKind::Bracket {
+            open: '(',
+            close: ')',
+        });
+        let a = TextObject::around(ObjectKind::Bracket {
+            open: '(',
+            close: ')',
+        });
+        let text = "f(a, (b), c) (d)";
+
+        assert_eq!(select(text, 3, i).as_deref(), Some("a, (b), c"));
+        assert_eq!(select(text, 6, i).as_deref(), Some("b"));
+        assert_eq!(select(text, 7, a).as_deref(), Some("(b)"));
+        assert_eq!(select(text, 1, a).as_deref(), Some("(a, (b), c)"));
+        assert_eq!(select(text, 11, i).as_deref(), Some("a, (b), c"));
+        assert_eq!(select(text, 0, i), None);
+        assert_eq!(select(text, 12, i), None);
+
+        let text = "if (\n    x\n  ) {}";
+        assert_eq!(select(text, 7, i).as_deref(), Some("    x\n"));
+        assert_eq!(select("()", 0, i).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn paragraphs() {
+        let ip = TextObject::inner(ObjectKind::Paragraph);
+        let ap = TextObject::around(ObjectKind::Paragraph);
+        let text = "a\nb\n\n\nc\nd\n";
+
+        assert_eq!(select(text, 2, ip).as_deref(), Some("a\nb\n"));
+        assert_eq!(select(text, 2, ap).as_deref(), Some("a\nb\n\n\n"));
+        assert_eq!(select(text, 4, ip).as_deref(), Some("\n\n"));
+        assert_eq!(select(text, 4, ap).as_deref(), Some("\n\nc\nd\n"));
+        assert_eq!(select(text, 8, ap).as_deref(), Some("\n\nc\nd\n"));
+        assert!(ip.is_linewise());
+    }
+}
@@ -2,13 +2,48 @@
 pub enum LinePosition {
     Start,
     CharacterStart,
-    CurrentWordEnd,
-    CurrentWordStart,
     End,
 }
 
+/// A search for a character in the cursor line, `f`, `F`, `t` or `T`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharSearch {
+    pub c: char,
+    /// Whether the character is searched for before the cursor rather than after it
+    pub backward: bool,
+    /// Whether the cursor stops next to the character rather than on it
+    pub till: bool,
+}
+
+impl CharSearch {
+    /// Returns the search in the other direction, as `,` repeats it
+    #[must_use]
+    pub const fn reversed(self) -> Self {
+        Self {
+            backward: !self.backward,
+            ..self
+        }
+    }
+}
+
+/// Where a word motion moves the cursor to, going on to the next or the previous lines if the
+/// cursor line has no such word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordPosition {
+    /// The start of the next word, `w`
+    NextStart,
+    /// The end of the word, or of the next one if the cursor is at its end, `e`
+    End,
+    /// The start of the word, or of the previous one if the cursor is at its start, `b`
+    Start,
+    /// The end of the previous word, `ge`
+    PreviousEnd,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum GlobalPosition {
     Start,
     End,
+    /// A zero-indexed line, the last one if it is past the end
+    Line(usize),
 }
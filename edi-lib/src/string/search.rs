@@ -7,6 +7,8 @@ pub struct Searcher<'a> {
     rev: bool,
 
     allow_skip: bool,
+    /// Whether words are runs of any characters but whitespace, as for `W`, `B` and `E`
+    big: bool,
 }
 
 impl<'a> Searcher<'a> {
@@ -18,6 +20,7 @@ impl<'a> Searcher<'a> {
             rev: false,
 
             allow_skip: true,
+            big: false,
         }
     }
 
@@ -29,6 +32,7 @@ impl<'a> Searcher<'a> {
             rev: true,
 
             allow_skip: true,
+            big: false,
         }
     }
 
@@ -38,6 +42,103 @@ impl<'a> Searcher<'a> {
         self
     }
 
+    #[must_use]
+    pub const fn with_big(mut self, big: bool) -> Self {
+        self.big = big;
+        self
+    }
+
+    /// Returns the offset of the start of the word after the one at the offset, or `None` if the
+    /// line has none
+    #[must_use]
+    pub fn find_next_start(self) -> Option<usize> {
+        let mut chars = self.line.chars().skip(self.offset).peekable();
+        let current = self.group(*chars.peek()?);
+
+        let mut offset = self.offset;
+        if current != CharGroup::Space {
+            while chars.next_if(|&c| self.group(c) == current).is_some() {
+                offset += 1;
+            }
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {
+            offset += 1;
+        }
+        chars.peek().map(|_| offset)
+    }
+
+    /// Returns the offset of the last character of the word at the offset, or the offset if it is
+    /// not in a word
+    #[must_use]
+    pub fn find_current_end(self) -> usize {
+        let mut chars = self.line.chars().skip(self.offset);
+        let Some(current) = chars.next().map(|c| self.group(c)) else {
+            return self.offset;
+        };
+        if current == CharGroup::Space {
+            return self.offset;
+        }
+        self.offset + chars.take_while(|&c| self.group(c) == current).count()
+    }
+
+    /// Returns the offset of the end of the word before the one at the offset, or `None` if the
+    /// line has none
+    #[must_use]
+    pub fn find_previous_end(self) -> Option<usize> {
+        let before: Vec<char> = self.line.chars().take(self.offset).collect();
+        let current = self
+            .line
+            .chars()
+            .nth(self.offset)
+            .map_or(CharGroup::Space, |c| self.group(c));
+
+        let mut chars = before.into_iter().rev().peekable();
+        let mut offset = self.offset;
+        if current != CharGroup::Space {
+            while chars.next_if(|&c| self.group(c) == current).is_some() {
+                offset -= 1;
+            }
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {
+            offset -= 1;
+        }
+        chars.peek().map(|_| offset - 1)
+    }
+
+    /// Returns the offset of the end of the word after the offset, which may be the word at the
+    /// offset, or `None` if the line has none
+    #[must_use]
+    pub fn find_next_end(self) -> Option<usize> {
+        let mut chars = self.line.chars().skip(self.offset + 1).peekable();
+        let mut offset = self.offset + 1;
+        while chars.next_if(|c| c.is_whitespace()).is_some() {
+            offset += 1;
+        }
+        let current = self.group(chars.next()?);
+        while chars.next_if(|&c| self.group(c) == current).is_some() {
+            offset += 1;
+        }
+        Some(offset)
+    }
+
+    /// Returns the offset of the start of the word before the offset, which may be the word at
+    /// the offset, or `None` if the line has none
+    #[must_use]
+    pub fn find_previous_start(self) -> Option<usize> {
+        let before: Vec<char> = self.line.chars().take(self.offset).collect();
+        let mut chars = before.into_iter().rev().peekable();
+        let mut offset = self.offset;
+        while chars.next_if(|c| c.is_whitespace()).is_some() {
+            offset -= 1;
+        }
+        let current = self.group(chars.next()?);
+        offset -= 1;
+        while chars.next_if(|&c| self.group(c) == current).is_some() {
+            offset -= 1;
+        }
+        Some(offset)
+    }
+
     #[must_use]
     pub fn find(self) -> usize {
         match (self.rev, self.offset) {
@@ -65,18 +166,19 @@ impl<'a> Searcher<'a> {
         if self.allow_skip {
             // Part two: hop to the next word if it it current's word end
             let (hopped, new_current_char) =
-                Self::hop_to_next_word(&mut chars, next_char, current_char, whitespace_consumed);
+                self.hop_to_next_word(&mut chars, next_char, current_char, whitespace_consumed);
             diff += hopped;
             current_char = new_current_char;
         }
 
         // Part 3: get the current character's group and
         // iterate until some other group is found
-        let current_group = CharGroup::new(current_char);
-        diff + Self::skip_to_different_group(chars, &current_group)
+        let current_group = self.group(current_char);
+        diff + self.skip_to_different_group(chars, &current_group)
     }
 
     fn hop_to_next_word(
+        &self,
         chars: &mut Peekable<impl Iterator<Item = char>>,
         next_char: char,
         mut current_char: char,
@@ -84,7 +186,7 @@ impl<'a> Searcher<'a> {
     ) -> (usize, char) {
         let mut diff = 0;
 
-        let is_at_end = CharGroup::new(next_char).ne(&CharGroup::new(current_char));
+        let is_at_end = self.group(next_char).ne(&self.group(current_char));
         if is_at_end && whitespace_consumed != 0 {
             return (diff, current_char);
         }
@@ -107,12 +209,13 @@ impl<'a> Searcher<'a> {
     }
 
     fn skip_to_different_group(
+        &self,
         chars: Peekable<impl Iterator<Item = char>>,
         current_group: &CharGroup,
     ) -> usize {
         let mut diff = 0;
         for char in chars {
-            if CharGroup::new(char).ne(current_group) {
+            if self.group(char).ne(current_group) {
                 break;
             }
             diff += 1;
@@ -121,6 +224,13 @@ impl<'a> Searcher<'a> {
         diff
     }
 
+    fn group(&self, c: char) -> CharGroup {
+        match CharGroup::new(c) {
+            CharGroup::Punct | CharGroup::Other if self.big => CharGroup::Alphanumeric,
+            group => group,
+        }
+    }
+
     fn get_it(&self) -> Peekable<impl Iterator<Item = char> + '_> {
         consume_n(self.line.chars(), self.offset).peekable()
     }
@@ -232,4 +342,60 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn next_start_and_previous_end() {
+        let line = "foo.bar  baz";
+        assert_eq!(Searcher::new(line, 0).find_next_start(), Some(3));
+        assert_eq!(Searcher::new(line, 3).find_next_start(), Some(4));
+        assert_eq!(Searcher::new(line, 5).find_next_start(), Some(9));
+        assert_eq!(Searcher::new(line, 7).find_next_start(), Some(9));
+        assert_eq!(Searcher::new(line, 9).find_next_start(), None);
+        assert_eq!(
+            Searcher::new(line, 0).with_big(true).find_next_start(),
+            Some(9)
+        );
+
+        assert_eq!(Searcher::new_rev(line, 10).find_previous_end(), Some(6));
+        assert_eq!(Searcher::new_rev(line, 8).find_previous_end(), Some(6));
+        assert_eq!(Searcher::new_rev(line, 4).find_previous_end(), Some(3));
+        assert_eq!(Searcher::new_rev(line, 2).find_previous_end(), None);
+        assert_eq!(Searcher::new_rev(line, 12).find_previous_end(), Some(11));
+        let previous = Searcher::new_rev(line, 10)
+            .with_big(true)
+            .find_previous_end();
+        assert_eq!(previous, Some(6));
+        let previous = Searcher::new_rev(line, 5)
+            .with_big(true)
+            .find_previous_end();
+        assert_eq!(previous, None);
+
+        assert_eq!(Searcher::new(line, 4).find_current_end(), 6);
+        assert_eq!(Searcher::new(line, 1).with_big(true).find_current_end(), 6);
+        assert_eq!(Searcher::new(line, 7).find_current_end(), 7);
+    }
+
+    #[test]
+    fn next_end_and_previous_start() {
+        let line = "foo.bar  baz";
+        assert_eq!(Searcher::new(line, 0).find_next_end(), Some(2));
+        assert_eq!(Searcher::new(line, 2).find_next_end(), Some(3));
+        assert_eq!(Searcher::new(line, 6).find_next_end(), Some(11));
+        assert_eq!(Searcher::new(line, 11).find_next_end(), None);
+        assert_eq!(
+            Searcher::new(line, 0).with_big(true).find_next_end(),
+            Some(6)
+        );
+
+        assert_eq!(Searcher::new_rev(line, 11).find_previous_start(), Some(9));
+        assert_eq!(Searcher::new_rev(line, 9).find_previous_start(), Some(4));
+        assert_eq!(Searcher::new_rev(line, 4).find_previous_start(), Some(3));
+        assert_eq!(Searcher::new_rev(line, 0).find_previous_start(), None);
+        assert_eq!(Searcher::new_rev(line, 12).find_previous_start(), Some(9));
+        let previous = Searcher::new_rev(line, 9)
+            .with_big(true)
+            .find_previous_start();
+        assert_eq!(previous, Some(0));
+        assert_eq!(Searcher::new_rev("  foo", 2).find_previous_start(), None);
+    }
 }
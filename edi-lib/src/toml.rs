@@ -0,0 +1,360 @@
+//! A parser for the subset of TOML the configuration files are written in
+//!
+//! Supported are comments, `[table]` headers with dotted names, `key = value` pairs with bare or
+//! quoted keys, basic and literal strings, integers, booleans and arrays, which may span several
+//! lines
+
+use std::{collections::BTreeMap, fmt, iter::Peekable, str::Chars};
+
+/// Keys of a table and their values
+pub type Table = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    /// Returns the name of the type of the value, as used in error messages
+    #[must_use]
+    pub const fn type_name(&self) -> &'static str {
+        match self {
+            Self::String(_) => "string",
+            Self::Integer(_) => "integer",
+            Self::Boolean(_) => "boolean",
+            Self::Array(_) => "array",
+            Self::Table(_) => "table",
+        }
+    }
+}
+
+/// A syntax error in a document, `line` starts at 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Parses a document into its top level table
+///
+/// # Errors
+///
+/// Returns an error if the document is not valid or uses something the parser does not support
+pub fn parse(s: &str) -> Result<Table, Error> {
+    Parser {
+        chars: s.chars().peekable(),
+        line: 1,
+    }
+    .document()
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn document(&mut self) -> Result<Table, Error> {
+        let mut root = Table::new();
+        let mut path = Vec::new();
+
+        loop {
+            self.skip_blank(true);
+            match self.chars.peek() {
+                None => return Ok(root),
+                Some('[') => {
+                    self.chars.next();
+                    path = self.key()?;
+                    self.expect(']')?;
+                    Self::table_at(&mut root, &path).map_err(|message| self.error(message))?;
+                }
+                Some(_) => {
+                    let key = self.key()?;
+                    self.expect('=')?;
+                    self.skip_blank(false);
+                    let value = self.value()?;
+
+                    let (name, parents) = key.split_last().expect("keys are never empty");
+                    let table = Self::table_at(&mut root, &path)
+                        .and_then(|table| Self::table_at(table, parents))
+                        .map_err(|message| self.error(message))?;
+                    if table.insert(name.clone(), value).is_some() {
+                        return Err(self.error(format!("duplicate key `{name}`")));
+                    }
+                }
+            }
+            self.end_of_line()?;
+        }
+    }
+
+    /// Returns the table at `path` in `table`, creating the missing ones
+    fn table_at<'t>(mut table: &'t mut Table, path: &[String]) -> Result<&'t mut Table, String> {
+        for name in path {
+            let value = table
+                .entry(name.clone())
+                .or_insert_with(|| Value::Table(Table::new()));
+            let Value::Table(inner) = value else {
+                return Err(format!("`{name}` is not a table"));
+            };
+            table = inner;
+        }
+        Ok(table)
+    }
+
+    /// Parses a key made of bare or quoted parts separated by dots
+    fn key(&mut self) -> Result<Vec<String>, Error> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_blank(false);
+            let part = match self.chars.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let mut part = String::new();
+                    while let Some(&c) = self.chars.peek() {
+                        if !(c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                            break;
+                        }
+                        part.push(c);
+                        self.chars.next();
+                    }
+                    if part.is_empty() {
+                        return Err(self.error(String::from("expected a key")));
+                    }
+                    part
+                }
+            };
+            parts.push(part);
+
+            self.skip_blank(false);
+            if self.chars.peek() != Some(&'.') {
+                return Ok(parts);
+            }
+            self.chars.next();
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        match self.chars.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some(c) if c.is_ascii_alphanumeric() || matches!(c, '+' | '-') => {
+                let mut word = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '_')) {
+                        break;
+                    }
+                    word.push(c);
+                    self.chars.next();
+                }
+                match word.as_str() {
+                    "true" => Ok(Value::Boolean(true)),
+                    "false" => Ok(Value::Boolean(false)),
+                    _ => word
+                        .replace('_', "")
+                        .parse()
+                        .map(Value::Integer)
+                        .map_err(|_| self.error(format!("invalid value `{word}`"))),
+                }
+            }
+            _ => Err(self.error(String::from("expected a value"))),
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, Error> {
+        self.chars.next();
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank(true);
+            if self.chars.peek() == Some(&']') {
+                self.chars.next();
+                return Ok(Value::Array(values));
+            }
+            values.push(self.value()?);
+
+            self.skip_blank(true);
+            match self.chars.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Value::Array(values)),
+                _ => return Err(self.error(String::from("expected `,` or `]` in an array"))),
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, Error> {
+        self.chars.next();
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                None | Some('\n') => return Err(self.error(String::from("unterminated string"))),
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let escaped = match self.chars.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('e') => '\u{1b}',
+                        Some(c @ ('"' | '\\')) => c,
+                        Some(c @ ('u' | 'U')) => {
+                            self.unicode_escape(if c == 'u' { 4 } else { 8 })?
+                        }
+                        _ => return Err(self.error(String::from("invalid escape sequence"))),
+                    };
+                    s.push(escaped);
+                }
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self, digits: usize) -> Result<char, Error> {
+        let hex: String = self.chars.by_ref().take(digits).collect();
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .filter(|_| hex.len() == digits)
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error(format!("invalid unicode escape `{hex}`")))
+    }
+
+    fn literal_string(&mut self) -> Result<String, Error> {
+        self.chars.next();
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                None | Some('\n') => return Err(self.error(String::from("unterminated string"))),
+                Some('\'') => return Ok(s),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// Skips whitespace and comments, and newlines too if `newlines` is set
+    fn skip_blank(&mut self, newlines: bool) {
+        while let Some(&c) = self.chars.peek() {
+            match c {
+                '#' => while self.chars.next_if(|&c| c != '\n').is_some() {},
+                '\n' if newlines => {
+                    self.line += 1;
+                    self.chars.next();
+                }
+                ' ' | '\t' | '\r' => {
+                    self.chars.next();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Makes sure nothing but a comment follows on the line
+    fn end_of_line(&mut self) -> Result<(), Error> {
+        self.skip_blank(false);
+        match self.chars.peek() {
+            None | Some('\n') => Ok(()),
+            Some(_) => Err(self.error(String::from("expected the end of the line"))),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        self.skip_blank(false);
+        if self.chars.next_if_eq(&expected).is_none() {
+            return Err(self.error(format!("expected `{expected}`")));
+        }
+        Ok(())
+    }
+
+    const fn error(&self, message: String) -> Error {
+        Error {
+            line: self.line,
+            message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Value {
+        Value::String(String::from(s))
+    }
+
+    #[test]
+    fn values() {
+        let table = parse(
+            r#"
+            # A comment
+            name = "a \"quoted\"\tstring"   # trailing comment
+            path = 'C:\dir'
+            count = -1_000
+            on = true
+            list = [
+                1, 2,
+                3,
+            ]
+            "quoted key" = "\u00e9"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(table["name"], string("a \"quoted\"\tstring"));
+        assert_eq!(table["path"], string(r"C:\dir"));
+        assert_eq!(table["count"], Value::Integer(-1000));
+        assert_eq!(table["on"], Value::Boolean(true));
+        assert_eq!(
+            table["list"],
+            Value::Array(vec![
+                Value::Integer(1),
+                Value::Integer(2),
+                Value::Integer(3)
+            ])
+        );
+        assert_eq!(table["quoted key"], string("é"));
+    }
+
+    #[test]
+    fn tables() {
+        let table = parse("a = 1\n[b.c]\nd = 2\n[b]\ne.f = 3\n").unwrap();
+        assert_eq!(table["a"], Value::Integer(1));
+
+        let Value::Table(b) = &table["b"] else {
+            panic!("`b` is not a table");
+        };
+        let Value::Table(c) = &b["c"] else {
+            panic!("`b.c` is not a table");
+        };
+        assert_eq!(c["d"], Value::Integer(2));
+        let Value::Table(e) = &b["e"] else {
+            panic!("`b.e` is not a table");
+        };
+        assert_eq!(e["f"], Value::Integer(3));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |s| parse(s).unwrap_err().to_string();
+        assert_eq!(error("a = 1\nb = \"x"), "line 2: unterminated string");
+        assert_eq!(error("a = 1\na = 2"), "line 2: duplicate key `a`");
+        assert_eq!(error("a = 1 2"), "line 1: expected the end of the line");
+        assert_eq!(error("a = yes"), "line 1: invalid value `yes`");
+        assert_eq!(error("a = 1\n[a]"), "line 2: `a` is not a table");
+        assert_eq!(error("= 1"), "line 1: expected a key");
+        assert_eq!(error("a 1"), "line 1: expected `=`");
+        assert_eq!(
+            error("a = [1 2]"),
+            "line 1: expected `,` or `]` in an array"
+        );
+    }
+}
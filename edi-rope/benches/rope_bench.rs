@@ -1,5 +1,20 @@
+#![allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss,
+    clippy::iter_nth_zero
+)]
+
 use criterion::{Criterion, criterion_group, criterion_main};
 use edi_rope::Rope;
+use rand as _;
+#[cfg(feature = "regex")]
+use regex_automata as _;
+#[cfg(feature = "serde")]
+use serde as _;
+use serde_test as _;
+#[cfg(feature = "graphemes")]
+use unicode_segmentation as _;
 
 const CHUNK_SIZE: usize = 16;
 
@@ -31,7 +46,7 @@ impl RopeSize {
         }
     }
 
-    fn content_len(&self) -> usize {
+    const fn content_len(&self) -> usize {
         match self {
             RopeSize::Empty => 0,
             RopeSize::Small => LOREM_SMALL.len(),
@@ -40,7 +55,7 @@ impl RopeSize {
         }
     }
 
-    fn name(&self) -> &'static str {
+    const fn name(&self) -> &'static str {
         match self {
             RopeSize::Empty => "empty",
             RopeSize::Small => "small",
@@ -49,7 +64,7 @@ impl RopeSize {
         }
     }
 
-    fn contents(&self) -> &'static str {
+    const fn contents(&self) -> &'static str {
         match self {
             RopeSize::Empty => "",
             RopeSize::Small => LOREM_SMALL,
@@ -79,7 +94,7 @@ fn bench_rope_create(c: &mut Criterion) {
 fn bench_rope_insert(c: &mut Criterion) {
     let group = &mut c.benchmark_group("insert");
 
-    group.bench_function(format!("at_start_empty"), |b| {
+    group.bench_function("at_start_empty", |b| {
         b.iter_batched(
             RopeSize::Small.create_fn(),
             |mut rope: Rope| {
@@ -109,7 +124,7 @@ fn bench_rope_insert(c: &mut Criterion) {
 fn bench_lines_skip(c: &mut Criterion) {
     let group = &mut c.benchmark_group("lines_skip");
 
-    group.bench_function(format!("at_start_empty"), |b| {
+    group.bench_function("at_start_empty", |b| {
         b.iter_batched(
             RopeSize::Small.create_fn(),
             |rope: Rope| {
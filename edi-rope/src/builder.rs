@@ -0,0 +1,264 @@
+//! Incremental `Rope` construction
+
+use std::{
+    io::{self, Read},
+    sync::Arc,
+};
+
+use crate::{Rope, RopeConfig, node::Node};
+
+/// Builds a balanced `Rope` from string slices appended one after another
+///
+/// Appended text is split into leaves of `max_leaf_bytes` bytes (rounded up to the next character
+/// boundary). Full subtrees are merged as soon as they are complete, so the resulting tree is
+/// balanced and the whole input never has to be stored in a single `String`
+#[derive(Debug)]
+pub struct RopeBuilder {
+    config: RopeConfig,
+    track_metadata: bool,
+    pending: String,
+    /// Complete subtrees along with their levels. Levels are strictly decreasing from bottom to
+    /// top, which keeps the stack `O(log n)` deep
+    stack: Vec<(Node, usize)>,
+}
+
+impl RopeBuilder {
+    /// Initiates a builder with the default chunk size
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_config(RopeConfig::default())
+    }
+
+    /// Initiates a builder that produces leaves of at most `chunk_size` + 3 bytes
+    #[must_use]
+    pub fn with_chunk_size(chunk_size: usize) -> Self {
+        Self::with_config(RopeConfig {
+            max_leaf_bytes: chunk_size,
+            ..RopeConfig::default()
+        })
+    }
+
+    /// Initiates a builder for a rope that keeps the leaf size bounds of `config`
+    #[must_use]
+    pub fn with_config(config: RopeConfig) -> Self {
+        let config = RopeConfig {
+            max_leaf_bytes: config.max_leaf_bytes.max(1),
+            min_leaf_bytes: config.min_leaf_bytes.min(config.max_leaf_bytes),
+        };
+        Self {
+            config,
+            track_metadata: false,
+            pending: String::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Makes the built rope maintain `Metadata` of its text through edits
+    ///
+    /// Tracking costs an extra pass over every leaf that is created or modified
+    pub const fn track_metadata(&mut self, track: bool) -> &mut Self {
+        self.track_metadata = track;
+        self
+    }
+
+    /// Appends `s` to the end of the built rope
+    pub fn append(&mut self, mut s: &str) -> &mut Self {
+        if !self.pending.is_empty() {
+            let needed = self
+                .config
+                .max_leaf_bytes
+                .saturating_sub(self.pending.len());
+            let split = ceil_char_boundary(s, needed);
+            self.pending.push_str(&s[..split]);
+            s = &s[split..];
+
+            if self.pending.len() < self.config.max_leaf_bytes {
+                return self;
+            }
+
+            let leaf = Node::new_leaf_with(&self.pending, self.track_metadata);
+            self.pending.clear();
+            self.push_leaf(leaf);
+        }
+
+        while s.len() >= self.config.max_leaf_bytes {
+            let split = ceil_char_boundary(s, self.config.max_leaf_bytes);
+            self.push_leaf(Node::new_leaf_with(&s[..split], self.track_metadata));
+            s = &s[split..];
+        }
+
+        self.pending.push_str(s);
+        self
+    }
+
+    /// Consumes the builder, returning the built rope
+    #[must_use]
+    pub fn finish(mut self) -> Rope {
+        if !self.pending.is_empty() {
+            let leaf = Node::new_leaf_with(&self.pending, self.track_metadata);
+            self.push_leaf(leaf);
+        }
+
+        let Some((mut root, _)) = self.stack.pop() else {
+            return Rope {
+                track_metadata: self.track_metadata,
+                config: self.config,
+                ..Rope::default()
+            };
+        };
+        while let Some((left, _)) = self.stack.pop() {
+            root = Node::join(left, root);
+        }
+
+        let rope = Rope {
+            root: Arc::new(root),
+            track_metadata: self.track_metadata,
+            config: self.config,
+        };
+
+        #[cfg(debug_assertions)]
+        rope.validate_newlines();
+
+        rope
+    }
+
+    fn push_leaf(&mut self, leaf: Node) {
+        let mut node = (leaf, 0);
+        while let Some(&(_, level)) = self.stack.last() {
+            if level != node.1 {
+                break;
+            }
+
+            let Some((left, _)) = self.stack.pop() else {
+                unreachable!("stack was checked to be non-empty");
+            };
+            node = (Node::new_value(left, node.0), level + 1);
+        }
+
+        self.stack.push(node);
+    }
+}
+
+impl Default for RopeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rope {
+    /// Reads utf-8 encoded text from `r` into a balanced rope without buffering the whole input
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails or the input is not valid utf-8
+    pub fn from_reader(mut r: impl Read) -> io::Result<Rope> {
+        const READ_SIZE: usize = 64 * 1024;
+
+        let mut builder = RopeBuilder::new();
+        let mut buf = vec![0; READ_SIZE];
+        // Number of bytes at the start of `buf` left over from a character split between reads
+        let mut carry = 0;
+
+        loop {
+            let read = match r.read(&mut buf[carry..]) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            let filled = carry + read;
+
+            let valid = match std::str::from_utf8(&buf[..filled]) {
+                Ok(s) => s,
+                Err(e) if e.error_len().is_none() => {
+                    // The read ended in the middle of a character, its start is carried over
+                    std::str::from_utf8(&buf[..e.valid_up_to()]).unwrap_or_default()
+                }
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            };
+            builder.append(valid);
+
+            let valid_len = valid.len();
+            buf.copy_within(valid_len..filled, 0);
+            carry = filled - valid_len;
+        }
+
+        if carry != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid utf-8",
+            ));
+        }
+
+        Ok(builder.finish())
+    }
+}
+
+fn ceil_char_boundary(s: &str, idx: usize) -> usize {
+    let mut idx = idx.min(s.len());
+    while !s.is_char_boundary(idx) {
+        idx += 1;
+    }
+    idx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_like_string() {
+        let pieces = ["привет", ", ", "", "мир\n", "hello ", "world", "\n", "👍🏽"];
+        for chunk_size in [1, 2, 3, 7, 64] {
+            let mut b = RopeBuilder::with_chunk_size(chunk_size);
+            for piece in pieces {
+                b.append(piece);
+            }
+            let r = b.finish();
+
+            assert_eq!(r.chars().collect::<String>(), pieces.concat());
+            assert_eq!(r.total_lines(), 2);
+            assert!(r.chunks().all(|c| c.len() <= chunk_size + 3));
+        }
+    }
+
+    #[test]
+    fn builder_balanced() {
+        let mut b = RopeBuilder::with_chunk_size(1);
+        for _ in 0..1024 {
+            b.append("a");
+        }
+        let r = b.finish();
+        assert_eq!(r.len(), 1024);
+        assert_eq!(r.depth(), 11);
+
+        assert_eq!(RopeBuilder::new().finish().len(), 0);
+    }
+
+    #[test]
+    fn from_reader() {
+        // Reads one byte at a time to split multi-byte characters between reads
+        struct ByteReader<'a>(&'a [u8]);
+
+        impl Read for ByteReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let Some((&first, rest)) = self.0.split_first() else {
+                    return Ok(0);
+                };
+                buf[0] = first;
+                self.0 = rest;
+                Ok(1)
+            }
+        }
+
+        let s = "привет, мир\n👍🏽\n";
+        let r = Rope::from_reader(ByteReader(s.as_bytes())).unwrap();
+        assert_eq!(r.chars().collect::<String>(), s);
+
+        let r = Rope::from_reader(s.as_bytes()).unwrap();
+        assert_eq!(r.chars().collect::<String>(), s);
+
+        assert!(Rope::from_reader(&b"ab\xffc"[..]).is_err());
+        assert!(Rope::from_reader(&b"ab\xd0"[..]).is_err());
+    }
+}
@@ -0,0 +1,72 @@
+//! Rolling hash of the `Rope` contents maintained in its nodes
+//!
+//! The hash of a text is the polynomial `b[0] * B^(n - 1) + ... + b[n - 1]` of its bytes modulo
+//! the Mersenne prime `2^61 - 1`. It depends only on the bytes, so ropes with the same contents
+//! have the same checksum regardless of the shape of their trees. The hash is not cryptographic
+
+const MODULUS: u64 = (1 << 61) - 1;
+/// An arbitrary base below the modulus
+const BASE: u64 = 0x1f3d_5b79_a2c4_e681 % MODULUS;
+
+/// Hash of a text along with `BASE` raised to the text length, which makes concatenating hashes
+/// a constant time operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Checksum {
+    pub(crate) hash: u64,
+    pow: u64,
+}
+
+impl Default for Checksum {
+    fn default() -> Self {
+        Self { hash: 0, pow: 1 }
+    }
+}
+
+impl Checksum {
+    /// Hashes the bytes of `s`
+    pub(crate) fn of(s: &str) -> Self {
+        s.bytes().fold(Self::default(), |acc, b| Self {
+            hash: add(mul(acc.hash, BASE), u64::from(b) + 1),
+            pow: mul(acc.pow, BASE),
+        })
+    }
+
+    /// Returns the checksum of the text hashed by `self` followed by the text hashed by `r`
+    #[must_use]
+    pub(crate) const fn merge(self, r: Self) -> Self {
+        Self {
+            hash: add(mul(self.hash, r.pow), r.hash),
+            pow: mul(self.pow, r.pow),
+        }
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+const fn mul(a: u64, b: u64) -> u64 {
+    let product = a as u128 * b as u128;
+    // `2^61` is congruent to one, so the high bits are folded onto the low ones
+    let folded = (product & MODULUS as u128) + (product >> 61);
+    add((folded & MODULUS as u128) as u64, (folded >> 61) as u64)
+}
+
+const fn add(a: u64, b: u64) -> u64 {
+    let sum = a + b;
+    if sum >= MODULUS { sum - MODULUS } else { sum }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_matches_whole_text() {
+        let s = "привет, мир\r\nhello";
+        for split in s.char_indices().map(|(b, _)| b) {
+            let (l, r) = s.split_at(split);
+            assert_eq!(Checksum::of(l).merge(Checksum::of(r)), Checksum::of(s));
+        }
+
+        assert_ne!(Checksum::of("ab"), Checksum::of("ba"));
+        assert_ne!(Checksum::of("a"), Checksum::of("a\0"));
+    }
+}
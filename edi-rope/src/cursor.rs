@@ -0,0 +1,289 @@
+//! A cursor for editing the `Rope` at a position
+
+use std::sync::Arc;
+
+use crate::{Rope, node::Node};
+
+/// A position in the `Rope` that remembers the leaf it points into
+///
+/// Edits and movement that stay within the cached leaf skip the search from the root: the leaf
+/// is spliced in place and only the counters of the nodes on the way to it are adjusted. Edits
+/// that cross leaf boundaries fall back to the regular `Rope` operations
+#[derive(Debug)]
+pub struct Cursor<'a> {
+    rope: &'a mut Rope,
+    position: usize,
+    leaf: Option<CachedLeaf>,
+}
+
+#[derive(Debug)]
+pub(crate) struct CachedLeaf {
+    /// Directions taken from the root to reach the leaf, `true` meaning right
+    pub(crate) path: Vec<bool>,
+    /// Character offset of the cursor inside of the leaf
+    pub(crate) char_offset: usize,
+    /// Byte offset of the cursor inside of the leaf
+    pub(crate) byte_offset: usize,
+    /// Length of the leaf in characters
+    pub(crate) char_len: usize,
+    /// Length of the leaf in bytes
+    pub(crate) byte_len: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub(crate) fn new(rope: &'a mut Rope, position: usize) -> Self {
+        let position = position.min(rope.len());
+        Self {
+            rope,
+            position,
+            leaf: None,
+        }
+    }
+
+    /// Returns the character index the cursor is at
+    #[must_use]
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the rope the cursor edits
+    #[must_use]
+    pub const fn rope(&self) -> &Rope {
+        self.rope
+    }
+
+    /// Moves the cursor to the character index, clamping it to the length of the rope
+    pub fn move_to(&mut self, position: usize) {
+        let position = position.min(self.rope.len());
+        if position != self.position {
+            self.position = position;
+            self.leaf = None;
+        }
+    }
+
+    /// Moves the cursor one character to the left, returning `false` if it is at the start
+    pub fn move_left(&mut self) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+
+        self.position -= 1;
+        let Some(leaf) = self.leaf.as_mut() else {
+            return true;
+        };
+        if leaf.char_offset == 0 {
+            self.leaf = None;
+            return true;
+        }
+
+        let Node::Leaf { value, .. } = self.rope.root.descend(&leaf.path) else {
+            unreachable!("cached path should end in a leaf");
+        };
+        let c = value[..leaf.byte_offset]
+            .chars()
+            .next_back()
+            .unwrap_or_default();
+        leaf.char_offset -= 1;
+        leaf.byte_offset -= c.len_utf8();
+        true
+    }
+
+    /// Moves the cursor one character to the right, returning `false` if it is at the end
+    pub fn move_right(&mut self) -> bool {
+        if self.position == self.rope.len() {
+            return false;
+        }
+
+        self.position += 1;
+        let Some(leaf) = self.leaf.as_mut() else {
+            return true;
+        };
+        if leaf.char_offset == leaf.char_len {
+            self.leaf = None;
+            return true;
+        }
+
+        let Node::Leaf { value, .. } = self.rope.root.descend(&leaf.path) else {
+            unreachable!("cached path should end in a leaf");
+        };
+        let c = value[leaf.byte_offset..].chars().next().unwrap_or_default();
+        leaf.char_offset += 1;
+        leaf.byte_offset += c.len_utf8();
+        true
+    }
+
+    /// Inserts `c` at the cursor position and moves the cursor past it
+    pub fn insert_char(&mut self, c: char) {
+        self.insert_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Inserts `s` at the cursor position and moves the cursor past it
+    pub fn insert_str(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+
+        let char_len = s.chars().count();
+        let position = self.position;
+        let (rope, leaf) = self.cached_leaf();
+        if leaf.byte_len + s.len() > rope.config.max_leaf_bytes {
+            rope.insert_split(position, s);
+            self.position += char_len;
+            self.leaf = None;
+            return;
+        }
+
+        let offset = leaf.byte_offset;
+        let track_metadata = rope.track_metadata;
+        Arc::make_mut(&mut rope.root).splice_leaf(&leaf.path, offset..offset, s, track_metadata);
+        leaf.char_offset += char_len;
+        leaf.byte_offset += s.len();
+        leaf.char_len += char_len;
+        leaf.byte_len += s.len();
+        self.position += char_len;
+    }
+
+    /// Deletes the character before the cursor, returning it
+    ///
+    /// Returns `None` if the cursor is at the start of the rope
+    pub fn delete_char(&mut self) -> Option<char> {
+        if self.position == 0 {
+            return None;
+        }
+
+        let (rope, leaf) = self.cached_leaf();
+        let Node::Leaf { value, .. } = rope.root.descend(&leaf.path) else {
+            unreachable!("cached path should end in a leaf");
+        };
+        let prev = value[..leaf.byte_offset].chars().next_back();
+
+        // Deleting from a neighbouring leaf, emptying a leaf or shrinking it below the minimum
+        // size requires restructuring the tree
+        let restructure = prev.is_none_or(|c| {
+            let new_len = leaf.byte_len - c.len_utf8();
+            new_len == 0 || new_len < rope.config.min_leaf_bytes
+        });
+        let Some(c) = prev.filter(|_| !restructure) else {
+            let c = self.rope.get(self.position - 1)?;
+            self.rope.delete(self.position - 1..self.position);
+            self.position -= 1;
+            self.leaf = None;
+            return Some(c);
+        };
+
+        let end = leaf.byte_offset;
+        let start = end - c.len_utf8();
+        let track_metadata = rope.track_metadata;
+        Arc::make_mut(&mut rope.root).splice_leaf(&leaf.path, start..end, "", track_metadata);
+        leaf.char_offset -= 1;
+        leaf.byte_offset = start;
+        leaf.char_len -= 1;
+        leaf.byte_len -= c.len_utf8();
+        self.position -= 1;
+
+        Some(c)
+    }
+
+    fn cached_leaf(&mut self) -> (&mut Rope, &mut CachedLeaf) {
+        let rope = &mut *self.rope;
+        let leaf = self
+            .leaf
+            .get_or_insert_with(|| locate(&rope.root, self.position));
+        (rope, leaf)
+    }
+}
+
+/// Finds the leaf containing the character index. On leaf boundaries the leaf to the left is
+/// preferred, so that text appended at the end of a leaf stays in it
+pub(crate) fn locate(root: &Node, mut idx: usize) -> CachedLeaf {
+    let mut path = Vec::new();
+    let mut node = root;
+    loop {
+        match node {
+            Node::Leaf {
+                value, char_len, ..
+            } => {
+                return CachedLeaf {
+                    path,
+                    char_offset: idx,
+                    byte_offset: crate::node::char_to_byte_in(value, idx),
+                    char_len: *char_len,
+                    byte_len: value.len(),
+                };
+            }
+            Node::Value { left_len, l, r, .. } => {
+                let go_left = (idx <= *left_len && *left_len > 0) || r.is_none();
+                let child = if go_left { l } else { r };
+                let Some(child) = child.as_deref() else {
+                    unreachable!("value nodes should have at least one child");
+                };
+                if !go_left {
+                    idx -= left_len;
+                }
+                path.push(!go_left);
+                node = child;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_contents(rope: &Rope, expected: &str) {
+        assert_eq!(rope.chars().collect::<String>(), expected);
+        assert_eq!(rope.len(), expected.chars().count());
+        assert_eq!(rope.byte_len(), expected.len());
+        assert_eq!(rope.total_lines(), expected.matches('\n').count());
+        assert_eq!(rope.assert_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn insert_and_delete() {
+        let mut rope = Rope::from_str_chunked("hello world", 4);
+        let mut cursor = rope.cursor(5);
+        for c in ",\nдорогой".chars() {
+            cursor.insert_char(c);
+        }
+        assert_eq!(cursor.position(), 14);
+        assert_eq!(cursor.delete_char(), Some('й'));
+        assert_eq!(cursor.delete_char(), Some('о'));
+        assert_eq!(cursor.position(), 12);
+        assert_contents(&rope, "hello,\nдорог world");
+    }
+
+    #[test]
+    fn delete_across_leaves() {
+        let mut rope = Rope::from_str_chunked("ab\ncd\nef", 2);
+        let mut cursor = rope.cursor(8);
+        let mut deleted = String::new();
+        while let Some(c) = cursor.delete_char() {
+            deleted.insert(0, c);
+        }
+        assert_eq!(deleted, "ab\ncd\nef");
+        assert_contents(&rope, "");
+    }
+
+    #[test]
+    fn movement() {
+        let mut rope = Rope::from_str_chunked("aб✓d", 2);
+        let mut cursor = rope.cursor(0);
+        cursor.insert_char('x');
+        assert!(cursor.move_right());
+        assert!(cursor.move_right());
+        cursor.insert_char('y');
+        assert!(cursor.move_left());
+        assert!(cursor.move_left());
+        cursor.insert_char('z');
+        cursor.move_to(100);
+        assert_eq!(cursor.position(), 7);
+        assert!(!cursor.move_right());
+        cursor.insert_char('!');
+        assert_contents(&rope, "xazбy✓d!");
+
+        let mut cursor = rope.cursor(0);
+        assert!(!cursor.move_left());
+        assert_eq!(cursor.delete_char(), None);
+    }
+}
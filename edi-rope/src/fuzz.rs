@@ -0,0 +1,246 @@
+//! Randomized tests that apply sequences of edits to a `Rope` and a `String` model, comparing
+//! the two after every step
+//!
+//! Failures print the seed and the step, so a failing sequence can be replayed by running a
+//! single seed
+
+use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+use crate::{Rope, RopeBuilder, RopeConfig, checksum::Checksum, iter::LineInfo};
+
+const SEEDS: u64 = 48;
+const STEPS: usize = 120;
+
+/// Pieces of text that edits are made of, chosen to hit multi-byte characters, `\r\n` pairs and
+/// line breaks on leaf boundaries
+const PIECES: &[&str] = &[
+    "a",
+    "hello",
+    " world",
+    "\n",
+    "\r\n",
+    "\r",
+    "\n\n",
+    "мир",
+    "👍🏽",
+    "é",
+    "\t",
+    "lorem ipsum\n",
+];
+
+#[derive(Debug)]
+enum Op {
+    Insert(usize, String),
+    Delete(usize, usize),
+    Replace(usize, usize, String),
+    SplitConcat(usize),
+    ConcatNew(String),
+    Type(usize, String),
+    Backspace(usize, usize),
+}
+
+struct Harness {
+    rng: SmallRng,
+    rope: Rope,
+    model: String,
+    track_metadata: bool,
+}
+
+impl Harness {
+    fn new(seed: u64) -> Self {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let max_leaf_bytes = rng.gen_range(1..24);
+        let config = RopeConfig {
+            max_leaf_bytes,
+            min_leaf_bytes: rng.gen_range(0..=max_leaf_bytes),
+        };
+        let track_metadata = rng.gen_bool(0.5);
+
+        let mut model = String::new();
+        for _ in 0..rng.gen_range(0..16) {
+            model.push_str(PIECES[rng.gen_range(0..PIECES.len())]);
+        }
+        let mut builder = RopeBuilder::with_config(config);
+        builder.track_metadata(track_metadata).append(&model);
+
+        Self {
+            rng,
+            rope: builder.finish(),
+            model,
+            track_metadata,
+        }
+    }
+
+    fn text(&mut self) -> String {
+        (0..self.rng.gen_range(0..4))
+            .map(|_| PIECES[self.rng.gen_range(0..PIECES.len())])
+            .collect()
+    }
+
+    fn index(&mut self) -> usize {
+        self.rng.gen_range(0..=self.model.chars().count())
+    }
+
+    fn range(&mut self) -> (usize, usize) {
+        let (a, b) = (self.index(), self.index());
+        (a.min(b), a.max(b))
+    }
+
+    fn random_op(&mut self) -> Op {
+        match self.rng.gen_range(0..7) {
+            0 => Op::Insert(self.index(), self.text()),
+            1 => {
+                let (start, end) = self.range();
+                Op::Delete(start, end)
+            }
+            2 => {
+                let (start, end) = self.range();
+                Op::Replace(start, end, self.text())
+            }
+            3 => Op::SplitConcat(self.index()),
+            4 => Op::ConcatNew(self.text()),
+            5 => Op::Type(self.index(), self.text()),
+            _ => {
+                let idx = self.index();
+                Op::Backspace(idx, self.rng.gen_range(0..=idx.min(8)))
+            }
+        }
+    }
+
+    fn apply(&mut self, op: &Op) {
+        let byte = |model: &str, idx: usize| {
+            model
+                .char_indices()
+                .nth(idx)
+                .map_or(model.len(), |(b, _)| b)
+        };
+
+        match op {
+            Op::Insert(idx, s) => {
+                self.rope.insert(*idx, s);
+                self.model.insert_str(byte(&self.model, *idx), s);
+            }
+            Op::Delete(start, end) => {
+                let removed = self.rope.remove(start..end);
+                let range = byte(&self.model, *start)..byte(&self.model, *end);
+                assert_eq!(removed, self.model[range.clone()]);
+                self.model.replace_range(range, "");
+            }
+            Op::Replace(start, end, s) => {
+                self.rope.replace(start..end, s);
+                let range = byte(&self.model, *start)..byte(&self.model, *end);
+                self.model.replace_range(range, s);
+            }
+            Op::SplitConcat(idx) => {
+                let (mut left, right) = self.rope.split(*idx);
+                let split = byte(&self.model, *idx);
+                assert_eq!(left, self.model[..split]);
+                assert_eq!(right, self.model[split..]);
+                left.concat(right);
+                self.rope = left;
+            }
+            Op::ConcatNew(s) => {
+                let mut builder = RopeBuilder::with_config(self.rope.config());
+                builder.track_metadata(self.track_metadata).append(s);
+                self.rope.concat(builder.finish());
+                self.model.push_str(s);
+            }
+            Op::Type(idx, s) => {
+                let mut cursor = self.rope.cursor(*idx);
+                for c in s.chars() {
+                    cursor.insert_char(c);
+                }
+                self.model.insert_str(byte(&self.model, *idx), s);
+            }
+            Op::Backspace(idx, n) => {
+                let mut cursor = self.rope.cursor(*idx);
+                for _ in 0..*n {
+                    cursor.delete_char();
+                }
+                let range = byte(&self.model, idx - n)..byte(&self.model, *idx);
+                self.model.replace_range(range, "");
+            }
+        }
+    }
+
+    fn check(&mut self) {
+        let (rope, model) = (&self.rope, self.model.as_str());
+        assert_eq!(rope.assert_invariants(), Ok(()));
+        assert_eq!(rope.to_string(), model);
+        assert_eq!(
+            rope.chars_rev().collect::<String>(),
+            model.chars().rev().collect::<String>()
+        );
+        assert_eq!(rope.len(), model.chars().count());
+        assert_eq!(rope.byte_len(), model.len());
+        assert_eq!(rope.total_lines(), model.matches('\n').count());
+        assert_eq!(rope.checksum(), Checksum::of(model).hash);
+        if self.track_metadata {
+            assert_eq!(rope.metadata(), Some(crate::Metadata::of(model)));
+        }
+
+        let lines = model_lines(model);
+        assert_eq!(rope.lines().collect::<Vec<_>>(), lines);
+        for (n, line) in lines.iter().enumerate() {
+            assert_eq!(rope.line_to_char(n), line.character_offset);
+            assert_eq!(rope.char_to_line(line.character_offset), n);
+        }
+        let n = self.rng.gen_range(0..=lines.len());
+        assert_eq!(rope.lines_at(n).collect::<Vec<_>>(), lines[n..]);
+        assert_eq!(rope.line(n).as_ref(), lines.get(n));
+        assert_eq!(
+            rope.line_info(n)
+                .map(|line| (line.character_offset, line.length)),
+            lines
+                .get(n)
+                .map(|line| (line.character_offset, line.length))
+        );
+
+        let idx = self.rng.gen_range(0..=rope.len());
+        assert_eq!(rope.get(idx), model.chars().nth(idx));
+        assert_eq!(
+            rope.chars_at(idx).collect::<String>(),
+            model.chars().skip(idx).collect::<String>()
+        );
+    }
+}
+
+/// Splits `s` into lines the way `Lines` does: `\n` and `\r\n` end a line, and the text after
+/// the last line break is a line only if it is not empty
+fn model_lines(s: &str) -> Vec<LineInfo> {
+    let mut lines = Vec::new();
+    let mut character_offset = 0;
+    for (line_number, segment) in s.split_inclusive('\n').enumerate() {
+        let contents = segment
+            .strip_suffix('\n')
+            .map_or(segment, |line| line.strip_suffix('\r').unwrap_or(line));
+        lines.push(LineInfo {
+            line_number,
+            character_offset,
+            length: contents.chars().count(),
+            contents: contents.to_owned(),
+        });
+        character_offset += segment.chars().count();
+    }
+    lines
+}
+
+#[test]
+fn random_edits_match_string() {
+    for seed in 0..SEEDS {
+        let mut harness = Harness::new(seed);
+        harness.check();
+        for step in 0..STEPS {
+            let op = harness.random_op();
+            let before = harness.model.clone();
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                harness.apply(&op);
+                harness.check();
+            }));
+            assert!(
+                result.is_ok(),
+                "seed {seed}, step {step}: {op:?} applied to {before:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! Validation of the `Rope`'s inner tree
+
+use std::fmt;
+
+use crate::{Rope, checksum::Checksum, metadata::Metadata, node::Node};
+
+/// A broken invariant of the rope's tree, returned by `Rope::assert_invariants`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantError {
+    /// Directions taken from the root to reach the offending node, `true` meaning right
+    pub path: Vec<bool>,
+    /// The invariant that does not hold
+    pub kind: InvariantErrorKind,
+}
+
+/// Kinds of invariants of the rope's tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantErrorKind {
+    /// A counter stored in a node differs from the value computed from its subtree
+    Counter {
+        /// Name of the node's field
+        field: &'static str,
+        /// The value stored in the node
+        stored: usize,
+        /// The value computed from the subtree
+        actual: usize,
+    },
+    /// A leaf is longer than the maximum leaf size of the rope
+    LeafTooLong {
+        /// Length of the leaf in bytes
+        bytes: usize,
+        /// The largest allowed length in bytes
+        max: usize,
+    },
+    /// A leaf other than the root is empty
+    EmptyLeaf,
+    /// A value node has no left or right child
+    MissingChild,
+    /// The heights of a node's children differ by more than one
+    Unbalanced {
+        /// Height of the left child
+        left: usize,
+        /// Height of the right child
+        right: usize,
+    },
+    /// The statistics stored in a node of a rope that tracks `Metadata` are missing or wrong
+    Metadata,
+    /// The hash stored in a node does not match the text of its subtree
+    Checksum,
+}
+
+impl fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("node at ")?;
+        if self.path.is_empty() {
+            f.write_str("root")?;
+        }
+        for &right in &self.path {
+            f.write_str(if right { "r" } else { "l" })?;
+        }
+        f.write_str(": ")?;
+
+        match &self.kind {
+            InvariantErrorKind::Counter {
+                field,
+                stored,
+                actual,
+            } => write!(f, "`{field}` is {stored}, but the subtree has {actual}"),
+            InvariantErrorKind::LeafTooLong { bytes, max } => {
+                write!(f, "leaf is {bytes} bytes long, at most {max} are allowed")
+            }
+            InvariantErrorKind::EmptyLeaf => f.write_str("leaf is empty"),
+            InvariantErrorKind::MissingChild => f.write_str("value node is missing a child"),
+            InvariantErrorKind::Unbalanced { left, right } => write!(
+                f,
+                "children have heights {left} and {right}, which differ by more than one"
+            ),
+            InvariantErrorKind::Metadata => f.write_str("metadata does not match the text"),
+            InvariantErrorKind::Checksum => f.write_str("checksum does not match the text"),
+        }
+    }
+}
+
+impl std::error::Error for InvariantError {}
+
+/// Totals of a subtree computed while validating it
+struct Totals {
+    chars: usize,
+    bytes: usize,
+    newlines: usize,
+    height: usize,
+    metadata: Metadata,
+    checksum: Checksum,
+}
+
+struct Checker {
+    path: Vec<bool>,
+    /// Largest leaf length in bytes, a character may cross the configured maximum
+    max_leaf_bytes: usize,
+    track_metadata: bool,
+}
+
+impl Checker {
+    fn error(&self, kind: InvariantErrorKind) -> InvariantError {
+        InvariantError {
+            path: self.path.clone(),
+            kind,
+        }
+    }
+
+    fn counter(
+        &self,
+        field: &'static str,
+        stored: usize,
+        actual: usize,
+    ) -> Result<(), InvariantError> {
+        if stored == actual {
+            return Ok(());
+        }
+
+        Err(self.error(InvariantErrorKind::Counter {
+            field,
+            stored,
+            actual,
+        }))
+    }
+
+    fn check(&mut self, node: &Node) -> Result<Totals, InvariantError> {
+        let totals = match node {
+            Node::Leaf {
+                value,
+                char_len,
+                newlines,
+                ..
+            } => {
+                let totals = Totals {
+                    chars: value.chars().count(),
+                    bytes: value.len(),
+                    newlines: value.bytes().filter(|&b| b == b'\n').count(),
+                    height: 0,
+                    metadata: Metadata::of(value),
+                    checksum: Checksum::of(value),
+                };
+                self.counter("char_len", *char_len, totals.chars)?;
+                self.counter("newlines", *newlines, totals.newlines)?;
+
+                if value.is_empty() && !self.path.is_empty() {
+                    return Err(self.error(InvariantErrorKind::EmptyLeaf));
+                }
+                if value.len() > self.max_leaf_bytes {
+                    return Err(self.error(InvariantErrorKind::LeafTooLong {
+                        bytes: value.len(),
+                        max: self.max_leaf_bytes,
+                    }));
+                }
+
+                totals
+            }
+            Node::Value {
+                left_len,
+                left_bytes,
+                left_newlines,
+                height,
+                l,
+                r,
+                ..
+            } => {
+                let (Some(l), Some(r)) = (l, r) else {
+                    return Err(self.error(InvariantErrorKind::MissingChild));
+                };
+
+                self.path.push(false);
+                let left = self.check(l)?;
+                self.path.pop();
+                self.path.push(true);
+                let right = self.check(r)?;
+                self.path.pop();
+
+                self.counter("left_len", *left_len, left.chars)?;
+                self.counter("left_bytes", *left_bytes, left.bytes)?;
+                self.counter("left_newlines", *left_newlines, left.newlines)?;
+                let actual_height = 1 + left.height.max(right.height);
+                self.counter("height", *height, actual_height)?;
+                if left.height.abs_diff(right.height) > 1 {
+                    return Err(self.error(InvariantErrorKind::Unbalanced {
+                        left: left.height,
+                        right: right.height,
+                    }));
+                }
+
+                Totals {
+                    chars: left.chars + right.chars,
+                    bytes: left.bytes + right.bytes,
+                    newlines: left.newlines + right.newlines,
+                    height: actual_height,
+                    metadata: left.metadata.merge(right.metadata),
+                    checksum: left.checksum.merge(right.checksum),
+                }
+            }
+        };
+
+        if self.track_metadata && node.metadata() != Some(totals.metadata) {
+            return Err(self.error(InvariantErrorKind::Metadata));
+        }
+        if node.checksum() != totals.checksum {
+            return Err(self.error(InvariantErrorKind::Checksum));
+        }
+
+        Ok(totals)
+    }
+}
+
+impl Rope {
+    /// Validates the rope's tree: the counters and checksums stored in the nodes, the leaf sizes,
+    /// the balance of the tree and the tracked `Metadata`
+    ///
+    /// Walks the whole tree, so it is meant for tests and bug reports rather than regular use
+    ///
+    /// # Errors
+    ///
+    /// Returns the first broken invariant found
+    pub fn assert_invariants(&self) -> Result<(), InvariantError> {
+        let mut checker = Checker {
+            path: Vec::new(),
+            max_leaf_bytes: self.config.max_leaf_bytes + 3,
+            track_metadata: self.track_metadata,
+        };
+        checker.check(&self.root).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{RopeBuilder, RopeConfig};
+
+    #[test]
+    fn valid_after_edits() {
+        let mut builder = RopeBuilder::with_chunk_size(3);
+        builder
+            .track_metadata(true)
+            .append("hello, мир\nhow are you?\n");
+        let mut r = builder.finish();
+        assert_eq!(r.assert_invariants(), Ok(()));
+
+        r.insert(4, "lorem ipsum dolor sit amet");
+        r.delete(10..20);
+        r.replace(0..2, "\r\n");
+        let (mut left, right) = r.split(7);
+        left.concat(right);
+        assert_eq!(left.assert_invariants(), Ok(()));
+        assert_eq!(Rope::new().assert_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn reports_broken_invariants() {
+        let rope = |root: Node| Rope {
+            root: Arc::new(root),
+            track_metadata: false,
+            config: RopeConfig {
+                max_leaf_bytes: 4,
+                min_leaf_bytes: 0,
+            },
+        };
+
+        let mut node = Node::new_value(Node::new_leaf("ab"), Node::new_leaf("c\nd"));
+        if let Node::Value { left_newlines, .. } = &mut node {
+            *left_newlines = 1;
+        }
+        let err = rope(node).assert_invariants().unwrap_err();
+        assert!(err.path.is_empty());
+        assert_eq!(
+            err.kind,
+            InvariantErrorKind::Counter {
+                field: "left_newlines",
+                stored: 1,
+                actual: 0
+            }
+        );
+
+        let node = Node::new_value(Node::new_leaf("ab"), Node::new_leaf("too long"));
+        let err = rope(node).assert_invariants().unwrap_err();
+        assert_eq!(err.path, [true]);
+        assert_eq!(
+            err.kind,
+            InvariantErrorKind::LeafTooLong { bytes: 8, max: 7 }
+        );
+        assert_eq!(
+            err.to_string(),
+            "node at r: leaf is 8 bytes long, at most 7 are allowed"
+        );
+
+        let deep = Node::new_value(
+            Node::new_value(Node::new_leaf("a"), Node::new_leaf("b")),
+            Node::new_leaf("c"),
+        );
+        let node = Node::new_value(deep, Node::new_leaf("d"));
+        let err = rope(node).assert_invariants().unwrap_err();
+        assert_eq!(
+            err.kind,
+            InvariantErrorKind::Unbalanced { left: 2, right: 0 }
+        );
+
+        let node = Node::new_value(Node::new_leaf(""), Node::new_leaf("a"));
+        let err = rope(node).assert_invariants().unwrap_err();
+        assert_eq!(err.kind, InvariantErrorKind::EmptyLeaf);
+    }
+}
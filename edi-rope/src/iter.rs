@@ -1,10 +1,15 @@
 //! `Rope` iterators
 
+#[cfg(feature = "graphemes")]
+use std::borrow::Cow;
 use std::{
     iter::{Skip, Take},
     ops::Range,
 };
 
+#[cfg(feature = "graphemes")]
+use unicode_segmentation::GraphemeCursor;
+
 use super::Node;
 
 #[derive(Debug)]
@@ -102,10 +107,63 @@ impl<'a> Chars<'a> {
             self.current_node_offset_b = 0;
         }
 
-        while target - self.global_line_offset > 0 {
-            if self.next().is_none() {
+        // Newlines within a leaf are searched for bytewise instead of decoding every character
+        while target > self.global_line_offset {
+            if !self.expand_to_leaf() {
                 return;
             }
+            let Some(CharsNode {
+                tree_node: Node::Leaf { value, .. },
+                ..
+            }) = self.stack.last()
+            else {
+                unreachable!("expand_to_leaf leaves a leaf on top of the stack");
+            };
+
+            let rest = &value[self.current_node_offset_b..];
+            let needed = target - self.global_line_offset;
+            let Some((end, _)) = rest.match_indices('\n').nth(needed - 1) else {
+                self.global_character_offset += rest.chars().count();
+                self.global_line_offset += rest.matches('\n').count();
+                self.current_node_offset_b = 0;
+                self.stack.pop();
+                continue;
+            };
+
+            let skipped = &rest[..=end];
+            self.global_character_offset += skipped.chars().count();
+            self.global_line_offset += needed;
+            self.current_node_offset_b += skipped.len();
+        }
+    }
+
+    /// Replaces value nodes on top of the stack with the leftmost leaves of their right subtrees,
+    /// without consuming any characters. Returns `false` if the stack is exhausted
+    fn expand_to_leaf(&mut self) -> bool {
+        loop {
+            let Some(node) = self.stack.last() else {
+                return false;
+            };
+            let Node::Value {
+                left_len,
+                left_newlines,
+                r,
+                ..
+            } = node.tree_node
+            else {
+                return true;
+            };
+
+            self.current_node_offset_b = 0;
+            let offs = node.offset_from_start + left_len;
+            self.global_character_offset = offs;
+            let newlines = node.newlines_from_start + left_newlines;
+            self.global_line_offset = newlines;
+            self.stack.pop();
+            self.push_left(
+                r.as_ref()
+                    .map(|tree_node| CharsNode::new(tree_node, offs, newlines)),
+            );
         }
     }
 
@@ -228,6 +286,7 @@ impl Iterator for Substring<'_> {
 
 /// An iterator over lines of the `Rope`
 ///
+/// Both `\n` and `\r\n` are treated as line breaks and are not included in the yielded lines.
 /// Can be modified to not parse the contents of the string into `contents` field of `LineInfo`
 /// returned
 #[derive(Debug)]
@@ -258,6 +317,15 @@ impl<'a> Lines<'a> {
         Self::from_raw(iter)
     }
 
+    /// Initializes `Lines` starting at the `n`th line
+    #[must_use]
+    pub(super) fn new_at(node: &'a Node, n: usize) -> Self {
+        let mut iter = Chars::new(node);
+        iter.skip_lines(n);
+
+        Self::from_raw(iter)
+    }
+
     const fn from_raw(iter: Chars<'a>) -> Self {
         Self {
             iter,
@@ -280,15 +348,21 @@ impl Iterator for Lines<'_> {
         let character_offset = self.iter.characters_consumed();
 
         let mut met_nl = false;
+        let mut prev_cr = false;
         let (mut contents, mut length) = (String::new(), 0);
         for c in self.iter.by_ref() {
             length += 1;
             if c == '\n' {
                 length -= 1;
                 met_nl = true;
+                if prev_cr {
+                    length -= 1;
+                    contents.pop();
+                }
                 break;
             }
 
+            prev_cr = c == '\r';
             if self.parse_contents {
                 contents.push(c);
             }
@@ -318,11 +392,272 @@ impl Iterator for Lines<'_> {
     }
 }
 
+/// An iterator over the string slices stored in `Rope`'s leaves, in order
+///
+/// Empty leaves are skipped. Concatenating all the yielded slices produces the string
+/// representation of the `Rope`. Can be iterated from both ends
+#[derive(Debug)]
+pub struct Chunks<'a> {
+    front: Vec<&'a Node>,
+    back: Vec<&'a Node>,
+    front_leaf: &'a str,
+    back_leaf: &'a str,
+    remaining_b: usize,
+}
+
+impl<'a> Chunks<'a> {
+    #[must_use]
+    pub(super) fn new(node: &'a Node) -> Self {
+        Self::new_range(node, 0..node.full_bytes())
+    }
+
+    /// Initializes `Chunks` yielding only the bytes in `range`. The first and the last yielded
+    /// slices are trimmed to the range
+    #[must_use]
+    pub(super) fn new_range(node: &'a Node, range: Range<usize>) -> Self {
+        let (front, front_leaf) = Self::descend_front(node, range.start);
+        let (back, back_leaf) = Self::descend_back(node, range.end);
+        Self {
+            front,
+            back,
+            front_leaf,
+            back_leaf,
+            remaining_b: range.len(),
+        }
+    }
+
+    /// Descends to the leaf containing the byte at `offset`, collecting the right siblings
+    fn descend_front(mut node: &'a Node, mut offset: usize) -> (Vec<&'a Node>, &'a str) {
+        let mut stack = vec![];
+        loop {
+            match node {
+                Node::Leaf { value, .. } => return (stack, &value[offset.min(value.len())..]),
+                Node::Value {
+                    left_bytes, l, r, ..
+                } => {
+                    if let Some(l) = l.as_deref().filter(|_| offset < *left_bytes) {
+                        stack.extend(r.as_deref());
+                        node = l;
+                    } else if let Some(r) = r.as_deref() {
+                        offset = offset.saturating_sub(*left_bytes);
+                        node = r;
+                    } else {
+                        return (stack, "");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Descends to the leaf containing the byte before `offset`, collecting the left siblings
+    fn descend_back(mut node: &'a Node, mut offset: usize) -> (Vec<&'a Node>, &'a str) {
+        let mut stack = vec![];
+        loop {
+            match node {
+                Node::Leaf { value, .. } => return (stack, &value[..offset.min(value.len())]),
+                Node::Value {
+                    left_bytes, l, r, ..
+                } => {
+                    if let Some(r) = r.as_deref().filter(|_| offset > *left_bytes) {
+                        stack.extend(l.as_deref());
+                        offset -= left_bytes;
+                        node = r;
+                    } else if let Some(l) = l.as_deref() {
+                        node = l;
+                    } else {
+                        return (stack, "");
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.remaining_b > 0 {
+            let leaf = std::mem::take(&mut self.front_leaf);
+            if !leaf.is_empty() {
+                let chunk = &leaf[..leaf.len().min(self.remaining_b)];
+                self.remaining_b -= chunk.len();
+                return Some(chunk);
+            }
+
+            let mut node = self.front.pop()?;
+            loop {
+                match node {
+                    Node::Leaf { value, .. } => {
+                        self.front_leaf = value;
+                        break;
+                    }
+                    Node::Value { l, r, .. } => {
+                        self.front.extend(r.as_deref());
+                        let Some(l) = l.as_deref() else {
+                            break;
+                        };
+                        node = l;
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl DoubleEndedIterator for Chunks<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while self.remaining_b > 0 {
+            let leaf = std::mem::take(&mut self.back_leaf);
+            if !leaf.is_empty() {
+                let chunk = &leaf[leaf.len().saturating_sub(self.remaining_b)..];
+                self.remaining_b -= chunk.len();
+                return Some(chunk);
+            }
+
+            let mut node = self.back.pop()?;
+            loop {
+                match node {
+                    Node::Leaf { value, .. } => {
+                        self.back_leaf = value;
+                        break;
+                    }
+                    Node::Value { l, r, .. } => {
+                        self.back.extend(l.as_deref());
+                        let Some(r) = r.as_deref() else {
+                            break;
+                        };
+                        node = r;
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// A double-ended iterator over characters of a part of the `Rope`, that is returned after
+/// calling `chars_at` or `chars_rev` methods of `Rope`
+///
+/// Unlike `Chars`, it does not keep track of the line and character offsets
+#[derive(Debug)]
+pub struct CharsAt<'a> {
+    chunks: Chunks<'a>,
+    front: std::str::Chars<'a>,
+    back: std::str::Chars<'a>,
+}
+
+impl<'a> CharsAt<'a> {
+    #[must_use]
+    pub(super) fn new(node: &'a Node, range: Range<usize>) -> Self {
+        Self {
+            chunks: Chunks::new_range(node, range),
+            front: "".chars(),
+            back: "".chars(),
+        }
+    }
+}
+
+impl Iterator for CharsAt<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(c) = self.front.next() {
+                return Some(c);
+            }
+
+            let Some(chunk) = self.chunks.next() else {
+                return self.back.next();
+            };
+            self.front = chunk.chars();
+        }
+    }
+}
+
+impl DoubleEndedIterator for CharsAt<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(c) = self.back.next_back() {
+                return Some(c);
+            }
+
+            let Some(chunk) = self.chunks.next_back() else {
+                return self.front.next_back();
+            };
+            self.back = chunk.chars();
+        }
+    }
+}
+
+/// An iterator over extended grapheme clusters of the `Rope`
+///
+/// Graphemes that lie within a single leaf are borrowed, the ones spanning multiple leaves are
+/// collected into an owned string
+#[cfg(feature = "graphemes")]
+#[derive(Debug)]
+pub struct Graphemes<'a> {
+    root: &'a Node,
+    offset: usize,
+    len: usize,
+}
+
+#[cfg(feature = "graphemes")]
+impl<'a> Graphemes<'a> {
+    #[must_use]
+    pub(super) fn new(n: &'a Node) -> Self {
+        Self {
+            root: n,
+            offset: 0,
+            len: n.full_bytes(),
+        }
+    }
+}
+
+#[cfg(feature = "graphemes")]
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // A fresh cursor is used for every grapheme, as a reused one loses track of regional
+        // indicator pairs split between leaves
+        let start = self.offset;
+        let mut cursor = GraphemeCursor::new(start, self.len, true);
+        let end = self.root.next_grapheme_boundary(&mut cursor)?;
+        self.offset = end;
+
+        let (chunk, chunk_start) = self.root.chunk_at_byte(start)?;
+        if end <= chunk_start + chunk.len() {
+            return Some(Cow::Borrowed(
+                &chunk[start - chunk_start..end - chunk_start],
+            ));
+        }
+
+        let mut grapheme = String::with_capacity(end - start);
+        let mut offset = start;
+        while offset < end {
+            let (chunk, chunk_start) = self.root.chunk_at_byte(offset)?;
+            let chunk_end = (chunk_start + chunk.len()).min(end);
+            grapheme.push_str(&chunk[offset - chunk_start..chunk_end - chunk_start]);
+            offset = chunk_end;
+        }
+
+        Some(Cow::Owned(grapheme))
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::iter_nth_zero, clippy::iter_skip_next)]
 mod tests {
 
-    use crate::{Chars, Node, Rope};
+    use std::sync::Arc;
+
+    use crate::{Chars, Chunks, Node, Rope, RopeConfig, checksum::Checksum};
 
+    #[allow(clippy::many_single_char_names)]
     fn example_rope() -> Rope {
         let m = Node::new_leaf("s");
         let n = Node::new_leaf(" Simon");
@@ -330,43 +665,71 @@ mod tests {
         let k = Node::new_leaf("me i");
         let g = Node::Value {
             left_len: 2,
+            left_bytes: 2,
             left_newlines: 0,
-            l: Some(Box::new(j)),
-            r: Some(Box::new(k)),
+            height: 1,
+            l: Some(Arc::new(j)),
+            r: Some(Arc::new(k)),
+            metadata: None,
+            checksum: Checksum::default(),
         };
         let h = Node::Value {
             left_len: 1,
+            left_bytes: 1,
             left_newlines: 0,
-            l: Some(Box::new(m)),
-            r: Some(Box::new(n)),
+            height: 1,
+            l: Some(Arc::new(m)),
+            r: Some(Arc::new(n)),
+            metadata: None,
+            checksum: Checksum::default(),
         };
         let e = Node::new_leaf("Hello ");
         let f = Node::new_leaf("my ");
         let c = Node::Value {
             left_len: 6,
+            left_bytes: 6,
             left_newlines: 0,
-            l: Some(Box::new(e)),
-            r: Some(Box::new(f)),
+            height: 1,
+            l: Some(Arc::new(e)),
+            r: Some(Arc::new(f)),
+            metadata: None,
+            checksum: Checksum::default(),
         };
         let d = Node::Value {
             left_len: 6,
+            left_bytes: 6,
             left_newlines: 0,
-            l: Some(Box::new(g)),
-            r: Some(Box::new(h)),
+            height: 2,
+            l: Some(Arc::new(g)),
+            r: Some(Arc::new(h)),
+            metadata: None,
+            checksum: Checksum::default(),
         };
         let b = Node::Value {
             left_len: 9,
+            left_bytes: 9,
             left_newlines: 0,
-            l: Some(Box::new(c)),
-            r: Some(Box::new(d)),
+            height: 3,
+            l: Some(Arc::new(c)),
+            r: Some(Arc::new(d)),
+            metadata: None,
+            checksum: Checksum::default(),
         };
         let a = Node::Value {
             left_len: 22,
+            left_bytes: 22,
             left_newlines: 0,
-            l: Some(Box::new(b)),
+            height: 4,
+            l: Some(Arc::new(b)),
             r: None,
+            metadata: None,
+            checksum: Checksum::default(),
         };
-        Rope { root: Box::new(a) }
+        Rope {
+            root: Arc::new(a),
+            track_metadata: false,
+            config: RopeConfig::default(),
+        }
     }
 
     #[test]
@@ -492,13 +855,13 @@ mod tests {
         assert!(lines.next().is_none());
 
         let mut rope = Rope::from("\n");
-        rope.insert(0, &"c");
-        rope.insert(2, &"c");
+        rope.insert(0, "c");
+        rope.insert(2, "c");
         let mut lines = rope.lines();
         assert_eq!(lines.nth(1).unwrap().length, 1);
 
         let mut rope = Rope::from("\nHe");
-        rope.insert(0, &"c");
+        rope.insert(0, "c");
         assert_eq!(rope.total_lines(), 1);
         let mut lines = rope.lines();
         assert_eq!(lines.nth(1).unwrap().length, 2);
@@ -525,4 +888,109 @@ mod tests {
         let mut chars = rope.chars().skip(7);
         assert_eq!(chars.next(), Some('y'));
     }
+
+    #[test]
+    fn chunks_range() {
+        let r = Rope::from_str_chunked("привет, мир\nhow are you?", 3);
+        let s = r.chunks().collect::<String>();
+        for start in 0..=s.len() {
+            for end in start..=s.len() {
+                if !s.is_char_boundary(start) || !s.is_char_boundary(end) {
+                    continue;
+                }
+
+                let chunks = Chunks::new_range(&r.root, start..end);
+                assert_eq!(chunks.collect::<String>(), &s[start..end]);
+                let mut rev: Vec<_> = Chunks::new_range(&r.root, start..end).rev().collect();
+                rev.reverse();
+                assert_eq!(rev.concat(), &s[start..end]);
+            }
+        }
+    }
+
+    #[test]
+    fn chars_at() {
+        let s = "привет, мир\nhow are you?";
+        let r = Rope::from_str_chunked(s, 3);
+        for idx in 0..=r.len() {
+            let expected: String = s.chars().skip(idx).collect();
+            assert_eq!(r.chars_at(idx).collect::<String>(), expected);
+            assert_eq!(
+                r.chars_at(idx).rev().collect::<String>(),
+                expected.chars().rev().collect::<String>()
+            );
+
+            let mut it = r.chars_at(idx);
+            let mut front = String::new();
+            let mut back = String::new();
+            loop {
+                match (it.next(), it.next_back()) {
+                    (Some(f), Some(b)) => {
+                        front.push(f);
+                        back.insert(0, b);
+                    }
+                    (Some(f), None) => front.push(f),
+                    _ => break,
+                }
+            }
+            assert_eq!(front + &back, expected);
+        }
+
+        assert_eq!(
+            r.chars_rev().collect::<String>(),
+            s.chars().rev().collect::<String>()
+        );
+        assert_eq!(Rope::new().chars_rev().count(), 0);
+        assert_eq!(r.chars_at(100).count(), 0);
+    }
+
+    #[test]
+    fn lines_at() {
+        let s = "привет\r\n\n\nмир\nhow are\r\nyou?\n\n";
+        for chunk_size in [1, 3, 64] {
+            let r = Rope::from_str_chunked(s, chunk_size);
+            let lines: Vec<_> = r.lines().collect();
+            for n in 0..lines.len() + 2 {
+                assert_eq!(
+                    r.lines_at(n).collect::<Vec<_>>(),
+                    lines.iter().skip(n).cloned().collect::<Vec<_>>(),
+                    "chunk_size: {chunk_size}, n: {n}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn lines_crlf() {
+        let r = Rope::from_str_chunked("a\r\nbc\r\n\r\nd\r", 1);
+        let lines: Vec<_> = r
+            .lines()
+            .map(|l| (l.character_offset, l.length, l.contents))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (0, 1, "a".to_owned()),
+                (3, 2, "bc".to_owned()),
+                (7, 0, String::new()),
+                (9, 2, "d\r".to_owned()),
+            ]
+        );
+
+        let lengths: Vec<_> = r.lines().parse_contents(false).map(|l| l.length).collect();
+        assert_eq!(lengths, [1, 2, 0, 2]);
+    }
+
+    #[test]
+    fn chunks() {
+        let r = example_rope();
+        let chunks: Vec<_> = r.chunks().collect();
+        assert_eq!(chunks, ["Hello ", "my ", "na", "me i", "s", " Simon"]);
+
+        let r = Rope::from_str_chunked("привет, мир", 4);
+        assert_eq!(r.chunks().collect::<String>(), "привет, мир");
+        assert!(r.chunks().all(|c| !c.is_empty()));
+
+        assert_eq!(Rope::new().chunks().count(), 0);
+    }
 }
@@ -4,22 +4,97 @@
 
 #[cfg(test)]
 use criterion as _;
+#[cfg(all(test, not(feature = "serde")))]
+use serde_test as _;
 
+pub mod builder;
+mod checksum;
+pub mod cursor;
+#[cfg(test)]
+mod fuzz;
+pub mod invariants;
 pub mod iter;
+pub mod metadata;
 pub mod node;
+#[cfg(feature = "regex")]
+pub mod regex;
+pub mod search;
+#[cfg(feature = "serde")]
+mod serde_impl;
 
 use std::{
     fmt::Debug,
     ops::{Range, RangeBounds},
+    sync::Arc,
 };
 
-use iter::{Chars, LineInfo, Lines, Substring};
+pub use builder::RopeBuilder;
+pub use cursor::Cursor;
+pub use invariants::InvariantError;
+#[cfg(feature = "graphemes")]
+use iter::Graphemes;
+use iter::{Chars, CharsAt, Chunks, LineInfo, Lines, Substring};
+pub use metadata::Metadata;
 use node::Node;
+use search::Matches;
+
+/// Leaf size used when the chunk size is not specified explicitly
+const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Bounds on the sizes of the rope's leaves, kept by the rope through its edits
+///
+/// Small leaves make edits cheaper, as an edit copies the leaf it touches, while large leaves
+/// make the tree smaller and iteration faster
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RopeConfig {
+    /// Leaves are split once they reach this many bytes, rounded up to the next character
+    /// boundary. Edits never grow a leaf past it
+    pub max_leaf_bytes: usize,
+    /// Leaves next to an edit that are shorter than this many bytes are merged with the edited
+    /// text. Zero disables merging
+    pub min_leaf_bytes: usize,
+}
+
+impl Default for RopeConfig {
+    fn default() -> Self {
+        Self {
+            max_leaf_bytes: DEFAULT_CHUNK_SIZE,
+            min_leaf_bytes: 0,
+        }
+    }
+}
+
+/// Line break style of a text
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEnding {
+    /// Unix-style `\n`
+    #[default]
+    Lf,
+    /// Windows-style `\r\n`
+    CrLf,
+}
+
+impl LineEnding {
+    /// Returns the string that breaks lines in this style
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+}
 
 /// Rope data structure. It is optimized for frequent modification
-#[derive(Debug)]
+///
+/// Cloning a rope is `O(1)`: the clones share the tree, and the nodes on the path to a
+/// modification are copied on write. This makes clones suitable as persistent snapshots
+#[derive(Debug, Clone)]
 pub struct Rope {
-    root: Box<Node>,
+    root: Arc<Node>,
+    /// Whether the nodes maintain `Metadata`, set through `RopeBuilder::track_metadata`
+    track_metadata: bool,
+    config: RopeConfig,
 }
 
 impl Rope {
@@ -58,21 +133,13 @@ impl Rope {
     }
 
     /// Concatenates `self` with `other`. The string representation becomes exactly `self` + `other`
-    pub fn concat(&mut self, mut other: Rope) {
-        // An edge case where the current rope is empty to avoid keeping empty nodes in the tree
-        if self.root.weight() == 0 {
-            let _ = std::mem::replace(&mut self.root, other.root);
-            return;
-        }
-
-        let new_root = Node::Value {
-            left_len: self.len(),
-            left_newlines: self.total_lines(),
-            l: Some(std::mem::take(&mut self.root)),
-            r: Some(std::mem::take(&mut other.root)),
-        };
-
-        self.root = Box::new(new_root);
+    ///
+    /// The result keeps tracking `Metadata` only if `other` tracks it as well or is empty
+    pub fn concat(&mut self, other: Rope) {
+        self.track_metadata &= other.track_metadata || other.is_empty();
+        let left = Arc::unwrap_or_clone(std::mem::take(&mut self.root));
+        let right = Arc::unwrap_or_clone(other.root);
+        self.root = Arc::new(Node::join(left, right));
         self.validate_newlines();
     }
 
@@ -91,10 +158,10 @@ impl Rope {
         match node {
             Node::Leaf { newlines, .. } => *newlines,
             Node::Value {
-                left_len: _,
                 left_newlines,
                 l,
                 r,
+                ..
             } => {
                 let left_newlines_actual = l
                     .as_ref()
@@ -119,12 +186,70 @@ impl Rope {
         self.root.full_weight()
     }
 
+    /// Returns the length of the string represented by the rope in bytes
+    #[must_use]
+    pub fn byte_len(&self) -> usize {
+        self.root.full_bytes()
+    }
+
+    /// Converts a character index into a byte offset of that character
+    ///
+    /// Indices past the end of the rope are clamped to `byte_len`
+    #[must_use]
+    pub fn char_to_byte(&self, char_idx: usize) -> usize {
+        self.root.char_to_byte(char_idx)
+    }
+
+    /// Converts a byte offset into the index of the character that contains this byte
+    ///
+    /// Offsets past the end of the rope are clamped to `len`
+    #[must_use]
+    pub fn byte_to_char(&self, byte_idx: usize) -> usize {
+        self.root.byte_to_char(byte_idx)
+    }
+
+    /// Returns a hash of the text for detecting changes, e.g. comparing it with the checksum of the
+    /// text that was last saved
+    ///
+    /// Ropes with equal texts have equal checksums. Hashes are maintained in the nodes, so this
+    /// takes `O(1)` time. The hash is not cryptographic, different texts collide with a
+    /// negligible but nonzero probability
+    #[must_use]
+    pub fn checksum(&self) -> u64 {
+        self.root.checksum().hash
+    }
+
+    /// Returns the leaf size bounds the rope keeps
+    #[must_use]
+    pub const fn config(&self) -> RopeConfig {
+        self.config
+    }
+
+    /// Returns the statistics of the text, or `None` if the rope was built without tracking them
+    ///
+    /// The statistics are maintained in the nodes, so this takes `O(1)` time
+    #[must_use]
+    pub fn metadata(&self) -> Option<Metadata> {
+        self.track_metadata.then(|| self.root.metadata()).flatten()
+    }
+
     /// Returns the number of lines in the rope
     #[must_use]
     pub fn total_lines(&self) -> usize {
         self.root.full_newlines()
     }
 
+    /// Detects the line break style of the text by its first line break
+    ///
+    /// Texts without line breaks are considered to use `LineEnding::Lf`
+    #[must_use]
+    pub fn line_ending(&self) -> LineEnding {
+        match self.find("\n", 0) {
+            Some(idx) if idx > 0 && self.get(idx - 1) == Some('\r') => LineEnding::CrLf,
+            _ => LineEnding::Lf,
+        }
+    }
+
     /// Returns `true` if the `Rope` contains no characters
     #[must_use]
     pub fn is_empty(&self) -> bool {
@@ -132,104 +257,108 @@ impl Rope {
     }
 
     /// Removes substring in the given character range from the `Rope`
+    ///
+    /// Use `remove` if the removed text is needed
     pub fn delete(&mut self, range: impl std::ops::RangeBounds<usize>) {
-        let range = self.normalize_range(range);
-        let (mut left, mut right) = self.split(range.start);
-        let (_, right) = right.split(range.end - range.start);
-        left.concat(right);
-        *self = left;
+        let _ = self.remove(range);
     }
 
-    fn weight(&self) -> usize {
-        self.root.weight()
+    /// Removes substring in the given character range from the `Rope`, returning it
+    pub fn remove(&mut self, range: impl std::ops::RangeBounds<usize>) -> String {
+        self.replace(range, "")
     }
 
-    fn is_balanced(&self) -> bool {
-        static FIB: [usize; 64] = {
-            let mut fib = [0; 64];
-            fib[0] = 0;
-            fib[1] = 1;
-            let mut i = 2;
-            while i < 64 {
-                fib[i] = fib[i - 1] + fib[i - 2];
-                i += 1;
-            }
-            fib
-        };
-
-        let depth = self.depth();
-        if depth >= FIB.len() {
-            return false;
+    /// Replaces substring in the given character range with `s`, returning the removed text
+    ///
+    /// If the range lies within a single leaf and the result stays within the leaf size bounds,
+    /// the leaf is spliced in place. Otherwise the tree is split around the range once and joined
+    /// back with `s` in between
+    pub fn replace(&mut self, range: impl RangeBounds<usize>, s: &str) -> String {
+        let range = self.normalize_range(range);
+        if let Some(removed) = self.replace_in_leaf(range.clone(), s) {
+            return removed;
         }
 
-        FIB[depth + 2] <= self.weight()
+        self.replace_split(range, s).chunks().collect()
     }
 
-    fn merge_range(leaves: &mut [Node], range: std::ops::Range<usize>) -> Node {
-        let len = range.end - range.start;
-        if len == 1 {
-            return std::mem::take(&mut leaves[range.start]);
-        }
-        if len == 2 {
-            let Node::Leaf {
-                char_len, newlines, ..
-            } = &leaves[range.start]
-            else {
-                unreachable!("all nodes passed to merge_range should be of type leaf");
-            };
+    /// Replaces the range with `s` by splitting the tree around it, returning the removed part
+    fn replace_split(&mut self, range: Range<usize>, s: &str) -> Rope {
+        let (mut left, mut rest) = self.split(range.start);
+        let (removed, mut right) = rest.split(range.len());
+        let mut middle = String::from(s);
+        self.absorb_short_leaves(&mut left, &mut middle, &mut right);
+        left.concat(self.piece(&middle));
+        left.concat(right);
+        *self = left;
 
-            return Node::Value {
-                left_len: *char_len,
-                left_newlines: *newlines,
-                l: Some(Box::new(std::mem::take(&mut leaves[range.start]))),
-                r: Some(Box::new(std::mem::take(&mut leaves[range.start + 1]))),
-            };
-        }
+        removed
+    }
 
-        let mid = range.start + len / 2;
-        let left = Self::merge_range(leaves, range.start..mid);
-        let left_weight = left.full_weight();
-        let left_newlines = left.full_newlines();
-        let right = Self::merge_range(leaves, mid..range.end);
-
-        Node::Value {
-            left_len: left_weight,
-            left_newlines,
-            l: Some(Box::new(left)),
-            r: Some(Box::new(right)),
+    /// Moves the leaves at the inner ends of `left` and `right` into `middle` while they or
+    /// `middle` are shorter than `min_leaf_bytes`
+    fn absorb_short_leaves(&self, left: &mut Rope, middle: &mut String, right: &mut Rope) {
+        let min = self.config.min_leaf_bytes;
+        while let Some(tail) = left
+            .chunks()
+            .next_back()
+            .filter(|tail| !tail.is_empty() && (tail.len() < min || middle.len() < min))
+        {
+            middle.insert_str(0, tail);
+            let tail_len = tail.chars().count();
+            *left = left.split(left.len() - tail_len).0;
+        }
+        while let Some(head) = right
+            .chunks()
+            .next()
+            .filter(|head| !head.is_empty() && (head.len() < min || middle.len() < min))
+        {
+            middle.push_str(head);
+            let head_len = head.chars().count();
+            *right = right.split(head_len).1;
         }
     }
 
-    fn rebalance(&mut self) {
-        if self.is_balanced() {
-            return;
+    fn replace_in_leaf(&mut self, range: Range<usize>, s: &str) -> Option<String> {
+        let leaf = cursor::locate(&self.root, range.end);
+        if leaf.char_offset < range.len() {
+            return None;
         }
 
-        let mut leaves = self.get_leaves();
-        let len = leaves.len();
-        self.root = Box::new(Self::merge_range(&mut leaves, 0..len));
-    }
+        let Node::Leaf { value, .. } = self.root.descend(&leaf.path) else {
+            unreachable!("located path should end in a leaf");
+        };
+        let start = node::char_to_byte_in(value, leaf.char_offset - range.len());
+        let removed = &value[start..leaf.byte_offset];
+
+        let new_len = leaf.byte_len - removed.len() + s.len();
+        // Emptied and shrunk leaves are left for `split` to prune and merge
+        let too_short = new_len == 0 || new_len < self.config.min_leaf_bytes;
+        if new_len > self.config.max_leaf_bytes || (too_short && !leaf.path.is_empty()) {
+            return None;
+        }
 
-    fn get_leaves(&mut self) -> Vec<Node> {
-        let mut leaves: Vec<Node> = Vec::new();
-        let root = *std::mem::take(&mut self.root);
-        Self::get_leaves_inner(root, &mut leaves);
+        let removed = removed.to_owned();
+        Arc::make_mut(&mut self.root).splice_leaf(
+            &leaf.path,
+            start..leaf.byte_offset,
+            s,
+            self.track_metadata,
+        );
+        Some(removed)
+    }
 
-        leaves
+    /// Removes substring in the given byte range from the `Rope`
+    ///
+    /// Offsets that do not lie on a character boundary refer to the character containing them
+    pub fn delete_bytes(&mut self, range: impl std::ops::RangeBounds<usize>) {
+        let range = self.byte_range_to_chars(range);
+        self.delete(range);
     }
 
-    fn get_leaves_inner(node: Node, leaves: &mut Vec<Node>) {
-        match node {
-            Node::Leaf { .. } => leaves.push(node),
-            Node::Value { l, r, .. } => {
-                if let Some(l) = l {
-                    Self::get_leaves_inner(*l, leaves);
-                }
-                if let Some(r) = r {
-                    Self::get_leaves_inner(*r, leaves);
-                }
-            }
-        }
+    #[cfg(test)]
+    fn weight(&self) -> usize {
+        self.root.weight()
     }
 
     /// Returns nth character of the string representation of the rope
@@ -258,78 +387,54 @@ impl Rope {
 
     /// Splits the rope in two at the character index
     pub fn split(&mut self, idx: usize) -> (Rope, Rope) {
-        let (l_node, r_node) = Self::split_inner(std::mem::take(&mut self.root), idx);
-
-        let mut left = Rope { root: l_node };
-        left.rebalance();
-
-        let mut right = Rope { root: r_node };
-        right.rebalance();
-
-        (left, right)
+        let (left, right) = Arc::unwrap_or_clone(std::mem::take(&mut self.root)).split(idx);
+
+        (
+            Rope {
+                root: Arc::new(left),
+                track_metadata: self.track_metadata,
+                config: self.config,
+            },
+            Rope {
+                root: Arc::new(right),
+                track_metadata: self.track_metadata,
+                config: self.config,
+            },
+        )
     }
 
-    fn split_inner(node: Node, idx: usize) -> (Box<Node>, Box<Node>) {
-        match node {
-            Node::Leaf { value, .. } => {
-                let left = Box::new(Node::new_leaf(value[..idx].into()));
-                let right = Box::new(Node::new_leaf(value[idx..].into()));
-                (left, right)
-            }
-            Node::Value {
-                left_len: val,
-                l,
-                r,
-                ..
-            } => {
-                if idx < val {
-                    let (left, right) = Self::split_inner(*l.unwrap(), idx);
-                    let right_newlines = right.full_newlines();
-                    let right = Box::new(Node::Value {
-                        left_len: val - idx,
-                        left_newlines: right_newlines,
-                        l: Some(right),
-                        r,
-                    });
-
-                    (left, right)
-                } else {
-                    let (left, right) = Self::split_inner(*r.unwrap(), idx - val);
-                    let left = Box::new(Node::Value {
-                        left_len: val,
-                        left_newlines: l.as_deref().map_or(0, Node::full_newlines),
-                        l,
-                        r: Some(left),
-                    });
-
-                    (left, right)
-                }
-            }
-        }
+    /// Returns a cursor at the character index for making a series of nearby edits
+    #[must_use]
+    pub fn cursor(&mut self, idx: usize) -> Cursor<'_> {
+        Cursor::new(self, idx)
     }
 
     /// Inserts `s` at `idx` character position
+    ///
+    /// Small insertions are spliced into the leaf at `idx` as long as it stays within the chunk
+    /// size, so that typing does not fragment the tree into a leaf per character
     pub fn insert(&mut self, idx: usize, s: &str) {
-        if idx == 0 {
-            self.prepend(s);
-            return;
-        }
+        self.cursor(idx).insert_str(s);
+    }
 
-        if idx == self.len() {
-            self.concat(Rope::from(s));
-            return;
-        }
+    /// Inserts `s` at `idx` by splitting the tree and joining a new leaf in between
+    fn insert_split(&mut self, idx: usize, s: &str) {
+        let _ = self.replace_split(idx..idx, s);
+    }
 
-        let (mut left, right) = self.split(idx);
-        left.concat(Rope::from(s));
-        left.concat(right);
-        *self = left;
+    /// Inserts `s` at `byte_idx` byte offset
+    ///
+    /// Offsets that do not lie on a character boundary refer to the character containing them
+    pub fn insert_at_byte(&mut self, byte_idx: usize, s: &str) {
+        self.insert(self.byte_to_char(byte_idx), s);
     }
 
-    fn prepend(&mut self, s: &str) {
-        let mut new = Rope::from(s);
-        new.concat(std::mem::take(self));
-        *self = new;
+    /// Builds a rope from `s` with the configuration of `self`, tracking `Metadata` if `self`
+    /// does
+    fn piece(&self, s: &str) -> Rope {
+        let mut builder = RopeBuilder::with_config(self.config);
+        builder.track_metadata(self.track_metadata).append(s);
+        builder.finish()
     }
 
     /// Returns iterator over represented string's characters
@@ -338,6 +443,79 @@ impl Rope {
         Chars::new(&self.root)
     }
 
+    /// Returns double-ended iterator over represented string's characters starting at `idx`
+    ///
+    /// Reversing it walks the characters from the end of the rope back to `idx`
+    #[must_use]
+    pub fn chars_at(&self, idx: usize) -> CharsAt<'_> {
+        let start = self.char_to_byte(idx);
+        CharsAt::new(&self.root, start..self.byte_len())
+    }
+
+    /// Returns iterator over represented string's characters in reverse order
+    pub fn chars_rev(&self) -> std::iter::Rev<CharsAt<'_>> {
+        self.chars_at(0).rev()
+    }
+
+    /// Returns iterator over the string slices that the rope is made of
+    ///
+    /// Allows reading the rope contents without any per-character overhead, e.g. to write it to
+    /// a file or to hash it
+    #[must_use]
+    pub fn chunks(&self) -> Chunks<'_> {
+        Chunks::new(&self.root)
+    }
+
+    /// Writes the string representation of the rope into `w` leaf by leaf
+    ///
+    /// Returns the number of bytes written
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `w` fails
+    pub fn write_to<W: std::io::Write>(&self, mut w: W) -> std::io::Result<u64> {
+        let mut written = 0;
+        for chunk in self.chunks() {
+            w.write_all(chunk.as_bytes())?;
+            written += chunk.len() as u64;
+        }
+
+        Ok(written)
+    }
+
+    /// Returns iterator over represented string's extended grapheme clusters
+    #[cfg(feature = "graphemes")]
+    #[must_use]
+    pub fn graphemes(&self) -> Graphemes<'_> {
+        Graphemes::new(&self.root)
+    }
+
+    /// Returns the character index of the first grapheme boundary after `idx`
+    ///
+    /// Returns `len` if there is no such boundary
+    #[cfg(feature = "graphemes")]
+    #[must_use]
+    pub fn next_grapheme_boundary(&self, idx: usize) -> usize {
+        let byte_idx = self.char_to_byte(idx);
+        let mut cursor = unicode_segmentation::GraphemeCursor::new(byte_idx, self.byte_len(), true);
+        self.root
+            .next_grapheme_boundary(&mut cursor)
+            .map_or_else(|| self.len(), |b| self.byte_to_char(b))
+    }
+
+    /// Returns the character index of the last grapheme boundary before `idx`
+    ///
+    /// Returns `0` if there is no such boundary
+    #[cfg(feature = "graphemes")]
+    #[must_use]
+    pub fn prev_grapheme_boundary(&self, idx: usize) -> usize {
+        let byte_idx = self.char_to_byte(idx);
+        let mut cursor = unicode_segmentation::GraphemeCursor::new(byte_idx, self.byte_len(), true);
+        self.root
+            .prev_grapheme_boundary(&mut cursor)
+            .map_or(0, |b| self.byte_to_char(b))
+    }
+
     /// Returns iterator over represented string's lines
     ///
     /// The iterator yeilds not just string representations, but line's character offset, number
@@ -347,13 +525,22 @@ impl Rope {
         Lines::new(&self.root)
     }
 
+    /// Returns iterator over represented string's lines, starting at the `n`th line
+    ///
+    /// Unlike `self.lines().skip(n)`, the start of the line is found using the newline counts
+    /// stored in the tree, without iterating over the preceding text
+    #[must_use]
+    pub fn lines_at(&self, n: usize) -> Lines<'_> {
+        Lines::new_at(&self.root, n)
+    }
+
     /// Returns `n`th line information, including string representation
     ///
     /// If string representation is not needed, consider using `line_info` instead, to avoid
     /// allocation
     #[must_use]
     pub fn line(&self, n: usize) -> Option<LineInfo> {
-        Lines::new(&self.root).nth(n)
+        self.lines_at(n).next()
     }
 
     /// Returns `n`th line information, excluding string representation
@@ -361,7 +548,7 @@ impl Rope {
     /// If string representation is needed, use `line` instead
     #[must_use]
     pub fn line_info(&self, n: usize) -> Option<LineInfo> {
-        Lines::new(&self.root).parse_contents(false).nth(n)
+        Lines::new_at(&self.root, n).parse_contents(false).next()
     }
 
     /// Returns iterator over represented string's substring
@@ -374,52 +561,106 @@ impl Rope {
         Substring::new(Chars::new(&self.root), range)
     }
 
+    /// Returns iterator over represented string's substring in the given byte range
+    ///
+    /// Offsets that do not lie on a character boundary refer to the character containing them
+    #[must_use]
+    pub fn substr_bytes(&self, range: impl RangeBounds<usize>) -> Substring<'_> {
+        let range = self.byte_range_to_chars(range);
+        Substring::new(Chars::new(&self.root), range)
+    }
+
+    /// Returns iterator over the character indices of non-overlapping matches of `pattern`
+    #[must_use]
+    pub fn matches(&self, pattern: &str) -> Matches<'_> {
+        Matches::new(&self.root, pattern, 0)
+    }
+
+    /// Returns the character index of the first match of `pattern` that starts at or after `from`
+    #[must_use]
+    pub fn find(&self, pattern: &str, from: usize) -> Option<usize> {
+        if pattern.is_empty() {
+            return (from <= self.len()).then_some(from);
+        }
+
+        Matches::new(&self.root, pattern, from).next()
+    }
+
+    /// Returns iterator over the character ranges of non-overlapping matches of the regular
+    /// expression `pattern`
+    ///
+    /// The syntax is the one of the `regex` crate, except for Unicode word boundaries. `\b` has
+    /// to be written as the ASCII-only `(?-u:\b)`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `pattern` is not a valid regular expression
+    #[cfg(feature = "regex")]
+    pub fn regex_find_iter(
+        &self,
+        pattern: &str,
+    ) -> Result<regex::RegexMatches<'_>, Box<regex::BuildError>> {
+        regex::RegexMatches::new(&self.root, pattern)
+    }
+
+    /// Returns the character index of the last match of `pattern` that ends at or before `until`
+    #[must_use]
+    pub fn rfind(&self, pattern: &str, until: usize) -> Option<usize> {
+        search::rfind(&self.root, pattern, until)
+    }
+
     /// Returns number of the line containing given index
     #[must_use]
     pub fn line_of_index(&self, index: usize) -> usize {
-        let (node, skipped, lines_skipped) = Self::skip_to(&self.root, index);
-        let to_parse = index - skipped;
-
-        lines_skipped
-            + Chars::new(node)
-                .take(to_parse)
-                .filter(|&c| c == '\n')
-                .count()
+        self.char_to_line(index)
     }
 
     /// Returns the line start index
     #[must_use]
     pub fn index_of_line(&self, line: usize) -> usize {
-        self.root.index_of_line(line)
+        self.line_to_char(line)
+    }
+
+    /// Returns number of the line containing the character index. A line break belongs to the
+    /// line it ends, indices past the end of the rope belong to the last line
+    ///
+    /// Takes `O(log n)` time, only the leaf containing the index is scanned
+    #[must_use]
+    pub fn char_to_line(&self, char_idx: usize) -> usize {
+        self.root.char_to_line(char_idx)
+    }
+
+    /// Returns the character index at which the line starts, lines past the end of the rope start
+    /// at its end
+    ///
+    /// Takes `O(log n)` time, only the leaf containing the line start is scanned
+    #[must_use]
+    pub fn line_to_char(&self, line: usize) -> usize {
+        self.root.line_to_char(line)
     }
 
     /// Converts a string into the rope. The number of bytes in a rope leaf may never exceed
     /// `chunk_size` + 3
     #[must_use]
     pub fn from_str_chunked(s: &str, chunk_size: usize) -> Rope {
-        let mut rope = Rope::default();
-        let mut offset = 0;
-        while offset < s.len() {
-            let mut end = (offset + chunk_size).min(s.len());
-            while !s.is_char_boundary(end) {
-                end += 1;
-                // TODO: handle this case
-                assert!(offset < s.len(), "invalid utf-8 encoded string");
-            }
-
-            rope.concat(Rope {
-                root: Box::new(Node::new_leaf(&s[offset..end])),
-            });
-            offset = end;
-        }
+        let mut builder = RopeBuilder::with_chunk_size(chunk_size);
+        builder.append(s);
+        builder.finish()
+    }
 
-        #[cfg(debug_assertions)]
-        rope.validate_newlines();
+    fn normalize_range(&self, range: impl std::ops::RangeBounds<usize>) -> Range<usize> {
+        Self::normalize_range_with_len(range, self.len())
+    }
 
-        rope
+    fn byte_range_to_chars(&self, range: impl std::ops::RangeBounds<usize>) -> Range<usize> {
+        let range = Self::normalize_range_with_len(range, self.byte_len());
+        self.byte_to_char(range.start)..self.byte_to_char(range.end)
     }
 
-    fn normalize_range(&self, range: impl std::ops::RangeBounds<usize>) -> Range<usize> {
+    fn normalize_range_with_len(
+        range: impl std::ops::RangeBounds<usize>,
+        len: usize,
+    ) -> Range<usize> {
         let start = match range.start_bound() {
             std::ops::Bound::Included(&s) => s,
             std::ops::Bound::Excluded(&s) => s + 1,
@@ -429,51 +670,101 @@ impl Rope {
         let end = match range.end_bound() {
             std::ops::Bound::Included(&e) => e + 1,
             std::ops::Bound::Excluded(&e) => e,
-            std::ops::Bound::Unbounded => self.len(),
+            std::ops::Bound::Unbounded => len,
         };
 
         start..end
     }
+}
 
-    fn skip_to(mut from: &Node, target: usize) -> (&Node, usize, usize) {
-        let mut skipped = 0;
-        let mut skipped_lines = 0;
-        // Skip the left subtree if it is not included in the substring
-        while let Node::Value {
-            left_len: val,
-            r,
-            left_newlines,
-            ..
-        } = from
-        {
-            if *val >= target - skipped {
-                break;
+impl From<&str> for Rope {
+    fn from(s: &str) -> Self {
+        Self::from_str_chunked(s, DEFAULT_CHUNK_SIZE)
+    }
+}
+
+impl std::fmt::Display for Rope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.chunks().try_for_each(|chunk| f.write_str(chunk))
+    }
+}
+
+impl PartialEq for Rope {
+    fn eq(&self, other: &Self) -> bool {
+        if Arc::ptr_eq(&self.root, &other.root) {
+            return true;
+        }
+        if self.byte_len() != other.byte_len() {
+            return false;
+        }
+
+        let mut other_chunks = other.chunks();
+        let mut other_chunk: &[u8] = &[];
+        for mut chunk in self.chunks().map(str::as_bytes) {
+            while !chunk.is_empty() {
+                if other_chunk.is_empty() {
+                    let Some(next) = other_chunks.next() else {
+                        return false;
+                    };
+                    other_chunk = next.as_bytes();
+                }
+
+                let len = chunk.len().min(other_chunk.len());
+                if chunk[..len] != other_chunk[..len] {
+                    return false;
+                }
+                chunk = &chunk[len..];
+                other_chunk = &other_chunk[len..];
             }
+        }
 
-            let Some(r) = r else {
-                break;
-            };
+        true
+    }
+}
+
+impl Eq for Rope {}
 
-            from = r;
-            skipped += val;
-            skipped_lines += left_newlines;
+impl PartialEq<str> for Rope {
+    fn eq(&self, other: &str) -> bool {
+        if self.byte_len() != other.len() {
+            return false;
         }
 
-        (from, skipped, skipped_lines)
+        let mut rest = other;
+        self.chunks().all(|chunk| {
+            let Some(tail) = rest.strip_prefix(chunk) else {
+                return false;
+            };
+            rest = tail;
+            true
+        })
     }
 }
 
-impl From<&str> for Rope {
-    fn from(s: &str) -> Self {
-        const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
-        Self::from_str_chunked(s, DEFAULT_CHUNK_SIZE)
+impl PartialEq<&str> for Rope {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<Rope> for str {
+    fn eq(&self, other: &Rope) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<Rope> for &str {
+    fn eq(&self, other: &Rope) -> bool {
+        other == *self
     }
 }
 
 impl Default for Rope {
     fn default() -> Self {
         Self {
-            root: Box::new(Node::default()),
+            root: Arc::new(Node::default()),
+            track_metadata: false,
+            config: RopeConfig::default(),
         }
     }
 }
@@ -481,7 +772,9 @@ impl Default for Rope {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::checksum::Checksum;
 
+    #[allow(clippy::many_single_char_names)]
     fn example_rope() -> Rope {
         let m = Node::new_leaf("s");
         let n = Node::new_leaf(" Simon");
@@ -489,47 +782,76 @@ mod tests {
         let k = Node::new_leaf("me i");
         let g = Node::Value {
             left_len: 2,
+            left_bytes: 2,
             left_newlines: 0,
-            l: Some(Box::new(j)),
-            r: Some(Box::new(k)),
+            height: 1,
+            l: Some(Arc::new(j)),
+            r: Some(Arc::new(k)),
+            metadata: None,
+            checksum: Checksum::default(),
         };
         let h = Node::Value {
             left_len: 1,
+            left_bytes: 1,
             left_newlines: 0,
-            l: Some(Box::new(m)),
-            r: Some(Box::new(n)),
+            height: 1,
+            l: Some(Arc::new(m)),
+            r: Some(Arc::new(n)),
+            metadata: None,
+            checksum: Checksum::default(),
         };
         let e = Node::new_leaf("Hello ");
         let f = Node::new_leaf("my ");
         let c = Node::Value {
             left_len: 6,
+            left_bytes: 6,
             left_newlines: 0,
-            l: Some(Box::new(e)),
-            r: Some(Box::new(f)),
+            height: 1,
+            l: Some(Arc::new(e)),
+            r: Some(Arc::new(f)),
+            metadata: None,
+            checksum: Checksum::default(),
         };
         let d = Node::Value {
             left_len: 6,
+            left_bytes: 6,
             left_newlines: 0,
-            l: Some(Box::new(g)),
-            r: Some(Box::new(h)),
+            height: 2,
+            l: Some(Arc::new(g)),
+            r: Some(Arc::new(h)),
+            metadata: None,
+            checksum: Checksum::default(),
         };
         let b = Node::Value {
             left_len: 9,
+            left_bytes: 9,
             left_newlines: 0,
-            l: Some(Box::new(c)),
-            r: Some(Box::new(d)),
+            height: 3,
+            l: Some(Arc::new(c)),
+            r: Some(Arc::new(d)),
+            metadata: None,
+            checksum: Checksum::default(),
         };
         let a = Node::Value {
             left_len: 22,
+            left_bytes: 22,
             left_newlines: 0,
-            l: Some(Box::new(b)),
+            height: 4,
+            l: Some(Arc::new(b)),
             r: None,
+            metadata: None,
+            checksum: Checksum::default(),
         };
-        Rope { root: Box::new(a) }
+        Rope {
+            root: Arc::new(a),
+            track_metadata: false,
+            config: RopeConfig::default(),
+        }
     }
 
     fn assert_correctness(r: &mut Rope, expected: &str) {
         assert_eq!(r.chars().collect::<String>(), expected);
+        assert_eq!(r.chunks().collect::<String>(), expected);
         expected.chars().enumerate().for_each(|(i, c)| {
             assert_eq!(
                 r.get(i),
@@ -542,6 +864,7 @@ mod tests {
             );
         });
         assert_eq!(r.chars().count(), r.len());
+        assert_eq!(expected.len(), r.byte_len());
         if r.chars().all(|c| c.is_ascii()) {
             for start in 0..expected.len() {
                 for end in start..expected.len() {
@@ -550,7 +873,7 @@ mod tests {
                         r.substr(start..end).collect::<String>(),
                         "substring: {}, start: {start}, end: {end}",
                         r.chars().collect::<String>(),
-                    )
+                    );
                 }
             }
         }
@@ -572,15 +895,42 @@ mod tests {
         assert_correctness(&mut r, &expected);
     }
 
+    fn assert_balanced(node: &Node) -> usize {
+        let Node::Value { height, l, r, .. } = node else {
+            return 0;
+        };
+
+        let l_height = l.as_deref().map_or(0, assert_balanced);
+        let r_height = r.as_deref().map_or(0, assert_balanced);
+        assert!(
+            l_height.abs_diff(r_height) <= 1,
+            "unbalanced node:\n{node:?}"
+        );
+        assert_eq!(*height, 1 + l_height.max(r_height));
+        *height
+    }
+
     #[test]
     fn depth() {
-        let mut r = example_rope();
-        let expected = "Hello my name is Simon".to_owned();
-
-        let mut leaves = r.get_leaves();
-        let len = leaves.len();
-        r.root = Box::new(Rope::merge_range(&mut leaves, 0..len));
+        let mut r = Rope::new();
+        let mut expected = String::new();
+        let mut seed: usize = 7;
+        for i in 0..128 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345) % (1 << 31);
+            let idx = seed % (expected.len() + 1);
+            let s = if i % 3 == 0 { "ab\n" } else { "c" };
+            r.insert(idx, s);
+            expected.insert_str(idx, s);
+
+            if i % 5 == 4 {
+                let end = (idx + 3).min(expected.len());
+                r.delete(idx..end);
+                expected.replace_range(idx..end, "");
+            }
+        }
 
+        assert_balanced(&r.root);
+        assert!(r.depth() <= 2 * (usize::BITS - r.len().leading_zeros()) as usize);
         assert_correctness(&mut r, &expected);
     }
 
@@ -591,7 +941,7 @@ mod tests {
         let expected = "Hello my name is Simon and I like to eat pizza";
 
         r.concat(second);
-        assert_correctness(&mut r, &expected);
+        assert_correctness(&mut r, expected);
     }
 
     #[test]
@@ -626,7 +976,7 @@ mod tests {
                 r.concat(Rope::from(s));
             }
 
-            assert_correctness(&mut r, &expected);
+            assert_correctness(&mut r, expected);
         }
     }
 
@@ -680,7 +1030,7 @@ mod tests {
         let expected: String = str
             .chars()
             .enumerate()
-            .filter_map(|(i, c)| if i >= 5 && i < 8 { None } else { Some(c) })
+            .filter_map(|(i, c)| if (5..8).contains(&i) { None } else { Some(c) })
             .collect();
 
         assert_correctness(&mut r, &expected);
@@ -754,6 +1104,38 @@ mod tests {
         assert_eq!(r.line_of_index(34), 3); // '\n' at end of fourth line
     }
 
+    #[test]
+    fn line_char_conversion() {
+        let s = "привет\n\nмир\nhello world\n\n👍🏽 end";
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(
+                s.chars()
+                    .enumerate()
+                    .filter(|&(_, c)| c == '\n')
+                    .map(|(i, _)| i + 1),
+            )
+            .collect();
+        let len = s.chars().count();
+
+        for chunk_size in [1, 2, 3, 5, 64] {
+            let r = Rope::from_str_chunked(s, chunk_size);
+            for idx in 0..=len + 2 {
+                let expected = s.chars().take(idx).filter(|&c| c == '\n').count();
+                assert_eq!(r.char_to_line(idx), expected, "index {idx}");
+            }
+            for (line, &start) in line_starts.iter().enumerate() {
+                assert_eq!(r.line_to_char(line), start, "line {line}");
+            }
+            assert_eq!(r.line_to_char(line_starts.len()), len);
+            assert_eq!(r.line_to_char(100), len);
+        }
+
+        let r = Rope::new();
+        assert_eq!(r.char_to_line(0), 0);
+        assert_eq!(r.line_to_char(0), 0);
+        assert_eq!(r.line_to_char(1), 0);
+    }
+
     #[test]
     fn weights_after_operations() {
         let mut r = Rope::new();
@@ -794,4 +1176,323 @@ mod tests {
         left.delete(5..6);
         assert_eq!(left.total_lines(), 2);
     }
+
+    #[test]
+    fn split_multibyte() {
+        let mut r = Rope::from_str_chunked("привет, мир", 4);
+        let (mut left, mut right) = r.split(3);
+        assert_correctness(&mut left, "при");
+        assert_correctness(&mut right, "вет, мир");
+
+        let mut r = Rope::from_str_chunked("привет, мир", 4);
+        r.insert(7, " ✓");
+        assert_correctness(&mut r, "привет, ✓ мир");
+    }
+
+    #[test]
+    fn char_byte_conversion() {
+        let s = "aé✓𝄞b";
+        let r = Rope::from_str_chunked(s, 2);
+        for (char_idx, (byte_idx, c)) in s.char_indices().enumerate() {
+            assert_eq!(r.char_to_byte(char_idx), byte_idx);
+            assert_eq!(r.byte_to_char(byte_idx), char_idx);
+            for inner in byte_idx..byte_idx + c.len_utf8() {
+                assert_eq!(r.byte_to_char(inner), char_idx);
+            }
+        }
+        assert_eq!(r.char_to_byte(r.len()), s.len());
+        assert_eq!(r.byte_to_char(s.len()), r.len());
+        assert_eq!(r.char_to_byte(100), s.len());
+        assert_eq!(r.byte_to_char(100), r.len());
+    }
+
+    #[test]
+    fn byte_operations() {
+        let mut r = Rope::from_str_chunked("aé✓𝄞b", 2);
+        assert_eq!(r.byte_len(), 11);
+        assert_eq!(r.substr_bytes(1..6).collect::<String>(), "é✓");
+        assert_eq!(r.substr_bytes(6..).collect::<String>(), "𝄞b");
+
+        r.insert_at_byte(3, "x");
+        assert_correctness(&mut r, "aéx✓𝄞b");
+
+        r.delete_bytes(4..11);
+        assert_correctness(&mut r, "aéxb");
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn graphemes() {
+        let s = "e\u{301}👍🏽 a🇺🇦\r\nb";
+        let expected = ["e\u{301}", "👍🏽", " ", "a", "🇺🇦", "\r\n", "b"];
+        for chunk_size in [1, 2, 3, 5, 64] {
+            let r = Rope::from_str_chunked(s, chunk_size);
+            assert_eq!(r.graphemes().collect::<Vec<_>>(), expected, "{chunk_size}");
+        }
+        assert_eq!(Rope::new().graphemes().count(), 0);
+    }
+
+    #[cfg(feature = "graphemes")]
+    #[test]
+    fn grapheme_boundaries() {
+        let s = "e\u{301}👍🏽 a🇺🇦\r\nb";
+        let boundaries = [0, 2, 4, 5, 6, 8, 10, 11];
+        for chunk_size in [1, 2, 3, 5, 64] {
+            let r = Rope::from_str_chunked(s, chunk_size);
+            for pair in boundaries.windows(2) {
+                assert_eq!(r.next_grapheme_boundary(pair[0]), pair[1]);
+                assert_eq!(r.prev_grapheme_boundary(pair[1]), pair[0]);
+                for inner in pair[0] + 1..pair[1] {
+                    assert_eq!(r.next_grapheme_boundary(inner), pair[1]);
+                    assert_eq!(r.prev_grapheme_boundary(inner), pair[0]);
+                }
+            }
+            assert_eq!(r.next_grapheme_boundary(r.len()), r.len());
+            assert_eq!(r.prev_grapheme_boundary(0), 0);
+        }
+    }
+
+    #[test]
+    fn insert_coalesces() {
+        let mut r = Rope::new();
+        let mut expected = String::new();
+        for (i, c) in "hello, мир\nhow are you?"
+            .chars()
+            .cycle()
+            .take(1000)
+            .enumerate()
+        {
+            let idx = i / 3;
+            r.insert(idx, c.encode_utf8(&mut [0; 4]));
+            expected.insert(
+                expected
+                    .char_indices()
+                    .nth(idx)
+                    .map_or(expected.len(), |(b, _)| b),
+                c,
+            );
+        }
+
+        assert_eq!(r.chunks().count(), 1);
+        assert_eq!(r.depth(), 1);
+        assert_eq!(r.chars().collect::<String>(), expected);
+        assert_eq!(r.total_lines(), expected.matches('\n').count());
+
+        let mut r = Rope::from_str_chunked("ab", 4);
+        r.insert(1, "x");
+        r.insert(3, "y");
+        assert_eq!(r.chunks().collect::<Vec<_>>(), ["axby"]);
+        r.insert(2, "z");
+        assert!(r.chunks().all(|c| c.len() <= 4));
+        assert_eq!(r.chars().collect::<String>(), "axzby");
+    }
+
+    #[test]
+    fn replace() {
+        let mut r = Rope::from_str_chunked("hello, мир\nhow are you?", 4);
+        assert_eq!(r.replace(7..10, "world"), "мир");
+        assert_correctness(&mut r, "hello, world\nhow are you?");
+
+        assert_eq!(r.replace(1..2, "a"), "e");
+        assert_correctness(&mut r, "hallo, world\nhow are you?");
+
+        assert_eq!(r.replace(5..16, ""), ", world\nhow");
+        assert_correctness(&mut r, "hallo are you?");
+
+        assert_eq!(r.replace(5..5, "!\n"), "");
+        assert_correctness(&mut r, "hallo!\n are you?");
+
+        assert_eq!(r.replace(.., ""), "hallo!\n are you?");
+        assert_correctness(&mut r, "");
+        assert_eq!(r.replace(.., "x"), "");
+        assert_correctness(&mut r, "x");
+    }
+
+    #[test]
+    fn remove() {
+        let mut r = example_rope();
+        assert_eq!(r.remove(5..8), " my");
+        assert_correctness(&mut r, "Hello name is Simon");
+        assert_eq!(r.remove(10..), " is Simon");
+        assert_correctness(&mut r, "Hello name");
+        assert_eq!(r.remove(3..3), "");
+        assert_eq!(r.remove(..), "Hello name");
+        assert_correctness(&mut r, "");
+    }
+
+    #[test]
+    fn snapshots() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Rope>();
+
+        let mut r = Rope::from_str_chunked("hello, мир\nhow are you?", 4);
+        let snapshot = r.clone();
+        assert!(Arc::ptr_eq(&r.root, &snapshot.root));
+
+        r.insert(5, "!");
+        r.insert(0, "oh, ");
+        r.delete(10..12);
+        let mut second = r.clone();
+        second.replace(0..4, "");
+
+        assert_correctness(&mut r, "oh, hello!мир\nhow are you?");
+        assert_correctness(&mut second, "hello!мир\nhow are you?");
+        let mut snapshot = snapshot;
+        assert_correctness(&mut snapshot, "hello, мир\nhow are you?");
+    }
+
+    #[test]
+    fn equality() {
+        let s = "привет, мир\nhow are you?";
+        let a = Rope::from_str_chunked(s, 3);
+        let b = Rope::from_str_chunked(s, 7);
+        assert_eq!(a, b);
+        assert_eq!(a, s);
+        assert_eq!(s, a);
+        assert_eq!(a, *s);
+        assert_eq!(a.to_string(), s);
+
+        assert_ne!(a, "привет, мир\nhow are you!");
+        assert_ne!(a, "привет");
+        assert_ne!(a, Rope::from("привет, мир\nhow are you!"));
+        assert_eq!(Rope::new(), "");
+        assert_eq!(Rope::new(), Rope::from_str_chunked("", 1));
+    }
+
+    #[test]
+    fn line_ending() {
+        assert_eq!(Rope::new().line_ending(), LineEnding::Lf);
+        assert_eq!(Rope::from("a\r").line_ending(), LineEnding::Lf);
+        assert_eq!(Rope::from("a\nb\r\n").line_ending(), LineEnding::Lf);
+        assert_eq!(
+            Rope::from_str_chunked("ab\r\nb\n", 1).line_ending(),
+            LineEnding::CrLf
+        );
+        assert_eq!(Rope::from("a\r\nb").total_lines(), 1);
+    }
+
+    #[test]
+    fn write_to() {
+        let r = Rope::from_str_chunked("привет,\nмир\n", 4);
+        let mut out = Vec::new();
+        let written = r.write_to(&mut out).unwrap();
+        assert_eq!(written, out.len() as u64);
+        assert_eq!(String::from_utf8(out).unwrap(), "привет,\nмир\n");
+    }
+
+    #[test]
+    fn metadata_tracking() {
+        let mut builder = RopeBuilder::with_chunk_size(3);
+        builder
+            .track_metadata(true)
+            .append("hello wonderful\r\nworld");
+        let mut r = builder.finish();
+        let mut expected = String::from("hello wonderful\r\nworld");
+
+        let edits: &[(usize, usize, &str)] = &[
+            (5, 5, " big"),
+            (0, 3, ""),
+            (10, 10, "\n\nпривет мир "),
+            (2, 2, "x"),
+            (4, 20, "a\r"),
+            (0, 0, "lorem ipsum dolor sit amet, consectetur\n"),
+        ];
+        for &(start, end, s) in edits {
+            r.replace(start..end, s);
+            let (b_start, b_end) = (
+                node::char_to_byte_in(&expected, start),
+                node::char_to_byte_in(&expected, end),
+            );
+            expected.replace_range(b_start..b_end, s);
+            assert_eq!(r.metadata(), Some(Metadata::of(&expected)), "{expected:?}");
+        }
+
+        let mut cursor = r.cursor(7);
+        cursor.insert_str(" ы\n");
+        cursor.delete_char();
+        expected.insert_str(node::char_to_byte_in(&expected, 7), " ы");
+        assert_eq!(r.metadata(), Some(Metadata::of(&expected)));
+
+        let (mut left, right) = r.split(12);
+        assert_eq!(
+            right.metadata().map(|m| m.words()),
+            Some(right.to_string().split_whitespace().count())
+        );
+        left.concat(right);
+        assert_eq!(left.metadata(), Some(Metadata::of(&expected)));
+
+        left.delete(..);
+        assert_eq!(left.metadata(), Some(Metadata::default()));
+        left.insert(0, "a b");
+        assert_eq!(left.metadata().map(|m| m.words()), Some(2));
+
+        left.concat(Rope::from("c"));
+        assert_eq!(left.metadata(), None);
+        assert_eq!(Rope::from("a b").metadata(), None);
+    }
+
+    #[test]
+    fn config_bounds() {
+        let config = RopeConfig {
+            max_leaf_bytes: 8,
+            min_leaf_bytes: 4,
+        };
+        let mut expected = "lorem ipsum, привет мир\n".repeat(4);
+        let mut builder = RopeBuilder::with_config(config);
+        builder.append(&expected);
+        let mut r = builder.finish();
+        assert_eq!(r.config(), config);
+
+        let assert_bounds = |r: &Rope, expected: &str| {
+            assert_eq!(r.to_string(), expected);
+            let lens: Vec<_> = r.chunks().map(str::len).collect();
+            assert!(lens.iter().all(|&len| len <= 8 + 3), "{lens:?}");
+            assert!(lens.windows(2).all(|w| w[0] >= 4 || w[1] >= 4), "{lens:?}");
+        };
+
+        let mut cursor = r.cursor(30);
+        for c in "dolor sit amet".chars() {
+            cursor.insert_char(c);
+        }
+        expected.insert_str(node::char_to_byte_in(&expected, 30), "dolor sit amet");
+        assert_bounds(&r, &expected);
+
+        let mut cursor = r.cursor(50);
+        for _ in 0..25 {
+            cursor.delete_char();
+        }
+        let (start, end) = (
+            node::char_to_byte_in(&expected, 25),
+            node::char_to_byte_in(&expected, 50),
+        );
+        expected.replace_range(start..end, "");
+        assert_bounds(&r, &expected);
+
+        r.replace(3..40, "ab");
+        let (start, end) = (
+            node::char_to_byte_in(&expected, 3),
+            node::char_to_byte_in(&expected, 40),
+        );
+        expected.replace_range(start..end, "ab");
+        assert_bounds(&r, &expected);
+    }
+
+    #[test]
+    fn checksum() {
+        let s = "привет, мир\nhow are you?";
+        let mut r = Rope::from_str_chunked(s, 3);
+        let saved = r.checksum();
+        assert_eq!(saved, Rope::from(s).checksum());
+
+        r.insert(5, "!");
+        assert_ne!(r.checksum(), saved);
+        r.cursor(6).delete_char();
+        assert_eq!(r.checksum(), saved);
+
+        let removed = r.remove(2..15);
+        assert_ne!(r.checksum(), saved);
+        r.insert(2, &removed);
+        assert_eq!(r.checksum(), saved);
+        assert_ne!(Rope::new().checksum(), Rope::from("\0").checksum());
+    }
 }
@@ -0,0 +1,152 @@
+//! Optional text statistics maintained in the nodes of the `Rope`
+//!
+//! Every tracking node stores the statistics of its whole subtree. They are merged from the
+//! children's statistics in constant time, so edits only recompute the nodes on the way to the
+//! changed leaf
+
+/// Statistics of a piece of text that can be combined with the statistics of the text following
+/// it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    chars: usize,
+    words: usize,
+    /// Length of the longest line that is delimited by line breaks on both sides
+    longest_inner_line: usize,
+    /// Length of the text before the first line break
+    first_line: usize,
+    /// Length of the text after the last line break
+    last_line: usize,
+    has_newline: bool,
+    start: Edge,
+    end: Edge,
+}
+
+/// Properties of one end of a text that matter when it is joined with another text
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Edge {
+    /// The character at the end is not whitespace
+    in_word: bool,
+    /// The text starts with `\n` or ends with `\r`, which may be a half of a `\r\n`
+    crlf_half: bool,
+}
+
+impl Metadata {
+    /// Collects the statistics of `s`
+    pub(crate) fn of(s: &str) -> Self {
+        let mut metadata = Self::default();
+        let mut line = 0;
+        let mut in_word = false;
+        let mut prev_cr = false;
+
+        for c in s.chars() {
+            metadata.chars += 1;
+            if c == '\n' {
+                let len = line - usize::from(prev_cr);
+                if metadata.has_newline {
+                    metadata.longest_inner_line = metadata.longest_inner_line.max(len);
+                } else {
+                    metadata.first_line = len;
+                    metadata.has_newline = true;
+                }
+                line = 0;
+            } else {
+                line += 1;
+            }
+
+            let is_word = !c.is_whitespace();
+            if is_word && !in_word {
+                metadata.words += 1;
+            }
+            in_word = is_word;
+            prev_cr = c == '\r';
+        }
+
+        if !metadata.has_newline {
+            metadata.first_line = line;
+        }
+        metadata.last_line = line;
+        metadata.start = Edge {
+            in_word: s.chars().next().is_some_and(|c| !c.is_whitespace()),
+            crlf_half: s.starts_with('\n'),
+        };
+        metadata.end = Edge {
+            in_word,
+            crlf_half: prev_cr,
+        };
+        metadata
+    }
+
+    /// Returns the statistics of the text described by `self` followed by the text described by
+    /// `r`
+    #[must_use]
+    pub(crate) fn merge(self, r: Self) -> Self {
+        if self.chars == 0 {
+            return r;
+        }
+        if r.chars == 0 {
+            return self;
+        }
+
+        // A `\r\n` split between the two texts is a single line break
+        let split_crlf = usize::from(self.end.crlf_half && r.start.crlf_half);
+        let seam_line = self.last_line + r.first_line - split_crlf;
+        let mut longest_inner_line = self.longest_inner_line.max(r.longest_inner_line);
+        if self.has_newline && r.has_newline {
+            longest_inner_line = longest_inner_line.max(seam_line);
+        }
+
+        Self {
+            chars: self.chars + r.chars,
+            words: self.words + r.words - usize::from(self.end.in_word && r.start.in_word),
+            longest_inner_line,
+            first_line: if self.has_newline {
+                self.first_line
+            } else {
+                seam_line
+            },
+            last_line: if r.has_newline {
+                r.last_line
+            } else {
+                seam_line
+            },
+            has_newline: self.has_newline || r.has_newline,
+            start: self.start,
+            end: r.end,
+        }
+    }
+
+    /// Returns the number of whitespace-separated words
+    #[must_use]
+    pub const fn words(&self) -> usize {
+        self.words
+    }
+
+    /// Returns the length of the longest line in characters, not counting the line break
+    #[must_use]
+    pub fn longest_line(&self) -> usize {
+        self.longest_inner_line
+            .max(self.first_line)
+            .max(self.last_line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_matches_whole_text() {
+        let s = "  привет, мир\r\n\nhello   wonderful\r\nworld \n\r\n👍🏽 end\r";
+        for split in s.char_indices().map(|(b, _)| b) {
+            let (l, r) = s.split_at(split);
+            let merged = Metadata::of(l).merge(Metadata::of(r));
+            assert_eq!(merged, Metadata::of(s), "split at {split}");
+        }
+
+        let metadata = Metadata::of(s);
+        assert_eq!(metadata.words(), s.split_whitespace().count());
+        assert_eq!(metadata.longest_line(), "hello   wonderful".len());
+        assert_eq!(Metadata::of("ab\r\ncd").longest_line(), 2);
+        assert_eq!(Metadata::of("ab\r").longest_line(), 3);
+    }
+}
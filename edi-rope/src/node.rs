@@ -1,31 +1,54 @@
 //! Node of the rope's inner tree
 
-use std::fmt::{Debug, Write};
+use std::{
+    fmt::{Debug, Write},
+    ops::Range,
+    sync::Arc,
+};
 
-use crate::iter::Lines;
+use crate::{checksum::Checksum, metadata::Metadata};
+
+#[cfg(feature = "graphemes")]
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
 
 /// A node in the rope binary tree.
+///
+/// Children are reference counted, so cloning a node is cheap and the subtrees are shared until
+/// one of the copies is modified
+#[derive(Clone)]
 pub(crate) enum Node {
     /// A leaf node contains an immutable string.
     /// Any operation that modifies the contained string should create new leaf nodes.
     Leaf {
         /// A part of the string that the rope represents
-        value: Box<str>,
+        value: Arc<str>,
         /// Length of the `value` field in utf-8 characters
         char_len: usize,
         /// Total number of newlines in the string
         newlines: usize,
+        /// Statistics of the string, if the rope tracks them
+        metadata: Option<Metadata>,
+        /// Hash of the string
+        checksum: Checksum,
     },
     /// A value node contains a cumulative length of the left subtree leaf nodes' lengths.
     Value {
         /// Cumulative length of the left subtree leaf nodes' lengths a.k.a. `weight`
         left_len: usize,
+        /// Cumulative length of the left subtree leaf nodes' values in bytes
+        left_bytes: usize,
         /// Cumulative length of the left subtree leaf nodes' newline counts
         left_newlines: usize,
+        /// Height of the subtree rooted at this node, leaves have the height of zero
+        height: usize,
         /// The left child of the node
-        l: Option<Box<Node>>,
+        l: Option<Arc<Node>>,
         /// The right child of the node
-        r: Option<Box<Node>>,
+        r: Option<Arc<Node>>,
+        /// Statistics of the whole subtree, if every leaf in it tracks them
+        metadata: Option<Metadata>,
+        /// Hash of the whole subtree
+        checksum: Checksum,
     },
 }
 
@@ -37,13 +60,283 @@ impl Debug for Node {
 
 impl Node {
     pub fn new_leaf(value: &str) -> Self {
+        Self::new_leaf_with(value, false)
+    }
+
+    /// Creates a leaf that collects the statistics of `value` if `metadata` is set
+    pub fn new_leaf_with(value: &str, metadata: bool) -> Self {
         let char_len = value.chars().count();
-        let newlines = value.chars().filter(|&c| c == '\n').count();
-        let value = Box::from(value);
+        let newlines = count_newlines(value);
+        let metadata = metadata.then(|| Metadata::of(value));
+        let checksum = Checksum::of(value);
+        let value = Arc::from(value);
         Self::Leaf {
             value,
             char_len,
             newlines,
+            metadata,
+            checksum,
+        }
+    }
+
+    /// Creates a value node with `l` and `r` as its children
+    pub fn new_value(l: Node, r: Node) -> Self {
+        Self::Value {
+            left_len: l.full_weight(),
+            left_bytes: l.full_bytes(),
+            left_newlines: l.full_newlines(),
+            height: 1 + l.height().max(r.height()),
+            metadata: Self::merged_metadata(Some(&l), Some(&r)),
+            checksum: l.checksum().merge(r.checksum()),
+            l: Some(Arc::new(l)),
+            r: Some(Arc::new(r)),
+        }
+    }
+
+    /// Returns the statistics of the subtree, or `None` if some of its leaves do not track them
+    ///
+    /// Empty leaves do not affect the statistics, so they are considered to track them
+    pub(crate) fn metadata(&self) -> Option<Metadata> {
+        match self {
+            Node::Leaf {
+                value, metadata, ..
+            } => metadata.or_else(|| value.is_empty().then(Metadata::default)),
+            Node::Value { metadata, .. } => *metadata,
+        }
+    }
+
+    /// Returns the hash of the subtree's text
+    pub(crate) const fn checksum(&self) -> Checksum {
+        match self {
+            Node::Leaf { checksum, .. } | Node::Value { checksum, .. } => *checksum,
+        }
+    }
+
+    fn merged_checksum(l: Option<&Node>, r: Option<&Node>) -> Checksum {
+        let l = l.map_or_else(Checksum::default, Node::checksum);
+        let r = r.map_or_else(Checksum::default, Node::checksum);
+        l.merge(r)
+    }
+
+    fn merged_metadata(l: Option<&Node>, r: Option<&Node>) -> Option<Metadata> {
+        let l = l.map_or(Some(Metadata::default()), Node::metadata)?;
+        let r = r.map_or(Some(Metadata::default()), Node::metadata)?;
+        Some(l.merge(r))
+    }
+
+    /// Concatenates `l` and `r`, rotating the nodes along the seam so that the heights of any
+    /// node's children differ by at most one, given that it holds for both `l` and `r`
+    ///
+    /// Only the nodes on the seam are touched, so the cost is proportional to the difference of
+    /// heights of `l` and `r`
+    pub fn join(l: Node, r: Node) -> Self {
+        if l.is_empty_leaf() {
+            return r;
+        }
+        if r.is_empty_leaf() {
+            return l;
+        }
+
+        let (l_height, r_height) = (l.height(), r.height());
+        if l_height > r_height + 1 {
+            let (ll, lr) = l.into_children();
+            let joined = Self::join(lr, r);
+            if joined.height() <= ll.height() + 1 {
+                return Self::new_value(ll, joined);
+            }
+
+            let (jl, jr) = joined.into_children();
+            if jl.height() > jr.height() {
+                let (jll, jlr) = jl.into_children();
+                return Self::new_value(Self::new_value(ll, jll), Self::new_value(jlr, jr));
+            }
+            return Self::new_value(Self::new_value(ll, jl), jr);
+        }
+
+        if r_height > l_height + 1 {
+            let (rl, rr) = r.into_children();
+            let joined = Self::join(l, rl);
+            if joined.height() <= rr.height() + 1 {
+                return Self::new_value(joined, rr);
+            }
+
+            let (jl, jr) = joined.into_children();
+            if jr.height() > jl.height() {
+                let (jrl, jrr) = jr.into_children();
+                return Self::new_value(Self::new_value(jl, jrl), Self::new_value(jrr, rr));
+            }
+            return Self::new_value(jl, Self::new_value(jr, rr));
+        }
+
+        Self::new_value(l, r)
+    }
+
+    /// Splits the node at the character index, joining the subtrees on both sides of the split
+    pub fn split(self, idx: usize) -> (Node, Node) {
+        match self {
+            Node::Leaf {
+                ref value,
+                metadata,
+                ..
+            } => {
+                let byte_idx = char_to_byte_in(value, idx);
+                if byte_idx == 0 {
+                    return (Node::default(), self);
+                }
+                if byte_idx == value.len() {
+                    return (self, Node::default());
+                }
+
+                (
+                    Node::new_leaf_with(&value[..byte_idx], metadata.is_some()),
+                    Node::new_leaf_with(&value[byte_idx..], metadata.is_some()),
+                )
+            }
+            Node::Value { left_len, .. } => {
+                let (l, r) = self.into_children();
+                if idx < left_len {
+                    let (left, right) = l.split(idx);
+                    (left, Self::join(right, r))
+                } else {
+                    let (left, right) = r.split(idx - left_len);
+                    (Self::join(l, left), right)
+                }
+            }
+        }
+    }
+
+    /// Follows `path` from the node, going right on `true` and left on `false`
+    ///
+    /// Missing children are treated as the end of the path
+    pub(crate) fn descend(&self, path: &[bool]) -> &Node {
+        let mut node = self;
+        for &right in path {
+            let child = if right { node.right() } else { node.left() };
+            let Some(child) = child else {
+                break;
+            };
+            node = child;
+        }
+        node
+    }
+
+    /// Replaces bytes in `range` of the leaf at the end of `path` with `s`, keeping the counters
+    /// of every node on the path up to date
+    ///
+    /// `range` must lie on character boundaries of the leaf. The checksums of the leaf and its
+    /// ancestors are recomputed, as well as their statistics if `metadata` is set
+    pub(crate) fn splice_leaf(
+        &mut self,
+        path: &[bool],
+        range: Range<usize>,
+        s: &str,
+        metadata: bool,
+    ) {
+        let Node::Leaf { value, .. } = self.descend(path) else {
+            unreachable!("path passed to splice_leaf should end in a leaf");
+        };
+        let removed = &value[range.clone()];
+        let (removed_chars, removed_newlines) = (removed.chars().count(), count_newlines(removed));
+        let (added_chars, added_newlines) = (s.chars().count(), count_newlines(s));
+
+        let mut node = &mut *self;
+        for &right in path {
+            let Node::Value {
+                left_len,
+                left_bytes,
+                left_newlines,
+                l,
+                r,
+                ..
+            } = node
+            else {
+                break;
+            };
+
+            let child = if right {
+                r
+            } else {
+                *left_len = *left_len + added_chars - removed_chars;
+                *left_bytes = *left_bytes + s.len() - range.len();
+                *left_newlines = *left_newlines + added_newlines - removed_newlines;
+                l
+            };
+            let Some(child) = child.as_mut().map(Arc::make_mut) else {
+                unreachable!("path passed to splice_leaf should end in a leaf");
+            };
+            node = child;
+        }
+
+        let Node::Leaf {
+            value,
+            char_len,
+            newlines,
+            metadata: leaf_metadata,
+            checksum,
+        } = node
+        else {
+            unreachable!("path passed to splice_leaf should end in a leaf");
+        };
+
+        let mut spliced = String::with_capacity(value.len() + s.len() - range.len());
+        spliced.push_str(&value[..range.start]);
+        spliced.push_str(s);
+        spliced.push_str(&value[range.end..]);
+
+        *value = Arc::from(spliced);
+        *char_len = *char_len + added_chars - removed_chars;
+        *newlines = *newlines + added_newlines - removed_newlines;
+        *leaf_metadata = metadata.then(|| Metadata::of(value));
+        *checksum = Checksum::of(value);
+
+        self.refresh_summaries(path);
+    }
+
+    /// Recomputes the checksums and statistics of the value nodes along `path` from their
+    /// children
+    fn refresh_summaries(&mut self, path: &[bool]) {
+        let Node::Value {
+            l,
+            r,
+            metadata,
+            checksum,
+            ..
+        } = self
+        else {
+            return;
+        };
+        let Some((&right, rest)) = path.split_first() else {
+            return;
+        };
+
+        let child = if right { r.as_mut() } else { l.as_mut() };
+        if let Some(child) = child {
+            Arc::make_mut(child).refresh_summaries(rest);
+        }
+        *metadata = Self::merged_metadata(l.as_deref(), r.as_deref());
+        *checksum = Self::merged_checksum(l.as_deref(), r.as_deref());
+    }
+
+    /// Returns the height of the subtree rooted at the node
+    pub const fn height(&self) -> usize {
+        match self {
+            Node::Leaf { .. } => 0,
+            Node::Value { height, .. } => *height,
+        }
+    }
+
+    fn is_empty_leaf(&self) -> bool {
+        matches!(self, Node::Leaf { value, .. } if value.is_empty())
+    }
+
+    /// Takes the children of a value node, missing children are replaced with empty leaves
+    fn into_children(self) -> (Node, Node) {
+        match self {
+            Node::Leaf { .. } => (self, Node::default()),
+            Node::Value { l, r, .. } => (
+                l.map_or_else(Node::default, Arc::unwrap_or_clone),
+                r.map_or_else(Node::default, Arc::unwrap_or_clone),
+            ),
         }
     }
 
@@ -77,6 +370,17 @@ impl Node {
         }
     }
 
+    /// Returns the weight of the node and all its children in bytes
+    pub fn full_bytes(&self) -> usize {
+        match self {
+            Node::Leaf { value, .. } => value.len(),
+            Node::Value { left_bytes, r, .. } => {
+                let r_bytes = r.as_deref().map_or(0, Self::full_bytes);
+                left_bytes + r_bytes
+            }
+        }
+    }
+
     /// Returns number of newlines of the node and all it's children combined
     pub fn full_newlines(&self) -> usize {
         match self {
@@ -106,12 +410,202 @@ impl Node {
         }
     }
 
-    #[must_use]
-    pub(crate) fn index_of_line(&self, line: usize) -> usize {
-        Lines::new(self)
-            .parse_contents(false)
-            .nth(line)
-            .map_or_else(|| self.full_weight(), |info| info.character_offset)
+    /// Converts a character index into a byte index by descending the tree
+    ///
+    /// Indices past the end of the node are clamped to its byte length
+    pub(crate) fn char_to_byte(&self, char_idx: usize) -> usize {
+        match self {
+            Node::Leaf { value, .. } => char_to_byte_in(value, char_idx),
+            Node::Value {
+                left_len,
+                left_bytes,
+                l,
+                r,
+                ..
+            } => {
+                if char_idx < *left_len {
+                    return l.as_deref().map_or(0, |l| l.char_to_byte(char_idx));
+                }
+
+                left_bytes
+                    + r.as_deref()
+                        .map_or(0, |r| r.char_to_byte(char_idx - left_len))
+            }
+        }
+    }
+
+    /// Converts a byte index into the index of the character containing that byte by descending
+    /// the tree
+    ///
+    /// Indices past the end of the node are clamped to its character length
+    pub(crate) fn byte_to_char(&self, byte_idx: usize) -> usize {
+        match self {
+            Node::Leaf {
+                value, char_len, ..
+            } => {
+                if byte_idx >= value.len() {
+                    return *char_len;
+                }
+
+                value
+                    .char_indices()
+                    .take_while(|&(b, _)| b <= byte_idx)
+                    .count()
+                    - 1
+            }
+            Node::Value {
+                left_len,
+                left_bytes,
+                l,
+                r,
+                ..
+            } => {
+                if byte_idx < *left_bytes {
+                    return l.as_deref().map_or(0, |l| l.byte_to_char(byte_idx));
+                }
+
+                left_len
+                    + r.as_deref()
+                        .map_or(0, |r| r.byte_to_char(byte_idx - left_bytes))
+            }
+        }
+    }
+
+    /// Returns the leaf containing the byte at `byte_idx` along with the byte offset of its start
+    ///
+    /// For indices past the end of the node the last leaf is returned
+    #[cfg(feature = "graphemes")]
+    pub(crate) fn chunk_at_byte(&self, byte_idx: usize) -> Option<(&str, usize)> {
+        match self {
+            Node::Leaf { value, .. } => Some((value, 0)),
+            Node::Value {
+                left_bytes, l, r, ..
+            } => {
+                if byte_idx < *left_bytes {
+                    return l.as_deref().and_then(|l| l.chunk_at_byte(byte_idx));
+                }
+
+                r.as_deref()
+                    .and_then(|r| r.chunk_at_byte(byte_idx - left_bytes))
+                    .map(|(chunk, start)| (chunk, start + left_bytes))
+                    .or_else(|| l.as_deref().and_then(|l| l.chunk_at_byte(byte_idx)))
+            }
+        }
+    }
+
+    /// Advances `cursor` to the next grapheme boundary, feeding it the leaves it asks for
+    ///
+    /// Returns the byte offset of the boundary, or `None` if the cursor is at the end
+    #[cfg(feature = "graphemes")]
+    pub(crate) fn next_grapheme_boundary(&self, cursor: &mut GraphemeCursor) -> Option<usize> {
+        let mut chunk_pos = cursor.cur_cursor();
+        loop {
+            let (chunk, start) = self.chunk_at_byte(chunk_pos)?;
+            match cursor.next_boundary(chunk, start) {
+                Ok(boundary) => return boundary,
+                Err(GraphemeIncomplete::NextChunk) => chunk_pos = start + chunk.len(),
+                Err(GraphemeIncomplete::PreContext(end)) => self.provide_context(cursor, end)?,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// Moves `cursor` back to the previous grapheme boundary, feeding it the leaves it asks for
+    ///
+    /// Returns the byte offset of the boundary, or `None` if the cursor is at the start
+    #[cfg(feature = "graphemes")]
+    pub(crate) fn prev_grapheme_boundary(&self, cursor: &mut GraphemeCursor) -> Option<usize> {
+        let mut chunk_pos = cursor.cur_cursor().checked_sub(1)?;
+        loop {
+            let (chunk, start) = self.chunk_at_byte(chunk_pos)?;
+            match cursor.prev_boundary(chunk, start) {
+                Ok(boundary) => return boundary,
+                Err(GraphemeIncomplete::PrevChunk) => chunk_pos = start.checked_sub(1)?,
+                Err(GraphemeIncomplete::PreContext(end)) => self.provide_context(cursor, end)?,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    #[cfg(feature = "graphemes")]
+    fn provide_context(&self, cursor: &mut GraphemeCursor, end: usize) -> Option<()> {
+        let (chunk, start) = self.chunk_at_byte(end.checked_sub(1)?)?;
+        cursor.provide_context(&chunk[..end - start], start);
+        Some(())
+    }
+
+    /// Returns the number of the line containing the character index by descending the tree
+    ///
+    /// Indices past the end of the node belong to the last line
+    pub(crate) fn char_to_line(&self, char_idx: usize) -> usize {
+        let mut node = self;
+        let (mut idx, mut lines) = (char_idx, 0);
+        loop {
+            match node {
+                Node::Leaf { value, .. } => {
+                    let end = char_to_byte_in(value, idx);
+                    return lines + count_newlines(&value[..end]);
+                }
+                Node::Value {
+                    left_len,
+                    left_newlines,
+                    l,
+                    r,
+                    ..
+                } => {
+                    if let Some(l) = l.as_deref().filter(|_| idx < *left_len) {
+                        node = l;
+                    } else if let Some(r) = r.as_deref() {
+                        idx -= left_len;
+                        lines += left_newlines;
+                        node = r;
+                    } else {
+                        return lines + left_newlines;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the character index of the line start by descending the tree
+    ///
+    /// Lines past the end of the node start at its end
+    pub(crate) fn line_to_char(&self, line: usize) -> usize {
+        let mut node = self;
+        let (mut line, mut chars) = (line, 0);
+        loop {
+            if line == 0 {
+                return chars;
+            }
+
+            match node {
+                Node::Leaf {
+                    value, char_len, ..
+                } => {
+                    let Some((newline, _)) = value.match_indices('\n').nth(line - 1) else {
+                        return chars + char_len;
+                    };
+                    return chars + value[..=newline].chars().count();
+                }
+                Node::Value {
+                    left_len,
+                    left_newlines,
+                    l,
+                    r,
+                    ..
+                } => {
+                    if let Some(l) = l.as_deref().filter(|_| line <= *left_newlines) {
+                        node = l;
+                    } else if let Some(r) = r.as_deref() {
+                        line -= left_newlines;
+                        chars += left_len;
+                        node = r;
+                    } else {
+                        return chars + left_len;
+                    }
+                }
+            }
+        }
     }
 
     //// Returns an ASCII tree representation of the node and its children
@@ -143,13 +637,16 @@ impl Node {
             }
             Node::Value {
                 left_len,
+                left_bytes,
                 left_newlines,
+                height,
                 l,
                 r,
+                ..
             } => {
                 let _ = writeln!(
                     buffer,
-                    "{prefix}Value ({}): left_len={left_len}, left_newlines={left_newlines}",
+                    "{prefix}Value ({}): left_len={left_len}, left_bytes={left_bytes}, left_newlines={left_newlines}, height={height}",
                     if is_r { "r" } else { "l" },
                 );
 
@@ -189,3 +686,139 @@ impl Default for Node {
         Node::new_leaf("")
     }
 }
+
+/// Returns the byte offset of `char_idx`th character in `s`, or `s.len()` if it is out of bounds
+pub(crate) fn char_to_byte_in(s: &str, char_idx: usize) -> usize {
+    s.char_indices().nth(char_idx).map_or(s.len(), |(b, _)| b)
+}
+
+fn count_newlines(s: &str) -> usize {
+    s.bytes().filter(|&b| b == b'\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+    use super::*;
+
+    const PIECES: &[&str] = &["a", "hello", "\n", "мир", "👍🏽", "é\n"];
+
+    /// Appends the text of the subtree to `buffer`, returning the number of its leaves
+    fn collect(node: &Node, buffer: &mut String) -> usize {
+        match node {
+            Node::Leaf { value, .. } => {
+                buffer.push_str(value);
+                1
+            }
+            Node::Value { l, r, .. } => {
+                l.as_deref().map_or(0, |l| collect(l, buffer))
+                    + r.as_deref().map_or(0, |r| collect(r, buffer))
+            }
+        }
+    }
+
+    /// Returns the height of the subtree, checking the stored heights and that the heights of the
+    /// children of every node differ by at most one
+    fn checked_height(node: &Node) -> usize {
+        let Node::Value { l, r, height, .. } = node else {
+            return 0;
+        };
+        let left = l.as_deref().map_or(0, checked_height);
+        let right = r.as_deref().map_or(0, checked_height);
+        assert!(
+            left.abs_diff(right) <= 1,
+            "children have heights {left} and {right}"
+        );
+        assert_eq!(*height, 1 + left.max(right));
+        *height
+    }
+
+    /// Checks that the tree holds `text` and is balanced. A tree of height `h` whose children
+    /// differ in height by at most one has at least `F(h + 2)` leaves, so its height is below
+    /// `1.45 * log2(leaves + 2)`
+    fn assert_balanced(node: &Node, text: &str) {
+        let mut collected = String::new();
+        let leaves = collect(node, &mut collected);
+        assert_eq!(collected, text);
+        assert_eq!(node.full_weight(), text.chars().count());
+
+        let height = checked_height(node);
+        let (mut min_leaves, mut next) = (1, 2);
+        for _ in 0..height {
+            (min_leaves, next) = (next, min_leaves + next);
+        }
+        assert!(
+            leaves >= min_leaves,
+            "height {height} needs at least {min_leaves} leaves, the tree has {leaves}"
+        );
+    }
+
+    fn random_tree(rng: &mut SmallRng) -> (Node, String) {
+        let (mut node, mut text) = (Node::default(), String::new());
+        for _ in 0..rng.gen_range(1..8) {
+            let piece = PIECES[rng.gen_range(0..PIECES.len())];
+            node = Node::join(node, Node::new_leaf(piece));
+            text.push_str(piece);
+        }
+        (node, text)
+    }
+
+    fn split_text(text: &str, idx: usize) -> (String, String) {
+        let (left, right) = text.split_at(char_to_byte_in(text, idx));
+        (String::from(left), String::from(right))
+    }
+
+    #[test]
+    fn stays_balanced() {
+        for seed in 0..8 {
+            let mut rng = SmallRng::seed_from_u64(seed);
+            let (mut node, mut text) = (Node::default(), String::new());
+            for step in 0..1500 {
+                let len = text.chars().count();
+                let idx = rng.gen_range(0..=len);
+                (node, text) = match rng.gen_range(0..4) {
+                    // Split and concatenate the halves again
+                    0 => {
+                        let (l, r) = node.split(idx);
+                        (Node::join(l, r), text)
+                    }
+                    // Concatenate a new tree on either side
+                    1 => {
+                        let (other, other_text) = random_tree(&mut rng);
+                        if rng.gen_bool(0.5) {
+                            (Node::join(node, other), text + &other_text)
+                        } else {
+                            (Node::join(other, node), other_text + &text)
+                        }
+                    }
+                    // Insert a leaf in the middle
+                    2 => {
+                        let piece = PIECES[rng.gen_range(0..PIECES.len())];
+                        let (l, r) = node.split(idx);
+                        let (left, right) = split_text(&text, idx);
+                        let node = Node::join(Node::join(l, Node::new_leaf(piece)), r);
+                        (node, left + piece + &right)
+                    }
+                    // Remove a range
+                    _ => {
+                        let end = rng.gen_range(idx..=len.min(idx + 8));
+                        let (l, rest) = node.split(idx);
+                        let (_, r) = rest.split(end - idx);
+                        let (left, rest) = split_text(&text, idx);
+                        let (_, right) = split_text(&rest, end - idx);
+                        (Node::join(l, r), left + &right)
+                    }
+                };
+
+                let result = std::panic::catch_unwind(|| assert_balanced(&node, &text));
+                assert!(result.is_ok(), "seed {seed}, step {step}");
+            }
+            assert!(
+                node.height() >= 8,
+                "seed {seed} grew only to {}",
+                node.height()
+            );
+        }
+    }
+}
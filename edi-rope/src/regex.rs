@@ -0,0 +1,210 @@
+//! Regular expression search over `Rope` contents, enabled by the `regex` feature
+//!
+//! Matches are found by feeding the bytes of the rope's leaves to the lazy DFAs of
+//! `regex_automata` one at a time, so the contents are never collected into a `String`. A forward
+//! DFA finds where the leftmost match ends, then a reverse DFA walks back from there to find where
+//! it starts
+
+use std::ops::Range;
+
+use regex_automata::{
+    Anchored,
+    hybrid::{
+        dfa::{Cache, DFA},
+        regex::{self, Regex},
+    },
+    util::start,
+};
+
+pub use regex_automata::hybrid::BuildError;
+
+use crate::{iter::Chunks, node::Node};
+
+/// An iterator over character ranges of non-overlapping matches of a regular expression, that is
+/// returned after calling `regex_find_iter` method of `Rope`
+///
+/// Matches follow the leftmost-first semantics of the `regex` crate. An empty match right after
+/// the previous match is skipped
+#[derive(Debug)]
+pub struct RegexMatches<'a> {
+    node: &'a Node,
+    regex: Regex,
+    cache: regex::Cache,
+    /// Byte offset the next search starts at
+    from_b: usize,
+    /// Byte offset where the last yielded match ends
+    last_end_b: Option<usize>,
+}
+
+impl<'a> RegexMatches<'a> {
+    pub(crate) fn new(node: &'a Node, pattern: &str) -> Result<Self, Box<BuildError>> {
+        let regex = Regex::new(pattern).map_err(Box::new)?;
+        Ok(Self {
+            node,
+            cache: regex.create_cache(),
+            regex,
+            from_b: 0,
+            last_end_b: None,
+        })
+    }
+
+    /// Returns the byte range of the leftmost match that starts at or after `from_b`
+    ///
+    /// The lazy DFAs are built with the default configuration, which never gives up on a search,
+    /// so the only errors are the ones that cannot happen
+    fn find_at(&mut self, from_b: usize) -> Option<Range<usize>> {
+        let (fwd_cache, rev_cache) = self.cache.as_parts_mut();
+        let end = find_end(self.node, self.regex.forward(), fwd_cache, from_b)?;
+        let start = find_start(self.node, self.regex.reverse(), rev_cache, from_b, end)?;
+        Some(start..end)
+    }
+}
+
+impl Iterator for RegexMatches<'_> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.from_b > self.node.full_bytes() {
+                return None;
+            }
+
+            let m = self.find_at(self.from_b)?;
+            if m.is_empty() && self.last_end_b == Some(m.end) {
+                // Steps over the whole character, so an empty match never splits one
+                let from = self.node.byte_to_char(self.from_b);
+                self.from_b = self.node.char_to_byte(from + 1).max(self.from_b + 1);
+                continue;
+            }
+
+            self.from_b = m.end;
+            self.last_end_b = Some(m.end);
+            return Some(self.node.byte_to_char(m.start)..self.node.byte_to_char(m.end));
+        }
+    }
+}
+
+/// Returns the byte before `offset`, which must lie on a character boundary
+fn byte_before(node: &Node, offset: usize) -> Option<u8> {
+    Chunks::new_range(node, 0..offset)
+        .next_back()
+        .and_then(|chunk| chunk.as_bytes().last().copied())
+}
+
+/// Returns the byte at `offset`, which must lie on a character boundary
+fn byte_at(node: &Node, offset: usize) -> Option<u8> {
+    Chunks::new_range(node, offset..node.full_bytes())
+        .next()
+        .and_then(|chunk| chunk.as_bytes().first().copied())
+}
+
+/// Runs the forward DFA from `from_b` and returns the end of the leftmost match
+fn find_end(node: &Node, dfa: &DFA, cache: &mut Cache, from_b: usize) -> Option<usize> {
+    let config = start::Config::new()
+        .anchored(Anchored::No)
+        .look_behind(byte_before(node, from_b));
+    let mut sid = dfa.start_state(cache, &config).ok()?;
+    let mut end = None;
+    let mut at = from_b;
+
+    for chunk in Chunks::new_range(node, from_b..node.full_bytes()) {
+        for &b in chunk.as_bytes() {
+            sid = dfa.next_state(cache, sid, b).ok()?;
+            if sid.is_tagged() {
+                // Matches are reported one byte late, so the match ends before `b`
+                if sid.is_match() {
+                    end = Some(at);
+                } else if sid.is_dead() || sid.is_quit() {
+                    return end;
+                }
+            }
+            at += 1;
+        }
+    }
+
+    sid = dfa.next_eoi_state(cache, sid).ok()?;
+    if sid.is_match() {
+        end = Some(at);
+    }
+    end
+}
+
+/// Runs the reverse DFA from `end` back to `from_b` and returns the start of the match that ends
+/// at `end`
+fn find_start(
+    node: &Node,
+    dfa: &DFA,
+    cache: &mut Cache,
+    from_b: usize,
+    end: usize,
+) -> Option<usize> {
+    let config = start::Config::new()
+        .anchored(Anchored::Yes)
+        .look_behind(byte_at(node, end));
+    let mut sid = dfa.start_state(cache, &config).ok()?;
+    let mut start = None;
+    let mut at = end;
+
+    for chunk in Chunks::new_range(node, from_b..end).rev() {
+        for &b in chunk.as_bytes().iter().rev() {
+            sid = dfa.next_state(cache, sid, b).ok()?;
+            if sid.is_tagged() {
+                // Matches are reported one byte late, so the match starts after `b`
+                if sid.is_match() {
+                    start = Some(at);
+                } else if sid.is_dead() || sid.is_quit() {
+                    return start;
+                }
+            }
+            at -= 1;
+        }
+    }
+
+    // The byte before the searched range is only looked at to tell whether a match starts at
+    // `from_b`
+    sid = match byte_before(node, from_b) {
+        Some(b) => dfa.next_state(cache, sid, b).ok()?,
+        None => dfa.next_eoi_state(cache, sid).ok()?,
+    };
+    if sid.is_match() {
+        start = Some(from_b);
+    }
+    start
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Rope;
+
+    #[test]
+    fn matches_across_leaves() {
+        let s = "ааб abab ababab мир мир\nмимир\nfoo123 bar45";
+        let r = Rope::from_str_chunked(s, 2);
+        let matches = |pattern| r.regex_find_iter(pattern).unwrap().collect::<Vec<_>>();
+
+        assert_eq!(matches("(ab)+"), [4..8, 9..15]);
+        assert_eq!(matches("м[а-я]+"), [16..19, 20..23, 24..29]);
+        assert_eq!(matches(r"[a-z]+\d+"), [30..36, 37..42]);
+        assert_eq!(matches(r"р\n."), [22..25, 28..31]);
+        assert_eq!(matches("(?m)^[^\n]"), [0..1, 24..25, 30..31]);
+        assert_eq!(matches("(?m)р$"), [22..23, 28..29]);
+        assert_eq!(matches(r"(?-u:\b)ab"), [4..6, 9..11]);
+        assert!(matches("нет").is_empty());
+    }
+
+    #[test]
+    fn empty_matches() {
+        let r = Rope::from_str_chunked("bяaa", 1);
+        let matches = |pattern| r.regex_find_iter(pattern).unwrap().collect::<Vec<_>>();
+
+        assert_eq!(matches("a*"), [0..0, 1..1, 2..4]);
+        assert_eq!(matches(""), [0..0, 1..1, 2..2, 3..3, 4..4]);
+        assert_eq!(Rope::new().regex_find_iter("").unwrap().count(), 1);
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(Rope::new().regex_find_iter("(unclosed").is_err());
+        assert!(Rope::new().regex_find_iter(r"\bword").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+//! Substring search over `Rope` contents
+//!
+//! The search streams the bytes of the rope's leaves through a Knuth-Morris-Pratt matcher, so
+//! neither the rope contents nor the text between matches are ever collected into a `String`
+
+use crate::{iter::Chunks, node::Node};
+
+/// A pattern prepared for the Knuth-Morris-Pratt search
+#[derive(Debug)]
+struct Pattern {
+    bytes: Vec<u8>,
+    /// Length of the longest proper prefix of `bytes[..=i]` that is also its suffix
+    fallback: Vec<usize>,
+    char_len: usize,
+}
+
+impl Pattern {
+    fn new(bytes: Vec<u8>, char_len: usize) -> Self {
+        let mut fallback = vec![0; bytes.len()];
+        let mut matched = 0;
+        for i in 1..bytes.len() {
+            while matched > 0 && bytes[i] != bytes[matched] {
+                matched = fallback[matched - 1];
+            }
+            if bytes[i] == bytes[matched] {
+                matched += 1;
+            }
+            fallback[i] = matched;
+        }
+
+        Self {
+            bytes,
+            fallback,
+            char_len,
+        }
+    }
+
+    /// Feeds the next byte to the matcher, returning the new number of matched bytes
+    fn step(&self, mut matched: usize, b: u8) -> usize {
+        while matched > 0 && self.bytes[matched] != b {
+            matched = self.fallback[matched - 1];
+        }
+        if self.bytes[matched] == b {
+            matched += 1;
+        }
+        matched
+    }
+
+    const fn is_match(&self, matched: usize) -> bool {
+        matched == self.bytes.len()
+    }
+}
+
+const fn is_char_start(b: u8) -> bool {
+    b & 0b1100_0000 != 0b1000_0000
+}
+
+/// An iterator over character indices of non-overlapping pattern matches, that is returned after
+/// calling `matches` method of `Rope`
+///
+/// An empty pattern never matches
+#[derive(Debug)]
+pub struct Matches<'a> {
+    chunks: Chunks<'a>,
+    chunk: &'a [u8],
+    pattern: Pattern,
+    matched: usize,
+    /// Number of characters before the current position
+    char_offset: usize,
+}
+
+impl<'a> Matches<'a> {
+    pub(crate) fn new(node: &'a Node, pattern: &str, from: usize) -> Self {
+        let from_b = node.char_to_byte(from);
+        Self {
+            chunks: Chunks::new_range(node, from_b..node.full_bytes()),
+            chunk: &[],
+            pattern: Pattern::new(pattern.as_bytes().to_vec(), pattern.chars().count()),
+            matched: 0,
+            char_offset: from.min(node.full_weight()),
+        }
+    }
+}
+
+impl Iterator for Matches<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pattern.bytes.is_empty() {
+            return None;
+        }
+
+        loop {
+            while let Some((&b, rest)) = self.chunk.split_first() {
+                self.chunk = rest;
+                if is_char_start(b) {
+                    self.char_offset += 1;
+                }
+
+                self.matched = self.pattern.step(self.matched, b);
+                if self.pattern.is_match(self.matched) {
+                    self.matched = 0;
+                    return Some(self.char_offset - self.pattern.char_len);
+                }
+            }
+
+            self.chunk = self.chunks.next()?.as_bytes();
+        }
+    }
+}
+
+/// Returns the character index of the last match of `pattern` that ends at or before `until`
+pub(crate) fn rfind(node: &Node, pattern: &str, until: usize) -> Option<usize> {
+    let until = until.min(node.full_weight());
+    if pattern.is_empty() {
+        return Some(until);
+    }
+
+    let until_b = node.char_to_byte(until);
+    let pattern = Pattern::new(pattern.bytes().rev().collect(), pattern.chars().count());
+    let mut matched = 0;
+    // Number of characters between the current position and `until`
+    let mut char_offset = 0;
+
+    for chunk in Chunks::new_range(node, 0..until_b).rev() {
+        for &b in chunk.as_bytes().iter().rev() {
+            if is_char_start(b) {
+                char_offset += 1;
+            }
+
+            matched = pattern.step(matched, b);
+            if pattern.is_match(matched) {
+                return Some(until - char_offset);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Rope;
+
+    fn naive_matches(s: &str, pattern: &str) -> Vec<usize> {
+        s.match_indices(pattern)
+            .map(|(b, _)| s[..b].chars().count())
+            .collect()
+    }
+
+    #[test]
+    fn matches_across_leaves() {
+        let s = "ааб abab ababab мир мир\nмимир";
+        let r = Rope::from_str_chunked(s, 2);
+        for pattern in [
+            "аб",
+            "ab",
+            "abab",
+            "мир",
+            "р\nм",
+            "мимир",
+            "b",
+            "нет",
+            "ааб abab",
+        ] {
+            assert_eq!(
+                r.matches(pattern).collect::<Vec<_>>(),
+                naive_matches(s, pattern),
+                "pattern: {pattern:?}"
+            );
+        }
+        assert_eq!(r.matches("").count(), 0);
+    }
+
+    #[test]
+    fn find() {
+        let s = "ааб abab ababab мир мир";
+        let r = Rope::from_str_chunked(s, 3);
+        assert_eq!(r.find("ab", 0), Some(4));
+        assert_eq!(r.find("ab", 5), Some(6));
+        assert_eq!(r.find("мир", 17), Some(20));
+        assert_eq!(r.find("мир", 21), None);
+        assert_eq!(r.find("", 3), Some(3));
+        assert_eq!(r.find("ab", 100), None);
+    }
+
+    #[test]
+    fn rfind() {
+        let s = "ааб abab ababab мир мир";
+        let r = Rope::from_str_chunked(s, 3);
+        let len = r.len();
+        assert_eq!(r.rfind("ab", len), Some(13));
+        assert_eq!(r.rfind("ab", 14), Some(11));
+        assert_eq!(r.rfind("ab", 15), Some(13));
+        assert_eq!(r.rfind("мир", len), Some(20));
+        assert_eq!(r.rfind("мир", 22), Some(16));
+        assert_eq!(r.rfind("аа", 1), None);
+        assert_eq!(r.rfind("аа", 2), Some(0));
+        assert_eq!(r.rfind("", 3), Some(3));
+        assert_eq!(r.rfind("x", len), None);
+    }
+}
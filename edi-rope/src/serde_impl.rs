@@ -0,0 +1,57 @@
+//! `serde` support for `Rope`, enabled by the `serde` feature
+//!
+//! A rope is serialized as a plain string, so the shape of the tree never leaks into the wire
+//! format and deserialized ropes are always balanced
+
+use std::fmt;
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, Visitor},
+};
+
+use crate::{DEFAULT_CHUNK_SIZE, Rope};
+
+impl Serialize for Rope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Serializers that support it write the leaves one by one through `Display`
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(RopeVisitor)
+    }
+}
+
+struct RopeVisitor;
+
+impl Visitor<'_> for RopeVisitor {
+    type Value = Rope;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        Ok(Rope::from_str_chunked(s, DEFAULT_CHUNK_SIZE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_test::{Token, assert_de_tokens, assert_ser_tokens};
+
+    use crate::Rope;
+
+    #[test]
+    fn text_round_trip() {
+        let s = "привет,\nмир\r\n";
+        let r = Rope::from_str_chunked(s, 2);
+        assert_ser_tokens(&r, &[Token::Str(s)]);
+        assert_de_tokens(&r, &[Token::Str(s)]);
+        assert_de_tokens(&r, &[Token::String(s)]);
+        assert_de_tokens(&Rope::new(), &[Token::BorrowedStr("")]);
+    }
+}
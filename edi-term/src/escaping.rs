@@ -1,11 +1,10 @@
 //! Terminal ANSI escape handling
 
-use std::borrow::Cow;
+use std::{borrow::Cow, ops::BitOr};
 
 use crate::coord::Coord;
 
 /// An ANSI color representation
-/// Does not support true color
 #[allow(unused)]
 #[allow(missing_docs)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -20,41 +19,128 @@ pub enum ANSIColor {
     Magenta,
     Cyan,
     White,
+    /// A true color, given by its red, green and blue components
+    Rgb(u8, u8, u8),
+    /// A color of the 256-color palette
+    Indexed(u8),
 }
 
 impl ANSIColor {
-    const fn value(self) -> &'static str {
+    fn value(self) -> Cow<'static, str> {
         match self {
-            Self::Reset => "\x1b[0m",
-            Self::Default => "\x1b[39m",
-            Self::Black => "\x1b[30m",
-            Self::Red => "\x1b[31m",
-            Self::Green => "\x1b[32m",
-            Self::Yellow => "\x1b[33m",
-            Self::Blue => "\x1b[34m",
-            Self::Magenta => "\x1b[35m",
-            Self::Cyan => "\x1b[36m",
-            Self::White => "\x1b[37m",
+            Self::Reset => Cow::Borrowed("\x1b[0m"),
+            Self::Default => Cow::Borrowed("\x1b[39m"),
+            Self::Black => Cow::Borrowed("\x1b[30m"),
+            Self::Red => Cow::Borrowed("\x1b[31m"),
+            Self::Green => Cow::Borrowed("\x1b[32m"),
+            Self::Yellow => Cow::Borrowed("\x1b[33m"),
+            Self::Blue => Cow::Borrowed("\x1b[34m"),
+            Self::Magenta => Cow::Borrowed("\x1b[35m"),
+            Self::Cyan => Cow::Borrowed("\x1b[36m"),
+            Self::White => Cow::Borrowed("\x1b[37m"),
+            Self::Rgb(r, g, b) => Cow::Owned(format!("\x1b[38;2;{r};{g};{b}m")),
+            Self::Indexed(i) => Cow::Owned(format!("\x1b[38;5;{i}m")),
         }
     }
 
-    const fn value_bg(self) -> &'static str {
+    fn value_bg(self) -> Cow<'static, str> {
         match self {
-            Self::Reset => "\x1b[0m",
-            Self::Default => "\x1b[49m",
-            Self::Black => "\x1b[40m",
-            Self::Red => "\x1b[41m",
-            Self::Green => "\x1b[42m",
-            Self::Yellow => "\x1b[43m",
-            Self::Blue => "\x1b[44m",
-            Self::Magenta => "\x1b[45m",
-            Self::Cyan => "\x1b[46m",
-            Self::White => "\x1b[47m",
+            Self::Reset => Cow::Borrowed("\x1b[0m"),
+            Self::Default => Cow::Borrowed("\x1b[49m"),
+            Self::Black => Cow::Borrowed("\x1b[40m"),
+            Self::Red => Cow::Borrowed("\x1b[41m"),
+            Self::Green => Cow::Borrowed("\x1b[42m"),
+            Self::Yellow => Cow::Borrowed("\x1b[43m"),
+            Self::Blue => Cow::Borrowed("\x1b[44m"),
+            Self::Magenta => Cow::Borrowed("\x1b[45m"),
+            Self::Cyan => Cow::Borrowed("\x1b[46m"),
+            Self::White => Cow::Borrowed("\x1b[47m"),
+            Self::Rgb(r, g, b) => Cow::Owned(format!("\x1b[48;2;{r};{g};{b}m")),
+            Self::Indexed(i) => Cow::Owned(format!("\x1b[48;5;{i}m")),
         }
     }
+
+    /// Returns the sequence that sets the color of underlines, which only the 256-color and true
+    /// color forms exist for
+    fn value_underline(self) -> Cow<'static, str> {
+        let index = match self {
+            Self::Reset => return Cow::Borrowed("\x1b[0m"),
+            Self::Default => return Cow::Borrowed("\x1b[59m"),
+            Self::Rgb(r, g, b) => return Cow::Owned(format!("\x1b[58;2;{r};{g};{b}m")),
+            Self::Black => 0,
+            Self::Red => 1,
+            Self::Green => 2,
+            Self::Yellow => 3,
+            Self::Blue => 4,
+            Self::Magenta => 5,
+            Self::Cyan => 6,
+            Self::White => 7,
+            Self::Indexed(i) => i,
+        };
+        Cow::Owned(format!("\x1b[58;5;{index}m"))
+    }
 }
 
-#[derive(PartialEq, Eq, Clone, Debug)]
+/// A set of text attributes, combined with `|`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Style(u8);
+
+#[allow(missing_docs)]
+impl Style {
+    pub const NONE: Self = Self(0);
+    pub const BOLD: Self = Self(1);
+    pub const DIM: Self = Self(1 << 1);
+    pub const ITALIC: Self = Self(1 << 2);
+    pub const UNDERLINE: Self = Self(1 << 3);
+    pub const REVERSE: Self = Self(1 << 4);
+    /// A curly underline, not supported by every terminal
+    pub const UNDERCURL: Self = Self(1 << 5);
+
+    /// Returns `true` if every attribute of `other` is set
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the attributes of `self` that are not set in `other`
+    #[must_use]
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+
+    /// Returns the SGR sequence that turns on every attribute of the style
+    fn value(self) -> String {
+        let codes = [
+            (Self::BOLD, "1"),
+            (Self::DIM, "2"),
+            (Self::ITALIC, "3"),
+            (Self::UNDERLINE, "4"),
+            (Self::REVERSE, "7"),
+            (Self::UNDERCURL, "4:3"),
+        ];
+        let params = codes
+            .iter()
+            .filter(|(attribute, _)| self.contains(*attribute))
+            .map(|(_, code)| *code)
+            .collect::<Vec<_>>();
+        format!("\x1b[{}m", params.join(";"))
+    }
+}
+
+impl BitOr for Style {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum CursorStyle {
     Line,
     Block,
@@ -84,6 +170,8 @@ pub enum ANSIEscape<'a> {
     SetColor(ANSIColor),
     /// Sets the backgrounod color to the ANSI color
     SetBgColor(ANSIColor),
+    /// Sets the color of underlines to the ANSI color, not supported by every terminal
+    SetUnderlineColor(ANSIColor),
     /// Makes the following text bold
     StartBold,
     /// Makes the following text NOT bold
@@ -96,14 +184,53 @@ pub enum ANSIEscape<'a> {
     StartUnderline,
     /// Makes the following text NOT underlined
     EndUnderline,
+    /// Turns on the attributes of the style, leaving the other attributes as they are
+    SetStyle(Style),
     /// Resets the styles for all the following text
     EndAll,
     /// Enters the alternate screen state
     EnterAlternateScreen,
     /// Exits the alternate screen state
     ExitAlternateScreen,
+    /// Makes the terminal surround pasted text with `input::PASTE_START` and `input::PASTE_END`
+    EnableBracketedPaste,
+    /// Makes the terminal send pasted text as if it was typed
+    DisableBracketedPaste,
+    /// Makes the terminal hold off displaying the following output until the update ends
+    BeginSynchronizedUpdate,
+    /// Displays everything written since the update began at once
+    EndSynchronizedUpdate,
+    /// Limits scrolling to the rows from `top` to `bottom`, both inclusive
+    SetScrollRegion {
+        /// The first row of the region
+        top: usize,
+        /// The last row of the region
+        bottom: usize,
+    },
+    /// Makes the whole screen scrollable again
+    ResetScrollRegion,
+    /// Moves the contents of the scroll region up by the given number of rows
+    ScrollUp(usize),
+    /// Moves the contents of the scroll region down by the given number of rows
+    ScrollDown(usize),
     /// Sets the cursor style
     ChangeCursor(CursorStyle),
+    /// Makes the cursor invisible
+    HideCursor,
+    /// Makes the cursor visible again
+    ShowCursor,
+    /// Writes `text` as a link to `uri` (OSC 8). Terminals that do not support links write just
+    /// the text
+    Hyperlink {
+        /// The target of the link
+        uri: Cow<'a, str>,
+        /// The text of the link
+        text: Cow<'a, str>,
+    },
+    /// Makes the following text a link to the given URI, until the link is ended
+    StartHyperlink(Cow<'a, str>),
+    /// Ends the link started with `StartHyperlink`
+    EndHyperlink,
 }
 
 impl<'a> ANSIEscape<'a> {
@@ -113,18 +240,37 @@ impl<'a> ANSIEscape<'a> {
             Self::ClearScreen => Cow::Borrowed("\x1b[2J"),
             Self::MoveTo(pos) => Cow::Owned(format!("\x1b[{};{}H", pos.y + 1, pos.x + 1)),
             Self::Write(text) => text,
-            Self::SetColor(color) => Cow::Borrowed(color.value()),
-            Self::SetBgColor(color) => Cow::Borrowed(color.value_bg()),
+            Self::SetColor(color) => color.value(),
+            Self::SetBgColor(color) => color.value_bg(),
+            Self::SetUnderlineColor(color) => color.value_underline(),
             Self::StartBold => Cow::Borrowed("\x1b[1m"),
             Self::EndBold => Cow::Borrowed("\x1b[22m"),
             Self::StartItalic => Cow::Borrowed("\x1b[3m"),
             Self::EndItalic => Cow::Borrowed("\x1b[23m"),
             Self::StartUnderline => Cow::Borrowed("\x1b[4m"),
             Self::EndUnderline => Cow::Borrowed("\x1b[24m"),
+            Self::SetStyle(style) => Cow::Owned(style.value()),
             Self::EndAll => Cow::Borrowed("\x1b[0m"),
             Self::EnterAlternateScreen => Cow::Borrowed("\x1b[?1049h"),
             Self::ExitAlternateScreen => Cow::Borrowed("\x1b[?1049l"),
+            Self::EnableBracketedPaste => Cow::Borrowed("\x1b[?2004h"),
+            Self::DisableBracketedPaste => Cow::Borrowed("\x1b[?2004l"),
+            Self::BeginSynchronizedUpdate => Cow::Borrowed("\x1b[?2026h"),
+            Self::EndSynchronizedUpdate => Cow::Borrowed("\x1b[?2026l"),
+            Self::SetScrollRegion { top, bottom } => {
+                Cow::Owned(format!("\x1b[{};{}r", top + 1, bottom + 1))
+            }
+            Self::ResetScrollRegion => Cow::Borrowed("\x1b[r"),
+            Self::ScrollUp(rows) => Cow::Owned(format!("\x1b[{rows}S")),
+            Self::ScrollDown(rows) => Cow::Owned(format!("\x1b[{rows}T")),
             Self::ChangeCursor(style) => Cow::Borrowed(style.escape()),
+            Self::HideCursor => Cow::Borrowed("\x1b[?25l"),
+            Self::ShowCursor => Cow::Borrowed("\x1b[?25h"),
+            Self::Hyperlink { uri, text } => {
+                Cow::Owned(format!("\x1b]8;;{uri}\x1b\\{text}\x1b]8;;\x1b\\"))
+            }
+            Self::StartHyperlink(uri) => Cow::Owned(format!("\x1b]8;;{uri}\x1b\\")),
+            Self::EndHyperlink => Cow::Borrowed("\x1b]8;;\x1b\\"),
         }
     }
 
@@ -214,6 +360,13 @@ impl<'a> EscapeBuilder<'a> {
         self
     }
 
+    /// Sets the color of underlines to the ANSI color
+    #[must_use]
+    pub fn set_underline_color(mut self, color: ANSIColor) -> Self {
+        self.inner.push(ANSIEscape::SetUnderlineColor(color));
+        self
+    }
+
     /// Makes the following text bold
     #[must_use]
     pub fn bold(mut self) -> Self {
@@ -256,6 +409,13 @@ impl<'a> EscapeBuilder<'a> {
         self
     }
 
+    /// Turns on the attributes of `style` for the following text
+    #[must_use]
+    pub fn set_style(mut self, style: Style) -> Self {
+        self.inner.push(ANSIEscape::SetStyle(style));
+        self
+    }
+
     /// Resets the styles for the following text
     #[must_use]
     pub fn reset(mut self) -> Self {
@@ -263,6 +423,91 @@ impl<'a> EscapeBuilder<'a> {
         self
     }
 
+    /// Makes the terminal display the escape codes added in between this and
+    /// `end_synchronized_update` at once
+    #[must_use]
+    pub fn begin_synchronized_update(mut self) -> Self {
+        self.inner.push(ANSIEscape::BeginSynchronizedUpdate);
+        self
+    }
+
+    /// Ends the update started with `begin_synchronized_update`
+    #[must_use]
+    pub fn end_synchronized_update(mut self) -> Self {
+        self.inner.push(ANSIEscape::EndSynchronizedUpdate);
+        self
+    }
+
+    /// Sets the cursor style
+    #[must_use]
+    pub fn change_cursor(mut self, style: CursorStyle) -> Self {
+        self.inner.push(ANSIEscape::ChangeCursor(style));
+        self
+    }
+
+    /// Makes the cursor invisible
+    #[must_use]
+    pub fn hide_cursor(mut self) -> Self {
+        self.inner.push(ANSIEscape::HideCursor);
+        self
+    }
+
+    /// Makes the cursor visible again
+    #[must_use]
+    pub fn show_cursor(mut self) -> Self {
+        self.inner.push(ANSIEscape::ShowCursor);
+        self
+    }
+
+    /// Writes `text` as a link to `uri`
+    #[must_use]
+    pub fn hyperlink(mut self, uri: Cow<'a, str>, text: Cow<'a, str>) -> Self {
+        self.inner.push(ANSIEscape::Hyperlink { uri, text });
+        self
+    }
+
+    /// Makes the following text a link to `uri`, until `end_hyperlink`
+    #[must_use]
+    pub fn start_hyperlink(mut self, uri: Cow<'a, str>) -> Self {
+        self.inner.push(ANSIEscape::StartHyperlink(uri));
+        self
+    }
+
+    /// Ends the link started with `start_hyperlink`
+    #[must_use]
+    pub fn end_hyperlink(mut self) -> Self {
+        self.inner.push(ANSIEscape::EndHyperlink);
+        self
+    }
+
+    /// Limits scrolling to the rows from `top` to `bottom`, both inclusive
+    #[must_use]
+    pub fn set_scroll_region(mut self, top: usize, bottom: usize) -> Self {
+        self.inner.push(ANSIEscape::SetScrollRegion { top, bottom });
+        self
+    }
+
+    /// Makes the whole screen scrollable again
+    #[must_use]
+    pub fn reset_scroll_region(mut self) -> Self {
+        self.inner.push(ANSIEscape::ResetScrollRegion);
+        self
+    }
+
+    /// Moves the contents of the scroll region up by `rows`
+    #[must_use]
+    pub fn scroll_up(mut self, rows: usize) -> Self {
+        self.inner.push(ANSIEscape::ScrollUp(rows));
+        self
+    }
+
+    /// Moves the contents of the scroll region down by `rows`
+    #[must_use]
+    pub fn scroll_down(mut self, rows: usize) -> Self {
+        self.inner.push(ANSIEscape::ScrollDown(rows));
+        self
+    }
+
     /// Concatenates the escape codes from `other` to `self`
     #[must_use]
     pub fn concat<'b>(mut self, other: EscapeBuilder<'b>) -> Self
@@ -285,3 +530,36 @@ impl<'a> EscapeBuilder<'a> {
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_escapes() {
+        let escapes = EscapeBuilder::new()
+            .set_color(ANSIColor::Rgb(255, 128, 0))
+            .set_bg_color(ANSIColor::Rgb(0, 0, 0))
+            .set_color(ANSIColor::Indexed(208))
+            .set_bg_color(ANSIColor::Indexed(16))
+            .set_color(ANSIColor::Red)
+            .set_style(Style::BOLD | Style::UNDERLINE | Style::REVERSE)
+            .build();
+        assert_eq!(
+            escapes,
+            "\x1b[38;2;255;128;0m\x1b[48;2;0;0;0m\x1b[38;5;208m\x1b[48;5;16m\x1b[31m\x1b[1;4;7m"
+        );
+    }
+
+    #[test]
+    fn hyperlink() {
+        let escape = ANSIEscape::Hyperlink {
+            uri: "file:///tmp/a.rs".into(),
+            text: "a.rs".into(),
+        };
+        assert_eq!(
+            escape.to_str(),
+            "\x1b]8;;file:///tmp/a.rs\x1b\\a.rs\x1b]8;;\x1b\\"
+        );
+    }
+}
@@ -1,11 +1,16 @@
 //! Raw mode terminal input hadnler implementation
 
 use std::{
+    fs::File,
     io::{self, Read},
-    os::fd::AsFd,
+    ops::BitOr,
+    os::fd::{AsFd, OwnedFd},
     sync::mpsc::{Receiver, RecvError, Sender},
+    time::Duration,
 };
 
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+
 /// A message sent through the event channel
 #[derive(Debug)]
 pub enum Message {
@@ -19,7 +24,7 @@ pub enum Message {
 /// An input receieved in the raw terminal mode
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Input {
-    /// A keypress that can be represented with a single ascii character
+    /// A keypress that can be represented with a single character
     Keypress(char),
     /// Simmilar to keypress, but with the ctrl key held
     Control(char),
@@ -37,36 +42,363 @@ pub enum Input {
     ArrowLeft,
     /// Arrow right
     ArrowRight,
+    /// A special key or a key pressed with modifiers, that has no variant of its own
+    Key(KeyEvent),
+    /// Text pasted while the bracketed paste mode is enabled
+    Paste(String),
 
     /// Inputs for which the handlers are yet to be imlemented
     #[allow(unused)]
     Unimplemented(Vec<u8>),
 }
 
+/// A key along with the modifiers held while pressing it
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub code: KeyCode,
+    pub modifiers: Modifiers,
+}
+
+impl KeyEvent {
+    /// Constructs a `KeyEvent` out of its parts
+    #[must_use]
+    pub const fn new(code: KeyCode, modifiers: Modifiers) -> Self {
+        Self { code, modifiers }
+    }
+}
+
+/// A key that is reported by the terminal with an escape sequence
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum KeyCode {
+    /// A key that produces a character, reported with an escape sequence when alt is held
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    /// Reported only along with shift
+    Tab,
+    /// A function key, `F(1)` being F1
+    F(u8),
+}
+
+/// A set of modifier keys held during a keypress
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Self = Self(0);
+    pub const SHIFT: Self = Self(1);
+    pub const ALT: Self = Self(1 << 1);
+    pub const CTRL: Self = Self(1 << 2);
+
+    /// Returns `true` if every modifier of `other` is held
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[must_use]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Decodes the modifier parameter of an escape sequence, which is one plus the modifier bits
+    const fn from_param(param: u8) -> Self {
+        Self(param.saturating_sub(1) & 0b111)
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
 pub const ESCAPE: u8 = 27;
 pub const LBRACE: u8 = 91;
+/// Starts the escape sequences of some keys instead of `LBRACE`
+pub const SS3: u8 = b'O';
+
+/// Sent by the terminal before the pasted text in the bracketed paste mode
+pub const PASTE_START: &[u8] = b"\x1b[200~";
+/// Sent by the terminal after the pasted text in the bracketed paste mode
+pub const PASTE_END: &[u8] = b"\x1b[201~";
 
 impl Input {
+    /// Constructs the input of a key, using the dedicated variants for keys that have them
+    #[must_use]
+    pub const fn key(code: KeyCode, modifiers: Modifiers) -> Self {
+        if !modifiers.is_empty() {
+            return Self::Key(KeyEvent::new(code, modifiers));
+        }
+
+        match code {
+            KeyCode::Char(c) => Self::Keypress(c),
+            KeyCode::Up => Self::ArrowUp,
+            KeyCode::Down => Self::ArrowDown,
+            KeyCode::Left => Self::ArrowLeft,
+            KeyCode::Right => Self::ArrowRight,
+            _ => Self::Key(KeyEvent::new(code, modifiers)),
+        }
+    }
+
+    /// Parses a single byte or a complete escape sequence
     #[must_use]
     pub fn from_bytes(bytes: &[u8]) -> Self {
         match bytes {
+            [2] => Input::Control('b'),
             [3] => Input::Control('c'),
             [4] => Input::Control('d'),
+            [6] => Input::Control('f'),
             [10] => Input::Enter,
+            [14] => Input::Control('n'),
+            [16] => Input::Control('p'),
             [18] => Input::Control('r'),
             [21] => Input::Control('u'),
+            [26] => Input::Control('z'),
             [ESCAPE] => Input::Escape,
             [127] => Input::Backspace,
             [c] if c.is_ascii() => Input::Keypress(*c as char),
 
-            [ESCAPE, LBRACE, 65] => Input::ArrowUp,
-            [ESCAPE, LBRACE, 66] => Input::ArrowDown,
-            [ESCAPE, LBRACE, 67] => Input::ArrowRight,
-            [ESCAPE, LBRACE, 68] => Input::ArrowLeft,
+            [ESCAPE, LBRACE, params @ .., last] => Self::parse_csi(params, *last).map_or_else(
+                || Input::Unimplemented(bytes.into()),
+                |(code, mods)| Input::key(code, mods),
+            ),
+            [ESCAPE, SS3, last] => Self::parse_csi(&[], *last).map_or_else(
+                || Input::Unimplemented(bytes.into()),
+                |(code, mods)| Input::key(code, mods),
+            ),
+            [ESCAPE, c] if c.is_ascii() && !c.is_ascii_control() => {
+                Input::key(KeyCode::Char(*c as char), Modifiers::ALT)
+            }
+
+            // A single character encoded with multiple bytes
+            _ => std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| {
+                    let mut chars = s.chars();
+                    chars.next().filter(|_| chars.next().is_none())
+                })
+                .map_or_else(|| Input::Unimplemented(bytes.into()), Input::Keypress),
+        }
+    }
+
+    /// Parses the key of a `ESC [ <key>;<modifiers> <last>` sequence, or of a `ESC O <last>`
+    /// sequence when `params` are empty
+    fn parse_csi(params: &[u8], last: u8) -> Option<(KeyCode, Modifiers)> {
+        let params = std::str::from_utf8(params).ok()?;
+        let mut params = params.split(';').map(|p| p.parse::<u8>().ok());
+        let first = params.next().flatten();
+        let modifiers = params
+            .next()
+            .flatten()
+            .map_or(Modifiers::NONE, Modifiers::from_param);
+
+        let code = match (last, first) {
+            (b'A', _) => KeyCode::Up,
+            (b'B', _) => KeyCode::Down,
+            (b'C', _) => KeyCode::Right,
+            (b'D', _) => KeyCode::Left,
+            (b'H', _) | (b'~', Some(1 | 7)) => KeyCode::Home,
+            (b'F', _) | (b'~', Some(4 | 8)) => KeyCode::End,
+            (b'P', _) => KeyCode::F(1),
+            (b'Q', _) => KeyCode::F(2),
+            (b'R', _) => KeyCode::F(3),
+            (b'S', _) => KeyCode::F(4),
+            (b'Z', _) => return Some((KeyCode::Tab, Modifiers::SHIFT | modifiers)),
+            (b'~', Some(2)) => KeyCode::Insert,
+            (b'~', Some(3)) => KeyCode::Delete,
+            (b'~', Some(5)) => KeyCode::PageUp,
+            (b'~', Some(6)) => KeyCode::PageDown,
+            (b'~', Some(n @ 11..=15)) => KeyCode::F(n - 10),
+            (b'~', Some(n @ 17..=21)) => KeyCode::F(n - 11),
+            (b'~', Some(n @ 23..=24)) => KeyCode::F(n - 12),
+            _ => return None,
+        };
+
+        Some((code, modifiers))
+    }
+}
+
+/// Turns the bytes read from the terminal into inputs
+///
+/// Bytes are decoded one at a time by a state machine, so escape sequences, UTF-8 encoded
+/// characters and pastes split between reads are completed by the following reads. Text between
+/// `PASTE_START` and `PASTE_END` is collected into a single `Input::Paste`
+///
+/// A lone `ESC` can't be told apart from the start of a sequence until more bytes arrive, so it
+/// stays pending. If nothing arrives within `ESCAPE_TIMEOUT`, the caller should `flush` the parser,
+/// which `read` does on its own
+#[derive(Debug, Default)]
+pub struct Parser {
+    state: State,
+    /// Bytes of the sequence, the character or the paste that is being received
+    sequence: Vec<u8>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum State {
+    #[default]
+    Ground,
+    /// After an `ESC`
+    Escape,
+    /// After `ESC [`, until the final byte of the control sequence
+    Csi,
+    /// After `ESC O`
+    Ss3,
+    /// Inside a UTF-8 encoded character, holding the number of bytes left
+    Utf8(u8),
+    /// Between `PASTE_START` and `PASTE_END`
+    Paste,
+}
+
+/// How long a pending lone `ESC` waits for the rest of an escape sequence before it is taken for
+/// the Esc key
+pub const ESCAPE_TIMEOUT: Duration = Duration::from_millis(25);
+
+impl Parser {
+    /// Constructs a parser that is not in the middle of a sequence
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the bytes of a single read, returning the inputs that are complete
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Input> {
+        let mut inputs = Vec::new();
+        for &b in bytes {
+            self.advance(b, &mut inputs);
+        }
+        inputs
+    }
+
+    /// Returns whether an unfinished escape sequence or character is waiting for more bytes
+    #[must_use]
+    pub const fn is_pending(&self) -> bool {
+        matches!(
+            self.state,
+            State::Escape | State::Csi | State::Ss3 | State::Utf8(_)
+        )
+    }
+
+    /// Gives up on waiting for the rest of the pending sequence, returning what it was so far.
+    /// A lone `ESC` becomes `Input::Escape`. An unfinished paste keeps waiting
+    pub fn flush(&mut self) -> Option<Input> {
+        self.is_pending().then(|| self.finish())
+    }
+
+    /// Reads once from `reader` and parses the bytes. Flushes the parser instead if it is pending
+    /// and nothing arrives within `ESCAPE_TIMEOUT`
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of reading from or polling `reader`
+    pub fn read<R: Read + AsFd>(&mut self, reader: &mut R) -> io::Result<Vec<Input>> {
+        if self.is_pending() {
+            let timeout = PollTimeout::try_from(ESCAPE_TIMEOUT).unwrap_or(PollTimeout::MAX);
+            let mut fds = [PollFd::new(reader.as_fd(), PollFlags::POLLIN)];
+            if poll(&mut fds, timeout)? == 0 {
+                return Ok(self.flush().into_iter().collect());
+            }
+        }
+
+        let mut buffer = [0_u8; 1024];
+        let n = reader.read(&mut buffer)?;
+        Ok(self.feed(&buffer[..n]))
+    }
 
-            _ => Input::Unimplemented(bytes.into()),
+    fn advance(&mut self, b: u8, inputs: &mut Vec<Input>) {
+        match self.state {
+            State::Ground => self.start(b, inputs),
+            State::Escape => {
+                self.sequence.push(b);
+                self.state = match b {
+                    LBRACE => State::Csi,
+                    SS3 => State::Ss3,
+                    ESCAPE => {
+                        // The first `ESC` was the Esc key, the second one starts over
+                        self.sequence.pop();
+                        inputs.push(Input::Escape);
+                        State::Escape
+                    }
+                    _ => return inputs.push(self.finish()),
+                };
+            }
+            State::Csi => {
+                self.sequence.push(b);
+                // The final byte of a control sequence lies in `@..=~`
+                if !(64..=126).contains(&b) {
+                    return;
+                }
+
+                if self.sequence == PASTE_START {
+                    self.sequence.clear();
+                    self.state = State::Paste;
+                } else {
+                    inputs.push(self.finish());
+                }
+            }
+            State::Ss3 => {
+                self.sequence.push(b);
+                inputs.push(self.finish());
+            }
+            State::Utf8(left) => {
+                if b & 0b1100_0000 != 0b1000_0000 {
+                    // Not a continuation byte, so the character is broken
+                    inputs.push(self.finish());
+                    return self.start(b, inputs);
+                }
+
+                self.sequence.push(b);
+                if left == 1 {
+                    inputs.push(self.finish());
+                } else {
+                    self.state = State::Utf8(left - 1);
+                }
+            }
+            State::Paste => {
+                self.sequence.push(b);
+                if self.sequence.ends_with(PASTE_END) {
+                    self.sequence
+                        .truncate(self.sequence.len() - PASTE_END.len());
+                    let paste = String::from_utf8_lossy(&self.sequence).into_owned();
+                    self.sequence.clear();
+                    self.state = State::Ground;
+                    inputs.push(Input::Paste(paste));
+                }
+            }
         }
     }
+
+    /// Handles the first byte of an input
+    fn start(&mut self, b: u8, inputs: &mut Vec<Input>) {
+        self.sequence.push(b);
+        self.state = match b {
+            ESCAPE => State::Escape,
+            0b1100_0000..=0b1101_1111 => State::Utf8(1),
+            0b1110_0000..=0b1110_1111 => State::Utf8(2),
+            0b1111_0000..=0b1111_0111 => State::Utf8(3),
+            _ => return inputs.push(self.finish()),
+        };
+    }
+
+    /// Turns the received sequence into an input, returning to the ground state
+    fn finish(&mut self) -> Input {
+        let input = Input::from_bytes(&self.sequence);
+        self.sequence.clear();
+        self.state = State::Ground;
+        input
+    }
 }
 
 /// A stream of input events
@@ -92,11 +424,36 @@ impl Stream {
     ///
     /// You may not want to use this with anything but the `stdin()`, though
     #[must_use]
-    pub fn from_read<H>(input_handle: H) -> Self
+    pub fn from_read<H>(mut input_handle: H) -> Self
     where
         H: Read + AsFd + Send + 'static,
     {
-        let (events, kill) = Self::to_event_stream(input_handle);
+        let (events, kill) =
+            Self::to_event_stream(move |parser| parser.read(&mut input_handle).map(Some));
+        Self { kill, events }
+    }
+
+    /// Initiates an input stream from a file descriptor, such as the master side of a PTY
+    #[must_use]
+    pub fn from_fd(fd: OwnedFd) -> Self {
+        Self::from_read(File::from(fd))
+    }
+
+    /// Transforms any reader into an event stream, which ends along with the reader
+    ///
+    /// Without a file descriptor there is no way to wait for the rest of an escape sequence with a
+    /// timeout, so a lone `ESC` is only reported once the following bytes or the end of the
+    /// reader arrive. Use `from_read` or `from_fd` for interactive input
+    #[must_use]
+    pub fn from_reader<R>(mut reader: R) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        let (events, kill) = Self::to_event_stream(move |parser| {
+            let mut buffer = [0_u8; 1024];
+            let n = reader.read(&mut buffer)?;
+            Ok((n != 0).then(|| parser.feed(&buffer[..n])))
+        });
         Self { kill, events }
     }
 
@@ -109,25 +466,27 @@ impl Stream {
         self.events.recv()
     }
 
-    fn to_event_stream<H>(input_handle: H) -> (Receiver<Message>, Sender<()>)
+    /// Spawns a thread that parses inputs with `read` until it returns `None`, which means that the
+    /// input has ended
+    fn to_event_stream<F>(mut read: F) -> (Receiver<Message>, Sender<()>)
     where
-        H: Read + AsFd + Send + 'static,
+        F: FnMut(&mut Parser) -> io::Result<Option<Vec<Input>>> + Send + 'static,
     {
-        let mut reader = timeout_readwrite::TimeoutReader::new(input_handle, None);
-
         let (t_events, r_events) = std::sync::mpsc::channel();
         let (t_kill, r_kill) = std::sync::mpsc::channel();
 
         std::thread::spawn(move || {
-            loop {
-                let mut buffer = [0_u8; 4];
-                let n = match reader.read(&mut buffer) {
-                    Ok(n) => n,
-                    Err(e) => {
-                        if e.kind() == std::io::ErrorKind::TimedOut {
-                            continue;
+            let mut parser = Parser::new();
+            'outer: loop {
+                let inputs = match read(&mut parser) {
+                    Ok(Some(inputs)) => inputs,
+                    Ok(None) => {
+                        if let Some(input) = parser.flush() {
+                            let _ = t_events.send(Message::Input(input));
                         }
-
+                        break;
+                    }
+                    Err(e) => {
                         // If the receiver is gone, we should probably kill the read loop
                         // and exit
                         if t_events.send(Message::Error(e)).is_err() {
@@ -141,11 +500,11 @@ impl Stream {
                     break;
                 }
 
-                let input = Input::from_bytes(&buffer[..n]);
-
-                // Same here. There is no point in reading if no one's receiving
-                if t_events.send(Message::Input(input)).is_err() {
-                    break;
+                for input in inputs {
+                    // Same here. There is no point in reading if no one's receiving
+                    if t_events.send(Message::Input(input)).is_err() {
+                        break 'outer;
+                    }
                 }
             }
         });
@@ -156,8 +515,171 @@ impl Stream {
 
 impl Drop for Stream {
     fn drop(&mut self) {
-        self.kill
-            .send(())
-            .expect("the receiver should not be dropped yet");
+        // The reading thread is already gone if the input has ended
+        let _ = self.kill.send(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn splits_reads() {
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed(b"ab\x1b"),
+            [Input::Keypress('a'), Input::Keypress('b')]
+        );
+        assert!(parser.is_pending());
+        assert_eq!(parser.feed(b"[A"), [Input::ArrowUp]);
+        assert_eq!(parser.feed(b"\x04"), [Input::Control('d')]);
+        assert_eq!(parser.feed(b"\x06"), [Input::Control('f')]);
+        assert_eq!(parser.feed(b"\x02"), [Input::Control('b')]);
+        assert_eq!(parser.feed(b"\x1a"), [Input::Control('z')]);
+        assert_eq!(parser.feed(b"\x0e"), [Input::Control('n')]);
+        assert_eq!(parser.feed(b"\x1b[1;"), []);
+        assert_eq!(
+            parser.feed(b"5Cx\x1b\x1b"),
+            [
+                Input::Key(KeyEvent::new(KeyCode::Right, Modifiers::CTRL)),
+                Input::Keypress('x'),
+                Input::Escape,
+            ]
+        );
+        assert_eq!(parser.flush(), Some(Input::Escape));
+        assert_eq!(parser.flush(), None);
+
+        assert_eq!(parser.feed(&"мир".as_bytes()[..3]), [Input::Keypress('м')]);
+        assert_eq!(
+            parser.feed(&"мир".as_bytes()[3..]),
+            [Input::Keypress('и'), Input::Keypress('р')]
+        );
+        assert_eq!(
+            parser.feed(b"\xd0a"),
+            [Input::Unimplemented(vec![0xd0]), Input::Keypress('a')]
+        );
+    }
+
+    #[test]
+    fn chunked_sequences() {
+        let key = |code, modifiers| Input::Key(KeyEvent::new(code, modifiers));
+        let bytes = "\x1b[1;5Dж\x1bOP\x1b[15~\x1bx\x1b[200~a\x1b[201~".as_bytes();
+        let expected = [
+            key(KeyCode::Left, Modifiers::CTRL),
+            Input::Keypress('ж'),
+            key(KeyCode::F(1), Modifiers::NONE),
+            key(KeyCode::F(5), Modifiers::NONE),
+            key(KeyCode::Char('x'), Modifiers::ALT),
+            Input::Paste("a".to_owned()),
+        ];
+
+        for size in 1..bytes.len() {
+            let mut parser = Parser::new();
+            let inputs: Vec<_> = bytes.chunks(size).flat_map(|c| parser.feed(c)).collect();
+            assert_eq!(inputs, expected, "chunk size {size}");
+            assert!(!parser.is_pending());
+        }
+    }
+
+    #[test]
+    fn lone_escape_times_out() {
+        let (mut tx, mut rx) = std::os::unix::net::UnixStream::pair().unwrap();
+        let mut parser = Parser::new();
+
+        tx.write_all(b"i\x1b").unwrap();
+        assert_eq!(parser.read(&mut rx).unwrap(), [Input::Keypress('i')]);
+        assert_eq!(parser.read(&mut rx).unwrap(), [Input::Escape]);
+
+        tx.write_all(b"\x1b").unwrap();
+        assert_eq!(parser.read(&mut rx).unwrap(), []);
+        tx.write_all(b"[B").unwrap();
+        assert_eq!(parser.read(&mut rx).unwrap(), [Input::ArrowDown]);
+    }
+
+    #[test]
+    fn streams() {
+        let stream = Stream::from_reader(&b"a\x1b[Ab\x1b"[..]);
+        let inputs: Vec<_> = std::iter::from_fn(|| match stream.recv() {
+            Ok(Message::Input(input)) => Some(input),
+            _ => None,
+        })
+        .collect();
+        assert_eq!(
+            inputs,
+            [
+                Input::Keypress('a'),
+                Input::ArrowUp,
+                Input::Keypress('b'),
+                Input::Escape
+            ]
+        );
+
+        let (mut tx, rx) = std::os::unix::net::UnixStream::pair().unwrap();
+        let stream = Stream::from_fd(rx.into());
+        tx.write_all("\x1b[1;2Bы".as_bytes()).unwrap();
+        let recv = || match stream.recv() {
+            Ok(Message::Input(input)) => input,
+            message => panic!("unexpected message: {message:?}"),
+        };
+        assert_eq!(
+            recv(),
+            Input::Key(KeyEvent::new(KeyCode::Down, Modifiers::SHIFT))
+        );
+        assert_eq!(recv(), Input::Keypress('ы'));
+    }
+
+    #[test]
+    fn parses_keys() {
+        let key = |code, modifiers| Input::Key(KeyEvent::new(code, modifiers));
+        let cases: &[(&[u8], Input)] = &[
+            (b"\x1b[B", Input::ArrowDown),
+            (b"\x1bOD", Input::ArrowLeft),
+            (b"\x1b[1;2A", key(KeyCode::Up, Modifiers::SHIFT)),
+            (
+                b"\x1b[1;7D",
+                key(KeyCode::Left, Modifiers::CTRL | Modifiers::ALT),
+            ),
+            (b"\x1b[H", key(KeyCode::Home, Modifiers::NONE)),
+            (b"\x1b[4~", key(KeyCode::End, Modifiers::NONE)),
+            (b"\x1b[5;5~", key(KeyCode::PageUp, Modifiers::CTRL)),
+            (b"\x1b[6~", key(KeyCode::PageDown, Modifiers::NONE)),
+            (b"\x1b[3;3~", key(KeyCode::Delete, Modifiers::ALT)),
+            (b"\x1bOP", key(KeyCode::F(1), Modifiers::NONE)),
+            (b"\x1b[15~", key(KeyCode::F(5), Modifiers::NONE)),
+            (b"\x1b[24;2~", key(KeyCode::F(12), Modifiers::SHIFT)),
+            (b"\x1b[Z", key(KeyCode::Tab, Modifiers::SHIFT)),
+            (b"\x1bx", key(KeyCode::Char('x'), Modifiers::ALT)),
+            (b"\x1b[99~", Input::Unimplemented(b"\x1b[99~".to_vec())),
+        ];
+
+        for (bytes, input) in cases {
+            assert_eq!(&Input::from_bytes(bytes), input, "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn collects_paste() {
+        let mut parser = Parser::new();
+        assert_eq!(
+            parser.feed("\x1b[200~hello\nмир\x1b[201~".as_bytes()),
+            [Input::Paste("hello\nмир".to_owned())]
+        );
+
+        let mut inputs = Vec::new();
+        let bytes = b"x\x1b[200~:q\x1b\x1b[201~i";
+        for read in bytes.chunks(4) {
+            inputs.extend(parser.feed(read));
+        }
+        assert_eq!(
+            inputs,
+            [
+                Input::Keypress('x'),
+                Input::Paste(":q\x1b".to_owned()),
+                Input::Keypress('i')
+            ]
+        );
     }
 }
@@ -7,9 +7,19 @@ pub mod prettify;
 pub mod window;
 
 use coord::Dimensions;
-use nix::{errno::Errno, ioctl_read_bad, libc::TIOCGWINSZ, sys::termios};
+use nix::{
+    errno::Errno,
+    ioctl_read_bad,
+    libc::TIOCGWINSZ,
+    sys::{signal, termios},
+};
 
-use std::os::fd::{AsRawFd, RawFd};
+use std::{
+    io::{Read, Write},
+    os::fd::{AsRawFd, RawFd},
+    sync::Mutex,
+    time::Duration,
+};
 
 /// Returns the current state of the terminal
 /// May be used to restore the state after manipulating it with the `restore_state` function
@@ -22,7 +32,7 @@ pub fn get_current_state() -> Result<termios::Termios, Errno> {
     termios::tcgetattr(std::io::stdin())
 }
 
-/// Puts the stdin into "raw" mode
+/// Puts the stdin into "raw" mode and enables the bracketed paste mode
 ///
 /// It shoud be restored to the initial state, as the "raw" state
 /// may persist after the program exits
@@ -44,19 +54,28 @@ pub fn into_raw() -> Result<(), Errno> {
     termios.control_chars[nix::libc::VMIN] = 1;
     termios.control_chars[nix::libc::VTIME] = 0;
 
-    termios::tcsetattr(std::io::stdin(), termios::SetArg::TCSAFLUSH, &termios)
+    termios::tcsetattr(std::io::stdin(), termios::SetArg::TCSAFLUSH, &termios)?;
+    write_escape(escaping::ANSIEscape::EnableBracketedPaste);
+    Ok(())
 }
 
-/// Restores the terminal state to the given state
+/// Restores the terminal state to the given state and disables the bracketed paste mode
 ///
 /// # Errors
 ///
 /// Returns an error with corresponding `Errno` if underlying c function fails
 ///
 pub fn restore_state(state: &termios::Termios) -> Result<(), Errno> {
+    write_escape(escaping::ANSIEscape::DisableBracketedPaste);
     termios::tcsetattr(std::io::stdin(), termios::SetArg::TCSAFLUSH, state)
 }
 
+/// Writes the escape to stdout right away, failing silently like the rest of the terminal output
+fn write_escape(escape: escaping::ANSIEscape<'_>) {
+    let _ = escape.write_to_stdout();
+    let _ = std::io::stdout().flush();
+}
+
 ioctl_read_bad!(get_win_size, TIOCGWINSZ, nix::pty::Winsize);
 
 /// Returns the size of the current terminal (columns and rows)
@@ -81,6 +100,190 @@ pub fn get_size() -> Result<Dimensions<u16>, Errno> {
     Ok(Dimensions::new(winsize.ws_col, winsize.ws_row))
 }
 
+/// Terminal state set up by the alive guards, which `suspend` leaves and comes back to
+#[derive(Debug)]
+struct GuardedState {
+    initial_state: termios::Termios,
+    alt_screen: bool,
+}
+
+static GUARDED_STATE: Mutex<Option<GuardedState>> = Mutex::new(None);
+
+fn update_guarded_state(f: impl FnOnce(&mut Option<GuardedState>)) {
+    if let Ok(mut state) = GUARDED_STATE.lock() {
+        f(&mut state);
+    }
+}
+
+/// Keeps the terminal in raw mode while alive, restoring the initial state when dropped, which
+/// also happens when unwinding from a panic
+#[derive(Debug)]
+pub struct RawModeGuard {
+    /// Taken when the state is restored
+    initial_state: Option<termios::Termios>,
+}
+
+impl RawModeGuard {
+    /// Puts the terminal into raw mode, see `into_raw`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with corresponding `Errno` if underlying c function fails
+    ///
+    pub fn new() -> Result<Self, Errno> {
+        let initial_state = get_current_state()?;
+        into_raw()?;
+        update_guarded_state(|state| {
+            state.get_or_insert_with(|| GuardedState {
+                initial_state: initial_state.clone(),
+                alt_screen: false,
+            });
+        });
+        Ok(Self {
+            initial_state: Some(initial_state),
+        })
+    }
+
+    /// Restores the initial state, reporting the error that dropping the guard would ignore
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with corresponding `Errno` if underlying c function fails
+    ///
+    pub fn restore(mut self) -> Result<(), Errno> {
+        self.initial_state
+            .take()
+            .map_or(Ok(()), |state| Self::restore_initial(&state))
+    }
+
+    fn restore_initial(initial_state: &termios::Termios) -> Result<(), Errno> {
+        update_guarded_state(|state| {
+            if state
+                .as_ref()
+                .is_some_and(|state| state.initial_state == *initial_state)
+            {
+                *state = None;
+            }
+        });
+        restore_state(initial_state)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        if let Some(state) = self.initial_state.take() {
+            let _ = Self::restore_initial(&state);
+        }
+    }
+}
+
+/// Keeps the terminal in the alternate screen and raw mode while alive, exiting both when dropped
+#[derive(Debug)]
+pub struct AltScreenGuard {
+    /// Taken when the alternate screen is exited
+    raw_mode: Option<RawModeGuard>,
+}
+
+impl AltScreenGuard {
+    /// Puts the terminal into raw mode and enters the alternate screen
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with corresponding `Errno` if underlying c function fails
+    ///
+    pub fn new() -> Result<Self, Errno> {
+        let raw_mode = RawModeGuard::new()?;
+        write_escape(escaping::ANSIEscape::EnterAlternateScreen);
+        update_guarded_state(|state| {
+            if let Some(state) = state {
+                state.alt_screen = true;
+            }
+        });
+        Ok(Self {
+            raw_mode: Some(raw_mode),
+        })
+    }
+
+    /// Exits the alternate screen and restores the initial state, reporting the error that
+    /// dropping the guard would ignore
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with corresponding `Errno` if underlying c function fails
+    ///
+    pub fn restore(mut self) -> Result<(), Errno> {
+        write_escape(escaping::ANSIEscape::ExitAlternateScreen);
+        self.raw_mode.take().map_or(Ok(()), RawModeGuard::restore)
+    }
+}
+
+impl Drop for AltScreenGuard {
+    fn drop(&mut self) {
+        if let Some(raw_mode) = self.raw_mode.take() {
+            write_escape(escaping::ANSIEscape::ExitAlternateScreen);
+            drop(raw_mode);
+        }
+    }
+}
+
+/// Installs a panic hook that exits the alternate screen and restores the current terminal state
+/// before reporting the panic, so the message is printed on the normal screen
+///
+/// Must be called before changing the terminal state. The previously installed hook is called
+/// after the cleanup
+///
+/// # Errors
+///
+/// Returns an error with corresponding `Errno` if underlying c function fails
+///
+pub fn install_panic_hook() -> Result<(), Errno> {
+    let initial_state = Mutex::new(get_current_state()?);
+    let prev_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        write_escape(escaping::ANSIEscape::ExitAlternateScreen);
+        if let Ok(state) = initial_state.lock() {
+            let _ = restore_state(&state);
+        }
+        prev_hook(info);
+    }));
+    Ok(())
+}
+
+/// Stops the process the way `Ctrl-Z` does in a regular terminal and waits until it is continued
+///
+/// Leaves the alternate screen and raw mode set up by the alive guards before raising `SIGTSTP`
+/// and enters them again once the process gets `SIGCONT`. The screen contents are lost by then,
+/// so the caller should rerender them. Does nothing but stopping the process if no guard is alive
+///
+/// # Errors
+///
+/// Returns an error with corresponding `Errno` if underlying c function fails
+///
+pub fn suspend() -> Result<(), Errno> {
+    let guarded = GUARDED_STATE.lock().ok().and_then(|state| {
+        state
+            .as_ref()
+            .map(|s| (s.initial_state.clone(), s.alt_screen))
+    });
+
+    if let Some((initial_state, alt_screen)) = &guarded {
+        if *alt_screen {
+            write_escape(escaping::ANSIEscape::ExitAlternateScreen);
+        }
+        restore_state(initial_state)?;
+    }
+
+    signal::raise(signal::Signal::SIGTSTP)?;
+
+    if let Some((_, alt_screen)) = guarded {
+        into_raw()?;
+        if alt_screen {
+            write_escape(escaping::ANSIEscape::EnterAlternateScreen);
+        }
+    }
+    Ok(())
+}
+
 /// Executes a function within raw mode, ensuring that state is restored after function returns
 ///
 /// # Errors
@@ -88,12 +291,9 @@ pub fn get_size() -> Result<Dimensions<u16>, Errno> {
 /// Returns an error with corresponding `Errno` if underlying c function fails
 ///
 pub fn within_raw_mode<T>(f: impl FnOnce() -> T) -> Result<T, Errno> {
-    let initial_state = get_current_state()?;
-    into_raw()?;
-
+    let guard = RawModeGuard::new()?;
     let ret = f();
-
-    restore_state(&initial_state)?;
+    guard.restore()?;
     Ok(ret)
 }
 
@@ -107,25 +307,79 @@ pub fn within_raw_mode<T>(f: impl FnOnce() -> T) -> Result<T, Errno> {
 /// Returns an error with corresponding `Errno` if underlying c function fails
 ///
 pub fn within_alternative_screen_mode<T>(f: impl FnOnce() -> T) -> Result<T, Errno> {
-    use std::io::Write;
+    let guard = AltScreenGuard::new()?;
+    let ret = f();
+    guard.restore()?;
+    Ok(ret)
+}
 
-    within_raw_mode(|| {
-        let _ = std::io::stdout().write(
-            escaping::ANSIEscape::EnterAlternateScreen
-                .to_str()
-                .as_bytes(),
-        );
+/// Asks the terminal whether it supports synchronized output (mode 2026), waiting at most
+/// `timeout` for each part of the answer
+///
+/// Must be called in raw mode before anything else reads stdin, as the answer comes through it.
+/// The query is followed by a device attributes request, which every terminal answers, so
+/// terminals that do not know the mode do not make the call wait for the whole timeout
+#[must_use]
+pub fn supports_synchronized_output(timeout: Duration) -> bool {
+    let mut stdout = std::io::stdout();
+    if stdout
+        .write_all(b"\x1b[?2026$p\x1b[c")
+        .and_then(|()| stdout.flush())
+        .is_err()
+    {
+        return false;
+    }
 
-        let out = f();
+    let mut reader = timeout_readwrite::TimeoutReader::new(std::io::stdin(), Some(timeout));
+    let mut answer = Vec::new();
+    let mut buf = [0_u8; 64];
+    // The device attributes answer ends with `c` and comes last
+    while !answer.ends_with(b"c") && answer.len() < 256 {
+        match reader.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => answer.extend_from_slice(&buf[..n]),
+        }
+    }
 
-        let _ = std::io::stdout().write(
-            escaping::ANSIEscape::ExitAlternateScreen
-                .to_str()
-                .as_bytes(),
-        );
+    parse_mode_report(&answer, 2026).is_some_and(|state| matches!(state, 1..=3))
+}
+
+/// Guesses whether the terminal draws undercurls and colored underlines from the environment, as
+/// there is no way to ask the terminal itself
+#[must_use]
+pub fn supports_extended_underlines() -> bool {
+    let var = |name| std::env::var(name).unwrap_or_default();
+    extended_underlines_from_env(&var("TERM"), &var("TERM_PROGRAM"), &var("VTE_VERSION"))
+}
 
-        out
-    })
+fn extended_underlines_from_env(term: &str, term_program: &str, vte_version: &str) -> bool {
+    const TERMS: &[&str] = &[
+        "kitty",
+        "wezterm",
+        "foot",
+        "ghostty",
+        "alacritty",
+        "contour",
+    ];
+    const PROGRAMS: &[&str] = &["WezTerm", "ghostty", "iTerm.app", "vscode"];
+    /// The first VTE version that draws undercurls
+    const VTE_UNDERCURL: u32 = 5102;
+
+    TERMS.iter().any(|name| term.contains(name))
+        || PROGRAMS.contains(&term_program)
+        || vte_version.parse().is_ok_and(|v: u32| v >= VTE_UNDERCURL)
+}
+
+/// Finds the state of `mode` in a `ESC [ ? <mode> ; <state> $ y` report
+fn parse_mode_report(answer: &[u8], mode: u16) -> Option<u8> {
+    let prefix = format!("\x1b[?{mode};");
+    let start = answer
+        .windows(prefix.len())
+        .position(|w| w == prefix.as_bytes())?
+        + prefix.len();
+    let rest = &answer[start..];
+    let end = rest.iter().position(|&b| b == b'$')?;
+    std::str::from_utf8(&rest[..end]).ok()?.parse().ok()
 }
 
 fn get_stdin_fd() -> RawFd {
@@ -136,6 +390,33 @@ fn get_stdin_fd() -> RawFd {
 mod tests {
     use super::*;
 
+    #[test]
+    fn mode_report() {
+        assert_eq!(
+            parse_mode_report(b"\x1b[?2026;2$y\x1b[?62;22c", 2026),
+            Some(2)
+        );
+        assert_eq!(parse_mode_report(b"\x1b[?2026;0$y", 2026), Some(0));
+        assert_eq!(parse_mode_report(b"\x1b[?62;22c", 2026), None);
+    }
+
+    #[test]
+    fn extended_underlines() {
+        assert!(extended_underlines_from_env("xterm-kitty", "", ""));
+        assert!(extended_underlines_from_env(
+            "xterm-256color",
+            "WezTerm",
+            ""
+        ));
+        assert!(extended_underlines_from_env("xterm-256color", "", "7600"));
+        assert!(!extended_underlines_from_env("xterm-256color", "", "5000"));
+        assert!(!extended_underlines_from_env(
+            "screen",
+            "Apple_Terminal",
+            ""
+        ));
+    }
+
     #[test]
     fn within_raw() {
         let init_state = get_current_state().unwrap();
@@ -146,6 +427,40 @@ mod tests {
         assert_ne!(init_state, raw_state);
     }
 
+    #[test]
+    fn guards_restore_on_panic() {
+        let init_state = get_current_state().unwrap();
+        let result = std::panic::catch_unwind(|| {
+            let _guard = AltScreenGuard::new().unwrap();
+            panic!("restores the terminal");
+        });
+
+        assert!(result.is_err());
+        assert_eq!(get_current_state().unwrap(), init_state);
+    }
+
+    #[test]
+    fn suspend_and_continue() {
+        // SAFETY: ignoring a signal installs no handler. With `SIGTSTP` ignored, raising it does not
+        // stop the tests, as if they were continued right away
+        let previous =
+            unsafe { signal::signal(signal::Signal::SIGTSTP, signal::SigHandler::SigIgn) }.unwrap();
+
+        let init_state = get_current_state().unwrap();
+        let (raw_state, continued_state) = within_raw_mode(|| {
+            let raw_state = get_current_state().unwrap();
+            suspend().unwrap();
+            (raw_state, get_current_state().unwrap())
+        })
+        .unwrap();
+        assert_ne!(raw_state, init_state);
+        assert_eq!(continued_state, raw_state);
+        assert_eq!(get_current_state().unwrap(), init_state);
+
+        // SAFETY: the previous disposition is put back as it was
+        unsafe { signal::signal(signal::Signal::SIGTSTP, previous) }.unwrap();
+    }
+
     #[test]
     fn within_as() {
         let init_state = get_current_state().unwrap();
@@ -1,31 +1,67 @@
 //! An api for handling the raw mode terminal
 
-use std::io::{Result, Stdout, Write, stdout};
+use std::{
+    io::{Result, Stdout, Write, stdout},
+    ops::Range,
+    rc::Rc,
+};
+
+use unicode_width::UnicodeWidthChar;
 
 use crate::{
     coord::{Coord, Dimensions},
-    escaping::{ANSIColor, EscapeBuilder},
+    escaping::{ANSIColor, CursorStyle, EscapeBuilder, Style},
 };
 
 /// A terminal cell representation
-/// A cell has an associated chacater, foreground and background colors
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A cell has an associated chacater, foreground and background colors, text attributes and an
+/// optional link
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Cell {
     pub character: char,
     pub fg_color: ANSIColor,
     pub bg_color: ANSIColor,
+    pub style: Style,
+    /// Color of the underline, drawn only by terminals that support it
+    pub underline_color: ANSIColor,
+    /// URI the cell links to, adjacent cells with the same link make up a single link
+    pub link: Option<Rc<str>>,
 }
 
 impl Cell {
-    /// Constructs a `Cell` out of its parts
+    /// Constructs a `Cell` with no text attributes out of its parts
     #[must_use]
     pub const fn new(character: char, fg_color: ANSIColor, bg_color: ANSIColor) -> Self {
         Self {
             character,
             fg_color,
             bg_color,
+            style: Style::NONE,
+            underline_color: ANSIColor::Default,
+            link: None,
         }
     }
+
+    /// Sets the text attributes of the cell
+    #[must_use]
+    pub const fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Sets the color of the cell's underline
+    #[must_use]
+    pub const fn with_underline_color(mut self, color: ANSIColor) -> Self {
+        self.underline_color = color;
+        self
+    }
+
+    /// Makes the cell a part of a link to `uri`
+    #[must_use]
+    pub fn with_link(mut self, uri: Rc<str>) -> Self {
+        self.link = Some(uri);
+        self
+    }
 }
 
 impl Default for Cell {
@@ -34,6 +70,39 @@ impl Default for Cell {
     }
 }
 
+/// The character of the cell to the right of a double-width character, which the double-width
+/// character covers. It is a control character, so `put_cell` never stores it otherwise
+pub const WIDE_CONTINUATION: char = '\u{1}';
+
+/// Returns the number of terminal columns `c` takes, which is 2 for wide characters such as CJK
+/// and emoji and 1 for the others, including the zero-width ones
+#[must_use]
+pub fn char_width(c: char) -> usize {
+    c.width().unwrap_or(1).clamp(1, 2)
+}
+
+/// Stands for a cell with unknown contents on the screen. `put_cell` never stores control
+/// characters, so it always differs from the drawn cell and gets redrawn
+const UNKNOWN_CELL: Cell = Cell::new('\0', ANSIColor::Default, ANSIColor::Default);
+
+/// Stands for a cell of a layer that has not been drawn to, through which the layers below show
+const TRANSPARENT_CELL: Cell = Cell::new('\0', ANSIColor::Default, ANSIColor::Default);
+
+/// The bottom layer of a window, which is drawn to unless another layer is selected
+pub const BASE_LAYER: usize = 0;
+
+/// Rows that moved vertically within a part of the screen since the last render
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Shift {
+    /// The first row of the moved part
+    top: usize,
+    /// The last row of the moved part, inclusive
+    bottom: usize,
+    /// Number of rows the contents moved by
+    by: usize,
+    up: bool,
+}
+
 /// A TUI "Window"
 ///
 /// It is used for drawing in the terminal that is exactly the size of the window
@@ -49,7 +118,27 @@ where
     cursor_pos: Coord,
 
     buffer: Vec<Cell>,
+    /// The layers composited together, which `buffer` is diffed against
     back_buffer: Vec<Cell>,
+    /// Rows that may differ between `buffer` and `back_buffer`, the other rows are never diffed
+    dirty_rows: Range<usize>,
+
+    /// Cells of the layers, which are composited in order. The base layer is opaque, the others
+    /// are transparent where they have not been drawn to
+    layers: Vec<Vec<Cell>>,
+    /// The layer that `put_cell` and `clear` draw to
+    active_layer: usize,
+
+    /// Wrap the output of a render in a synchronized update, so it is never displayed halfway
+    synchronized_output: bool,
+
+    /// Draw undercurls and colored underlines, which are replaced with plain underlines otherwise
+    extended_underlines: bool,
+
+    /// The cursor style requested with `set_cursor_style`
+    cursor_style: Option<CursorStyle>,
+    /// The cursor style the terminal was last told to use
+    drawn_cursor_style: Option<CursorStyle>,
 
     writer: W,
 }
@@ -68,6 +157,16 @@ where
 
             buffer: Vec::default(),
             back_buffer: Vec::default(),
+            dirty_rows: 0..0,
+
+            layers: vec![Vec::default()],
+            active_layer: BASE_LAYER,
+
+            synchronized_output: false,
+            extended_underlines: false,
+
+            cursor_style: None,
+            drawn_cursor_style: None,
 
             writer,
         }
@@ -101,6 +200,61 @@ where
 
         self.buffer = vec![Cell::default(); width * height];
         self.back_buffer = self.buffer.clone();
+        self.dirty_rows = 0..0;
+
+        self.layers[BASE_LAYER] = self.buffer.clone();
+        for layer in &mut self.layers[1..] {
+            *layer = vec![TRANSPARENT_CELL; width * height];
+        }
+    }
+
+    /// Selects the layer that the following `put_cell` and `clear` calls draw to, creating the
+    /// layers up to it. Layers with greater indices are drawn over the ones with lower indices
+    ///
+    /// Apart from `BASE_LAYER`, layers are transparent until drawn to, so popups can be drawn on
+    /// a layer of their own and erased with `clear_layer` without drawing the contents below again
+    pub fn set_layer(&mut self, layer: usize) {
+        while self.layers.len() <= layer {
+            self.layers
+                .push(vec![TRANSPARENT_CELL; self.width * self.height]);
+        }
+        self.active_layer = layer;
+    }
+
+    /// Returns the layer that is drawn to
+    pub const fn layer(&self) -> usize {
+        self.active_layer
+    }
+
+    /// Makes every cell of `layer` transparent, or resets every cell of `BASE_LAYER` to the
+    /// default cell. Does not draw
+    pub fn clear_layer(&mut self, layer: usize) {
+        let cell = if layer == BASE_LAYER {
+            Cell::default()
+        } else {
+            TRANSPARENT_CELL
+        };
+        if let Some(layer) = self.layers.get_mut(layer) {
+            layer.fill(cell);
+            self.dirty_rows = 0..self.height;
+        }
+    }
+
+    /// Enables or disables synchronized output, which should only be enabled for terminals that
+    /// support it. See `edi_term::supports_synchronized_output`
+    pub const fn set_synchronized_output(&mut self, enabled: bool) {
+        self.synchronized_output = enabled;
+    }
+
+    /// Enables or disables undercurls and colored underlines, which should only be enabled for
+    /// terminals that support them. See `edi_term::supports_extended_underlines`
+    pub const fn set_extended_underlines(&mut self, enabled: bool) {
+        self.extended_underlines = enabled;
+    }
+
+    /// Sets the style of the cursor, which is applied by the following render
+    pub const fn set_cursor_style(&mut self, style: CursorStyle) {
+        self.cursor_style = Some(style);
     }
 
     /// Returns width and height of the window
@@ -110,19 +264,30 @@ where
 
     /// Draws everyting in the writer and flushes
     ///
+    /// Only the rows changed since the last render are diffed. When a part of the screen moved
+    /// vertically, it is scrolled by the terminal instead of being drawn again
+    ///
     /// # Errors
     ///
     /// Fails when writing/flushing to the writer fails
     pub fn render(&mut self) -> Result<()> {
-        let diffs = self.produce_diffs();
-        self.buffer.copy_from_slice(&self.back_buffer);
+        self.composite();
+        let scroll = self
+            .find_shift()
+            .map_or_else(EscapeBuilder::new, |shift| self.apply_shift(shift));
+        let diffs = self.with_cursor(scroll.concat(self.produce_diffs()));
+        let diffs = self.synchronized(diffs);
+        self.buffer.clone_from_slice(&self.back_buffer);
+        self.dirty_rows = 0..0;
         self.write_flush(diffs.build().as_bytes())
     }
 
-    /// Resets all drawn cells to default cell with set background color. Does not draw
+    /// Resets all cells of the layer that is drawn to to default cell with set background color.
+    /// Does not draw
     pub fn clear(&mut self, color: ANSIColor) {
         let cell = Cell::new(' ', ANSIColor::Default, color);
-        self.back_buffer = vec![cell; self.width * self.height];
+        self.layers[self.active_layer] = vec![cell; self.width * self.height];
+        self.dirty_rows = 0..self.height;
     }
 
     /// Sets the cursor position to the `new_pos`
@@ -130,6 +295,11 @@ where
         self.cursor_pos = new_pos;
     }
 
+    /// Returns the position the cursor is drawn at
+    pub const fn cursor(&self) -> Coord {
+        self.cursor_pos
+    }
+
     /// Draws everyting in the writer and flushes
     /// The difference between this and `render()` is that this method does not rely on previous
     /// state to efficiently generate new output. The `render()` method should be preferred, unless
@@ -140,18 +310,27 @@ where
     /// Fails when writing/flushing to the writer fails
     ///
     pub fn rerender(&mut self) -> Result<()> {
-        self.buffer.copy_from_slice(&self.back_buffer);
-        let changes = EscapeBuilder::new()
-            .clear_screen()
-            .concat(self.as_escapes())
-            .move_to(Coord::default())
-            .build();
+        self.dirty_rows = 0..self.height;
+        self.composite();
+        self.buffer.clone_from_slice(&self.back_buffer);
+        self.dirty_rows = 0..0;
+        self.drawn_cursor_style = None;
+        let changes = self.with_cursor(
+            EscapeBuilder::new()
+                .clear_screen()
+                .concat(self.as_escapes())
+                .move_to(Coord::default()),
+        );
+        let changes = self.synchronized(changes).build();
 
         self.write_flush(changes.as_bytes())
     }
 
-    /// Puts a `Cell` in the position `pos`. Does not draw
-    pub fn put_cell(&mut self, pos: Coord, cell: Cell) -> bool {
+    /// Puts a `Cell` in the position `pos` of the layer that is drawn to. Does not draw
+    ///
+    /// A double-width character also takes the cell to the right, it is replaced with a space if
+    /// there is no room for it. Double-width characters that are partly overwritten are erased
+    pub fn put_cell(&mut self, pos: Coord, mut cell: Cell) -> bool {
         if pos.x >= self.width || pos.y >= self.height {
             return false;
         }
@@ -160,25 +339,166 @@ where
             return false;
         }
 
+        let wide = char_width(cell.character) == 2;
+        if wide && pos.x + 1 >= self.width {
+            cell.character = ' ';
+        }
+        let wide = wide && pos.x + 1 < self.width;
+
+        let layer = &mut self.layers[self.active_layer];
         let index = pos.y * self.width + pos.x;
-        self.back_buffer[index] = cell;
+        let row = pos.y * self.width..(pos.y + 1) * self.width;
+        let covered = if wide {
+            index..index + 2
+        } else {
+            index..index + 1
+        };
+        // Erases the double-width characters the new cell overlaps with only partly
+        if layer[covered.start].character == WIDE_CONTINUATION && covered.start > row.start {
+            layer[covered.start - 1].character = ' ';
+        }
+        if covered.end < row.end && layer[covered.end].character == WIDE_CONTINUATION {
+            layer[covered.end].character = ' ';
+        }
+
+        if wide {
+            layer[index + 1] = Cell {
+                character: WIDE_CONTINUATION,
+                ..cell.clone()
+            };
+        }
+        layer[index] = cell;
+        self.dirty_rows = if self.dirty_rows.is_empty() {
+            pos.y..pos.y + 1
+        } else {
+            self.dirty_rows.start.min(pos.y)..self.dirty_rows.end.max(pos.y + 1)
+        };
 
         true
     }
 
+    /// Draws the dirty rows of the layers into `back_buffer`, one over another
+    fn composite(&mut self) {
+        let width = self.width;
+        for y in self.dirty_rows.clone() {
+            let row = y * width..(y + 1) * width;
+            let composed = &mut self.back_buffer[row.clone()];
+            composed.clone_from_slice(&self.layers[BASE_LAYER][row.clone()]);
+            for layer in &self.layers[1..] {
+                for (cell, layer_cell) in composed.iter_mut().zip(&layer[row.clone()]) {
+                    if layer_cell.character != TRANSPARENT_CELL.character {
+                        cell.clone_from(layer_cell);
+                    }
+                }
+            }
+
+            // Erases the halves of double-width characters that the layers above cut off
+            for x in 0..width {
+                let head = x > 0 && char_width(composed[x - 1].character) == 2;
+                let continued = x + 1 < width && composed[x + 1].character == WIDE_CONTINUATION;
+                let cell = &mut composed[x];
+                if (cell.character == WIDE_CONTINUATION && !head)
+                    || (char_width(cell.character) == 2 && !continued)
+                {
+                    cell.character = ' ';
+                }
+            }
+        }
+    }
+
+    fn row(buffer: &[Cell], width: usize, y: usize) -> &[Cell] {
+        &buffer[y * width..(y + 1) * width]
+    }
+
+    /// Finds the vertical move of rows that saves the most rows from being drawn again
+    ///
+    /// For every distance, looks for the longest run of rows that show the rows that were drawn
+    /// `by` rows below (when scrolling up) or above (when scrolling down). Scrolling pays off only
+    /// if it saves more rows than it exposes, as the exposed rows are drawn again
+    fn find_shift(&self) -> Option<Shift> {
+        let rows = self.dirty_rows.clone();
+        let new = |y| Self::row(&self.back_buffer, self.width, y);
+        let old = |y| Self::row(&self.buffer, self.width, y);
+
+        let mut best: Option<(usize, Shift)> = None;
+        for by in 1..rows.len() {
+            for up in [true, false] {
+                let mut run = None;
+                let mut saved = 0;
+                for y in rows.start..rows.end {
+                    let source = if up { y + by } else { y.wrapping_sub(by) };
+                    if rows.contains(&source) && new(y) == old(source) {
+                        let start = *run.get_or_insert(y);
+                        saved += usize::from(new(y) != old(y));
+                        let shift = if up {
+                            Shift {
+                                top: start,
+                                bottom: y + by,
+                                by,
+                                up,
+                            }
+                        } else {
+                            Shift {
+                                top: start - by,
+                                bottom: y,
+                                by,
+                                up,
+                            }
+                        };
+                        if saved > by && best.is_none_or(|(best_saved, _)| saved > best_saved) {
+                            best = Some((saved, shift));
+                        }
+                    } else {
+                        run = None;
+                        saved = 0;
+                    }
+                }
+            }
+        }
+
+        best.map(|(_, shift)| shift)
+    }
+
+    /// Scrolls the part of the screen described by `shift`, updating `buffer` to match the screen
+    fn apply_shift<'a>(&mut self, shift: Shift) -> EscapeBuilder<'a> {
+        let Shift {
+            top,
+            bottom,
+            by,
+            up,
+        } = shift;
+        let width = self.width;
+        let escapes = EscapeBuilder::new().set_scroll_region(top, bottom);
+
+        let region = &mut self.buffer[top * width..(bottom + 1) * width];
+        let (escapes, exposed) = if up {
+            region.rotate_left(by * width);
+            (escapes.scroll_up(by), bottom + 1 - by..bottom + 1)
+        } else {
+            region.rotate_right(by * width);
+            (escapes.scroll_down(by), top..top + by)
+        };
+        self.buffer[exposed.start * width..exposed.end * width].fill(UNKNOWN_CELL);
+
+        escapes.reset_scroll_region()
+    }
+
     fn produce_diffs<'a>(&self) -> EscapeBuilder<'a> {
         let mut escape = EscapeBuilder::new();
 
         let mut prev_pos = None;
         let mut prev_color = None;
         let mut prev_bg = None;
+        let mut prev_style = None;
+        let mut prev_underline = None;
+        let mut prev_link = None;
 
-        for y in 0..self.height {
+        for y in self.dirty_rows.clone() {
             let row_offs = y * self.width;
             for x in 0..self.width {
                 let index = row_offs + x;
-                let cell = self.back_buffer[index];
-                if cell == self.buffer[index] {
+                let cell = &self.back_buffer[index];
+                if *cell == self.buffer[index] {
                     continue;
                 }
 
@@ -186,6 +506,18 @@ where
                     escape = escape.move_to(Coord::new(x, y));
                 }
 
+                let style = self.displayed_style(cell.style);
+                if prev_style != Some(style) {
+                    let reset;
+                    (escape, reset) = Self::switch_style(escape, prev_style, style);
+                    if reset {
+                        prev_color = None;
+                        prev_bg = None;
+                        prev_underline = None;
+                    }
+                    prev_style = Some(style);
+                }
+
                 if prev_color != Some(cell.fg_color) {
                     prev_color = Some(cell.fg_color);
                     escape = escape.set_color(cell.fg_color);
@@ -196,35 +528,145 @@ where
                     escape = escape.set_bg_color(cell.bg_color);
                 }
 
+                if self.extended_underlines && prev_underline != Some(cell.underline_color) {
+                    prev_underline = Some(cell.underline_color);
+                    escape = escape.set_underline_color(cell.underline_color);
+                }
+
                 prev_pos = Some((x, y));
+                // Written along with the double-width character before it
+                if cell.character == WIDE_CONTINUATION {
+                    continue;
+                }
+                escape = Self::switch_link(escape, &mut prev_link, cell.link.as_ref());
                 escape = escape.write(cell.character.to_string().into());
             }
         }
 
+        escape = Self::switch_link(escape, &mut prev_link, None);
         escape = escape.move_to(self.cursor_pos);
 
         escape
     }
 
-    fn as_escapes(&self) -> EscapeBuilder<'_> {
+    /// Returns the attributes `style` is drawn with, which has undercurls replaced with plain
+    /// underlines unless extended underlines are enabled
+    fn displayed_style(&self, style: Style) -> Style {
+        if self.extended_underlines || !style.contains(Style::UNDERCURL) {
+            return style;
+        }
+
+        style.difference(Style::UNDERCURL) | Style::UNDERLINE
+    }
+
+    /// Switches the text attributes from `prev` to `style`, `None` meaning that the attributes in
+    /// effect are unknown
+    ///
+    /// Attributes can only be turned off all at once, along with the colors. Returns whether
+    /// they were, in which case the colors have to be set again
+    fn switch_style(
+        escape: EscapeBuilder<'_>,
+        prev: Option<Style>,
+        style: Style,
+    ) -> (EscapeBuilder<'_>, bool) {
+        let (escape, added, reset) = match prev {
+            Some(prev) if style.contains(prev) => (escape, style.difference(prev), false),
+            _ => (escape.reset(), style, true),
+        };
+
+        if added.is_empty() {
+            (escape, reset)
+        } else {
+            (escape.set_style(added), reset)
+        }
+    }
+
+    fn as_escapes<'a>(&self) -> EscapeBuilder<'a> {
         let mut result = EscapeBuilder::new();
+        let mut prev_color = None;
+        let mut prev_bg = None;
+        let mut prev_style = None;
+        let mut prev_underline = None;
+        let mut prev_link = None;
 
         for i in 0..self.height {
             for j in 0..self.width {
                 let index = i * self.width + j;
-                let mut prev_cell = None;
-                let cell = self.buffer[index];
-                if index != 0 {
-                    prev_cell = self.buffer.get(index - 1);
+                let cell = &self.buffer[index];
+                let style = self.displayed_style(cell.style);
+                if prev_style != Some(style) {
+                    let reset;
+                    (result, reset) = Self::switch_style(result, prev_style, style);
+                    if reset {
+                        prev_color = None;
+                        prev_bg = None;
+                        prev_underline = None;
+                    }
+                    prev_style = Some(style);
                 }
-                if prev_cell.map(|c| c.fg_color) != Some(cell.fg_color) {
+                if prev_color != Some(cell.fg_color) {
+                    prev_color = Some(cell.fg_color);
                     result = result.set_color(cell.fg_color);
                 }
-                result = result.write(cell.character.to_string().into());
+                if prev_bg != Some(cell.bg_color) {
+                    prev_bg = Some(cell.bg_color);
+                    result = result.set_bg_color(cell.bg_color);
+                }
+                if self.extended_underlines && prev_underline != Some(cell.underline_color) {
+                    prev_underline = Some(cell.underline_color);
+                    result = result.set_underline_color(cell.underline_color);
+                }
+                if cell.character != WIDE_CONTINUATION {
+                    result = Self::switch_link(result, &mut prev_link, cell.link.as_ref());
+                    result = result.write(cell.character.to_string().into());
+                }
             }
         }
 
-        result
+        Self::switch_link(result, &mut prev_link, None)
+    }
+
+    /// Ends the link in effect, `prev`, and starts `link` if they differ
+    fn switch_link<'a>(
+        mut escape: EscapeBuilder<'a>,
+        prev: &mut Option<Rc<str>>,
+        link: Option<&Rc<str>>,
+    ) -> EscapeBuilder<'a> {
+        if prev.as_ref() == link {
+            return escape;
+        }
+
+        if prev.is_some() {
+            escape = escape.end_hyperlink();
+        }
+        if let Some(link) = link {
+            escape = escape.start_hyperlink(link.to_string().into());
+        }
+        *prev = link.cloned();
+        escape
+    }
+
+    /// Hides the cursor while `escapes` are drawn, then shows it with the requested style
+    fn with_cursor<'a>(&mut self, escapes: EscapeBuilder<'a>) -> EscapeBuilder<'a> {
+        let mut result = EscapeBuilder::new().hide_cursor().concat(escapes);
+        if let Some(style) = self.cursor_style
+            && self.drawn_cursor_style != Some(style)
+        {
+            result = result.change_cursor(style);
+            self.drawn_cursor_style = Some(style);
+        }
+        result.show_cursor()
+    }
+
+    fn synchronized<'a>(&self, escapes: EscapeBuilder<'a>) -> EscapeBuilder<'a> {
+        if !self.synchronized_output {
+            return escapes;
+        }
+
+        EscapeBuilder::new()
+            .begin_synchronized_update()
+            .concat(escapes)
+            .end_synchronized_update()
     }
 
     fn write_flush(&mut self, buf: &[u8]) -> Result<()> {
@@ -232,3 +674,252 @@ where
         self.writer.flush()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synchronized_output() {
+        let mut window = Window::from_writer(Vec::new());
+        window.set_size(Dimensions::new(2, 1));
+        window.put_cell(
+            Coord::new(0, 0),
+            Cell::new('a', ANSIColor::Red, ANSIColor::Default),
+        );
+        window.render().unwrap();
+        assert!(!window.writer.starts_with(b"\x1b[?2026h"));
+
+        window.writer.clear();
+        window.set_synchronized_output(true);
+        window.put_cell(
+            Coord::new(1, 0),
+            Cell::new('b', ANSIColor::Red, ANSIColor::Default),
+        );
+        window.render().unwrap();
+        assert!(window.writer.starts_with(b"\x1b[?2026h"));
+        assert!(window.writer.ends_with(b"\x1b[?2026l"));
+    }
+
+    fn put_column(window: &mut Window<Vec<u8>>, column: &str) {
+        for (y, c) in column.chars().enumerate() {
+            window.put_cell(
+                Coord::new(0, y),
+                Cell::new(c, ANSIColor::Red, ANSIColor::Default),
+            );
+        }
+    }
+
+    #[test]
+    fn scrolls_moved_rows() {
+        let mut window = Window::from_writer(Vec::new());
+        window.set_size(Dimensions::new(1, 6));
+        put_column(&mut window, "auvwxy");
+        window.render().unwrap();
+
+        window.writer.clear();
+        put_column(&mut window, "avwxyz");
+        window.render().unwrap();
+        let output = String::from_utf8(window.writer.clone()).unwrap();
+        assert!(
+            output.starts_with("\x1b[?25l\x1b[2;6r\x1b[1S\x1b[r"),
+            "{output:?}"
+        );
+        assert!(output.contains('z'));
+        assert!(!output.contains(['a', 'v', 'w', 'x', 'y']));
+        assert_eq!(window.buffer, window.back_buffer);
+
+        window.writer.clear();
+        put_column(&mut window, "auvwxy");
+        window.render().unwrap();
+        let output = String::from_utf8(window.writer.clone()).unwrap();
+        assert!(
+            output.starts_with("\x1b[?25l\x1b[2;6r\x1b[1T\x1b[r"),
+            "{output:?}"
+        );
+        assert!(output.contains('u'));
+        assert!(!output.contains(['v', 'w', 'x', 'y']));
+
+        window.writer.clear();
+        window.render().unwrap();
+        let output = String::from_utf8(window.writer.clone()).unwrap();
+        assert_eq!(output, "\x1b[?25l\x1b[1;1H\x1b[?25h");
+    }
+
+    #[test]
+    fn cursor_style() {
+        let mut window = Window::from_writer(Vec::new());
+        window.set_size(Dimensions::new(1, 1));
+        window.set_cursor_style(CursorStyle::Line);
+        window.render().unwrap();
+        window.render().unwrap();
+        assert_eq!(
+            String::from_utf8(window.writer.clone()).unwrap(),
+            "\x1b[?25l\x1b[1;1H\x1b[6 q\x1b[?25h\x1b[?25l\x1b[1;1H\x1b[?25h"
+        );
+
+        window.writer.clear();
+        window.rerender().unwrap();
+        let output = String::from_utf8(window.writer.clone()).unwrap();
+        assert!(output.ends_with("\x1b[6 q\x1b[?25h"), "{output:?}");
+    }
+
+    #[test]
+    fn switches_styles() {
+        let mut window = Window::from_writer(Vec::new());
+        window.set_size(Dimensions::new(4, 1));
+        let cell = |c, style| Cell::new(c, ANSIColor::Red, ANSIColor::Default).with_style(style);
+        window.put_cell(Coord::new(0, 0), cell('a', Style::BOLD));
+        window.put_cell(Coord::new(1, 0), cell('b', Style::BOLD | Style::ITALIC));
+        window.put_cell(Coord::new(2, 0), cell('c', Style::ITALIC));
+        window.put_cell(Coord::new(3, 0), cell('d', Style::NONE));
+        window.render().unwrap();
+
+        let colors = "\x1b[31m\x1b[49m";
+        let expected = [
+            "\x1b[?25l\x1b[1;1H\x1b[0m\x1b[1m",
+            colors,
+            "a\x1b[3mb\x1b[0m\x1b[3m",
+            colors,
+            "c\x1b[0m",
+            colors,
+            "d\x1b[1;1H\x1b[?25h",
+        ]
+        .concat();
+        assert_eq!(String::from_utf8(window.writer).unwrap(), expected);
+    }
+
+    #[test]
+    fn rerender_sets_background() {
+        let mut window = Window::from_writer(Vec::new());
+        window.set_size(Dimensions::new(3, 1));
+        window.put_cell(
+            Coord::new(0, 0),
+            Cell::new('a', ANSIColor::Black, ANSIColor::Cyan),
+        );
+        window.put_cell(
+            Coord::new(1, 0),
+            Cell::new('b', ANSIColor::Black, ANSIColor::Cyan),
+        );
+        window.put_cell(
+            Coord::new(2, 0),
+            Cell::new('c', ANSIColor::Black, ANSIColor::Default),
+        );
+        window.rerender().unwrap();
+
+        assert_eq!(
+            String::from_utf8(window.writer).unwrap(),
+            "\x1b[?25l\x1b[2J\x1b[0m\x1b[30m\x1b[46mab\x1b[49mc\x1b[1;1H\x1b[?25h"
+        );
+    }
+
+    #[test]
+    fn wide_characters() {
+        let mut window = Window::from_writer(Vec::new());
+        window.set_size(Dimensions::new(5, 1));
+        let cell = |c| Cell::new(c, ANSIColor::Red, ANSIColor::Default);
+        let row = |window: &Window<Vec<u8>>| {
+            window.layers[BASE_LAYER]
+                .iter()
+                .map(|cell| cell.character)
+                .collect::<String>()
+        };
+
+        window.put_cell(Coord::new(0, 0), cell('世'));
+        window.put_cell(Coord::new(2, 0), cell('界'));
+        window.put_cell(Coord::new(4, 0), cell('👍'));
+        assert_eq!(row(&window), "世\u{1}界\u{1} ");
+
+        window.render().unwrap();
+        let output = String::from_utf8(window.writer.clone()).unwrap();
+        assert!(output.contains("世界"), "{output:?}");
+        assert!(!output.contains('\u{1}'));
+
+        window.put_cell(Coord::new(1, 0), cell('a'));
+        assert_eq!(row(&window), " a界\u{1} ");
+        window.put_cell(Coord::new(2, 0), cell('b'));
+        assert_eq!(row(&window), " ab  ");
+    }
+
+    #[test]
+    fn links() {
+        let mut window = Window::from_writer(Vec::new());
+        window.set_size(Dimensions::new(4, 1));
+        let link: Rc<str> = Rc::from("https://example.com");
+        let cell = |c| Cell::new(c, ANSIColor::Red, ANSIColor::Default);
+        window.put_cell(Coord::new(0, 0), cell('a').with_link(Rc::clone(&link)));
+        window.put_cell(Coord::new(1, 0), cell('b').with_link(Rc::clone(&link)));
+        window.put_cell(Coord::new(2, 0), cell('c'));
+        window.put_cell(Coord::new(3, 0), cell('d').with_link(link));
+        window.render().unwrap();
+
+        let output = String::from_utf8(window.writer.clone()).unwrap();
+        let (start, end) = ("\x1b]8;;https://example.com\x1b\\", "\x1b]8;;\x1b\\");
+        assert!(
+            output.contains(&format!("{start}ab{end}c{start}d{end}")),
+            "{output:?}"
+        );
+
+        window.writer.clear();
+        window.rerender().unwrap();
+        let output = String::from_utf8(window.writer.clone()).unwrap();
+        assert_eq!(output.matches(start).count(), 2, "{output:?}");
+        assert_eq!(output.matches(end).count(), 2, "{output:?}");
+    }
+
+    #[test]
+    fn underline_fallback() {
+        let mut window = Window::from_writer(Vec::new());
+        window.set_size(Dimensions::new(1, 1));
+        let cell = Cell::new('a', ANSIColor::Red, ANSIColor::Default)
+            .with_style(Style::UNDERCURL)
+            .with_underline_color(ANSIColor::Rgb(255, 0, 0));
+        window.put_cell(Coord::new(0, 0), cell.clone());
+        window.render().unwrap();
+        let output = String::from_utf8(window.writer.clone()).unwrap();
+        assert!(output.contains("\x1b[4m"), "{output:?}");
+        assert!(!output.contains("4:3") && !output.contains("\x1b[58"));
+
+        window.writer.clear();
+        window.set_extended_underlines(true);
+        window.rerender().unwrap();
+        let output = String::from_utf8(window.writer.clone()).unwrap();
+        assert!(output.contains("\x1b[4:3m"), "{output:?}");
+        assert!(output.contains("\x1b[58;2;255;0;0m"), "{output:?}");
+    }
+
+    #[test]
+    fn composites_layers() {
+        let mut window = Window::from_writer(Vec::new());
+        window.set_size(Dimensions::new(4, 1));
+        let cell = |c| Cell::new(c, ANSIColor::Red, ANSIColor::Default);
+        for (x, c) in "a世c".chars().enumerate() {
+            window.put_cell(Coord::new(x + usize::from(x > 1), 0), cell(c));
+        }
+        window.render().unwrap();
+
+        window.set_layer(2);
+        window.put_cell(Coord::new(0, 0), cell('x'));
+        window.set_layer(1);
+        window.put_cell(Coord::new(0, 0), cell('y'));
+        window.put_cell(Coord::new(2, 0), cell('z'));
+        window.render().unwrap();
+        let row = |window: &Window<Vec<u8>>| {
+            window
+                .buffer
+                .iter()
+                .map(|cell| cell.character)
+                .collect::<String>()
+        };
+        assert_eq!(row(&window), "x zc");
+
+        window.writer.clear();
+        window.clear_layer(1);
+        window.clear_layer(2);
+        window.render().unwrap();
+        assert_eq!(row(&window), "a世\u{1}c");
+        let output = String::from_utf8(window.writer.clone()).unwrap();
+        assert!(output.contains("a世"), "{output:?}");
+        assert_eq!(window.layer(), 1);
+    }
+}
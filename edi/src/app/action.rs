@@ -1,15 +1,27 @@
 use std::borrow::Borrow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 
 use edi_lib::buffer;
-use edi_lib::string::position::{GlobalPosition, LinePosition};
-use edi_term::input::Input;
+use edi_lib::string::object::{ObjectKind, TextObject};
+use edi_lib::string::position::{CharSearch, GlobalPosition, LinePosition, WordPosition};
+use edi_term::input::{Input, KeyCode, Modifiers};
 use smallvec::{smallvec, SmallVec};
 
 const BUFFER_SIZE: usize = 4;
+/// The largest count a command can be repeated by
+const MAX_COUNT: usize = 99_999;
+/// The operators typed after `g` that change the case of letters, along with the key of each
+const CASE_OPERATORS: [(char, Operator); 3] = [
+    ('u', Operator::Lowercase),
+    ('U', Operator::Uppercase),
+    ('~', Operator::ToggleCase),
+];
 
-use super::meta::Flags;
+use super::explorer::Operation;
+use super::keymap;
+use super::meta::{Flags, ViewPosition};
+use super::substitute::Answer;
 use super::Mode;
 
 #[derive(Debug, Clone, Copy)]
@@ -31,15 +43,163 @@ impl From<Direction> for buffer::Direction {
     }
 }
 
+/// A command that acts on the text a motion moves over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    /// Deletes the text and switches to the insert mode
+    Change,
+    Yank,
+    /// Indents the lines by one shiftwidth
+    Indent,
+    /// Removes one shiftwidth of the indentation of the lines
+    Dedent,
+    /// Comments the lines out, or uncomments them if all of them are comments
+    Comment,
+    /// Makes the letters lowercase
+    Lowercase,
+    /// Makes the letters uppercase
+    Uppercase,
+    /// Makes the lowercase letters uppercase and the uppercase ones lowercase
+    ToggleCase,
+}
+
+/// A number typed in front of a command, which repeats it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Count(Option<usize>);
+
+impl Count {
+    pub const fn new() -> Self {
+        Self(None)
+    }
+
+    /// Appends a typed digit, returns `false` if `c` does not continue the count. A `0` does not
+    /// start one, as by itself it moves to the start of the line
+    pub fn push(&mut self, c: char) -> bool {
+        let Some(digit) = c.to_digit(10) else {
+            return false;
+        };
+        if digit == 0 && self.0.is_none() {
+            return false;
+        }
+
+        let count = self.0.unwrap_or(0) * 10 + digit as usize;
+        self.0 = Some(count.min(MAX_COUNT));
+        true
+    }
+
+    /// Returns how many times to repeat the command, once if no count was typed
+    pub fn get(self) -> usize {
+        self.0.unwrap_or(1)
+    }
+
+    /// Combines the count typed before an operator with the one typed after it, `2d3w` deletes
+    /// six words
+    pub fn times(self, other: Self) -> Self {
+        match (self.0, other.0) {
+            (Some(a), Some(b)) => Self(Some(a.saturating_mul(b).min(MAX_COUNT))),
+            (a, b) => Self(a.or(b)),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Action {
     SwitchMode(Mode),
     InsertChar(char),
+    InsertStr(String),
     DeleteChar,
     Submit,
     Undo,
     Redo,
-    Move { action: MoveAction, repeat: usize },
+    Suspend,
+    /// Makes the next keypress name the register the following command uses
+    SelectRegister,
+    YankLine,
+    Paste {
+        before: bool,
+    },
+    /// Makes the next keypress replace the characters under the cursor, as many as the count
+    ReplaceChar,
+    /// Makes the next keypress the character searched for in the cursor line, before the cursor
+    /// if `backward` is set. With `till` set the cursor stops next to it
+    FindChar {
+        backward: bool,
+        till: bool,
+    },
+    /// Searches for the character of the last `f`, `F`, `t` or `T` again, the other way if
+    /// `reverse` is set
+    RepeatFind {
+        reverse: bool,
+    },
+    /// Starts a new line below the cursor line, or above it if `above` is set
+    OpenLine {
+        above: bool,
+    },
+    /// Toggles the case of the characters from the cursor on and moves past them
+    ToggleCase,
+    StartSearch {
+        backward: bool,
+    },
+    SearchNext {
+        reverse: bool,
+    },
+    /// Scrolls the view so the cursor line is at the given position
+    Align(ViewPosition),
+    /// Scrolls the view sideways, to the right if `right` is set
+    ScrollColumns {
+        right: bool,
+    },
+    ConfirmSubstitution(Answer),
+    /// Shows what the language server knows about the symbol under the cursor
+    Hover,
+    /// Jumps to where the symbol under the cursor is defined
+    GotoDefinition,
+    /// Jumps to the next problem the language server or the linter found, or the previous one if
+    /// `reverse` is set
+    Diagnostic {
+        reverse: bool,
+    },
+    /// Jumps to the next misspelled word, or the previous one if `reverse` is set
+    Misspelling {
+        reverse: bool,
+    },
+    /// Opens the menu of the words the word under the cursor may have been meant to be
+    SuggestSpelling,
+    /// Selects the next candidate for the word being typed, or the previous one if `reverse` is
+    /// set, opening the menu of them if it is not open
+    Complete {
+        reverse: bool,
+    },
+    /// Makes the file explorer active, opening it if it is not open
+    ShowExplorer,
+    /// Carries out the operation on the entry under the cursor of the file explorer
+    Explore(Operation),
+    /// Completes the last word of the command line
+    CompleteCommand,
+    /// Shows an older command of the history in the command line, or a newer one
+    RecallCommand {
+        older: bool,
+    },
+    /// Makes the next motion select the text the operator acts on. Typing the operator twice
+    /// makes it act on the current line
+    Operator(Operator),
+    /// Makes the operator act on the current line and the lines after it, as typing it twice does
+    OperatorOnLines(Operator),
+    /// Makes the operator act on the text the motion moves over, as `x` does for `dl`
+    OperatorWithMotion(Operator, MoveAction),
+    /// Makes the pending operator act on the text object around the cursor
+    SelectObject(TextObject),
+    Move {
+        action: MoveAction,
+        repeat: usize,
+    },
+    /// Types the keys a user-defined mapping stands for. Unless `remap` is set, they are looked
+    /// up in the default mappings only
+    Keys {
+        inputs: Vec<Input>,
+        remap: bool,
+    },
 }
 
 impl Action {
@@ -51,11 +211,36 @@ impl Action {
 #[derive(Debug, Clone)]
 pub enum MoveAction {
     Regular(Direction),
+    /// Scrolls the view half a screen up or down, moving the cursor along with it
     HalfScreen(Direction),
+    /// Scrolls the view a screen up or down, keeping two lines of the previous one visible
+    FullScreen(Direction),
+    /// Moves up or down by the rows the wrapped lines are drawn on, rather than by lines
+    DisplayLine(Direction),
     InLine(LinePosition),
+    /// Moves by words, which are runs of any characters but whitespace if `big` is set
+    Word {
+        position: WordPosition,
+        big: bool,
+    },
+    /// Moves to a character in the cursor line. A `repeated` search is one `;` or `,` made
+    FindChar {
+        search: CharSearch,
+        repeated: bool,
+    },
     Global(GlobalPosition),
 }
 
+impl MoveAction {
+    /// Makes `G` and `gg` go to the line of the count instead, if one was typed
+    pub const fn with_count(self, count: Count) -> Self {
+        match (self, count.0) {
+            (Self::Global(_), Some(line)) => Self::Global(GlobalPosition::Line(line - 1)),
+            (action, _) => action,
+        }
+    }
+}
+
 trait KeyPair<K1, K2> {
     fn key1(&self) -> &K1;
     fn key2(&self) -> &K2;
@@ -106,15 +291,45 @@ impl<A: Eq, B: Eq> PartialEq for dyn KeyPair<A, B> + '_ {
 
 impl<A: Eq, B: Eq> Eq for dyn KeyPair<A, B> + '_ {}
 
+/// What the inputs typed so far map to
+#[derive(Debug)]
+pub enum Sequence {
+    /// The inputs are a whole mapping, which may have no actions if they are not mapped
+    Complete(SmallVec<[Action; BUFFER_SIZE]>),
+    /// The inputs start a longer mapping. `fallback` is what they map to by themselves, which is
+    /// used if no more input follows
+    Pending {
+        fallback: SmallVec<[Action; BUFFER_SIZE]>,
+    },
+}
+
 #[derive(Debug)]
 pub struct InputMapper {
     mappings: HashMap<(Mode, Input), SmallVec<[Action; BUFFER_SIZE]>>,
+    /// Mappings of several inputs
+    sequences: HashMap<(Mode, Vec<Input>), SmallVec<[Action; BUFFER_SIZE]>>,
+    /// Every proper prefix of the keys of `sequences`
+    prefixes: HashSet<(Mode, Vec<Input>)>,
+    /// Default mappings of the keys typed after the leader
+    leader_sequences: HashMap<(Mode, Vec<Input>), SmallVec<[Action; BUFFER_SIZE]>>,
+    /// Mappings defined by the user, which take precedence over the default ones
+    user: HashMap<(Mode, Vec<Input>), Action>,
+    /// Every proper prefix of the keys of `user`
+    user_prefixes: HashSet<(Mode, Vec<Input>)>,
+    /// The key `<leader>` stands for in user-defined mappings
+    pub leader: Input,
 }
 
 impl Default for InputMapper {
     fn default() -> Self {
         let mut mapper = InputMapper {
             mappings: HashMap::new(),
+            sequences: HashMap::new(),
+            prefixes: HashSet::new(),
+            leader_sequences: HashMap::new(),
+            user: HashMap::new(),
+            user_prefixes: HashSet::new(),
+            leader: Input::Keypress('\\'),
         };
 
         mapper.add_default_mappings();
@@ -125,8 +340,34 @@ impl Default for InputMapper {
 impl InputMapper {
     fn add_default_mappings(&mut self) {
         self.add_default_mappings_n();
+        self.add_default_multi_mappings_n();
+        self.add_default_sequences_n();
         self.add_default_mappings_i();
         self.add_default_mappings_t();
+        self.add_default_mappings_c();
+        self.add_default_mappings_o();
+        self.add_default_mappings_e();
+        self.add_default_find_mappings();
+    }
+
+    /// Maps `f`, `F`, `t`, `T`, `;` and `,` in the normal mode, and after an operator as they are
+    /// motions too
+    fn add_default_find_mappings(&mut self) {
+        let finds = [
+            ('f', false, false),
+            ('F', true, false),
+            ('t', false, true),
+            ('T', true, true),
+        ]
+        .map(|(c, backward, till)| (c, Action::FindChar { backward, till }));
+        let repeats =
+            [(';', false), (',', true)].map(|(c, reverse)| (c, Action::RepeatFind { reverse }));
+
+        for mode in [Mode::Normal, Mode::OperatorPending] {
+            for (c, action) in finds.iter().chain(&repeats) {
+                self.add_mapping(mode, Input::Keypress(*c), action.clone());
+            }
+        }
     }
 
     fn add_default_mappings_n(&mut self) {
@@ -134,14 +375,14 @@ impl InputMapper {
             self.add_mapping(Mode::Normal, input, action);
         };
 
-        map(
-            Input::Control('d'),
-            Action::move_once(MoveAction::HalfScreen(Direction::Down)),
-        );
-        map(
-            Input::Control('u'),
-            Action::move_once(MoveAction::HalfScreen(Direction::Up)),
-        );
+        for (c, action) in [
+            ('d', MoveAction::HalfScreen(Direction::Down)),
+            ('u', MoveAction::HalfScreen(Direction::Up)),
+            ('f', MoveAction::FullScreen(Direction::Down)),
+            ('b', MoveAction::FullScreen(Direction::Up)),
+        ] {
+            map(Input::Control(c), Action::move_once(action));
+        }
         map(
             Input::Keypress('h'),
             Action::move_once(MoveAction::Regular(Direction::Left)),
@@ -173,15 +414,26 @@ impl InputMapper {
             Action::move_once(MoveAction::InLine(LinePosition::CharacterStart)),
         );
 
-        map(
-            Input::Keypress('e'),
-            Action::move_once(MoveAction::InLine(LinePosition::CurrentWordEnd)),
-        );
-
-        map(
-            Input::Keypress('b'),
-            Action::move_once(MoveAction::InLine(LinePosition::CurrentWordStart)),
-        );
+        for (c, position) in [
+            ('w', WordPosition::NextStart),
+            ('e', WordPosition::End),
+            ('b', WordPosition::Start),
+        ] {
+            map(
+                Input::Keypress(c),
+                Action::move_once(MoveAction::Word {
+                    position,
+                    big: false,
+                }),
+            );
+            map(
+                Input::Keypress(c.to_ascii_uppercase()),
+                Action::move_once(MoveAction::Word {
+                    position,
+                    big: true,
+                }),
+            );
+        }
 
         map(
             Input::Keypress('G'),
@@ -190,8 +442,44 @@ impl InputMapper {
 
         map(Input::Keypress('u'), Action::Undo);
         map(Input::Control('r'), Action::Redo);
+        map(Input::Control('z'), Action::Suspend);
 
-        // TODO: Add 'gg' mapping
+        map(Input::Keypress('"'), Action::SelectRegister);
+        map(Input::Keypress('Y'), Action::YankLine);
+        map(Input::Keypress('d'), Action::Operator(Operator::Delete));
+        map(Input::Keypress('c'), Action::Operator(Operator::Change));
+        map(Input::Keypress('y'), Action::Operator(Operator::Yank));
+        map(Input::Keypress('>'), Action::Operator(Operator::Indent));
+        map(Input::Keypress('<'), Action::Operator(Operator::Dedent));
+        map(Input::Keypress('p'), Action::Paste { before: false });
+        map(Input::Keypress('P'), Action::Paste { before: true });
+        map(Input::Keypress('r'), Action::ReplaceChar);
+        for (c, operator, motion) in [
+            ('x', Operator::Delete, MoveAction::Regular(Direction::Right)),
+            ('X', Operator::Delete, MoveAction::Regular(Direction::Left)),
+            ('D', Operator::Delete, MoveAction::InLine(LinePosition::End)),
+            ('s', Operator::Change, MoveAction::Regular(Direction::Right)),
+            ('C', Operator::Change, MoveAction::InLine(LinePosition::End)),
+        ] {
+            map(
+                Input::Keypress(c),
+                Action::OperatorWithMotion(operator, motion),
+            );
+        }
+        map(Input::Keypress('~'), Action::ToggleCase);
+
+        map(
+            Input::Keypress('/'),
+            Action::StartSearch { backward: false },
+        );
+        map(Input::Keypress('?'), Action::StartSearch { backward: true });
+        map(Input::Keypress('n'), Action::SearchNext { reverse: false });
+        map(Input::Keypress('N'), Action::SearchNext { reverse: true });
+        map(Input::Keypress('K'), Action::Hover);
+    }
+
+    /// Maps the keys that move the cursor before entering insert mode
+    fn add_default_multi_mappings_n(&mut self) {
         let mut multimap = |input, actions| {
             self.add_multi_mapping(Mode::Normal, input, actions);
         };
@@ -219,6 +507,102 @@ impl InputMapper {
                 Action::SwitchMode(Mode::Insert),
             ],
         );
+
+        for (c, above) in [('o', false), ('O', true)] {
+            multimap(
+                Input::Keypress(c),
+                smallvec![Action::SwitchMode(Mode::Insert), Action::OpenLine { above }],
+            );
+        }
+    }
+
+    fn add_default_sequences_n(&mut self) {
+        self.add_sequence_mapping(
+            Mode::Normal,
+            &[Input::Keypress('g'), Input::Keypress('g')],
+            smallvec![Action::move_once(MoveAction::Global(GlobalPosition::Start))],
+        );
+        for (c, big) in [('e', false), ('E', true)] {
+            self.add_sequence_mapping(
+                Mode::Normal,
+                &[Input::Keypress('g'), Input::Keypress(c)],
+                smallvec![Action::move_once(MoveAction::Word {
+                    position: WordPosition::PreviousEnd,
+                    big,
+                })],
+            );
+        }
+        for (c, direction) in [('j', Direction::Down), ('k', Direction::Up)] {
+            self.add_sequence_mapping(
+                Mode::Normal,
+                &[Input::Keypress('g'), Input::Keypress(c)],
+                smallvec![Action::move_once(MoveAction::DisplayLine(direction))],
+            );
+        }
+        self.add_sequence_mapping(
+            Mode::Normal,
+            &[Input::Keypress('g'), Input::Keypress('d')],
+            smallvec![Action::GotoDefinition],
+        );
+        for (c, reverse) in [(']', false), ('[', true)] {
+            self.add_sequence_mapping(
+                Mode::Normal,
+                &[Input::Keypress(c), Input::Keypress('d')],
+                smallvec![Action::Diagnostic { reverse }],
+            );
+            self.add_sequence_mapping(
+                Mode::Normal,
+                &[Input::Keypress(c), Input::Keypress('s')],
+                smallvec![Action::Misspelling { reverse }],
+            );
+        }
+        let gc = [Input::Keypress('g'), Input::Keypress('c')];
+        self.add_sequence_mapping(
+            Mode::Normal,
+            &gc,
+            smallvec![Action::Operator(Operator::Comment)],
+        );
+        self.add_sequence_mapping(
+            Mode::Normal,
+            &[gc[0].clone(), gc[1].clone(), Input::Keypress('c')],
+            smallvec![Action::OperatorOnLines(Operator::Comment)],
+        );
+        for (c, operator) in CASE_OPERATORS {
+            let keys = [Input::Keypress('g'), Input::Keypress(c)];
+            self.add_sequence_mapping(Mode::Normal, &keys, smallvec![Action::Operator(operator)]);
+            self.add_sequence_mapping(
+                Mode::Normal,
+                &[keys[0].clone(), keys[1].clone(), Input::Keypress(c)],
+                smallvec![Action::OperatorOnLines(operator)],
+            );
+        }
+        for (c, position) in [
+            ('z', ViewPosition::Center),
+            ('t', ViewPosition::Top),
+            ('b', ViewPosition::Bottom),
+        ] {
+            self.add_sequence_mapping(
+                Mode::Normal,
+                &[Input::Keypress('z'), Input::Keypress(c)],
+                smallvec![Action::Align(position)],
+            );
+        }
+        for (c, right) in [('h', false), ('l', true)] {
+            self.add_sequence_mapping(
+                Mode::Normal,
+                &[Input::Keypress('z'), Input::Keypress(c)],
+                smallvec![Action::ScrollColumns { right }],
+            );
+        }
+        self.add_sequence_mapping(
+            Mode::Normal,
+            &[Input::Keypress('z'), Input::Keypress('=')],
+            smallvec![Action::SuggestSpelling],
+        );
+        self.leader_sequences.insert(
+            (Mode::Normal, vec![Input::Keypress('e')]),
+            smallvec![Action::ShowExplorer],
+        );
     }
 
     fn add_default_mappings_i(&mut self) {
@@ -229,6 +613,8 @@ impl InputMapper {
         map(Input::Escape, Action::SwitchMode(Mode::Normal));
         map(Input::Enter, Action::InsertChar('\n'));
         map(Input::Backspace, Action::DeleteChar);
+        map(Input::Control('n'), Action::Complete { reverse: false });
+        map(Input::Control('p'), Action::Complete { reverse: true });
         map(
             Input::ArrowLeft,
             Action::move_once(MoveAction::Regular(Direction::Left)),
@@ -245,6 +631,14 @@ impl InputMapper {
             Input::ArrowRight,
             Action::move_once(MoveAction::Regular(Direction::Right)),
         );
+        map(
+            Input::key(KeyCode::Home, Modifiers::NONE),
+            Action::move_once(MoveAction::InLine(LinePosition::Start)),
+        );
+        map(
+            Input::key(KeyCode::End, Modifiers::NONE),
+            Action::move_once(MoveAction::InLine(LinePosition::End)),
+        );
     }
 
     fn add_default_mappings_t(&mut self) {
@@ -263,6 +657,169 @@ impl InputMapper {
             Action::move_once(MoveAction::Regular(Direction::Left)),
         );
         map(Input::Enter, Action::Submit);
+        map(Input::Keypress('\t'), Action::CompleteCommand);
+        map(Input::ArrowUp, Action::RecallCommand { older: true });
+        map(Input::ArrowDown, Action::RecallCommand { older: false });
+    }
+
+    fn add_default_mappings_c(&mut self) {
+        let mut map = |input, action| {
+            self.add_mapping(Mode::Confirm, input, action);
+        };
+
+        map(
+            Input::Keypress('y'),
+            Action::ConfirmSubstitution(Answer::Yes),
+        );
+        map(
+            Input::Keypress('n'),
+            Action::ConfirmSubstitution(Answer::No),
+        );
+        map(
+            Input::Keypress('a'),
+            Action::ConfirmSubstitution(Answer::All),
+        );
+        map(
+            Input::Keypress('q'),
+            Action::ConfirmSubstitution(Answer::Quit),
+        );
+        map(Input::Escape, Action::ConfirmSubstitution(Answer::Quit));
+    }
+
+    /// Makes the keys typed after an operator move the same way they do in the normal mode
+    fn add_default_mappings_o(&mut self) {
+        let is_motion = |actions: &SmallVec<[Action; BUFFER_SIZE]>| {
+            actions.iter().all(|a| matches!(a, Action::Move { .. }))
+        };
+
+        let motions: Vec<_> = self
+            .mappings
+            .iter()
+            .filter(|&(&(mode, _), actions)| mode == Mode::Normal && is_motion(actions))
+            .map(|((_, input), actions)| (input.clone(), actions.clone()))
+            .collect();
+        for (input, actions) in motions {
+            self.add_multi_mapping(Mode::OperatorPending, input, actions);
+        }
+
+        let sequences: Vec<_> = self
+            .sequences
+            .iter()
+            .filter(|&(&(mode, _), actions)| mode == Mode::Normal && is_motion(actions))
+            .map(|((_, inputs), actions)| (inputs.clone(), actions.clone()))
+            .collect();
+        for (inputs, actions) in sequences {
+            self.add_sequence_mapping(Mode::OperatorPending, &inputs, actions);
+        }
+
+        for (c, operator) in [
+            ('d', Operator::Delete),
+            ('c', Operator::Change),
+            ('y', Operator::Yank),
+            ('>', Operator::Indent),
+            ('<', Operator::Dedent),
+        ] {
+            self.add_mapping(
+                Mode::OperatorPending,
+                Input::Keypress(c),
+                Action::Operator(operator),
+            );
+        }
+        self.add_sequence_mapping(
+            Mode::OperatorPending,
+            &[Input::Keypress('g'), Input::Keypress('c')],
+            smallvec![Action::Operator(Operator::Comment)],
+        );
+        for (c, operator) in CASE_OPERATORS {
+            self.add_sequence_mapping(
+                Mode::OperatorPending,
+                &[Input::Keypress('g'), Input::Keypress(c)],
+                smallvec![Action::Operator(operator)],
+            );
+        }
+
+        // Text objects: `iw`, `a"`, `i(`, `ab`, `ip` and the like
+        let quotes = ['"', '\'', '`'].map(|q| (q, ObjectKind::Quote(q)));
+        let brackets = [
+            ("()b", '(', ')'),
+            ("[]", '[', ']'),
+            ("{}B", '{', '}'),
+            ("<>", '<', '>'),
+        ];
+        let keys = [('w', ObjectKind::Word), ('p', ObjectKind::Paragraph)]
+            .into_iter()
+            .chain(quotes)
+            .chain(brackets.into_iter().flat_map(|(keys, open, close)| {
+                keys.chars()
+                    .map(move |key| (key, ObjectKind::Bracket { open, close }))
+            }));
+        for (key, kind) in keys {
+            for (prefix, object) in [
+                ('i', TextObject::inner(kind)),
+                ('a', TextObject::around(kind)),
+            ] {
+                self.add_sequence_mapping(
+                    Mode::OperatorPending,
+                    &[Input::Keypress(prefix), Input::Keypress(key)],
+                    smallvec![Action::SelectObject(object)],
+                );
+            }
+        }
+    }
+
+    /// Moves the cursor through the file explorer as in the normal mode, and maps the keys that
+    /// act on its entries
+    fn add_default_mappings_e(&mut self) {
+        let is_motion = |actions: &SmallVec<[Action; BUFFER_SIZE]>| {
+            actions.iter().all(|a| matches!(a, Action::Move { .. }))
+        };
+
+        let motions: Vec<_> = self
+            .mappings
+            .iter()
+            .filter(|&(&(mode, _), actions)| mode == Mode::Normal && is_motion(actions))
+            .map(|((_, input), actions)| (input.clone(), actions.clone()))
+            .collect();
+        for (input, actions) in motions {
+            self.add_multi_mapping(Mode::Explorer, input, actions);
+        }
+        self.add_sequence_mapping(
+            Mode::Explorer,
+            &[Input::Keypress('g'), Input::Keypress('g')],
+            smallvec![Action::move_once(MoveAction::Global(GlobalPosition::Start))],
+        );
+
+        let mut map = |input, action| {
+            self.add_mapping(Mode::Explorer, input, action);
+        };
+
+        map(
+            Input::ArrowUp,
+            Action::move_once(MoveAction::Regular(Direction::Up)),
+        );
+        map(
+            Input::ArrowDown,
+            Action::move_once(MoveAction::Regular(Direction::Down)),
+        );
+        map(Input::Enter, Action::Explore(Operation::Open));
+        map(Input::Keypress('o'), Action::Explore(Operation::Open));
+        map(Input::Keypress('l'), Action::Explore(Operation::Open));
+        map(Input::Keypress('h'), Action::Explore(Operation::Collapse));
+        map(Input::Keypress('-'), Action::Explore(Operation::Parent));
+        map(Input::Keypress('R'), Action::Explore(Operation::Refresh));
+        map(Input::Keypress('a'), Action::Explore(Operation::Create));
+        map(Input::Keypress('r'), Action::Explore(Operation::Rename));
+        map(Input::Keypress('d'), Action::Explore(Operation::Delete));
+        map(Input::Keypress('q'), Action::Explore(Operation::Close));
+        map(Input::Keypress(':'), Action::SwitchMode(Mode::Terminal));
+        map(
+            Input::Keypress('/'),
+            Action::StartSearch { backward: false },
+        );
+        map(Input::Keypress('?'), Action::StartSearch { backward: true });
+        map(Input::Keypress('n'), Action::SearchNext { reverse: false });
+        map(Input::Keypress('N'), Action::SearchNext { reverse: true });
+        map(Input::Control('z'), Action::Suspend);
     }
 
     pub fn add_mapping(&mut self, mode: Mode, input: Input, action: Action) {
@@ -278,6 +835,97 @@ impl InputMapper {
         self.mappings.insert((mode, input), actions);
     }
 
+    /// Maps `inputs` typed one after another to the given actions
+    pub fn add_sequence_mapping(
+        &mut self,
+        mode: Mode,
+        inputs: &[Input],
+        actions: SmallVec<[Action; BUFFER_SIZE]>,
+    ) {
+        if let [input] = inputs {
+            self.add_multi_mapping(mode, input.clone(), actions);
+            return;
+        }
+
+        for len in 1..inputs.len() {
+            self.prefixes.insert((mode, inputs[..len].to_vec()));
+        }
+        self.sequences.insert((mode, inputs.to_vec()), actions);
+    }
+
+    /// Maps the keys written as `lhs` to the ones written as `rhs`, both of which may contain
+    /// `<leader>` and names of keys such as `<CR>`. Unless `remap` is set, the keys of `rhs` are
+    /// looked up in the default mappings only
+    pub fn add_user_mapping(&mut self, mode: Mode, lhs: &str, rhs: &str, remap: bool) {
+        let inputs = keymap::parse_keys(lhs, &self.leader);
+        for len in 1..inputs.len() {
+            self.user_prefixes.insert((mode, inputs[..len].to_vec()));
+        }
+
+        let action = Action::Keys {
+            inputs: keymap::parse_keys(rhs, &self.leader),
+            remap,
+        };
+        self.user.insert((mode, inputs), action);
+    }
+
+    /// Looks up the inputs typed so far, which may be the start of a longer mapping. The
+    /// user-defined mappings are only looked up if `remap` is set
+    pub fn map_sequence(
+        &self,
+        inputs: &[Input],
+        mode: Mode,
+        active_flags: Flags,
+        remap: bool,
+    ) -> Sequence {
+        let key = (mode, inputs.to_vec());
+        let user = self.user.get(&key).filter(|_| remap);
+        let actions = match (user, inputs) {
+            (Some(action), _) => smallvec![action.clone()],
+            (None, [input]) => self.map_input(input, mode, active_flags),
+            (None, _) => self
+                .sequences
+                .get(&key)
+                .or_else(|| self.leader_sequence(mode, inputs))
+                .cloned()
+                .unwrap_or_default(),
+        };
+
+        if self.prefixes.contains(&key)
+            || self.is_leader_prefix(mode, inputs)
+            || (remap && self.user_prefixes.contains(&key))
+        {
+            Sequence::Pending { fallback: actions }
+        } else {
+            Sequence::Complete(actions)
+        }
+    }
+
+    /// Returns what the inputs map to if they are the leader followed by the keys of a default
+    /// mapping
+    fn leader_sequence(
+        &self,
+        mode: Mode,
+        inputs: &[Input],
+    ) -> Option<&SmallVec<[Action; BUFFER_SIZE]>> {
+        let (first, rest) = inputs.split_first()?;
+        if *first != self.leader {
+            return None;
+        }
+        self.leader_sequences.get(&(mode, rest.to_vec()))
+    }
+
+    /// Returns whether the inputs are the leader followed by the start of the keys of a default
+    /// mapping
+    fn is_leader_prefix(&self, mode: Mode, inputs: &[Input]) -> bool {
+        inputs.split_first().is_some_and(|(first, rest)| {
+            *first == self.leader
+                && self.leader_sequences.keys().any(|(sequence_mode, keys)| {
+                    *sequence_mode == mode && keys.len() > rest.len() && keys.starts_with(rest)
+                })
+        })
+    }
+
     pub fn map_input(
         &self,
         input: &Input,
@@ -298,7 +946,260 @@ impl InputMapper {
             (Mode::Insert | Mode::Terminal, Input::Keypress(c)) => {
                 smallvec![Action::InsertChar(*c)]
             }
+            (Mode::Insert | Mode::Terminal, Input::Paste(s)) => {
+                smallvec![Action::InsertStr(s.clone())]
+            }
             _ => smallvec![],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(mapper: &InputMapper, keys: &str, mode: Mode) -> Sequence {
+        let inputs: Vec<_> = keys.chars().map(Input::Keypress).collect();
+        mapper.map_sequence(&inputs, mode, Flags::empty(), true)
+    }
+
+    #[test]
+    fn sequences() {
+        let mut mapper = InputMapper::default();
+
+        assert!(matches!(
+            map(&mapper, "g", Mode::Normal),
+            Sequence::Pending { fallback } if fallback.is_empty()
+        ));
+        assert!(matches!(
+            map(&mapper, "gg", Mode::Normal),
+            Sequence::Complete(actions) if matches!(
+                actions[..],
+                [Action::Move { action: MoveAction::Global(GlobalPosition::Start), .. }]
+            )
+        ));
+        assert!(matches!(
+            map(&mapper, "gj", Mode::OperatorPending),
+            Sequence::Complete(actions) if matches!(
+                actions[..],
+                [Action::Move { action: MoveAction::DisplayLine(Direction::Down), .. }]
+            )
+        ));
+        assert!(matches!(
+            map(&mapper, "gx", Mode::Normal),
+            Sequence::Complete(actions) if actions.is_empty()
+        ));
+
+        mapper.add_sequence_mapping(
+            Mode::Normal,
+            &[Input::Keypress('y'), Input::Keypress('z')],
+            smallvec![Action::Undo],
+        );
+        assert!(matches!(
+            map(&mapper, "y", Mode::Normal),
+            Sequence::Pending { fallback } if matches!(fallback[..], [Action::Operator(Operator::Yank)])
+        ));
+    }
+
+    #[test]
+    fn user_mappings() {
+        let mut mapper = InputMapper {
+            leader: Input::Keypress(','),
+            ..InputMapper::default()
+        };
+        mapper.add_user_mapping(Mode::Normal, "<leader>w", ":w<CR>", false);
+        mapper.add_user_mapping(Mode::Insert, "jk", "<Esc>", true);
+
+        assert!(matches!(
+            map(&mapper, ",", Mode::Normal),
+            Sequence::Pending { fallback }
+                if matches!(fallback[..], [Action::RepeatFind { reverse: true }])
+        ));
+        assert!(matches!(
+            map(&mapper, ",w", Mode::Normal),
+            Sequence::Complete(actions) if matches!(
+                &actions[..],
+                [Action::Keys { inputs, remap: false }]
+                    if inputs[..] == [Input::Keypress(':'), Input::Keypress('w'), Input::Enter]
+            )
+        ));
+        assert!(matches!(
+            map(&mapper, "j", Mode::Insert),
+            Sequence::Pending { fallback } if matches!(fallback[..], [Action::InsertChar('j')])
+        ));
+
+        // Keys typed by a mapping that does not remap skip the user-defined mappings
+        let inputs = [Input::Keypress('j')];
+        assert!(matches!(
+            mapper.map_sequence(&inputs, Mode::Insert, Flags::empty(), false),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::InsertChar('j')])
+        ));
+    }
+
+    #[test]
+    fn leader() {
+        let mut mapper = InputMapper::default();
+        assert!(matches!(
+            map(&mapper, "\\", Mode::Normal),
+            Sequence::Pending { fallback } if fallback.is_empty()
+        ));
+        assert!(matches!(
+            map(&mapper, "\\e", Mode::Normal),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::ShowExplorer])
+        ));
+
+        // The default mappings follow the leader when it is changed
+        mapper.leader = Input::Keypress(' ');
+        assert!(matches!(
+            map(&mapper, " e", Mode::Normal),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::ShowExplorer])
+        ));
+        assert!(matches!(
+            map(&mapper, "\\e", Mode::Normal),
+            Sequence::Complete(actions) if actions.is_empty()
+        ));
+    }
+
+    #[test]
+    fn explorer() {
+        let mapper = InputMapper::default();
+        assert!(matches!(
+            map(&mapper, "j", Mode::Explorer),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::Move { .. }])
+        ));
+        assert!(matches!(
+            map(&mapper, "d", Mode::Explorer),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::Explore(Operation::Delete)])
+        ));
+        assert!(matches!(
+            map(&mapper, "i", Mode::Explorer),
+            Sequence::Complete(actions) if actions.is_empty()
+        ));
+    }
+
+    #[test]
+    fn count() {
+        let mut count = Count::default();
+        assert!(!count.push('0'));
+        assert_eq!(count.get(), 1);
+        assert!(count.push('1') && count.push('0') && !count.push('x'));
+        assert_eq!(count.get(), 10);
+        assert_eq!(count.times(Count::default()).get(), 10);
+
+        let mut other = Count::default();
+        assert!(other.push('3'));
+        assert_eq!(count.times(other).get(), 30);
+        assert_eq!(Count::default().times(other).get(), 3);
+
+        for _ in 0..10 {
+            count.push('9');
+        }
+        assert_eq!(count.get(), MAX_COUNT);
+
+        let end = MoveAction::Global(GlobalPosition::End);
+        assert!(matches!(
+            end.clone().with_count(other),
+            MoveAction::Global(GlobalPosition::Line(2))
+        ));
+        assert!(matches!(
+            end.with_count(Count::default()),
+            MoveAction::Global(GlobalPosition::End)
+        ));
+    }
+
+    #[test]
+    fn operator_pending() {
+        let mapper = InputMapper::default();
+
+        assert!(matches!(
+            map(&mapper, "d", Mode::Normal),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::Operator(Operator::Delete)])
+        ));
+        assert!(matches!(
+            map(&mapper, "j", Mode::OperatorPending),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::Move { .. }])
+        ));
+        assert!(matches!(
+            map(&mapper, "g", Mode::OperatorPending),
+            Sequence::Pending { .. }
+        ));
+        assert!(matches!(
+            map(&mapper, "i", Mode::OperatorPending),
+            Sequence::Pending { fallback } if fallback.is_empty()
+        ));
+        assert!(matches!(
+            map(&mapper, "ab", Mode::OperatorPending),
+            Sequence::Complete(actions) if matches!(
+                actions[..],
+                [Action::SelectObject(TextObject {
+                    kind: ObjectKind::Bracket { open: '(', close: ')' },
+                    around: true,
+                })]
+            )
+        ));
+        assert!(matches!(
+            map(&mapper, "iw", Mode::Normal),
+            Sequence::Complete(actions) if actions.is_empty()
+        ));
+    }
+
+    #[test]
+    fn case_operators() {
+        let mapper = InputMapper::default();
+
+        assert!(matches!(
+            map(&mapper, "gU", Mode::OperatorPending),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::Operator(Operator::Uppercase)])
+        ));
+        assert!(matches!(
+            map(&mapper, "guu", Mode::Normal),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::OperatorOnLines(Operator::Lowercase)])
+        ));
+        assert!(matches!(
+            map(&mapper, "~", Mode::Normal),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::ToggleCase])
+        ));
+    }
+
+    #[test]
+    fn shorthand_operators() {
+        let mapper = InputMapper::default();
+
+        assert!(matches!(
+            map(&mapper, "x", Mode::Normal),
+            Sequence::Complete(actions) if matches!(
+                actions[..],
+                [Action::OperatorWithMotion(Operator::Delete, MoveAction::Regular(Direction::Right))]
+            )
+        ));
+        assert!(matches!(
+            map(&mapper, "C", Mode::Normal),
+            Sequence::Complete(actions) if matches!(
+                actions[..],
+                [Action::OperatorWithMotion(Operator::Change, MoveAction::InLine(LinePosition::End))]
+            )
+        ));
+        assert!(matches!(
+            map(&mapper, "r", Mode::Normal),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::ReplaceChar])
+        ));
+    }
+
+    #[test]
+    fn find_char() {
+        let mapper = InputMapper::default();
+
+        for mode in [Mode::Normal, Mode::OperatorPending] {
+            assert!(matches!(
+                map(&mapper, "T", mode),
+                Sequence::Complete(actions)
+                    if matches!(actions[..], [Action::FindChar { backward: true, till: true }])
+            ));
+            assert!(matches!(
+                map(&mapper, ";", mode),
+                Sequence::Complete(actions)
+                    if matches!(actions[..], [Action::RepeatFind { reverse: false }])
+            ));
+        }
+    }
+}
@@ -1,5 +1,6 @@
-use edi_lib::buffer;
 use edi_lib::brand::Id;
+use edi_lib::buffer;
+use edi_lib::string::highlight::Edit;
 
 use crate::{controller::Handle, event::emitter};
 
@@ -11,6 +12,8 @@ pub struct BufferBundle {
     pub(super) position: usize,
     buffer: buffer::Buffer,
     meta: meta::BufferMeta,
+    /// The changes made to the buffer since its highlights were last updated
+    edits: Vec<Edit>,
 }
 
 impl BufferBundle {
@@ -25,13 +28,10 @@ impl BufferBundle {
             position,
             buffer,
             meta,
+            edits: Vec::new(),
         }
     }
 
-    pub const fn is_active(&self) -> bool {
-        self.position() == 0
-    }
-
     pub const fn position(&self) -> usize {
         self.position
     }
@@ -50,11 +50,17 @@ impl BufferBundle {
         ctrl: &'b mut Handle<State>,
     ) -> (emitter::buffer::Buffer<'a, 'b>, &'a mut meta::BufferMeta) {
         (
-            emitter::buffer::Buffer::new(self.id, &mut self.buffer, ctrl),
+            emitter::buffer::Buffer::new(self.id, &mut self.buffer, &mut self.edits, ctrl),
             &mut self.meta,
         )
     }
 
+    /// Splits the bundle without tracking the changes made to the buffer, meant for moving the
+    /// cursor rather than editing
+    pub const fn as_split_mut_silent(&mut self) -> (&mut buffer::Buffer, &mut meta::BufferMeta) {
+        (&mut self.buffer, &mut self.meta)
+    }
+
     pub const fn buffer(&self) -> &buffer::Buffer {
         &self.buffer
     }
@@ -64,7 +70,22 @@ impl BufferBundle {
         &'a mut self,
         ctrl: &'b mut Handle<State>,
     ) -> emitter::buffer::Buffer<'a, 'b> {
-        emitter::buffer::Buffer::new(self.id, &mut self.buffer, ctrl)
+        emitter::buffer::Buffer::new(self.id, &mut self.buffer, &mut self.edits, ctrl)
+    }
+
+    /// Brings the highlights and the positions of the invalid bytes up to date with the changes
+    /// made to the buffer since the last time, the misspelled words are to be found again
+    pub fn apply_edits(&mut self) {
+        let meta = &mut self.meta;
+        if !self.edits.is_empty() {
+            meta.misspellings = None;
+        }
+        meta.highlighter
+            .update(&self.buffer.inner, &self.edits, &mut meta.highlights);
+        for edit in &self.edits {
+            meta.invalid_bytes.apply(edit);
+        }
+        self.edits.clear();
     }
 
     pub const fn meta(&self) -> &meta::BufferMeta {
@@ -10,6 +10,8 @@ use super::{buffer_bundle::BufferBundle, meta::BufferMeta, Mode};
 pub enum Selector {
     First,
     Active,
+    /// The buffer that was active before the active one, which is what a prompt acts on
+    Previous,
     Nth(usize),
     WithId(Id),
 }
@@ -19,7 +21,10 @@ pub struct Buffers {
     brand: Tag,
     inner: BTreeMap<Id, BufferBundle>,
     buffer_order: Vec<Id>, // INVARIANT: length is always equal to the length of
-                           // inner AND all ids are unique
+    // inner AND all ids are unique
+    /// The ids of `buffer_order` from the least to the most recently focused one, the last one
+    /// being active
+    focus: Vec<Id>,
 }
 
 impl Buffers {
@@ -30,6 +35,7 @@ impl Buffers {
             brand,
             inner: BTreeMap::new(),
             buffer_order: Vec::new(),
+            focus: Vec::new(),
         }
     }
 
@@ -43,6 +49,7 @@ impl Buffers {
         match selector {
             Selector::First | Selector::Nth(0) => self.first(),
             Selector::Active => self.active(),
+            Selector::Previous => self.previous_id().and_then(|id| self.inner.get(&id)),
             Selector::WithId(id) => self.inner.get(id),
             &Selector::Nth(n) => self.nth(n),
         }
@@ -52,6 +59,7 @@ impl Buffers {
         match selector {
             Selector::First | Selector::Nth(0) => self.first_mut(),
             Selector::Active => self.active_mut(),
+            Selector::Previous => self.previous_id().and_then(|id| self.inner.get_mut(&id)),
             Selector::WithId(id) => self.inner.get_mut(id),
             &Selector::Nth(n) => self.nth_mut(n),
         }
@@ -62,11 +70,6 @@ impl Buffers {
         self.inner.get(first_id)
     }
 
-    pub fn second(&self) -> Option<&BufferBundle> {
-        let first_id = self.buffer_order.get(1)?;
-        self.inner.get(first_id)
-    }
-
     #[allow(unused)]
     pub fn nth(&self, n: usize) -> Option<&BufferBundle> {
         let id = self.buffer_order.get(n)?;
@@ -74,7 +77,37 @@ impl Buffers {
     }
 
     pub fn active(&self) -> Option<&BufferBundle> {
-        self.first()
+        let id = self.focus.last()?;
+        self.inner.get(id)
+    }
+
+    fn previous_id(&self) -> Option<Id> {
+        self.focus.iter().rev().nth(1).copied()
+    }
+
+    /// Makes the buffer with the given id active, returns `false` if there is no such buffer
+    pub fn set_active(&mut self, id: Id) -> bool {
+        if !self.inner.contains_key(&id) {
+            return false;
+        }
+
+        self.focus.retain(|&focused| focused != id);
+        self.focus.push(id);
+        true
+    }
+
+    /// Returns the id of the most recently focused buffer that is not a prompt, the one that
+    /// fills the screen
+    pub fn displayed(&self) -> Option<Id> {
+        self.focus
+            .iter()
+            .rev()
+            .find(|id| {
+                self.inner
+                    .get(id)
+                    .is_some_and(|bundle| !bundle.meta().flags.is_terminal())
+            })
+            .copied()
     }
 
     pub fn first_mut(&mut self) -> Option<&mut BufferBundle> {
@@ -83,7 +116,8 @@ impl Buffers {
     }
 
     pub fn active_mut(&mut self) -> Option<&mut BufferBundle> {
-        self.first_mut()
+        let id = self.focus.last()?;
+        self.inner.get_mut(id)
     }
 
     pub fn nth_mut(&mut self, n: usize) -> Option<&mut BufferBundle> {
@@ -96,6 +130,7 @@ impl Buffers {
         let pos = bundle.position();
 
         self.buffer_order.remove(pos);
+        self.focus.retain(|&focused| focused != id);
         for i in pos..self.buffer_order.len() {
             self.set_buffer_order(i);
         }
@@ -107,21 +142,31 @@ impl Buffers {
     pub fn remove_first(&mut self) -> Option<BufferBundle> {
         (!self.buffer_order.is_empty()).then_some(())?;
         let first_id = self.buffer_order.remove(0);
+        self.focus.retain(|&focused| focused != first_id);
+        for i in 0..self.buffer_order.len() {
+            self.set_buffer_order(i);
+        }
         self.inner.remove(&first_id)
     }
 
-    pub fn attach(&mut self, buffer: buffer::Buffer, meta: BufferMeta) {
+    /// Attaches the buffer at the bottom, it becomes active only if there are no other buffers
+    pub fn attach(&mut self, buffer: buffer::Buffer, meta: BufferMeta) -> Id {
         let id = self.brand.child_id();
         self.inner.insert(
             id,
             BufferBundle::new(id, self.buffer_order.len(), buffer, meta),
         );
         self.buffer_order.push(id);
+        self.focus.insert(0, id);
+        id
     }
 
-    pub fn attach_first(&mut self, buffer: buffer::Buffer, meta: BufferMeta) {
-        self.attach(buffer, meta);
+    /// Attaches the buffer on top of the others and makes it active
+    pub fn attach_first(&mut self, buffer: buffer::Buffer, meta: BufferMeta) -> Id {
+        let id = self.attach(buffer, meta);
         self.swap(0, self.inner.len() - 1);
+        self.set_active(id);
+        id
     }
 
     fn swap(&mut self, a_ord: usize, b_ord: usize) {
@@ -322,6 +367,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn active_is_independent_of_order() {
+        let mut b = make_buffers(3);
+        let ids = b.buffer_order.clone();
+        assert_eq!(b.active().unwrap().id(), ids[0]);
+
+        assert!(b.set_active(ids[2]));
+        assert_eq!(b.active().unwrap().id(), ids[2]);
+        assert_eq!(b.get(&Selector::Previous).unwrap().id(), ids[0]);
+        assert_eq!(b.buffer_order, ids);
+
+        let prompt = b.attach_first(buffer::Buffer::new(":"), BufferMeta::new(Mode::Terminal));
+        assert_eq!(b.active().unwrap().id(), prompt);
+        assert_eq!(b.get(&Selector::Previous).unwrap().id(), ids[2]);
+
+        b.remove(prompt);
+        assert_eq!(b.active().unwrap().id(), ids[2]);
+        b.remove(ids[2]);
+        assert_eq!(b.active().unwrap().id(), ids[0]);
+        assert!(!b.set_active(ids[2]));
+    }
+
+    #[test]
+    fn displayed_skips_prompts() {
+        let mut b = make_buffers(2);
+        let file = b.buffer_order[1];
+        b.set_active(file);
+
+        let meta =
+            BufferMeta::new(Mode::Terminal).with_flags(meta::Flags::empty().set_is_terminal());
+        let prompt = b.attach_first(buffer::Buffer::new(":"), meta);
+        assert_eq!(b.active().unwrap().id(), prompt);
+        assert_eq!(b.displayed(), Some(file));
+    }
+
     #[test]
     fn order_matches_inner_len() {
         let mut b = make_buffers(5);
@@ -0,0 +1,436 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    substitute::{self, Address},
+    Mode,
+};
+
+/// What a command typed in the command line does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Opens a file, or switches to it if it is already open. Without a file the current one is
+    /// read again
+    Edit,
+    /// Opens a file read-only, or switches to it and makes it read-only if it is already open
+    View,
+    /// Writes the buffer to its file, or to the given path. A read-only buffer is only written to
+    /// its file with `!`
+    Write,
+    /// Writes all the modified buffers
+    WriteAll,
+    Quit,
+    WriteQuit,
+    BufferNext,
+    BufferPrevious,
+    BufferDelete,
+    /// Lists the open buffers
+    Buffers,
+    /// Opens the messages shown so far in a scratch buffer
+    Messages,
+    /// Stops highlighting the matches of the last search
+    NoHighlight,
+    /// Changes the options
+    Set,
+    /// Switches to the color scheme, or shows the name of the current one
+    Colorscheme,
+    /// Checks the file with the given command, or the linter of its language
+    Lint,
+    /// Shows the files of the directory, the working directory if none is given, in a file
+    /// explorer
+    Explore,
+    /// Shows the commit each line was last changed in after it, or stops showing them
+    GitBlame,
+    /// Shows how the lines under the cursor differ from the staged ones
+    GitPreviewHunk,
+    /// Replaces the changed lines under the cursor with the staged ones
+    GitRevertHunk,
+    /// Maps keys typed in the mode to other keys. Unless `remap` is set, the keys they are mapped
+    /// to are not looked up in the user-defined mappings
+    Map {
+        mode: Mode,
+        remap: bool,
+    },
+    /// Moves the cursor to the line, typed as a bare range such as `:42` or `:$`
+    Line(Address),
+}
+
+impl Kind {
+    /// Full names of the commands, the shortest abbreviation each of them can be typed as, and the
+    /// least and the most arguments they take
+    const TABLE: &[(&str, &str, (usize, usize), Self)] = &[
+        ("edit", "e", (0, 1), Self::Edit),
+        ("view", "vie", (1, 1), Self::View),
+        ("write", "w", (0, 1), Self::Write),
+        ("wall", "wa", (0, 0), Self::WriteAll),
+        ("quit", "q", (0, 0), Self::Quit),
+        ("wq", "wq", (0, 0), Self::WriteQuit),
+        ("bnext", "bn", (0, 0), Self::BufferNext),
+        ("bprevious", "bp", (0, 0), Self::BufferPrevious),
+        ("bdelete", "bd", (0, 0), Self::BufferDelete),
+        ("buffers", "buffers", (0, 0), Self::Buffers),
+        ("ls", "ls", (0, 0), Self::Buffers),
+        ("messages", "mes", (0, 0), Self::Messages),
+        ("nohlsearch", "noh", (0, 0), Self::NoHighlight),
+        ("set", "se", (1, usize::MAX), Self::Set),
+        ("colorscheme", "colo", (0, 1), Self::Colorscheme),
+        ("lint", "lint", (0, usize::MAX), Self::Lint),
+        ("Explore", "Ex", (0, 1), Self::Explore),
+        ("GitBlame", "GitB", (0, 0), Self::GitBlame),
+        ("GitPreviewHunk", "GitP", (0, 0), Self::GitPreviewHunk),
+        ("GitRevertHunk", "GitR", (0, 0), Self::GitRevertHunk),
+        ("map", "map", (2, 2), Self::map(Mode::Normal, true)),
+        ("nmap", "nm", (2, 2), Self::map(Mode::Normal, true)),
+        ("imap", "im", (2, 2), Self::map(Mode::Insert, true)),
+        ("noremap", "no", (2, 2), Self::map(Mode::Normal, false)),
+        ("nnoremap", "nn", (2, 2), Self::map(Mode::Normal, false)),
+        ("inoremap", "ino", (2, 2), Self::map(Mode::Insert, false)),
+    ];
+
+    const fn map(mode: Mode, remap: bool) -> Self {
+        Self::Map { mode, remap }
+    }
+
+    /// Looks up a command by its name, which may be abbreviated
+    fn lookup(name: &str) -> Option<(Self, (usize, usize))> {
+        Self::TABLE
+            .iter()
+            .find(|&&(full, short, ..)| name.starts_with(short) && full.starts_with(name))
+            .map(|&(_, _, arity, kind)| (kind, arity))
+    }
+
+    /// Returns whether the arguments of the command are paths
+    const fn takes_path(self) -> bool {
+        matches!(self, Self::Edit | Self::View | Self::Write | Self::Explore)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Unknown(String),
+    MissingArgument,
+    TrailingCharacters(String),
+    UnterminatedQuote,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(name) => write!(f, "not an editor command: {name}"),
+            Self::MissingArgument => write!(f, "argument required"),
+            Self::TrailingCharacters(rest) => write!(f, "trailing characters: {rest}"),
+            Self::UnterminatedQuote => write!(f, "unterminated quote"),
+        }
+    }
+}
+
+/// A parsed `:name[!] [args]` command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Command {
+    pub kind: Kind,
+    /// Whether the name is followed by `!`
+    pub bang: bool,
+    pub args: Vec<String>,
+}
+
+impl Command {
+    /// Parses a command without the leading `:`
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let line = line.trim_start();
+        if let Some((_, end, rest)) = substitute::parse_range(line) {
+            if rest.len() < line.len() && rest.trim().is_empty() {
+                return Ok(Self {
+                    kind: Kind::Line(end),
+                    bang: false,
+                    args: Vec::new(),
+                });
+            }
+        }
+        let (name, rest) = split_name(line);
+        let (bang, rest) = rest
+            .strip_prefix('!')
+            .map_or((false, rest), |rest| (true, rest));
+
+        let Some((kind, (least, most))) = Kind::lookup(name) else {
+            return Err(Error::Unknown(String::from(line.trim_end())));
+        };
+
+        let args = match kind {
+            Kind::Map { .. } => split_mapping(rest),
+            _ => split_args(rest)?,
+        };
+        if args.len() < least {
+            return Err(Error::MissingArgument);
+        }
+        if args.len() > most {
+            return Err(Error::TrailingCharacters(args[most..].join(" ")));
+        }
+
+        Ok(Self { kind, bang, args })
+    }
+}
+
+/// Splits off the alphabetic name in front of a command
+fn split_name(line: &str) -> (&str, &str) {
+    let end = line
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(line.len());
+    line.split_at(end)
+}
+
+/// Splits the arguments of a mapping command into the keys that are mapped and the rest of the
+/// line, which is kept as it is
+fn split_mapping(s: &str) -> Vec<String> {
+    let s = s.trim_start();
+    let (lhs, rhs) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+    [lhs, rhs.trim_start()]
+        .into_iter()
+        .filter(|arg| !arg.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Splits the arguments of a command at whitespace. Whitespace inside of single or double quotes
+/// or after a backslash is part of the argument
+pub fn split_args(s: &str) -> Result<Vec<String>, Error> {
+    let mut args = Vec::new();
+    let mut arg = None::<String>;
+    let mut quote = None;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            (c, Some(q)) if c == q => quote = None,
+            // Backslashes are kept as they are between single quotes
+            ('\\', q) if q != Some('\'') => {
+                let escaped = chars.next().unwrap_or('\\');
+                arg.get_or_insert_default().push(escaped);
+            }
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                arg.get_or_insert_default();
+            }
+            (c, None) if c.is_whitespace() => args.extend(arg.take()),
+            (c, _) => arg.get_or_insert_default().push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(Error::UnterminatedQuote);
+    }
+    args.extend(arg);
+    Ok(args)
+}
+
+/// The ways the word under the cursor of the command line can be completed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// The byte offset in the line of the word that is completed
+    pub start: usize,
+    pub candidates: Vec<String>,
+}
+
+impl Completion {
+    /// Completes the last word of a command line without the leading `:`, the name of a command or
+    /// a path relative to `cwd`
+    pub fn of(line: &str, cwd: &Path) -> Self {
+        let start = line.len() - line.trim_start().len();
+        let (name, rest) = split_name(&line[start..]);
+
+        if rest.is_empty() {
+            let candidates = Kind::TABLE
+                .iter()
+                .map(|&(full, ..)| full)
+                .filter(|full| full.starts_with(name))
+                .map(String::from)
+                .collect();
+            return Self { start, candidates };
+        }
+
+        let rest = rest.strip_prefix('!').unwrap_or(rest);
+        let takes_path = Kind::lookup(name).is_some_and(|(kind, _)| kind.takes_path());
+        let word_start = line.len() - rest.len() + rest.rfind(' ').map_or(0, |i| i + 1);
+        if !takes_path || word_start == line.len() - rest.len() {
+            return Self {
+                start: line.len(),
+                candidates: Vec::new(),
+            };
+        }
+
+        Self {
+            start: word_start,
+            candidates: complete_path(&line[word_start..], cwd),
+        }
+    }
+
+    /// Returns the longest prefix all the candidates share
+    pub fn common_prefix(&self) -> Option<&str> {
+        let (first, rest) = self.candidates.split_first()?;
+        let len = rest.iter().fold(first.len(), |len, candidate| {
+            first[..len]
+                .char_indices()
+                .zip(candidate.chars())
+                .find(|&((_, a), b)| a != b)
+                .map_or(len.min(candidate.len()), |((i, _), _)| i)
+        });
+        Some(&first[..len])
+    }
+}
+
+/// Returns the paths that start with `partial`, directories ending with a `/`. Hidden files are
+/// only completed if the partial name starts with a `.`
+fn complete_path(partial: &str, cwd: &Path) -> Vec<String> {
+    let (dir, prefix) = partial
+        .rsplit_once('/')
+        .map_or(("", partial), |(dir, prefix)| (dir, prefix));
+    let dir_path = match dir {
+        "" if partial.starts_with('/') => PathBuf::from("/"),
+        "" => cwd.to_path_buf(),
+        dir => cwd.join(dir),
+    };
+    let Ok(entries) = std::fs::read_dir(dir_path) else {
+        return Vec::new();
+    };
+
+    let dir_prefix = if partial.contains('/') {
+        format!("{dir}/")
+    } else {
+        String::new()
+    };
+    let mut candidates: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{dir_prefix}{name}{slash}"))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let command = Command::parse("e  'my file.txt'").unwrap();
+        assert_eq!(command.kind, Kind::Edit);
+        assert_eq!(command.args, ["my file.txt"]);
+
+        let command = Command::parse("bd!").unwrap();
+        assert_eq!((command.kind, command.bang), (Kind::BufferDelete, true));
+        assert_eq!(Command::parse("bnex").unwrap().kind, Kind::BufferNext);
+        assert_eq!(Command::parse("ls").unwrap().kind, Kind::Buffers);
+        assert_eq!(Command::parse("mes").unwrap().kind, Kind::Messages);
+        assert_eq!(Command::parse("vie a.txt").unwrap().kind, Kind::View);
+        assert_eq!(Command::parse("wa").unwrap().kind, Kind::WriteAll);
+        assert_eq!(Command::parse("colo nord").unwrap().args, ["nord"]);
+        assert_eq!(Command::parse("wq!").unwrap().kind, Kind::WriteQuit);
+        assert_eq!(
+            Command::parse("se nowrap so=3").unwrap().args,
+            ["nowrap", "so=3"]
+        );
+
+        let command = Command::parse("w out.txt").unwrap();
+        assert_eq!(
+            (command.kind, command.args),
+            (Kind::Write, vec![String::from("out.txt")])
+        );
+        assert!(Command::parse("w").unwrap().args.is_empty());
+
+        let command = Command::parse(r#"nn <leader>s  :s/a\b/"c"<CR>"#).unwrap();
+        assert_eq!(command.kind, Kind::map(Mode::Normal, false));
+        assert_eq!(command.args, ["<leader>s", r#":s/a\b/"c"<CR>"#]);
+        assert_eq!(
+            Command::parse("ino jk").unwrap_err(),
+            Error::MissingArgument
+        );
+
+        assert_eq!(
+            Command::parse("42").unwrap().kind,
+            Kind::Line(Address::Line(42))
+        );
+        assert_eq!(
+            Command::parse(" $ ").unwrap().kind,
+            Kind::Line(Address::Last)
+        );
+        assert_eq!(
+            Command::parse("1,5").unwrap().kind,
+            Kind::Line(Address::Line(5))
+        );
+        assert_eq!(Command::parse("b"), Err(Error::Unknown(String::from("b"))));
+        assert_eq!(
+            Command::parse("quitx"),
+            Err(Error::Unknown(String::from("quitx")))
+        );
+        assert!(Command::parse("edit").unwrap().args.is_empty());
+        assert_eq!(
+            Command::parse("e a b"),
+            Err(Error::TrailingCharacters(String::from("b")))
+        );
+        assert_eq!(
+            Command::parse("q now please"),
+            Err(Error::TrailingCharacters(String::from("now please")))
+        );
+    }
+
+    #[test]
+    fn args() {
+        assert_eq!(
+            split_args(r#" a "b c"  d\ e 'f\g' "" "#).unwrap(),
+            ["a", "b c", "d e", r"f\g", ""]
+        );
+        assert_eq!(split_args(r#"a"b"c"#).unwrap(), ["abc"]);
+        assert_eq!(split_args("'a"), Err(Error::UnterminatedQuote));
+        assert!(split_args("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn complete_commands() {
+        let cwd = Path::new("/");
+        let completion = Completion::of("b", cwd);
+        assert_eq!(completion.start, 0);
+        assert_eq!(
+            completion.candidates,
+            ["bnext", "bprevious", "bdelete", "buffers"]
+        );
+        assert_eq!(completion.common_prefix(), Some("b"));
+
+        let completion = Completion::of("bu", cwd);
+        assert_eq!(completion.common_prefix(), Some("buffers"));
+        assert!(Completion::of("q ", cwd).candidates.is_empty());
+        assert_eq!(Completion::of("x", cwd).common_prefix(), None);
+    }
+
+    #[test]
+    fn complete_paths() {
+        let dir = std::env::temp_dir().join(format!("edi-complete-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        for file in ["main.rs", "mod.rs", ".hidden"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        std::fs::write(dir.join("src").join("lib.rs"), "").unwrap();
+
+        let completion = Completion::of("e m", &dir);
+        assert_eq!(completion.start, 2);
+        assert_eq!(completion.candidates, ["main.rs", "mod.rs"]);
+        assert_eq!(completion.common_prefix(), Some("m"));
+
+        assert_eq!(
+            Completion::of("e ", &dir).candidates,
+            ["main.rs", "mod.rs", "src/"]
+        );
+        assert_eq!(Completion::of("e .", &dir).candidates, [".hidden"]);
+        assert_eq!(Completion::of("e src/l", &dir).candidates, ["src/lib.rs"]);
+        assert!(Completion::of("ls m", &dir).candidates.is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+
+/// The most commands the history keeps
+const MAX_ENTRIES: usize = 200;
+
+/// The commands typed in the command line, kept in a file between sessions
+#[derive(Debug, Default)]
+pub struct CommandHistory {
+    entries: Vec<String>,
+    /// The file the history is loaded from and saved to, none if it is not persisted
+    path: Option<PathBuf>,
+    /// The entry shown in the command line while browsing, `entries.len()` being the typed line
+    cursor: usize,
+    /// The line typed before browsing started
+    draft: String,
+}
+
+impl CommandHistory {
+    /// Loads the history from `path`, the history is empty if the file does not exist yet
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries: Vec<String> = std::fs::read_to_string(&path)
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect();
+
+        let skip = entries.len().saturating_sub(MAX_ENTRIES);
+        let entries = entries[skip..].to_vec();
+        Self {
+            cursor: entries.len(),
+            entries,
+            path: Some(path),
+            draft: String::new(),
+        }
+    }
+
+    /// Returns where the history is kept by default, `$XDG_STATE_HOME/edi/history` or
+    /// `~/.local/state/edi/history`
+    pub fn default_path() -> Option<PathBuf> {
+        let state_home = std::env::var_os("XDG_STATE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/state"))
+            })?;
+        Some(state_home.join("edi").join("history"))
+    }
+
+    /// Adds a command to the end of the history and saves it, the command is moved there if it is
+    /// already in the history
+    pub fn push(&mut self, command: &str) {
+        self.reset();
+        if command.trim().is_empty() {
+            return;
+        }
+
+        self.entries.retain(|entry| entry != command);
+        self.entries.push(String::from(command));
+        let skip = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..skip);
+        self.cursor = self.entries.len();
+
+        if let Err(e) = self.save() {
+            edi_lib::debug!("unable to save the command history: {e}");
+        }
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let mut contents = self.entries.join("\n");
+        contents.push('\n');
+        std::fs::write(path, contents)
+    }
+
+    /// Returns the command before the one shown, `typed` being the line in the command line. The
+    /// line is kept to be returned after browsing back to it
+    pub fn older(&mut self, typed: &str) -> Option<&str> {
+        if self.cursor == 0 {
+            return None;
+        }
+        if self.cursor == self.entries.len() {
+            self.draft = String::from(typed);
+        }
+
+        self.cursor -= 1;
+        Some(&self.entries[self.cursor])
+    }
+
+    /// Returns the command after the one shown, or the line typed before browsing after the last
+    /// one
+    pub fn newer(&mut self) -> Option<&str> {
+        if self.cursor >= self.entries.len() {
+            return None;
+        }
+
+        self.cursor += 1;
+        Some(self.entries.get(self.cursor).unwrap_or(&self.draft))
+    }
+
+    /// Stops browsing, the next command returned is the last one
+    pub fn reset(&mut self) {
+        self.cursor = self.entries.len();
+        self.draft.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn browse() {
+        let mut history = CommandHistory::default();
+        assert_eq!(history.older("x"), None);
+
+        for command in ["a", "b", "c", "a", " "] {
+            history.push(command);
+        }
+        assert_eq!(history.older("typed"), Some("a"));
+        assert_eq!(history.older("ignored"), Some("c"));
+        assert_eq!(history.older(""), Some("b"));
+        assert_eq!(history.older(""), None);
+        assert_eq!(history.newer(), Some("c"));
+        assert_eq!(history.newer(), Some("a"));
+        assert_eq!(history.newer(), Some("typed"));
+        assert_eq!(history.newer(), None);
+
+        history.older("");
+        history.reset();
+        assert_eq!(history.older(""), Some("a"));
+    }
+
+    #[test]
+    fn persists() {
+        let path = std::env::temp_dir()
+            .join(format!("edi-history-{}", std::process::id()))
+            .join("history");
+
+        let mut history = CommandHistory::load(&path);
+        history.push("e foo");
+        history.push("wq");
+
+        let mut loaded = CommandHistory::load(&path);
+        assert_eq!(loaded.older(""), Some("wq"));
+        assert_eq!(loaded.older(""), Some("e foo"));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
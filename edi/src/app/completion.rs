@@ -0,0 +1,175 @@
+//! Completion of the word before the cursor, picked from a popup menu of candidates
+
+use std::collections::HashSet;
+
+use edi_rope::Rope;
+
+/// A word the one being typed may be completed to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub text: String,
+    /// What the candidate is, shown next to it, empty if it is not known
+    pub kind: String,
+}
+
+impl Candidate {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            kind: String::new(),
+        }
+    }
+}
+
+/// Finds candidates for the word being typed. A provider that gets them from elsewhere, such as
+/// a language server, answers with the ones it got last
+pub trait Provider: std::fmt::Debug + Send {
+    /// Returns the candidates for the word that starts at `start` in the text and is typed up to
+    /// the cursor as `prefix`, the best ones first
+    fn candidates(&self, text: &Rope, start: usize, prefix: &str) -> Vec<Candidate>;
+}
+
+/// Completes words to the other words of the buffer, the ones closest to the cursor first
+#[derive(Debug, Default)]
+pub struct BufferWords;
+
+impl Provider for BufferWords {
+    fn candidates(&self, text: &Rope, start: usize, prefix: &str) -> Vec<Candidate> {
+        let mut words: Vec<(usize, String)> = Vec::new();
+        let mut word = String::new();
+        for (offset, c) in text.chars().chain(std::iter::once(' ')).enumerate() {
+            if is_word_char(c) {
+                word.push(c);
+                continue;
+            }
+            if word.is_empty() {
+                continue;
+            }
+            let word_start = offset - word.chars().count();
+            let word = std::mem::take(&mut word);
+            if word_start != start && word.starts_with(prefix) && word != prefix {
+                words.push((word_start.abs_diff(start), word));
+            }
+        }
+
+        words.sort_by_key(|&(distance, _)| distance);
+        let mut seen = HashSet::new();
+        words
+            .into_iter()
+            .filter(|(_, word)| seen.insert(word.clone()))
+            .map(|(_, word)| Candidate::new(word))
+            .collect()
+    }
+}
+
+/// The popup menu of the candidates for the word being typed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// Where the word being completed starts
+    pub start: usize,
+    /// Where the word the candidate replaces ends if it is not the cursor, as a misspelled word
+    /// does
+    pub end: Option<usize>,
+    pub candidates: Vec<Candidate>,
+    pub selected: usize,
+}
+
+impl Completion {
+    /// Opens the menu with the first candidate selected, or the last one if `reverse` is set.
+    /// `None` if there are no candidates
+    pub fn new(start: usize, candidates: Vec<Candidate>, reverse: bool) -> Option<Self> {
+        if candidates.is_empty() {
+            return None;
+        }
+        let selected = if reverse { candidates.len() - 1 } else { 0 };
+        Some(Self {
+            start,
+            end: None,
+            candidates,
+            selected,
+        })
+    }
+
+    /// Selects the next candidate, or the previous one if `reverse` is set, wrapping around
+    pub const fn select_next(&mut self, reverse: bool) {
+        let len = self.candidates.len();
+        if len == 0 {
+            return;
+        }
+        self.selected = if reverse {
+            (self.selected + len - 1) % len
+        } else {
+            (self.selected + 1) % len
+        };
+    }
+
+    pub fn selected(&self) -> Option<&Candidate> {
+        self.candidates.get(self.selected)
+    }
+}
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Returns where the word that ends at `cursor` starts, `cursor` if there is no such word
+pub fn word_start(text: &Rope, cursor: usize) -> usize {
+    let line_start = text.line_to_char(text.char_to_line(cursor));
+    let before: Vec<char> = text
+        .chars_at(line_start)
+        .take(cursor.saturating_sub(line_start))
+        .collect();
+    let word_len = before
+        .iter()
+        .rev()
+        .take_while(|&&c| is_word_char(c))
+        .count();
+    cursor - word_len
+}
+
+/// Returns the text between `start` and `cursor` if all of it is a part of a word
+pub fn typed_prefix(text: &Rope, start: usize, cursor: usize) -> Option<String> {
+    let len = cursor.checked_sub(start)?;
+    let prefix: String = text.chars_at(start).take(len).collect();
+    (prefix.chars().count() == len && prefix.chars().all(is_word_char)).then_some(prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_words() {
+        let text = Rope::from("foobar fox\nfo food(foo_bar) foobar\n");
+        let start = 11;
+        assert_eq!(word_start(&text, 13), start);
+        assert_eq!(word_start(&text, 11), 11);
+        assert_eq!(typed_prefix(&text, start, 13).as_deref(), Some("fo"));
+        assert_eq!(typed_prefix(&text, start, 15), None);
+        assert_eq!(typed_prefix(&text, 13, 11), None);
+
+        let words: Vec<String> = BufferWords
+            .candidates(&text, start, "fo")
+            .into_iter()
+            .map(|candidate| candidate.text)
+            .collect();
+        assert_eq!(words, ["food", "fox", "foo_bar", "foobar"]);
+
+        let words = BufferWords.candidates(&text, start, "foo_");
+        assert_eq!(words, [Candidate::new("foo_bar")]);
+    }
+
+    #[test]
+    fn selection() {
+        let candidates = vec![Candidate::new("a"), Candidate::new("b")];
+        let mut completion = Completion::new(0, candidates.clone(), true).unwrap();
+        assert_eq!(completion.selected, 1);
+        completion.select_next(false);
+        assert_eq!(completion.selected(), Some(&Candidate::new("a")));
+        completion.select_next(true);
+        assert_eq!(completion.selected, 1);
+
+        assert_eq!(Completion::new(0, Vec::new(), false), None);
+        assert_eq!(Completion::new(0, Vec::new(), true), None);
+    }
+}
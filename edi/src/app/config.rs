@@ -0,0 +1,327 @@
+//! Loading the options from the configuration file
+
+use std::path::{Path, PathBuf};
+
+use edi_frame::cell::{Color, Style};
+use edi_lib::{
+    buffer::theme::Theme,
+    language::{self, Registry},
+    toml::{self, Table, Value},
+};
+
+use crate::error::{AppError, Result};
+
+use super::{action::InputMapper, context::Options, keymap, Mode};
+
+/// What the configuration file sets
+#[derive(Debug, Default)]
+pub struct Config {
+    pub options: Options,
+    /// The default mappings along with the ones defined in the file
+    pub mapper: InputMapper,
+    /// The built-in languages along with the ones defined in the `languages` directory next to
+    /// the file
+    pub languages: Registry,
+}
+
+/// Returns where the configuration is read from by default, `$XDG_CONFIG_HOME/edi/config.toml` or
+/// `~/.config/edi/config.toml`
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_home.join("edi").join("config.toml"))
+}
+
+/// Reads the configuration file at `path`, or the default one if no path is given, and the
+/// language definitions in the `languages` directory next to it. Everything keeps its default if
+/// the default file does not exist
+pub fn load(path: Option<&Path>) -> Result<Config> {
+    use std::format as f;
+
+    let mut config = Config::default();
+    let Some(path) = path.map(Path::to_path_buf).or_else(default_path) else {
+        return Ok(config);
+    };
+    config
+        .languages
+        .load_dir(&path.with_file_name("languages"))
+        .map_err(|e| match e {
+            language::Error::Io(path, cause) => AppError::io(f!(
+                "unable to read the language definition `{}`",
+                path.display()
+            ))
+            .with_cause(cause),
+            e @ language::Error::Invalid(..) => AppError::invalid_argument(e.to_string()),
+        })?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && !path_given(path.as_path()) => {
+            return Ok(config);
+        }
+        Err(e) => {
+            return Err(AppError::io(f!(
+                "unable to read the configuration file `{}`",
+                path.display()
+            ))
+            .with_cause(e));
+        }
+    };
+
+    toml::parse(&contents)
+        .map_err(|e| e.to_string())
+        .and_then(|table| config.apply(&table))
+        .map_err(|e| {
+            AppError::invalid_argument(f!("invalid configuration `{}`: {e}", path.display()))
+        })?;
+    Ok(config)
+}
+
+/// Returns whether `path` is not the default configuration file, which is allowed to be missing
+fn path_given(path: &Path) -> bool {
+    default_path().is_none_or(|default| default != path)
+}
+
+impl Config {
+    /// Applies the contents of a configuration file. The options are named as in `:set`, the
+    /// color scheme is chosen with `colorscheme` and changed in the `theme` table, and the
+    /// mappings are in the `keymaps` table
+    fn apply(&mut self, config: &Table) -> std::result::Result<(), String> {
+        // The theme has to be chosen before the `theme` table changes it
+        match config.get("colorscheme") {
+            Some(Value::String(name)) => {
+                self.options.theme =
+                    Theme::builtin(name).ok_or_else(|| format!("unknown color scheme `{name}`"))?;
+            }
+            Some(value) => return Err(unknown("colorscheme", value)),
+            None => {}
+        }
+
+        // The leader has to be known before the mappings that use it are read
+        match config.get("leader") {
+            Some(Value::String(leader)) => {
+                let [leader] = &keymap::parse_keys(leader, &self.mapper.leader)[..] else {
+                    return Err(String::from("`leader` is not a single key"));
+                };
+                self.mapper.leader = leader.clone();
+            }
+            Some(value) => return Err(unknown("leader", value)),
+            None => {}
+        }
+
+        for (name, value) in config {
+            match value {
+                _ if name == "leader" || name == "colorscheme" => {}
+                Value::Table(theme) if name == "theme" => {
+                    apply_theme(&mut self.options.theme, theme)?;
+                }
+                Value::Table(keymaps) if name == "keymaps" => self.apply_keymaps(keymaps)?,
+                &Value::Boolean(on) => {
+                    *self
+                        .options
+                        .flag(name)
+                        .ok_or_else(|| unknown(name, value))? = on;
+                }
+                &Value::Integer(n) => {
+                    let number = self.options.number(name);
+                    let number = number.ok_or_else(|| unknown(name, value))?;
+                    *number = usize::try_from(n).map_err(|_| format!("`{name}` is negative"))?;
+                }
+                Value::String(text) => {
+                    let option = self.options.text(name);
+                    let option = option.ok_or_else(|| unknown(name, value))?;
+                    if !Options::is_valid_text(name, text) {
+                        return Err(format!("invalid value for `{name}`"));
+                    }
+                    text.clone_into(option);
+                }
+                _ => return Err(unknown(name, value)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the mappings of the `keymaps` table, which has a table of keys and the keys they map
+    /// to for each mode. The keys they map to are not looked up in the user-defined mappings
+    fn apply_keymaps(&mut self, keymaps: &Table) -> std::result::Result<(), String> {
+        for (mode_name, mappings) in keymaps {
+            let mode = match mode_name.as_str() {
+                "normal" => Mode::Normal,
+                "insert" => Mode::Insert,
+                "terminal" => Mode::Terminal,
+                "explorer" => Mode::Explorer,
+                _ => return Err(format!("unknown mode `keymaps.{mode_name}`")),
+            };
+            let Value::Table(mappings) = mappings else {
+                return Err(format!("`keymaps.{mode_name}` is not a table"));
+            };
+
+            for (lhs, rhs) in mappings {
+                let Value::String(rhs) = rhs else {
+                    return Err(format!("`keymaps.{mode_name}.{lhs}` is not a string"));
+                };
+                self.mapper.add_user_mapping(mode, lhs, rhs, false);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unknown(name: &str, value: &Value) -> String {
+    format!("unknown {} option `{name}`", value.type_name())
+}
+
+/// Sets the faces of the highlight groups named in a `theme` table. A color given as a name,
+/// `#rrggbb` or a palette index sets the foreground, a table sets any of `fg`, `bg` and `style`
+fn apply_theme(theme: &mut Theme, table: &Table) -> std::result::Result<(), String> {
+    for (group, value) in table {
+        let face = theme
+            .face_mut(group)
+            .ok_or_else(|| format!("unknown highlight group `theme.{group}`"))?;
+        let Value::Table(attributes) = value else {
+            face.fg = color(value).ok_or_else(|| format!("invalid color for `theme.{group}`"))?;
+            continue;
+        };
+
+        for (name, value) in attributes {
+            let invalid = |what| format!("invalid {what} for `theme.{group}.{name}`");
+            match name.as_str() {
+                "fg" => face.fg = color(value).ok_or_else(|| invalid("color"))?,
+                "bg" => face.bg = color(value).ok_or_else(|| invalid("color"))?,
+                "style" => face.style = style(value).ok_or_else(|| invalid("style"))?,
+                _ => return Err(format!("unknown attribute `theme.{group}.{name}`")),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn color(value: &Value) -> Option<Color> {
+    match value {
+        Value::String(s) => Color::parse(s),
+        &Value::Integer(n) => u8::try_from(n).ok().map(Color::Indexed),
+        _ => None,
+    }
+}
+
+/// Parses an array of attribute names such as `["bold", "italic"]`
+fn style(value: &Value) -> Option<Style> {
+    let Value::Array(names) = value else {
+        return None;
+    };
+    names.iter().try_fold(Style::NONE, |style, name| {
+        let attribute = match name {
+            Value::String(name) => match name.as_str() {
+                "bold" => Style::BOLD,
+                "dim" => Style::DIM,
+                "italic" => Style::ITALIC,
+                "underline" => Style::UNDERLINE,
+                "reverse" => Style::REVERSE,
+                "undercurl" => Style::UNDERCURL,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(style | attribute)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use edi_lib::buffer::theme::Face;
+    use edi_term::input::Input;
+
+    use super::*;
+    use crate::app::{
+        action::{Action, Sequence},
+        meta::Flags,
+    };
+
+    fn apply(config: &str) -> std::result::Result<Config, String> {
+        let mut applied = Config::default();
+        applied.apply(&toml::parse(config).unwrap())?;
+        Ok(applied)
+    }
+
+    #[test]
+    fn options() {
+        let Config { options, .. } = apply(
+            "wrap = false\nnu = false\nscrolloff = 2\ntabstop = 8\nshowbreak = \"> \"\n\
+             [theme]\nkeyword = \"blue\"\ntext = 250\n\
+             [theme.match]\nbg = \"#102030\"\nstyle = [\"bold\", \"underline\"]\n",
+        )
+        .unwrap();
+        assert!(!options.word_wrap);
+        assert!(!options.line_numbers);
+        assert_eq!((options.scrolloff, options.tab_width), (2, 8));
+        assert_eq!(options.showbreak, "> ");
+        assert_eq!(options.theme.keyword.fg, Color::Blue);
+        assert_eq!(
+            options.theme.search_match,
+            Face::fg(Color::Black)
+                .with_bg(Color::Rgb(0x10, 0x20, 0x30))
+                .with_style(Style::BOLD | Style::UNDERLINE)
+        );
+        assert_eq!(options.theme.text.fg, Color::Indexed(250));
+
+        let Config { options, .. } =
+            apply("colorscheme = \"nord\"\n[theme]\ncomment = \"red\"\n").unwrap();
+        assert_eq!(options.theme.name, "nord");
+        assert_eq!(options.theme.comment.fg, Color::Red);
+        assert_eq!(options.theme.string, Theme::builtin("nord").unwrap().string);
+
+        let error = |config| apply(config).unwrap_err();
+        assert_eq!(error("wrap = 1"), "unknown integer option `wrap`");
+        assert_eq!(error("color = true"), "unknown boolean option `color`");
+        assert_eq!(error("wrap = \"yes\""), "unknown string option `wrap`");
+        assert_eq!(
+            error("listchars = \"tab:>\""),
+            "invalid value for `listchars`"
+        );
+        assert_eq!(error("scrolloff = -1"), "`scrolloff` is negative");
+        assert_eq!(
+            error("[theme]\nkeyword = \"purple\""),
+            "invalid color for `theme.keyword`"
+        );
+        assert_eq!(
+            error("[theme]\nborder = \"red\""),
+            "unknown highlight group `theme.border`"
+        );
+        assert_eq!(
+            error("[theme.string]\nstyle = [\"blink\"]"),
+            "invalid style for `theme.string.style`"
+        );
+        assert_eq!(
+            error("colorscheme = \"solarized\""),
+            "unknown color scheme `solarized`"
+        );
+    }
+
+    #[test]
+    fn keymaps() {
+        let Config { mapper, .. } = apply(
+            "leader = \"<Space>\"\n\
+             [keymaps.normal]\n\"<leader>w\" = \":w<CR>\"\n\
+             [keymaps.insert]\njk = \"<Esc>\"\n",
+        )
+        .unwrap();
+        assert_eq!(mapper.leader, Input::Keypress(' '));
+        let inputs = [Input::Keypress(' '), Input::Keypress('w')];
+        assert!(matches!(
+            mapper.map_sequence(&inputs, Mode::Normal, Flags::empty(), true),
+            Sequence::Complete(actions) if matches!(actions[..], [Action::Keys { remap: false, .. }])
+        ));
+
+        let error = |config| apply(config).unwrap_err();
+        assert_eq!(error("leader = \"ab\""), "`leader` is not a single key");
+        assert_eq!(
+            error("[keymaps.visual]\nx = \"y\""),
+            "unknown mode `keymaps.visual`"
+        );
+        assert_eq!(
+            error("[keymaps.normal]\nx = 1"),
+            "`keymaps.normal.x` is not a string"
+        );
+    }
+}
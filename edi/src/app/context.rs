@@ -1,7 +1,12 @@
+use edi_lib::{
+    buffer::{list::ListChars, theme::Theme, Buffer},
+    string::indent,
+};
+
 /// Global app context that should be passed to almost every function
 #[derive(Debug, Default)]
 pub struct Context {
-    pub settings: Settings,
+    pub options: Options,
 }
 
 impl Context {
@@ -12,16 +17,286 @@ impl Context {
 
 /// Configurable editor behaviour
 #[derive(Debug)]
-pub struct Settings {
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "the options are toggled independently"
+)]
+pub struct Options {
     pub line_numbers: bool,
+    /// Whether the lines are numbered by their distance to the cursor line
+    pub relative_line_numbers: bool,
     pub word_wrap: bool,
+    /// Text drawn at the start of the continuation rows of wrapped lines
+    pub showbreak: String,
+    /// Whether tabs, trailing spaces and non-breaking spaces are drawn as markers
+    pub list: bool,
+    /// The markers whitespace is drawn as with `list` set, parsed by `ListChars`
+    pub listchars: String,
+    pub scrollbar: bool,
+    /// The least number of lines kept visible above and below the cursor
+    pub scrolloff: usize,
+    /// Whether the previous contents of a file are kept in a backup file when it is written
+    pub backup: bool,
+    /// The number of columns a tab takes on the screen
+    pub tab_width: usize,
+    /// Whether typing a tab inserts spaces instead
+    pub expand_tab: bool,
+    /// The number of columns a level of indentation takes, the tab width if it is 0
+    pub shift_width: usize,
+    /// Whether new lines get the indentation of the line they are started from
+    pub auto_indent: bool,
+    /// Whether searches ignore the case of letters
+    pub ignore_case: bool,
+    /// Whether the message of a diagnostic is shown after the end of its line
+    pub virtual_text: bool,
+    /// Whether misspelled words are highlighted
+    pub spell: bool,
+    /// The name of the dictionary the spelling is checked with, such as `en_US`
+    pub spell_lang: String,
+    pub theme: Theme,
+    /// Whether true colors are drawn as they are, instead of as the closest colors of the
+    /// 256-color palette
+    pub truecolor: bool,
 }
 
-impl Default for Settings {
+impl Default for Options {
     fn default() -> Self {
         Self {
             line_numbers: true,
+            relative_line_numbers: false,
             word_wrap: true,
+            showbreak: String::new(),
+            list: false,
+            listchars: String::from(ListChars::DEFAULT),
+            scrollbar: false,
+            scrolloff: 5,
+            backup: false,
+            tab_width: Buffer::DEFAULT_TAB_WIDTH,
+            expand_tab: false,
+            shift_width: 4,
+            auto_indent: true,
+            ignore_case: false,
+            virtual_text: false,
+            spell: false,
+            spell_lang: String::from("en_US"),
+            theme: Theme::default(),
+            truecolor: std::env::var("COLORTERM")
+                .is_ok_and(|colorterm| matches!(colorterm.as_str(), "truecolor" | "24bit")),
+        }
+    }
+}
+
+impl Options {
+    /// Applies an argument of `:set`. `name` turns an option on, `noname` turns it off, `name!`
+    /// toggles it and `name=value` sets a number or a text. `name?` and the name of a number or a
+    /// text return the value of the option to show
+    pub fn set(&mut self, arg: &str) -> Result<Option<String>, String> {
+        if let Some(name) = arg.strip_suffix('?') {
+            return self.show(name).map(Some);
+        }
+        if self.number(arg).is_some() || self.text(arg).is_some() {
+            return self.show(arg).map(Some);
+        }
+
+        if let Some((name, value)) = arg.split_once('=') {
+            if let Some(text) = self.text(name) {
+                if !Self::is_valid_text(name, value) {
+                    return Err(format!("invalid argument: {arg}"));
+                }
+                value.clone_into(text);
+                return Ok(None);
+            }
+            let number = self
+                .number(name)
+                .ok_or_else(|| format!("unknown option: {name}"))?;
+            *number = value
+                .parse()
+                .map_err(|_| format!("invalid argument: {arg}"))?;
+            return Ok(None);
+        }
+
+        if let Some(flag) = arg.strip_suffix('!').and_then(|name| self.flag(name)) {
+            *flag = !*flag;
+            return Ok(None);
+        }
+        if let Some(flag) = self.flag(arg) {
+            *flag = true;
+            return Ok(None);
+        }
+        if let Some(flag) = arg.strip_prefix("no").and_then(|name| self.flag(name)) {
+            *flag = false;
+            return Ok(None);
+        }
+
+        Err(format!("unknown option: {arg}"))
+    }
+
+    /// Returns the markers whitespace is drawn as, if it is made visible
+    pub fn list_chars(&self) -> Option<ListChars> {
+        self.list
+            .then(|| ListChars::parse(&self.listchars).unwrap_or_default())
+    }
+
+    /// Returns the theme the buffers are drawn with, without true colors if they are not drawn
+    pub fn drawn_theme(&self) -> Theme {
+        if self.truecolor {
+            self.theme
+        } else {
+            self.theme.to_palette()
         }
     }
+
+    /// Returns the whitespace a level of indentation is made of. Without `expand_tab` as much of
+    /// it as possible is made of tabs
+    pub fn indent_unit(&self) -> String {
+        self.indent(self.indent_width())
+    }
+
+    /// Returns the whitespace of an indentation that takes `columns` columns
+    pub fn indent(&self, columns: usize) -> String {
+        indent::whitespace(columns, self.tab_width, self.expand_tab)
+    }
+
+    /// Returns the number of columns a level of indentation takes
+    pub const fn indent_width(&self) -> usize {
+        if self.shift_width == 0 {
+            self.tab_width
+        } else {
+            self.shift_width
+        }
+    }
+
+    /// Returns the value of an option as `name`/`noname` or `name=value`
+    fn show(&mut self, name: &str) -> Result<String, String> {
+        if let Some(&mut on) = self.flag(name) {
+            let no = if on { "" } else { "no" };
+            return Ok(format!("{no}{name}"));
+        }
+        if let Some(&mut value) = self.number(name) {
+            return Ok(format!("{name}={value}"));
+        }
+        if let Some(value) = self.text(name) {
+            return Ok(format!("{name}={value}"));
+        }
+
+        Err(format!("unknown option: {name}"))
+    }
+
+    pub(super) fn flag(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "number" | "nu" => Some(&mut self.line_numbers),
+            "relativenumber" | "rnu" => Some(&mut self.relative_line_numbers),
+            "wrap" => Some(&mut self.word_wrap),
+            "scrollbar" => Some(&mut self.scrollbar),
+            "backup" | "bk" => Some(&mut self.backup),
+            "expandtab" | "et" => Some(&mut self.expand_tab),
+            "ignorecase" | "ic" => Some(&mut self.ignore_case),
+            "autoindent" | "ai" => Some(&mut self.auto_indent),
+            "termguicolors" | "tgc" => Some(&mut self.truecolor),
+            "virtualtext" | "vt" => Some(&mut self.virtual_text),
+            "list" => Some(&mut self.list),
+            "spell" => Some(&mut self.spell),
+            _ => None,
+        }
+    }
+
+    pub(super) fn number(&mut self, name: &str) -> Option<&mut usize> {
+        match name {
+            "scrolloff" | "so" => Some(&mut self.scrolloff),
+            "tabstop" | "ts" => Some(&mut self.tab_width),
+            "shiftwidth" | "sw" => Some(&mut self.shift_width),
+            _ => None,
+        }
+    }
+
+    pub(super) fn text(&mut self, name: &str) -> Option<&mut String> {
+        match name {
+            "showbreak" | "sbr" => Some(&mut self.showbreak),
+            "listchars" | "lcs" => Some(&mut self.listchars),
+            "spelllang" | "spl" => Some(&mut self.spell_lang),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the text option can be set to the value
+    pub(super) fn is_valid_text(name: &str, value: &str) -> bool {
+        match name {
+            "listchars" | "lcs" => ListChars::parse(value).is_some(),
+            // It names the files of the dictionary
+            "spelllang" | "spl" => {
+                !value.is_empty()
+                    && value
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let mut options = Options::default();
+        options.set("nowrap").unwrap();
+        assert!(!options.word_wrap);
+        options.set("wrap!").unwrap();
+        assert!(options.word_wrap);
+        options.set("nonu").unwrap();
+        assert!(!options.line_numbers);
+        options.set("so=2").unwrap();
+        assert_eq!(options.scrolloff, 2);
+        options.set("rnu").unwrap();
+        assert!(options.relative_line_numbers);
+
+        assert_eq!(options.set("wrap?"), Ok(Some(String::from("wrap"))));
+        assert_eq!(options.set("nu?"), Ok(Some(String::from("nonu"))));
+        assert_eq!(options.set("so"), Ok(Some(String::from("so=2"))));
+        assert_eq!(options.set("ts?"), Ok(Some(String::from("ts=4"))));
+        options.set("sbr=>>").unwrap();
+        assert_eq!(options.showbreak, ">>");
+        assert_eq!(options.set("sbr"), Ok(Some(String::from("sbr=>>"))));
+        assert_eq!(options.list_chars(), None);
+        options.set("list").unwrap();
+        options.set("lcs=trail:.").unwrap();
+        assert_eq!(options.list_chars().and_then(|list| list.trail), Some('.'));
+        assert_eq!(
+            options.set("lcs=eol:$"),
+            Err(String::from("invalid argument: lcs=eol:$"))
+        );
+        assert_eq!(options.listchars, "trail:.");
+        assert_eq!(
+            options.set("spl=../en"),
+            Err(String::from("invalid argument: spl=../en"))
+        );
+
+        assert_eq!(options.indent_unit(), "\t");
+        options.set("sw=6").unwrap();
+        assert_eq!(options.indent_unit(), "\t  ");
+        options.set("et").unwrap();
+        assert_eq!(options.indent_unit(), "      ");
+        options.set("sw=0").unwrap();
+        assert_eq!(options.indent_unit(), "    ");
+
+        assert_eq!(
+            options.set("foo?"),
+            Err(String::from("unknown option: foo"))
+        );
+
+        assert_eq!(
+            options.set("so=x"),
+            Err(String::from("invalid argument: so=x"))
+        );
+        assert_eq!(
+            options.set("nofoo"),
+            Err(String::from("unknown option: nofoo"))
+        );
+        assert_eq!(
+            options.set("wrap=1"),
+            Err(String::from("unknown option: wrap"))
+        );
+    }
 }
@@ -0,0 +1,178 @@
+//! The problems found in the buffers, by their language servers and by linters
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use edi_lib::{
+    brand::Id,
+    diagnostic::{Diagnostic, Severity},
+};
+use edi_rope::Rope;
+
+/// What reported diagnostics, each origin only replaces its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Origin {
+    Lsp,
+    Lint,
+}
+
+/// The diagnostics of every buffer
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    buffers: HashMap<Id, BTreeMap<Origin, Vec<Diagnostic>>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the diagnostics the origin reported for the buffer
+    pub fn set(&mut self, buffer_id: Id, origin: Origin, diagnostics: Vec<Diagnostic>) {
+        self.buffers
+            .entry(buffer_id)
+            .or_default()
+            .insert(origin, diagnostics);
+    }
+
+    /// Forgets the diagnostics of a buffer that was closed
+    pub fn remove(&mut self, buffer_id: Id) {
+        self.buffers.remove(&buffer_id);
+    }
+
+    /// Returns the diagnostics of every origin of the buffer, sorted by where they start
+    pub fn of(&self, buffer_id: Id) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self
+            .buffers
+            .get(&buffer_id)
+            .into_iter()
+            .flat_map(BTreeMap::values)
+            .flatten()
+            .cloned()
+            .collect();
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.range.start, diagnostic.severity));
+        diagnostics
+    }
+}
+
+/// Returns the first diagnostic after `offset`, or the last one before it if `reverse` is set,
+/// wrapping around the end of the buffer
+pub fn next(diagnostics: &[Diagnostic], offset: usize, reverse: bool) -> Option<&Diagnostic> {
+    if reverse {
+        diagnostics
+            .iter()
+            .rfind(|diagnostic| diagnostic.range.start < offset)
+            .or_else(|| diagnostics.last())
+    } else {
+        diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.range.start > offset)
+            .or_else(|| diagnostics.first())
+    }
+}
+
+/// Parses the output of a linter, made of lines such as `file:line:column: severity: message`.
+/// The column and the severity may be left out, and only the lines about the file at `path`
+/// are kept. Lines and columns are counted from 1
+pub fn parse_lint(output: &str, path: &Path, text: &Rope) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = output
+        .lines()
+        .filter_map(|line| {
+            let (file, rest) = line.split_once(':')?;
+            if !same_file(Path::new(file.trim()), path) {
+                return None;
+            }
+            let (line_number, rest) = rest.split_once(':')?;
+            let line_number = line_number.trim().parse::<usize>().ok()?.checked_sub(1)?;
+            let (column, rest) = rest
+                .split_once(':')
+                .and_then(|(column, after)| Some((column.trim().parse::<usize>().ok()?, after)))
+                .unwrap_or((1, rest));
+            let (severity, message) = rest
+                .split_once(':')
+                .and_then(|(name, message)| Some((Severity::parse(name.trim())?, message)))
+                .unwrap_or((Severity::Warning, rest));
+
+            if line_number >= text.total_lines() {
+                return None;
+            }
+            let line_start = text.line_to_char(line_number);
+            let line_end = text
+                .line_to_char(line_number + 1)
+                .saturating_sub(1)
+                .max(line_start);
+            let start = (line_start + column.saturating_sub(1)).min(line_end);
+            Some(Diagnostic {
+                range: start..start + 1,
+                severity,
+                message: String::from(message.trim()),
+            })
+        })
+        .collect();
+    diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+    diagnostics
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    a == b
+        || std::fs::canonicalize(a)
+            .ok()
+            .is_some_and(|a| std::fs::canonicalize(b).ok() == Some(a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lint_output() {
+        let text = Rope::from("echo $a\nls\n\tcd x\n");
+        let output = "\
+a.sh:1:6: warning: a is referenced but not assigned [SC2154]
+a.sh:3:2: error: use cd ... || exit [SC2164]
+b.sh:1:1: error: another file
+a.sh:2: note: no column
+a.sh:9:1: error: past the end
+not a diagnostic";
+        let diagnostics = parse_lint(output, Path::new("a.sh"), &text);
+        let summary: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.range.start, d.severity, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    5,
+                    Severity::Warning,
+                    "a is referenced but not assigned [SC2154]"
+                ),
+                (8, Severity::Info, "no column"),
+                (12, Severity::Error, "use cd ... || exit [SC2164]"),
+            ]
+        );
+
+        let diagnostics = parse_lint("a.sh:2:1: odd", Path::new("a.sh"), &text);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].message, "odd");
+    }
+
+    #[test]
+    fn navigation() {
+        let diagnostic = |start| Diagnostic {
+            range: start..start + 1,
+            severity: Severity::Error,
+            message: String::new(),
+        };
+        let diagnostics = [diagnostic(2), diagnostic(8)];
+        let next = |offset, reverse| next(&diagnostics, offset, reverse).map(|d| d.range.start);
+        assert_eq!(next(0, false), Some(2));
+        assert_eq!(next(2, false), Some(8));
+        assert_eq!(next(8, false), Some(2));
+        assert_eq!(next(8, true), Some(2));
+        assert_eq!(next(2, true), Some(8));
+        assert_eq!(super::next(&[], 0, false), None);
+    }
+}
@@ -0,0 +1,249 @@
+//! A tree of the files of a directory, shown in a buffer of its own
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// What a key typed in the file explorer does to the entry under the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Expands a collapsed directory and collapses an expanded one, opens a file
+    Open,
+    /// Collapses the directory, or the directory the entry is in
+    Collapse,
+    /// Shows the parent of the root directory in its place
+    Parent,
+    /// Reads the directories again
+    Refresh,
+    /// Asks for the path of a file to create, a directory if it ends with a `/`
+    Create,
+    /// Asks for the path to move the entry to
+    Rename,
+    /// Asks whether to delete the entry, along with everything in it
+    Delete,
+    /// Closes the explorer
+    Close,
+}
+
+/// A file or a directory shown in the explorer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub path: PathBuf,
+    /// How many directories below the root the entry is
+    pub depth: usize,
+    pub is_dir: bool,
+}
+
+/// The files of the root directory and of the directories expanded in it. The root is shown on
+/// the first line, and each entry on a line after it
+#[derive(Debug)]
+pub struct Explorer {
+    pub root: PathBuf,
+    expanded: HashSet<PathBuf>,
+    entries: Vec<Entry>,
+}
+
+impl Explorer {
+    pub fn new(root: PathBuf) -> Self {
+        let mut explorer = Self {
+            root,
+            expanded: HashSet::new(),
+            entries: Vec::new(),
+        };
+        explorer.refresh();
+        explorer
+    }
+
+    /// Reads the root directory and the expanded directories again
+    pub fn refresh(&mut self) {
+        self.expanded.retain(|dir| dir.is_dir());
+        self.entries.clear();
+        let root = self.root.clone();
+        self.read_dir(&root, 0);
+    }
+
+    /// Appends the entries of the directory, the directories first, and the entries of the
+    /// expanded directories after each of them
+    fn read_dir(&mut self, dir: &Path, depth: usize) {
+        let Ok(read) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let mut entries: Vec<Entry> = read
+            .filter_map(Result::ok)
+            .map(|entry| {
+                let path = entry.path();
+                Entry {
+                    is_dir: path.is_dir(),
+                    path,
+                    depth,
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            b.is_dir
+                .cmp(&a.is_dir)
+                .then_with(|| a.path.file_name().cmp(&b.path.file_name()))
+        });
+
+        for entry in entries {
+            let expanded = entry.is_dir && self.expanded.contains(&entry.path);
+            let path = entry.path.clone();
+            self.entries.push(entry);
+            if expanded {
+                self.read_dir(&path, depth + 1);
+            }
+        }
+    }
+
+    /// Returns the text of the buffer the explorer is shown in
+    pub fn text(&self) -> String {
+        let mut text = self.root.display().to_string();
+        if !text.ends_with('/') {
+            text.push('/');
+        }
+        text.push('\n');
+
+        for entry in &self.entries {
+            let name = entry
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default();
+            let marker = match (entry.is_dir, self.expanded.contains(&entry.path)) {
+                (true, true) => "▾ ",
+                (true, false) => "▸ ",
+                (false, _) => "  ",
+            };
+            let slash = if entry.is_dir { "/" } else { "" };
+            text.push_str(&"  ".repeat(entry.depth));
+            text.push_str(marker);
+            text.push_str(&name);
+            text.push_str(slash);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Returns the entry shown on the line, `None` for the line of the root
+    pub fn entry(&self, line: usize) -> Option<&Entry> {
+        self.entries.get(line.checked_sub(1)?)
+    }
+
+    /// Returns the line the path is shown on, if it is shown
+    pub fn line_of(&self, path: &Path) -> Option<usize> {
+        if path == self.root {
+            return Some(0);
+        }
+        self.entries
+            .iter()
+            .position(|entry| entry.path == path)
+            .map(|i| i + 1)
+    }
+
+    /// Expands the directory if it is collapsed, and collapses it otherwise
+    pub fn toggle(&mut self, dir: &Path) {
+        if !self.expanded.remove(dir) {
+            self.expanded.insert(dir.to_path_buf());
+        }
+        self.refresh();
+    }
+
+    /// Expands the directories the path is in, so it is shown
+    pub fn reveal(&mut self, path: &Path) {
+        let dirs = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root) && *dir != self.root);
+        self.expanded.extend(dirs.map(Path::to_path_buf));
+        self.refresh();
+    }
+
+    /// Collapses the expanded directory on the line, or the directory the entry on it is in.
+    /// Returns the collapsed directory
+    pub fn collapse(&mut self, line: usize) -> Option<PathBuf> {
+        let entry = self.entry(line)?;
+        let dir = if self.expanded.contains(&entry.path) {
+            entry.path.clone()
+        } else {
+            entry
+                .path
+                .parent()
+                .filter(|&parent| parent != self.root)?
+                .to_path_buf()
+        };
+
+        self.expanded.remove(&dir);
+        self.refresh();
+        Some(dir)
+    }
+
+    /// Shows the parent of the root directory in its place, with the root expanded
+    pub fn parent(&mut self) {
+        let Some(parent) = self.root.parent().map(Path::to_path_buf) else {
+            return;
+        };
+        let root = std::mem::replace(&mut self.root, parent);
+        self.expanded.insert(root);
+        self.refresh();
+    }
+
+    /// Returns the directory a file created from the line goes to, the one on the line or the
+    /// one the file on it is in
+    pub fn directory(&self, line: usize) -> &Path {
+        match self.entry(line) {
+            Some(entry) if entry.is_dir => &entry.path,
+            Some(entry) => entry.path.parent().unwrap_or(&self.root),
+            None => &self.root,
+        }
+    }
+
+    /// Returns the path as it is typed in the prompts, relative to the root
+    pub fn relative(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        relative.display().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn tree() {
+        let root = std::env::temp_dir().join(format!("edi-explorer-{}", std::process::id()));
+        fs::create_dir_all(root.join("src/app")).unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("src/app/mod.rs"), "").unwrap();
+        fs::write(root.join("Cargo.toml"), "").unwrap();
+
+        let mut explorer = Explorer::new(root.clone());
+        let header = format!("{}/\n", root.display());
+        assert_eq!(explorer.text(), format!("{header}▸ src/\n  Cargo.toml\n"));
+        assert_eq!(explorer.entry(0), None);
+        assert_eq!(explorer.directory(2), root);
+
+        explorer.toggle(&root.join("src"));
+        assert_eq!(
+            explorer.text(),
+            format!("{header}▾ src/\n  ▸ app/\n    main.rs\n  Cargo.toml\n")
+        );
+        assert_eq!(explorer.line_of(&root.join("src/main.rs")), Some(3));
+        assert_eq!(explorer.relative(&root.join("src/main.rs")), "src/main.rs");
+        assert_eq!(explorer.directory(3), root.join("src"));
+
+        explorer.reveal(&root.join("src/app/mod.rs"));
+        assert_eq!(explorer.line_of(&root.join("src/app/mod.rs")), Some(3));
+        assert_eq!(explorer.collapse(3), Some(root.join("src/app")));
+        assert_eq!(explorer.collapse(1), Some(root.join("src")));
+        assert_eq!(explorer.collapse(1), None);
+
+        explorer.parent();
+        assert_eq!(explorer.root, std::env::temp_dir());
+        assert!(explorer.line_of(&root.join("src")).is_some());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+//! Who changed the lines of a file last, and how they differ from the ones staged in git
+
+use std::path::Path;
+
+use crate::jobs::Spec;
+
+/// Lines of a file that differ from the staged ones, lines are counted from 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// The first of the changed lines, or the one the removed lines were before if there are
+    /// none
+    pub start: usize,
+    /// How many lines the hunk has in the file
+    pub count: usize,
+    /// The staged lines the ones of the hunk replaced
+    pub old: Vec<String>,
+    /// The lines of the diff of the hunk, starting with its `@@` header
+    pub diff: Vec<String>,
+}
+
+impl Hunk {
+    /// Returns whether the hunk is on the line, a hunk that only removed lines is on the line
+    /// before them
+    pub const fn contains(&self, line: usize) -> bool {
+        if self.count == 0 {
+            line == self.start.saturating_sub(1)
+        } else {
+            self.start <= line && line < self.start + self.count
+        }
+    }
+
+    /// Returns the text with the lines of the hunk replaced by the staged ones
+    pub fn revert(&self, text: &str) -> String {
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        let end = (self.start + self.count).min(lines.len());
+        let start = self.start.min(end);
+        lines.splice(start..end, self.old.iter().map(String::as_str));
+        lines.join("\n")
+    }
+}
+
+/// Returns the job that runs `git blame` on the text of the file at `path`, which may differ from
+/// the one written to it. Its output is parsed by `parse_blame`
+pub fn blame(path: &Path, text: &str) -> Spec {
+    let (dir, name) = split_path(path);
+    Spec::new("git")
+        .with_args(["blame", "--line-porcelain", "--contents", "-", "--"])
+        .with_args([name.to_string_lossy()])
+        .with_dir(dir)
+        .with_input(text)
+}
+
+/// Returns the job that runs `git diff` on the file at `path`, comparing it with the staged one.
+/// Its output is parsed by `parse_diff`
+pub fn diff(path: &Path) -> Spec {
+    let (dir, name) = split_path(path);
+    Spec::new("git")
+        .with_args(["diff", "-U0", "--no-color", "--no-ext-diff", "--"])
+        .with_args([name.to_string_lossy()])
+        .with_dir(dir)
+}
+
+/// Splits the path into the directory git is run in and the name of the file in it
+fn split_path(path: &Path) -> (&Path, &Path) {
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = path.file_name().map_or(path, Path::new);
+    (dir, name)
+}
+
+/// Returns the first line git wrote to its standard error
+pub fn error(stderr: &str) -> String {
+    let line = stderr.lines().next().unwrap_or("git failed");
+    String::from(line.strip_prefix("fatal: ").unwrap_or(line))
+}
+
+/// Parses the output of `git blame --line-porcelain` into a line for each line of the file, made
+/// of the abbreviated hash, the author, the date and the summary of the commit
+pub fn parse_blame(output: &str) -> Vec<String> {
+    let mut annotations = Vec::new();
+    let (mut hash, mut author, mut summary) = ("", "", "");
+    let (mut time, mut tz) = (0, 0);
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            let annotation = if hash.bytes().all(|b| b == b'0') {
+                String::from("not committed yet")
+            } else {
+                let date = date(time + tz);
+                let hash = &hash[..hash.len().min(8)];
+                format!("{hash} {author} {date} {summary}")
+            };
+            annotations.push(annotation);
+            continue;
+        }
+
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "author" => author = value,
+            "author-time" => time = value.parse().unwrap_or(0),
+            "author-tz" => tz = parse_tz(value),
+            "summary" => summary = value,
+            _ if key.len() == 40 && key.bytes().all(|b| b.is_ascii_hexdigit()) => hash = key,
+            _ => {}
+        }
+    }
+    annotations
+}
+
+/// Returns the offset of a time zone such as `+0130` in seconds
+fn parse_tz(tz: &str) -> i64 {
+    let (sign, digits) = match tz.split_at_checked(1) {
+        Some(("-", digits)) => (-1, digits),
+        Some(("+", digits)) => (1, digits),
+        _ => return 0,
+    };
+    let Ok(hhmm) = digits.parse::<i64>() else {
+        return 0;
+    };
+    sign * (hhmm / 100 * 3600 + hhmm % 100 * 60)
+}
+
+/// Returns the `yyyy-mm-dd` date of the unix time
+fn date(time: i64) -> String {
+    // Days are turned into a date of the proleptic Gregorian calendar by counting 400-year eras
+    // from the 1st of March of the year 0
+    let days = time.div_euclid(86400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Parses the output of `git diff -U0` of a single file
+pub fn parse_diff(output: &str) -> Vec<Hunk> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("@@ ") {
+            let Some((start, count)) = parse_header(header) else {
+                continue;
+            };
+            hunks.push(Hunk {
+                start,
+                count,
+                old: Vec::new(),
+                diff: vec![String::from(line)],
+            });
+            continue;
+        }
+
+        let Some(hunk) = hunks.last_mut() else {
+            continue;
+        };
+        if let Some(old) = line.strip_prefix('-') {
+            hunk.old.push(String::from(old));
+        } else if !line.starts_with('+') {
+            continue;
+        }
+        hunk.diff.push(String::from(line));
+    }
+    hunks
+}
+
+/// Parses `-a,b +c,d @@` into where the hunk starts in the file and how many lines it has there
+fn parse_header(header: &str) -> Option<(usize, usize)> {
+    let new = header.split(' ').nth(1)?.strip_prefix('+')?;
+    let (start, count) = match new.split_once(',') {
+        Some((start, count)) => (start.parse::<usize>().ok()?, count.parse().ok()?),
+        None => (new.parse::<usize>().ok()?, 1),
+    };
+    // Lines are counted from 1, and the line before removed ones is given when there are none
+    let start = if count == 0 { start } else { start - 1 };
+    Some((start, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blame_output() {
+        let hash = "1234567890abcdef1234567890abcdef12345678";
+        let output = format!(
+            "{hash} 1 1 2\nauthor Jane Doe\nauthor-mail <jane@doe>\nauthor-time 1700000000\n\
+             author-tz -0500\nsummary Add the thing\nfilename a.rs\n\tfn main() {{\n\
+             {hash} 2 2\nauthor Jane Doe\nauthor-time 1700000000\nauthor-tz -0500\n\
+             summary Add the thing\nfilename a.rs\n\t}}\n\
+             {zero} 3 3 1\nauthor Not Committed Yet\nauthor-time 1700000000\n\
+             author-tz +0000\nsummary Version of a.rs from a.rs\nfilename a.rs\n\t\n",
+            zero = "0".repeat(40)
+        );
+        assert_eq!(
+            parse_blame(&output),
+            [
+                "12345678 Jane Doe 2023-11-14 Add the thing",
+                "12345678 Jane Doe 2023-11-14 Add the thing",
+                "not committed yet",
+            ]
+        );
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(parse_tz("+0130"), 5400);
+    }
+
+    #[test]
+    fn diff_output() {
+        let output = "diff --git a/a.txt b/a.txt\nindex 1..2 100644\n--- a/a.txt\n+++ b/a.txt\n\
+                      @@ -2 +2,2 @@ fn main\n-b\n+B\n+C\n@@ -5,2 +5,0 @@\n-e\n-f\n\
+                      @@ -9,0 +8 @@\n+new\n\\ No newline at end of file\n";
+        let hunks = parse_diff(output);
+        assert_eq!(
+            hunks,
+            [
+                Hunk {
+                    start: 1,
+                    count: 2,
+                    old: vec![String::from("b")],
+                    diff: ["@@ -2 +2,2 @@ fn main", "-b", "+B", "+C"]
+                        .map(String::from)
+                        .to_vec(),
+                },
+                Hunk {
+                    start: 5,
+                    count: 0,
+                    old: vec![String::from("e"), String::from("f")],
+                    diff: ["@@ -5,2 +5,0 @@", "-e", "-f"].map(String::from).to_vec(),
+                },
+                Hunk {
+                    start: 7,
+                    count: 1,
+                    old: Vec::new(),
+                    diff: ["@@ -9,0 +8 @@", "+new"].map(String::from).to_vec(),
+                },
+            ]
+        );
+        assert!(hunks[0].contains(2) && !hunks[0].contains(3));
+        assert!(hunks[1].contains(4) && !hunks[1].contains(5));
+
+        let text = "a\nB\nC\nd\ng";
+        assert_eq!(hunks[0].revert(text), "a\nb\nd\ng");
+        assert_eq!(
+            hunks[1].revert("a\nb\nc\nd\ng\nh"),
+            "a\nb\nc\nd\ng\ne\nf\nh"
+        );
+    }
+}
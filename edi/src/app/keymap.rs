@@ -0,0 +1,107 @@
+//! Mappings defined by the user, in the config file or with `:map` and the like
+
+use edi_term::input::{Input, KeyCode, Modifiers};
+
+/// Parses keys written the way `:map` takes them, e.g. `<leader>w`, `<C-s>` or `:w<CR>`. A `<...>`
+/// that does not name a key stands for its characters
+pub fn parse_keys(notation: &str, leader: &Input) -> Vec<Input> {
+    let mut inputs = Vec::new();
+    let mut rest = notation;
+
+    while let Some(c) = rest.chars().next() {
+        let named = rest
+            .strip_prefix('<')
+            .and_then(|inner| inner.split_once('>'))
+            .and_then(|(name, after)| Some((named_key(name, leader)?, after)));
+        if let Some((input, after)) = named {
+            inputs.push(input);
+            rest = after;
+            continue;
+        }
+
+        inputs.push(Input::Keypress(c));
+        rest = &rest[c.len_utf8()..];
+    }
+
+    inputs
+}
+
+/// Returns the key written as `<name>`
+fn named_key(name: &str, leader: &Input) -> Option<Input> {
+    let lower = name.to_ascii_lowercase();
+    let input = match lower.as_str() {
+        "leader" => leader.clone(),
+        "cr" | "enter" | "return" => Input::Enter,
+        "esc" => Input::Escape,
+        "bs" => Input::Backspace,
+        "tab" => Input::Keypress('\t'),
+        "space" => Input::Keypress(' '),
+        "lt" => Input::Keypress('<'),
+        "bar" => Input::Keypress('|'),
+        "bslash" => Input::Keypress('\\'),
+        "up" => Input::ArrowUp,
+        "down" => Input::ArrowDown,
+        "left" => Input::ArrowLeft,
+        "right" => Input::ArrowRight,
+        "home" => Input::key(KeyCode::Home, Modifiers::NONE),
+        "end" => Input::key(KeyCode::End, Modifiers::NONE),
+        "pageup" => Input::key(KeyCode::PageUp, Modifiers::NONE),
+        "pagedown" => Input::key(KeyCode::PageDown, Modifiers::NONE),
+        "del" => Input::key(KeyCode::Delete, Modifiers::NONE),
+        _ => return modified_key(&lower),
+    };
+    Some(input)
+}
+
+/// Returns a function key such as `f5`, or a key held with ctrl or alt such as `c-s` or `m-x`
+fn modified_key(name: &str) -> Option<Input> {
+    if let Some(n) = name.strip_prefix('f') {
+        return n
+            .parse()
+            .ok()
+            .map(|n| Input::key(KeyCode::F(n), Modifiers::NONE));
+    }
+
+    let (modifier, key) = name.split_once('-')?;
+    let mut chars = key.chars();
+    let c = chars.next().filter(|_| chars.next().is_none())?;
+    match modifier {
+        // The terminal sends the control code of the letter, which is what it is parsed back from
+        "c" if c.is_ascii_lowercase() => Some(Input::from_bytes(&[c as u8 & 0x1f])),
+        "m" | "a" => Some(Input::key(KeyCode::Char(c), Modifiers::ALT)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys() {
+        let leader = Input::Keypress(' ');
+        assert_eq!(
+            parse_keys("<leader>w", &leader),
+            [Input::Keypress(' '), Input::Keypress('w')]
+        );
+        assert_eq!(
+            parse_keys(":w<CR>", &leader),
+            [Input::Keypress(':'), Input::Keypress('w'), Input::Enter]
+        );
+        assert_eq!(
+            parse_keys("<c-d><Esc><lt>", &leader),
+            [Input::Control('d'), Input::Escape, Input::Keypress('<')]
+        );
+        assert_eq!(
+            parse_keys("<M-x><F2>", &leader),
+            [
+                Input::key(KeyCode::Char('x'), Modifiers::ALT),
+                Input::key(KeyCode::F(2), Modifiers::NONE)
+            ]
+        );
+        assert_eq!(
+            parse_keys("<x>", &leader),
+            "<x>".chars().map(Input::Keypress).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+
+use edi_term::coord::UDims;
+
+/// How important a message is, which the colors it is shown with tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A line shown in the message line until the next key is pressed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub text: String,
+    pub severity: Severity,
+}
+
+impl Message {
+    pub fn new(text: impl Into<String>, severity: Severity) -> Self {
+        Self {
+            text: text.into(),
+            severity,
+        }
+    }
+
+    pub fn info(text: impl Into<String>) -> Self {
+        Self::new(text, Severity::Info)
+    }
+
+    pub fn warning(text: impl Into<String>) -> Self {
+        Self::new(text, Severity::Warning)
+    }
+
+    pub fn error(text: impl Into<String>) -> Self {
+        Self::new(text, Severity::Error)
+    }
+}
+
+/// The messages shown so far, from the oldest one. Only the last `CAPACITY` of them are kept
+#[derive(Debug, Default)]
+pub struct History {
+    messages: VecDeque<Message>,
+}
+
+impl History {
+    const CAPACITY: usize = 200;
+
+    pub fn push(&mut self, message: Message) {
+        if self.messages.len() == Self::CAPACITY {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(message);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Returns the messages one per line, warnings and errors marked as such
+    pub fn to_text(&self) -> String {
+        let lines: Vec<String> = self
+            .messages
+            .iter()
+            .map(|message| match message.severity {
+                Severity::Info => message.text.clone(),
+                Severity::Warning => format!("warning: {}", message.text),
+                Severity::Error => format!("error: {}", message.text),
+            })
+            .collect();
+        lines.join("\n")
+    }
+}
+
+/// The rows at the bottom of the terminal that the message line takes, below the buffers
+const ROWS: usize = 1;
+
+/// Returns the size of the part of the terminal the buffers are drawn in, above the message line
+pub const fn buffers_area(terminal: UDims) -> UDims {
+    UDims::new(terminal.width, terminal.height.saturating_sub(ROWS))
+}
+
+/// Returns the row of the terminal the message line is on
+pub const fn row(terminal: UDims) -> usize {
+    terminal.height.saturating_sub(ROWS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history() {
+        let mut history = History::default();
+        assert!(history.is_empty());
+        history.push(Message::info("\"a.txt\" written"));
+        history.push(Message::error("no file name"));
+        history.push(Message::warning("unused variable"));
+        assert_eq!(
+            history.to_text(),
+            "\"a.txt\" written\nerror: no file name\nwarning: unused variable"
+        );
+
+        for i in 0..History::CAPACITY {
+            history.push(Message::info(i.to_string()));
+        }
+        assert_eq!(history.messages.len(), History::CAPACITY);
+        assert_eq!(history.messages[0].text, "0");
+    }
+}
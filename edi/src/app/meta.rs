@@ -1,16 +1,32 @@
-use std::path::PathBuf;
+use std::{ops::Range, path::PathBuf, sync::Arc};
 
-use edi_frame::unit::Unit;
-use edi_lib::buffer::{draw::FlushOptions, Buffer};
-use edi_lib::string::highlight::Highlight;
-use edi_lib::{fs::filetype::Filetype, vec2::Vec2};
+use edi_frame::{rect::Rect, unit::Unit};
+use edi_lib::buffer::{
+    draw::{FlushOptions, Layer},
+    Buffer,
+};
+use edi_lib::string::highlight::{self, Highlight, HighlightProvider};
+use edi_lib::{
+    diagnostic::Diagnostic,
+    fs::{filetype::Filetype, read::InvalidBytes},
+    language::Language,
+    vec2::Vec2,
+};
+use edi_rope::{LineEnding, Rope};
 use edi_term::coord::UDims;
 
-use crate::app::Mode;
+use crate::{
+    app::{completion::Completion, explorer::Explorer, Mode},
+    error::{AppError, AppErrorKind, Result},
+};
 
 use super::context::Context;
 
 #[derive(Debug)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "the flags describe unrelated properties of the buffer"
+)]
 pub struct BufferMeta {
     pub statusline: bool,
     pub filepath: Option<PathBuf>,
@@ -18,8 +34,47 @@ pub struct BufferMeta {
     pub size: Vec2<Unit>,
     pub offset: Vec2<Unit>,
     pub line_offset: usize,
+    /// The first screen column shown when lines are not wrapped
+    pub column_offset: usize,
     pub highlights: Vec<Highlight>,
+    /// Finds the highlights of the buffer's text, it is made for the language of the filetype
+    pub highlighter: Box<dyn HighlightProvider>,
+    /// The diagnostics of the buffer, sorted by where they start
+    pub diagnostics: Vec<Diagnostic>,
+    /// Character ranges of the diagnostics, merged where they overlap
+    pub diagnostic_ranges: Vec<Range<usize>>,
+    /// Character ranges of the matches of the current search
+    pub matches: Vec<Range<usize>>,
+    /// Character ranges of the selected text, drawn over the matches
+    pub selections: Vec<Range<usize>>,
+    /// Character ranges of the misspelled words, `None` if the text changed since they were found
+    pub misspellings: Option<Vec<Range<usize>>>,
+    /// Text shown on the right side of the statusline
+    pub status: String,
+    /// The keys of an unfinished command, shown before the status
+    pub pending_keys: String,
+    /// The popup menu of the candidates for the word being typed, if it is open
+    pub completion: Option<Completion>,
+    /// The tree of files shown in the buffer, if it is a file explorer
+    pub explorer: Option<Explorer>,
+    /// The commit each line was last changed in, shown after the line while blame is on
+    pub blame: Vec<String>,
     pub line_numbers: bool,
+    /// Whether the buffer was changed since it was last written
+    pub modified: bool,
+    /// The checksum of the text when it was last read from or written to the file. The buffer is
+    /// modified while its text differs from it, or after every change if there is none, as after
+    /// its line ending was changed
+    pub saved_checksum: Option<u64>,
+    /// Whether the buffer refuses changes, as the buffers of files opened for viewing or that
+    /// cannot be written do
+    pub read_only: bool,
+    /// Whether the file ended with a newline when it was read, one is added when writing if so
+    pub trailing_newline: bool,
+    /// The line ending of the file, the lines of the buffer end with `\n` whatever it is
+    pub line_ending: LineEnding,
+    /// The bytes of the file that were not valid utf-8, shown as replacement characters
+    pub invalid_bytes: InvalidBytes,
 
     pub mode: Mode,
 
@@ -36,8 +91,26 @@ impl BufferMeta {
             size: Vec2::new(Unit::full_width(), Unit::full_height()),
             offset: Vec2::new(Unit::zero(), Unit::zero()),
             line_offset: 0,
+            column_offset: 0,
             highlights: Vec::new(),
+            highlighter: highlight::provider(&Arc::default()),
+            diagnostics: Vec::new(),
+            diagnostic_ranges: Vec::new(),
+            matches: Vec::new(),
+            selections: Vec::new(),
+            misspellings: None,
+            status: String::new(),
+            pending_keys: String::new(),
+            completion: None,
+            explorer: None,
+            blame: Vec::new(),
             line_numbers: false,
+            modified: false,
+            saved_checksum: None,
+            read_only: false,
+            trailing_newline: true,
+            line_ending: LineEnding::Lf,
+            invalid_bytes: InvalidBytes::default(),
 
             mode,
 
@@ -49,11 +122,28 @@ impl BufferMeta {
         self.mode
     }
 
+    /// Returns the mode the keys typed in the buffer are mapped in, a file explorer has mappings
+    /// of its own in place of the ones of the normal mode
+    pub const fn keymap_mode(&self) -> Mode {
+        match self.mode {
+            Mode::Normal if self.explorer.is_some() => Mode::Explorer,
+            mode => mode,
+        }
+    }
+
     pub const fn set_mode(&mut self, mode: Mode) {
         self.mode = mode;
     }
 
-    pub fn with_statusline(mut self, statusline: bool) -> Self {
+    /// Returns an error if the buffer refuses changes
+    pub fn ensure_modifiable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(AppError::from_kind(AppErrorKind::ReadOnly));
+        }
+        Ok(())
+    }
+
+    pub const fn with_statusline(mut self, statusline: bool) -> Self {
         self.statusline = statusline;
         self
     }
@@ -63,8 +153,9 @@ impl BufferMeta {
         self
     }
 
-    pub fn with_filetype(mut self, filetype: Filetype) -> Self {
-        self.filetype = filetype;
+    pub fn with_language(mut self, language: &Arc<Language>) -> Self {
+        self.highlighter = highlight::provider(language);
+        self.filetype = language.filetype.clone();
         self
     }
 
@@ -83,14 +174,20 @@ impl BufferMeta {
         self
     }
 
-    pub fn with_highlights(mut self, highlights: Vec<Highlight>) -> Self {
-        self.highlights = highlights;
+    pub const fn with_line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
         self
     }
 
-    pub fn with_line_numbers(mut self, line_numbers: bool) -> Self {
-        self.line_numbers = line_numbers;
-        self
+    /// Records that `text` is what the file has now, so the buffer is not modified
+    pub fn mark_saved(&mut self, text: &Rope) {
+        self.modified = false;
+        self.saved_checksum = Some(text.checksum());
+    }
+
+    /// Updates whether the buffer is modified after its text changed to `text`
+    pub fn update_modified(&mut self, text: &Rope) {
+        self.modified = self.saved_checksum != Some(text.checksum());
     }
 
     pub fn set_highlights(&mut self, highlights: Vec<Highlight>) -> &mut Self {
@@ -98,59 +195,335 @@ impl BufferMeta {
         self
     }
 
-    pub fn updated_flush_options(&mut self, ctx: &Context) -> FlushOptions {
+    /// Replaces the diagnostics, which are sorted by where they start
+    pub fn set_diagnostics(&mut self, diagnostics: Vec<Diagnostic>) {
+        let mut ranges: Vec<Range<usize>> = Vec::with_capacity(diagnostics.len());
+        for diagnostic in &diagnostics {
+            let range = diagnostic.range.clone();
+            match ranges.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => ranges.push(range),
+            }
+        }
+        self.diagnostic_ranges = ranges;
+        self.diagnostics = diagnostics;
+    }
+
+    pub fn updated_flush_options<'a>(&'a mut self, ctx: &'a Context) -> FlushOptions<'a, 'a> {
+        let theme = ctx.options.drawn_theme();
         FlushOptions::default()
-            .with_wrap(ctx.settings.word_wrap)
-            .with_mode(self.mode.as_str())
-            .with_line_numbers(ctx.settings.line_numbers)
+            .with_wrap(ctx.options.word_wrap)
+            .with_showbreak(&ctx.options.showbreak)
+            .with_list_chars(ctx.options.list_chars())
+            .with_mode(self.keymap_mode().as_str())
+            .with_line_numbers(ctx.options.line_numbers || ctx.options.relative_line_numbers)
+            .with_relative_line_numbers(ctx.options.relative_line_numbers)
             .with_statusline(self.statusline)
+            .with_scrollbar(ctx.options.scrollbar && !self.flags.is_terminal())
             .with_line_offset(self.line_offset)
+            .with_column_offset(if ctx.options.word_wrap {
+                0
+            } else {
+                self.column_offset
+            })
             .with_highlights(&self.highlights)
+            .with_diagnostics(&self.diagnostics)
+            .with_virtual_text(ctx.options.virtual_text)
+            .with_annotations(&self.blame)
+            .with_layer(Layer::new(
+                self.misspellings
+                    .as_deref()
+                    .filter(|_| ctx.options.spell)
+                    .unwrap_or_default(),
+                theme.spell,
+                Layer::SPELLING,
+            ))
+            .with_layer(Layer::new(
+                &self.diagnostic_ranges,
+                theme.diagnostic,
+                Layer::DIAGNOSTICS,
+            ))
+            .with_layer(Layer::new(
+                &self.matches,
+                theme.search_match,
+                Layer::MATCHES,
+            ))
+            .with_layer(Layer::new(
+                &self.selections,
+                theme.selection,
+                Layer::SELECTION,
+            ))
+            .with_status(&self.status)
+            .with_pending_keys(&self.pending_keys)
+            .with_theme(theme)
     }
 
     pub fn size_resolved(&self, window_dimensions: UDims) -> Vec2<usize> {
-        self.size.map(|coord| coord.resolve(window_dimensions))
+        Vec2::new(
+            self.size.x.resolve_width(window_dimensions),
+            self.size.y.resolve_height(window_dimensions),
+        )
     }
 
-    pub fn normalize(&mut self, ctx: &Context, buffer: &Buffer, window_dimensions: UDims) {
+    /// Returns the area of the window the buffer occupies
+    pub fn rect_resolved(&self, window_dimensions: UDims) -> Rect {
+        let size = self.size_resolved(window_dimensions);
+        Rect::new(
+            self.offset.x.resolve_width(window_dimensions),
+            self.offset.y.resolve_height(window_dimensions),
+            size.x,
+            size.y,
+        )
+    }
+
+    /// Returns the size of the part of the screen the text of the buffer is drawn in
+    pub fn view_dimensions(
+        &mut self,
+        ctx: &Context,
+        buffer: &Buffer,
+        window_dimensions: UDims,
+    ) -> UDims {
         let size_resolved = self.size_resolved(window_dimensions).into_dims();
-        let current_line = buffer.current_line();
         let total_lines = buffer.total_lines();
         let opts = self.updated_flush_options(ctx);
-        let y = buffer
-            .main_dimensions(size_resolved, total_lines, &opts)
-            .height;
+        buffer.main_dimensions(size_resolved, total_lines, &opts)
+    }
+
+    /// Returns how many lines of the buffer fit on the screen
+    pub fn view_height(
+        &mut self,
+        ctx: &Context,
+        buffer: &Buffer,
+        window_dimensions: UDims,
+    ) -> usize {
+        self.view_dimensions(ctx, buffer, window_dimensions).height
+    }
+
+    /// Scrolls the view `count` columns to the right, or to the left if `right` is not set. The
+    /// cursor is moved to the nearest visible column if it is scrolled out of the view
+    pub fn scroll_columns(
+        &mut self,
+        ctx: &Context,
+        buffer: &mut Buffer,
+        window_dimensions: UDims,
+        right: bool,
+        count: usize,
+    ) {
+        if ctx.options.word_wrap {
+            return;
+        }
+
+        let width = self
+            .view_dimensions(ctx, buffer, window_dimensions)
+            .width
+            .max(1);
+        self.column_offset = if right {
+            self.column_offset.saturating_add(count)
+        } else {
+            self.column_offset.saturating_sub(count)
+        };
+
+        let column = buffer.cursor_column();
+        if column < self.column_offset {
+            buffer.set_cursor_column(self.column_offset);
+        } else if column >= self.column_offset + width {
+            buffer.set_cursor_column(self.column_offset + width - 1);
+        }
+    }
+
+    /// Scrolls the view of `height` lines `count` lines down, or up if `down` is not set. It is
+    /// not scrolled further down once the last line is in it
+    pub fn scroll_lines(&mut self, buffer: &Buffer, height: usize, down: bool, count: usize) {
+        self.line_offset = if down {
+            let last = buffer.total_lines().saturating_sub(height);
+            self.line_offset
+                .saturating_add(count)
+                .min(last.max(self.line_offset))
+        } else {
+            self.line_offset.saturating_sub(count)
+        };
+    }
+
+    /// Scrolls the view so the cursor line is at `position`
+    pub fn align(
+        &mut self,
+        ctx: &Context,
+        buffer: &Buffer,
+        window_dimensions: UDims,
+        position: ViewPosition,
+    ) {
+        let height = self.view_height(ctx, buffer, window_dimensions).max(1);
+        let current_line = buffer.current_line();
+        self.line_offset = match position {
+            ViewPosition::Top => current_line,
+            ViewPosition::Center => current_line.saturating_sub((height - 1) / 2),
+            ViewPosition::Bottom => (current_line + 1).saturating_sub(height),
+        };
+    }
+
+    /// Scrolls the view as little as needed for the cursor line to be visible, along with
+    /// `scrolloff` lines above and below it
+    pub fn normalize(&mut self, ctx: &Context, buffer: &Buffer, window_dimensions: UDims) {
+        let height = self.view_height(ctx, buffer, window_dimensions).max(1);
+        let current_line = buffer.current_line();
+
+        // The lines around the cursor take at most half of the view, and there is no need to
+        // scroll past the end of the buffer to show them
+        let scrolloff = ctx.options.scrolloff.min((height - 1) / 2);
+        let below = scrolloff.min(buffer.total_lines().saturating_sub(current_line + 1));
 
         self.line_offset = self.line_offset.clamp(
-            current_line.saturating_sub(y.saturating_sub(1)),
-            current_line,
+            (current_line + below + 1).saturating_sub(height),
+            current_line.saturating_sub(scrolloff),
         );
+
+        if !ctx.options.word_wrap {
+            let width = self
+                .view_dimensions(ctx, buffer, window_dimensions)
+                .width
+                .max(1);
+            let column = buffer.cursor_column();
+            self.column_offset = self
+                .column_offset
+                .clamp((column + 1).saturating_sub(width), column);
+        }
     }
 }
 
+/// Where the `z` commands put the cursor line in the view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewPosition {
+    Top,
+    Center,
+    Bottom,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Flags(u16);
 
 impl Flags {
     const IS_TERMINAL: u8 = 0;
+    const IS_SEARCH: u8 = 1;
+    const IS_EXPLORER_PROMPT: u8 = 2;
 
-    pub fn empty() -> Self {
+    pub const fn empty() -> Self {
         Self(0)
     }
 
-    pub fn set_is_terminal(self) -> Self {
+    pub const fn set_is_terminal(self) -> Self {
         self.set(Self::IS_TERMINAL)
     }
 
-    pub fn is_terminal(&self) -> bool {
+    pub const fn is_terminal(self) -> bool {
         self.get(Self::IS_TERMINAL)
     }
 
-    fn set(&self, offs: u8) -> Self {
+    /// Marks a terminal buffer as the prompt of a search
+    pub const fn set_is_search(self) -> Self {
+        self.set(Self::IS_SEARCH)
+    }
+
+    pub const fn is_search(self) -> bool {
+        self.get(Self::IS_SEARCH)
+    }
+
+    /// Marks a terminal buffer as the prompt of a file explorer for a path
+    pub const fn set_is_explorer_prompt(self) -> Self {
+        self.set(Self::IS_EXPLORER_PROMPT)
+    }
+
+    pub const fn is_explorer_prompt(self) -> bool {
+        self.get(Self::IS_EXPLORER_PROMPT)
+    }
+
+    const fn set(self, offs: u8) -> Self {
         Self(self.0 | (1 << offs))
     }
 
-    fn get(&self, offs: u8) -> bool {
+    const fn get(self, offs: u8) -> bool {
         (self.0 & (1 << offs)) != 0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use edi_lib::{
+        buffer::Direction,
+        string::{GlobalPosition, LinePosition},
+    };
+    use edi_term::coord::Dimensions;
+
+    use super::*;
+
+    #[test]
+    fn scrolloff() {
+        let ctx = Context::new();
+        let dimensions = Dimensions::new(40, 10);
+        let text = "line\n".repeat(30);
+        let mut buffer = Buffer::new(&text);
+        let mut meta = BufferMeta::new(Mode::Normal);
+        assert_eq!(meta.view_height(&ctx, &buffer, dimensions), 10);
+
+        buffer.move_cursor(Direction::Down, 7);
+        meta.normalize(&ctx, &buffer, dimensions);
+        assert_eq!(meta.line_offset, 2);
+
+        buffer.move_cursor(Direction::Up, 3);
+        meta.normalize(&ctx, &buffer, dimensions);
+        assert_eq!(meta.line_offset, 0);
+
+        // Nothing past the end of the buffer is shown to keep lines below the cursor visible
+        buffer.move_global(GlobalPosition::End);
+        meta.normalize(&ctx, &buffer, dimensions);
+        assert_eq!(meta.line_offset, 20);
+
+        buffer.move_cursor(Direction::Up, 14);
+        meta.align(&ctx, &buffer, dimensions, ViewPosition::Center);
+        assert_eq!(meta.line_offset, 11);
+        meta.align(&ctx, &buffer, dimensions, ViewPosition::Top);
+        meta.normalize(&ctx, &buffer, dimensions);
+        assert_eq!(meta.line_offset, 11);
+        meta.align(&ctx, &buffer, dimensions, ViewPosition::Bottom);
+        meta.normalize(&ctx, &buffer, dimensions);
+        assert_eq!(meta.line_offset, 10);
+    }
+
+    #[test]
+    fn scroll_lines() {
+        let buffer = Buffer::new(&"line\n".repeat(30));
+        let mut meta = BufferMeta::new(Mode::Normal);
+
+        meta.scroll_lines(&buffer, 10, true, 5);
+        assert_eq!(meta.line_offset, 5);
+        meta.scroll_lines(&buffer, 10, true, 40);
+        assert_eq!(meta.line_offset, 20);
+        meta.scroll_lines(&buffer, 10, false, 8);
+        assert_eq!(meta.line_offset, 12);
+        meta.scroll_lines(&buffer, 10, false, 40);
+        assert_eq!(meta.line_offset, 0);
+    }
+
+    #[test]
+    fn sideways() {
+        let mut ctx = Context::new();
+        ctx.options.word_wrap = false;
+        ctx.options.line_numbers = false;
+        let dimensions = Dimensions::new(10, 5);
+        let mut buffer = Buffer::new(&"0123456789".repeat(3));
+        let mut meta = BufferMeta::new(Mode::Normal);
+
+        buffer.move_in_line(LinePosition::End);
+        meta.normalize(&ctx, &buffer, dimensions);
+        assert_eq!(meta.column_offset, 21);
+
+        meta.scroll_columns(&ctx, &mut buffer, dimensions, false, 15);
+        assert_eq!(meta.column_offset, 6);
+        assert_eq!(buffer.cursor_column(), 15);
+        meta.scroll_columns(&ctx, &mut buffer, dimensions, true, 12);
+        assert_eq!(buffer.cursor_column(), 18);
+
+        ctx.options.word_wrap = true;
+        meta.scroll_columns(&ctx, &mut buffer, dimensions, true, 1);
+        assert_eq!(meta.column_offset, 18);
+    }
+}
@@ -1,17 +1,38 @@
 pub mod action;
 pub mod buffer_bundle;
 pub mod buffers;
+pub mod command;
+pub mod command_history;
+pub mod completion;
+pub mod config;
 pub mod context;
+pub mod diagnostics;
+pub mod explorer;
+pub mod git;
+pub mod keymap;
+pub mod message;
 pub mod meta;
+pub mod pattern;
+pub mod registers;
+pub mod shell;
+pub mod sort;
+pub mod spell;
 pub mod state;
+pub mod substitute;
+#[cfg(test)]
+pub mod testing;
 
-use action::MoveAction;
+use std::time::Duration;
+
+use action::{Direction, MoveAction};
+use config::Config;
+use context::Context;
 use edi_frame::unit::Unit;
 use edi_lib::vec2::Vec2;
 use edi_term::{
-    coord::Coord,
+    coord::{Coord, UDims},
     escaping::{ANSIEscape, CursorStyle},
-    window::Window,
+    window::{char_width, Window},
 };
 use meta::BufferMeta;
 
@@ -24,24 +45,41 @@ use crate::{
     handlers, query,
 };
 
+/// How long to wait for the terminal to answer whether it supports synchronized output
+const SYNC_OUTPUT_PROBE_TIMEOUT: Duration = Duration::from_millis(100);
+/// How often the controller emits `Tick`, which the terminal is checked for being resized on
+const TICK_INTERVAL: Duration = Duration::from_millis(250);
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Mode {
     Normal,
     Insert,
     Terminal,
+    /// Asking whether to replace the highlighted match of a substitution
+    Confirm,
+    /// Waiting for the motion an operator applies to. Buffers are never in this mode, it only
+    /// selects the mappings the keys after an operator are looked up in
+    OperatorPending,
+    /// The normal mode of a file explorer, which has mappings of its own. Buffers are never in
+    /// this mode either
+    Explorer,
 }
 
 impl Mode {
-    pub fn as_str(&self) -> &'static str {
+    pub const fn as_str(self) -> &'static str {
         match self {
             Self::Normal => "normal",
             Self::Insert => "insert",
             Self::Terminal => "terminal",
+            Self::Confirm => "confirm",
+            Self::OperatorPending => "operator pending",
+            Self::Explorer => "explorer",
         }
     }
 }
 
 pub fn handle_move(
+    ctx: &Context,
     buffer: &mut emitter::buffer::Buffer,
     meta: &mut BufferMeta,
     action: &MoveAction,
@@ -51,51 +89,108 @@ pub fn handle_move(
         MoveAction::Regular(direction) => {
             buffer.move_cursor(direction.into(), repeat);
         }
+        MoveAction::FindChar { search, repeated } => buffer.find_char(search, repeat, repeated),
+        MoveAction::Word { position, big } => {
+            for _ in 0..repeat {
+                buffer.move_word(position, big);
+            }
+        }
         MoveAction::InLine(line_position) => {
             buffer.move_in_line(line_position);
         }
-        MoveAction::HalfScreen(direction) => {
-            let Ok(dimensions) = edi_term::get_size() else {
-                edi_lib::debug!("unable to get trminal dimensions");
+        MoveAction::HalfScreen(direction) | MoveAction::FullScreen(direction) => {
+            let Some(area) = buffers_area() else {
+                return;
+            };
+            let height = meta.view_height(ctx, buffer.as_ref(), area).max(1);
+            let lines = match action {
+                MoveAction::HalfScreen(_) => (height / 2).max(1),
+                _ => height.saturating_sub(2).max(1),
+            } * repeat;
+            let down = matches!(direction, Direction::Down);
+            meta.scroll_lines(buffer.as_ref(), height, down, lines);
+            buffer.move_cursor(direction.into(), lines);
+        }
+        MoveAction::DisplayLine(direction) if !ctx.options.word_wrap => {
+            buffer.move_cursor(direction.into(), repeat);
+        }
+        MoveAction::DisplayLine(direction) => {
+            let Some(area) = buffers_area() else {
                 return;
             };
-            let dimensions = dimensions.map(|v| v as usize);
-            buffer.move_cursor(direction.into(), meta.size.y.resolve(dimensions) / 2);
+            let width = meta.view_dimensions(ctx, buffer.as_ref(), area).width;
+            let indent = ctx.options.showbreak.chars().map(char_width).sum();
+            let down = matches!(direction, Direction::Down);
+            buffer.move_display_rows(down, repeat, width, indent);
         }
         MoveAction::Global(global_position) => buffer.move_global(global_position),
     }
 }
 
+/// Returns the size of the part of the terminal the buffers are drawn in
+fn buffers_area() -> Option<UDims> {
+    let Ok(dimensions) = edi_term::get_size() else {
+        edi_lib::debug!("unable to get trminal dimensions");
+        return None;
+    };
+    Some(message::buffers_area(dimensions.map(|v| v as usize)))
+}
+
 /// Runs the `edi` application, blocknig until receiving an error / close signal
-pub fn run(args: EdiCli) -> anyhow::Result<()> {
+pub fn run(args: EdiCli, config: Config) -> anyhow::Result<()> {
     let mut controller = Controller::new();
+    controller.set_tick_interval(TICK_INTERVAL);
 
     controller.attach_source(sources::input_source);
 
+    edi_term::install_panic_hook()?;
     edi_term::within_alternative_screen_mode(|| {
         let mut window = Window::new();
+        window.set_synchronized_output(edi_term::supports_synchronized_output(
+            SYNC_OUTPUT_PROBE_TIMEOUT,
+        ));
+        window.set_extended_underlines(edi_term::supports_extended_underlines());
 
         let size = edi_term::get_size()?.map(|v| v as usize);
 
         window.set_size(size);
         window.set_cursor(Coord::new(0, 0));
+        window.set_cursor_style(CursorStyle::Block);
         window.rerender()?;
 
         let mut state = State::new(window);
+        state.context.options = config.options;
+        state.mapper = config.mapper;
+        state.languages = config.languages;
+        state.context.options.backup |= args.backup;
+        if let Err(e) = state.load_spell() {
+            state.notify(message::Message::error(e));
+        }
+        controller.attach_source(state.watcher.clone());
+        if let Some(reader) = state.lsp.take_reader() {
+            controller.attach_source(reader);
+        }
+        if let Some(runner) = state.jobs.take_runner() {
+            controller.attach_source(runner);
+        }
 
-        if let Some(filepath) = args.edit_file {
-            state.open_file(filepath, Vec2::new(Unit::full_width(), Unit::full_height()))?;
+        for filepath in args.edit_files {
+            let id =
+                state.open_file(filepath, Vec2::new(Unit::full_width(), Unit::full_height()))?;
+            if args.read_only {
+                state.set_read_only(id);
+            }
         }
 
         init_handlers(&mut controller);
 
         controller.pipe_query(query::Payload::Draw(query::DrawQuery::Redraw));
 
-        let _ = controller.run(state);
+        let ran = controller.run(state);
 
         let _ = ANSIEscape::ChangeCursor(CursorStyle::Block).write_to_stdout();
 
-        Ok(())
+        ran.map(drop).map_err(|err| anyhow::anyhow!(err.summary()))
     })?
 }
 
@@ -121,6 +216,39 @@ pub fn init_handlers(controller: &mut Controller<State>) {
     let command_handler = handlers::command::Handler::new();
     controller.attach_query_handler(query::Type::Command, command_handler);
 
+    let registers_handler = handlers::registers::Handler::new();
+    controller.attach_query_handler(query::Type::Registers, registers_handler);
+
+    let search_handler = handlers::search::Handler::new();
+    controller.attach_query_handler(query::Type::Search, search_handler);
+
+    let operator_handler = handlers::operator::Handler::new();
+    controller.attach_query_handler(query::Type::Operator, operator_handler);
+
+    let substitute_handler = handlers::substitute::Handler::new();
+    controller.attach_query_handler(query::Type::Substitute, substitute_handler);
+
+    let lsp_handler = handlers::lsp::Handler::new();
+    controller.attach_query_handler(query::Type::Lsp, lsp_handler);
+
+    let diagnostics_handler = handlers::diagnostics::Handler::new();
+    controller.attach_query_handler(query::Type::Diagnostics, diagnostics_handler);
+
+    let completion_handler = handlers::completion::Handler::new();
+    controller.attach_query_handler(query::Type::Completion, completion_handler);
+
+    let spell_handler = handlers::spell::Handler::new();
+    controller.attach_query_handler(query::Type::Spell, spell_handler);
+
+    let explorer_handler = handlers::explorer::Handler::new();
+    controller.attach_query_handler(query::Type::Explorer, explorer_handler);
+
+    let git_handler = handlers::git::Handler::new();
+    controller.attach_query_handler(query::Type::Git, git_handler);
+
+    let shell_handler = handlers::shell::Handler::new();
+    controller.attach_query_handler(query::Type::Shell, shell_handler);
+
     let draw_handler = handlers::draw::Handler::new();
     controller.attach_query_handler(query::Type::Draw, draw_handler);
 }
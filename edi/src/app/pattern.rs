@@ -0,0 +1,169 @@
+use std::ops::Range;
+
+use edi_rope::Rope;
+use regex::{Regex, RegexBuilder};
+
+/// A regular expression matched against the text of a buffer. `^` and `$` match at the start
+/// and the end of each line
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    regex: Regex,
+    /// The pattern with its flags, for the streaming regex of `Rope`
+    source: String,
+}
+
+/// A match of a `Pattern` within a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    /// The characters of the line that matched
+    pub range: Range<usize>,
+    /// The text of the capture groups, the whole match first
+    pub groups: Vec<Option<String>>,
+}
+
+impl Pattern {
+    /// Compiles `pattern`, written in the syntax of the `regex` crate
+    pub fn new(pattern: &str, ignore_case: bool) -> Result<Self, regex::Error> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()?;
+        let flags = if ignore_case { "(?mi)" } else { "(?m)" };
+        let source = format!("{flags}{pattern}");
+        Ok(Self { regex, source })
+    }
+
+    /// Returns the first match in `line` that starts at or after the character `from`
+    pub fn find_in(&self, line: &str, from: usize) -> Option<Match> {
+        let from = byte_offset(line, from)?;
+        let captures = self.regex.captures_at(line, from)?;
+
+        let whole = captures.get(0)?;
+        let start = line[..whole.start()].chars().count();
+        let len = whole.as_str().chars().count();
+        let groups = captures
+            .iter()
+            .map(|group| group.map(|g| String::from(g.as_str())))
+            .collect();
+
+        Some(Match {
+            range: start..start + len,
+            groups,
+        })
+    }
+
+    /// Returns the character ranges of all the matches in `rope`, which may span several lines.
+    /// The rope is searched without collecting its text, unless the pattern has what its regex
+    /// does not support, such as Unicode word boundaries
+    pub fn find_all(&self, rope: &Rope) -> Vec<Range<usize>> {
+        rope.regex_find_iter(&self.source).map_or_else(
+            |e| {
+                edi_lib::debug!("searching the rope line by line: {e}");
+                self.find_all_in_lines(rope)
+            },
+            Iterator::collect,
+        )
+    }
+
+    /// Returns the character ranges of all the matches in `rope`, matching a line at a time
+    fn find_all_in_lines(&self, rope: &Rope) -> Vec<Range<usize>> {
+        let mut matches = Vec::new();
+        for line in rope.lines() {
+            let mut chars = 0;
+            let mut bytes = 0;
+            for m in self.regex.find_iter(&line.contents) {
+                chars += line.contents[bytes..m.start()].chars().count();
+                let len = m.as_str().chars().count();
+                let start = line.character_offset + chars;
+                matches.push(start..start + len);
+
+                chars += len;
+                bytes = m.end();
+            }
+        }
+
+        matches
+    }
+}
+
+/// Returns the byte offset of the character `n` of `s`, which may be the end of `s`
+fn byte_offset(s: &str, n: usize) -> Option<usize> {
+    s.char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(s.len()))
+        .nth(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find() {
+        let p = Pattern::new(r"(\w)(\d+)", false).unwrap();
+        let m = p.find_in("да a12 b3", 0).unwrap();
+        assert_eq!(m.range, 3..6);
+        assert_eq!(
+            m.groups,
+            [Some("a12".into()), Some("a".into()), Some("12".into())]
+        );
+        assert_eq!(p.find_in("да a12 b3", 4).unwrap().range, 4..6);
+        assert_eq!(p.find_in("да a12 b3", 6).unwrap().range, 7..9);
+        assert_eq!(p.find_in("да a12 b3", 20), None);
+
+        let p = Pattern::new("^x", false).unwrap();
+        assert_eq!(p.find_in("xx", 1), None);
+
+        let p = Pattern::new("ДА", true).unwrap();
+        assert_eq!(p.find_in("нет, да", 0).unwrap().range, 5..7);
+        assert!(Pattern::new("(", false).is_err());
+    }
+
+    #[test]
+    fn find_all() {
+        let rope = Rope::from("ab ab\nнет ab\n\nab");
+        let p = Pattern::new("ab", false).unwrap();
+        assert_eq!(p.find_all(&rope), [0..2, 3..5, 10..12, 14..16]);
+
+        let p = Pattern::new("^$|нет", false).unwrap();
+        assert_eq!(p.find_all(&rope), [6..9, 13..13]);
+        assert_eq!(p.find_all_in_lines(&rope), [6..9, 13..13]);
+
+        let p = Pattern::new(r"b\n", false).unwrap();
+        assert_eq!(p.find_all(&rope), [4..6, 11..13]);
+        let p = Pattern::new("AB$", true).unwrap();
+        assert_eq!(p.find_all(&rope), [3..5, 10..12, 14..16]);
+
+        // Unicode word boundaries are only supported line by line
+        let p = Pattern::new(r"\bab\b", false).unwrap();
+        assert!(rope.regex_find_iter(&p.source).is_err());
+        assert_eq!(p.find_all(&rope), [0..2, 3..5, 10..12, 14..16]);
+    }
+
+    #[test]
+    fn find_all_across_leaves() {
+        let text = "fn main() {\n    let мир = 1;\n}\n".repeat(200);
+        let rope = Rope::from_str_chunked(&text, 5);
+        assert!(rope.depth() >= 10);
+
+        let chars = |bytes: Range<usize>| {
+            let start = text[..bytes.start].chars().count();
+            start..start + text[bytes].chars().count()
+        };
+        // The matches span several leaves, and all but the first pattern line ends as well
+        for (pattern, count) in [
+            (r"main\(\) \{$", 200),
+            (r"мир = \d;\n\}\nfn", 199),
+            (r"^\}$\n^fn", 199),
+            (r"\{\s+let", 200),
+        ] {
+            let p = Pattern::new(pattern, false).unwrap();
+            let expected: Vec<_> = Regex::new(&p.source)
+                .unwrap()
+                .find_iter(&text)
+                .map(|m| chars(m.range()))
+                .collect();
+            assert_eq!(expected.len(), count, "{pattern}");
+            assert_eq!(p.find_all(&rope), expected, "{pattern}");
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::VecDeque;
+
+/// The number of numbered registers that keep deleted text, `"1` to `"9`
+const DELETE_HISTORY: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterKind {
+    /// A piece of text pasted at the cursor
+    Charwise,
+    /// Whole lines, each ending with a newline, pasted below or above the current line
+    Linewise,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    pub kind: RegisterKind,
+}
+
+impl Register {
+    pub fn charwise(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            kind: RegisterKind::Charwise,
+        }
+    }
+
+    /// Creates a linewise register, adding the trailing newline if `text` is missing it
+    pub fn linewise(text: impl Into<String>) -> Self {
+        let mut text = text.into();
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+
+        Self {
+            text,
+            kind: RegisterKind::Linewise,
+        }
+    }
+}
+
+/// Storage of yanked and deleted text
+///
+/// Registers are addressed by name:
+/// - `"` is the unnamed register, that holds the last yanked or deleted text
+/// - `a` to `z` are named registers, written with `A` to `Z` they get appended to
+/// - `0` holds the last yanked text
+/// - `1` to `9` hold the last deleted texts, the most recent one first
+#[derive(Debug, Default)]
+pub struct Registers {
+    unnamed: Option<Register>,
+    yanked: Option<Register>,
+    deleted: VecDeque<Register>,
+    named: [Option<Register>; 26],
+}
+
+impl Registers {
+    pub const UNNAMED: char = '"';
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether `name` refers to a register
+    pub const fn is_valid_name(name: char) -> bool {
+        matches!(name, '"' | 'a'..='z' | 'A'..='Z' | '0'..='9')
+    }
+
+    /// Returns the contents of the register with the given `name`
+    pub fn get(&self, name: char) -> Option<&Register> {
+        match name {
+            '"' => self.unnamed.as_ref(),
+            '0' => self.yanked.as_ref(),
+            '1'..='9' => self.deleted.get(name as usize - '1' as usize),
+            'a'..='z' | 'A'..='Z' => self.named[Self::named_index(name)].as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Stores yanked text in the register with the given `name`, or in `"0` if there is none
+    pub fn yank(&mut self, name: Option<char>, register: Register) {
+        match name {
+            None | Some('"' | '0') => {
+                self.yanked = Some(register.clone());
+                self.unnamed = Some(register);
+            }
+            Some(name) => self.set_named(name, register),
+        }
+    }
+
+    /// Stores deleted text in the register with the given `name`, shifting the numbered
+    /// registers if there is none
+    pub fn delete(&mut self, name: Option<char>, register: Register) {
+        match name {
+            None | Some('"') => {
+                self.deleted.push_front(register.clone());
+                self.deleted.truncate(DELETE_HISTORY);
+                self.unnamed = Some(register);
+            }
+            Some(name) => self.set_named(name, register),
+        }
+    }
+
+    /// Writes a named register, which the unnamed one then mirrors
+    fn set_named(&mut self, name: char, register: Register) {
+        if !name.is_ascii_alphabetic() {
+            edi_lib::debug!("register {name:?} cannot be written to");
+            return;
+        }
+
+        let slot = &mut self.named[Self::named_index(name)];
+        match slot {
+            Some(existing) if name.is_ascii_uppercase() => {
+                // Appending lines to a piece of text turns the whole register linewise
+                if register.kind == RegisterKind::Linewise
+                    && existing.kind == RegisterKind::Charwise
+                {
+                    *existing = Register::linewise(std::mem::take(&mut existing.text));
+                }
+                existing.text.push_str(&register.text);
+            }
+            _ => *slot = Some(register),
+        }
+        self.unnamed.clone_from(slot);
+    }
+
+    const fn named_index(name: char) -> usize {
+        name.to_ascii_lowercase() as usize - 'a' as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yank_and_delete() {
+        let mut r = Registers::new();
+
+        r.yank(None, Register::linewise("line"));
+        assert_eq!(r.get('0'), Some(&Register::linewise("line\n")));
+        assert_eq!(r.get('"'), r.get('0'));
+
+        for c in ["a", "b", "c"] {
+            r.delete(None, Register::charwise(c));
+        }
+        assert_eq!(r.get('1'), Some(&Register::charwise("c")));
+        assert_eq!(r.get('3'), Some(&Register::charwise("a")));
+        assert_eq!(r.get('4'), None);
+        assert_eq!(r.get('"'), Some(&Register::charwise("c")));
+        assert_eq!(r.get('0'), Some(&Register::linewise("line")));
+
+        for _ in 0..20 {
+            r.delete(None, Register::charwise("x"));
+        }
+        assert_eq!(r.get('9'), Some(&Register::charwise("x")));
+    }
+
+    #[test]
+    fn named() {
+        let mut r = Registers::new();
+
+        r.yank(Some('a'), Register::charwise("foo"));
+        r.yank(Some('A'), Register::charwise("bar"));
+        assert_eq!(r.get('a'), Some(&Register::charwise("foobar")));
+        assert_eq!(r.get('A'), r.get('a'));
+        assert_eq!(r.get('"'), Some(&Register::charwise("foobar")));
+        assert_eq!(r.get('0'), None);
+
+        r.delete(Some('A'), Register::linewise("baz"));
+        assert_eq!(r.get('a'), Some(&Register::linewise("foobar\nbaz\n")));
+        assert_eq!(r.get('1'), None);
+
+        r.yank(Some('a'), Register::charwise("new"));
+        assert_eq!(r.get('a'), Some(&Register::charwise("new")));
+        assert!(!Registers::is_valid_name('%'));
+        assert_eq!(r.get('%'), None);
+    }
+}
@@ -0,0 +1,111 @@
+//! Shell commands typed as `:!cmd`, `:r !cmd` and `:[range]!cmd`
+
+use std::ops::Range;
+
+use super::substitute::{self, Address};
+
+/// Where the output of a shell command goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// A scratch buffer of its own, `:!cmd`
+    Scratch,
+    /// Below the line with the cursor, `:r !cmd`
+    Read,
+    /// In place of the lines, which are the input of the command, `:[range]!cmd`
+    Filter { start: Address, end: Address },
+}
+
+/// A parsed shell command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellCommand {
+    pub command: String,
+    pub target: Target,
+}
+
+impl ShellCommand {
+    /// Parses a command without the leading `:`, returns `None` if it does not run a shell command
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_start();
+        let (start, end, rest) = substitute::parse_range(line)?;
+        let (target, rest) = if rest.len() != line.len() {
+            (Target::Filter { start, end }, rest)
+        } else if let Some(rest) = rest.strip_prefix("read").or_else(|| rest.strip_prefix('r')) {
+            (Target::Read, rest.trim_start())
+        } else {
+            (Target::Scratch, rest)
+        };
+
+        let command = rest.strip_prefix('!')?.trim();
+        if command.is_empty() {
+            return None;
+        }
+        Some(Self {
+            command: String::from(command),
+            target,
+        })
+    }
+}
+
+/// Returns the text with the lines in `lines` replaced by the lines of the output, which may end
+/// with a newline
+pub fn splice_lines(text: &str, lines: Range<usize>, output: &str) -> String {
+    let mut text_lines: Vec<&str> = text.split('\n').collect();
+    let end = lines.end.min(text_lines.len());
+    let start = lines.start.min(end);
+    let output = output.strip_suffix('\n').unwrap_or(output);
+    let output_lines = (!output.is_empty()).then(|| output.split('\n'));
+    text_lines.splice(start..end, output_lines.into_iter().flatten());
+    text_lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let parsed = ShellCommand::parse("!ls -l").unwrap();
+        assert_eq!(parsed.command, "ls -l");
+        assert_eq!(parsed.target, Target::Scratch);
+
+        let parsed = ShellCommand::parse("r !date").unwrap();
+        assert_eq!(
+            (parsed.command.as_str(), parsed.target),
+            ("date", Target::Read)
+        );
+        assert_eq!(
+            ShellCommand::parse("read!date").map(|parsed| parsed.target),
+            Some(Target::Read)
+        );
+
+        let parsed = ShellCommand::parse("%!sort").unwrap();
+        assert_eq!(
+            parsed.target,
+            Target::Filter {
+                start: Address::Line(1),
+                end: Address::Last
+            }
+        );
+        let parsed = ShellCommand::parse(".,5! tr a-z A-Z").unwrap();
+        assert_eq!(parsed.command, "tr a-z A-Z");
+        assert_eq!(
+            parsed.target,
+            Target::Filter {
+                start: Address::Current,
+                end: Address::Line(5)
+            }
+        );
+
+        assert_eq!(ShellCommand::parse("!"), None);
+        assert_eq!(ShellCommand::parse("w !sh"), None);
+        assert_eq!(ShellCommand::parse("s/a/b/"), None);
+    }
+
+    #[test]
+    fn splicing() {
+        assert_eq!(splice_lines("a\nb\nc", 1..2, "x\ny\n"), "a\nx\ny\nc");
+        assert_eq!(splice_lines("a\nb\nc", 3..3, "d\n"), "a\nb\nc\nd");
+        assert_eq!(splice_lines("a\nb\nc", 0..2, ""), "c");
+        assert_eq!(splice_lines("a", 1..1, "b"), "a\nb");
+    }
+}
@@ -0,0 +1,192 @@
+//! Sorting lines with `:[range]sort[!] [n][i][u]` and removing repeated ones with
+//! `:[range]uniq [i]`
+
+use std::{borrow::Cow, cmp::Reverse, ops::RangeInclusive};
+
+use super::substitute::{self, parse_range, Address};
+
+/// A parsed `:sort` or `:uniq` command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sort {
+    pub start: Address,
+    pub end: Address,
+    pub order: Order,
+    /// Sorts by the first decimal number in each line, lines without one come first, `n`
+    pub numeric: bool,
+    /// Compares the lines regardless of case, `i`
+    pub ignore_case: bool,
+    /// Keeps only the first of the equal lines that follow each other, `u`
+    pub unique: bool,
+}
+
+/// The order lines are put in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// The lines are kept in the order they are in, `:uniq`
+    Kept,
+    Ascending,
+    /// `:sort!`
+    Descending,
+}
+
+/// What lines are sorted by
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Key<'a> {
+    Number(Option<i64>),
+    Text(Cow<'a, str>),
+}
+
+impl Sort {
+    /// Parses a command without the leading `:`, returns `None` if it does not sort. Without a
+    /// range all the lines are sorted
+    pub fn parse(command: &str) -> Option<Self> {
+        let (start, end, rest) = parse_range(command)?;
+        let (start, end) = if rest.len() == command.len() {
+            (Address::Line(1), Address::Last)
+        } else {
+            (start, end)
+        };
+
+        let name_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let (name, rest) = rest.split_at(name_len);
+        let sort = if name.starts_with("sor") && "sort".starts_with(name) {
+            true
+        } else if name.starts_with("uni") && "uniq".starts_with(name) {
+            false
+        } else {
+            return None;
+        };
+        let (order, flags) = match rest.strip_prefix('!') {
+            Some(flags) if sort => (Order::Descending, flags),
+            Some(_) => return None,
+            None if sort => (Order::Ascending, rest),
+            None => (Order::Kept, rest),
+        };
+
+        let mut parsed = Self {
+            start,
+            end,
+            order,
+            numeric: false,
+            ignore_case: false,
+            unique: !sort,
+        };
+        for flag in flags.chars().filter(|c| !c.is_whitespace()) {
+            match flag {
+                'n' if sort => parsed.numeric = true,
+                'u' if sort => parsed.unique = true,
+                'i' => parsed.ignore_case = true,
+                _ => return None,
+            }
+        }
+
+        Some(parsed)
+    }
+
+    /// Returns the zero-indexed lines that are sorted
+    pub fn lines(&self, current_line: usize, total_lines: usize) -> RangeInclusive<usize> {
+        substitute::lines(self.start, self.end, current_line, total_lines)
+    }
+
+    /// Sorts the lines, the ones that compare equal are kept in the order they were in
+    pub fn apply<'a>(&self, mut lines: Vec<&'a str>) -> Vec<&'a str> {
+        match self.order {
+            Order::Kept => {}
+            Order::Ascending => lines.sort_by_cached_key(|line| self.key(line)),
+            Order::Descending => lines.sort_by_cached_key(|line| Reverse(self.key(line))),
+        }
+        if self.unique {
+            lines.dedup_by(|line, previous| {
+                if self.ignore_case {
+                    line.to_lowercase() == previous.to_lowercase()
+                } else {
+                    line == previous
+                }
+            });
+        }
+        lines
+    }
+
+    fn key<'a>(&self, line: &'a str) -> Key<'a> {
+        if self.numeric {
+            Key::Number(first_number(line))
+        } else if self.ignore_case {
+            Key::Text(Cow::Owned(line.to_lowercase()))
+        } else {
+            Key::Text(Cow::Borrowed(line))
+        }
+    }
+}
+
+/// Returns the first decimal number in the line, which is negative if there is a `-` before it.
+/// Numbers too large to fit are clamped
+fn first_number(line: &str) -> Option<i64> {
+    let start = line.find(|c: char| c.is_ascii_digit())?;
+    let digits = &line[start..];
+    let digits = &digits[..digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len())];
+    let negative = line[..start].ends_with('-');
+
+    let n = digits.parse::<i64>().unwrap_or(i64::MAX);
+    Some(if negative { -n } else { n })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let sort = Sort::parse("sort").unwrap();
+        assert_eq!(
+            (sort.start, sort.end, sort.order),
+            (Address::Line(1), Address::Last, Order::Ascending)
+        );
+        let sort = Sort::parse("2,$sor! n u").unwrap();
+        assert_eq!((sort.start, sort.end), (Address::Line(2), Address::Last));
+        assert_eq!(sort.order, Order::Descending);
+        assert!(sort.numeric && sort.unique && !sort.ignore_case);
+        let sort = Sort::parse(".sort!iu").unwrap();
+        assert_eq!((sort.start, sort.end), (Address::Current, Address::Current));
+        assert_eq!(sort.order, Order::Descending);
+        assert!(sort.ignore_case && sort.unique);
+
+        let uniq = Sort::parse("%uniq i").unwrap();
+        assert_eq!(uniq.order, Order::Kept);
+        assert!(uniq.unique && uniq.ignore_case);
+
+        assert_eq!(Sort::parse("so"), None);
+        assert_eq!(Sort::parse("sortn"), None);
+        assert_eq!(Sort::parse("sort x"), None);
+        assert_eq!(Sort::parse("uniq!"), None);
+        assert_eq!(Sort::parse("uniq n"), None);
+        assert_eq!(Sort::parse("s/a/b/"), None);
+    }
+
+    #[test]
+    fn sorting() {
+        let lines = vec!["b", "B", "a10", "a", "x-2", "a10", "c"];
+        let sort = Sort::parse("sort").unwrap();
+        assert_eq!(
+            sort.apply(lines.clone()),
+            ["B", "a", "a10", "a10", "b", "c", "x-2"]
+        );
+        let sort = Sort::parse("sort! iu").unwrap();
+        assert_eq!(sort.apply(lines.clone()), ["x-2", "c", "b", "a10", "a"]);
+        let sort = Sort::parse("sort n").unwrap();
+        assert_eq!(
+            sort.apply(lines.clone()),
+            ["b", "B", "a", "c", "x-2", "a10", "a10"]
+        );
+
+        let uniq = Sort::parse("uniq").unwrap();
+        assert_eq!(uniq.apply(vec!["a", "a", "b", "a"]), ["a", "b", "a"]);
+        let uniq = Sort::parse("uniq i").unwrap();
+        assert_eq!(uniq.apply(vec!["a", "A", "b"]), ["a", "b"]);
+        assert_eq!(first_number("v1.20"), Some(1));
+        assert_eq!(first_number("99999999999999999999"), Some(i64::MAX));
+    }
+}
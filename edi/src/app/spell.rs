@@ -0,0 +1,178 @@
+//! Checking the spelling of the buffers with the dictionary `spelllang` names
+
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use edi_lib::{
+    spell::Dictionary,
+    string::highlight::{Highlight, Type},
+};
+use edi_rope::Rope;
+
+/// The list of words used when there is no dictionary of the language
+const WORDS: &str = "/usr/share/dict/words";
+
+/// The dictionary of a language
+#[derive(Debug)]
+pub struct Spell {
+    /// The name of the language, as `spelllang` has it
+    pub lang: String,
+    pub dictionary: Dictionary,
+}
+
+impl Spell {
+    /// Loads the Hunspell dictionary of the language, `<lang>.dic` along with `<lang>.aff`, from
+    /// the first of the directories it is in. The list of words of the system is loaded if there
+    /// is none
+    pub fn load(lang: &str) -> Result<Self, String> {
+        let read = |path: &Path| {
+            std::fs::read(path)
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                .map_err(|e| format!("unable to read {}: {e}", path.display()))
+        };
+
+        let dic = dirs()
+            .into_iter()
+            .map(|dir| dir.join(format!("{lang}.dic")))
+            .find(|path| path.is_file());
+        let dictionary = match dic {
+            Some(dic) => {
+                let aff = dic.with_extension("aff");
+                let aff = if aff.is_file() {
+                    read(&aff)?
+                } else {
+                    String::new()
+                };
+                Dictionary::from_hunspell(&read(&dic)?, &aff)
+            }
+            None if Path::new(WORDS).is_file() => Dictionary::from_words(&read(Path::new(WORDS))?),
+            None => return Err(format!("no dictionary found for {lang}")),
+        };
+
+        Ok(Self {
+            lang: String::from(lang),
+            dictionary,
+        })
+    }
+}
+
+/// Returns the directories the dictionaries are looked for in, `$XDG_DATA_HOME/edi/spell` or
+/// `~/.local/share/edi/spell` first and then the ones of Hunspell
+fn dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+
+    data_home
+        .map(|dir| dir.join("edi").join("spell"))
+        .into_iter()
+        .chain(
+            [
+                "/usr/share/hunspell",
+                "/usr/share/myspell",
+                "/usr/share/myspell/dicts",
+            ]
+            .into_iter()
+            .map(PathBuf::from),
+        )
+        .collect()
+}
+
+/// Returns the character ranges of the misspelled words in the comments of the text, or in the
+/// whole of it if it is `prose`, sorted
+pub fn misspellings(
+    dictionary: &Dictionary,
+    text: &Rope,
+    highlights: &[Highlight],
+    prose: bool,
+) -> Vec<Range<usize>> {
+    if prose {
+        return dictionary.misspellings(text.chars(), 0);
+    }
+
+    let mut misspellings: Vec<Range<usize>> = highlights
+        .iter()
+        .filter(|highlight| highlight.ty == Type::Comment && highlight.start < text.len())
+        .flat_map(|highlight| {
+            let comment = text.chars_at(highlight.start).take(highlight.len);
+            dictionary.misspellings(comment, highlight.start)
+        })
+        .collect();
+    misspellings.sort_by_key(|range| range.start);
+    misspellings.dedup_by(|range, previous| range.start < previous.end);
+    misspellings
+}
+
+/// Returns the misspelled word after `offset`, or the last one before it if `reverse` is set,
+/// wrapping around the end of the buffer
+pub fn next(misspellings: &[Range<usize>], offset: usize, reverse: bool) -> Option<&Range<usize>> {
+    if reverse {
+        misspellings
+            .iter()
+            .rfind(|range| range.start < offset)
+            .or_else(|| misspellings.last())
+    } else {
+        misspellings
+            .iter()
+            .find(|range| range.start > offset)
+            .or_else(|| misspellings.first())
+    }
+}
+
+/// Returns the character range of the word at `offset`, without the apostrophes around it
+pub fn word_at(text: &Rope, offset: usize) -> Option<Range<usize>> {
+    let is_word_char = |c: char| c.is_alphabetic() || c == '\'';
+    let line_start = text.line_to_char(text.char_to_line(offset));
+    let before: Vec<char> = text
+        .chars_at(line_start)
+        .take(offset - line_start)
+        .collect();
+    let start = offset
+        - before
+            .iter()
+            .rev()
+            .take_while(|&&c| is_word_char(c))
+            .count();
+    let end = offset
+        + text
+            .chars_at(offset)
+            .take_while(|&c| is_word_char(c))
+            .count();
+
+    let word: String = text.chars_at(start).take(end - start).collect();
+    let leading = word.chars().take_while(|&c| c == '\'').count();
+    let len = word.trim_matches('\'').chars().count();
+    (len > 0).then(|| start + leading..start + leading + len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments() {
+        let dictionary = Dictionary::from_words("a\nlet\ncomment\n");
+        let text = Rope::from("let mispelled = 1; // a coment\n'tset'");
+        let highlights = [Highlight {
+            start: 19,
+            len: 18,
+            ty: Type::Comment,
+        }];
+        let found = misspellings(&dictionary, &text, &highlights, false);
+        assert_eq!(found, [24..30, 32..36]);
+        let found = misspellings(&dictionary, &text, &highlights, true);
+        assert_eq!(found, [4..13, 24..30, 32..36]);
+
+        assert_eq!(next(&found, 4, false), Some(&(24..30)));
+        assert_eq!(next(&found, 40, false), Some(&(4..13)));
+        assert_eq!(next(&found, 24, true), Some(&(4..13)));
+        assert_eq!(next(&found, 2, true), Some(&(32..36)));
+
+        assert_eq!(word_at(&text, 27), Some(24..30));
+        assert_eq!(word_at(&text, 31), Some(32..36));
+        assert_eq!(word_at(&text, 14), None);
+    }
+}
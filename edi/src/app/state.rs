@@ -1,17 +1,40 @@
 use edi_frame::unit::Unit;
 use edi_lib::{
-    brand::Id, buffer::Buffer, fs::filetype::Filetype, string::highlight::get_highlights,
+    brand::Id,
+    buffer::Buffer,
+    diagnostic::Diagnostic,
+    fs::{
+        read,
+        save::{ends_with_newline, is_writable},
+    },
+    language::Registry,
     vec2::Vec2,
 };
 use edi_term::window::Window;
 
 use crate::{
-    app::{action::InputMapper, context::Context, meta::BufferMeta, Mode},
-    controller::Handle,
-    event::emitter,
+    app::{
+        action::InputMapper,
+        buffer_bundle::BufferBundle,
+        context::Context,
+        diagnostics::{Diagnostics, Origin},
+        meta::BufferMeta,
+        spell::{self, Spell},
+        Mode,
+    },
+    controller::{Handle, Report},
+    error::AppError,
+    event::{emitter, sources::FileWatcher},
+    jobs::Jobs,
+    lsp,
 };
 
-use super::buffers::Buffers;
+use super::{
+    buffers::{Buffers, Selector},
+    command_history::CommandHistory,
+    message::{self, Message},
+    registers::Registers,
+};
 
 #[derive(Debug)]
 pub struct State {
@@ -21,6 +44,26 @@ pub struct State {
 
     pub mapper: InputMapper,
     pub buffers: Buffers,
+    pub registers: Registers,
+    pub command_history: CommandHistory,
+    /// The message shown in the message line
+    pub message: Option<Message>,
+    /// The messages shown so far, which `:messages` opens
+    pub message_history: message::History,
+    /// Lines shown in a popup under the cursor, until the next key is pressed like the message
+    pub popup: Vec<String>,
+    /// Looks for changes made to the open files by other programs
+    pub watcher: FileWatcher,
+    /// How the code of each filetype is written
+    pub languages: Registry,
+    /// The language servers of the open files
+    pub lsp: lsp::Client,
+    /// The problems found in the buffers
+    pub diagnostics: Diagnostics,
+    /// The processes running in the background
+    pub jobs: Jobs,
+    /// The dictionary the spelling is checked with, loaded once `spell` is set
+    pub spell: Option<Spell>,
 }
 
 impl State {
@@ -33,33 +76,173 @@ impl State {
             window,
             mapper: InputMapper::default(),
             buffers: Buffers::new(),
+            registers: Registers::new(),
+            command_history: CommandHistory::default_path()
+                .map(CommandHistory::load)
+                .unwrap_or_default(),
+            message: None,
+            message_history: message::History::default(),
+            popup: Vec::new(),
+            watcher: FileWatcher::new(),
+            languages: Registry::default(),
+            lsp: lsp::Client::new(),
+            diagnostics: Diagnostics::new(),
+            jobs: Jobs::new(),
+            spell: None,
         }
     }
 
-    /// Opens a file with the given path, appending it's contents to the leftmost buffer
+    /// Opens a file with the given path in a new buffer at the bottom, returning the buffer's id.
+    /// The buffer is read-only if the file cannot be written
     pub fn open_file(
         &mut self,
         filepath: impl AsRef<std::path::Path>,
         buff_dimensions: Vec2<Unit>,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<Id> {
         let filepath = filepath.as_ref();
-        let contents = std::fs::read_to_string(filepath)?;
-
-        let buffer = Buffer::new(&contents);
-        let filetype = Filetype::from(filepath);
+        let contents = read::read(filepath)?;
+        let mut buffer = Buffer::from_rope(contents.text);
+        buffer.tab_width = self.context.options.tab_width;
+        let language = self.languages.language(&self.languages.filetype(filepath));
 
-        let hl = get_highlights(&buffer.inner, &filetype);
-        let meta = BufferMeta::new(Mode::Normal)
+        let mut meta = BufferMeta::new(Mode::Normal)
             .with_filepath(Some(filepath.into()))
-            .with_filetype(filetype)
+            .with_language(&language)
             .with_size(buff_dimensions)
             .with_statusline(true)
-            .with_highlights(hl)
             .with_line_numbers(true);
+        let highlights = meta.highlighter.highlights(&buffer.inner);
+        meta.set_highlights(highlights);
+        meta.trailing_newline = ends_with_newline(&buffer.inner);
+        meta.line_ending = contents.line_ending;
+        meta.mark_saved(&buffer.inner);
+        if !contents.invalid.is_empty() {
+            self.notify(Message::warning(format!(
+                "\"{}\" has {} invalid utf-8 sequences, shown as \u{fffd}",
+                filepath.display(),
+                contents.invalid.len()
+            )));
+        }
+        meta.invalid_bytes = contents.invalid;
+        meta.read_only = !is_writable(filepath);
+        self.watcher.watch(filepath);
+        let text = buffer.inner.clone();
+
+        let id = self.buffers.attach(buffer, meta);
+        self.lsp.open(id, filepath, &language, &text);
+        Ok(id)
+    }
+
+    /// Makes the buffer of the file at the given path active, opening the file in a new buffer if
+    /// it is not open yet
+    pub fn edit_file(&mut self, filepath: &std::path::Path) -> anyhow::Result<Id> {
+        let open = self
+            .buffers
+            .iter()
+            .find(|bundle| bundle.meta().filepath.as_deref() == Some(filepath))
+            .map(BufferBundle::id);
+
+        let id = match open {
+            Some(id) => id,
+            None => self.open_file(filepath, Vec2::new(Unit::full_width(), Unit::full_height()))?,
+        };
+        self.buffers.set_active(id);
+        Ok(id)
+    }
+
+    /// Makes the buffer refuse changes
+    pub fn set_read_only(&mut self, id: Id) {
+        if let Some(bundle) = self.buffers.get_mut(&Selector::WithId(id)) {
+            bundle.meta_mut().read_only = true;
+        }
+    }
+
+    /// Replaces the diagnostics the origin reported for the buffer, along with the ones it is
+    /// drawn with
+    pub fn set_diagnostics(&mut self, buffer_id: Id, origin: Origin, diagnostics: Vec<Diagnostic>) {
+        self.diagnostics.set(buffer_id, origin, diagnostics);
+        if let Some(bundle) = self.buffers.get_mut(&Selector::WithId(buffer_id)) {
+            bundle
+                .meta_mut()
+                .set_diagnostics(self.diagnostics.of(buffer_id));
+        }
+    }
 
-        self.buffers.attach(buffer, meta);
+    /// Loads the dictionary `spelllang` names if `spell` is set and it is not loaded yet. The
+    /// misspelled words of the buffers are to be found again, as the options may have changed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no dictionary of the language, `spell` is unset then
+    pub fn load_spell(&mut self) -> Result<(), String> {
+        for bundle in self.buffers.iter_mut() {
+            bundle.meta_mut().misspellings = None;
+        }
+        let options = &mut self.context.options;
+        if !options.spell
+            || self
+                .spell
+                .as_ref()
+                .is_some_and(|spell| spell.lang == options.spell_lang)
+        {
+            return Ok(());
+        }
+
+        match Spell::load(&options.spell_lang) {
+            Ok(spell) => {
+                self.spell = Some(spell);
+                Ok(())
+            }
+            Err(e) => {
+                options.spell = false;
+                Err(e)
+            }
+        }
+    }
+
+    /// Finds the misspelled words of the buffer if `spell` is set and the buffer changed since
+    /// they were last found. The whole text is checked if its language has no comments
+    pub fn check_spelling(&mut self, id: Id) {
+        let Some(spell) = self.spell.as_ref().filter(|_| self.context.options.spell) else {
+            return;
+        };
+        let Some(bundle) = self.buffers.get_mut(&Selector::WithId(id)) else {
+            return;
+        };
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        if meta.misspellings.is_some() || meta.flags.is_terminal() || meta.explorer.is_some() {
+            return;
+        }
 
-        Ok(())
+        let language = self.languages.language(&meta.filetype);
+        let prose = language.line_comment.is_none() && language.block_comment.is_none();
+        meta.misspellings = Some(spell::misspellings(
+            &spell.dictionary,
+            &buffer.inner,
+            &meta.highlights,
+            prose,
+        ));
+    }
+
+    /// Shows the message in the message line, keeping it in the history
+    pub fn notify(&mut self, message: Message) {
+        self.message_history.push(message.clone());
+        self.message = Some(message);
+    }
+
+    /// Opens the text in a new scratch buffer with the given status, making it active
+    pub fn open_scratch(&mut self, status: String, text: &str) -> Id {
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let mut buffer = Buffer::new(text);
+        buffer.tab_width = self.context.options.tab_width;
+        let mut meta = BufferMeta::new(Mode::Normal)
+            .with_size(Vec2::new(Unit::full_width(), Unit::full_height()))
+            .with_statusline(true);
+        meta.status = status;
+
+        let id = self.buffers.attach(buffer, meta);
+        self.buffers.set_active(id);
+        id
     }
 
     pub fn within_active_buffer<F>(&mut self, mut f: F, ctrl: &mut Handle<State>)
@@ -73,3 +256,10 @@ impl State {
             .map(|(id, (buffer, meta))| f(id, buffer, meta));
     }
 }
+
+impl Report for State {
+    fn report(&mut self, error: &AppError, ctrl: &mut Handle<Self>) {
+        self.notify(Message::error(error.summary()));
+        ctrl.query_redraw();
+    }
+}
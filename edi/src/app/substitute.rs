@@ -0,0 +1,284 @@
+use std::ops::RangeInclusive;
+
+use super::pattern::{Match, Pattern};
+
+/// A line of the buffer a substitution starts or ends at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    /// A one-indexed line number
+    Line(usize),
+    /// The line with the cursor, `.`
+    Current,
+    /// The last line, `$`
+    Last,
+}
+
+/// A reply to the question whether to replace a match of a substitution with the `c` flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    /// Replaces the match and moves to the next one
+    Yes,
+    /// Skips the match
+    No,
+    /// Replaces the match and all the remaining ones
+    All,
+    /// Stops substituting
+    Quit,
+}
+
+/// Parsed `:[range]s/pattern/replacement/[flags]` command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitution {
+    pub start: Address,
+    pub end: Address,
+    /// A regular expression, matched within a single line
+    pub pattern: String,
+    pub replacement: Replacement,
+    /// Replaces every match in a line instead of the first one, `g`
+    pub global: bool,
+    /// Asks before replacing each match, `c`
+    pub confirm: bool,
+    /// Matches regardless of case, `i`
+    pub ignore_case: bool,
+}
+
+impl Substitution {
+    /// Parses a command without the leading `:`, returns `None` if it is not a substitution
+    pub fn parse(command: &str) -> Option<Self> {
+        let (start, end, rest) = parse_range(command)?;
+        let rest = rest
+            .strip_prefix("substitute")
+            .or_else(|| rest.strip_prefix('s'))?;
+
+        let delimiter = rest.chars().next()?;
+        if delimiter.is_alphanumeric() || delimiter.is_whitespace() || delimiter == '\\' {
+            return None;
+        }
+
+        let rest = &rest[delimiter.len_utf8()..];
+        let (pattern, rest) = split_unescaped(rest, delimiter);
+        let (replacement, flags) = rest.map_or((String::new(), None), |rest| {
+            split_unescaped(rest, delimiter)
+        });
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let mut substitution = Self {
+            start,
+            end,
+            pattern,
+            replacement: Replacement::parse(&replacement),
+            global: false,
+            confirm: false,
+            ignore_case: false,
+        };
+        for flag in flags.unwrap_or_default().trim_end().chars() {
+            match flag {
+                'g' => substitution.global = true,
+                'c' => substitution.confirm = true,
+                'i' => substitution.ignore_case = true,
+                _ => return None,
+            }
+        }
+
+        Some(substitution)
+    }
+
+    /// Returns the zero-indexed lines the substitution applies to
+    pub fn lines(&self, current_line: usize, total_lines: usize) -> RangeInclusive<usize> {
+        lines(self.start, self.end, current_line, total_lines)
+    }
+
+    /// Compiles the pattern to look for, ignoring the case of letters if the `i` flag is given or
+    /// `ignore_case` is set
+    pub fn compile(&self, ignore_case: bool) -> Result<Pattern, regex::Error> {
+        Pattern::new(&self.pattern, self.ignore_case || ignore_case)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    /// The capture group with the given index, the whole match being `0`
+    Group(usize),
+}
+
+/// The text matches are replaced with, which can refer to the capture groups of the match as
+/// `\0` to `\9`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replacement {
+    pieces: Vec<Piece>,
+}
+
+impl Replacement {
+    /// Parses the replacement, `\n` and `\t` stand for a newline and a tab, any other escaped
+    /// character stands for itself
+    pub fn parse(s: &str) -> Self {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                text.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('n') => text.push('\n'),
+                Some('t') => text.push('\t'),
+                Some(digit @ '0'..='9') => {
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Group(digit as usize - '0' as usize));
+                }
+                Some(other) => text.push(other),
+                None => text.push('\\'),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+
+        Self { pieces }
+    }
+
+    /// Returns the text to replace `m` with, groups that did not take part in the match expand to
+    /// nothing
+    pub fn expand(&self, m: &Match) -> String {
+        self.pieces
+            .iter()
+            .map(|piece| match *piece {
+                Piece::Text(ref text) => text.as_str(),
+                Piece::Group(i) => m.groups.get(i).and_then(Option::as_deref).unwrap_or(""),
+            })
+            .collect()
+    }
+}
+
+/// Returns the zero-indexed lines between the addresses
+pub fn lines(
+    start: Address,
+    end: Address,
+    current_line: usize,
+    total_lines: usize,
+) -> RangeInclusive<usize> {
+    let resolve = |address| match address {
+        Address::Line(n) => n.saturating_sub(1),
+        Address::Current => current_line,
+        Address::Last => total_lines.saturating_sub(1),
+    };
+
+    let (start, end) = (resolve(start), resolve(end));
+    start.min(end)..=start.max(end)
+}
+
+/// Splits off the range in front of the command, the current line if there is none
+pub fn parse_range(command: &str) -> Option<(Address, Address, &str)> {
+    if let Some(rest) = command.strip_prefix('%') {
+        return Some((Address::Line(1), Address::Last, rest));
+    }
+
+    let Some((start, rest)) = parse_address(command) else {
+        return Some((Address::Current, Address::Current, command));
+    };
+    let Some(rest) = rest.strip_prefix(',') else {
+        return Some((start, start, rest));
+    };
+    let (end, rest) = parse_address(rest)?;
+    Some((start, end, rest))
+}
+
+fn parse_address(s: &str) -> Option<(Address, &str)> {
+    if let Some(rest) = s.strip_prefix('.') {
+        return Some((Address::Current, rest));
+    }
+    if let Some(rest) = s.strip_prefix('$') {
+        return Some((Address::Last, rest));
+    }
+
+    let digits = s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let n = s[..digits].parse().ok()?;
+    Some((Address::Line(n), &s[digits..]))
+}
+
+/// Splits `s` at the first `delimiter` that is not escaped, dropping the backslashes that escape
+/// it. The second part is `None` if there is no delimiter
+fn split_unescaped(s: &str, delimiter: char) -> (String, Option<&str>) {
+    let mut part = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, next)) if next == delimiter => part.push(next),
+                Some((_, next)) => {
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => part.push('\\'),
+            },
+            c if c == delimiter => return (part, Some(&s[i + c.len_utf8()..])),
+            c => part.push(c),
+        }
+    }
+
+    (part, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let sub = Substitution::parse("%s/foo/bar/gi").unwrap();
+        assert_eq!((sub.start, sub.end), (Address::Line(1), Address::Last));
+        assert_eq!(sub.pattern, "foo");
+        assert_eq!(sub.replacement, Replacement::parse("bar"));
+        assert!(sub.global && sub.ignore_case && !sub.confirm);
+
+        let sub = Substitution::parse(r"2,$s#a\#b\d#c\nd#c").unwrap();
+        assert_eq!((sub.start, sub.end), (Address::Line(2), Address::Last));
+        assert_eq!(sub.pattern, r"a#b\d");
+        assert_eq!(sub.replacement, Replacement::parse("c\nd"));
+        assert!(sub.confirm && !sub.global);
+
+        let sub = Substitution::parse("s/x").unwrap();
+        assert_eq!((sub.start, sub.end), (Address::Current, Address::Current));
+        assert_eq!(sub.replacement, Replacement::default());
+
+        let sub = Substitution::parse(".,3substitute/a/b/").unwrap();
+        assert_eq!((sub.start, sub.end), (Address::Current, Address::Line(3)));
+
+        assert_eq!(Substitution::parse("q"), None);
+        assert_eq!(Substitution::parse("s//b/"), None);
+        assert_eq!(Substitution::parse("s/a/b/z"), None);
+        assert_eq!(Substitution::parse("1,s/a/b/"), None);
+        assert_eq!(Substitution::parse("sx"), None);
+    }
+
+    #[test]
+    fn lines() {
+        let sub = Substitution::parse("5,2s/ab/x/").unwrap();
+        assert_eq!(sub.lines(0, 10), 1..=4);
+        let sub = Substitution::parse("%s/ab/x/").unwrap();
+        assert_eq!(sub.lines(3, 10), 0..=9);
+        assert_eq!(sub.lines(0, 0), 0..=0);
+    }
+
+    #[test]
+    fn replacement() {
+        let sub = Substitution::parse(r"s/(\w+)=(\d)?/\2:\1\\1\t\0/i").unwrap();
+        let pattern = sub.compile(false).unwrap();
+
+        let m = pattern.find_in("Key=4", 0).unwrap();
+        assert_eq!(sub.replacement.expand(&m), "4:Key\\1\tKey=4");
+        let m = pattern.find_in("key=", 0).unwrap();
+        assert_eq!(sub.replacement.expand(&m), ":key\\1\tkey=");
+
+        let sub = Substitution::parse(r"s/a/\9/").unwrap();
+        let m = sub.compile(false).unwrap().find_in("a", 0).unwrap();
+        assert_eq!(sub.replacement.expand(&m), "");
+    }
+}
@@ -0,0 +1,156 @@
+//! An editor that runs without a terminal, for the tests of the handlers
+
+use std::{
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use edi_frame::unit::Unit;
+use edi_lib::{brand::Id, vec2::Vec2};
+use edi_term::{input::Input, window::Window};
+
+use crate::{
+    app::{
+        buffer_bundle::BufferBundle, buffers::Selector, command_history::CommandHistory,
+        state::State,
+    },
+    controller::{self, Controller, Handle},
+    error::Result,
+    event::{self, Event, Sender, Source},
+    query,
+};
+
+/// The handlers of the editor, but the one that draws, with a file open in the active buffer
+pub struct Editor {
+    pub controller: Controller<State>,
+    pub state: State,
+    /// The buffer of the file
+    pub id: Id,
+    pub path: PathBuf,
+    events: Arc<Mutex<Vec<event::Payload>>>,
+    /// The events of the jobs, once they are waited for
+    jobs: Option<mpsc::Receiver<event::Payload>>,
+}
+
+/// Keeps the payloads of all the events
+struct Recorder(Arc<Mutex<Vec<event::Payload>>>);
+
+impl controller::EventHandler<State> for Recorder {
+    fn handle(&mut self, _: &State, event: &Event, _: &mut Handle<State>) -> Result<()> {
+        self.0.lock().unwrap().push(event.payload().clone());
+        Ok(())
+    }
+}
+
+impl Editor {
+    /// Opens a file with `text` in a new temporary directory, `name` tells the directories of the
+    /// tests apart
+    pub fn new(name: &str, text: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("edi-editor-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        std::fs::write(&path, text).unwrap();
+
+        let mut state = State::new(Window::new());
+        state.command_history = CommandHistory::default();
+        let id = state
+            .open_file(&path, Vec2::new(Unit::full_width(), Unit::full_height()))
+            .unwrap();
+        state.buffers.set_active(id);
+
+        let mut controller = Controller::new();
+        super::init_handlers(&mut controller);
+        controller.detach_query_handler(query::Type::Draw);
+        let events = Arc::default();
+        controller.attach_event_handler(Recorder(Arc::clone(&events)));
+        Self {
+            controller,
+            state,
+            id,
+            path,
+            events,
+            jobs: None,
+        }
+    }
+
+    /// Runs the query and what it leads to, returns whether quitting was asked for
+    pub fn query(&mut self, payload: query::Payload) -> bool {
+        self.controller
+            .settle_query(&mut self.state, payload)
+            .unwrap()
+    }
+
+    /// Handles the input as if it was typed, returns whether quitting was asked for
+    pub fn input(&mut self, input: Input) -> bool {
+        self.controller
+            .settle_event(&mut self.state, event::Payload::Input(input))
+            .unwrap()
+    }
+
+    /// Types the keys one after another
+    pub fn keys(&mut self, keys: &str) {
+        for c in keys.chars() {
+            self.input(Input::Keypress(c));
+        }
+    }
+
+    /// Handles the events of the jobs until one of them exits, returns whether quitting was asked
+    /// for
+    pub fn wait_job(&mut self) -> bool {
+        let jobs = self.jobs.get_or_insert_with(|| {
+            let (tx, rx) = mpsc::channel();
+            let mut runner = self.state.jobs.take_runner().unwrap();
+            std::thread::spawn(move || runner.run(Sender::new(tx)));
+            rx
+        });
+        let mut quit = false;
+        loop {
+            let payload = jobs.recv_timeout(Duration::from_secs(10)).unwrap();
+            let exited = matches!(payload, event::Payload::JobExited { .. });
+            quit |= self
+                .controller
+                .settle_event(&mut self.state, payload)
+                .unwrap();
+            if exited {
+                return quit;
+            }
+        }
+    }
+
+    /// Types the command line, starting with `:`, and submits it. Returns whether quitting was
+    /// asked for
+    pub fn command(&mut self, line: &str) -> bool {
+        self.keys(line);
+        self.input(Input::Enter)
+    }
+
+    /// Returns the payloads of the events since the last call
+    pub fn take_events(&self) -> Vec<event::Payload> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+
+    pub fn bundle(&self) -> &BufferBundle {
+        self.state.buffers.get(&Selector::WithId(self.id)).unwrap()
+    }
+
+    pub fn text(&self) -> String {
+        self.bundle().buffer().inner.to_string()
+    }
+
+    /// Returns the text of the last message shown
+    pub fn message(&self) -> Option<&str> {
+        self.state
+            .message
+            .as_ref()
+            .map(|message| message.text.as_str())
+    }
+}
+
+impl Drop for Editor {
+    fn drop(&mut self) {
+        if let Some(dir) = self.path.parent() {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+}
@@ -4,7 +4,13 @@ use crate::error::{AppError, Result};
 
 #[derive(Debug)]
 pub struct EdiCli {
-    pub edit_file: Option<PathBuf>,
+    pub edit_files: Vec<PathBuf>,
+    /// Whether backups of the files are made when they are written
+    pub backup: bool,
+    /// The configuration file to read instead of the default one
+    pub config: Option<PathBuf>,
+    /// Whether the files are opened read-only, for viewing them
+    pub read_only: bool,
 }
 
 impl EdiCli {
@@ -14,19 +20,43 @@ impl EdiCli {
             AppError::unexpected("unable to read the application name, 0 arguments provided")
         })?;
 
-        let path_str = args.next();
-        let path = path_str.clone().map(PathBuf::from);
-
-        let is_file = path.as_ref().map(|p| p.is_file()) != Some(false);
-
-        if !is_file {
-            return Err(AppError::invalid_argument(f!(
-                "`{path}` does not exist or is a directory",
-                path = path_str.unwrap_or_default()
-            ))
-            .with_hint(f!("run `{program_path} <file_to_edit>`")));
+        let mut edit_files = Vec::new();
+        let mut backup = false;
+        let mut config = None;
+        let mut read_only = false;
+        while let Some(path_str) = args.next() {
+            if path_str == "--backup" {
+                backup = true;
+                continue;
+            }
+            if path_str == "-R" {
+                read_only = true;
+                continue;
+            }
+            if path_str == "--config" {
+                let path = args.next().ok_or_else(|| {
+                    AppError::invalid_argument("`--config` requires a path").with_hint(f!(
+                        "run `{program_path} --config <file> <files_to_edit>...`"
+                    ))
+                })?;
+                config = Some(PathBuf::from(path));
+                continue;
+            }
+            let path = PathBuf::from(&path_str);
+            if !path.is_file() {
+                return Err(AppError::invalid_argument(f!(
+                    "`{path_str}` does not exist or is a directory"
+                ))
+                .with_hint(f!("run `{program_path} <files_to_edit>...`")));
+            }
+            edit_files.push(path);
         }
 
-        Ok(Self { edit_file: path })
+        Ok(Self {
+            edit_files,
+            backup,
+            config,
+            read_only,
+        })
     }
 }
@@ -1,17 +1,26 @@
-use std::collections::{HashMap, VecDeque};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
 
 use edi_lib::brand::Id;
 use edi_term::input::Input;
 
 use crate::{
     app::{self, buffers::Selector},
+    error::{AppError, Result},
     event::{Event, Payload},
     query::{
-        self, CommandQuery, DrawQuery, HistoryQuery, MoveQuery, Query, SpawnQuery, Type, WriteQuery,
+        self, CommandQuery, CompletionQuery, DiagnosticsQuery, DrawQuery, ExplorerQuery, GitQuery,
+        HistoryQuery, LspQuery, MoveQuery, OperatorQuery, Priority, Query, RegistersQuery,
+        SearchQuery, ShellQuery, SpawnQuery, SpellQuery, SubstituteQuery, Type, WriteQuery,
     },
 };
 
-use super::handler;
+use super::{
+    handler,
+    timer::{TimerId, Timers},
+};
 
 // A handle to controller which allows to send queries and emit events
 pub struct Handle<State> {
@@ -21,6 +30,9 @@ pub struct Handle<State> {
 
     collected_events: VecDeque<Event>,
     collected_queries: VecDeque<Query>,
+    /// Queries with `Priority::Background`, run once no other queries or events are pending
+    background_queries: VecDeque<Query>,
+    timers: Timers,
 }
 
 impl<State> Handle<State> {
@@ -34,34 +46,49 @@ impl<State> Handle<State> {
 
             collected_events: VecDeque::new(),
             collected_queries: VecDeque::new(),
+            background_queries: VecDeque::new(),
+            timers: Timers::default(),
         }
     }
 
-    pub fn query(&mut self, state: &mut State, payload: query::Payload) {
+    pub fn query(&mut self, state: &mut State, payload: query::Payload) -> Result<()> {
         let query = Query::new(self.handler_id, payload);
 
-        self.run_query(state, query);
+        self.run_query(state, query)
     }
 
-    pub(super) fn run_query(&mut self, state: &mut State, query: query::Query) {
+    pub(super) fn run_query(&mut self, state: &mut State, query: query::Query) -> Result<()> {
         let prev_id = self.handler_id;
         let ty = query.ty();
 
         // TODO: the performance here sucks and we don't need a hash map anyway
         let Some((id, mut handler)) = self.query_handlers.remove(&ty) else {
-            edi_lib::debug!("no query handler found for query: {query:?}");
-            return;
+            return Err(AppError::unexpected(format!(
+                "no query handler found for query: {query:?}"
+            )));
         };
 
-        handler.handle(state, query, self.with_handler_id(id));
+        let handled = handler.handle(state, query, self.with_handler_id(id));
 
         self.query_handlers.insert(ty, (id, handler));
         self.handler_id = prev_id;
+        handled
     }
 
+    /// Submits the query to be run after the pending ones, unless a pending one already does what
+    /// it does
     pub(super) fn query_async(&mut self, payload: query::Payload) {
-        let query = Query::new(self.handler_id, payload);
-        self.collected_queries.push_back(query);
+        let queue = match payload.priority() {
+            Priority::Normal => &mut self.collected_queries,
+            Priority::Background => &mut self.background_queries,
+        };
+        if queue
+            .iter()
+            .any(|pending| payload.coalesces_with(pending.payload()))
+        {
+            return;
+        }
+        queue.push_back(Query::new(self.handler_id, payload));
     }
 
     pub(super) const fn with_handler_id(&mut self, id: Id) -> &mut Self {
@@ -69,6 +96,20 @@ impl<State> Handle<State> {
         self
     }
 
+    /// Returns whether a handler is attached for the queries of the type
+    #[cfg(test)]
+    pub(super) fn handles(&self, ty: Type) -> bool {
+        self.query_handlers.contains_key(&ty)
+    }
+
+    /// Gives back the query handlers, so another handle can be made with them
+    #[cfg(test)]
+    pub(super) fn into_query_handlers(
+        self,
+    ) -> HashMap<Type, (Id, Box<dyn handler::QueryHandler<State>>)> {
+        self.query_handlers
+    }
+
     pub(super) fn pop_event(&mut self) -> Option<Event> {
         self.collected_events.pop_front()
     }
@@ -77,6 +118,24 @@ impl<State> Handle<State> {
         self.collected_queries.pop_front()
     }
 
+    pub(super) fn pop_background_query(&mut self) -> Option<Query> {
+        self.background_queries.pop_front()
+    }
+
+    /// Removes the earliest event whose deadline has passed by `now`
+    pub(super) fn pop_elapsed_timer(&mut self, now: Instant) -> Option<Event> {
+        self.timers.pop_elapsed(now)
+    }
+
+    pub(super) fn next_deadline(&self) -> Option<Instant> {
+        self.timers.next_deadline()
+    }
+
+    /// Emits `Tick` every `interval`, or stops emitting it
+    pub(super) fn set_tick_interval(&mut self, interval: Option<Duration>) {
+        self.timers.set_tick_interval(interval, Instant::now());
+    }
+
     pub(super) fn check_event(&mut self, state: &mut State, event: &Event) {
         for ty in query::Type::all() {
             let Some((id, mut handler)) = self.query_handlers.remove(&ty) else {
@@ -96,6 +155,23 @@ impl<State> Handle<State> {
         self.collected_events.push_back(event);
     }
 
+    /// Emits the event once `delay` passes
+    pub fn add_event_after(&mut self, delay: Duration, payload: Payload) {
+        let event = Event::new(self.handler_id, payload);
+        self.timers.add(Instant::now() + delay, event);
+    }
+
+    /// Emits `Timeout` with the returned id once `delay` passes, unless it is cancelled before
+    pub fn set_timeout(&mut self, delay: Duration) -> TimerId {
+        let id = self.timers.next_id();
+        self.add_event_after(delay, Payload::Timeout(id));
+        id
+    }
+
+    pub fn cancel_timeout(&mut self, id: TimerId) {
+        self.timers.cancel(id);
+    }
+
     #[allow(unused)]
     pub fn add_input(&mut self, input: Input) {
         self.add_event(Payload::Input(input));
@@ -146,6 +222,50 @@ impl<State> Handle<State> {
         self.query_async(query::Payload::Command(query));
     }
 
+    pub fn query_registers(&mut self, query: RegistersQuery) {
+        self.query_async(query::Payload::Registers(query));
+    }
+
+    pub fn query_search(&mut self, query: SearchQuery) {
+        self.query_async(query::Payload::Search(query));
+    }
+
+    pub fn query_substitute(&mut self, query: SubstituteQuery) {
+        self.query_async(query::Payload::Substitute(query));
+    }
+
+    pub fn query_operator(&mut self, query: OperatorQuery) {
+        self.query_async(query::Payload::Operator(query));
+    }
+
+    pub fn query_lsp(&mut self, query: LspQuery) {
+        self.query_async(query::Payload::Lsp(query));
+    }
+
+    pub fn query_diagnostics(&mut self, query: DiagnosticsQuery) {
+        self.query_async(query::Payload::Diagnostics(query));
+    }
+
+    pub fn query_completion(&mut self, query: CompletionQuery) {
+        self.query_async(query::Payload::Completion(query));
+    }
+
+    pub fn query_spell(&mut self, query: SpellQuery) {
+        self.query_async(query::Payload::Spell(query));
+    }
+
+    pub fn query_explorer(&mut self, query: ExplorerQuery) {
+        self.query_async(query::Payload::Explorer(query));
+    }
+
+    pub fn query_git(&mut self, query: GitQuery) {
+        self.query_async(query::Payload::Git(query));
+    }
+
+    pub fn query_shell(&mut self, query: ShellQuery) {
+        self.query_async(query::Payload::Shell(query));
+    }
+
     pub fn query_redraw(&mut self) {
         self.query_async(query::Payload::Draw(DrawQuery::Redraw));
     }
@@ -158,3 +278,37 @@ impl<State> Handle<State> {
         self.query_async(query::Payload::Quit);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use edi_lib::brand::Tag;
+
+    use super::*;
+
+    #[test]
+    fn queries() {
+        let mut tag = Tag::new();
+        let (first, second) = (tag.child_id(), tag.child_id());
+        let mut ctrl = Handle::<()>::new(HashMap::new());
+        ctrl.query_redraw();
+        ctrl.query_draw(DrawQuery::Rehighlight(Selector::WithId(first)));
+        ctrl.query_quit();
+        ctrl.query_redraw();
+        ctrl.query_draw(DrawQuery::Rehighlight(Selector::WithId(second)));
+        ctrl.query_draw(DrawQuery::Rehighlight(Selector::WithId(first)));
+
+        assert!(ctrl.pop_query().is_some_and(|query| query.is_quit()));
+        assert!(ctrl.pop_query().is_none());
+        let background: Vec<_> = std::iter::from_fn(|| ctrl.pop_background_query())
+            .map(Query::into_payload)
+            .collect();
+        assert!(matches!(
+            background.as_slice(),
+            [
+                query::Payload::Draw(DrawQuery::Redraw),
+                query::Payload::Draw(DrawQuery::Rehighlight(Selector::WithId(a))),
+                query::Payload::Draw(DrawQuery::Rehighlight(Selector::WithId(b))),
+            ] if *a == first && *b == second
+        ));
+    }
+}
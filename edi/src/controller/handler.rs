@@ -1,11 +1,15 @@
 use edi_lib::brand::Id;
 
-use crate::{event::Event, query::Query};
+use crate::{
+    error::{AppError, Result},
+    event::Event,
+    query::Query,
+};
 
 use super::Handle;
 
 pub trait EventHandler<State> {
-    fn handle(&mut self, state: &State, event: &Event, ctrl: &mut Handle<State>);
+    fn handle(&mut self, state: &State, event: &Event, ctrl: &mut Handle<State>) -> Result<()>;
     fn interested_in(&self, own_id: Id, event: &Event) -> bool {
         let _ = (own_id, event);
         true
@@ -13,7 +17,7 @@ pub trait EventHandler<State> {
 }
 
 pub trait QueryHandler<State> {
-    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>);
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()>;
 
     fn check_event(&mut self, state: &State, event: &Event, ctrl: &mut Handle<State>) {
         let _ = (state, event, ctrl);
@@ -24,3 +28,9 @@ pub trait QueryHandler<State> {
         false
     }
 }
+
+/// The state the errors of handlers are shown in
+pub trait Report: Sized {
+    /// Shows the error of a handler that the editor goes on after
+    fn report(&mut self, error: &AppError, ctrl: &mut Handle<Self>);
+}
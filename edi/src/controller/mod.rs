@@ -1,20 +1,31 @@
 pub mod handle;
 pub mod handler;
+pub mod timer;
 
 pub use handle::Handle;
 pub use handler::EventHandler;
 pub use handler::QueryHandler;
+pub use handler::Report;
+pub use timer::{Debounce, TimerId};
 
-use std::{collections::HashMap, sync::mpsc};
+use std::{
+    collections::HashMap,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
 
 use edi_lib::brand::{Id, Tag};
 
 use crate::query;
 use crate::{
+    error::{AppErrorKind, Result},
     event::{self, source::SourcesHandle, Event},
     query::Type,
 };
 
+/// How many events that arrived are handled in a row before the timers that are due run
+const MAX_BATCH: usize = 256;
+
 pub struct Controller<State> {
     tag: Tag,
 
@@ -27,6 +38,8 @@ pub struct Controller<State> {
     query_handlers: HashMap<Type, (Id, Box<dyn handler::QueryHandler<State>>)>,
 
     piped_queries: Vec<query::Payload>,
+    /// How often `Tick` is emitted, if it is
+    tick_interval: Option<Duration>,
 }
 
 impl<State> Controller<State> {
@@ -46,6 +59,7 @@ impl<State> Controller<State> {
             query_handlers: HashMap::new(),
 
             piped_queries: Vec::new(),
+            tick_interval: None,
         }
     }
 
@@ -53,6 +67,11 @@ impl<State> Controller<State> {
         self.piped_queries.push(query);
     }
 
+    /// Emits `Tick` every `interval` while the controller runs
+    pub const fn set_tick_interval(&mut self, interval: Duration) {
+        self.tick_interval = Some(interval);
+    }
+
     pub fn attach_source<Src>(&mut self, source: Src)
     where
         Src: event::Source + Send + 'static,
@@ -76,7 +95,12 @@ impl<State> Controller<State> {
         self.query_handlers.insert(ty, (id, Box::new(handler)));
     }
 
-    pub fn run(mut self, mut state: State) -> SourcesHandle {
+    /// Handles the events and queries until a `Quit` query, or until a handler fails in a way the
+    /// editor can not go on after, whose error is returned
+    pub fn run(mut self, mut state: State) -> Result<SourcesHandle>
+    where
+        State: Report,
+    {
         let mut sources_handle = SourcesHandle::new(self.event_sources.len());
         let sources = std::mem::take(&mut self.event_sources);
         let mut piped_queries = std::mem::take(&mut self.piped_queries);
@@ -89,9 +113,12 @@ impl<State> Controller<State> {
         }
 
         let mut handle = Handle::new(std::mem::take(&mut self.query_handlers));
+        let mut batched = 0;
+        handle.set_tick_interval(self.tick_interval);
 
         while let Some(payload) = piped_queries.pop() {
-            handle.query(&mut state, payload);
+            let handled = handle.query(&mut state, payload);
+            Self::recover(&mut state, &mut handle, handled)?;
         }
 
         'outer: loop {
@@ -100,7 +127,8 @@ impl<State> Controller<State> {
                     break 'outer;
                 }
 
-                handle.run_query(&mut state, query);
+                let handled = handle.run_query(&mut state, query);
+                Self::recover(&mut state, &mut handle, handled)?;
                 continue 'outer;
             }
 
@@ -110,22 +138,87 @@ impl<State> Controller<State> {
                     &event,
                     &mut state,
                     &mut handle,
-                );
+                )?;
 
                 continue 'outer;
             }
 
-            if let Ok(event) = self.event_rx.recv() {
+            if let Some(query) = handle.pop_background_query() {
+                let handled = handle.run_query(&mut state, query);
+                Self::recover(&mut state, &mut handle, handled)?;
+                continue 'outer;
+            }
+
+            // Events that already arrived are handled before the timers that are due, so a burst
+            // of them such as a paste is drawn once it is handled rather than after every key. A
+            // long burst still lets the timers run after every `MAX_BATCH` events
+            if batched < MAX_BATCH {
+                if let Ok(event) = self.event_rx.try_recv() {
+                    batched += 1;
+                    Self::handle_event(
+                        self.event_handlers.iter_mut(),
+                        &Event::without_source(event),
+                        &mut state,
+                        &mut handle,
+                    )?;
+
+                    continue 'outer;
+                }
+            }
+            batched = 0;
+
+            if let Some(event) = handle.pop_elapsed_timer(Instant::now()) {
+                Self::handle_event(
+                    self.event_handlers.iter_mut(),
+                    &event,
+                    &mut state,
+                    &mut handle,
+                )?;
+
+                continue 'outer;
+            }
+
+            let received = match handle.next_deadline() {
+                Some(deadline) => self
+                    .event_rx
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    .ok(),
+                None => self.event_rx.recv().ok(),
+            };
+            if let Some(event) = received {
                 Self::handle_event(
                     self.event_handlers.iter_mut(),
                     &Event::without_source(event),
                     &mut state,
                     &mut handle,
-                );
+                )?;
             }
         }
 
-        sources_handle
+        Ok(sources_handle)
+    }
+
+    /// Shows the error of a handler and goes on, unless the editor can not go on after it, as
+    /// when the terminal can not be drawn to
+    fn recover(state: &mut State, ctrl: &mut Handle<State>, handled: Result<()>) -> Result<()>
+    where
+        State: Report,
+    {
+        let Err(error) = handled else {
+            return Ok(());
+        };
+        edi_lib::debug!("handler failed: {}", error.summary());
+
+        match error.kind {
+            AppErrorKind::TerminalIo => Err(error),
+            AppErrorKind::Io
+            | AppErrorKind::Unexpected
+            | AppErrorKind::InvalidArgument
+            | AppErrorKind::ReadOnly => {
+                state.report(&error, ctrl);
+                Ok(())
+            }
+        }
     }
 
     fn new_sender(&mut self) -> event::Sender {
@@ -137,7 +230,10 @@ impl<State> Controller<State> {
         event: &'a Event,
         state: &'a mut State,
         ctrl: &mut Handle<State>,
-    ) {
+    ) -> Result<()>
+    where
+        State: Report,
+    {
         ctrl.check_event(state, event);
 
         for (&id, handler) in handlers {
@@ -145,7 +241,103 @@ impl<State> Controller<State> {
                 continue;
             }
 
-            handler.handle(state, event, ctrl.with_handler_id(id));
+            let handled = handler.handle(state, event, ctrl.with_handler_id(id));
+            Self::recover(state, ctrl, handled)?;
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl<State: Report> Controller<State> {
+    /// Stops handling the queries of the type, such as the ones that draw to the terminal
+    pub fn detach_query_handler(&mut self, ty: query::Type) {
+        self.query_handlers.remove(&ty);
+    }
+
+    /// Runs the query and handles what it leads to as `run` would, without the event sources,
+    /// until nothing is left. Returns whether quitting was asked for
+    pub fn settle_query(&mut self, state: &mut State, payload: query::Payload) -> Result<bool> {
+        self.settle(state, |handle, state| handle.query(state, payload))
+    }
+
+    /// Handles the event as if a source sent it, and what it leads to, until nothing is left.
+    /// Returns whether quitting was asked for
+    pub fn settle_event(&mut self, state: &mut State, payload: event::Payload) -> Result<bool> {
+        self.settle(state, |handle, _| {
+            handle.add_event(payload);
+            Ok(())
+        })
+    }
+
+    fn settle(
+        &mut self,
+        state: &mut State,
+        start: impl FnOnce(&mut Handle<State>, &mut State) -> Result<()>,
+    ) -> Result<bool> {
+        let mut handle = Handle::new(std::mem::take(&mut self.query_handlers));
+        let started = start(&mut handle, state);
+        let settled = Self::recover(state, &mut handle, started)
+            .and_then(|()| self.handle_pending(state, &mut handle));
+        self.query_handlers = handle.into_query_handlers();
+        settled
+    }
+
+    /// Handles the pending queries and events, and the timers due by now. Queries no handler is
+    /// attached for are dropped
+    fn handle_pending(&mut self, state: &mut State, handle: &mut Handle<State>) -> Result<bool> {
+        let mut quit = false;
+        loop {
+            let query = handle.pop_query().or_else(|| handle.pop_background_query());
+            if let Some(query) = query {
+                quit |= query.is_quit();
+                if !query.is_quit() && handle.handles(query.ty()) {
+                    let handled = handle.run_query(state, query);
+                    Self::recover(state, handle, handled)?;
+                }
+                continue;
+            }
+
+            let Some(event) = handle
+                .pop_event()
+                .or_else(|| handle.pop_elapsed_timer(Instant::now()))
+            else {
+                return Ok(quit);
+            };
+            Self::handle_event(self.event_handlers.iter_mut(), &event, state, handle)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::AppError;
+
+    use super::*;
+
+    /// Keeps the errors reported to it
+    #[derive(Default)]
+    struct Reported(Vec<String>);
+
+    impl Report for Reported {
+        fn report(&mut self, error: &AppError, _ctrl: &mut Handle<Self>) {
+            self.0.push(error.summary());
+        }
+    }
+
+    #[test]
+    fn recover() {
+        let mut state = Reported::default();
+        let mut handle = Handle::new(HashMap::new());
+        let mut recover = |handled| Controller::recover(&mut state, &mut handle, handled);
+
+        assert!(recover(Ok(())).is_ok());
+        let failed = AppError::io("cannot read a.txt").with_cause(std::io::Error::other("gone"));
+        assert!(recover(Err(failed)).is_ok());
+        assert!(recover(Err(AppError::unexpected("a bug"))).is_ok());
+        let fatal = recover(Err(AppError::terminal_io("unable to draw")));
+        assert!(fatal.is_err_and(|error| error.summary() == "unable to draw"));
+
+        assert_eq!(state.0, ["cannot read a.txt: gone", "a bug"]);
     }
 }
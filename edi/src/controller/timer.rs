@@ -0,0 +1,158 @@
+use std::time::{Duration, Instant};
+
+use crate::event::{Event, Payload};
+
+use super::Handle;
+
+/// Identifies a timeout in the `Timeout` event it emits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(usize);
+
+/// The events the controller emits once their deadlines pass
+#[derive(Debug, Default)]
+pub(super) struct Timers {
+    /// Events to emit once their deadlines pass
+    pending: Vec<(Instant, Event)>,
+    next_id: usize,
+    /// How often `Tick` is emitted and when it is next, if it is
+    tick: Option<(Duration, Instant)>,
+}
+
+impl Timers {
+    pub fn add(&mut self, deadline: Instant, event: Event) {
+        self.pending.push((deadline, event));
+    }
+
+    pub const fn next_id(&mut self) -> TimerId {
+        self.next_id += 1;
+        TimerId(self.next_id)
+    }
+
+    /// Drops the timeout, whose event is not emitted then
+    pub fn cancel(&mut self, id: TimerId) {
+        self.pending.retain(
+            |(_, event)| !matches!(*event.payload(), Payload::Timeout(other) if other == id),
+        );
+    }
+
+    /// Emits `Tick` every `interval` from `now` on, or stops emitting it
+    pub fn set_tick_interval(&mut self, interval: Option<Duration>, now: Instant) {
+        self.tick = interval.map(|interval| (interval, now + interval));
+    }
+
+    /// Removes the earliest event whose deadline has passed by `now`. Ticks missed while the
+    /// controller was busy are emitted once
+    pub fn pop_elapsed(&mut self, now: Instant) -> Option<Event> {
+        let earliest = self
+            .pending
+            .iter()
+            .enumerate()
+            .filter(|(_, (deadline, _))| *deadline <= now)
+            .min_by_key(|(_, (deadline, _))| *deadline);
+
+        match (earliest, &mut self.tick) {
+            (Some((_, &(deadline, _))), Some((_, tick))) if *tick < deadline => {}
+            (Some((i, _)), _) => return Some(self.pending.swap_remove(i).1),
+            (None, _) => {}
+        }
+        let (interval, tick) = self.tick.as_mut().filter(|(_, tick)| *tick <= now)?;
+        *tick = now + *interval;
+        Some(Event::without_source(Payload::Tick))
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let pending = self.pending.iter().map(|&(deadline, _)| deadline);
+        pending.chain(self.tick.map(|(_, tick)| tick)).min()
+    }
+}
+
+/// Runs an action once the requests for it stop coming for a while, such as drawing once a burst
+/// of changes is over
+#[derive(Debug)]
+pub struct Debounce {
+    delay: Duration,
+    /// The timeout of the last request, after which the action runs
+    timer: Option<TimerId>,
+}
+
+impl Debounce {
+    /// A `delay` of zero runs the action once the events and queries pending are handled
+    pub const fn new(delay: Duration) -> Self {
+        Self { delay, timer: None }
+    }
+
+    /// Puts the action off until `delay` passes without another request
+    pub fn request<State>(&mut self, ctrl: &mut Handle<State>) {
+        if let Some(timer) = self.timer.take() {
+            ctrl.cancel_timeout(timer);
+        }
+        self.timer = Some(ctrl.set_timeout(self.delay));
+    }
+
+    /// Returns whether the action is due to run because of the timeout
+    pub fn is_due(&mut self, timer: TimerId) -> bool {
+        if self.timer != Some(timer) {
+            return false;
+        }
+        self.timer = None;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::event::Type;
+
+    #[test]
+    fn timers() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let popped = |timers: &mut Timers, now| timers.pop_elapsed(now).map(|e| e.ty());
+        let mut timers = Timers::default();
+        let first = timers.next_id();
+        let second = timers.next_id();
+        timers.add(at(20), Event::without_source(Payload::Timeout(second)));
+        timers.add(at(10), Event::without_source(Payload::Timeout(first)));
+        timers.set_tick_interval(Some(Duration::from_millis(15)), start);
+        assert_eq!(timers.next_deadline(), Some(at(10)));
+
+        assert!(popped(&mut timers, at(5)).is_none());
+        assert!(matches!(
+            timers.pop_elapsed(at(30)).map(|e| e.payload().clone()),
+            Some(Payload::Timeout(id)) if id == first
+        ));
+        assert_eq!(popped(&mut timers, at(30)), Some(Type::Tick));
+        assert!(matches!(
+            timers.pop_elapsed(at(30)).map(|e| e.payload().clone()),
+            Some(Payload::Timeout(id)) if id == second
+        ));
+        assert!(popped(&mut timers, at(30)).is_none());
+        assert_eq!(timers.next_deadline(), Some(at(45)));
+
+        let third = timers.next_id();
+        timers.add(at(50), Event::without_source(Payload::Timeout(third)));
+        timers.cancel(third);
+        timers.set_tick_interval(None, at(30));
+        assert_eq!(timers.next_deadline(), None);
+    }
+
+    #[test]
+    fn debounce() {
+        let mut ctrl = Handle::<()>::new(HashMap::new());
+        let mut debounce = Debounce::new(Duration::ZERO);
+        debounce.request(&mut ctrl);
+        debounce.request(&mut ctrl);
+
+        let mut due = 0;
+        while let Some(event) = ctrl.pop_elapsed_timer(Instant::now()) {
+            let Payload::Timeout(timer) = *event.payload() else {
+                panic!("unexpected event {event:?}");
+            };
+            due += usize::from(debounce.is_due(timer));
+        }
+        assert_eq!(due, 1);
+    }
+}
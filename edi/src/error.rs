@@ -28,6 +28,7 @@ pub enum AppErrorKind {
     TerminalIo,
     Unexpected,
     InvalidArgument,
+    ReadOnly,
 }
 
 impl AppErrorKind {
@@ -37,6 +38,7 @@ impl AppErrorKind {
             AppErrorKind::TerminalIo => "unable to perform i/o operation on the terminal",
             AppErrorKind::Unexpected => "unexpected error occurred",
             AppErrorKind::InvalidArgument => "invalid argument supplied",
+            AppErrorKind::ReadOnly => "the buffer is read-only",
         }
     }
 }
@@ -92,6 +94,15 @@ impl AppError {
         self
     }
 
+    /// Returns the message followed by its cause, without the styling of `build_error`
+    #[must_use]
+    pub fn summary(&self) -> String {
+        self.cause.as_ref().map_or_else(
+            || String::from(&*self.message),
+            |cause| format!("{}: {cause}", self.message),
+        )
+    }
+
     #[must_use]
     pub fn build_error(&self, opts: &DisplayOptions) -> String {
         let mut s = EscapeBuilder::new()
@@ -1,6 +1,9 @@
+use std::ops::Range;
+
 use edi_lib::{
-    brand::Id, buffer, buffer::Direction, string::position::GlobalPosition,
-    string::position::LinePosition,
+    brand::Id, buffer, buffer::Direction, string::highlight::Edit, string::position::CharSearch,
+    string::position::GlobalPosition, string::position::LinePosition,
+    string::position::WordPosition,
 };
 
 use crate::{app::state::State, controller::Handle, event::Payload};
@@ -20,40 +23,116 @@ macro_rules! proxy_method {
 pub struct Buffer<'a, 'b> {
     id: Id,
     inner: &'a mut buffer::Buffer,
+    /// The changes made since the highlights were last updated
+    edits: &'a mut Vec<Edit>,
     ctrl: &'b mut Handle<State>,
 }
 
 impl<'a, 'b> Buffer<'a, 'b> {
-    pub const fn new(id: Id, buf: &'a mut buffer::Buffer, ctrl: &'b mut Handle<State>) -> Self {
+    pub const fn new(
+        id: Id,
+        buf: &'a mut buffer::Buffer,
+        edits: &'a mut Vec<Edit>,
+        ctrl: &'b mut Handle<State>,
+    ) -> Self {
         Self {
             id,
             inner: buf,
+            edits,
             ctrl,
         }
     }
 
     pub fn write(&mut self, c: char) {
+        let offset = self.inner.cursor_offset;
         let write_event = Payload::CharWritten {
             buffer_id: self.id,
-            offset: self.inner.cursor_offset,
+            offset,
             c,
         };
         self.inner.write(c);
+        self.edits.push(Edit::insert(offset, 1));
         self.ctrl.add_event(write_event);
     }
 
-    pub fn delete(&mut self) {
+    /// Deletes the character before the cursor, returning it
+    pub fn delete(&mut self) -> Option<char> {
         let buffer_id = self.id;
         let offset = self.inner.cursor_offset;
-        let Some(deleted_char) = self.inner.delete() else {
-            return;
-        };
+        let deleted_char = self.inner.delete()?;
+        self.edits.push(Edit::remove(offset - 1, 1));
         let delete_event = Payload::CharDeleted {
             buffer_id,
             offset,
             c: deleted_char,
         };
         self.ctrl.add_event(delete_event);
+        Some(deleted_char)
+    }
+
+    /// Replaces the characters in `range` with `text` as a single edit, returning the removed
+    /// ones. The cursor is left after `text`
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) -> String {
+        let len = self.inner.inner.len();
+        let range = range.start.min(len)..range.end.clamp(range.start.min(len), len);
+        let removed = self.inner.replace(range.clone(), text);
+        let inserted = text.chars().count();
+        if range.is_empty() && text.is_empty() {
+            return removed;
+        }
+
+        self.edits.push(Edit {
+            offset: range.start,
+            removed: range.len(),
+            inserted,
+        });
+        self.ctrl.add_event(Payload::TextReplaced {
+            buffer_id: self.id,
+            offset: range.start,
+            removed: removed.clone(),
+            inserted: String::from(text),
+        });
+        removed
+    }
+
+    /// Writes `s` at the cursor position, leaving the cursor after it
+    pub fn write_str(&mut self, s: &str) {
+        let offset = self.inner.cursor_offset;
+        self.replace_range(offset..offset, s);
+    }
+
+    /// Deletes the characters in `range`, returning them. The cursor is left at the start of the
+    /// range
+    pub fn delete_range(&mut self, range: Range<usize>) -> String {
+        self.replace_range(range, "")
+    }
+
+    /// Replaces the text with `text`, replacing only the characters between the ones both of
+    /// them start and end with. The cursor is left after the written characters
+    pub fn replace_all(&mut self, text: &str) {
+        let current = &self.inner.inner;
+        let prefix = current
+            .chars()
+            .zip(text.chars())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let (current_len, len) = (current.len(), text.chars().count());
+        let suffix = current
+            .chars_rev()
+            .zip(text.chars().rev())
+            .take_while(|(a, b)| a == b)
+            .count()
+            .min(current_len.min(len) - prefix);
+
+        let start = text
+            .char_indices()
+            .nth(prefix)
+            .map_or(text.len(), |(i, _)| i);
+        let end = text
+            .char_indices()
+            .nth(len - suffix)
+            .map_or(text.len(), |(i, _)| i);
+        self.replace_range(prefix..current_len - suffix, &text[start..end]);
     }
 
     pub const fn set_cursor_offset(&mut self, cursor_offset: usize) {
@@ -63,6 +142,9 @@ impl<'a, 'b> Buffer<'a, 'b> {
     proxy_method!(fn move_cursor(&mut self, direction: Direction, steps: usize));
     proxy_method!(fn move_global(&mut self, position: GlobalPosition));
     proxy_method!(fn move_in_line(&mut self, position: LinePosition));
+    proxy_method!(fn move_word(&mut self, position: WordPosition, big: bool));
+    proxy_method!(fn find_char(&mut self, search: CharSearch, count: usize, repeated: bool));
+    proxy_method!(fn move_display_rows(&mut self, down: bool, steps: usize, width: usize, indent: usize));
 
     pub const fn ctrl(&mut self) -> &mut Handle<State> {
         self.ctrl
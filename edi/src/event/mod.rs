@@ -6,10 +6,16 @@ pub mod sources;
 pub use sender::Sender;
 pub use source::Source;
 
-use edi_lib::brand::Id;
+use std::path::PathBuf;
+
+use edi_lib::{brand::Id, fs::filetype::Filetype, json};
 use edi_term::input::Input;
 
-use crate::app::{self};
+use crate::{
+    app::{self},
+    controller::TimerId,
+    jobs::{JobId, Stream},
+};
 
 #[derive(Debug)]
 pub struct Event {
@@ -44,6 +50,14 @@ impl Event {
 #[derive(Debug, Clone)]
 pub enum Payload {
     Input(Input),
+    /// A key typed by a user-defined mapping
+    MappedInput {
+        input: Input,
+        /// Whether the key is looked up in the user-defined mappings too
+        remap: bool,
+        /// How many mappings were expanded to get to the key
+        depth: usize,
+    },
     ModeSwitched {
         buffer_id: Id,
         target_mode: app::Mode,
@@ -58,15 +72,80 @@ pub enum Payload {
         offset: usize,
         c: char,
     },
+    /// The characters `removed` starting at `offset` were replaced with `inserted`, as a single
+    /// edit
+    TextReplaced {
+        buffer_id: Id,
+        offset: usize,
+        removed: String,
+        inserted: String,
+    },
+    /// Changes to the buffer until `ChangeGroupEnded` are undone together
+    ChangeGroupStarted {
+        buffer_id: Id,
+    },
+    ChangeGroupEnded {
+        buffer_id: Id,
+    },
+    /// No input followed the keys of an unfinished sequence in time
+    SequenceTimedOut {
+        sequence: usize,
+    },
+    /// The file at `path` was modified by something other than the editor
+    FileChanged {
+        path: PathBuf,
+    },
+    /// The language server of the filetype sent a message
+    LspMessage {
+        filetype: Filetype,
+        message: json::Value,
+    },
+    /// The changes made to the buffer are due to be sent to its language server
+    LspSyncDue {
+        buffer_id: Id,
+    },
+    /// The blame shown in the buffer is due to be updated to its changed text
+    BlameDue {
+        buffer_id: Id,
+    },
+    /// The process of the job wrote a line
+    JobOutput {
+        job: JobId,
+        stream: Stream,
+        line: String,
+    },
+    /// The process of the job exited, after all of its output was emitted
+    JobExited {
+        job: JobId,
+        /// The exit code, `None` if the process was killed, as it is when the job is cancelled
+        status: Option<i32>,
+    },
+    /// Emitted by the controller at the interval it was set up with
+    Tick,
+    /// The delay of the timeout with the id has passed
+    Timeout(TimerId),
 }
 
 impl Payload {
     pub const fn ty(&self) -> Type {
         match self {
             Self::Input(_) => Type::Input,
+            Self::MappedInput { .. } => Type::MappedInput,
             Self::ModeSwitched { .. } => Type::ModeSwitched,
             Self::CharWritten { .. } => Type::CharWritten,
             Self::CharDeleted { .. } => Type::CharDeleted,
+            Self::TextReplaced { .. } => Type::TextReplaced,
+            Self::ChangeGroupStarted { .. } => Type::ChangeGroupStarted,
+            Self::ChangeGroupEnded { .. } => Type::ChangeGroupEnded,
+            Self::SequenceTimedOut { .. } => Type::SequenceTimedOut,
+            Self::FileChanged { .. } => Type::FileChanged,
+            Self::LspMessage { .. } => Type::LspMessage,
+            Self::LspSyncDue { .. } => Type::LspSyncDue,
+            Self::BlameDue { .. } => Type::BlameDue,
+            Self::JobOutput { .. } => Type::JobOutput,
+            Self::JobExited { .. } => Type::JobExited,
+            Self::Tick => Type::Tick,
+            Self::Timeout(_) => Type::Timeout,
         }
     }
 }
@@ -74,9 +153,22 @@ impl Payload {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Type {
     Input,
+    MappedInput,
     ModeSwitched,
     CharWritten,
     CharDeleted,
+    TextReplaced,
+    ChangeGroupStarted,
+    ChangeGroupEnded,
+    SequenceTimedOut,
+    FileChanged,
+    LspMessage,
+    LspSyncDue,
+    BlameDue,
+    JobOutput,
+    JobExited,
+    Tick,
+    Timeout,
 }
 
 impl Type {
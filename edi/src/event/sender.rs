@@ -2,6 +2,7 @@ use super::Payload;
 use edi_term::input::Input;
 use std::sync::mpsc;
 
+#[derive(Clone)]
 pub struct Sender {
     tx: mpsc::Sender<Payload>,
 }
@@ -1,50 +1,136 @@
-use std::io::Read;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
 
-use edi_term::input::Input;
+use edi_term::input::Parser;
 
-use crate::event::Sender;
+use crate::event::{Payload, Sender, Source};
 
 pub fn input_source(sender: &Sender) {
     let _span = edi_lib::span!("input");
 
-    let mut buf = [0_u8; 4];
     let mut stdin = std::io::stdin().lock();
+    let mut parser = Parser::new();
 
-    'outer: loop {
-        let n = match stdin.read(&mut buf) {
-            Ok(0) => continue,
-            Ok(n) => {
-                edi_lib::debug!("input: {:?}", &buf[..n]);
-                n
-            }
+    loop {
+        let inputs = match parser.read(&mut stdin) {
+            Ok(inputs) => inputs,
             Err(err) => {
                 edi_lib::debug!("error: {err}");
                 continue;
             }
         };
 
-        let total_bytes = n;
-        let mut chunk = &buf[..total_bytes];
-        while !chunk.is_empty() {
-            if chunk[0] != edi_term::input::ESCAPE || chunk.len() == 1 {
-                let input = Input::from_bytes(&chunk[..1]);
-                chunk = &chunk[1..];
+        for input in inputs {
+            edi_lib::debug!("input: {input:?}");
+            if !sender.send_input(input) {
+                return;
+            }
+        }
+    }
+}
 
-                if !sender.send_input(input) {
-                    break 'outer;
-                }
+/// The modification time and the size of a file, none if it cannot be read
+type Stamp = Option<(SystemTime, u64)>;
 
-                continue;
-            }
+fn stamp(path: &Path) -> Stamp {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
 
-            let input = Input::from_bytes(chunk);
-            chunk = &[];
+/// Polls the open files, emitting `FileChanged` when one of them is modified by something else.
+/// Clones of the watcher share the watched files
+#[derive(Debug, Clone, Default)]
+pub struct FileWatcher {
+    files: Arc<Mutex<HashMap<PathBuf, Stamp>>>,
+}
 
-            edi_lib::debug!("got non-zero input: {input:?}");
+impl FileWatcher {
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
 
-            if !sender.send_input(input) {
-                break 'outer;
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts watching the file at `path`. If it is watched already, its current state is the one
+    /// later changes are looked for from, which is how the editor's own writes are ignored
+    pub fn watch(&self, path: &Path) {
+        let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        files.insert(path.to_path_buf(), stamp(path));
+    }
+
+    pub fn unwatch(&self, path: &Path) {
+        let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        files.remove(path);
+    }
+
+    /// Returns the watched files that changed since they were last looked at. Files that were
+    /// removed are not reported until they appear again
+    fn poll(&self) -> Vec<PathBuf> {
+        let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        files
+            .iter_mut()
+            .filter_map(|(path, seen)| {
+                let current = stamp(path);
+                if current.is_none() || current == *seen {
+                    return None;
+                }
+                *seen = current;
+                Some(path.clone())
+            })
+            .collect()
+    }
+}
+
+impl Source for FileWatcher {
+    fn run(&mut self, sender: Sender) {
+        let _span = edi_lib::span!("watcher");
+
+        loop {
+            std::thread::sleep(Self::POLL_INTERVAL);
+            for path in self.poll() {
+                edi_lib::debug!("file changed: {path:?}");
+                if !sender.send_event(Payload::FileChanged { path }) {
+                    return;
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poll() {
+        let dir = std::env::temp_dir().join(format!("edi-watcher-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file");
+        std::fs::write(&path, "a").unwrap();
+
+        let watcher = FileWatcher::new();
+        watcher.watch(&path);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::write(&path, "ab").unwrap();
+        assert_eq!(watcher.poll(), std::slice::from_ref(&path));
+        assert!(watcher.poll().is_empty());
+
+        // The editor's own writes are taken in by watching the file again
+        std::fs::write(&path, "abc").unwrap();
+        watcher.watch(&path);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(watcher.poll().is_empty());
+        watcher.unwatch(&path);
+        std::fs::write(&path, "a").unwrap();
+        assert!(watcher.poll().is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
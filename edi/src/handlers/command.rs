@@ -1,13 +1,35 @@
-use std::{
-    fs::OpenOptions,
-    io::{BufWriter, Write},
-    path::PathBuf,
+use std::path::{Path, PathBuf};
+
+use edi_lib::{
+    brand::Id,
+    buffer::theme::Theme,
+    fs::{
+        line_ending, read,
+        save::{ends_with_newline, save, SaveOptions},
+    },
+    string::{diff::Change, GlobalPosition},
 };
 
 use crate::{
-    app::state::State,
+    app::{
+        buffer_bundle::BufferBundle,
+        buffers::{Buffers, Selector},
+        command::{Command, Completion, Kind},
+        context::Context,
+        message::Message,
+        meta::BufferMeta,
+        shell::ShellCommand,
+        sort::Sort,
+        state::State,
+        substitute::{Address, Substitution},
+    },
     controller::{self, Handle},
-    query::{CommandQuery, Payload, Query},
+    error::{AppError, AppErrorKind, Result},
+    event::{self, Event},
+    query::{
+        CommandQuery, DiagnosticsQuery, DrawQuery, ExplorerQuery, GitQuery, Payload, Query,
+        SearchQuery, ShellQuery, SubstituteQuery,
+    },
 };
 
 pub struct Handler;
@@ -19,76 +41,706 @@ impl Handler {
 }
 
 impl controller::QueryHandler<State> for Handler {
-    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) {
-        let _span = edi_lib::span!("write");
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
+        let _span = edi_lib::span!("command");
 
         let Payload::Command(command_query) = query.payload() else {
-            edi_lib::debug!(
-                "non-spawn query submitted to spawn query handler, this is likely a bug"
-            );
-            return;
+            return Err(AppError::unexpected(
+                "non-command query submitted to command query handler, this is likely a bug",
+            ));
         };
 
-        let CommandQuery { command } = command_query;
-        Self::handle_command(state, ctrl, command);
+        match command_query {
+            CommandQuery::Run(line) => Self::run(state, ctrl, line)?,
+            CommandQuery::Complete => Self::complete(state, ctrl),
+            &CommandQuery::Recall { older } => Self::recall(state, ctrl, older),
+            &CommandQuery::Modified { buffer_id } => {
+                Self::mark_modified(state, buffer_id);
+                return Ok(());
+            }
+            &CommandQuery::Reload { buffer_id, force } => {
+                Self::reload(state, ctrl, buffer_id, force)?;
+            }
+        }
 
         ctrl.query_redraw();
+        Ok(())
+    }
+
+    fn check_event(&mut self, state: &State, event: &Event, ctrl: &mut Handle<State>) {
+        let (event::Payload::CharWritten { buffer_id, .. }
+        | event::Payload::CharDeleted { buffer_id, .. }
+        | event::Payload::TextReplaced { buffer_id, .. }) = event.payload()
+        else {
+            if let event::Payload::FileChanged { path } = event.payload() {
+                Self::file_changed(state, ctrl, path);
+            }
+            return;
+        };
+
+        // Undoing the changes makes the buffer not modified again, so every change is checked
+        let is_file = state
+            .buffers
+            .get(&Selector::WithId(*buffer_id))
+            .is_some_and(|bundle| !bundle.meta().flags.is_terminal());
+        if is_file {
+            ctrl.query_command(CommandQuery::Modified {
+                buffer_id: *buffer_id,
+            });
+        }
+    }
+
+    fn interested_in(&self, own_id: Id, event: &Event) -> bool {
+        // Reloading a file does not make its buffer differ from it
+        if event.source_id().is_some_and(|id| id.eq(&own_id)) {
+            return false;
+        }
+
+        let types = &[
+            event::Type::CharWritten,
+            event::Type::CharDeleted,
+            event::Type::TextReplaced,
+            event::Type::FileChanged,
+        ];
+        event.ty().is_oneof(types)
     }
 }
 
 impl Handler {
-    fn handle_command(state: &mut State, ctrl: &mut Handle<State>, command: &str) {
-        if command == ":q" {
-            ctrl.query_quit();
+    fn run(state: &mut State, ctrl: &mut Handle<State>, line: &str) -> Result<()> {
+        let Some(line) = line.strip_prefix(':') else {
+            return Ok(());
+        };
+        state.command_history.push(line);
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+
+        if let Some(command) = ShellCommand::parse(line) {
+            let Some(bundle) = state.buffers.get(&Selector::Previous) else {
+                return Err(AppError::unexpected("no buffer to run the command from"));
+            };
+            ctrl.query_shell(ShellQuery::Run {
+                buffer: Selector::WithId(bundle.id()),
+                command,
+            });
+            return Ok(());
         }
-        if command == ":wq" {
-            let Some(bundle) = state.buffers.second() else {
-                edi_lib::fatal!("no buffer to write")
+        if let Some(substitution) = Substitution::parse(line) {
+            let Some(bundle) = state.buffers.get(&Selector::Previous) else {
+                return Err(AppError::unexpected("no buffer to substitute in"));
             };
-            let (b, meta) = bundle.as_split();
-
-            let swap_name = meta
-                .filepath
-                .as_ref()
-                .map_or(PathBuf::from("out.swp"), |fp| {
-                    let mut fp = fp.clone();
-                    fp.set_extension(".swp");
-                    fp
-                });
-
-            let file = match OpenOptions::new()
-                .write(true)
-                .truncate(true)
-                .create(true)
-                .open(&swap_name)
-            {
-                Ok(f) => f,
+            ctrl.query_substitute(SubstituteQuery::Run {
+                buffer: Selector::WithId(bundle.id()),
+                substitution,
+            });
+            return Ok(());
+        }
+        if let Some(sort) = Sort::parse(line) {
+            return Self::sort(state, ctrl, &sort);
+        }
+
+        let command =
+            Command::parse(line).map_err(|e| AppError::invalid_argument(e.to_string()))?;
+        match command.kind {
+            Kind::Edit => match command.args.first() {
+                Some(path) => Self::edit(state, path)?,
+                None => Self::edit_current(state, ctrl, command.bang)?,
+            },
+            Kind::View => Self::view(state, &command.args[0])?,
+            Kind::Write => {
+                let path = command.args.first().map(String::as_str);
+                Self::write(state, path, command.bang)?;
+            }
+            Kind::WriteAll => Self::write_all(state)?,
+            Kind::Quit => Self::quit(state, ctrl, command.bang),
+            Kind::WriteQuit => {
+                Self::write(state, None, command.bang)?;
+                Self::quit(state, ctrl, command.bang);
+            }
+            Kind::BufferNext => Self::cycle(state, true),
+            Kind::BufferPrevious => Self::cycle(state, false),
+            Kind::BufferDelete => Self::delete(state, command.bang),
+            Kind::Buffers => Self::list(state),
+            Kind::Messages => Self::messages(state),
+            Kind::NoHighlight => ctrl.query_search(SearchQuery::Clear),
+            Kind::Set => Self::set(state, &command.args),
+            Kind::Colorscheme => Self::colorscheme(state, command.args.first()),
+            Kind::Lint => ctrl.query_diagnostics(DiagnosticsQuery::Lint(command.args)),
+            Kind::Explore => {
+                let dir = command.args.first().map(PathBuf::from);
+                ctrl.query_explorer(ExplorerQuery::Show(dir));
+            }
+            Kind::GitBlame => ctrl.query_git(GitQuery::ToggleBlame),
+            Kind::GitPreviewHunk => ctrl.query_git(GitQuery::PreviewHunk),
+            Kind::GitRevertHunk => ctrl.query_git(GitQuery::RevertHunk),
+            Kind::Map { mode, remap } => {
+                state
+                    .mapper
+                    .add_user_mapping(mode, &command.args[0], &command.args[1], remap);
+            }
+            Kind::Line(address) => Self::go_to_line(state, ctrl, address),
+        }
+        Ok(())
+    }
+
+    /// Sorts the lines of the buffer the command line was opened from, as a single change
+    fn sort(state: &mut State, ctrl: &mut Handle<State>, sort: &Sort) -> Result<()> {
+        let Some(bundle) = state.buffers.get_mut(&Selector::Previous) else {
+            return Err(AppError::unexpected("no buffer to sort"));
+        };
+        bundle.meta().ensure_modifiable()?;
+
+        let text = bundle.buffer().inner.to_string();
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        let trailing_newline = text.ends_with('\n');
+        if trailing_newline {
+            lines.pop();
+        }
+        let range = sort.lines(bundle.buffer().current_line(), lines.len());
+        let end = (range.end() + 1).min(lines.len());
+        let start = (*range.start()).min(end);
+        let sorted = sort.apply(lines[start..end].to_vec());
+        lines.splice(start..end, sorted);
+
+        let mut sorted = lines.join("\n");
+        if trailing_newline {
+            sorted.push('\n');
+        }
+        if sorted != text {
+            let buffer_id = bundle.id();
+            ctrl.add_event(event::Payload::ChangeGroupStarted { buffer_id });
+            bundle.buffer_mut(ctrl).replace_all(&sorted);
+            ctrl.add_event(event::Payload::ChangeGroupEnded { buffer_id });
+        }
+
+        let (buffer, _) = bundle.as_split_mut_silent();
+        let line = start.min(buffer.total_lines().saturating_sub(1));
+        buffer.cursor_offset = buffer.inner.line_to_char(line);
+        Ok(())
+    }
+
+    /// Moves the cursor of the buffer the command line was opened from to the line, keeping its
+    /// column
+    fn go_to_line(state: &mut State, ctrl: &mut Handle<State>, address: Address) {
+        let Some(bundle) = state.buffers.get_mut(&Selector::Previous) else {
+            return;
+        };
+        let position = match address {
+            Address::Line(line) => GlobalPosition::Line(line.saturating_sub(1)),
+            Address::Current => GlobalPosition::Line(bundle.buffer().current_line()),
+            Address::Last => GlobalPosition::End,
+        };
+        bundle.buffer_mut(ctrl).move_global(position);
+    }
+
+    /// Returns the command line if it is the active buffer
+    fn prompt(buffers: &mut Buffers) -> Option<&mut BufferBundle> {
+        buffers.active_mut().filter(|bundle| {
+            let flags = bundle.meta().flags;
+            flags.is_terminal() && !flags.is_search() && !flags.is_explorer_prompt()
+        })
+    }
+
+    /// Replaces the text of the command line after the `:`
+    fn set_prompt(bundle: &mut BufferBundle, ctrl: &mut Handle<State>, line: &str) {
+        let mut buffer = bundle.buffer_mut(ctrl);
+        let len = buffer.as_ref().inner.len();
+        buffer.delete_range(1..len);
+        buffer.set_cursor_offset(1);
+        buffer.write_str(line);
+    }
+
+    fn complete(state: &mut State, ctrl: &mut Handle<State>) {
+        let Some(bundle) = Self::prompt(&mut state.buffers) else {
+            return;
+        };
+        let line: String = bundle.buffer().inner.chars().skip(1).collect();
+        let cwd = std::env::current_dir().unwrap_or_default();
+
+        let completion = Completion::of(&line, &cwd);
+        let Some(prefix) = completion.common_prefix() else {
+            return;
+        };
+        let completed = format!("{}{prefix}", &line[..completion.start]);
+        Self::set_prompt(bundle, ctrl, &completed);
+
+        if completion.candidates.len() > 1 {
+            state.notify(Message::info(completion.candidates.join("  ")));
+        }
+    }
+
+    fn recall(state: &mut State, ctrl: &mut Handle<State>, older: bool) {
+        let Some(bundle) = Self::prompt(&mut state.buffers) else {
+            return;
+        };
+        let line: String = bundle.buffer().inner.chars().skip(1).collect();
+
+        let history = &mut state.command_history;
+        let recalled = if older {
+            history.older(&line)
+        } else {
+            history.newer()
+        };
+        if let Some(recalled) = recalled {
+            Self::set_prompt(bundle, ctrl, &String::from(recalled));
+        }
+    }
+
+    /// Returns the ids of the buffers that are not prompts, in the order they were opened
+    fn files(state: &State) -> Vec<Id> {
+        state
+            .buffers
+            .iter()
+            .filter(|bundle| !bundle.meta().flags.is_terminal())
+            .map(BufferBundle::id)
+            .collect()
+    }
+
+    /// Makes the buffer of the file at `path` active, opening the file if it is not open yet
+    fn edit(state: &mut State, path: &str) -> Result<()> {
+        state
+            .edit_file(Path::new(path))
+            .map(drop)
+            .map_err(|e| AppError::io(format!("cannot open {path}: {e}")))
+    }
+
+    /// Opens the file at the path read-only
+    fn view(state: &mut State, path: &str) -> Result<()> {
+        let id = state
+            .edit_file(Path::new(path))
+            .map_err(|e| AppError::io(format!("cannot open {path}: {e}")))?;
+        state.set_read_only(id);
+        Ok(())
+    }
+
+    /// Reads the file of the current buffer again, discarding the changes if `force` is set
+    fn edit_current(state: &mut State, ctrl: &mut Handle<State>, force: bool) -> Result<()> {
+        let Some(bundle) = state.buffers.get(&Selector::Previous) else {
+            return Ok(());
+        };
+        if bundle.meta().modified && !force {
+            state.notify(Message::error(
+                "no write since last change (add ! to override)",
+            ));
+            return Ok(());
+        }
+
+        let id = bundle.id();
+        Self::reload(state, ctrl, id, true)
+    }
+
+    fn set(state: &mut State, args: &[String]) {
+        let mut shown = Vec::new();
+        for arg in args {
+            let set =
+                Self::set_file_format(state, arg).unwrap_or_else(|| state.context.options.set(arg));
+            match set {
+                Ok(value) => shown.extend(value),
                 Err(e) => {
-                    edi_lib::debug!("unable to create output file {e} {swap_name:?}");
-                    ctrl.query_quit();
-                    return;
+                    state.notify(Message::error(e));
+                    shown.clear();
+                    break;
                 }
-            };
+            }
+        }
+        if !shown.is_empty() {
+            state.notify(Message::info(shown.join("  ")));
+        }
+        if let Err(e) = state.load_spell() {
+            state.notify(Message::error(e));
+        }
+
+        let tab_width = state.context.options.tab_width;
+        for bundle in state.buffers.iter_mut() {
+            bundle.as_split_mut_silent().0.tab_width = tab_width;
+        }
+    }
+
+    /// Applies an argument of `:set` to `fileformat`, the line ending of the current buffer, if it
+    /// is about it. Changing the line ending leaves the buffer to be written
+    fn set_file_format(
+        state: &mut State,
+        arg: &str,
+    ) -> Option<std::result::Result<Option<String>, String>> {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.strip_suffix('?').unwrap_or(arg), None),
+        };
+        if !matches!(name, "fileformat" | "ff") {
+            return None;
+        }
+        let Some(bundle) = state.buffers.get_mut(&Selector::Previous) else {
+            return Some(Err(String::from("no buffer")));
+        };
+        let meta = bundle.meta_mut();
+        let Some(value) = value else {
+            return Some(Ok(Some(format!(
+                "{name}={}",
+                line_ending::name(meta.line_ending)
+            ))));
+        };
+        let Some(line_ending) = line_ending::parse(value) else {
+            return Some(Err(format!(
+                "invalid argument: {arg}, the formats are {}",
+                line_ending::NAMES.join(", ")
+            )));
+        };
+        if meta.line_ending != line_ending {
+            meta.line_ending = line_ending;
+            meta.modified = true;
+            meta.saved_checksum = None;
+        }
+        Some(Ok(None))
+    }
+
+    /// Switches to the color scheme built in under the `name`, or shows the name of the current
+    /// one if no name is given
+    fn colorscheme(state: &mut State, name: Option<&String>) {
+        let Some(name) = name else {
+            state.notify(Message::info(state.context.options.theme.name));
+            return;
+        };
+        match Theme::builtin(name) {
+            Some(theme) => state.context.options.theme = theme,
+            None => {
+                state.notify(Message::error(format!(
+                    "cannot find color scheme {name}, the schemes are {}",
+                    Theme::BUILTIN.join(", ")
+                )));
+            }
+        }
+    }
+
+    /// Makes the next buffer active, or the previous one if `forward` is not set
+    fn cycle(state: &mut State, forward: bool) {
+        let files = Self::files(state);
+        let Some(current) = state.buffers.get(&Selector::Previous).map(BufferBundle::id) else {
+            return;
+        };
+        let Some(position) = files.iter().position(|&id| id == current) else {
+            return;
+        };
+
+        let next = if forward {
+            (position + 1) % files.len()
+        } else {
+            (position + files.len() - 1) % files.len()
+        };
+        state.buffers.set_active(files[next]);
+    }
+
+    /// Closes the current buffer, the one focused before it becomes active. A buffer with changes
+    /// that were not written is only closed if `force` is set
+    fn delete(state: &mut State, force: bool) {
+        let Some(current) = state.buffers.get(&Selector::Previous) else {
+            return;
+        };
+        if current.meta().modified && !force {
+            state.notify(Message::error(
+                "no write since last change (add ! to override)",
+            ));
+            return;
+        }
+        let (current, filepath) = (current.id(), current.meta().filepath.clone());
+        if Self::files(state).len() <= 1 {
+            state.notify(Message::error("cannot close the last buffer"));
+            return;
+        }
 
-            let mut w = BufWriter::new(file);
-            b.inner.lines().for_each(|line| {
-                let Err(err) = w
-                    .write_all(line.contents.as_bytes())
-                    .and_then(|()| w.write_all(b"\n"))
-                else {
-                    return;
+        state.buffers.remove(current);
+        state.lsp.close(current);
+        state.diagnostics.remove(current);
+        if let Some(filepath) = filepath {
+            state.watcher.unwatch(&filepath);
+        }
+    }
+
+    /// Shows the open buffers in the message area, `%` marking the current one
+    fn list(state: &mut State) {
+        let current = state.buffers.get(&Selector::Previous).map(BufferBundle::id);
+        let list = state
+            .buffers
+            .iter()
+            .filter(|bundle| !bundle.meta().flags.is_terminal())
+            .enumerate()
+            .map(|(i, bundle)| {
+                let meta = bundle.meta();
+                let name = meta
+                    .filepath
+                    .as_ref()
+                    .or(meta.explorer.as_ref().map(|explorer| &explorer.root))
+                    .map_or(String::from("[No Name]"), |path| path.display().to_string());
+                let marker = if Some(bundle.id()) == current {
+                    "%"
+                } else {
+                    ""
                 };
-                edi_lib::debug!("unable to write line contents: {:?}", err);
+                format!("{n} {marker}\"{name}\"", n = i + 1)
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+
+        state.notify(Message::info(list));
+    }
+
+    /// Opens the message history in a scratch buffer
+    fn messages(state: &mut State) {
+        if state.message_history.is_empty() {
+            state.message = Some(Message::info("no messages"));
+            return;
+        }
+        let text = state.message_history.to_text();
+        state.open_scratch(String::from("messages"), &text);
+    }
+
+    /// Writes the current buffer to `path`, or to its own file if no path is given
+    fn write(state: &mut State, path: Option<&str>, force: bool) -> Result<()> {
+        let Some(bundle) = state.buffers.get_mut(&Selector::Previous) else {
+            return Err(AppError::unexpected("no buffer to write"));
+        };
+        bundle.apply_edits();
+        let (buffer, meta) = bundle.as_split();
+        if meta.read_only && path.is_none() && !force {
+            return Err(AppError::new(
+                "read-only buffer, add ! to override",
+                AppErrorKind::ReadOnly,
+            ));
+        }
+        let options = Self::save_options(&state.context, meta);
+        let target = match (path, &meta.filepath) {
+            (Some(path), _) => PathBuf::from(path),
+            (None, Some(filepath)) => filepath.clone(),
+            (None, None) => return Err(AppError::invalid_argument("no file name")),
+        };
+
+        save(&buffer.inner, &target, options).map_err(|e| {
+            AppError::io(format!("cannot write {}", target.display())).with_cause(e)
+        })?;
+
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        if meta.filepath.is_none() {
+            meta.filepath = Some(target.clone());
+        }
+        if meta.filepath.as_ref() == Some(&target) {
+            meta.mark_saved(&buffer.inner);
+            state.watcher.watch(&target);
+        }
+        state.notify(Message::info(format!("\"{}\" written", target.display())));
+        Ok(())
+    }
+
+    /// Writes every modified buffer to its file
+    fn write_all(state: &mut State) -> Result<()> {
+        let mut written = 0;
+        for bundle in state.buffers.iter_mut() {
+            let meta = bundle.meta();
+            let Some(filepath) = meta.filepath.clone().filter(|_| meta.modified) else {
+                continue;
+            };
+            bundle.apply_edits();
+            let meta = bundle.meta();
+
+            let options = Self::save_options(&state.context, meta);
+            save(&bundle.buffer().inner, &filepath, options).map_err(|e| {
+                AppError::io(format!("cannot write {}", filepath.display())).with_cause(e)
+            })?;
+            let (buffer, meta) = bundle.as_split_mut_silent();
+            meta.mark_saved(&buffer.inner);
+            state.watcher.watch(&filepath);
+            written += 1;
+        }
+
+        state.notify(Message::info(format!("{written} buffers written")));
+        Ok(())
+    }
+
+    /// Quits unless a buffer has changes that were not written, or `force` is set
+    fn quit(state: &mut State, ctrl: &mut Handle<State>, force: bool) {
+        let unsaved = state
+            .buffers
+            .iter()
+            .any(|bundle| bundle.meta().modified && !bundle.meta().flags.is_terminal());
+        if unsaved && !force {
+            state.notify(Message::error(
+                "no write since last change (add ! to override)",
+            ));
+            return;
+        }
+
+        ctrl.query_quit();
+    }
+
+    fn save_options<'a>(context: &Context, meta: &'a BufferMeta) -> SaveOptions<'a> {
+        SaveOptions::default()
+            .with_trailing_newline(meta.trailing_newline)
+            .with_line_ending(meta.line_ending)
+            .with_invalid_bytes(&meta.invalid_bytes)
+            .with_backup(context.options.backup)
+    }
+
+    /// Reloads the buffer of the file at `path`, which was changed by another program
+    fn file_changed(state: &State, ctrl: &mut Handle<State>, path: &Path) {
+        let changed = state
+            .buffers
+            .iter()
+            .find(|bundle| bundle.meta().filepath.as_deref() == Some(path));
+        if let Some(bundle) = changed {
+            ctrl.query_command(CommandQuery::Reload {
+                buffer_id: bundle.id(),
+                force: false,
             });
+        }
+    }
 
-            if let Err(e) = std::fs::rename(
-                swap_name,
-                meta.filepath.as_ref().unwrap_or(&PathBuf::from("out.txt")),
-            ) {
-                edi_lib::debug!("app::handle_event failed to rename file {e}");
-            }
+    /// Replaces the contents of the buffer with those of its file through the smallest change, so
+    /// the reload can be undone. If the buffer was modified and `force` is not set, it is kept and
+    /// the conflict is reported instead
+    fn reload(
+        state: &mut State,
+        ctrl: &mut Handle<State>,
+        buffer_id: Id,
+        force: bool,
+    ) -> Result<()> {
+        let Some(bundle) = state.buffers.get_mut(&Selector::WithId(buffer_id)) else {
+            return Ok(());
+        };
+        let Some(filepath) = bundle.meta().filepath.clone() else {
+            return Err(AppError::invalid_argument("no file name"));
+        };
+        let name = filepath.display();
+        if bundle.meta().modified && !force {
+            state.notify(Message::error(format!(
+                "\"{name}\" changed on disk, :e! discards the changes and loads it"
+            )));
+            return Ok(());
+        }
+
+        let contents = read::read(&filepath)
+            .map_err(|e| AppError::io(format!("cannot read {name}")).with_cause(e))?;
+        state.watcher.watch(&filepath);
 
-            ctrl.query_quit();
+        if let Some(change) = Change::between(&bundle.buffer().inner, &contents.text.to_string()) {
+            let cursor = change.map(bundle.buffer().cursor_offset);
+
+            ctrl.add_event(event::Payload::ChangeGroupStarted { buffer_id });
+            let mut buffer = bundle.buffer_mut(ctrl);
+            buffer.delete_range(change.range);
+            buffer.write_str(&change.text);
+            buffer.set_cursor_offset(cursor.min(buffer.as_ref().inner.len()));
+            ctrl.add_event(event::Payload::ChangeGroupEnded { buffer_id });
+            ctrl.query_draw(DrawQuery::Rehighlight(Selector::WithId(buffer_id)));
+        }
+
+        // The invalid bytes of the file are those of the new contents, not moved by the change
+        bundle.apply_edits();
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        meta.mark_saved(&buffer.inner);
+        meta.trailing_newline = ends_with_newline(&buffer.inner);
+        meta.line_ending = contents.line_ending;
+        meta.invalid_bytes = contents.invalid;
+        state.notify(Message::info(format!("\"{name}\" reloaded")));
+        Ok(())
+    }
+
+    fn mark_modified(state: &mut State, buffer_id: Id) {
+        if let Some(bundle) = state.buffers.get_mut(&Selector::WithId(buffer_id)) {
+            let (buffer, meta) = bundle.as_split_mut_silent();
+            meta.update_modified(&buffer.inner);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app::{buffers::Selector, testing::Editor},
+        event,
+        query::{HistoryQuery, Payload, WriteQuery},
+    };
+
+    const UNSAVED: &str = "no write since last change (add ! to override)";
+
+    #[test]
+    fn quit_with_changes() {
+        let mut editor = Editor::new("quit", "text\n");
+        assert!(!editor.bundle().meta().modified);
+        editor.query(Payload::Write(WriteQuery::WriteChar('a')));
+        assert!(editor.bundle().meta().modified);
+
+        assert!(!editor.command(":q"));
+        assert_eq!(editor.message(), Some(UNSAVED));
+        assert!(!editor.command(":e!"));
+        assert_eq!(editor.text(), "text\n");
+        assert!(!editor.bundle().meta().modified);
+
+        editor.query(Payload::Write(WriteQuery::WriteChar('a')));
+        assert!(editor.command(":q!"));
+    }
+
+    #[test]
+    fn write_clears_modified() {
+        let mut editor = Editor::new("write", "text\n");
+        editor.query(Payload::Write(WriteQuery::WriteChar('a')));
+        assert!(!editor.command(":w"));
+        assert!(!editor.bundle().meta().modified);
+        assert_eq!(std::fs::read_to_string(&editor.path).unwrap(), "atext\n");
+        assert!(editor.command(":q"));
+    }
+
+    #[test]
+    fn undo_to_saved_text() {
+        let mut editor = Editor::new("undo", "text\n");
+        editor.query(Payload::Write(WriteQuery::WriteChar('a')));
+        editor.query(Payload::History(HistoryQuery::Undo(Selector::Active)));
+        assert_eq!(editor.text(), "text\n");
+        assert!(!editor.bundle().meta().modified);
+        assert!(editor.command(":q"));
+
+        // The text written last is the one the buffer is compared with
+        let mut editor = Editor::new("undo-write", "text\n");
+        editor.query(Payload::Write(WriteQuery::WriteChar('a')));
+        editor.command(":w");
+        editor.query(Payload::History(HistoryQuery::Undo(Selector::Active)));
+        assert!(editor.bundle().meta().modified);
+        assert!(!editor.command(":q"));
+    }
+
+    #[test]
+    fn undo_sorted_lines() {
+        let mut editor = Editor::new("sort", "a\né\nc\nb\n");
+        assert!(!editor.command(":sort"));
+        assert_eq!(editor.text(), "a\nb\nc\né\n");
+
+        // Only the lines between the ones that stayed in place are replaced, at once
+        let id = editor.id;
+        let edits: Vec<_> = editor
+            .take_events()
+            .into_iter()
+            .filter_map(|payload| match payload {
+                event::Payload::TextReplaced {
+                    buffer_id,
+                    offset,
+                    removed,
+                    inserted,
+                } if buffer_id == id => Some((offset, removed, inserted)),
+                event::Payload::CharWritten { buffer_id, .. }
+                | event::Payload::CharDeleted { buffer_id, .. }
+                    if buffer_id == id =>
+                {
+                    panic!("the lines were not replaced at once: {payload:?}")
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            edits,
+            [(2, String::from("é\nc\nb"), String::from("b\nc\né"))]
+        );
+
+        editor.query(Payload::History(HistoryQuery::Undo(Selector::Active)));
+        assert_eq!(editor.text(), "a\né\nc\nb\n");
+        editor.query(Payload::History(HistoryQuery::Redo(Selector::Active)));
+        assert_eq!(editor.text(), "a\nb\nc\né\n");
+    }
+}
@@ -0,0 +1,175 @@
+use edi_lib::brand::Id;
+use edi_rope::Rope;
+
+use crate::{
+    app::{
+        buffers::Selector,
+        completion::{self, BufferWords, Candidate, Completion, Provider},
+        message::Message,
+        state::State,
+        Mode,
+    },
+    controller::{self, Handle},
+    error::{AppError, Result},
+    event::{self, Event},
+    query::{CompletionQuery, Payload, Query},
+};
+
+pub struct Handler {
+    /// Where the candidates come from, the ones of the providers earlier in the list first
+    providers: Vec<Box<dyn Provider>>,
+}
+
+impl Handler {
+    pub fn new() -> Self {
+        Self {
+            providers: vec![Box::new(BufferWords)],
+        }
+    }
+
+    /// Selects the next candidate, opening the menu of the candidates for the word before the
+    /// cursor if it is not open
+    fn select(&self, state: &mut State, reverse: bool) {
+        let Some(bundle) = state.buffers.active_mut() else {
+            return;
+        };
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        if let Some(completion) = &mut meta.completion {
+            completion.select_next(reverse);
+            return;
+        }
+
+        let cursor = buffer.cursor_offset;
+        let start = completion::word_start(&buffer.inner, cursor);
+        let prefix = completion::typed_prefix(&buffer.inner, start, cursor).unwrap_or_default();
+        let candidates = self.candidates(&buffer.inner, start, &prefix);
+        meta.completion = Completion::new(start, candidates, reverse);
+        if meta.completion.is_none() {
+            state.notify(Message::error("no completions found"));
+        }
+    }
+
+    /// Replaces the word being typed with the selected candidate. A misspelled word is replaced
+    /// as a single change, leaving the cursor at its start
+    fn accept(state: &mut State, ctrl: &mut Handle<State>) -> Result<()> {
+        let Some(bundle) = state.buffers.active_mut() else {
+            return Ok(());
+        };
+        let Some(completion) = bundle.meta_mut().completion.take() else {
+            return Ok(());
+        };
+        let Some(candidate) = completion.selected() else {
+            return Ok(());
+        };
+        bundle.meta().ensure_modifiable()?;
+
+        let buffer_id = bundle.id();
+        let Some(end) = completion.end else {
+            let mut buffer = bundle.buffer_mut(ctrl);
+            let cursor = buffer.as_ref().cursor_offset;
+            buffer.delete_range(completion.start..cursor);
+            buffer.write_str(&candidate.text);
+            return Ok(());
+        };
+
+        ctrl.add_event(event::Payload::ChangeGroupStarted { buffer_id });
+        let mut buffer = bundle.buffer_mut(ctrl);
+        buffer.delete_range(completion.start..end);
+        buffer.write_str(&candidate.text);
+        buffer.set_cursor_offset(completion.start);
+        ctrl.add_event(event::Payload::ChangeGroupEnded { buffer_id });
+        Ok(())
+    }
+
+    /// Looks for the candidates for the word being typed again, closing the menu if it is no
+    /// longer typed or there are none
+    fn update(&self, state: &mut State, buffer_id: Id) {
+        let Some(bundle) = state.buffers.get_mut(&Selector::WithId(buffer_id)) else {
+            return;
+        };
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        let Some(start) = meta.completion.as_ref().map(|completion| completion.start) else {
+            return;
+        };
+
+        let text = &buffer.inner;
+        meta.completion = completion::typed_prefix(text, start, buffer.cursor_offset)
+            .filter(|prefix| !prefix.is_empty())
+            .and_then(|prefix| {
+                Completion::new(start, self.candidates(text, start, &prefix), false)
+            });
+    }
+
+    /// Returns the candidates of every provider, leaving out the ones an earlier provider gave
+    fn candidates(&self, text: &Rope, start: usize, prefix: &str) -> Vec<Candidate> {
+        let mut candidates: Vec<Candidate> = Vec::new();
+        for provider in &self.providers {
+            for candidate in provider.candidates(text, start, prefix) {
+                if !candidates.iter().any(|other| other.text == candidate.text) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+        candidates
+    }
+}
+
+impl controller::QueryHandler<State> for Handler {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
+        let _span = edi_lib::span!("completion");
+
+        let Payload::Completion(completion_query) = query.into_payload() else {
+            return Err(AppError::unexpected(
+                "non-completion query submitted to completion query handler, this is likely a bug",
+            ));
+        };
+
+        match completion_query {
+            CompletionQuery::Select { reverse } => self.select(state, reverse),
+            CompletionQuery::Accept => Self::accept(state, ctrl)?,
+            CompletionQuery::Update { buffer_id } => self.update(state, buffer_id),
+            CompletionQuery::Close(selector) => {
+                if let Some(bundle) = state.buffers.get_mut(&selector) {
+                    bundle.meta_mut().completion = None;
+                }
+            }
+        }
+        ctrl.query_redraw();
+        Ok(())
+    }
+
+    fn check_event(&mut self, state: &State, event: &Event, ctrl: &mut Handle<State>) {
+        let (buffer_id, query) = match *event.payload() {
+            event::Payload::CharWritten { buffer_id, .. }
+            | event::Payload::CharDeleted { buffer_id, .. }
+            | event::Payload::TextReplaced { buffer_id, .. } => {
+                (buffer_id, CompletionQuery::Update { buffer_id })
+            }
+            event::Payload::ModeSwitched {
+                buffer_id,
+                target_mode,
+            } if target_mode != Mode::Insert => (
+                buffer_id,
+                CompletionQuery::Close(Selector::WithId(buffer_id)),
+            ),
+            _ => return,
+        };
+
+        let is_open = state
+            .buffers
+            .get(&Selector::WithId(buffer_id))
+            .is_some_and(|bundle| bundle.meta().completion.is_some());
+        if is_open {
+            ctrl.query_completion(query);
+        }
+    }
+
+    fn interested_in(&self, _own_id: Id, event: &Event) -> bool {
+        event.ty().is_oneof(&[
+            event::Type::CharWritten,
+            event::Type::CharDeleted,
+            event::Type::TextReplaced,
+            event::Type::ModeSwitched,
+        ])
+    }
+}
@@ -0,0 +1,173 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use edi_lib::{brand::Id, diagnostic::Severity};
+
+use crate::{
+    app::{
+        buffers::Selector,
+        diagnostics::{self, Origin},
+        message::Message,
+        state::State,
+    },
+    controller::{self, Handle},
+    error::{AppError, Result},
+    event::{self, Event},
+    jobs::{JobId, Spec, Stream},
+    query::{DiagnosticsQuery, Payload, Query},
+};
+
+/// A running linter, with what it wrote so far
+#[derive(Debug)]
+struct Lint {
+    buffer_id: Id,
+    path: PathBuf,
+    stdout: String,
+    stderr: String,
+}
+
+pub struct Handler {
+    /// The running linters by their jobs
+    lints: HashMap<JobId, Lint>,
+}
+
+impl Handler {
+    pub fn new() -> Self {
+        Self {
+            lints: HashMap::new(),
+        }
+    }
+}
+
+impl controller::QueryHandler<State> for Handler {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
+        let _span = edi_lib::span!("diagnostics");
+
+        let Payload::Diagnostics(diagnostics_query) = query.into_payload() else {
+            return Err(AppError::unexpected(
+                "non-diagnostics query submitted to diagnostics query handler, this is likely a bug",
+            ));
+        };
+
+        match diagnostics_query {
+            DiagnosticsQuery::Jump { reverse } => Self::jump(state, reverse),
+            DiagnosticsQuery::Lint(command) => self.lint(state, command),
+            DiagnosticsQuery::Linted { job } => self.linted(state, job),
+        }
+        ctrl.query_redraw();
+        Ok(())
+    }
+
+    fn check_event(&mut self, _state: &State, event: &Event, ctrl: &mut Handle<State>) {
+        match *event.payload() {
+            event::Payload::JobOutput {
+                job,
+                stream,
+                ref line,
+            } => {
+                let Some(lint) = self.lints.get_mut(&job) else {
+                    return;
+                };
+                let output = match stream {
+                    Stream::Stdout => &mut lint.stdout,
+                    Stream::Stderr => &mut lint.stderr,
+                };
+                output.push_str(line);
+                output.push('\n');
+            }
+            event::Payload::JobExited { job, .. } if self.lints.contains_key(&job) => {
+                ctrl.query_diagnostics(DiagnosticsQuery::Linted { job });
+            }
+            _ => {}
+        }
+    }
+
+    fn interested_in(&self, _own_id: Id, event: &Event) -> bool {
+        event
+            .ty()
+            .is_oneof(&[event::Type::JobOutput, event::Type::JobExited])
+    }
+}
+
+impl Handler {
+    /// Moves the cursor of the active buffer to the start of the next diagnostic after it, or the
+    /// previous one before it if `reverse` is set, and shows its message
+    fn jump(state: &mut State, reverse: bool) {
+        let Some(bundle) = state.buffers.active_mut() else {
+            return;
+        };
+        let cursor = bundle.buffer().cursor_offset;
+        let Some(diagnostic) = diagnostics::next(&bundle.meta().diagnostics, cursor, reverse)
+        else {
+            state.notify(Message::error("no diagnostics"));
+            return;
+        };
+        let message = match diagnostic.severity {
+            Severity::Error => Message::error(diagnostic.message.clone()),
+            Severity::Warning => Message::warning(diagnostic.message.clone()),
+            Severity::Info | Severity::Hint => Message::info(diagnostic.message.clone()),
+        };
+
+        let start = diagnostic.range.start;
+        let (buffer, _) = bundle.as_split_mut_silent();
+        buffer.cursor_offset = start.min(buffer.inner.len());
+        state.notify(message);
+    }
+
+    /// Starts the linter on the file of the active buffer
+    fn lint(&mut self, state: &mut State, mut command: Vec<String>) {
+        let Some(bundle) = state.buffers.active() else {
+            return;
+        };
+        let meta = bundle.meta();
+        let Some(path) = meta.filepath.clone() else {
+            state.notify(Message::error("no file name"));
+            return;
+        };
+        if meta.modified {
+            state.notify(Message::error("no write since last change"));
+            return;
+        }
+        if command.is_empty() {
+            command.clone_from(&state.languages.language(&meta.filetype).linter);
+        }
+        let Some((program, args)) = command.split_first() else {
+            state.notify(Message::error("no linter for the filetype"));
+            return;
+        };
+
+        let spec = Spec::new(program)
+            .with_args(args)
+            .with_args([path.to_string_lossy()]);
+        match state.jobs.spawn(spec) {
+            Ok(job) => {
+                let lint = Lint {
+                    buffer_id: bundle.id(),
+                    path,
+                    stdout: String::new(),
+                    stderr: String::new(),
+                };
+                self.lints.insert(job, lint);
+            }
+            Err(e) => state.notify(Message::error(format!("cannot run {program}: {e}"))),
+        }
+    }
+
+    /// Replaces the diagnostics the linter reported before with the ones it reported now
+    fn linted(&mut self, state: &mut State, job: JobId) {
+        let Some(lint) = self.lints.remove(&job) else {
+            return;
+        };
+        let Some(bundle) = state.buffers.get(&Selector::WithId(lint.buffer_id)) else {
+            return;
+        };
+        let output = lint.stdout + &lint.stderr;
+        let found = diagnostics::parse_lint(&output, &lint.path, &bundle.buffer().inner);
+
+        state.notify(match found.len() {
+            0 => Message::info("no problems found"),
+            1 => Message::info("1 problem found"),
+            n => Message::info(format!("{n} problems found")),
+        });
+        state.set_diagnostics(lint.buffer_id, Origin::Lint, found);
+    }
+}
@@ -1,22 +1,65 @@
-use edi_frame::rect::Rect;
-use edi_frame::{cell::Color, prelude::*};
-use edi_lib::string::highlight::get_highlights;
+use std::{collections::HashSet, time::Duration};
+
+use edi_frame::{
+    cell::Color,
+    prelude::*,
+    rect::Rect,
+    widget::{draw_line, Alignment, Menu, MenuItem, Span},
+};
+use edi_lib::brand::Id;
+use edi_term::coord::{Coord, UDims};
 use edi_term::escaping::ANSIColor;
+use edi_term::window::char_width;
 
 use crate::{
-    app::{buffers::Selector, state::State},
-    controller::{self, Handle},
+    app::{
+        buffer_bundle::BufferBundle,
+        buffers::Selector,
+        completion,
+        message::{self, Severity},
+        meta::ViewPosition,
+        state::State,
+    },
+    controller::{self, Debounce, Handle},
+    error::{AppError, Result},
+    event::{self, Event},
     query::{DrawQuery, Payload, Query},
 };
 
-pub struct Handler;
+pub struct Handler {
+    /// Draws once the queries and events pending are handled, so a burst of changes is drawn once
+    redraw: Debounce,
+    /// Updates the highlights of the buffers in `stale` once typing pauses
+    rehighlight: Debounce,
+    /// The buffers whose highlights are to be updated to their changed text
+    stale: HashSet<Id>,
+    /// The size of the terminal when it was last drawn to, it is drawn to again once it differs
+    dimensions: Option<UDims>,
+}
 
 impl Handler {
-    pub const fn new() -> Self {
-        Self
+    /// The layer of the window popups are drawn on, over the buffers
+    const POPUP_LAYER: usize = 1;
+    /// The most candidates the completion menu shows at once
+    const MENU_HEIGHT: usize = 10;
+    /// How long typing has to pause for before the changed buffers are highlighted again
+    const REHIGHLIGHT_DELAY: Duration = Duration::from_millis(30);
+
+    pub fn new() -> Self {
+        Self {
+            redraw: Debounce::new(Duration::ZERO),
+            rehighlight: Debounce::new(Self::REHIGHLIGHT_DELAY),
+            stale: HashSet::new(),
+            dimensions: None,
+        }
     }
 
-    fn redraw(state: &mut State, ctrl: &mut Handle<State>) {
+    fn redraw(&mut self, state: &mut State, ctrl: &mut Handle<State>) -> Result<()> {
+        // Of the buffers that are not prompts only the most recently focused one is shown
+        let displayed = state.buffers.displayed();
+        if let Some(id) = displayed {
+            state.check_spelling(id);
+        }
         let ctx = &state.context;
 
         edi_lib::debug!(
@@ -24,65 +67,259 @@ impl Handler {
             buffer_count = state.buffers.len()
         );
 
-        let Ok(dimensions) = edi_term::get_size() else {
-            edi_lib::debug!("unable to get trminal dimensions");
-            return;
+        let dimensions = Self::dimensions()?;
+        self.dimensions = Some(dimensions);
+        // A resized terminal may have moved or dropped what it showed, so it is drawn whole
+        let resized = state.window.size() != dimensions;
+        if resized {
+            state.window.set_size(dimensions);
+        }
+        // The buffers are laid out above the message line
+        let area = message::buffers_area(dimensions);
+
+        let is_shown = |bundle: &&mut BufferBundle| {
+            bundle.meta().flags.is_terminal() || Some(bundle.id()) == displayed
         };
-        let dimensions = dimensions.map(|v| v as usize);
 
         state.window.clear(ANSIColor::Reset);
-        state.buffers.iter_mut().rev().for_each(|bundle| {
-            let (buffer, meta) = bundle.as_split_mut(ctrl);
-            meta.normalize(ctx, buffer.as_ref(), dimensions);
-
-            let (offset_x, offset_y) = (
-                meta.offset.x.resolve(dimensions),
-                meta.offset.y.resolve(dimensions),
-            );
-            let (size_x, size_y) = (
-                meta.size.x.resolve(dimensions),
-                meta.size.y.resolve(dimensions),
-            );
-
-            let mut bound = Rect::new(offset_x, offset_y, size_x, size_y).bind(&mut state.window);
-            bound.clear(Color::None);
-
-            buffer
-                .as_ref()
-                .flush(&mut bound, &meta.updated_flush_options(ctx));
-        });
-
-        if let Err(err) = state.window.render() {
-            edi_lib::debug!("{err}");
-        }
+        state
+            .buffers
+            .iter_mut()
+            .rev()
+            .filter(is_shown)
+            .for_each(|bundle| {
+                let (buffer, meta) = bundle.as_split_mut(ctrl);
+                meta.normalize(ctx, buffer.as_ref(), area);
+
+                let mut bound = meta.rect_resolved(area).bind(&mut state.window);
+                bound.clear(Color::None);
+
+                buffer
+                    .as_ref()
+                    .flush(&mut bound, &meta.updated_flush_options(ctx));
+            });
+        state.window.clear_layer(Self::POPUP_LAYER);
+        Self::draw_completion(state, area);
+        Self::draw_popup(state, area);
+        Self::draw_message(state, dimensions);
+
+        let rendered = if resized {
+            state.window.rerender()
+        } else {
+            state.window.render()
+        };
+        rendered.map_err(|err| AppError::terminal_io("unable to draw").with_cause(err))
     }
 
-    fn rehighlight(state: &mut State, ctrl: &mut Handle<State>, selector: &Selector) {
-        let _span = edi_lib::span!("rehighlight");
+    fn dimensions() -> Result<UDims> {
+        let dimensions = edi_term::get_size().map_err(|err| {
+            AppError::terminal_io("unable to get terminal dimensions").with_cause(err)
+        })?;
+        Ok(dimensions.map(|v| v as usize))
+    }
+
+    /// Draws the menu of the candidates for the word being typed below it, or above it if there is
+    /// more room there
+    fn draw_completion(state: &mut State, dimensions: UDims) {
+        let Some(bundle) = state.buffers.active() else {
+            return;
+        };
+        let Some(completion) = &bundle.meta().completion else {
+            return;
+        };
+
+        let buffer = bundle.buffer();
+        let typed = completion::typed_prefix(&buffer.inner, completion.start, buffer.cursor_offset)
+            .unwrap_or_default();
+        let cursor = state.window.cursor();
+        let anchor = Coord::new(
+            cursor.x.saturating_sub(typed.chars().map(char_width).sum()),
+            cursor.y,
+        );
+
+        let items = completion
+            .candidates
+            .iter()
+            .map(|candidate| MenuItem::new(&candidate.text).with_detail(&candidate.kind))
+            .collect();
+        let theme = state.context.options.drawn_theme();
+        let menu = Menu::new(items, Some(completion.selected))
+            .with_colors(theme.menu.fg, theme.menu.bg)
+            .with_selected_colors(theme.menu_selected.fg, theme.menu_selected.bg);
+        let area = menu.place(
+            anchor,
+            bundle.meta().rect_resolved(dimensions),
+            Self::MENU_HEIGHT,
+        );
+
+        let screen = Rect::new_in_origin(dimensions.width, dimensions.height);
+        let mut popup = screen.bind_layer(&mut state.window, Self::POPUP_LAYER);
+        menu.draw(area, &mut popup);
+    }
+
+    /// Draws the lines of the popup below the cursor, or above it if there is more room there
+    fn draw_popup(state: &mut State, dimensions: UDims) {
+        if state.popup.is_empty() {
+            return;
+        }
+        let Some(bundle) = state.buffers.active() else {
+            return;
+        };
 
-        let Some(bundle) = state.buffers.get_mut(selector) else {
-            edi_lib::debug!("invalid selector passed {selector:?}");
+        let items = state.popup.iter().map(|line| MenuItem::new(line)).collect();
+        let theme = state.context.options.drawn_theme();
+        let menu = Menu::new(items, None).with_colors(theme.menu.fg, theme.menu.bg);
+        let area = menu.place(
+            state.window.cursor(),
+            bundle.meta().rect_resolved(dimensions),
+            dimensions.height,
+        );
+
+        let screen = Rect::new_in_origin(dimensions.width, dimensions.height);
+        let mut popup = screen.bind_layer(&mut state.window, Self::POPUP_LAYER);
+        menu.draw(area, &mut popup);
+    }
+
+    /// Draws the message on the message line at the bottom of the terminal
+    fn draw_message(state: &mut State, dimensions: UDims) {
+        let Some(message) = &state.message else {
             return;
         };
 
-        let (buffer, meta) = bundle.as_split_mut(ctrl);
-        meta.set_highlights(get_highlights(&buffer.as_ref().inner, &meta.filetype));
-        edi_lib::debug!("buffer with id: {id:?} rehighlighted", id = bundle.id());
+        let (fg, bg) = match message.severity {
+            Severity::Info => (Color::White, Color::Black),
+            Severity::Warning => (Color::Black, Color::Yellow),
+            Severity::Error => (Color::White, Color::Red),
+        };
+        let area = Rect::new(0, message::row(dimensions), dimensions.width, 1);
+        area.clear(&mut state.window, bg);
+        let span = Span::new(&message.text).with_colors(fg, bg);
+        draw_line(area, &[span], Alignment::Left, &mut state.window);
+    }
+
+    fn align(state: &mut State, position: ViewPosition) -> Result<()> {
+        let area = message::buffers_area(Self::dimensions()?);
+        let Some(bundle) = state.buffers.active_mut() else {
+            return Ok(());
+        };
+
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        meta.align(&state.context, buffer, area, position);
+        Ok(())
+    }
+
+    fn scroll_columns(state: &mut State, right: bool, count: usize) -> Result<()> {
+        let area = message::buffers_area(Self::dimensions()?);
+        let Some(bundle) = state.buffers.active_mut() else {
+            return Ok(());
+        };
+
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        meta.scroll_columns(&state.context, buffer, area, right, count);
+        Ok(())
+    }
+
+    fn suspend(&mut self, state: &mut State, ctrl: &mut Handle<State>) -> Result<()> {
+        edi_term::suspend().map_err(|err| AppError::io("unable to suspend").with_cause(err))?;
+
+        // The terminal might have been used by anything else in the meantime
+        state
+            .window
+            .rerender()
+            .map_err(|err| AppError::terminal_io("unable to draw").with_cause(err))?;
+        self.redraw(state, ctrl)
+    }
+
+    fn show_pending_keys(state: &mut State, keys: &str) {
+        for bundle in state.buffers.iter_mut() {
+            bundle.meta_mut().pending_keys.clear();
+        }
+        if let Some(bundle) = state.buffers.active_mut() {
+            bundle.meta_mut().pending_keys.push_str(keys);
+        }
+    }
+
+    /// Updates the highlights of the buffers whose text changed
+    fn rehighlight(&mut self, state: &mut State) {
+        let _span = edi_lib::span!("rehighlight");
+
+        for id in self.stale.drain() {
+            let Some(bundle) = state.buffers.get_mut(&Selector::WithId(id)) else {
+                continue;
+            };
+
+            bundle.apply_edits();
+            edi_lib::debug!("buffer with id: {id:?} rehighlighted");
+        }
     }
 }
 
 impl controller::QueryHandler<State> for Handler {
-    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
         let _span = edi_lib::span!("draw");
 
         let Payload::Draw(draw_query) = query.payload() else {
-            edi_lib::debug!("non-draw query submitted to draw query handler, this is likely a bug");
-            return;
+            return Err(AppError::unexpected(
+                "non-draw query submitted to draw query handler, this is likely a bug",
+            ));
         };
 
         match draw_query {
-            DrawQuery::Redraw => Self::redraw(state, ctrl),
-            DrawQuery::Rehighlight(selector) => Self::rehighlight(state, ctrl, selector),
+            DrawQuery::Redraw => self.redraw.request(ctrl),
+            DrawQuery::Rehighlight(selector) => {
+                let Some(bundle) = state.buffers.get(selector) else {
+                    edi_lib::debug!("invalid selector passed {selector:?}");
+                    return Ok(());
+                };
+                self.stale.insert(bundle.id());
+                self.rehighlight.request(ctrl);
+            }
+            &DrawQuery::TimedOut(timer) => {
+                if self.rehighlight.is_due(timer) {
+                    self.rehighlight(state);
+                    self.redraw(state, ctrl)?;
+                } else if self.redraw.is_due(timer) {
+                    self.redraw(state, ctrl)?;
+                }
+            }
+            DrawQuery::Suspend => self.suspend(state, ctrl)?,
+            &DrawQuery::Align(position) => {
+                Self::align(state, position)?;
+                self.redraw(state, ctrl)?;
+            }
+            &DrawQuery::ScrollColumns { right, count } => {
+                Self::scroll_columns(state, right, count)?;
+                self.redraw(state, ctrl)?;
+            }
+            DrawQuery::ClearMessage => {
+                state.message = None;
+                state.popup.clear();
+                self.redraw(state, ctrl)?;
+            }
+            DrawQuery::PendingKeys(keys) => {
+                Self::show_pending_keys(state, keys);
+                self.redraw(state, ctrl)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_event(&mut self, _state: &State, event: &Event, ctrl: &mut Handle<State>) {
+        match *event.payload() {
+            event::Payload::Timeout(timer) => ctrl.query_draw(DrawQuery::TimedOut(timer)),
+            // The terminal is drawn to again once it is resized
+            event::Payload::Tick
+                if self.dimensions.is_some() && Self::dimensions().ok() != self.dimensions =>
+            {
+                ctrl.query_redraw();
+            }
+            _ => {}
         }
     }
+
+    fn interested_in(&self, _own_id: Id, event: &Event) -> bool {
+        event
+            .ty()
+            .is_oneof(&[event::Type::Timeout, event::Type::Tick])
+    }
 }
@@ -0,0 +1,391 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use edi_frame::unit::Unit;
+use edi_lib::{brand::Id, buffer::Buffer, vec2::Vec2};
+use edi_rope::Rope;
+
+use crate::{
+    app::{
+        buffer_bundle::BufferBundle,
+        buffers::Selector,
+        explorer::{Explorer, Operation},
+        message::Message,
+        meta::BufferMeta,
+        state::State,
+        Mode,
+    },
+    controller::{self, Handle},
+    error::{AppError, Result},
+    event::{self, Event},
+    query::{ExplorerQuery, Payload, Query, SpawnQuery},
+};
+
+/// What the open explorer prompt asks the path for
+#[derive(Debug)]
+struct Prompt {
+    /// The buffer of the explorer the prompt was opened from
+    explorer: Id,
+    operation: Operation,
+    /// The entry the operation acts on, the directory a file is created in
+    path: PathBuf,
+    /// The text in front of the typed path
+    label: String,
+}
+
+pub struct Handler {
+    prompt: Option<Prompt>,
+}
+
+impl Handler {
+    pub const fn new() -> Self {
+        Self { prompt: None }
+    }
+}
+
+impl controller::QueryHandler<State> for Handler {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
+        let _span = edi_lib::span!("explorer");
+
+        let Payload::Explorer(explorer_query) = query.into_payload() else {
+            return Err(AppError::unexpected(
+                "non-explorer query submitted to explorer query handler, this is likely a bug",
+            ));
+        };
+
+        match explorer_query {
+            ExplorerQuery::Show(dir) => Self::show(state, dir.as_deref()),
+            ExplorerQuery::Run(operation) => self.run(state, ctrl, operation)?,
+            ExplorerQuery::Submit => self.submit(state),
+        }
+        ctrl.query_redraw();
+        Ok(())
+    }
+
+    fn check_event(&mut self, _state: &State, event: &Event, _ctrl: &mut Handle<State>) {
+        // The prompt is closed, it was submitted before that if it ever was
+        if let event::Payload::ModeSwitched { .. } = event.payload() {
+            self.prompt = None;
+        }
+    }
+
+    fn interested_in(&self, _own_id: Id, event: &Event) -> bool {
+        event.ty().is_oneof(&[event::Type::ModeSwitched])
+    }
+}
+
+impl Handler {
+    /// Makes the explorer active, opening it with the file of the displayed buffer revealed if it
+    /// is not open. A given directory becomes the root of the explorer
+    fn show(state: &mut State, dir: Option<&Path>) {
+        let root = match dir.map_or_else(std::env::current_dir, fs::canonicalize) {
+            Ok(root) => root,
+            Err(e) => {
+                let dir = dir.unwrap_or(Path::new(".")).display();
+                state.notify(Message::error(format!("cannot open {dir}: {e}")));
+                return;
+            }
+        };
+        let file = state
+            .buffers
+            .displayed()
+            .and_then(|id| state.buffers.get(&Selector::WithId(id)))
+            .and_then(|bundle| bundle.meta().filepath.as_ref())
+            .and_then(|filepath| fs::canonicalize(filepath).ok());
+
+        let open = state
+            .buffers
+            .iter()
+            .find(|bundle| bundle.meta().explorer.is_some())
+            .map(BufferBundle::id);
+        if let Some(id) = open {
+            let Some(bundle) = state.buffers.get_mut(&Selector::WithId(id)) else {
+                return;
+            };
+            if let Some(explorer) = &mut bundle.meta_mut().explorer {
+                if dir.is_some() {
+                    *explorer = Explorer::new(root);
+                } else {
+                    explorer.refresh();
+                }
+            }
+            Self::show_tree(bundle, None);
+            state.buffers.set_active(id);
+            return;
+        }
+
+        let mut explorer = Explorer::new(root);
+        if let Some(file) = &file {
+            explorer.reveal(file);
+        }
+        let mut buffer = Buffer::new("");
+        buffer.tab_width = state.context.options.tab_width;
+        let mut meta = BufferMeta::new(Mode::Normal)
+            .with_size(Vec2::new(Unit::full_width(), Unit::full_height()))
+            .with_statusline(true);
+        meta.explorer = Some(explorer);
+
+        let id = state.buffers.attach(buffer, meta);
+        state.buffers.set_active(id);
+        if let Some(bundle) = state.buffers.get_mut(&Selector::WithId(id)) {
+            Self::show_tree(bundle, file.as_deref());
+        }
+    }
+
+    /// Writes the tree of the explorer to its buffer, putting the cursor on the line of the path
+    /// if it is shown, or keeping it on the same line otherwise
+    fn show_tree(bundle: &mut BufferBundle, cursor_on: Option<&Path>) {
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        let Some(explorer) = &meta.explorer else {
+            return;
+        };
+
+        let line = cursor_on
+            .and_then(|path| explorer.line_of(path))
+            .unwrap_or_else(|| buffer.current_line());
+        buffer.inner = Rope::from(explorer.text().as_str());
+        let line = line.min(buffer.total_lines().saturating_sub(1));
+        buffer.cursor_offset = buffer.inner.line_to_char(line);
+    }
+
+    /// Carries out the operation on the entry under the cursor of the active explorer
+    fn run(
+        &mut self,
+        state: &mut State,
+        ctrl: &mut Handle<State>,
+        operation: Operation,
+    ) -> Result<()> {
+        let Some(bundle) = state.buffers.active_mut() else {
+            return Ok(());
+        };
+        let id = bundle.id();
+        let line = bundle.buffer().current_line();
+        let Some(explorer) = &mut bundle.meta_mut().explorer else {
+            return Ok(());
+        };
+        let entry = explorer.entry(line).cloned();
+
+        let cursor_on = match (operation, entry) {
+            (Operation::Open, Some(entry)) if !entry.is_dir => {
+                return Self::open(state, &entry.path);
+            }
+            (Operation::Open, Some(entry)) => {
+                explorer.toggle(&entry.path);
+                Some(entry.path)
+            }
+            (Operation::Open, None) | (Operation::Parent, _) => {
+                let root = explorer.root.clone();
+                explorer.parent();
+                Some(root)
+            }
+            (Operation::Collapse, _) => explorer.collapse(line),
+            (Operation::Refresh, entry) => {
+                explorer.refresh();
+                entry.map(|entry| entry.path)
+            }
+            (Operation::Create, _) => {
+                let dir = explorer.directory(line).to_path_buf();
+                let mut typed = explorer.relative(&dir);
+                if !typed.is_empty() {
+                    typed.push('/');
+                }
+                self.ask(ctrl, id, Operation::Create, dir, "create: ", &typed);
+                return Ok(());
+            }
+            (Operation::Rename, Some(entry)) => {
+                let typed = explorer.relative(&entry.path);
+                self.ask(ctrl, id, Operation::Rename, entry.path, "rename: ", &typed);
+                return Ok(());
+            }
+            (Operation::Delete, Some(entry)) => {
+                let label = format!("delete {}? (y/n): ", explorer.relative(&entry.path));
+                self.ask(ctrl, id, Operation::Delete, entry.path, &label, "");
+                return Ok(());
+            }
+            (Operation::Rename | Operation::Delete, None) => return Ok(()),
+            (Operation::Close, _) => {
+                Self::close(state, id);
+                return Ok(());
+            }
+        };
+        Self::show_tree(bundle, cursor_on.as_deref());
+        Ok(())
+    }
+
+    /// Opens the prompt for the path the operation needs, with the text typed in it already
+    fn ask(
+        &mut self,
+        ctrl: &mut Handle<State>,
+        explorer: Id,
+        operation: Operation,
+        path: PathBuf,
+        label: &str,
+        typed: &str,
+    ) {
+        self.prompt = Some(Prompt {
+            explorer,
+            operation,
+            path,
+            label: String::from(label),
+        });
+        ctrl.query_spawn(SpawnQuery::ExplorerPrompt(format!("{label}{typed}")));
+    }
+
+    /// Carries out the operation of the prompt with the path typed in it, then shows the changed
+    /// tree in the explorer
+    fn submit(&mut self, state: &mut State) {
+        let Some(prompt) = self.prompt.take() else {
+            return;
+        };
+        let Some(bundle) = state.buffers.active() else {
+            return;
+        };
+        if !bundle.meta().flags.is_explorer_prompt() {
+            return;
+        }
+        let text: String = bundle.buffer().inner.chars().collect();
+        let Some(typed) = text.strip_prefix(&prompt.label).map(str::trim) else {
+            return;
+        };
+        let Some(explorer) = state
+            .buffers
+            .get(&Selector::WithId(prompt.explorer))
+            .and_then(|bundle| bundle.meta().explorer.as_ref())
+        else {
+            return;
+        };
+        let target = explorer.root.join(typed);
+        let name = explorer.relative(&prompt.path);
+
+        let (verb, result) = match prompt.operation {
+            Operation::Create if !typed.is_empty() => {
+                ("create", Self::create(&target, typed.ends_with('/')))
+            }
+            Operation::Rename if !typed.is_empty() => {
+                let result = Self::rename(&prompt.path, &target);
+                if result.is_ok() {
+                    Self::moved(state, &prompt.path, &target);
+                }
+                ("rename", result)
+            }
+            Operation::Delete if typed == "y" => ("delete", Self::delete(&prompt.path)),
+            _ => return,
+        };
+        let shown = if prompt.operation == Operation::Delete {
+            name
+        } else {
+            String::from(typed)
+        };
+        state.notify(match result {
+            Ok(()) => Message::info(format!("\"{shown}\" {verb}d")),
+            Err(e) => Message::error(format!("cannot {verb} {shown}: {e}")),
+        });
+
+        let Some(bundle) = state.buffers.get_mut(&Selector::WithId(prompt.explorer)) else {
+            return;
+        };
+        let Some(explorer) = &mut bundle.meta_mut().explorer else {
+            return;
+        };
+        let cursor_on = if prompt.operation == Operation::Delete {
+            None
+        } else {
+            explorer.reveal(&target);
+            Some(target)
+        };
+        Self::show_tree(bundle, cursor_on.as_deref());
+    }
+
+    /// Creates an empty file at the path, or a directory if `dir` is set, along with the
+    /// directories it is in
+    fn create(path: &Path, dir: bool) -> io::Result<()> {
+        if dir {
+            return fs::create_dir_all(path);
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::File::create_new(path).map(drop)
+    }
+
+    /// Moves the entry to the path, which must not exist yet
+    fn rename(from: &Path, to: &Path) -> io::Result<()> {
+        if to.exists() {
+            return Err(io::Error::from(io::ErrorKind::AlreadyExists));
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(from, to)
+    }
+
+    /// Deletes the entry, along with everything in it if it is a directory
+    fn delete(path: &Path) -> io::Result<()> {
+        if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+
+    /// Makes the buffers of the files that were moved from under `from` to `to` write them to
+    /// where they are now
+    fn moved(state: &mut State, from: &Path, to: &Path) {
+        for bundle in state.buffers.iter_mut() {
+            let meta = bundle.meta_mut();
+            let Some(filepath) = meta.filepath.clone() else {
+                continue;
+            };
+            let Ok(absolute) = std::path::absolute(&filepath) else {
+                continue;
+            };
+            let Ok(rest) = absolute.strip_prefix(from) else {
+                continue;
+            };
+
+            let moved = if rest.as_os_str().is_empty() {
+                to.to_path_buf()
+            } else {
+                to.join(rest)
+            };
+            let moved = relative_to_cwd(&moved).to_path_buf();
+            state.watcher.unwatch(&filepath);
+            state.watcher.watch(&moved);
+            meta.filepath = Some(moved);
+        }
+    }
+
+    /// Opens the file in a buffer of its own
+    fn open(state: &mut State, path: &Path) -> Result<()> {
+        let path = relative_to_cwd(path);
+        state.edit_file(path).map(drop).map_err(|e| {
+            let path = path.display();
+            AppError::io(format!("cannot open {path}: {e}"))
+        })
+    }
+
+    /// Closes the explorer, unless it is the last buffer
+    fn close(state: &mut State, id: Id) {
+        let others = state
+            .buffers
+            .iter()
+            .filter(|bundle| !bundle.meta().flags.is_terminal() && bundle.id() != id)
+            .count();
+        if others == 0 {
+            state.notify(Message::error("cannot close the last buffer"));
+            return;
+        }
+        state.buffers.remove(id);
+    }
+}
+
+/// Returns the path relative to the working directory if it is in it, files are opened from the
+/// command line by such paths
+fn relative_to_cwd(path: &Path) -> &Path {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok())
+        .unwrap_or(path)
+}
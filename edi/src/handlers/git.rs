@@ -0,0 +1,406 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use edi_lib::brand::Id;
+
+use crate::{
+    app::{
+        buffers::Selector,
+        git::{self, Hunk},
+        message::Message,
+        state::State,
+        Mode,
+    },
+    controller::{self, Handle},
+    error::{AppError, Result},
+    event::{self, Event},
+    jobs::{JobId, Stream},
+    query::{GitQuery, Payload, Query},
+};
+
+/// A running blame, with what git wrote so far
+#[derive(Debug)]
+struct Blame {
+    buffer_id: Id,
+    stdout: String,
+    stderr: String,
+}
+
+/// What is done with the hunk under the cursor once the diff is known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HunkAction {
+    Preview,
+    Revert,
+}
+
+/// A running diff of the file of a buffer, with what git wrote so far
+#[derive(Debug)]
+struct Diff {
+    buffer_id: Id,
+    action: HunkAction,
+    /// The line the cursor was on when the diff was started
+    line: usize,
+    /// The checksum of the text of the buffer when the diff was started
+    checksum: u64,
+    stdout: String,
+    stderr: String,
+}
+
+pub struct Handler {
+    /// The running blames by their jobs
+    blames: HashMap<JobId, Blame>,
+    /// The running diffs by their jobs
+    diffs: HashMap<JobId, Diff>,
+    /// The buffers whose text changed since blame was last run on them
+    stale: HashSet<Id>,
+    /// The buffers blame is due to be run on again
+    due: HashSet<Id>,
+}
+
+impl Handler {
+    pub fn new() -> Self {
+        Self {
+            blames: HashMap::new(),
+            diffs: HashMap::new(),
+            stale: HashSet::new(),
+            due: HashSet::new(),
+        }
+    }
+}
+
+impl controller::QueryHandler<State> for Handler {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
+        let _span = edi_lib::span!("git");
+
+        let Payload::Git(git_query) = query.into_payload() else {
+            return Err(AppError::unexpected(
+                "non-git query submitted to git query handler, this is likely a bug",
+            ));
+        };
+
+        match git_query {
+            GitQuery::ToggleBlame => self.toggle_blame(state),
+            GitQuery::Blame { buffer_id } => {
+                self.stale.remove(&buffer_id);
+                self.due.remove(&buffer_id);
+                self.blame(state, buffer_id);
+            }
+            GitQuery::Blamed { job, status } => self.blamed(state, job, status),
+            GitQuery::PreviewHunk => self.diff(state, HunkAction::Preview),
+            GitQuery::RevertHunk => {
+                if let Some(bundle) = state.buffers.active() {
+                    bundle.meta().ensure_modifiable()?;
+                }
+                self.diff(state, HunkAction::Revert);
+            }
+            GitQuery::Diffed { job, status } => self.diffed(state, job, status, ctrl)?,
+        }
+        ctrl.query_redraw();
+        Ok(())
+    }
+
+    fn check_event(&mut self, state: &State, event: &Event, ctrl: &mut Handle<State>) {
+        // Blame is run again once a line is added or removed, so it stays next to its line, and
+        // once the buffer is back in the normal mode after other changes
+        let (buffer_id, due) = match *event.payload() {
+            event::Payload::CharWritten { buffer_id, c, .. }
+            | event::Payload::CharDeleted { buffer_id, c, .. } => {
+                self.stale.insert(buffer_id);
+                (buffer_id, c == '\n')
+            }
+            event::Payload::TextReplaced {
+                buffer_id,
+                ref removed,
+                ref inserted,
+                ..
+            } => {
+                self.stale.insert(buffer_id);
+                (buffer_id, removed.contains('\n') || inserted.contains('\n'))
+            }
+            event::Payload::ModeSwitched {
+                buffer_id,
+                target_mode,
+            } => (
+                buffer_id,
+                target_mode == Mode::Normal && self.stale.contains(&buffer_id),
+            ),
+            event::Payload::BlameDue { buffer_id } => {
+                ctrl.query_git(GitQuery::Blame { buffer_id });
+                return;
+            }
+            event::Payload::JobOutput {
+                job,
+                stream,
+                ref line,
+            } => {
+                let (stdout, stderr) = if let Some(blame) = self.blames.get_mut(&job) {
+                    (&mut blame.stdout, &mut blame.stderr)
+                } else if let Some(diff) = self.diffs.get_mut(&job) {
+                    (&mut diff.stdout, &mut diff.stderr)
+                } else {
+                    return;
+                };
+                let output = match stream {
+                    Stream::Stdout => stdout,
+                    Stream::Stderr => stderr,
+                };
+                output.push_str(line);
+                output.push('\n');
+                return;
+            }
+            event::Payload::JobExited { job, status } => {
+                if self.blames.contains_key(&job) {
+                    ctrl.query_git(GitQuery::Blamed { job, status });
+                } else if self.diffs.contains_key(&job) {
+                    ctrl.query_git(GitQuery::Diffed { job, status });
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        let blamed = state
+            .buffers
+            .get(&Selector::WithId(buffer_id))
+            .is_some_and(|bundle| !bundle.meta().blame.is_empty());
+        if !blamed {
+            self.stale.remove(&buffer_id);
+            return;
+        }
+        if due && self.due.insert(buffer_id) {
+            ctrl.add_event_after(Duration::ZERO, event::Payload::BlameDue { buffer_id });
+        }
+    }
+
+    fn interested_in(&self, _own_id: Id, event: &Event) -> bool {
+        event.ty().is_oneof(&[
+            event::Type::CharWritten,
+            event::Type::CharDeleted,
+            event::Type::TextReplaced,
+            event::Type::ModeSwitched,
+            event::Type::BlameDue,
+            event::Type::JobOutput,
+            event::Type::JobExited,
+        ])
+    }
+}
+
+impl Handler {
+    /// Shows blame in the active buffer, or stops showing it if it is shown
+    fn toggle_blame(&mut self, state: &mut State) {
+        let Some(bundle) = state.buffers.active_mut() else {
+            return;
+        };
+        let meta = bundle.meta_mut();
+        if !meta.blame.is_empty() {
+            meta.blame.clear();
+            return;
+        }
+        let id = bundle.id();
+        self.blame(state, id);
+    }
+
+    /// Starts running blame on the text of the buffer, in place of the one that is running on it
+    fn blame(&mut self, state: &mut State, buffer_id: Id) {
+        let Some(bundle) = state.buffers.get_mut(&Selector::WithId(buffer_id)) else {
+            return;
+        };
+        let Some(path) = bundle.meta().filepath.clone() else {
+            state.notify(Message::error("no file name"));
+            return;
+        };
+        let meta = bundle.meta();
+        let mut text = bundle.buffer().inner.to_string();
+        if meta.trailing_newline {
+            text.push('\n');
+        }
+        let text = text.replace('\n', meta.line_ending.as_str());
+
+        self.blames
+            .retain(|&job, blame| blame.buffer_id != buffer_id || !state.jobs.cancel(job));
+        match state.jobs.spawn(git::blame(&path, &text)) {
+            Ok(job) => {
+                let blame = Blame {
+                    buffer_id,
+                    stdout: String::new(),
+                    stderr: String::new(),
+                };
+                self.blames.insert(job, blame);
+            }
+            Err(e) => state.notify(Message::error(format!("cannot run git: {e}"))),
+        }
+    }
+
+    /// Shows the output of the finished blame in its buffer
+    fn blamed(&mut self, state: &mut State, job: JobId, status: Option<i32>) {
+        let Some(blame) = self.blames.remove(&job) else {
+            return;
+        };
+        let Some(bundle) = state.buffers.get_mut(&Selector::WithId(blame.buffer_id)) else {
+            return;
+        };
+        let meta = bundle.meta_mut();
+        if status == Some(0) {
+            meta.blame = git::parse_blame(&blame.stdout);
+            return;
+        }
+        meta.blame.clear();
+        let error = git::error(&blame.stderr);
+        state.notify(Message::error(format!("cannot blame: {error}")));
+    }
+
+    /// Starts comparing the file of the active buffer with the staged one, to find the hunk the
+    /// cursor is on. The buffer must be written, as the file is what is compared
+    fn diff(&mut self, state: &mut State, action: HunkAction) {
+        let Some(bundle) = state.buffers.active() else {
+            return;
+        };
+        let meta = bundle.meta();
+        let Some(path) = &meta.filepath else {
+            state.notify(Message::error("no file name"));
+            return;
+        };
+        if meta.modified {
+            state.notify(Message::error("no write since last change"));
+            return;
+        }
+
+        let buffer_id = bundle.id();
+        let diff = Diff {
+            buffer_id,
+            action,
+            line: bundle.buffer().current_line(),
+            checksum: bundle.buffer().inner.checksum(),
+            stdout: String::new(),
+            stderr: String::new(),
+        };
+        let spec = git::diff(path);
+        self.diffs
+            .retain(|&job, diff| diff.buffer_id != buffer_id || !state.jobs.cancel(job));
+        match state.jobs.spawn(spec) {
+            Ok(job) => {
+                self.diffs.insert(job, diff);
+            }
+            Err(e) => state.notify(Message::error(format!("cannot run git: {e}"))),
+        }
+    }
+
+    /// Previews or reverts the hunk the cursor was on once the diff finished. Nothing is done if
+    /// the buffer changed while git ran, as the hunks may no longer match its lines
+    fn diffed(
+        &mut self,
+        state: &mut State,
+        job: JobId,
+        status: Option<i32>,
+        ctrl: &mut Handle<State>,
+    ) -> Result<()> {
+        let Some(diff) = self.diffs.remove(&job) else {
+            return Ok(());
+        };
+        let Some(bundle) = state.buffers.get(&Selector::WithId(diff.buffer_id)) else {
+            return Ok(());
+        };
+        if bundle.buffer().inner.checksum() != diff.checksum {
+            state.notify(Message::error("the buffer changed while it was diffed"));
+            return Ok(());
+        }
+        if status != Some(0) {
+            let error = git::error(&diff.stderr);
+            state.notify(Message::error(format!("cannot diff: {error}")));
+            return Ok(());
+        }
+
+        let hunk = git::parse_diff(&diff.stdout)
+            .into_iter()
+            .find(|hunk| hunk.contains(diff.line));
+        let Some(hunk) = hunk else {
+            state.notify(Message::error("no changes under the cursor"));
+            return Ok(());
+        };
+        match diff.action {
+            HunkAction::Preview => {
+                let active = state.buffers.active();
+                if active.is_some_and(|bundle| bundle.id() == diff.buffer_id) {
+                    state.popup = hunk.diff;
+                }
+            }
+            HunkAction::Revert => {
+                bundle.meta().ensure_modifiable()?;
+                Self::revert_hunk(state, diff.buffer_id, &hunk, ctrl);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the lines of the hunk with the staged ones, as a single change
+    fn revert_hunk(state: &mut State, buffer_id: Id, hunk: &Hunk, ctrl: &mut Handle<State>) {
+        let Some(bundle) = state.buffers.get_mut(&Selector::WithId(buffer_id)) else {
+            return;
+        };
+        let reverted = hunk.revert(&bundle.buffer().inner.to_string());
+
+        ctrl.add_event(event::Payload::ChangeGroupStarted { buffer_id });
+        bundle.buffer_mut(ctrl).replace_all(&reverted);
+        ctrl.add_event(event::Payload::ChangeGroupEnded { buffer_id });
+
+        let (buffer, _) = bundle.as_split_mut_silent();
+        let line = hunk.start.min(buffer.total_lines().saturating_sub(1));
+        buffer.cursor_offset = buffer.inner.line_to_char(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use crate::{
+        app::testing::Editor,
+        query::{Payload, WriteQuery},
+    };
+
+    /// Opens a file whose staged text is `staged`, changed to `text` and written
+    fn staged(name: &str, staged: &str, text: &str) -> Editor {
+        let mut editor = Editor::new(name, staged);
+        let dir = editor.path.parent().unwrap();
+        for args in [&["init", "-q"][..], &["add", "file.txt"]] {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(dir)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+        std::fs::write(&editor.path, text).unwrap();
+        assert!(!editor.command(":e!"));
+        assert_eq!(editor.text(), text);
+        editor
+    }
+
+    #[test]
+    fn revert_hunk_of_started_line() {
+        let mut editor = staged("revert-hunk", "a\nb\nc\nd\n", "a\nB\nc\nD\n");
+        editor.keys("j");
+        assert!(!editor.command(":GitRevertHunk"));
+        // The hunk is the one the cursor was on when the diff was started
+        editor.keys("jj");
+        editor.wait_job();
+        assert_eq!(editor.text(), "a\nb\nc\nD\n");
+        assert_eq!(editor.bundle().buffer().current_line(), 1);
+    }
+
+    #[test]
+    fn revert_hunk_changed_meanwhile() {
+        let mut editor = staged("revert-hunk-changed", "a\nb\n", "a\nB\n");
+        editor.keys("j");
+        editor.command(":GitRevertHunk");
+        editor.query(Payload::Write(WriteQuery::WriteChar('x')));
+        editor.wait_job();
+        assert_eq!(editor.text(), "a\nxB\n");
+        assert_eq!(
+            editor.message(),
+            Some("the buffer changed while it was diffed")
+        );
+    }
+}
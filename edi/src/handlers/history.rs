@@ -5,6 +5,7 @@ use edi_lib::brand::Id;
 use crate::{
     app::{buffer_bundle::BufferBundle, buffers::Selector, state::State},
     controller::{self, Handle},
+    error::{AppError, Result},
     event::{self, emitter::buffer, Event, Payload},
     query::{self, DrawQuery, HistoryQuery, Query},
 };
@@ -12,9 +13,21 @@ use crate::{
 #[derive(Debug)]
 enum Change {
     // Write `content` at `offset`
-    Write { offset: usize, content: String },
+    Write {
+        offset: usize,
+        content: String,
+    },
     // Remove `content` starting from `offset`
-    Delete { offset: usize, content: String },
+    Delete {
+        offset: usize,
+        content: String,
+    },
+    // Replace `removed` starting from `offset` with `inserted`
+    Replace {
+        offset: usize,
+        removed: String,
+        inserted: String,
+    },
 }
 
 impl Change {
@@ -31,6 +44,15 @@ impl Change {
                     buffer.write(c);
                 }
             }
+
+            Change::Replace {
+                offset,
+                removed,
+                inserted,
+            } => {
+                buffer.replace_range(*offset..*offset + inserted.chars().count(), removed);
+                buffer.set_cursor_offset(*offset);
+            }
         }
     }
 
@@ -47,6 +69,14 @@ impl Change {
                     buffer.write(c);
                 }
             }
+
+            Change::Replace {
+                offset,
+                removed,
+                inserted,
+            } => {
+                buffer.replace_range(*offset..*offset + removed.chars().count(), inserted);
+            }
         }
     }
 }
@@ -68,6 +98,8 @@ struct History {
     changes: Vec<Record>,
     current_age: usize,
     current_position: usize,
+    /// Whether the changes are grouped explicitly, so switching modes does not split them
+    grouping: bool,
 }
 
 impl History {
@@ -136,6 +168,16 @@ impl Handler {
         });
     }
 
+    fn text_replaced(&mut self, buffer_id: Id, offset: usize, removed: &str, inserted: &str) {
+        let history = self.id_to_history.entry(buffer_id).or_default();
+
+        history.write_furute(Change::Replace {
+            offset,
+            removed: String::from(removed),
+            inserted: String::from(inserted),
+        });
+    }
+
     fn undo(&mut self, bundle: &mut BufferBundle, ctrl: &mut Handle<State>) {
         let Some(history) = self.id_to_history.get_mut(&bundle.id()) else {
             return;
@@ -194,34 +236,36 @@ impl Handler {
 }
 
 impl controller::QueryHandler<State> for Handler {
-    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
         let _span = edi_lib::span!("history");
 
         let query::Payload::History(history_query) = query.payload() else {
-            edi_lib::debug!(
-                "non-history query submitted to history query handler, this is likely a bug"
-            );
-            return;
+            return Err(AppError::unexpected(
+                "non-history query submitted to history query handler, this is likely a bug",
+            ));
         };
 
         match history_query {
             HistoryQuery::Undo(selector) => {
                 let Some(bundle) = state.buffers.get_mut(selector) else {
-                    return;
+                    return Ok(());
                 };
+                bundle.meta().ensure_modifiable()?;
                 self.undo(bundle, ctrl);
                 ctrl.query_redraw();
             }
             HistoryQuery::Redo(selector) => {
                 let Some(bundle) = state.buffers.get_mut(selector) else {
-                    return;
+                    return Ok(());
                 };
+                bundle.meta().ensure_modifiable()?;
                 self.redo(bundle, ctrl);
                 ctrl.query_redraw();
             }
         }
 
         edi_lib::debug!("history changed, new history: {:?}", self.id_to_history);
+        Ok(())
     }
 
     fn check_event(&mut self, _state: &State, event: &Event, _ctrl: &mut Handle<State>) {
@@ -238,8 +282,31 @@ impl controller::QueryHandler<State> for Handler {
                 offset,
                 c,
             } => self.char_deleted(buffer_id, offset, c),
+            Payload::TextReplaced {
+                buffer_id,
+                offset,
+                removed,
+                inserted,
+            } => self.text_replaced(*buffer_id, *offset, removed, inserted),
             Payload::ModeSwitched { buffer_id, .. } => {
-                self.id_to_history.get_mut(buffer_id).map(History::next_age);
+                if let Some(history) = self.id_to_history.get_mut(buffer_id) {
+                    if !history.grouping {
+                        history.next_age();
+                    }
+                }
+                return;
+            }
+            &Payload::ChangeGroupStarted { buffer_id } => {
+                let history = self.id_to_history.entry(buffer_id).or_default();
+                history.next_age();
+                history.grouping = true;
+                return;
+            }
+            &Payload::ChangeGroupEnded { buffer_id } => {
+                if let Some(history) = self.id_to_history.get_mut(&buffer_id) {
+                    history.next_age();
+                    history.grouping = false;
+                }
                 return;
             }
             _ => return,
@@ -256,8 +323,82 @@ impl controller::QueryHandler<State> for Handler {
         let types = &[
             event::Type::CharWritten,
             event::Type::CharDeleted,
+            event::Type::TextReplaced,
             event::Type::ModeSwitched,
+            event::Type::ChangeGroupStarted,
+            event::Type::ChangeGroupEnded,
         ];
         event.ty().is_oneof(types)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app::{action::Operator, buffers::Selector, registers::Register, testing::Editor},
+        event,
+        query::{HistoryQuery, OperatorQuery, OperatorTarget, Payload, RegistersQuery},
+    };
+
+    fn replaced(events: &[event::Payload]) -> Vec<(usize, &str, &str)> {
+        events
+            .iter()
+            .filter_map(|payload| match payload {
+                event::Payload::TextReplaced {
+                    offset,
+                    removed,
+                    inserted,
+                    ..
+                } => Some((*offset, removed.as_str(), inserted.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn undo_deleted_lines() {
+        let mut editor = Editor::new("undo-delete", "a\nb\nc\n");
+        editor.query(Payload::Operator(OperatorQuery {
+            operator: Operator::Delete,
+            target: OperatorTarget::Lines(2),
+            register: None,
+        }));
+        assert_eq!(editor.text(), "c\n");
+        let events = editor.take_events();
+        assert_eq!(replaced(&events), [(0, "a\nb\n", "")]);
+
+        editor.query(Payload::History(HistoryQuery::Undo(Selector::Active)));
+        assert_eq!(editor.text(), "a\nb\nc\n");
+        let events = editor.take_events();
+        assert_eq!(replaced(&events), [(0, "", "a\nb\n")]);
+
+        // Nothing else is left to undo
+        editor.query(Payload::History(HistoryQuery::Undo(Selector::Active)));
+        assert_eq!(editor.text(), "a\nb\nc\n");
+
+        editor.query(Payload::History(HistoryQuery::Redo(Selector::Active)));
+        assert_eq!(editor.text(), "c\n");
+    }
+
+    #[test]
+    fn paste_lines_at_once() {
+        let mut editor = Editor::new("paste", "a\nb\n");
+        editor
+            .state
+            .registers
+            .yank(None, Register::linewise("x\ny"));
+        editor.query(Payload::Registers(RegistersQuery::Paste {
+            register: None,
+            before: false,
+        }));
+        assert_eq!(editor.text(), "a\nx\ny\nb\n");
+        let events = editor.take_events();
+        assert_eq!(replaced(&events), [(2, "", "x\ny\n")]);
+        assert!(!events
+            .iter()
+            .any(|payload| matches!(payload, event::Payload::CharWritten { .. })));
+
+        editor.query(Payload::History(HistoryQuery::Undo(Selector::Active)));
+        assert_eq!(editor.text(), "a\nb\n");
+    }
+}
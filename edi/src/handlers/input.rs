@@ -1,26 +1,428 @@
-use edi_lib::brand::Id;
+use std::time::Duration;
+
+use edi_lib::{brand::Id, string::position::CharSearch};
+use edi_term::input::Input;
 
 use crate::{
     app::{
-        action::Action, buffer_bundle::BufferBundle, buffers::Selector, meta::Flags, state::State,
+        action::{Action, Count, MoveAction, Operator, Sequence},
+        buffer_bundle::BufferBundle,
+        buffers::Selector,
+        meta::Flags,
+        registers::Registers,
+        state::State,
         Mode,
     },
     controller::{self, Handle},
+    error::Result,
     event::{self, Event, Payload},
-    query::{CommandQuery, HistoryQuery, MoveQuery, SpawnQuery, WriteQuery},
+    query::{
+        CommandQuery, CompletionQuery, DiagnosticsQuery, DrawQuery, ExplorerQuery, HistoryQuery,
+        LspQuery, MoveQuery, OperatorQuery, OperatorTarget, RegistersQuery, SearchQuery,
+        SpawnQuery, SpellQuery, SubstituteQuery, WriteQuery,
+    },
 };
 
-pub struct Handler;
+/// How long to wait for the next key of an unfinished sequence
+const SEQUENCE_TIMEOUT: Duration = Duration::from_secs(1);
+/// The most user-defined mappings expanded one within another, so recursive ones stop
+const MAX_MAPPING_DEPTH: usize = 100;
+
+pub struct Handler {
+    /// Whether the next keypress names a register
+    awaiting_register: bool,
+    /// The count of `r` while it waits for the character to replace the ones under the cursor
+    /// with
+    replacing: Option<Count>,
+    /// Whether the search goes backward and whether it stops next to the character, along with
+    /// the count, while `f`, `F`, `t` or `T` waits for the character to search for
+    finding: Option<(bool, bool, Count)>,
+    /// The last character searched for in a line, which `;` and `,` search for again
+    last_find: Option<CharSearch>,
+    /// The register the next command uses, the unnamed one if `None`
+    register: Option<char>,
+    /// The count typed in front of the next command
+    count: Count,
+    /// The operator waiting for a motion, along with the register and the count it uses
+    operator: Option<(Operator, Option<char>, Count)>,
+    /// The inputs of an unfinished sequence
+    pending: Vec<Input>,
+    /// The actions to run if the pending inputs time out
+    fallback: Vec<Action>,
+    /// Counts the started sequences, so a timeout of a finished one is ignored
+    sequence: usize,
+    /// The keys of the command typed so far, shown in the statusline
+    typed: String,
+    /// The keys the statusline shows at the moment
+    shown: String,
+    /// How many mappings were expanded to get to the input that is handled
+    depth: usize,
+}
 
 impl Handler {
     pub const fn new() -> Self {
-        Self
+        Self {
+            awaiting_register: false,
+            replacing: None,
+            finding: None,
+            last_find: None,
+            register: None,
+            count: Count::new(),
+            operator: None,
+            pending: Vec::new(),
+            fallback: Vec::new(),
+            sequence: 0,
+            typed: String::new(),
+            shown: String::new(),
+            depth: 0,
+        }
+    }
+
+    /// Handles a typed key, or one typed by a mapping `depth` mappings deep. The user-defined
+    /// mappings are only looked up if `remap` is set
+    fn handle_input(
+        &mut self,
+        app_state: &State,
+        input: &Input,
+        (remap, depth): (bool, usize),
+        ctrl: &mut Handle<State>,
+    ) {
+        let (active_mode, active_flags) = app_state
+            .buffers
+            .active()
+            .map(BufferBundle::meta)
+            .map_or((Mode::Normal, Flags::empty()), |bundle| {
+                (bundle.keymap_mode(), bundle.flags)
+            });
+        let mode = if self.operator.is_some() {
+            Mode::OperatorPending
+        } else {
+            active_mode
+        };
+
+        if app_state.message.is_some() || !app_state.popup.is_empty() {
+            ctrl.query_draw(DrawQuery::ClearMessage);
+        }
+        let completing = matches!(mode, Mode::Insert | Mode::Normal)
+            && app_state
+                .buffers
+                .active()
+                .is_some_and(|bundle| bundle.meta().completion.is_some());
+        if completing && self.pending.is_empty() && Self::complete(mode, input, ctrl) {
+            return;
+        }
+        if matches!(mode, Mode::Normal | Mode::OperatorPending | Mode::Explorer) {
+            self.type_key(input);
+        }
+        if self.select_register(input)
+            || self.replace_chars(input, ctrl)
+            || self.find_char(app_state, input, ctrl)
+            || self.type_count(mode, input)
+        {
+            self.show_typed(ctrl);
+            return;
+        }
+
+        self.depth = depth;
+        self.pending.push(input.clone());
+        let actions = match app_state
+            .mapper
+            .map_sequence(&self.pending, mode, active_flags, remap)
+        {
+            Sequence::Pending { fallback } => {
+                self.fallback = fallback.into_vec();
+                self.sequence += 1;
+                ctrl.add_event_after(
+                    SEQUENCE_TIMEOUT,
+                    Payload::SequenceTimedOut {
+                        sequence: self.sequence,
+                    },
+                );
+                self.show_typed(ctrl);
+                return;
+            }
+            // The keys before the input do not start a longer sequence with it, so they run by
+            // themselves and the input is handled once they are done. After an operator such keys
+            // cancel it instead
+            Sequence::Complete(actions)
+                if actions.is_empty()
+                    && self.pending.len() > 1
+                    && mode != Mode::OperatorPending =>
+            {
+                self.pending.clear();
+                let fallback = std::mem::take(&mut self.fallback);
+                self.run(ctrl, app_state, fallback);
+                ctrl.add_event(Payload::MappedInput {
+                    input: input.clone(),
+                    remap,
+                    depth,
+                });
+                return;
+            }
+            Sequence::Complete(actions) => actions.into_vec(),
+        };
+        self.pending.clear();
+        self.fallback.clear();
+
+        self.run(ctrl, app_state, actions);
+    }
+
+    /// Handles an input typed while the menu of the candidates for the word being typed, or of the
+    /// suggestions for a misspelled word in the normal mode, is open. Enter and Tab accept the
+    /// selected candidate, and keys that do not edit the word close the menu before they are
+    /// handled. Returns whether the input was consumed
+    fn complete(mode: Mode, input: &Input, ctrl: &mut Handle<State>) -> bool {
+        let normal = mode == Mode::Normal;
+        match input {
+            Input::Enter | Input::Keypress('\t') => {
+                ctrl.query_completion(CompletionQuery::Accept);
+                true
+            }
+            Input::Keypress(_) | Input::Backspace | Input::Control('n' | 'p') if !normal => false,
+            Input::Keypress('j') | Input::Control('n') | Input::ArrowDown if normal => {
+                ctrl.query_completion(CompletionQuery::Select { reverse: false });
+                true
+            }
+            Input::Keypress('k') | Input::Control('p') | Input::ArrowUp if normal => {
+                ctrl.query_completion(CompletionQuery::Select { reverse: true });
+                true
+            }
+            Input::Escape if normal => {
+                ctrl.query_completion(CompletionQuery::Close(Selector::Active));
+                true
+            }
+            _ => {
+                ctrl.query_completion(CompletionQuery::Close(Selector::Active));
+                false
+            }
+        }
     }
 
-    fn handle_action(ctrl: &mut Handle<State>, state: &State, action: Action) {
+    /// Runs what the pending inputs map to by themselves, as no more input followed them
+    fn time_out(&mut self, app_state: &State, sequence: usize, ctrl: &mut Handle<State>) {
+        if sequence != self.sequence || self.pending.is_empty() {
+            return;
+        }
+
+        self.pending.clear();
+        let actions = std::mem::take(&mut self.fallback);
+        self.run(ctrl, app_state, actions);
+    }
+
+    fn run(&mut self, ctrl: &mut Handle<State>, app_state: &State, actions: Vec<Action>) {
+        // Keys that mean nothing after an operator cancel it
+        if actions.is_empty() {
+            self.operator = None;
+        }
+        let count = std::mem::take(&mut self.count);
+        for action in actions {
+            self.handle_action(ctrl, app_state, action, count);
+        }
+
+        let awaiting = self.awaiting_register || self.replacing.is_some() || self.finding.is_some();
+        if self.operator.is_none() && !awaiting {
+            self.typed.clear();
+        }
+        self.show_typed(ctrl);
+    }
+
+    fn type_key(&mut self, input: &Input) {
+        match *input {
+            Input::Keypress(c) => self.typed.push(c),
+            Input::Control(c) => {
+                self.typed.push('^');
+                self.typed.push(c);
+            }
+            _ => {}
+        }
+    }
+
+    fn show_typed(&mut self, ctrl: &mut Handle<State>) {
+        if self.shown != self.typed {
+            self.shown.clone_from(&self.typed);
+            ctrl.query_draw(DrawQuery::PendingKeys(self.typed.clone()));
+        }
+    }
+
+    /// Consumes the input if it names a register after `"`
+    fn select_register(&mut self, input: &Input) -> bool {
+        if !std::mem::take(&mut self.awaiting_register) {
+            return false;
+        }
+
+        match input {
+            &Input::Keypress(c) if Registers::is_valid_name(c) => self.register = Some(c),
+            _ => self.register = None,
+        }
+        true
+    }
+
+    /// Consumes the input if it is the character `r` replaces the ones under the cursor with,
+    /// Enter standing for a newline. Other keys cancel it
+    fn replace_chars(&mut self, input: &Input, ctrl: &mut Handle<State>) -> bool {
+        let Some(count) = self.replacing.take() else {
+            return false;
+        };
+
+        let c = match *input {
+            Input::Keypress(c) => Some(c),
+            Input::Enter => Some('\n'),
+            _ => None,
+        };
+        if let Some(c) = c {
+            ctrl.query_write(WriteQuery::ReplaceChars {
+                c,
+                count: count.get(),
+            });
+        }
+        self.typed.clear();
+        true
+    }
+
+    /// Consumes the input if it is the character `f`, `F`, `t` or `T` searches for, and moves to
+    /// it or applies the pending operator up to it. Other keys cancel the search along with the
+    /// operator
+    fn find_char(&mut self, app_state: &State, input: &Input, ctrl: &mut Handle<State>) -> bool {
+        let Some((backward, till, count)) = self.finding.take() else {
+            return false;
+        };
+
+        let actions = match *input {
+            Input::Keypress(c) => {
+                let search = CharSearch { c, backward, till };
+                self.last_find = Some(search);
+                vec![Action::move_once(MoveAction::FindChar {
+                    search,
+                    repeated: false,
+                })]
+            }
+            _ => Vec::new(),
+        };
+        self.count = count;
+        self.run(ctrl, app_state, actions);
+        true
+    }
+
+    /// Consumes the input if it is a digit of a count
+    fn type_count(&mut self, mode: Mode, input: &Input) -> bool {
+        let counted = matches!(mode, Mode::Normal | Mode::OperatorPending | Mode::Explorer);
+        if !counted || !self.pending.is_empty() {
+            return false;
+        }
+
+        match *input {
+            Input::Keypress(c) => self.count.push(c),
+            _ => false,
+        }
+    }
+
+    /// Applies the pending operator to the text the motion moves over, `count` being the one typed
+    /// after the operator
+    fn operate(
+        &mut self,
+        ctrl: &mut Handle<State>,
+        count: Count,
+        target: impl FnOnce(usize) -> OperatorTarget,
+    ) {
+        let Some((operator, register, operator_count)) = self.operator.take() else {
+            return;
+        };
+
+        ctrl.query_operator(OperatorQuery {
+            operator,
+            target: target(operator_count.times(count).get()),
+            register,
+        });
+    }
+
+    /// Runs the command or the search typed in the active prompt
+    fn submit(ctrl: &mut Handle<State>, state: &State) {
+        let _span = edi_lib::span!("submit");
+        edi_lib::debug!(
+            "hit submit action with {buf_count} buffers",
+            buf_count = state.buffers.len()
+        );
+        let Some(bundle) = state.buffers.active() else {
+            edi_lib::debug!("invalid submit query, no buffers are found");
+            return;
+        };
+        let prompt = bundle.id();
+
+        if bundle.meta().flags.is_search() {
+            ctrl.query_search(SearchQuery::Submit);
+        } else if bundle.meta().flags.is_explorer_prompt() {
+            ctrl.query_explorer(ExplorerQuery::Submit);
+        } else {
+            let cmd_buf = bundle.buffer();
+            let command: String = cmd_buf.inner.chars().collect();
+
+            ctrl.query_command(CommandQuery::Run(command));
+        }
+
+        edi_lib::debug!(
+            "exit submit action with {buf_count} buffers",
+            buf_count = state.buffers.len()
+        );
+        // The command may make another buffer active, so the prompt is closed by its id
+        ctrl.query_switch_mode(Selector::WithId(prompt), Mode::Normal);
+    }
+
+    /// Handles the keys a mapping types as if they were typed one after another
+    fn type_keys(&self, ctrl: &mut Handle<State>, inputs: Vec<Input>, remap: bool) {
+        if self.depth >= MAX_MAPPING_DEPTH {
+            edi_lib::debug!("recursive mapping, stopped after {MAX_MAPPING_DEPTH} levels");
+            return;
+        }
+        for input in inputs {
+            ctrl.add_event(Payload::MappedInput {
+                input,
+                remap,
+                depth: self.depth + 1,
+            });
+        }
+    }
+
+    fn handle_action(
+        &mut self,
+        ctrl: &mut Handle<State>,
+        state: &State,
+        action: Action,
+        count: Count,
+    ) {
         let _span = edi_lib::span!("handle_action");
 
         match action {
+            Action::Operator(operator) => match self.operator {
+                Some((pending, ..)) if pending == operator => {
+                    self.operate(ctrl, count, OperatorTarget::Lines);
+                }
+                Some(_) => self.operator = None,
+                None => self.operator = Some((operator, self.register.take(), count)),
+            },
+            Action::OperatorOnLines(operator) => {
+                self.operator = Some((operator, self.register.take(), Count::new()));
+                self.operate(ctrl, count, OperatorTarget::Lines);
+            }
+            Action::OperatorWithMotion(operator, action) => {
+                self.operator = Some((operator, self.register.take(), Count::new()));
+                self.operate(ctrl, count, |n| OperatorTarget::Motion {
+                    action,
+                    repeat: n,
+                });
+            }
+            Action::Move { action, repeat } if self.operator.is_some() => {
+                let total = self
+                    .operator
+                    .map_or(count, |(.., operator_count)| operator_count.times(count));
+                let action = action.with_count(total);
+                self.operate(ctrl, count, |n| OperatorTarget::Motion {
+                    action,
+                    repeat: repeat * n,
+                });
+            }
+            Action::SelectObject(object) => {
+                self.operate(ctrl, count, |_| OperatorTarget::Object(object));
+            }
             Action::SwitchMode(Mode::Terminal) => {
                 ctrl.query_spawn(SpawnQuery::TerminalBuffer);
             }
@@ -30,67 +432,185 @@ impl Handler {
             Action::InsertChar(c) => {
                 ctrl.query_write(WriteQuery::WriteChar(c));
             }
+            Action::InsertStr(s) => {
+                ctrl.query_write(WriteQuery::WriteStr(s));
+            }
             Action::DeleteChar => {
                 ctrl.query_write(WriteQuery::DeleteChar);
             }
-            Action::Submit => {
-                let _span = edi_lib::span!("submit");
-                edi_lib::debug!(
-                    "hit submit action with {buf_count} buffers",
-                    buf_count = state.buffers.len()
-                );
-                let Some(bundle) = state.buffers.first() else {
-                    edi_lib::debug!("invalid submit query, no buffers are found");
-                    return;
-                };
-
-                let cmd_buf = bundle.buffer();
-                let command: String = cmd_buf.inner.chars().collect();
-
-                ctrl.query_command(CommandQuery { command });
-
-                edi_lib::debug!(
-                    "exit submit action with {buf_count} buffers",
-                    buf_count = state.buffers.len()
-                );
-                ctrl.query_switch_mode(Selector::Active, Mode::Normal);
-            }
-
+            Action::Submit => Self::submit(ctrl, state),
             Action::Move { action, repeat } => {
-                ctrl.query_move(MoveQuery::Action { action, repeat });
+                ctrl.query_move(MoveQuery::Action {
+                    action: action.with_count(count),
+                    repeat: repeat * count.get(),
+                });
+            }
+            Action::SelectRegister => {
+                self.awaiting_register = true;
+            }
+            Action::YankLine => {
+                let register = self.register.take();
+                ctrl.query_registers(RegistersQuery::YankLine { register });
             }
+            Action::Paste { before } => {
+                let register = self.register.take();
+                ctrl.query_registers(RegistersQuery::Paste { register, before });
+            }
+            Action::ReplaceChar => self.replacing = Some(count),
+            Action::FindChar { backward, till } => self.finding = Some((backward, till, count)),
+            Action::RepeatFind { reverse } => match self.last_find {
+                Some(search) => {
+                    let search = if reverse { search.reversed() } else { search };
+                    let action = MoveAction::FindChar {
+                        search,
+                        repeated: true,
+                    };
+                    self.handle_action(ctrl, state, Action::move_once(action), count);
+                }
+                None => self.operator = None,
+            },
+            Action::Keys { inputs, remap } => self.type_keys(ctrl, inputs, remap),
+            ref action => Self::query(ctrl, action, count),
+        }
+    }
+
+    /// Runs the actions that only submit a query, whatever keys were typed before them
+    fn query(ctrl: &mut Handle<State>, action: &Action, count: Count) {
+        match *action {
             Action::Undo => {
                 ctrl.query_history(HistoryQuery::Undo(Selector::Active));
             }
             Action::Redo => {
                 ctrl.query_history(HistoryQuery::Redo(Selector::Active));
             }
+            Action::Suspend => {
+                ctrl.query_draw(DrawQuery::Suspend);
+            }
+            Action::StartSearch { backward } => {
+                ctrl.query_spawn(SpawnQuery::SearchBuffer { backward });
+            }
+            Action::SearchNext { reverse } => {
+                ctrl.query_search(SearchQuery::Next { reverse });
+            }
+            Action::OpenLine { above } => ctrl.query_write(WriteQuery::OpenLine { above }),
+            Action::Align(position) => ctrl.query_draw(DrawQuery::Align(position)),
+            Action::ScrollColumns { right } => ctrl.query_draw(DrawQuery::ScrollColumns {
+                right,
+                count: count.get(),
+            }),
+            Action::ShowExplorer => ctrl.query_explorer(ExplorerQuery::Show(None)),
+            Action::Explore(operation) => ctrl.query_explorer(ExplorerQuery::Run(operation)),
+            Action::CompleteCommand => {
+                ctrl.query_command(CommandQuery::Complete);
+            }
+            Action::RecallCommand { older } => {
+                ctrl.query_command(CommandQuery::Recall { older });
+            }
+            Action::ConfirmSubstitution(answer) => {
+                ctrl.query_substitute(SubstituteQuery::Answer(answer));
+            }
+            Action::Hover => ctrl.query_lsp(LspQuery::Hover),
+            Action::GotoDefinition => ctrl.query_lsp(LspQuery::Definition),
+            Action::Diagnostic { reverse } => {
+                ctrl.query_diagnostics(DiagnosticsQuery::Jump { reverse });
+            }
+            Action::Misspelling { reverse } => {
+                ctrl.query_spell(SpellQuery::Jump { reverse });
+            }
+            Action::SuggestSpelling => ctrl.query_spell(SpellQuery::Suggest),
+            Action::ToggleCase => ctrl.query_operator(OperatorQuery {
+                operator: Operator::ToggleCase,
+                target: OperatorTarget::Chars(count.get()),
+                register: None,
+            }),
+            Action::Complete { reverse } => {
+                ctrl.query_completion(CompletionQuery::Select { reverse });
+            }
+            // The other actions depend on the typed keys and are run by `handle_action`
+            _ => {}
         }
     }
 }
 
 impl controller::EventHandler<State> for Handler {
-    fn handle(&mut self, app_state: &State, event: &Event, ctrl: &mut Handle<State>) {
-        let Payload::Input(input) = event.payload() else {
-            return;
+    fn handle(&mut self, app_state: &State, event: &Event, ctrl: &mut Handle<State>) -> Result<()> {
+        let _span = edi_lib::span!("input");
+
+        let (input, mapping) = match *event.payload() {
+            Payload::Input(ref input) => (input, (true, 0)),
+            Payload::MappedInput {
+                ref input,
+                remap,
+                depth,
+            } => (input, (remap, depth)),
+            Payload::SequenceTimedOut { sequence } => {
+                self.time_out(app_state, sequence, ctrl);
+                return Ok(());
+            }
+            _ => return Ok(()),
         };
 
-        let _span = edi_lib::span!("input");
+        self.handle_input(app_state, input, mapping, ctrl);
+        Ok(())
+    }
 
-        let (active_mode, active_flags) = app_state
-            .buffers
-            .active()
-            .map(BufferBundle::meta)
-            .map(|bundle| (bundle.mode(), bundle.flags))
-            .unwrap_or((Mode::Normal, Flags::empty()));
+    fn interested_in(&self, _own_id: Id, event: &Event) -> bool {
+        event.ty().is_oneof(&[
+            event::Type::Input,
+            event::Type::MappedInput,
+            event::Type::SequenceTimedOut,
+        ])
+    }
+}
 
-        let actions = app_state.mapper.map_input(input, active_mode, active_flags);
-        for action in actions {
-            Self::handle_action(ctrl, app_state, action);
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use edi_term::input::Input;
+
+    use crate::{
+        app::{state::State, testing::Editor, Mode},
+        controller::{self, Handle},
+        error::Result,
+        query::{self, DrawQuery, Payload, Query},
+    };
+
+    /// Stands in for the handler that draws, counting the times suspending was asked for
+    struct Suspended(Arc<AtomicUsize>);
+
+    impl controller::QueryHandler<State> for Suspended {
+        fn handle(&mut self, _: &mut State, query: Query, _: &mut Handle<State>) -> Result<()> {
+            if matches!(query.payload(), Payload::Draw(DrawQuery::Suspend)) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+            Ok(())
         }
     }
 
-    fn interested_in(&self, _own_id: Id, event: &Event) -> bool {
-        event.ty() == event::Type::Input
+    #[test]
+    fn suspend_empty_buffer() {
+        let mut editor = Editor::new("suspend", "");
+        let suspended = Arc::new(AtomicUsize::new(0));
+        editor
+            .controller
+            .attach_query_handler(query::Type::Draw, Suspended(Arc::clone(&suspended)));
+
+        assert!(!editor.input(Input::Control('z')));
+        assert_eq!(suspended.load(Ordering::Relaxed), 1);
+        assert_eq!(editor.text(), "");
+        assert_eq!(editor.bundle().meta().mode, Mode::Normal);
+        assert_eq!(editor.message(), None);
+
+        // Once continued, the buffer is edited as before
+        editor.keys("ix");
+        editor.input(Input::Escape);
+        assert_eq!(editor.text(), "x\n");
+        assert!(!editor.input(Input::Control('z')));
+        assert_eq!(suspended.load(Ordering::Relaxed), 2);
+        assert_eq!(editor.text(), "x\n");
     }
 }
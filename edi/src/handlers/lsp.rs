@@ -0,0 +1,246 @@
+use std::{path::Path, time::Duration};
+
+use edi_frame::unit::Unit;
+use edi_lib::{brand::Id, vec2::Vec2};
+
+use crate::{
+    app::{
+        buffer_bundle::BufferBundle, buffers::Selector, diagnostics::Origin, message::Message,
+        state::State,
+    },
+    controller::{self, Handle},
+    error::{AppError, Result},
+    event::{self, Event},
+    lsp::{Position, Response},
+    query::{LspQuery, Payload, Query},
+};
+
+pub struct Handler;
+
+impl Handler {
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl controller::QueryHandler<State> for Handler {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
+        let _span = edi_lib::span!("lsp");
+
+        let Payload::Lsp(lsp_query) = query.into_payload() else {
+            return Err(AppError::unexpected(
+                "non-lsp query submitted to lsp query handler, this is likely a bug",
+            ));
+        };
+
+        match lsp_query {
+            LspQuery::CharWritten {
+                buffer_id,
+                offset,
+                c,
+            } => {
+                if state.lsp.write(buffer_id, offset, c) {
+                    Self::schedule_sync(ctrl, buffer_id);
+                }
+            }
+            LspQuery::CharDeleted { buffer_id, offset } => {
+                if state.lsp.delete(buffer_id, offset) {
+                    Self::schedule_sync(ctrl, buffer_id);
+                }
+            }
+            LspQuery::TextReplaced {
+                buffer_id,
+                range,
+                text,
+            } => {
+                if state.lsp.replace(buffer_id, range, &text) {
+                    Self::schedule_sync(ctrl, buffer_id);
+                }
+            }
+            LspQuery::Sync { buffer_id } => state.lsp.sync(buffer_id),
+            LspQuery::Receive { filetype, message } => {
+                if let Some(response) = state.lsp.receive(&filetype, &message) {
+                    Self::show(state, response);
+                    ctrl.query_redraw();
+                }
+            }
+            LspQuery::Hover => Self::ask(state, ctrl, true),
+            LspQuery::Definition => Self::ask(state, ctrl, false),
+        }
+        Ok(())
+    }
+
+    fn check_event(&mut self, state: &State, event: &Event, ctrl: &mut Handle<State>) {
+        let Some(query) = Self::query_of(event.payload()) else {
+            return;
+        };
+
+        let is_open = match query {
+            LspQuery::CharWritten { buffer_id, .. }
+            | LspQuery::CharDeleted { buffer_id, .. }
+            | LspQuery::TextReplaced { buffer_id, .. } => state.lsp.is_open(buffer_id),
+            _ => true,
+        };
+        if is_open {
+            ctrl.query_lsp(query);
+        }
+    }
+
+    fn interested_in(&self, _own_id: Id, event: &Event) -> bool {
+        event.ty().is_oneof(&[
+            event::Type::CharWritten,
+            event::Type::CharDeleted,
+            event::Type::TextReplaced,
+            event::Type::LspSyncDue,
+            event::Type::LspMessage,
+        ])
+    }
+}
+
+impl Handler {
+    /// Returns the query that handles the event, recording the change of the buffer it tells about
+    fn query_of(payload: &event::Payload) -> Option<LspQuery> {
+        let query = match *payload {
+            event::Payload::CharWritten {
+                buffer_id,
+                offset,
+                c,
+            } => LspQuery::CharWritten {
+                buffer_id,
+                offset,
+                c,
+            },
+            event::Payload::CharDeleted {
+                buffer_id, offset, ..
+            } => LspQuery::CharDeleted { buffer_id, offset },
+            event::Payload::TextReplaced {
+                buffer_id,
+                offset,
+                ref removed,
+                ref inserted,
+            } => LspQuery::TextReplaced {
+                buffer_id,
+                range: offset..offset + removed.chars().count(),
+                text: inserted.clone(),
+            },
+            event::Payload::LspSyncDue { buffer_id } => LspQuery::Sync { buffer_id },
+            event::Payload::LspMessage {
+                ref filetype,
+                ref message,
+            } => LspQuery::Receive {
+                filetype: filetype.clone(),
+                message: message.clone(),
+            },
+            _ => return None,
+        };
+        Some(query)
+    }
+
+    /// Makes the changes of the buffer be sent once the events of the ones being made now are
+    /// handled, so a whole paste or undo is sent at once
+    fn schedule_sync(ctrl: &mut Handle<State>, buffer_id: Id) {
+        ctrl.add_event_after(Duration::ZERO, event::Payload::LspSyncDue { buffer_id });
+    }
+
+    /// Asks the language server of the active buffer about the symbol under the cursor, for the
+    /// information about it if `hover` is set or for its definition otherwise
+    fn ask(state: &mut State, ctrl: &mut Handle<State>, hover: bool) {
+        let Some(bundle) = state.buffers.active() else {
+            return;
+        };
+        let (id, offset) = (bundle.id(), bundle.buffer().cursor_offset);
+
+        if !state.lsp.is_open(id) {
+            state.notify(Message::error("no language server"));
+        } else if !state.lsp.is_ready(id) {
+            state.notify(Message::error("the language server is starting"));
+        } else if hover {
+            state.lsp.hover(id, offset);
+            return;
+        } else {
+            state.lsp.definition(id, offset);
+            return;
+        }
+        ctrl.query_redraw();
+    }
+
+    fn show(state: &mut State, response: Response) {
+        match response {
+            Response::Hover(text) => {
+                state.notify(text.map_or_else(|| Message::error("no information"), Message::info));
+            }
+            Response::Definition(Some((path, position))) => {
+                Self::jump_to_definition(state, &path, position);
+            }
+            Response::Definition(None) => {
+                state.notify(Message::error("no definition found"));
+            }
+            Response::Diagnostics(buffer_id, diagnostics) => {
+                state.set_diagnostics(buffer_id, Origin::Lsp, diagnostics);
+            }
+        }
+    }
+
+    /// Makes the buffer of the file active, opening it if it is not open yet, and moves the
+    /// cursor to the position
+    fn jump_to_definition(state: &mut State, path: &Path, position: Position) {
+        let same_file =
+            |other: &Path| std::fs::canonicalize(other).ok() == std::fs::canonicalize(path).ok();
+        let open = state
+            .buffers
+            .iter()
+            .find(|bundle| bundle.meta().filepath.as_deref().is_some_and(same_file))
+            .map(BufferBundle::id);
+
+        let id = match open {
+            Some(id) => id,
+            None => match state.open_file(path, Vec2::new(Unit::full_width(), Unit::full_height()))
+            {
+                Ok(id) => id,
+                Err(e) => {
+                    edi_lib::debug!("unable to open {path:?}: {e}");
+                    state.notify(Message::error(format!(
+                        "cannot open {}: {e}",
+                        path.display()
+                    )));
+                    return;
+                }
+            },
+        };
+
+        state.buffers.set_active(id);
+        if let Some(bundle) = state.buffers.get_mut(&Selector::WithId(id)) {
+            let (buffer, _) = bundle.as_split_mut_silent();
+            buffer.cursor_offset = position.to_offset(&buffer.inner);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edi_lib::brand::Tag;
+
+    use super::*;
+
+    #[test]
+    fn replaced_text() {
+        let id = Tag::new().child_id();
+        let query = Handler::query_of(&event::Payload::TextReplaced {
+            buffer_id: id,
+            offset: 1,
+            removed: String::from("😀b\né"),
+            inserted: String::from("x\n"),
+        });
+        let Some(LspQuery::TextReplaced {
+            buffer_id,
+            range,
+            text,
+        }) = query
+        else {
+            panic!("expected a replacement, got {query:?}");
+        };
+        assert_eq!(buffer_id, id);
+        assert_eq!(range, 1..5);
+        assert_eq!(text, "x\n");
+    }
+}
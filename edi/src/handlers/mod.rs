@@ -1,8 +1,19 @@
 pub mod command;
+pub mod completion;
+pub mod diagnostics;
 pub mod draw;
+pub mod explorer;
+pub mod git;
 pub mod history;
 pub mod input;
+pub mod lsp;
 pub mod mode;
 pub mod movement;
+pub mod operator;
+pub mod registers;
+pub mod search;
+pub mod shell;
 pub mod spawn;
+pub mod spell;
+pub mod substitute;
 pub mod write;
@@ -1,8 +1,9 @@
-use edi_term::escaping::{ANSIEscape, CursorStyle};
+use edi_term::escaping::CursorStyle;
 
 use crate::{
-    app::{buffers::Selector, state::State, Mode},
+    app::{buffer_bundle::BufferBundle, buffers::Selector, state::State, Mode},
     controller::{self, Handle},
+    error::{AppError, Result},
     event::{self},
     query::{Payload, Query},
 };
@@ -16,28 +17,39 @@ impl Handler {
 }
 
 impl controller::QueryHandler<State> for Handler {
-    fn handle(&mut self, app_state: &mut State, query: Query, ctrl: &mut Handle<State>) {
+    fn handle(
+        &mut self,
+        app_state: &mut State,
+        query: Query,
+        ctrl: &mut Handle<State>,
+    ) -> Result<()> {
         let _span = edi_lib::span!("mode");
         let Payload::SwitchMode {
             buffer_selector,
             target_mode,
         } = query.into_payload()
         else {
-            edi_lib::debug!("non-mode query submitted to mode query handler, this is likely a bug");
-            return;
+            return Err(AppError::unexpected(
+                "non-mode query submitted to mode query handler, this is likely a bug",
+            ));
         };
 
+        let active = app_state.buffers.active().map(BufferBundle::id);
         let Some(bundle) = app_state.buffers.get_mut(&buffer_selector) else {
             edi_lib::debug!("no buffer found by selector: {buffer_selector:?}");
-            return;
+            return Ok(());
         };
 
+        if target_mode == Mode::Insert {
+            bundle.meta().ensure_modifiable()?;
+        }
+
         let id = bundle.id();
         edi_lib::debug!("ID: {id:?}");
         bundle.meta_mut().set_mode(target_mode);
 
-        if !bundle.is_active() {
-            return;
+        if active != Some(id) {
+            return Ok(());
         }
 
         if bundle.meta().flags.is_terminal() {
@@ -47,14 +59,15 @@ impl controller::QueryHandler<State> for Handler {
                 buffers_left = app_state.buffers.len()
             );
             ctrl.query_switch_mode(Selector::Active, target_mode);
-            return;
+            return Ok(());
         }
 
-        if bundle.meta().mode() == Mode::Insert {
-            let _ = ANSIEscape::ChangeCursor(CursorStyle::Line).write_to_stdout();
+        let cursor_style = if bundle.meta().mode() == Mode::Insert {
+            CursorStyle::Line
         } else {
-            let _ = ANSIEscape::ChangeCursor(CursorStyle::Block).write_to_stdout();
-        }
+            CursorStyle::Block
+        };
+        app_state.window.set_cursor_style(cursor_style);
 
         edi_lib::debug!("mode switched to: {target_mode:?}");
 
@@ -64,5 +77,6 @@ impl controller::QueryHandler<State> for Handler {
         });
 
         ctrl.query_redraw();
+        Ok(())
     }
 }
@@ -1,6 +1,7 @@
 use crate::{
     app::{self, action::MoveAction, state::State},
     controller::{self, Handle},
+    error::{AppError, Result},
     query::{MoveQuery, Payload, Query},
 };
 
@@ -13,12 +14,13 @@ impl Handler {
 }
 
 impl controller::QueryHandler<State> for Handler {
-    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
         let _span = edi_lib::span!("write");
 
         let Payload::Move(move_query) = query.into_payload() else {
-            edi_lib::debug!("non-move query submitted to move query handler, this is likely a bug");
-            return;
+            return Err(AppError::unexpected(
+                "non-move query submitted to move query handler, this is likely a bug",
+            ));
         };
 
         match move_query {
@@ -28,6 +30,7 @@ impl controller::QueryHandler<State> for Handler {
         }
 
         ctrl.query_redraw();
+        Ok(())
     }
 }
 
@@ -38,12 +41,11 @@ impl Handler {
         action: &MoveAction,
         repeat: usize,
     ) {
-        state.within_active_buffer(
-            |_, mut buffer, meta| {
-                app::handle_move(&mut buffer, meta, action, repeat);
-                buffer.ctrl().query_redraw();
-            },
-            ctrl,
-        );
+        let Some(bundle) = state.buffers.active_mut() else {
+            return;
+        };
+        let (mut buffer, meta) = bundle.as_split_mut(ctrl);
+        app::handle_move(&state.context, &mut buffer, meta, action, repeat);
+        buffer.ctrl().query_redraw();
     }
 }
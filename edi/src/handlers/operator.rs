@@ -0,0 +1,507 @@
+use std::ops::Range;
+
+use edi_lib::{
+    buffer,
+    language::Registry,
+    string::{
+        comment, indent,
+        position::{LinePosition, WordPosition},
+        search::{self, Searcher},
+    },
+};
+use edi_rope::Rope;
+
+use crate::{
+    app::{
+        self,
+        action::{Direction, MoveAction, Operator},
+        buffer_bundle::BufferBundle,
+        buffers::Selector,
+        context::{Context, Options},
+        message::Message,
+        registers::{Register, RegisterKind},
+        state::State,
+        Mode,
+    },
+    controller::{self, Handle},
+    error::{self, AppError},
+    event,
+    query::{DrawQuery, OperatorQuery, OperatorTarget, Payload, Query},
+};
+
+/// How a motion selects the text an operator acts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MotionKind {
+    /// The characters from the cursor up to, but not including, where the motion ends
+    Exclusive,
+    /// The characters from the cursor up to and including where the motion ends
+    Inclusive,
+    /// Whole lines from the cursor to where the motion ends
+    Linewise,
+}
+
+impl MotionKind {
+    const fn of(action: &MoveAction) -> Self {
+        match *action {
+            MoveAction::Regular(Direction::Up | Direction::Down)
+            | MoveAction::HalfScreen(_)
+            | MoveAction::FullScreen(_)
+            | MoveAction::Global(_) => Self::Linewise,
+            MoveAction::Word {
+                position: WordPosition::End | WordPosition::PreviousEnd,
+                ..
+            } => Self::Inclusive,
+            MoveAction::FindChar { search, .. } if !search.backward => Self::Inclusive,
+            MoveAction::Regular(Direction::Left | Direction::Right)
+            | MoveAction::DisplayLine(_)
+            | MoveAction::InLine(_)
+            | MoveAction::Word { .. }
+            | MoveAction::FindChar { .. } => Self::Exclusive,
+        }
+    }
+}
+
+/// The text an operator acts on
+#[derive(Debug)]
+struct Selection {
+    range: Range<usize>,
+    kind: RegisterKind,
+}
+
+impl Selection {
+    const fn charwise(range: Range<usize>) -> Self {
+        Self {
+            range,
+            kind: RegisterKind::Charwise,
+        }
+    }
+
+    /// Selects the lines from the one containing `a` to the one containing `b`, including the
+    /// newline at the end
+    fn lines(buffer: &buffer::Buffer, a: usize, b: usize) -> Self {
+        let rope = &buffer.inner;
+        let first = rope.line_of_index(a.min(b));
+        let last = rope.line_of_index(a.max(b));
+
+        let start = rope
+            .line_info(first)
+            .map_or(rope.len(), |line| line.character_offset);
+        let end = rope
+            .line_info(last + 1)
+            .map_or(rope.len(), |line| line.character_offset);
+
+        Self {
+            range: start..end,
+            kind: RegisterKind::Linewise,
+        }
+    }
+}
+
+pub struct Handler;
+
+impl Handler {
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Returns the text the operator acts on, or `None` if the motion did not move the cursor or
+    /// there is no such object around it
+    fn select(
+        ctx: &Context,
+        bundle: &mut BufferBundle,
+        query: &OperatorQuery,
+        ctrl: &mut Handle<State>,
+    ) -> Option<Selection> {
+        let (mut buffer, meta) = bundle.as_split_mut(ctrl);
+        let cursor = buffer.as_ref().cursor_offset;
+
+        let (action, repeat) = match query.target {
+            OperatorTarget::Lines(count) => {
+                let rope = &buffer.as_ref().inner;
+                let last = rope.line_of_index(cursor) + count.saturating_sub(1);
+                let end = rope
+                    .line_info(last)
+                    .map_or(rope.len(), |line| line.character_offset);
+                return Some(Selection::lines(buffer.as_ref(), cursor, end));
+            }
+            OperatorTarget::Chars(count) => {
+                let rope = &buffer.as_ref().inner;
+                let len = rope
+                    .chars_at(cursor)
+                    .take(count)
+                    .take_while(|&c| c != '\n')
+                    .count();
+                return (len > 0).then(|| Selection::charwise(cursor..cursor + len));
+            }
+            OperatorTarget::Object(object) => {
+                let range = object.find(&buffer.as_ref().inner, cursor)?;
+                let kind = if object.is_linewise() {
+                    RegisterKind::Linewise
+                } else {
+                    RegisterKind::Charwise
+                };
+                return Some(Selection { range, kind });
+            }
+            OperatorTarget::Motion { ref action, repeat } => (action, repeat),
+        };
+
+        let on_word = buffer
+            .as_ref()
+            .inner
+            .get(cursor)
+            .is_some_and(|c| !c.is_whitespace());
+        let moved = match *action {
+            // `cw` changes up to the end of the word as `ce` does, but keeps to the word the cursor
+            // is on if it is at its end
+            MoveAction::Word {
+                position: WordPosition::NextStart,
+                big,
+            } if query.operator == Operator::Change && on_word => {
+                let rope = &buffer.as_ref().inner;
+                let line = rope.line(rope.line_of_index(cursor))?;
+                let end = Searcher::new(&line.contents, cursor - line.character_offset)
+                    .with_big(big)
+                    .find_current_end();
+                buffer.set_cursor_offset(line.character_offset + end);
+                let to_end = MoveAction::Word {
+                    position: WordPosition::End,
+                    big,
+                };
+                app::handle_move(ctx, &mut buffer, meta, &to_end, repeat - 1);
+                let moved = buffer.as_ref().cursor_offset;
+                buffer.set_cursor_offset(cursor);
+                return Some(Selection::charwise(cursor..moved + 1));
+            }
+            MoveAction::Word {
+                position: WordPosition::NextStart,
+                ..
+            } => {
+                app::handle_move(ctx, &mut buffer, meta, action, repeat);
+                word_motion_end(
+                    &buffer.as_ref().inner,
+                    cursor,
+                    buffer.as_ref().cursor_offset,
+                )
+            }
+            _ => {
+                app::handle_move(ctx, &mut buffer, meta, action, repeat);
+                buffer.as_ref().cursor_offset
+            }
+        };
+        buffer.set_cursor_offset(cursor);
+        // A character that is not found selects nothing, rather than the one under the cursor
+        if matches!(action, MoveAction::FindChar { .. }) && moved == cursor {
+            return None;
+        }
+
+        let (start, end) = (cursor.min(moved), cursor.max(moved));
+        match MotionKind::of(action) {
+            MotionKind::Linewise => Some(Selection::lines(buffer.as_ref(), cursor, moved)),
+            MotionKind::Exclusive if start == end => None,
+            MotionKind::Exclusive => Some(Selection::charwise(start..end)),
+            MotionKind::Inclusive => {
+                let len = buffer.as_ref().inner.len();
+                Some(Selection::charwise(start..(end + 1).min(len)))
+            }
+        }
+    }
+
+    fn operate(state: &mut State, query: &OperatorQuery, ctrl: &mut Handle<State>) {
+        let Some(bundle) = state.buffers.active_mut() else {
+            return;
+        };
+        let Some(selection) = Self::select(&state.context, bundle, query, ctrl) else {
+            // Changing the nothing a motion moved over, as `s` does on an empty line, still starts
+            // inserting
+            if query.operator == Operator::Change
+                && matches!(query.target, OperatorTarget::Motion { .. })
+            {
+                ctrl.query_switch_mode(Selector::Active, Mode::Insert);
+            }
+            return;
+        };
+
+        let text: String = bundle
+            .buffer()
+            .inner
+            .substr(selection.range.clone())
+            .collect();
+        let register = match selection.kind {
+            RegisterKind::Charwise => Register::charwise(text),
+            RegisterKind::Linewise => Register::linewise(text),
+        };
+
+        match query.operator {
+            Operator::Yank => {
+                if selection.kind == RegisterKind::Charwise {
+                    let (buffer, _) = bundle.as_split_mut_silent();
+                    buffer.cursor_offset = selection.range.start;
+                }
+                state.registers.yank(query.register, register);
+            }
+            Operator::Delete => {
+                Self::delete(bundle, &selection, false, ctrl);
+                state.registers.delete(query.register, register);
+            }
+            Operator::Change => {
+                Self::delete(bundle, &selection, true, ctrl);
+                state.registers.delete(query.register, register);
+                ctrl.query_switch_mode(Selector::Active, Mode::Insert);
+            }
+            Operator::Indent => Self::shift(bundle, &selection, &state.context.options, true, ctrl),
+            Operator::Dedent => {
+                Self::shift(bundle, &selection, &state.context.options, false, ctrl);
+            }
+            Operator::Comment => {
+                if let Err(e) = Self::comment(bundle, &selection, &state.languages, ctrl) {
+                    state.notify(Message::error(e));
+                }
+            }
+            Operator::Lowercase | Operator::Uppercase | Operator::ToggleCase => {
+                let advance = matches!(query.target, OperatorTarget::Chars(_));
+                Self::convert_case(bundle, &selection, query.operator, advance, ctrl);
+            }
+        }
+    }
+
+    /// Changes the case of the letters of the selection as a single change. The cursor goes to
+    /// the start of the selection, or past its end within the line if `advance` is set
+    fn convert_case(
+        bundle: &mut BufferBundle,
+        selection: &Selection,
+        operator: Operator,
+        advance: bool,
+        ctrl: &mut Handle<State>,
+    ) {
+        let buffer_id = bundle.id();
+        let start = selection.range.start;
+        let old: String = bundle
+            .buffer()
+            .inner
+            .substr(selection.range.clone())
+            .collect();
+        let new: String = match operator {
+            Operator::Lowercase => old.to_lowercase(),
+            Operator::Uppercase => old.to_uppercase(),
+            _ => old.chars().flat_map(toggle_case).collect(),
+        };
+
+        ctrl.add_event(event::Payload::ChangeGroupStarted { buffer_id });
+        let mut buffer = bundle.buffer_mut(ctrl);
+        if new != old {
+            buffer.delete_range(selection.range.clone());
+            buffer.write_str(&new);
+        }
+        let end = start + new.chars().count();
+        let cursor = match buffer.as_ref().inner.get(end) {
+            Some(c) if advance && c != '\n' => end,
+            _ if advance => end - 1,
+            _ => start,
+        };
+        buffer.set_cursor_offset(cursor);
+        ctrl.add_event(event::Payload::ChangeGroupEnded { buffer_id });
+
+        ctrl.query_draw(DrawQuery::Rehighlight(Selector::WithId(buffer_id)));
+    }
+
+    /// Returns the numbers of the first and the last line the selection touches
+    fn line_span(bundle: &BufferBundle, selection: &Selection) -> (usize, usize) {
+        let rope = &bundle.buffer().inner;
+        let Range { start, end } = selection.range;
+        (
+            rope.line_of_index(start),
+            rope.line_of_index(end.saturating_sub(1).max(start)),
+        )
+    }
+
+    /// Replaces the lines from `first` on with `lines` as a single change and moves the cursor to
+    /// the first character of the first one that is not whitespace. Each edit is the offset from
+    /// the start of a line, the number of characters removed there and the text put in their place
+    fn edit_lines(
+        bundle: &mut BufferBundle,
+        first: usize,
+        edits: Vec<(usize, usize, String)>,
+        ctrl: &mut Handle<State>,
+    ) {
+        let buffer_id = bundle.id();
+        let rope = &bundle.buffer().inner;
+        let mut edits: Vec<_> = rope
+            .lines_at(first)
+            .zip(edits)
+            .map(|(line, (offset, removed, text))| (line.character_offset + offset, removed, text))
+            .collect();
+        // Later lines go first, so the offsets of the earlier ones stay the same
+        edits.reverse();
+
+        ctrl.add_event(event::Payload::ChangeGroupStarted { buffer_id });
+        let mut buffer = bundle.buffer_mut(ctrl);
+        for (offset, removed, text) in edits {
+            if removed > 0 {
+                buffer.delete_range(offset..offset + removed);
+            }
+            if !text.is_empty() {
+                buffer.set_cursor_offset(offset);
+                buffer.write_str(&text);
+            }
+        }
+        if let Some(line) = buffer.as_ref().inner.line_info(first) {
+            buffer.set_cursor_offset(line.character_offset);
+            buffer.move_in_line(LinePosition::CharacterStart);
+        }
+        ctrl.add_event(event::Payload::ChangeGroupEnded { buffer_id });
+
+        ctrl.query_draw(DrawQuery::Rehighlight(Selector::WithId(buffer_id)));
+    }
+
+    /// Toggles the comments of the lines the selection touches
+    fn comment(
+        bundle: &mut BufferBundle,
+        selection: &Selection,
+        languages: &Registry,
+        ctrl: &mut Handle<State>,
+    ) -> Result<(), String> {
+        let language = languages.language(&bundle.meta().filetype);
+        let Some(prefix) = &language.line_comment else {
+            return Err(String::from("the filetype has no line comments"));
+        };
+        let (first, last) = Self::line_span(bundle, selection);
+
+        let lines: Vec<_> = bundle
+            .buffer()
+            .inner
+            .lines_at(first)
+            .take(last - first + 1)
+            .map(|line| line.contents)
+            .collect();
+        let edits = comment::toggle(&lines, prefix)
+            .into_iter()
+            .zip(&lines)
+            .map(|(new, old)| (0, old.chars().count(), new))
+            .collect();
+
+        Self::edit_lines(bundle, first, edits, ctrl);
+        Ok(())
+    }
+
+    /// Indents the lines the selection touches by one shiftwidth as a single change, or removes
+    /// one shiftwidth of their indentation if `right` is not set. Empty lines are not indented
+    fn shift(
+        bundle: &mut BufferBundle,
+        selection: &Selection,
+        options: &Options,
+        right: bool,
+        ctrl: &mut Handle<State>,
+    ) {
+        let (first, last) = Self::line_span(bundle, selection);
+
+        let edits = bundle
+            .buffer()
+            .inner
+            .lines_at(first)
+            .take(last - first + 1)
+            .map(|line| {
+                let old = indent::leading_whitespace(&line.contents).chars().count();
+                if right && line.contents.is_empty() {
+                    return (0, 0, String::new());
+                }
+
+                let columns = indent::indent_columns(&line.contents, options.tab_width);
+                let columns = if right {
+                    columns + options.indent_width()
+                } else {
+                    columns.saturating_sub(options.indent_width())
+                };
+                (0, old, options.indent(columns))
+            })
+            .collect();
+
+        Self::edit_lines(bundle, first, edits, ctrl);
+    }
+
+    /// Deletes the selected text as a single change. Changed lines are emptied instead of being
+    /// removed
+    fn delete(
+        bundle: &mut BufferBundle,
+        selection: &Selection,
+        change: bool,
+        ctrl: &mut Handle<State>,
+    ) {
+        let buffer_id = bundle.id();
+        let rope = &bundle.buffer().inner;
+        let mut range = selection.range.clone();
+        if selection.kind == RegisterKind::Linewise {
+            let ends_with_newline =
+                range.end > range.start && rope.get(range.end - 1) == Some('\n');
+            if change && ends_with_newline {
+                range.end -= 1;
+            } else if !change && !ends_with_newline && range.start > 0 {
+                // The last line has no newline of its own, so the one before it goes instead
+                range.start -= 1;
+            }
+        }
+
+        ctrl.add_event(event::Payload::ChangeGroupStarted { buffer_id });
+        let mut buffer = bundle.buffer_mut(ctrl);
+        buffer.delete_range(range);
+        if selection.kind == RegisterKind::Linewise && !change {
+            buffer.move_in_line(LinePosition::CharacterStart);
+        }
+        ctrl.add_event(event::Payload::ChangeGroupEnded { buffer_id });
+
+        ctrl.query_draw(DrawQuery::Rehighlight(Selector::WithId(buffer_id)));
+    }
+}
+
+impl controller::QueryHandler<State> for Handler {
+    fn handle(
+        &mut self,
+        state: &mut State,
+        query: Query,
+        ctrl: &mut Handle<State>,
+    ) -> error::Result<()> {
+        let _span = edi_lib::span!("operator");
+
+        let Payload::Operator(operator_query) = query.payload() else {
+            return Err(AppError::unexpected(
+                "non-operator query submitted to operator query handler, this is likely a bug",
+            ));
+        };
+
+        if operator_query.operator != Operator::Yank {
+            if let Some(bundle) = state.buffers.active() {
+                bundle.meta().ensure_modifiable()?;
+            }
+        }
+
+        Self::operate(state, operator_query, ctrl);
+        ctrl.query_redraw();
+        Ok(())
+    }
+}
+
+/// Returns where the text `w` moved over ends for an operator. If the motion went on to the start
+/// of a later line, the text ends with the line before it rather than taking its newline along,
+/// so `dw` on the last word of a line keeps the line break
+fn word_motion_end(rope: &Rope, cursor: usize, moved: usize) -> usize {
+    let line_number = rope.line_of_index(moved);
+    if line_number == rope.line_of_index(cursor) {
+        return moved;
+    }
+    let Some(line) = rope.line(line_number) else {
+        return moved;
+    };
+
+    if moved > line.character_offset + search::character_start(&line.contents) {
+        return moved;
+    }
+    line.character_offset.saturating_sub(1).max(cursor)
+}
+
+/// Returns the character in the other case if it is a letter that has one
+fn toggle_case(c: char) -> Vec<char> {
+    if c.is_uppercase() {
+        c.to_lowercase().collect()
+    } else {
+        c.to_uppercase().collect()
+    }
+}
@@ -0,0 +1,143 @@
+use edi_lib::buffer;
+
+use crate::{
+    app::{
+        buffers::Selector,
+        registers::{Register, RegisterKind, Registers},
+        state::State,
+    },
+    controller::{self, Handle},
+    error::{AppError, Result},
+    query::{DrawQuery, Payload, Query, RegistersQuery},
+};
+
+pub struct Handler;
+
+impl Handler {
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl controller::QueryHandler<State> for Handler {
+    fn handle(
+        &mut self,
+        app_state: &mut State,
+        query: Query,
+        ctrl: &mut Handle<State>,
+    ) -> Result<()> {
+        let _span = edi_lib::span!("registers");
+
+        let Payload::Registers(registers_query) = query.payload() else {
+            return Err(AppError::unexpected(
+                "non-registers query submitted to registers query handler, this is likely a bug",
+            ));
+        };
+
+        match *registers_query {
+            RegistersQuery::YankLine { register } => Self::yank_line(app_state, register),
+            RegistersQuery::Paste { register, before } => {
+                if let Some(bundle) = app_state.buffers.active() {
+                    bundle.meta().ensure_modifiable()?;
+                }
+                Self::paste(app_state, register, before, ctrl);
+                ctrl.query_redraw();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Handler {
+    fn yank_line(state: &mut State, register: Option<char>) {
+        let Some(bundle) = state.buffers.active() else {
+            return;
+        };
+        let buffer = bundle.buffer();
+        let contents = buffer
+            .inner
+            .line(buffer.current_line())
+            .map(|line| line.contents)
+            .unwrap_or_default();
+
+        state.registers.yank(register, Register::linewise(contents));
+    }
+
+    fn paste(state: &mut State, register: Option<char>, before: bool, ctrl: &mut Handle<State>) {
+        let name = register.unwrap_or(Registers::UNNAMED);
+        let Some(register) = state.registers.get(name).cloned() else {
+            edi_lib::debug!("register {name:?} is empty");
+            return;
+        };
+
+        state.within_active_buffer(
+            |id, mut buffer, _| {
+                let Paste {
+                    offset,
+                    text,
+                    lines_start,
+                } = Self::paste_position(buffer.as_ref(), &register, before);
+                buffer.set_cursor_offset(offset);
+                buffer.write_str(&text);
+
+                // Linewise pastes leave the cursor on the first pasted line, charwise ones on the
+                // last pasted character
+                let cursor = match register.kind {
+                    RegisterKind::Linewise => lines_start,
+                    RegisterKind::Charwise => {
+                        buffer.as_ref().cursor_offset.saturating_sub(1).max(offset)
+                    }
+                };
+                buffer.set_cursor_offset(cursor);
+                buffer
+                    .ctrl()
+                    .query_draw(DrawQuery::Rehighlight(Selector::WithId(id)));
+            },
+            ctrl,
+        );
+    }
+
+    /// Returns where the `register` is inserted and the text to insert
+    fn paste_position(buffer: &buffer::Buffer, register: &Register, before: bool) -> Paste {
+        let cursor = buffer.cursor_offset;
+        let current_line = buffer.current_line();
+        let line = buffer.inner.line_info(current_line);
+
+        let offset = match (register.kind, line) {
+            (RegisterKind::Charwise, Some(line)) if !before => {
+                (cursor + 1).min(line.character_offset + line.length)
+            }
+            (RegisterKind::Linewise, Some(line)) if before => line.character_offset,
+            (RegisterKind::Linewise, _) if !before => {
+                let len = buffer.inner.len();
+                if let Some(next) = buffer.inner.line_info(current_line + 1) {
+                    next.character_offset
+                } else if len == 0 || buffer.inner.get(len - 1) == Some('\n') {
+                    len
+                } else {
+                    // The last line has no newline to paste after
+                    let text = register.text.strip_suffix('\n').unwrap_or(&register.text);
+                    return Paste {
+                        offset: len,
+                        text: format!("\n{text}"),
+                        lines_start: len + 1,
+                    };
+                }
+            }
+            _ => cursor,
+        };
+
+        Paste {
+            offset,
+            text: register.text.clone(),
+            lines_start: offset,
+        }
+    }
+}
+
+struct Paste {
+    offset: usize,
+    text: String,
+    /// Where the first pasted line starts, if the register is linewise
+    lines_start: usize,
+}
@@ -0,0 +1,244 @@
+use std::ops::Range;
+
+use edi_lib::brand::Id;
+
+use crate::{
+    app::{buffer_bundle::BufferBundle, buffers::Selector, pattern::Pattern, state::State},
+    controller::{self, Handle},
+    error::{AppError, Result},
+    event::{self, Event, Payload},
+    query::{self, Query, SearchQuery},
+};
+
+/// State of the searched buffer before the search prompt was opened
+#[derive(Debug)]
+struct Preview {
+    target: Id,
+    cursor: usize,
+    matches: Vec<Range<usize>>,
+    status: String,
+}
+
+pub struct Handler {
+    /// The last searched pattern and whether it was searched backwards
+    last: Option<(String, bool)>,
+    preview: Option<Preview>,
+}
+
+impl Handler {
+    pub const fn new() -> Self {
+        Self {
+            last: None,
+            preview: None,
+        }
+    }
+
+    /// Returns the pattern typed in the active search prompt and whether it searches backwards
+    fn prompt(state: &State) -> Option<(String, bool)> {
+        let bundle = state.buffers.active()?;
+        if !bundle.meta().flags.is_search() {
+            return None;
+        }
+
+        let mut chars = bundle.buffer().inner.chars();
+        let backward = chars.next() == Some('?');
+        Some((chars.collect(), backward))
+    }
+
+    fn preview(&mut self, state: &mut State) {
+        let Some((pattern, backward)) = Self::prompt(state) else {
+            return;
+        };
+        let Some(target) = state.buffers.get_mut(&Selector::Previous) else {
+            return;
+        };
+
+        let preview = self.preview.get_or_insert_with(|| Preview {
+            target: target.id(),
+            cursor: target.buffer().cursor_offset,
+            matches: target.meta().matches.clone(),
+            status: target.meta().status.clone(),
+        });
+
+        Self::search(
+            target,
+            &pattern,
+            preview.cursor,
+            backward,
+            state.context.options.ignore_case,
+        );
+    }
+
+    fn submit(&mut self, state: &mut State) {
+        let Some((mut pattern, backward)) = Self::prompt(state) else {
+            return;
+        };
+        let preview = self.preview.take();
+        let Some(target) = state.buffers.get_mut(&Selector::Previous) else {
+            return;
+        };
+
+        // An empty pattern repeats the last search
+        if pattern.is_empty() {
+            let Some((last, _)) = &self.last else {
+                return;
+            };
+            pattern.clone_from(last);
+        }
+
+        let from = preview.map_or(target.buffer().cursor_offset, |p| p.cursor);
+        Self::search(
+            target,
+            &pattern,
+            from,
+            backward,
+            state.context.options.ignore_case,
+        );
+        self.last = Some((pattern, backward));
+    }
+
+    /// Restores the searched buffer after the prompt was closed without searching
+    fn cancel(&mut self, state: &mut State) {
+        let Some(preview) = self.preview.take() else {
+            return;
+        };
+        let Some(target) = state.buffers.get_mut(&Selector::WithId(preview.target)) else {
+            return;
+        };
+
+        let (buffer, meta) = target.as_split_mut_silent();
+        buffer.cursor_offset = preview.cursor;
+        meta.matches = preview.matches;
+        meta.status = preview.status;
+    }
+
+    fn next(&self, state: &mut State, reverse: bool) {
+        let Some((pattern, backward)) = &self.last else {
+            return;
+        };
+        let Some(target) = state.buffers.active_mut() else {
+            return;
+        };
+
+        let from = target.buffer().cursor_offset;
+        Self::search(
+            target,
+            pattern,
+            from,
+            backward ^ reverse,
+            state.context.options.ignore_case,
+        );
+    }
+
+    fn clear(state: &mut State) {
+        for bundle in state.buffers.iter_mut() {
+            let meta = bundle.meta_mut();
+            meta.matches.clear();
+            meta.status.clear();
+        }
+    }
+
+    /// Highlights the matches of the regular expression `pattern` in the bundle's buffer and
+    /// moves the cursor from `from` to the closest one in the given direction
+    fn search(
+        bundle: &mut BufferBundle,
+        pattern: &str,
+        from: usize,
+        backward: bool,
+        ignore_case: bool,
+    ) {
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        let compiled = match Pattern::new(pattern, ignore_case) {
+            _ if pattern.is_empty() => None,
+            Ok(compiled) => Some(compiled),
+            Err(e) => {
+                edi_lib::debug!("invalid search pattern: {e}");
+                meta.matches.clear();
+                meta.status = String::from("invalid pattern");
+                return;
+            }
+        };
+        meta.matches = compiled.map_or_else(Vec::new, |p| p.find_all(&buffer.inner));
+
+        let current = next_match(&meta.matches, from, backward);
+        buffer.cursor_offset = current.map_or(from, |i| meta.matches[i].start);
+        meta.status = match current {
+            _ if pattern.is_empty() => String::new(),
+            Some(i) => format!("[{}/{}]", i + 1, meta.matches.len()),
+            None => String::from("no matches"),
+        };
+    }
+}
+
+/// Returns the index of the match to jump to from `from`, wrapping around the end of the buffer
+fn next_match(matches: &[Range<usize>], from: usize, backward: bool) -> Option<usize> {
+    if matches.is_empty() {
+        return None;
+    }
+
+    Some(if backward {
+        let i = matches.partition_point(|m| m.start < from);
+        i.checked_sub(1).unwrap_or(matches.len() - 1)
+    } else {
+        let i = matches.partition_point(|m| m.start <= from);
+        if i == matches.len() {
+            0
+        } else {
+            i
+        }
+    })
+}
+
+impl controller::QueryHandler<State> for Handler {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
+        let _span = edi_lib::span!("search");
+
+        let query::Payload::Search(search_query) = query.payload() else {
+            return Err(AppError::unexpected(
+                "non-search query submitted to search query handler, this is likely a bug",
+            ));
+        };
+
+        match *search_query {
+            SearchQuery::Preview => self.preview(state),
+            SearchQuery::Submit => self.submit(state),
+            SearchQuery::Cancel => self.cancel(state),
+            SearchQuery::Next { reverse } => self.next(state, reverse),
+            SearchQuery::Clear => Self::clear(state),
+        }
+
+        ctrl.query_redraw();
+        Ok(())
+    }
+
+    fn check_event(&mut self, state: &State, event: &Event, ctrl: &mut Handle<State>) {
+        match event.payload() {
+            Payload::CharWritten { buffer_id, .. }
+            | Payload::CharDeleted { buffer_id, .. }
+            | Payload::TextReplaced { buffer_id, .. } => {
+                let is_prompt = state
+                    .buffers
+                    .get(&Selector::WithId(*buffer_id))
+                    .is_some_and(|bundle| bundle.meta().flags.is_search());
+                if is_prompt {
+                    ctrl.query_search(SearchQuery::Preview);
+                }
+            }
+            // The prompt is gone, but the search was never submitted
+            Payload::ModeSwitched { .. } if self.preview.is_some() => {
+                ctrl.query_search(SearchQuery::Cancel);
+            }
+            _ => {}
+        }
+    }
+
+    fn interested_in(&self, _own_id: Id, event: &Event) -> bool {
+        let types = &[
+            event::Type::CharWritten,
+            event::Type::CharDeleted,
+            event::Type::TextReplaced,
+            event::Type::ModeSwitched,
+        ];
+        event.ty().is_oneof(types)
+    }
+}
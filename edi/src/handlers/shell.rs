@@ -0,0 +1,270 @@
+use std::{collections::HashMap, ops::Range};
+
+use edi_lib::brand::Id;
+
+use crate::{
+    app::{
+        buffers::Selector,
+        message::Message,
+        shell::{self, ShellCommand, Target},
+        state::State,
+        substitute,
+    },
+    controller::{self, Handle},
+    error::{AppError, Result},
+    event::{self, Event},
+    jobs::{JobId, Spec, Stream},
+    query::{Payload, Query, ShellQuery},
+};
+
+/// Where the output of a running command goes, the lines are the ones of the buffer when the
+/// command was started
+#[derive(Debug)]
+enum Destination {
+    Scratch,
+    Buffer {
+        buffer_id: Id,
+        lines: Range<usize>,
+        /// The checksum of the text when the command was started, the lines may be other ones
+        /// once it has changed
+        checksum: u64,
+    },
+}
+
+/// A command that is running, with what it wrote so far
+#[derive(Debug)]
+struct Running {
+    command: String,
+    destination: Destination,
+    stdout: String,
+    stderr: String,
+}
+
+pub struct Handler {
+    /// The running commands by their jobs
+    running: HashMap<JobId, Running>,
+}
+
+impl Handler {
+    pub fn new() -> Self {
+        Self {
+            running: HashMap::new(),
+        }
+    }
+}
+
+impl controller::QueryHandler<State> for Handler {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
+        let _span = edi_lib::span!("shell");
+
+        let Payload::Shell(shell_query) = query.into_payload() else {
+            return Err(AppError::unexpected(
+                "non-shell query submitted to shell query handler, this is likely a bug",
+            ));
+        };
+
+        match shell_query {
+            ShellQuery::Run { buffer, command } => {
+                if let (Target::Read | Target::Filter { .. }, Some(bundle)) =
+                    (&command.target, state.buffers.get(&buffer))
+                {
+                    bundle.meta().ensure_modifiable()?;
+                }
+                self.run(state, &buffer, &command);
+            }
+            ShellQuery::Finished { job, status } => {
+                let Some(running) = self.running.remove(&job) else {
+                    return Ok(());
+                };
+                Self::finish(state, ctrl, running, status)?;
+            }
+        }
+        ctrl.query_redraw();
+        Ok(())
+    }
+
+    fn check_event(&mut self, _state: &State, event: &Event, ctrl: &mut Handle<State>) {
+        match *event.payload() {
+            event::Payload::JobOutput {
+                job,
+                stream,
+                ref line,
+            } => {
+                let Some(running) = self.running.get_mut(&job) else {
+                    return;
+                };
+                let output = match stream {
+                    Stream::Stdout => &mut running.stdout,
+                    Stream::Stderr => &mut running.stderr,
+                };
+                output.push_str(line);
+                output.push('\n');
+            }
+            event::Payload::JobExited { job, status } if self.running.contains_key(&job) => {
+                ctrl.query_shell(ShellQuery::Finished { job, status });
+            }
+            _ => {}
+        }
+    }
+
+    fn interested_in(&self, _own_id: Id, event: &Event) -> bool {
+        event
+            .ty()
+            .is_oneof(&[event::Type::JobOutput, event::Type::JobExited])
+    }
+}
+
+impl Handler {
+    /// Starts the command with the lines of the buffer it filters as its input
+    fn run(&mut self, state: &mut State, selector: &Selector, command: &ShellCommand) {
+        let (destination, input) = match command.target {
+            Target::Scratch => (Destination::Scratch, None),
+            Target::Read | Target::Filter { .. } => {
+                let Some(bundle) = state.buffers.get(selector) else {
+                    edi_lib::debug!("no buffer to put the output of the command in");
+                    return;
+                };
+                let buffer = bundle.buffer();
+                let line = buffer.current_line();
+                let (lines, input) = match command.target {
+                    Target::Filter { start, end } => {
+                        let lines = substitute::lines(start, end, line, buffer.total_lines());
+                        let input = buffer
+                            .inner
+                            .lines_at(*lines.start())
+                            .take(lines.clone().count())
+                            .fold(String::new(), |mut input, info| {
+                                input.push_str(&info.contents);
+                                input.push('\n');
+                                input
+                            });
+                        (*lines.start()..lines.end() + 1, Some(input))
+                    }
+                    _ => (line + 1..line + 1, None),
+                };
+                let destination = Destination::Buffer {
+                    buffer_id: bundle.id(),
+                    lines,
+                    checksum: buffer.inner.checksum(),
+                };
+                (destination, input)
+            }
+        };
+
+        let mut spec = Spec::shell(&command.command);
+        if let Some(input) = input {
+            spec = spec.with_input(input);
+        }
+        match state.jobs.spawn(spec) {
+            Ok(job) => {
+                let running = Running {
+                    command: command.command.clone(),
+                    destination,
+                    stdout: String::new(),
+                    stderr: String::new(),
+                };
+                self.running.insert(job, running);
+            }
+            Err(e) => state.notify(Message::error(format!("cannot run sh: {e}"))),
+        }
+    }
+
+    /// Puts the output of the finished command where it goes. It is not put in a buffer that was
+    /// changed while the command ran, as the lines it replaces may not be there anymore
+    fn finish(
+        state: &mut State,
+        ctrl: &mut Handle<State>,
+        running: Running,
+        status: Option<i32>,
+    ) -> Result<()> {
+        let Running {
+            command,
+            destination,
+            stdout,
+            stderr,
+        } = running;
+        let failed = match status {
+            Some(0) => None,
+            Some(code) => Some(format!("!{command}: shell returned {code}")),
+            None => Some(format!("!{command}: killed")),
+        };
+
+        match destination {
+            Destination::Scratch => {
+                let text = stdout + &stderr;
+                if !text.is_empty() {
+                    state.open_scratch(format!("!{command}"), &text);
+                }
+                match failed {
+                    Some(failed) => state.notify(Message::error(failed)),
+                    None if text.is_empty() => {
+                        state.notify(Message::info(format!("!{command}: no output")));
+                    }
+                    None => state.message = None,
+                }
+            }
+            Destination::Buffer { .. } if failed.is_some() => {
+                let error = stderr.lines().find(|line| !line.trim().is_empty());
+                let error = error.map(String::from).or(failed).unwrap_or_default();
+                state.notify(Message::error(error));
+            }
+            Destination::Buffer {
+                buffer_id,
+                lines,
+                checksum,
+            } => {
+                let Some(bundle) = state.buffers.get_mut(&Selector::WithId(buffer_id)) else {
+                    return Ok(());
+                };
+                bundle.meta().ensure_modifiable()?;
+                if bundle.buffer().inner.checksum() != checksum {
+                    state.notify(Message::error(format!(
+                        "!{command}: the buffer changed while the command ran, its output was not put in"
+                    )));
+                    return Ok(());
+                }
+                let text =
+                    shell::splice_lines(&bundle.buffer().inner.to_string(), lines.clone(), &stdout);
+
+                ctrl.add_event(event::Payload::ChangeGroupStarted { buffer_id });
+                bundle.buffer_mut(ctrl).replace_all(&text);
+                ctrl.add_event(event::Payload::ChangeGroupEnded { buffer_id });
+
+                let (buffer, _) = bundle.as_split_mut_silent();
+                let line = lines.start.min(buffer.total_lines().saturating_sub(1));
+                buffer.cursor_offset = buffer.inner.line_to_char(line);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        app::testing::Editor,
+        query::{Payload, WriteQuery},
+    };
+
+    #[test]
+    fn filter_lines() {
+        let mut editor = Editor::new("filter", "b\na\nc\n");
+        assert!(!editor.command(":1,2!sort"));
+        assert_eq!(editor.text(), "b\na\nc\n");
+        editor.wait_job();
+        assert_eq!(editor.text(), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn changed_while_running() {
+        let mut editor = Editor::new("filter-changed", "b\na\n");
+        editor.command(":%!sort");
+        editor.query(Payload::Write(WriteQuery::WriteChar('c')));
+        editor.wait_job();
+        assert_eq!(editor.text(), "cb\na\n");
+        assert_eq!(
+            editor.message(),
+            Some("!sort: the buffer changed while the command ran, its output was not put in")
+        );
+    }
+}
@@ -9,6 +9,7 @@ use crate::{
         Mode,
     },
     controller::{self, Handle},
+    error::{AppError, Result},
     query::{Payload, Query, SpawnQuery},
 };
 
@@ -21,32 +22,44 @@ impl Handler {
 }
 
 impl controller::QueryHandler<State> for Handler {
-    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
         let _span = edi_lib::span!("write");
 
         let Payload::Spawn(spawn_query) = query.payload() else {
-            edi_lib::debug!(
-                "non-spawn query submitted to spawn query handler, this is likely a bug"
-            );
-            return;
+            return Err(AppError::unexpected(
+                "non-spawn query submitted to spawn query handler, this is likely a bug",
+            ));
         };
 
         match spawn_query {
-            &SpawnQuery::TerminalBuffer => Self::spawn_terminal_buffer(state),
+            SpawnQuery::TerminalBuffer => {
+                state.command_history.reset();
+                Self::spawn_terminal_buffer(state, ":", Flags::empty());
+            }
+            &SpawnQuery::SearchBuffer { backward } => {
+                let prompt = if backward { "?" } else { "/" };
+                Self::spawn_terminal_buffer(state, prompt, Flags::empty().set_is_search());
+            }
+            SpawnQuery::ExplorerPrompt(prompt) => {
+                let flags = Flags::empty().set_is_explorer_prompt();
+                Self::spawn_terminal_buffer(state, prompt, flags);
+            }
         }
 
         ctrl.query_redraw();
+        Ok(())
     }
 }
 
 impl Handler {
-    fn spawn_terminal_buffer(state: &mut State) {
+    fn spawn_terminal_buffer(state: &mut State, prompt: &str, flags: Flags) {
         let buffer_size = Vec2::new(Unit::full_width(), Unit::Cells(1));
         let buffer_offset = Vec2::new(Unit::zero(), Unit::half_height());
 
-        let mut buffer = Buffer::new(":");
-        buffer.cursor_offset = 1;
-        let flags = Flags::empty().set_is_terminal();
+        let mut buffer = Buffer::new(prompt);
+        buffer.cursor_offset = prompt.chars().count();
+        buffer.tab_width = state.context.options.tab_width;
+        let flags = flags.set_is_terminal();
         let meta = BufferMeta::new(Mode::Terminal)
             .with_size(buffer_size)
             .with_offset(buffer_offset)
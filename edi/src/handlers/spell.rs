@@ -0,0 +1,109 @@
+use crate::{
+    app::{
+        buffer_bundle::BufferBundle,
+        completion::{Candidate, Completion},
+        message::Message,
+        spell,
+        state::State,
+    },
+    controller::{self, Handle},
+    error::{AppError, Result},
+    query::{Payload, Query, SpellQuery},
+};
+
+pub struct Handler;
+
+impl Handler {
+    /// The most words suggested for a misspelled one
+    const SUGGESTIONS: usize = 20;
+
+    pub const fn new() -> Self {
+        Self
+    }
+
+    /// Moves the cursor to the start of the next misspelled word
+    fn jump(state: &mut State, reverse: bool) {
+        let Some(id) = state.buffers.active().map(BufferBundle::id) else {
+            return;
+        };
+        if !state.context.options.spell {
+            state.notify(Message::error(
+                "spell checking is off, :set spell turns it on",
+            ));
+            return;
+        }
+        state.check_spelling(id);
+
+        let Some(bundle) = state.buffers.active_mut() else {
+            return;
+        };
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        let next = meta
+            .misspellings
+            .as_deref()
+            .and_then(|misspellings| spell::next(misspellings, buffer.cursor_offset, reverse));
+        match next {
+            Some(range) => buffer.cursor_offset = range.start,
+            None => state.notify(Message::error("no misspelled words")),
+        }
+    }
+
+    /// Opens the menu of the suggestions for the word under the cursor, the cursor is moved to
+    /// the start of the word that the chosen suggestion replaces
+    fn suggest(state: &mut State) {
+        let Some(spell) = state.spell.as_ref().filter(|_| state.context.options.spell) else {
+            state.notify(Message::error(
+                "spell checking is off, :set spell turns it on",
+            ));
+            return;
+        };
+        let Some(bundle) = state.buffers.active_mut() else {
+            return;
+        };
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        let Some(range) = spell::word_at(&buffer.inner, buffer.cursor_offset) else {
+            state.notify(Message::error("no word under the cursor"));
+            return;
+        };
+
+        let word: String = buffer
+            .inner
+            .chars_at(range.start)
+            .take(range.len())
+            .collect();
+        let candidates = spell
+            .dictionary
+            .suggest(&word, Self::SUGGESTIONS)
+            .into_iter()
+            .map(Candidate::new)
+            .collect();
+        meta.completion = Completion::new(range.start, candidates, false).map(|mut completion| {
+            completion.end = Some(range.end);
+            completion
+        });
+        if meta.completion.is_none() {
+            state.notify(Message::error(format!("no suggestions for \"{word}\"")));
+            return;
+        }
+        buffer.cursor_offset = range.start;
+    }
+}
+
+impl controller::QueryHandler<State> for Handler {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
+        let _span = edi_lib::span!("spell");
+
+        let Payload::Spell(spell_query) = query.into_payload() else {
+            return Err(AppError::unexpected(
+                "non-spell query submitted to spell query handler, this is likely a bug",
+            ));
+        };
+
+        match spell_query {
+            SpellQuery::Jump { reverse } => Self::jump(state, reverse),
+            SpellQuery::Suggest => Self::suggest(state),
+        }
+        ctrl.query_redraw();
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+use edi_lib::{brand::Id, buffer};
+
+use crate::{
+    app::{
+        buffer_bundle::BufferBundle,
+        buffers::Selector,
+        message::Message,
+        pattern::{Match, Pattern},
+        state::State,
+        substitute::{Answer, Substitution},
+        Mode,
+    },
+    controller::{self, Handle},
+    error::{AppError, Result},
+    event::Payload,
+    query::{self, DrawQuery, Query, SubstituteQuery},
+};
+
+/// A substitution that is being run, stopped at its current match
+#[derive(Debug)]
+struct Running {
+    target: Id,
+    substitution: Substitution,
+    pattern: Pattern,
+    /// The line of the current match
+    line: usize,
+    current: Match,
+    /// The last line the substitution applies to
+    end_line: usize,
+    count: usize,
+}
+
+impl Running {
+    /// Replaces the current match and moves to the next one, returning `false` if there is none
+    fn replace(&mut self, bundle: &mut BufferBundle, ctrl: &mut Handle<State>) -> bool {
+        let range = self.current.range.clone();
+        let replacement = self.substitution.replacement.expand(&self.current);
+
+        let mut buffer = bundle.buffer_mut(ctrl);
+        let start = line_offset(buffer.as_ref(), self.line) + range.start;
+        buffer.delete_range(start..start + range.len());
+        buffer.write_str(&replacement);
+        buffer.set_cursor_offset(start);
+        self.count += 1;
+
+        let added_lines = replacement.matches('\n').count();
+        self.end_line += added_lines;
+
+        // Matches are never looked for inside of the replacement, and an empty match is stepped
+        // over so it is not matched again
+        let empty = usize::from(range.is_empty());
+        let from = if !self.substitution.global {
+            (self.line + added_lines + 1, 0)
+        } else if let Some((_, last_line)) = replacement.rsplit_once('\n') {
+            (self.line + added_lines, last_line.chars().count() + empty)
+        } else {
+            (self.line, range.start + replacement.chars().count() + empty)
+        };
+        self.advance(bundle.buffer(), from)
+    }
+
+    /// Moves to the next match without replacing the current one, returning `false` if there is
+    /// none
+    fn skip(&mut self, buffer: &buffer::Buffer) -> bool {
+        let range = &self.current.range;
+        let from = if self.substitution.global {
+            (self.line, range.end + usize::from(range.is_empty()))
+        } else {
+            (self.line + 1, 0)
+        };
+        self.advance(buffer, from)
+    }
+
+    fn advance(&mut self, buffer: &buffer::Buffer, from: (usize, usize)) -> bool {
+        let Some((line, current)) = find_next(buffer, &self.pattern, from, self.end_line) else {
+            return false;
+        };
+        self.line = line;
+        self.current = current;
+        true
+    }
+}
+
+pub struct Handler {
+    /// The substitution with the `c` flag that waits for an answer
+    pending: Option<Running>,
+}
+
+impl Handler {
+    pub const fn new() -> Self {
+        Self { pending: None }
+    }
+
+    fn run(
+        &mut self,
+        state: &mut State,
+        selector: &Selector,
+        substitution: &Substitution,
+        ctrl: &mut Handle<State>,
+    ) {
+        if self.pending.is_some() {
+            edi_lib::debug!("a substitution is already waiting for confirmation");
+            return;
+        }
+        let Some(bundle) = state.buffers.get_mut(selector) else {
+            edi_lib::debug!("no buffer found by selector: {selector:?}");
+            return;
+        };
+
+        let pattern = match substitution.compile(state.context.options.ignore_case) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                edi_lib::debug!("invalid substitution pattern: {e}");
+                state.notify(Message::error(format!(
+                    "invalid pattern: {}",
+                    substitution.pattern
+                )));
+                return;
+            }
+        };
+
+        let buffer = bundle.buffer();
+        let last_line = buffer.inner.total_lines();
+        let lines = substitution.lines(buffer.current_line(), last_line + 1);
+        let end_line = (*lines.end()).min(last_line);
+
+        let Some((line, current)) = find_next(buffer, &pattern, (*lines.start(), 0), end_line)
+        else {
+            state.notify(Message::error(format!(
+                "pattern not found: {}",
+                substitution.pattern
+            )));
+            return;
+        };
+
+        let mut running = Running {
+            target: bundle.id(),
+            substitution: substitution.clone(),
+            pattern,
+            line,
+            current,
+            end_line,
+            count: 0,
+        };
+        ctrl.add_event(Payload::ChangeGroupStarted {
+            buffer_id: running.target,
+        });
+
+        if substitution.confirm {
+            Self::ask(bundle, &running);
+            ctrl.query_switch_mode(Selector::WithId(running.target), Mode::Confirm);
+            self.pending = Some(running);
+            return;
+        }
+
+        while running.replace(bundle, ctrl) {}
+        Self::finish(bundle, &running, ctrl);
+    }
+
+    fn answer(&mut self, state: &mut State, answer: Answer, ctrl: &mut Handle<State>) {
+        let Some(mut running) = self.pending.take() else {
+            return;
+        };
+        let Some(bundle) = state.buffers.get_mut(&Selector::WithId(running.target)) else {
+            return;
+        };
+
+        let has_next = match answer {
+            Answer::Yes => running.replace(bundle, ctrl),
+            Answer::No => running.skip(bundle.buffer()),
+            Answer::All => {
+                while running.replace(bundle, ctrl) {}
+                false
+            }
+            Answer::Quit => false,
+        };
+
+        if has_next {
+            Self::ask(bundle, &running);
+            self.pending = Some(running);
+        } else {
+            Self::finish(bundle, &running, ctrl);
+        }
+    }
+
+    /// Selects the current match and asks whether to replace it
+    fn ask(bundle: &mut BufferBundle, running: &Running) {
+        let (buffer, meta) = bundle.as_split_mut_silent();
+        let range = &running.current.range;
+        let start = line_offset(buffer, running.line) + range.start;
+        let replacement = running.substitution.replacement.expand(&running.current);
+
+        buffer.cursor_offset = start;
+        meta.selections.clear();
+        meta.selections.push(start..start + range.len());
+        meta.status = format!("replace with \"{}\"? (y/n/a/q)", replacement.escape_debug());
+    }
+
+    fn finish(bundle: &mut BufferBundle, running: &Running, ctrl: &mut Handle<State>) {
+        let id = running.target;
+        ctrl.add_event(Payload::ChangeGroupEnded { buffer_id: id });
+
+        let meta = bundle.meta_mut();
+        meta.status = match running.count {
+            1 => String::from("1 substitution"),
+            n => format!("{n} substitutions"),
+        };
+
+        if running.substitution.confirm {
+            meta.selections.clear();
+            ctrl.query_switch_mode(Selector::WithId(id), Mode::Normal);
+        }
+        ctrl.query_draw(DrawQuery::Rehighlight(Selector::WithId(id)));
+    }
+}
+
+/// Returns the first match at or after the `(line, character)` position `from`, on the lines up
+/// to `end_line`
+fn find_next(
+    buffer: &buffer::Buffer,
+    pattern: &Pattern,
+    from: (usize, usize),
+    end_line: usize,
+) -> Option<(usize, Match)> {
+    let (from_line, from_character) = from;
+    (from_line..=end_line).find_map(|n| {
+        let line = buffer.inner.line(n)?;
+        let from = if n == from_line { from_character } else { 0 };
+        pattern.find_in(&line.contents, from).map(|m| (n, m))
+    })
+}
+
+fn line_offset(buffer: &buffer::Buffer, line: usize) -> usize {
+    buffer
+        .inner
+        .line_info(line)
+        .map_or(buffer.inner.len(), |info| info.character_offset)
+}
+
+impl controller::QueryHandler<State> for Handler {
+    fn handle(&mut self, state: &mut State, query: Query, ctrl: &mut Handle<State>) -> Result<()> {
+        let _span = edi_lib::span!("substitute");
+
+        let query::Payload::Substitute(substitute_query) = query.payload() else {
+            return Err(AppError::unexpected(
+                "non-substitute query submitted to substitute query handler, this is likely a bug",
+            ));
+        };
+
+        match substitute_query {
+            SubstituteQuery::Run {
+                buffer,
+                substitution,
+            } => {
+                if let Some(bundle) = state.buffers.get(buffer) {
+                    bundle.meta().ensure_modifiable()?;
+                }
+                self.run(state, buffer, substitution, ctrl);
+            }
+            &SubstituteQuery::Answer(answer) => self.answer(state, answer, ctrl),
+        }
+
+        ctrl.query_redraw();
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
+use edi_lib::string::indent::{leading_whitespace, next_line_indent};
+
 use crate::{
     app::{buffers::Selector, state::State},
     controller::{self, Handle},
+    error::{AppError, Result},
+    event,
     query::{DrawQuery, Payload, Query, WriteQuery},
 };
 
@@ -13,33 +17,57 @@ impl Handler {
 }
 
 impl controller::QueryHandler<State> for Handler {
-    fn handle(&mut self, app_state: &mut State, query: Query, ctrl: &mut Handle<State>) {
+    fn handle(
+        &mut self,
+        app_state: &mut State,
+        query: Query,
+        ctrl: &mut Handle<State>,
+    ) -> Result<()> {
         let _span = edi_lib::span!("write");
 
         let Payload::Write(write_query) = query.payload() else {
-            edi_lib::debug!(
-                "non-write query submitted to write query handler, this is likely a bug"
-            );
-            return;
+            return Err(AppError::unexpected(
+                "non-write query submitted to write query handler, this is likely a bug",
+            ));
         };
 
-        match *write_query {
-            WriteQuery::WriteChar(c) => Self::write_char(app_state, c, ctrl),
+        if let Some(bundle) = app_state.buffers.active() {
+            bundle.meta().ensure_modifiable()?;
+        }
+
+        match write_query {
+            WriteQuery::WriteChar('\t') => {
+                let indent = app_state.context.options.indent_unit();
+                Self::write_str(app_state, &indent, ctrl);
+            }
+            WriteQuery::WriteChar('\n') if app_state.context.options.auto_indent => {
+                Self::new_line(app_state, ctrl);
+            }
+            WriteQuery::WriteChar(c) => {
+                Self::write_str(app_state, c.encode_utf8(&mut [0; 4]), ctrl);
+            }
+            WriteQuery::WriteStr(s) => Self::write_str(app_state, s, ctrl),
             WriteQuery::DeleteChar => Self::delete_char(app_state, ctrl),
+            &WriteQuery::ReplaceChars { c, count } => {
+                Self::replace_chars(app_state, c, count, ctrl);
+            }
+            &WriteQuery::OpenLine { above } => Self::open_line(app_state, above, ctrl),
         }
 
         ctrl.query_redraw();
+        Ok(())
     }
 }
 
 impl Handler {
-    fn write_char(state: &mut State, c: char, ctrl: &mut Handle<State>) {
+    /// Writes the whole string before rehighlighting the buffer once
+    fn write_str(state: &mut State, s: &str, ctrl: &mut Handle<State>) {
         state.within_active_buffer(
             |id, mut buffer, _| {
                 let is_empty = buffer.as_ref().inner.is_empty();
-                buffer.write(c);
+                buffer.write_str(s);
                 // Hack to always add a newline at the end of the file
-                if is_empty {
+                if is_empty && !s.is_empty() {
                     buffer.write('\n');
                     buffer.set_cursor_offset(buffer.as_ref().cursor_offset - 1);
                 }
@@ -51,6 +79,59 @@ impl Handler {
         );
     }
 
+    /// Breaks the line at the cursor, indenting the new line like the one it is started from
+    fn new_line(state: &mut State, ctrl: &mut Handle<State>) {
+        let unit = state.context.options.indent_unit();
+        let Some(bundle) = state.buffers.active() else {
+            return;
+        };
+        let buffer = bundle.buffer();
+        let before: String = buffer
+            .inner
+            .line(buffer.current_line())
+            .map(|line| {
+                let len = buffer.cursor_offset - line.character_offset;
+                line.contents.chars().take(len).collect()
+            })
+            .unwrap_or_default();
+
+        let indent = next_line_indent(&before, &bundle.meta().filetype, &unit);
+        Self::write_str(state, &format!("\n{indent}"), ctrl);
+    }
+
+    /// Starts a new line below or above the cursor line. With `autoindent` set, a line below is
+    /// indented as if the cursor line was broken at its end, a line above as the cursor line is
+    fn open_line(state: &mut State, above: bool, ctrl: &mut Handle<State>) {
+        let unit = state.context.options.indent_unit();
+        let auto_indent = state.context.options.auto_indent;
+        let Some(bundle) = state.buffers.active() else {
+            return;
+        };
+        let buffer = bundle.buffer();
+        let (start, end, contents) = buffer.inner.line(buffer.current_line()).map_or_else(
+            || (buffer.inner.len(), buffer.inner.len(), String::new()),
+            |line| {
+                let end = line.character_offset + line.length;
+                (line.character_offset, end, line.contents)
+            },
+        );
+        let indent = match (auto_indent, above) {
+            (false, _) => String::new(),
+            (true, false) => next_line_indent(&contents, &bundle.meta().filetype, &unit),
+            (true, true) => String::from(leading_whitespace(&contents)),
+        };
+
+        if above {
+            state.within_active_buffer(|_, mut buffer, _| buffer.set_cursor_offset(start), ctrl);
+            Self::write_str(state, &format!("{indent}\n"), ctrl);
+            let cursor = start + indent.chars().count();
+            state.within_active_buffer(|_, mut buffer, _| buffer.set_cursor_offset(cursor), ctrl);
+        } else {
+            state.within_active_buffer(|_, mut buffer, _| buffer.set_cursor_offset(end), ctrl);
+            Self::write_str(state, &format!("\n{indent}"), ctrl);
+        }
+    }
+
     fn delete_char(state: &mut State, ctrl: &mut Handle<State>) {
         state.within_active_buffer(
             |id, mut buffer, _| {
@@ -62,4 +143,115 @@ impl Handler {
             ctrl,
         );
     }
+
+    /// Replaces the characters as a single change, leaving the cursor on the last one put in, or
+    /// at the start of the line a newline breaks off
+    fn replace_chars(state: &mut State, c: char, count: usize, ctrl: &mut Handle<State>) {
+        state.within_active_buffer(
+            |buffer_id, mut buffer, _| {
+                let cursor = buffer.as_ref().cursor_offset;
+                let available = buffer
+                    .as_ref()
+                    .inner
+                    .chars_at(cursor)
+                    .take(count)
+                    .take_while(|&c| c != '\n')
+                    .count();
+                if count == 0 || available < count {
+                    return;
+                }
+
+                let (replacement, end) = if c == '\n' {
+                    (String::from('\n'), cursor + 1)
+                } else {
+                    (std::iter::repeat_n(c, count).collect(), cursor + count - 1)
+                };
+                buffer
+                    .ctrl()
+                    .add_event(event::Payload::ChangeGroupStarted { buffer_id });
+                buffer.delete_range(cursor..cursor + count);
+                buffer.write_str(&replacement);
+                buffer.set_cursor_offset(end);
+                let ctrl = buffer.ctrl();
+                ctrl.add_event(event::Payload::ChangeGroupEnded { buffer_id });
+                ctrl.query_draw(DrawQuery::Rehighlight(Selector::WithId(buffer_id)));
+            },
+            ctrl,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edi_term::input::Input;
+
+    use crate::{
+        app::{buffers::Selector, testing::Editor, Mode},
+        event,
+        query::{HistoryQuery, Payload, WriteQuery},
+    };
+
+    #[test]
+    fn write_lines_at_once() {
+        let mut editor = Editor::new("write-str", "ab\n");
+        editor.query(Payload::Write(WriteQuery::WriteStr(String::from("x\ny"))));
+        assert_eq!(editor.text(), "x\nyab\n");
+        assert_eq!(editor.bundle().buffer().cursor_offset, 3);
+
+        let events = editor.take_events();
+        let edits: Vec<_> = events
+            .iter()
+            .filter(|payload| {
+                matches!(
+                    payload,
+                    event::Payload::CharWritten { .. } | event::Payload::TextReplaced { .. }
+                )
+            })
+            .collect();
+        assert!(matches!(
+            edits[..],
+            [event::Payload::TextReplaced { offset: 0, removed, inserted, .. }]
+                if removed.is_empty() && inserted == "x\ny"
+        ));
+
+        editor.query(Payload::History(HistoryQuery::Undo(Selector::Active)));
+        assert_eq!(editor.text(), "ab\n");
+    }
+
+    /// Types `keys` in `text` and then `x` in the line they open, returns the text with it and
+    /// checks that leaving the insert mode and undoing once restores `text`
+    fn open_line(text: &str, auto_indent: bool, keys: &str) -> String {
+        let name = format!("open-line-{auto_indent}-{keys}");
+        let mut editor = Editor::new(&name, text);
+        editor.state.context.options.auto_indent = auto_indent;
+        editor.keys(keys);
+        assert_eq!(editor.bundle().meta().mode, Mode::Insert);
+        editor.keys("x");
+        let opened = editor.text();
+
+        editor.input(Input::Escape);
+        editor.keys("u");
+        assert_eq!(editor.text(), text, "{keys:?} was not undone at once");
+        opened
+    }
+
+    #[test]
+    fn open_line_indented() {
+        let text = "  first\n    last\n";
+        assert_eq!(open_line(text, true, "o"), "  first\n  x\n    last\n");
+        assert_eq!(open_line(text, true, "O"), "  x\n  first\n    last\n");
+        assert_eq!(open_line(text, true, "jo"), "  first\n    last\n    x\n");
+        assert_eq!(open_line(text, true, "jO"), "  first\n    x\n    last\n");
+        assert_eq!(open_line("a\n  b", true, "jo"), "a\n  b\n  x");
+    }
+
+    #[test]
+    fn open_line_not_indented() {
+        let text = "  first\n    last\n";
+        assert_eq!(open_line(text, false, "o"), "  first\nx\n    last\n");
+        assert_eq!(open_line(text, false, "O"), "x\n  first\n    last\n");
+        assert_eq!(open_line(text, false, "jo"), "  first\n    last\nx\n");
+        assert_eq!(open_line(text, false, "jO"), "  first\nx\n    last\n");
+        assert_eq!(open_line("a\n  b", false, "jo"), "a\n  b\nx");
+    }
 }
@@ -0,0 +1,324 @@
+//! Processes run in the background, whose output and exit are emitted as events so the handlers
+//! that start them never wait for them
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::PathBuf,
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{mpsc, Arc, Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+use crate::event::{Payload, Sender, Source};
+
+/// Identifies a started job in the events it emits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JobId(usize);
+
+/// The output a line was written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// What a job runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spec {
+    program: String,
+    args: Vec<String>,
+    /// The working directory of the process, the editor's if it is not set
+    dir: Option<PathBuf>,
+    /// Written to the standard input of the process, which is closed after it
+    input: Option<String>,
+}
+
+impl Spec {
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            dir: None,
+            input: None,
+        }
+    }
+
+    /// Runs the command line with `sh`
+    pub fn shell(command: &str) -> Self {
+        Self::new("sh").with_args(["-c", command])
+    }
+
+    #[must_use]
+    pub fn with_args<S: Into<String>>(mut self, args: impl IntoIterator<Item = S>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    #[must_use]
+    pub fn with_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    #[must_use]
+    pub fn with_input(mut self, input: impl Into<String>) -> Self {
+        self.input = Some(input.into());
+        self
+    }
+}
+
+/// The running processes, each waited for by the thread that reads its output
+type Children = Arc<Mutex<HashMap<JobId, Child>>>;
+
+/// A process handed over to the runner to read the output of
+struct Started {
+    id: JobId,
+    stdout: ChildStdout,
+    stderr: ChildStderr,
+    input: Option<(ChildStdin, String)>,
+}
+
+/// Starts processes, each of which emits `JobOutput` for every line it writes and `JobExited`
+/// once it exits, after all of its output
+#[derive(Debug)]
+pub struct Jobs {
+    children: Children,
+    started: mpsc::Sender<Started>,
+    next_id: usize,
+    /// The source of the events of the jobs, until it is taken to be attached
+    runner: Option<Runner>,
+}
+
+impl Default for Jobs {
+    fn default() -> Self {
+        let children = Children::default();
+        let (started, rx) = mpsc::channel();
+        Self {
+            children: Arc::clone(&children),
+            started,
+            next_id: 0,
+            runner: Some(Runner {
+                children,
+                started: rx,
+            }),
+        }
+    }
+}
+
+impl Jobs {
+    /// How often a process whose output is closed is checked for having exited
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the source of the events of the jobs
+    pub const fn take_runner(&mut self) -> Option<Runner> {
+        self.runner.take()
+    }
+
+    /// Starts the process, its events are emitted with the returned id
+    pub fn spawn(&mut self, spec: Spec) -> io::Result<JobId> {
+        let mut command = Command::new(&spec.program);
+        command
+            .args(&spec.args)
+            .stdin(if spec.input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(dir) = &spec.dir {
+            command.current_dir(dir);
+        }
+        let mut child = command.spawn()?;
+
+        let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+            let _ = child.kill();
+            return Err(io::Error::other("the process has no output"));
+        };
+        let input = child.stdin.take().zip(spec.input);
+
+        let id = JobId(self.next_id);
+        self.next_id += 1;
+        self.lock().insert(id, child);
+        let started = Started {
+            id,
+            stdout,
+            stderr,
+            input,
+        };
+        if self.started.send(started).is_err() {
+            edi_lib::debug!("the job runner is not running");
+        }
+        Ok(id)
+    }
+
+    /// Kills the process of the job, which then exits without an exit code. Returns whether it
+    /// was running
+    pub fn cancel(&self, id: JobId) -> bool {
+        self.lock()
+            .get_mut(&id)
+            .is_some_and(|child| child.kill().is_ok())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<JobId, Child>> {
+        self.children.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Reads the output of the started processes and waits for them, in threads of their own
+#[derive(Debug)]
+pub struct Runner {
+    children: Children,
+    started: mpsc::Receiver<Started>,
+}
+
+impl Runner {
+    /// Emits a `JobOutput` for every line read, the last one may not end with a newline
+    fn read(id: JobId, stream: Stream, output: impl Read, sender: &Sender) {
+        let mut output = BufReader::new(output);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match output.read_until(b'\n', &mut line) {
+                Ok(0) => return,
+                Ok(_) => {}
+                Err(e) => {
+                    edi_lib::debug!("unable to read the output of {id:?}: {e}");
+                    return;
+                }
+            }
+            let line = String::from_utf8_lossy(&line);
+            let line = line.strip_suffix('\n').unwrap_or(&line);
+            let line = String::from(line.strip_suffix('\r').unwrap_or(line));
+            if !sender.send_event(Payload::JobOutput {
+                job: id,
+                stream,
+                line,
+            }) {
+                return;
+            }
+        }
+    }
+
+    /// Returns the exit code of the process once it exits, `None` if it was killed
+    fn wait(children: &Children, id: JobId) -> Option<i32> {
+        loop {
+            {
+                let mut children = children.lock().unwrap_or_else(PoisonError::into_inner);
+                let child = children.get_mut(&id)?;
+                match child.try_wait() {
+                    Ok(Some(status)) => {
+                        children.remove(&id);
+                        return status.code();
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        edi_lib::debug!("unable to wait for {id:?}: {e}");
+                        children.remove(&id);
+                        return None;
+                    }
+                }
+            }
+            thread::sleep(Jobs::POLL_INTERVAL);
+        }
+    }
+
+    fn run_job(children: &Children, started: Started, sender: &Sender) {
+        let Started {
+            id,
+            stdout,
+            stderr,
+            input,
+        } = started;
+
+        // The input is written while the output is read, so a process that writes a lot before
+        // reading all of it does not block
+        if let Some((mut stdin, input)) = input {
+            thread::spawn(move || {
+                let _ = stdin.write_all(input.as_bytes());
+            });
+        }
+        let stderr_sender = sender.clone();
+        let stderr = thread::spawn(move || Self::read(id, Stream::Stderr, stderr, &stderr_sender));
+        Self::read(id, Stream::Stdout, stdout, sender);
+        let _ = stderr.join();
+
+        let status = Self::wait(children, id);
+        sender.send_event(Payload::JobExited { job: id, status });
+    }
+}
+
+impl Source for Runner {
+    fn run(&mut self, sender: Sender) {
+        let _span = edi_lib::span!("jobs");
+
+        while let Ok(started) = self.started.recv() {
+            let sender = sender.clone();
+            let children = Arc::clone(&self.children);
+            thread::spawn(move || Self::run_job(&children, started, &sender));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Starts a runner whose events are received from the returned channel
+    fn start(jobs: &mut Jobs) -> mpsc::Receiver<Payload> {
+        let (tx, rx) = mpsc::channel();
+        let mut runner = jobs.take_runner().unwrap();
+        thread::spawn(move || runner.run(Sender::new(tx)));
+        rx
+    }
+
+    #[test]
+    fn output() {
+        let mut jobs = Jobs::new();
+        let events = start(&mut jobs);
+        let spec = Spec::shell("tr a-z A-Z; echo oops >&2; exit 3").with_input("ab\ncd");
+        let id = jobs.spawn(spec).unwrap();
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let status = loop {
+            match events.recv().unwrap() {
+                Payload::JobOutput { job, stream, line } => {
+                    assert_eq!(job, id);
+                    match stream {
+                        Stream::Stdout => stdout.push(line),
+                        Stream::Stderr => stderr.push(line),
+                    }
+                }
+                Payload::JobExited { job, status } => {
+                    assert_eq!(job, id);
+                    break status;
+                }
+                payload => panic!("unexpected event {payload:?}"),
+            }
+        };
+        assert_eq!(stdout, ["AB", "CD"]);
+        assert_eq!(stderr, ["oops"]);
+        assert_eq!(status, Some(3));
+        assert!(!jobs.cancel(id));
+    }
+
+    #[test]
+    fn cancel() {
+        let mut jobs = Jobs::new();
+        let events = start(&mut jobs);
+        let id = jobs.spawn(Spec::new("sleep").with_args(["10"])).unwrap();
+        assert!(jobs.cancel(id));
+
+        let exited = events.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(exited, Payload::JobExited { job, status: None } if job == id));
+        assert!(!jobs.cancel(id));
+        assert!(jobs.spawn(Spec::new("edi-no-such-program")).is_err());
+    }
+}
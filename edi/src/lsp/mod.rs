@@ -0,0 +1,821 @@
+//! The client of the language servers. A server is started for a filetype when the first file of
+//! it is opened, and every open file of the filetype is kept in sync with it by sending the
+//! changes made to the buffer as they are recorded
+
+pub mod transport;
+
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::mpsc,
+};
+
+use edi_lib::{
+    brand::Id,
+    diagnostic::{Diagnostic, Severity},
+    fs::filetype::Filetype,
+    json::{Object, Value},
+    language::Language,
+};
+use edi_rope::Rope;
+
+use transport::Reader;
+
+/// A position in a document as language servers count it, the column is in UTF-16 code units
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Position {
+    /// Returns the position of the character at `offset` in the text
+    pub fn of_offset(text: &Rope, offset: usize) -> Self {
+        let line = text.char_to_line(offset);
+        let start = text.line_to_char(line);
+        let character = text
+            .chars_at(start)
+            .take(offset.saturating_sub(start))
+            .map(char::len_utf16)
+            .sum();
+        Self { line, character }
+    }
+
+    /// Returns the character offset of the position in the text. A column past the end of the
+    /// line stands for the end of it
+    pub fn to_offset(self, text: &Rope) -> usize {
+        let start = text.line_to_char(self.line);
+        let mut units = 0;
+        let len = text
+            .chars_at(start)
+            .take_while(|&c| {
+                units += c.len_utf16();
+                c != '\n' && units <= self.character
+            })
+            .count();
+        start + len
+    }
+
+    fn parse(value: &Value) -> Option<Self> {
+        Some(Self {
+            line: value.get("line")?.as_usize()?,
+            character: value.get("character")?.as_usize()?,
+        })
+    }
+
+    fn to_json(self) -> Value {
+        Value::from([
+            ("line", Value::from(self.line)),
+            ("character", Value::from(self.character)),
+        ])
+    }
+}
+
+/// A change to a document that was not sent to its language server yet, the text between
+/// `start` and `end` is replaced with `text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Change {
+    start: Position,
+    end: Position,
+    text: String,
+    /// The character offset of `start`
+    offset: usize,
+}
+
+impl Change {
+    fn to_json(&self) -> Value {
+        Value::from([
+            (
+                "range",
+                Value::from([("start", self.start.to_json()), ("end", self.end.to_json())]),
+            ),
+            ("text", Value::from(self.text.as_str())),
+        ])
+    }
+}
+
+/// An open file, as its language server knows it
+#[derive(Debug)]
+struct Document {
+    uri: String,
+    filetype: Filetype,
+    version: i64,
+    /// The text with the changes that were not sent yet applied
+    text: Rope,
+    /// The changes made since the document was last sent, each of them to the text the previous
+    /// ones left. Consecutive characters typed or deleted are merged into one change
+    changes: Vec<Change>,
+}
+
+impl Document {
+    /// Records the character written at `offset`
+    fn write(&mut self, offset: usize, c: char) {
+        let continued = self
+            .changes
+            .last_mut()
+            .filter(|last| last.offset + last.text.chars().count() == offset);
+        if let Some(last) = continued {
+            last.text.push(c);
+        } else {
+            let position = Position::of_offset(&self.text, offset);
+            self.changes.push(Change {
+                start: position,
+                end: position,
+                text: String::from(c),
+                offset,
+            });
+        }
+        self.text.insert(offset, c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Records the deletion of the character before `offset`
+    fn delete(&mut self, offset: usize) {
+        let Some(deleted) = offset.checked_sub(1) else {
+            return;
+        };
+        let start = Position::of_offset(&self.text, deleted);
+        match self.changes.last_mut() {
+            // Deleting what was just written takes it back
+            Some(last)
+                if last.offset + last.text.chars().count() == offset && offset > last.offset =>
+            {
+                last.text.pop();
+            }
+            // Deleting right before what was just deleted deletes more
+            Some(last) if last.text.is_empty() && last.offset == offset => {
+                last.start = start;
+                last.offset = deleted;
+            }
+            _ => self.changes.push(Change {
+                start,
+                end: Position::of_offset(&self.text, offset),
+                text: String::new(),
+                offset: deleted,
+            }),
+        }
+        self.text.delete(deleted..offset);
+    }
+
+    /// Records the replacement of the characters in `range` with `text`
+    fn replace(&mut self, range: Range<usize>, text: &str) {
+        self.changes.push(Change {
+            start: Position::of_offset(&self.text, range.start),
+            end: Position::of_offset(&self.text, range.end),
+            text: String::from(text),
+            offset: range.start,
+        });
+        self.text.replace(range, text);
+    }
+}
+
+/// What was sent a request is waiting for the answer to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    Initialize,
+    Hover,
+    Definition,
+}
+
+/// What an answer of a language server is to be shown as
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// The first line of the information about the symbol under the cursor
+    Hover(Option<String>),
+    /// Where the symbol under the cursor is defined
+    Definition(Option<(PathBuf, Position)>),
+    /// The diagnostics of the buffer, sorted by where they start
+    Diagnostics(Id, Vec<Diagnostic>),
+}
+
+/// A running language server
+#[derive(Debug)]
+struct Server {
+    process: Child,
+    stdin: ChildStdin,
+    next_id: i64,
+    /// The requests that were not answered yet, by id
+    pending: HashMap<i64, Request>,
+    /// The notifications sent before the server answered `initialize`, which are held back until
+    /// it does. `None` once it did
+    queued: Option<Vec<Value>>,
+    /// Whether the server takes changes rather than the whole text of a document
+    incremental: bool,
+}
+
+impl Server {
+    fn start(
+        command: &[String],
+        filetype: &Filetype,
+        outputs: &mpsc::Sender<(Filetype, ChildStdout)>,
+    ) -> std::io::Result<Self> {
+        let Some((program, args)) = command.split_first() else {
+            return Err(std::io::Error::other("the command is empty"));
+        };
+        let mut process = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (process.stdin.take(), process.stdout.take()) else {
+            let _ = process.kill();
+            return Err(std::io::Error::other("the server has no stdio"));
+        };
+        let _ = outputs.send((filetype.clone(), stdout));
+
+        let mut server = Self {
+            process,
+            stdin,
+            next_id: 0,
+            pending: HashMap::new(),
+            queued: None,
+            incremental: true,
+        };
+        let root = std::env::current_dir().map_or(Value::Null, |dir| Value::from(uri(&dir)));
+        let capabilities = Value::from([(
+            "textDocument",
+            Value::from([
+                (
+                    "hover",
+                    Value::from([(
+                        "contentFormat",
+                        Value::Array(vec![Value::from("plaintext"), Value::from("markdown")]),
+                    )]),
+                ),
+                (
+                    "definition",
+                    Value::from([("linkSupport", Value::from(true))]),
+                ),
+                ("publishDiagnostics", Value::Object(Object::new())),
+            ]),
+        )]);
+        server.request(
+            Request::Initialize,
+            "initialize",
+            Value::from([
+                ("processId", Value::from(i64::from(std::process::id()))),
+                ("clientInfo", Value::from([("name", Value::from("edi"))])),
+                ("rootUri", root),
+                ("capabilities", capabilities),
+            ]),
+        );
+        server.queued = Some(Vec::new());
+        Ok(server)
+    }
+
+    fn write(&mut self, message: &Value) {
+        if let Err(e) = transport::write_message(&mut self.stdin, message) {
+            edi_lib::debug!("unable to write to the language server: {e}");
+        }
+    }
+
+    fn request(&mut self, request: Request, method: &str, params: Value) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, request);
+        self.write(&Value::from([
+            ("jsonrpc", Value::from("2.0")),
+            ("id", Value::from(id)),
+            ("method", Value::from(method)),
+            ("params", params),
+        ]));
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        let message = Value::from([
+            ("jsonrpc", Value::from("2.0")),
+            ("method", Value::from(method)),
+            ("params", params),
+        ]);
+        match &mut self.queued {
+            Some(queued) => queued.push(message),
+            None => self.write(&message),
+        }
+    }
+
+    fn respond(&mut self, id: Value, result: Value) {
+        self.write(&Value::from([
+            ("jsonrpc", Value::from("2.0")),
+            ("id", id),
+            ("result", result),
+        ]));
+    }
+
+    /// Takes in the answer to `initialize` and sends what was held back until it came
+    fn initialized(&mut self, result: &Value) {
+        let sync = result
+            .get("capabilities")
+            .and_then(|capabilities| capabilities.get("textDocumentSync"));
+        let kind = sync.and_then(|sync| sync.get("change").unwrap_or(sync).as_i64());
+        // 1 is sending the whole text, 2 the changes
+        self.incremental = kind != Some(1);
+
+        self.write(&Value::from([
+            ("jsonrpc", Value::from("2.0")),
+            ("method", Value::from("initialized")),
+            ("params", Value::Object(Object::new())),
+        ]));
+        for message in self.queued.take().unwrap_or_default() {
+            self.write(&message);
+        }
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// The language servers and the documents open in them
+#[derive(Debug)]
+pub struct Client {
+    /// The servers by filetype, `None` for the ones that could not be started
+    servers: HashMap<Filetype, Option<Server>>,
+    /// The documents by the id of their buffer
+    documents: HashMap<Id, Document>,
+    outputs: mpsc::Sender<(Filetype, ChildStdout)>,
+    /// The source of the messages of the servers, until it is taken to be attached
+    reader: Option<Reader>,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        let (outputs, rx) = mpsc::channel();
+        Self {
+            servers: HashMap::new(),
+            documents: HashMap::new(),
+            outputs,
+            reader: Some(Reader::new(rx)),
+        }
+    }
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the source of the events of the messages the servers send
+    pub const fn take_reader(&mut self) -> Option<Reader> {
+        self.reader.take()
+    }
+
+    /// Returns whether the buffer is open in a language server
+    pub fn is_open(&self, buffer_id: Id) -> bool {
+        self.documents.contains_key(&buffer_id)
+    }
+
+    /// Returns whether the language server of the buffer answered `initialize`, so it can be
+    /// asked about it
+    pub fn is_ready(&self, buffer_id: Id) -> bool {
+        self.documents
+            .get(&buffer_id)
+            .and_then(|document| self.servers.get(&document.filetype)?.as_ref())
+            .is_some_and(|server| server.queued.is_none())
+    }
+
+    /// Opens the file of the buffer in the language server of its language, starting the server
+    /// if it is not running yet. Nothing is done if the language has no server
+    pub fn open(&mut self, buffer_id: Id, path: &Path, language: &Language, text: &Rope) {
+        if language.language_server.is_empty() {
+            return;
+        }
+        let filetype = &language.filetype;
+        let outputs = &self.outputs;
+        let server = self.servers.entry(filetype.clone()).or_insert_with(|| {
+            Server::start(&language.language_server, filetype, outputs)
+                .inspect_err(|e| {
+                    edi_lib::debug!("unable to start {:?}: {e}", language.language_server);
+                })
+                .ok()
+        });
+        let Some(server) = server else {
+            return;
+        };
+
+        let document = Document {
+            uri: uri(path),
+            filetype: filetype.clone(),
+            version: 0,
+            text: text.clone(),
+            changes: Vec::new(),
+        };
+        server.notify(
+            "textDocument/didOpen",
+            Value::from([(
+                "textDocument",
+                Value::from([
+                    ("uri", Value::from(document.uri.as_str())),
+                    ("languageId", Value::from(filetype.name())),
+                    ("version", Value::from(document.version)),
+                    ("text", Value::from(text.to_string())),
+                ]),
+            )]),
+        );
+        self.documents.insert(buffer_id, document);
+    }
+
+    /// Closes the document of the buffer
+    pub fn close(&mut self, buffer_id: Id) {
+        let Some(document) = self.documents.remove(&buffer_id) else {
+            return;
+        };
+        if let Some(Some(server)) = self.servers.get_mut(&document.filetype) {
+            server.notify(
+                "textDocument/didClose",
+                Value::from([("textDocument", identifier(&document))]),
+            );
+        }
+    }
+
+    /// Records the character written at `offset` in the buffer, returns whether there were no
+    /// changes waiting to be sent before
+    pub fn write(&mut self, buffer_id: Id, offset: usize, c: char) -> bool {
+        self.documents.get_mut(&buffer_id).is_some_and(|document| {
+            document.write(offset, c);
+            document.changes.len() == 1
+        })
+    }
+
+    /// Records the deletion of the character before `offset` in the buffer, returns whether
+    /// there were no changes waiting to be sent before
+    pub fn delete(&mut self, buffer_id: Id, offset: usize) -> bool {
+        self.documents.get_mut(&buffer_id).is_some_and(|document| {
+            document.delete(offset);
+            document.changes.len() == 1
+        })
+    }
+
+    /// Records the replacement of the characters in `range` of the buffer with `text`, returns
+    /// whether there were no changes waiting to be sent before
+    pub fn replace(&mut self, buffer_id: Id, range: Range<usize>, text: &str) -> bool {
+        self.documents.get_mut(&buffer_id).is_some_and(|document| {
+            document.replace(range, text);
+            document.changes.len() == 1
+        })
+    }
+
+    /// Sends the changes made to the buffer since it was last sent
+    pub fn sync(&mut self, buffer_id: Id) {
+        let Some(document) = self.documents.get_mut(&buffer_id) else {
+            return;
+        };
+        let Some(Some(server)) = self.servers.get_mut(&document.filetype) else {
+            return;
+        };
+        if document.changes.is_empty() || server.queued.is_some() {
+            return;
+        }
+
+        document.version += 1;
+        let changes = std::mem::take(&mut document.changes);
+        let changes = if server.incremental {
+            changes.iter().map(Change::to_json).collect()
+        } else {
+            vec![Value::from([(
+                "text",
+                Value::from(document.text.to_string()),
+            )])]
+        };
+        server.notify(
+            "textDocument/didChange",
+            Value::from([
+                ("textDocument", identifier(document)),
+                ("contentChanges", Value::Array(changes)),
+            ]),
+        );
+    }
+
+    /// Asks for the information about the symbol at `offset` in the buffer
+    pub fn hover(&mut self, buffer_id: Id, offset: usize) {
+        self.ask(buffer_id, offset, Request::Hover, "textDocument/hover");
+    }
+
+    /// Asks where the symbol at `offset` in the buffer is defined
+    pub fn definition(&mut self, buffer_id: Id, offset: usize) {
+        self.ask(
+            buffer_id,
+            offset,
+            Request::Definition,
+            "textDocument/definition",
+        );
+    }
+
+    fn ask(&mut self, buffer_id: Id, offset: usize, request: Request, method: &str) {
+        self.sync(buffer_id);
+        let Some(document) = self.documents.get(&buffer_id) else {
+            return;
+        };
+        let Some(Some(server)) = self.servers.get_mut(&document.filetype) else {
+            return;
+        };
+        let position = Position::of_offset(&document.text, offset);
+        server.request(
+            request,
+            method,
+            Value::from([
+                ("textDocument", identifier(document)),
+                ("position", position.to_json()),
+            ]),
+        );
+    }
+
+    /// Takes in a message the language server of the filetype sent, returning what is to be
+    /// shown of it
+    pub fn receive(&mut self, filetype: &Filetype, message: &Value) -> Option<Response> {
+        let Some(Some(server)) = self.servers.get_mut(filetype) else {
+            return None;
+        };
+
+        let method = message.get("method").and_then(Value::as_str);
+        let id = message.get("id");
+        match (method, id) {
+            (Some(method), Some(id)) => {
+                // Nothing is configured, and the rest of the requests of the servers only need
+                // an answer
+                let result = match message.get("params").and_then(|params| params.get("items")) {
+                    Some(Value::Array(items)) if method == "workspace/configuration" => {
+                        Value::Array(vec![Value::Null; items.len()])
+                    }
+                    _ => Value::Null,
+                };
+                server.respond(id.clone(), result);
+                None
+            }
+            (Some("textDocument/publishDiagnostics"), None) => self.publish(message.get("params")?),
+            (None, Some(id)) => {
+                let request = server.pending.remove(&id.as_i64()?)?;
+                if let Some(error) = message.get("error") {
+                    edi_lib::debug!("the language server answered with an error: {error}");
+                    return None;
+                }
+                let result = message.get("result").unwrap_or(&Value::Null);
+                match request {
+                    Request::Initialize => {
+                        server.initialized(result);
+                        let ids: Vec<Id> = self
+                            .documents
+                            .iter()
+                            .filter(|(_, document)| document.filetype == *filetype)
+                            .map(|(&id, _)| id)
+                            .collect();
+                        for id in ids {
+                            self.sync(id);
+                        }
+                        None
+                    }
+                    Request::Hover => {
+                        Some(Response::Hover(result.get("contents").and_then(hover_text)))
+                    }
+                    Request::Definition => Some(Response::Definition(location(result))),
+                }
+            }
+            (_, None) => None,
+        }
+    }
+
+    fn publish(&self, params: &Value) -> Option<Response> {
+        let uri = params.get("uri")?.as_str()?;
+        let (&buffer_id, document) = self
+            .documents
+            .iter()
+            .find(|(_, document)| document.uri == uri)?;
+
+        let offset = |position: Option<&Value>| {
+            position
+                .and_then(Position::parse)
+                .map(|position| position.to_offset(&document.text))
+        };
+        let mut diagnostics: Vec<Diagnostic> = params
+            .get("diagnostics")?
+            .as_array()?
+            .iter()
+            .filter_map(|diagnostic| {
+                let range = diagnostic.get("range")?;
+                let start = offset(range.get("start"))?;
+                let end = offset(range.get("end"))?;
+                Some(Diagnostic {
+                    // An empty range is drawn as the character it starts at
+                    range: start..end.max(start + 1),
+                    severity: diagnostic
+                        .get("severity")
+                        .and_then(Value::as_i64)
+                        .map_or(Severity::Error, Severity::from_lsp),
+                    message: String::from(diagnostic.get("message")?.as_str()?),
+                })
+            })
+            .collect();
+        diagnostics.sort_by_key(|diagnostic| diagnostic.range.start);
+        Some(Response::Diagnostics(buffer_id, diagnostics))
+    }
+}
+
+fn identifier(document: &Document) -> Value {
+    Value::from([
+        ("uri", Value::from(document.uri.as_str())),
+        ("version", Value::from(document.version)),
+    ])
+}
+
+/// Returns the first line of the contents of a hover that is not a fence of a code block or a
+/// rule
+fn hover_text(contents: &Value) -> Option<String> {
+    fn value(item: &Value) -> Option<&str> {
+        item.as_str().or_else(|| item.get("value")?.as_str())
+    }
+    let text = match contents {
+        Value::Array(items) => items
+            .iter()
+            .filter_map(value)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        contents => String::from(value(contents)?),
+    };
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with("```") && *line != "---")
+        .map(String::from)
+}
+
+/// Returns the file and the position of the first location of a definition, which may be a
+/// location, a link or a list of either
+fn location(result: &Value) -> Option<(PathBuf, Position)> {
+    let location = match result {
+        Value::Array(locations) => locations.first()?,
+        location => location,
+    };
+    let uri = location.get("uri").or_else(|| location.get("targetUri"))?;
+    let range = location
+        .get("range")
+        .or_else(|| location.get("targetSelectionRange"))?;
+    Some((path(uri.as_str()?)?, Position::parse(range.get("start")?)?))
+}
+
+/// Returns the `file` URI of the path, made absolute
+fn uri(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut uri = String::from("file://");
+    for &byte in path.to_string_lossy().as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            uri.push(char::from(byte));
+        } else {
+            let _ = write!(uri, "%{byte:02X}");
+        }
+    }
+    uri
+}
+
+/// Returns the path of a `file` URI
+fn path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let hex = encoded.get(i + 1..i + 3).filter(|_| encoded[i] == b'%');
+        if let Some(byte) =
+            hex.and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+        {
+            bytes.push(byte);
+            i += 3;
+        } else {
+            bytes.push(encoded[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document(text: &str) -> Document {
+        Document {
+            uri: String::new(),
+            filetype: Filetype::default(),
+            version: 0,
+            text: Rope::from(text),
+            changes: Vec::new(),
+        }
+    }
+
+    fn change(start: (usize, usize), end: (usize, usize), text: &str, offset: usize) -> Change {
+        let position = |(line, character)| Position { line, character };
+        Change {
+            start: position(start),
+            end: position(end),
+            text: String::from(text),
+            offset,
+        }
+    }
+
+    #[test]
+    fn positions() {
+        let text = Rope::from("a😀b\néc\n");
+        let position = |offset| Position::of_offset(&text, offset);
+        assert_eq!(
+            position(2),
+            Position {
+                line: 0,
+                character: 3
+            }
+        );
+        assert_eq!(
+            position(3),
+            Position {
+                line: 0,
+                character: 4
+            }
+        );
+        assert_eq!(
+            position(5),
+            Position {
+                line: 1,
+                character: 1
+            }
+        );
+        assert_eq!(
+            position(7),
+            Position {
+                line: 2,
+                character: 0
+            }
+        );
+
+        for offset in 0..=7 {
+            assert_eq!(position(offset).to_offset(&text), offset);
+        }
+        let past_end = Position {
+            line: 1,
+            character: 10,
+        };
+        assert_eq!(past_end.to_offset(&text), 6);
+    }
+
+    #[test]
+    fn changes() {
+        let mut document = document("ab\ncd");
+        for (i, c) in "xy".chars().enumerate() {
+            document.write(4 + i, c);
+        }
+        document.delete(6);
+        assert_eq!(document.changes, [change((1, 1), (1, 1), "x", 4)]);
+
+        document.delete(5);
+        document.delete(4);
+        document.delete(3);
+        assert_eq!(document.changes, [change((0, 2), (1, 1), "", 2)]);
+
+        document.write(0, 'z');
+        assert_eq!(document.changes[1], change((0, 0), (0, 0), "z", 0));
+        assert_eq!(document.text.to_string(), "zabd");
+
+        document.replace(1..3, "x\ny");
+        assert_eq!(document.changes[2], change((0, 1), (0, 3), "x\ny", 1));
+        assert_eq!(document.text.to_string(), "zx\nyd");
+    }
+
+    #[test]
+    fn replacements() {
+        let mut document = document("a😀b\néc\n");
+        document.replace(1..5, "x\n");
+        assert_eq!(document.changes, [change((0, 1), (1, 1), "x\n", 1)]);
+        assert_eq!(document.text.to_string(), "ax\nc\n");
+
+        document.replace(5..5, "d");
+        assert_eq!(document.changes[1], change((2, 0), (2, 0), "d", 5));
+        assert_eq!(document.text.to_string(), "ax\nc\nd");
+    }
+
+    #[test]
+    fn answers() {
+        let markdown = Value::from([
+            ("kind", Value::from("markdown")),
+            ("value", Value::from("```rust\nfn main()\n```\n---\ndocs")),
+        ]);
+        assert_eq!(hover_text(&markdown).as_deref(), Some("fn main()"));
+        let marked = Value::Array(vec![Value::from(""), Value::from("int x")]);
+        assert_eq!(hover_text(&marked).as_deref(), Some("int x"));
+
+        let path = PathBuf::from("/tmp/a b/é.rs");
+        let uri = uri(&path);
+        assert_eq!(uri, "file:///tmp/a%20b/%C3%A9.rs");
+        let start = Position {
+            line: 3,
+            character: 1,
+        };
+        let link = Value::Array(vec![Value::from([
+            ("targetUri", Value::from(uri)),
+            (
+                "targetSelectionRange",
+                Value::from([("start", start.to_json()), ("end", start.to_json())]),
+            ),
+        ])]);
+        assert_eq!(location(&link), Some((path, start)));
+    }
+}
@@ -0,0 +1,130 @@
+//! The framing of the messages exchanged with language servers, and the source of the events of
+//! the messages they send
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::ChildStdout,
+    sync::mpsc,
+};
+
+use edi_lib::{fs::filetype::Filetype, json};
+
+use crate::event::{Payload, Sender, Source};
+
+/// Writes the message preceded by the header of its length
+pub fn write_message(w: &mut impl Write, message: &json::Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(w, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    w.flush()
+}
+
+/// Reads the body of the next message, `None` if the stream ended before it
+pub fn read_message(r: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if r.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "a message has no content length",
+        ));
+    };
+    let mut body = vec![0; length];
+    r.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Emits `LspMessage` for every message the language servers send. The output of each server is
+/// read in a thread of its own, the servers are handed over as they are started
+#[derive(Debug)]
+pub struct Reader {
+    outputs: mpsc::Receiver<(Filetype, ChildStdout)>,
+}
+
+impl Reader {
+    pub const fn new(outputs: mpsc::Receiver<(Filetype, ChildStdout)>) -> Self {
+        Self { outputs }
+    }
+
+    fn read(filetype: &Filetype, stdout: ChildStdout, sender: &Sender) {
+        let mut stdout = BufReader::new(stdout);
+        loop {
+            let body = match read_message(&mut stdout) {
+                Ok(Some(body)) => body,
+                Ok(None) => {
+                    edi_lib::debug!("the language server of {filetype:?} exited");
+                    return;
+                }
+                Err(e) => {
+                    edi_lib::debug!("unable to read from the language server of {filetype:?}: {e}");
+                    return;
+                }
+            };
+
+            let message = match json::parse(&body) {
+                Ok(message) => message,
+                Err(e) => {
+                    edi_lib::debug!("invalid message from the language server: {e}");
+                    continue;
+                }
+            };
+            let filetype = filetype.clone();
+            if !sender.send_event(Payload::LspMessage { filetype, message }) {
+                return;
+            }
+        }
+    }
+}
+
+impl Source for Reader {
+    fn run(&mut self, sender: Sender) {
+        let _span = edi_lib::span!("lsp");
+
+        while let Ok((filetype, stdout)) = self.outputs.recv() {
+            let sender = sender.clone();
+            std::thread::spawn(move || Self::read(&filetype, stdout, &sender));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framing() {
+        let message = json::Value::from([("jsonrpc", json::Value::from("2.0"))]);
+        let mut written = Vec::new();
+        write_message(&mut written, &message).unwrap();
+        write_message(&mut written, &json::Value::from("é")).unwrap();
+        assert!(written.starts_with(b"Content-Length: 17\r\n\r\n{\"jsonrpc\""));
+
+        let mut r = io::Cursor::new(written);
+        assert_eq!(
+            read_message(&mut r).unwrap().as_deref(),
+            Some("{\"jsonrpc\":\"2.0\"}")
+        );
+        assert_eq!(read_message(&mut r).unwrap().as_deref(), Some("\"é\""));
+        assert_eq!(read_message(&mut r).unwrap(), None);
+
+        let mut r = io::Cursor::new("Content-Type: x\r\n\r\n{}");
+        assert!(read_message(&mut r).is_err());
+    }
+}
@@ -15,6 +15,8 @@ mod controller;
 mod error;
 mod event;
 mod handlers;
+mod jobs;
+mod lsp;
 mod query;
 
 const DEBUG_FILE: &str = "log";
@@ -46,7 +48,8 @@ fn run() -> Result<()> {
     setup_logging()?;
 
     let args = cli::EdiCli::parse(std::env::args())?;
-    app::run(args).map_err(|err| AppError::unexpected(format!("fatal error: {err:?}")))?;
+    let config = app::config::load(args.config.as_deref())?;
+    app::run(args, config).map_err(|err| AppError::unexpected(format!("fatal error: {err:?}")))?;
 
     Ok(())
 }
@@ -1,8 +1,18 @@
-use edi_lib::brand::Id;
+use std::{ops::Range, path::PathBuf};
 
-use crate::app::{
-    self,
-    buffers::{self, Selector},
+use edi_lib::{brand::Id, fs::filetype::Filetype, json};
+
+use crate::{
+    app::{
+        self,
+        buffers::{self, Selector},
+        explorer::Operation,
+        meta::ViewPosition,
+        shell::ShellCommand,
+        substitute::{Answer, Substitution},
+    },
+    controller::TimerId,
+    jobs::JobId,
 };
 
 #[derive(Debug)]
@@ -37,7 +47,19 @@ impl Query {
 #[derive(Debug)]
 pub enum WriteQuery {
     WriteChar(char),
+    WriteStr(String),
     DeleteChar,
+    /// Replaces `count` characters from the cursor on with `c`, if the line has as many. A
+    /// newline replaces all of them with a single line break
+    ReplaceChars {
+        c: char,
+        count: usize,
+    },
+    /// Starts a new line below the cursor line, or above it if `above` is set, and moves the
+    /// cursor to it
+    OpenLine {
+        above: bool,
+    },
 }
 
 #[derive(Debug)]
@@ -49,6 +71,12 @@ pub enum HistoryQuery {
 #[derive(Debug)]
 pub enum SpawnQuery {
     TerminalBuffer,
+    /// A prompt for the pattern to search for, backwards if `backward` is set
+    SearchBuffer {
+        backward: bool,
+    },
+    /// A prompt for a path the file explorer asks for, starting with the given text
+    ExplorerPrompt(String),
 }
 
 #[derive(Debug)]
@@ -61,14 +89,203 @@ pub enum MoveQuery {
 }
 
 #[derive(Debug)]
-pub struct CommandQuery {
-    pub command: String,
+pub enum CommandQuery {
+    /// Runs the command typed in the command line, along with the leading `:`
+    Run(String),
+    /// Completes the last word of the command line
+    Complete,
+    /// Replaces the command line with an older command from the history, or a newer one
+    Recall { older: bool },
+    /// Marks the buffer as changed since it was last written, unless its text is the one written
+    /// again
+    Modified { buffer_id: Id },
+    /// Replaces the contents of the buffer with those of its file. Unless `force` is set, a
+    /// modified buffer is kept as it is
+    Reload { buffer_id: Id, force: bool },
+}
+
+#[derive(Debug)]
+pub enum RegistersQuery {
+    /// Copies the current line of the active buffer into the given register
+    YankLine { register: Option<char> },
+    /// Inserts the contents of the given register after the cursor, or before it if `before` is
+    /// set
+    Paste {
+        register: Option<char>,
+        before: bool,
+    },
+}
+
+#[derive(Debug)]
+pub enum SearchQuery {
+    /// Highlights the matches of the pattern typed in the search prompt so far
+    Preview,
+    /// Searches for the pattern typed in the search prompt
+    Submit,
+    /// Restores the searched buffer after the search prompt was closed without searching
+    Cancel,
+    /// Jumps to the next match of the last search, or the previous one if `reverse` is set
+    Next { reverse: bool },
+    /// Stops highlighting the matches
+    Clear,
+}
+
+/// The text an operator acts on
+#[derive(Debug)]
+pub enum OperatorTarget {
+    /// The text between the cursor and where the motion moves it
+    Motion {
+        action: app::action::MoveAction,
+        repeat: usize,
+    },
+    /// The given number of lines, starting with the current one
+    Lines(usize),
+    /// The given number of characters from the cursor on, up to the end of its line
+    Chars(usize),
+    /// The text object around the cursor
+    Object(edi_lib::string::object::TextObject),
+}
+
+#[derive(Debug)]
+pub struct OperatorQuery {
+    pub operator: app::action::Operator,
+    pub target: OperatorTarget,
+    /// The register that keeps the yanked or deleted text
+    pub register: Option<char>,
+}
+
+#[derive(Debug)]
+pub enum SubstituteQuery {
+    /// Runs the substitution in the selected buffer
+    Run {
+        buffer: Selector,
+        substitution: Substitution,
+    },
+    /// Answers whether to replace the match the running substitution asks about
+    Answer(Answer),
 }
 
 #[derive(Debug)]
 pub enum DrawQuery {
+    /// Draws the state once the queries and events pending are handled
     Redraw,
+    /// Updates the highlights of the buffer to its changed text once typing pauses
     Rehighlight(Selector),
+    /// Runs the drawing or highlighting put off until the timeout
+    TimedOut(TimerId),
+    /// Shows the keys of an unfinished command in the statusline of the active buffer
+    PendingKeys(String),
+    /// Stops the process until it is continued, then draws the whole screen again
+    Suspend,
+    /// Hides the message shown in the command line area, along with the popup
+    ClearMessage,
+    /// Scrolls the active buffer so the cursor line is at the given position
+    Align(ViewPosition),
+    /// Scrolls the active buffer `count` columns to the right, or to the left if `right` is not
+    /// set
+    ScrollColumns { right: bool, count: usize },
+}
+
+#[derive(Debug)]
+pub enum LspQuery {
+    /// Records the character written at `offset` in the buffer
+    CharWritten {
+        buffer_id: Id,
+        offset: usize,
+        c: char,
+    },
+    /// Records the deletion of the character before `offset` in the buffer
+    CharDeleted { buffer_id: Id, offset: usize },
+    /// Records the replacement of the characters in `range` of the buffer with `text`
+    TextReplaced {
+        buffer_id: Id,
+        range: Range<usize>,
+        text: String,
+    },
+    /// Sends the recorded changes of the buffer to its language server
+    Sync { buffer_id: Id },
+    /// Takes in a message the language server of the filetype sent
+    Receive {
+        filetype: Filetype,
+        message: json::Value,
+    },
+    /// Shows the information about the symbol under the cursor
+    Hover,
+    /// Jumps to where the symbol under the cursor is defined
+    Definition,
+}
+
+#[derive(Debug)]
+pub enum DiagnosticsQuery {
+    /// Jumps to the next diagnostic of the active buffer, or the previous one if `reverse` is set
+    Jump { reverse: bool },
+    /// Runs a linter and its arguments on the file of the active buffer, the linter of its
+    /// language if there are none
+    Lint(Vec<String>),
+    /// Shows the diagnostics the linter the job ran reported
+    Linted { job: JobId },
+}
+
+#[derive(Debug)]
+pub enum CompletionQuery {
+    /// Selects the next candidate for the word before the cursor of the active buffer, or the
+    /// previous one if `reverse` is set, opening the menu of them if it is not open
+    Select { reverse: bool },
+    /// Replaces the word being typed with the selected candidate and closes the menu
+    Accept,
+    /// Looks for the candidates again after the text of the buffer changed
+    Update { buffer_id: Id },
+    /// Closes the menu of the buffer
+    Close(Selector),
+}
+
+#[derive(Debug)]
+pub enum SpellQuery {
+    /// Jumps to the next misspelled word of the active buffer, or the previous one if `reverse`
+    /// is set
+    Jump { reverse: bool },
+    /// Opens the menu of the words the word under the cursor may have been meant to be
+    Suggest,
+}
+
+#[derive(Debug)]
+pub enum ExplorerQuery {
+    /// Makes the file explorer active, opening it if it is not open. It shows the given
+    /// directory, or the working directory if the explorer is opened without one
+    Show(Option<PathBuf>),
+    /// Carries out the operation on the entry under the cursor of the active explorer
+    Run(Operation),
+    /// Carries out the operation the explorer prompt asked the path for
+    Submit,
+}
+
+#[derive(Debug)]
+pub enum GitQuery {
+    /// Shows the commit each line of the active buffer was last changed in after it, or stops
+    /// showing them if they are shown
+    ToggleBlame,
+    /// Runs blame again on the buffer after its text changed
+    Blame { buffer_id: Id },
+    /// Shows the output of the blame the job ran
+    Blamed { job: JobId, status: Option<i32> },
+    /// Shows how the lines under the cursor differ from the staged ones
+    PreviewHunk,
+    /// Replaces the lines under the cursor with the staged ones
+    RevertHunk,
+    /// Previews or reverts the hunk under the cursor with the output of the diff the job ran
+    Diffed { job: JobId, status: Option<i32> },
+}
+
+#[derive(Debug)]
+pub enum ShellQuery {
+    /// Starts running the command, its output goes where its target is in the selected buffer
+    /// once it exits
+    Run {
+        buffer: Selector,
+        command: ShellCommand,
+    },
+    /// Puts the output of the command of the job where its target is
+    Finished { job: JobId, status: Option<i32> },
 }
 
 #[derive(Debug)]
@@ -78,6 +295,17 @@ pub enum Payload {
     Spawn(SpawnQuery),
     Move(MoveQuery),
     Command(CommandQuery),
+    Registers(RegistersQuery),
+    Search(SearchQuery),
+    Substitute(SubstituteQuery),
+    Operator(OperatorQuery),
+    Lsp(LspQuery),
+    Diagnostics(DiagnosticsQuery),
+    Completion(CompletionQuery),
+    Spell(SpellQuery),
+    Explorer(ExplorerQuery),
+    Git(GitQuery),
+    Shell(ShellQuery),
     SwitchMode {
         buffer_selector: Selector,
         target_mode: app::Mode,
@@ -94,11 +322,58 @@ impl Payload {
             Self::Spawn(_) => Type::Spawn,
             Self::Move(_) => Type::Move,
             Self::Command(_) => Type::Command,
+            Self::Registers(_) => Type::Registers,
+            Self::Search(_) => Type::Search,
+            Self::Substitute(_) => Type::Substitute,
+            Self::Operator(_) => Type::Operator,
+            Self::Lsp(_) => Type::Lsp,
+            Self::Diagnostics(_) => Type::Diagnostics,
+            Self::Completion(_) => Type::Completion,
+            Self::Spell(_) => Type::Spell,
+            Self::Explorer(_) => Type::Explorer,
+            Self::Git(_) => Type::Git,
+            Self::Shell(_) => Type::Shell,
             Self::SwitchMode { .. } => Type::SwitchMode,
             Self::Draw(_) => Type::Draw,
             Self::Quit => Type::Quit,
         }
     }
+
+    pub const fn priority(&self) -> Priority {
+        match self {
+            Self::Draw(DrawQuery::Redraw | DrawQuery::Rehighlight(Selector::WithId(_))) => {
+                Priority::Background
+            }
+            _ => Priority::Normal,
+        }
+    }
+
+    /// Returns whether running the query after `other` does nothing more than running `other`
+    /// does, so it is dropped while `other` is pending
+    pub fn coalesces_with(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Draw(DrawQuery::Redraw), Self::Draw(DrawQuery::Redraw)) => true,
+            (
+                Self::Draw(DrawQuery::Rehighlight(Selector::WithId(id))),
+                Self::Draw(DrawQuery::Rehighlight(Selector::WithId(other))),
+            ) => id == other,
+            (
+                Self::Command(CommandQuery::Modified { buffer_id }),
+                Self::Command(CommandQuery::Modified { buffer_id: other }),
+            ) => buffer_id == other,
+            _ => false,
+        }
+    }
+}
+
+/// When a query runs relative to the other queries and events pending
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// Runs in the order it was submitted in
+    Normal,
+    /// Runs once no queries or events of other priorities are pending, as a query that only
+    /// shows the state does
+    Background,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -108,19 +383,41 @@ pub enum Type {
     Spawn,
     Move,
     Command,
+    Registers,
+    Search,
+    Substitute,
+    Operator,
+    Lsp,
+    Diagnostics,
+    Completion,
+    Spell,
+    Explorer,
+    Git,
+    Shell,
     SwitchMode,
     Draw,
     Quit,
 }
 
 impl Type {
-    pub const fn all() -> [Self; 8] {
+    pub const fn all() -> [Self; 19] {
         [
             Self::Write,
             Self::History,
             Self::Spawn,
             Self::Move,
             Self::Command,
+            Self::Registers,
+            Self::Search,
+            Self::Substitute,
+            Self::Operator,
+            Self::Lsp,
+            Self::Diagnostics,
+            Self::Completion,
+            Self::Spell,
+            Self::Explorer,
+            Self::Git,
+            Self::Shell,
             Self::SwitchMode,
             Self::Draw,
             Self::Quit,